@@ -0,0 +1,47 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use criterion::{Criterion, criterion_group, criterion_main, black_box};
+use beacon::{Config, MinimalConfig, BeaconState};
+use beacon::types::Validator;
+use bm_le::tree_root;
+
+fn state_with_validators(count: usize) -> BeaconState<MinimalConfig> {
+	let mut state = BeaconState::<MinimalConfig>::default();
+	for i in 0..count {
+		state.validators.push(Validator {
+			effective_balance: i as u64,
+			..Default::default()
+		});
+		state.balances.push(i as u64);
+	}
+	state
+}
+
+fn hash(c: &mut Criterion) {
+	c.bench_function("hash 64 bytes", |b| {
+		let input = [0u8; 64];
+		b.iter(|| MinimalConfig::hash(&[black_box(&input[..])]))
+	});
+
+	let state = state_with_validators(1024);
+	c.bench_function("tree root of state with 1024 validators", |b| {
+		b.iter(|| tree_root::<<MinimalConfig as Config>::Digest, _>(black_box(&state)))
+	});
+}
+
+criterion_group!(benches, hash);
+criterion_main!(benches);
@@ -0,0 +1,145 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Benchmarks of the state transition on large synthetic states.
+//!
+//! Validator counts are set by `BENCH_VALIDATORS`, a comma-separated list,
+//! defaulting to 1024 and 16384. Run with `cargo bench --bench transition`.
+
+use criterion::{
+	Criterion, BenchmarkId, BatchSize, criterion_group, criterion_main, black_box,
+};
+use beacon::{
+	Config, MinimalConfig, BeaconState, BeaconExecutive, BLSNoVerification, Inherent,
+};
+use beacon::primitives::{ValidatorId, Slot, Epoch};
+use beacon::types::{BeaconBlock, Validator};
+use beacon::consts::FAR_FUTURE_EPOCH;
+use bm_le::tree_root;
+use ssz::{Encode, Decode};
+
+type C = MinimalConfig;
+type BLS = BLSNoVerification;
+
+fn validator_counts() -> Vec<usize> {
+	std::env::var("BENCH_VALIDATORS")
+		.unwrap_or_else(|_| "1024,16384".to_string())
+		.split(',')
+		.map(|count| count.trim().parse().expect("Invalid BENCH_VALIDATORS"))
+		.collect()
+}
+
+/// State at `slot` with `count` active validators at maximum effective
+/// balance.
+fn synthetic_state(count: usize, slot: u64) -> BeaconState<C> {
+	let mut state = BeaconState::<C>::default();
+	for i in 0..count {
+		state.validators.push(Validator {
+			pubkey: ValidatorId::from_low_u64_be(i as u64),
+			effective_balance: C::max_effective_balance(),
+			activation_eligibility_epoch: Epoch::new(0),
+			activation_epoch: Epoch::new(0),
+			exit_epoch: FAR_FUTURE_EPOCH,
+			withdrawable_epoch: FAR_FUTURE_EPOCH,
+			..Default::default()
+		});
+		state.balances.push(C::max_effective_balance().as_u64());
+	}
+	beacon::initialize_block::<C>(&mut state, Slot::new(slot)).expect("Advancing state failed");
+	state
+}
+
+/// Empty block on top of `state`, along with the state it applies to.
+fn empty_block(state: &BeaconState<C>) -> (BeaconBlock<C>, BeaconState<C>) {
+	let parent_state = state.clone();
+	let mut state = state.clone();
+	beacon::initialize_block::<C>(&mut state, parent_state.slot + 1)
+		.expect("Advancing state failed");
+	let mut block = beacon::apply_inherent::<C, BLS>(
+		&BeaconBlock::default(), &mut state, Inherent {
+			randao_reveal: Default::default(),
+			eth1_data: state.eth1_data.clone(),
+			graffiti: Default::default(),
+		},
+	).expect("Applying inherent failed");
+	beacon::finalize_block::<C, BLS>(&mut block, &mut state).expect("Finalizing block failed");
+	(block.fake_seal(), parent_state)
+}
+
+fn execute_block(c: &mut Criterion) {
+	let mut group = c.benchmark_group("execute_block");
+	group.sample_size(10);
+	for count in validator_counts() {
+		let (block, state) = empty_block(&synthetic_state(count, 1));
+		group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, _| {
+			b.iter_batched(
+				|| state.clone(),
+				|mut state| beacon::execute_block::<C, BLS>(black_box(&block), &mut state)
+					.expect("Executing block failed"),
+				BatchSize::LargeInput,
+			)
+		});
+	}
+	group.finish();
+}
+
+fn process_epoch(c: &mut Criterion) {
+	let mut group = c.benchmark_group("process_epoch");
+	group.sample_size(10);
+	for count in validator_counts() {
+		let state = synthetic_state(count, C::slots_per_epoch() * 3 - 1);
+		group.bench_with_input(BenchmarkId::from_parameter(count), &count, |b, _| {
+			b.iter_batched(
+				|| state.clone(),
+				|mut state| BeaconExecutive::new(&mut state).process_epoch()
+					.expect("Processing epoch failed"),
+				BatchSize::LargeInput,
+			)
+		});
+	}
+	group.finish();
+}
+
+fn hash_tree_root(c: &mut Criterion) {
+	let mut group = c.benchmark_group("hash_tree_root");
+	group.sample_size(10);
+	for count in validator_counts() {
+		let state = synthetic_state(count, 0);
+		group.bench_with_input(BenchmarkId::from_parameter(count), &state, |b, state| {
+			b.iter(|| tree_root::<<C as Config>::Digest, _>(black_box(state)))
+		});
+	}
+	group.finish();
+}
+
+fn ssz(c: &mut Criterion) {
+	let mut group = c.benchmark_group("ssz");
+	group.sample_size(10);
+	for count in validator_counts() {
+		let state = synthetic_state(count, 0);
+		let encoded = state.encode();
+		group.bench_with_input(BenchmarkId::new("encode", count), &state, |b, state| {
+			b.iter(|| black_box(state).encode())
+		});
+		group.bench_with_input(BenchmarkId::new("decode", count), &encoded, |b, encoded| {
+			b.iter(|| BeaconState::<C>::decode(black_box(encoded)).expect("Decoding state failed"))
+		});
+	}
+	group.finish();
+}
+
+criterion_group!(benches, execute_block, process_epoch, hash_tree_root, ssz);
+criterion_main!(benches);
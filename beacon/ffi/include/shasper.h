@@ -0,0 +1,58 @@
+/* C interface to the Serenity beacon chain state transition function. */
+
+#ifndef SHASPER_H
+#define SHASPER_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define SHASPER_CONFIG_MINIMAL 0
+#define SHASPER_CONFIG_MAINNET 1
+
+typedef enum {
+	SHASPER_OK = 0,
+	SHASPER_NULL_POINTER = 1,
+	SHASPER_UNKNOWN_CONFIG = 2,
+	SHASPER_INVALID_STATE = 3,
+	SHASPER_INVALID_BLOCK = 4,
+	SHASPER_TRANSITION_FAILED = 5,
+	SHASPER_PANIC = 6,
+} shasper_result;
+
+/* Buffer owned by the library, released with shasper_buffer_free. */
+typedef struct {
+	uint8_t *ptr;
+	size_t len;
+} shasper_buffer;
+
+/* Execute an SSZ-encoded block on an SSZ-encoded pre-state, writing the
+ * SSZ-encoded post-state to post_state. */
+shasper_result shasper_execute_block(
+	uint32_t config,
+	uint8_t verify_signatures,
+	const uint8_t *pre_state,
+	size_t pre_state_len,
+	const uint8_t *block,
+	size_t block_len,
+	shasper_buffer *post_state
+);
+
+/* Write the 32 byte hash tree root of an SSZ-encoded state to root. */
+shasper_result shasper_hash_tree_root(
+	uint32_t config,
+	const uint8_t *state,
+	size_t state_len,
+	uint8_t *root
+);
+
+void shasper_buffer_free(shasper_buffer buffer);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,211 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+//! C interface to the beacon chain state transition function.
+//!
+//! States and blocks cross the boundary SSZ-encoded. Buffers returned to the
+//! caller are owned by this library and must be released with
+//! `shasper_buffer_free`. See `include/shasper.h` for the C declarations.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::{ptr, slice};
+use beacon::{BeaconState, BLSConfig, BLSNoVerification, Config, MainnetConfig, MinimalConfig};
+use beacon::types::BeaconBlock;
+use bm_le::tree_root;
+use shasper_crypto::bls::BLSVerification;
+use ssz::{Decode, Encode};
+
+/// Minimal configuration.
+pub const SHASPER_CONFIG_MINIMAL: u32 = 0;
+/// Mainnet configuration.
+pub const SHASPER_CONFIG_MAINNET: u32 = 1;
+
+/// Result codes returned by the interface.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ShasperResult {
+	/// Call succeeded.
+	Ok = 0,
+	/// A required pointer is null.
+	NullPointer = 1,
+	/// Configuration is unknown.
+	UnknownConfig = 2,
+	/// State failed to decode.
+	InvalidState = 3,
+	/// Block failed to decode.
+	InvalidBlock = 4,
+	/// State transition rejected the block.
+	TransitionFailed = 5,
+	/// The library panicked.
+	Panic = 6,
+}
+
+/// Byte buffer owned by this library.
+#[repr(C)]
+pub struct ShasperBuffer {
+	/// Start of the buffer.
+	pub ptr: *mut u8,
+	/// Length of the buffer.
+	pub len: usize,
+}
+
+impl ShasperBuffer {
+	fn empty() -> Self {
+		Self { ptr: ptr::null_mut(), len: 0 }
+	}
+
+	fn from_vec(value: Vec<u8>) -> Self {
+		let mut value = value.into_boxed_slice();
+		let buffer = Self { ptr: value.as_mut_ptr(), len: value.len() };
+		std::mem::forget(value);
+		buffer
+	}
+}
+
+unsafe fn input<'a>(data: *const u8, len: usize) -> Option<&'a [u8]> {
+	if data.is_null() {
+		if len == 0 { Some(&[]) } else { None }
+	} else {
+		Some(slice::from_raw_parts(data, len))
+	}
+}
+
+fn catch<F: FnOnce() -> ShasperResult>(f: F) -> ShasperResult {
+	panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(ShasperResult::Panic)
+}
+
+fn execute<C: Config, BLS: BLSConfig>(
+	pre_state: &[u8],
+	block: &[u8],
+) -> Result<Vec<u8>, ShasperResult> {
+	let mut state = BeaconState::<C>::decode(pre_state)
+		.map_err(|_| ShasperResult::InvalidState)?;
+	let block = BeaconBlock::<C>::decode(block)
+		.map_err(|_| ShasperResult::InvalidBlock)?;
+
+	beacon::execute_block::<C, BLS>(&block, &mut state)
+		.map_err(|_| ShasperResult::TransitionFailed)?;
+
+	Ok(state.encode())
+}
+
+fn state_root<C: Config>(state: &[u8]) -> Result<[u8; 32], ShasperResult> {
+	let state = BeaconState::<C>::decode(state)
+		.map_err(|_| ShasperResult::InvalidState)?;
+
+	let mut root = [0u8; 32];
+	root.copy_from_slice(&tree_root::<C::Digest, _>(&state)[..]);
+	Ok(root)
+}
+
+/// Execute an SSZ-encoded block on top of an SSZ-encoded pre-state, writing
+/// the SSZ-encoded post-state to `post_state`. Signatures are only checked
+/// when `verify_signatures` is non-zero.
+///
+/// # Safety
+///
+/// `pre_state` and `block` must point to `pre_state_len` and `block_len`
+/// readable bytes, and `post_state` must point to a writable buffer.
+#[no_mangle]
+pub unsafe extern "C" fn shasper_execute_block(
+	config: u32,
+	verify_signatures: u8,
+	pre_state: *const u8,
+	pre_state_len: usize,
+	block: *const u8,
+	block_len: usize,
+	post_state: *mut ShasperBuffer,
+) -> ShasperResult {
+	if post_state.is_null() {
+		return ShasperResult::NullPointer
+	}
+	*post_state = ShasperBuffer::empty();
+
+	let (pre_state, block) = match (input(pre_state, pre_state_len), input(block, block_len)) {
+		(Some(pre_state), Some(block)) => (pre_state, block),
+		_ => return ShasperResult::NullPointer,
+	};
+
+	catch(|| {
+		let result = match (config, verify_signatures != 0) {
+			(SHASPER_CONFIG_MINIMAL, false) =>
+				execute::<MinimalConfig, BLSNoVerification>(pre_state, block),
+			(SHASPER_CONFIG_MINIMAL, true) =>
+				execute::<MinimalConfig, BLSVerification>(pre_state, block),
+			(SHASPER_CONFIG_MAINNET, false) =>
+				execute::<MainnetConfig, BLSNoVerification>(pre_state, block),
+			(SHASPER_CONFIG_MAINNET, true) =>
+				execute::<MainnetConfig, BLSVerification>(pre_state, block),
+			_ => Err(ShasperResult::UnknownConfig),
+		};
+
+		match result {
+			Ok(encoded) => {
+				*post_state = ShasperBuffer::from_vec(encoded);
+				ShasperResult::Ok
+			},
+			Err(err) => err,
+		}
+	})
+}
+
+/// Compute the hash tree root of an SSZ-encoded state, writing the 32 byte
+/// root to `root`.
+///
+/// # Safety
+///
+/// `state` must point to `state_len` readable bytes, and `root` must point to
+/// 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn shasper_hash_tree_root(
+	config: u32,
+	state: *const u8,
+	state_len: usize,
+	root: *mut u8,
+) -> ShasperResult {
+	let state = match input(state, state_len) {
+		Some(state) if !root.is_null() => state,
+		_ => return ShasperResult::NullPointer,
+	};
+
+	catch(|| {
+		let result = match config {
+			SHASPER_CONFIG_MINIMAL => state_root::<MinimalConfig>(state),
+			SHASPER_CONFIG_MAINNET => state_root::<MainnetConfig>(state),
+			_ => Err(ShasperResult::UnknownConfig),
+		};
+
+		match result {
+			Ok(value) => {
+				ptr::copy_nonoverlapping(value.as_ptr(), root, value.len());
+				ShasperResult::Ok
+			},
+			Err(err) => err,
+		}
+	})
+}
+
+/// Release a buffer returned by this library. Releasing an empty buffer is a
+/// no-op.
+///
+/// # Safety
+///
+/// `buffer` must have been returned by this library and not released before.
+#[no_mangle]
+pub unsafe extern "C" fn shasper_buffer_free(buffer: ShasperBuffer) {
+	if !buffer.ptr.is_null() {
+		drop(Box::from_raw(slice::from_raw_parts_mut(buffer.ptr, buffer.len)));
+	}
+}
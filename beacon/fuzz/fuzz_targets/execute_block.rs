@@ -0,0 +1,27 @@
+//! Mutates a valid block and executes it, which must return an error rather
+//! than panic, however the block is malformed.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use lazy_static::lazy_static;
+use beacon::BeaconState;
+use beacon::types::BeaconBlock;
+use beacon_fuzz::{C, BLS, fuzz_state, valid_block, mutate_block_with};
+
+lazy_static! {
+	static ref PARENT: (BeaconState<C>, BeaconBlock<C>) = {
+		let state = fuzz_state();
+		let block = valid_block(&state);
+		(state, block)
+	};
+}
+
+fuzz_target!(|data: &[u8]| {
+	let (state, block) = &*PARENT;
+	let mut block = block.clone();
+	mutate_block_with(&mut block, data);
+
+	let mut state = state.clone();
+	let _ = beacon::execute_block::<C, BLS>(&block, &mut state);
+});
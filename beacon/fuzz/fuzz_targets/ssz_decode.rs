@@ -0,0 +1,41 @@
+//! Decodes arbitrary bytes as every beacon container. Decoding must never
+//! panic, and decoded values must encode back to the same value.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use core::fmt::Debug;
+use ssz::{Encode, Decode};
+use beacon::BeaconState;
+use beacon::types::*;
+use beacon_fuzz::C;
+
+fn round_trip<T: Encode + Decode + PartialEq + Debug>(data: &[u8]) {
+	if let Ok(value) = T::decode(data) {
+		let encoded = value.encode();
+		let decoded = T::decode(&encoded).expect("Encoded value must decode");
+		assert_eq!(decoded, value);
+	}
+}
+
+fuzz_target!(|data: &[u8]| {
+	round_trip::<Fork>(data);
+	round_trip::<Checkpoint>(data);
+	round_trip::<Validator>(data);
+	round_trip::<AttestationData>(data);
+	round_trip::<AttestationDataAndCustodyBit>(data);
+	round_trip::<IndexedAttestation<C>>(data);
+	round_trip::<PendingAttestation<C>>(data);
+	round_trip::<Eth1Data>(data);
+	round_trip::<HistoricalBatch<C>>(data);
+	round_trip::<DepositData>(data);
+	round_trip::<BeaconBlockHeader>(data);
+	round_trip::<ProposerSlashing>(data);
+	round_trip::<AttesterSlashing<C>>(data);
+	round_trip::<Attestation<C>>(data);
+	round_trip::<Deposit>(data);
+	round_trip::<VoluntaryExit>(data);
+	round_trip::<BeaconBlockBody<C>>(data);
+	round_trip::<BeaconBlock<C>>(data);
+	round_trip::<BeaconState<C>>(data);
+});
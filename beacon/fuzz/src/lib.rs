@@ -0,0 +1,118 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+//! Helpers shared by the fuzzing targets.
+//!
+//! Run a target with `cargo fuzz run <target>` from the `beacon` directory.
+
+use beacon::{Config, MinimalConfig, BeaconState, BLSNoVerification, Inherent};
+use beacon::consts::FAR_FUTURE_EPOCH;
+use beacon::primitives::{H256, ValidatorId, Slot, Epoch};
+use beacon::types::*;
+use ssz::Decode;
+
+/// Configuration used by the fuzzing targets.
+pub type C = MinimalConfig;
+/// Signatures are not verified, so that mutated blocks get past signature
+/// checks and exercise the rest of the state transition.
+pub type BLS = BLSNoVerification;
+
+/// Number of validators of the fuzzed state.
+pub const VALIDATORS: usize = 64;
+
+/// State at the start of the second epoch with `VALIDATORS` active
+/// validators.
+pub fn fuzz_state() -> BeaconState<C> {
+	let mut state = BeaconState::<C>::default();
+	for i in 0..VALIDATORS {
+		state.validators.push(Validator {
+			pubkey: ValidatorId::from_low_u64_be(i as u64),
+			effective_balance: C::max_effective_balance(),
+			activation_eligibility_epoch: Epoch::new(0),
+			activation_epoch: Epoch::new(0),
+			exit_epoch: FAR_FUTURE_EPOCH,
+			withdrawable_epoch: FAR_FUTURE_EPOCH,
+			..Default::default()
+		});
+		state.balances.push(C::max_effective_balance().as_u64());
+	}
+	beacon::initialize_block::<C>(&mut state, Slot::new(C::slots_per_epoch()))
+		.expect("Advancing fuzz state failed");
+	state
+}
+
+/// Valid empty block on top of `state`.
+pub fn valid_block(state: &BeaconState<C>) -> BeaconBlock<C> {
+	let mut state = state.clone();
+	let target_slot = state.slot + 1;
+	beacon::initialize_block::<C>(&mut state, target_slot)
+		.expect("Advancing fuzz state failed");
+	let mut block = beacon::apply_inherent::<C, BLS>(
+		&BeaconBlock::default(), &mut state, Inherent {
+			randao_reveal: Default::default(),
+			eth1_data: state.eth1_data.clone(),
+			graffiti: Default::default(),
+		},
+	).expect("Applying inherent failed");
+	beacon::finalize_block::<C, BLS>(&mut block, &mut state)
+		.expect("Finalizing block failed");
+	block.fake_seal()
+}
+
+fn decode<T: Decode>(data: &[u8]) -> Option<T> {
+	T::decode(data).ok()
+}
+
+/// Apply a single mutation to a block, selected by `kind`, with `data` as
+/// its SSZ-encoded payload. Payloads that fail to decode leave the block
+/// unchanged.
+pub fn mutate_block(block: &mut BeaconBlock<C>, kind: u8, data: &[u8]) {
+	let body = &mut block.body;
+	match kind % 12 {
+		0 => if let Some(slot) = decode::<Slot>(data) { block.slot = slot },
+		1 => if let Some(root) = decode::<H256>(data) { block.parent_root = root },
+		2 => if let Some(root) = decode::<H256>(data) { block.state_root = root },
+		3 => if let Some(graffiti) = decode::<H256>(data) { body.graffiti = graffiti },
+		4 => if let Some(eth1_data) = decode::<Eth1Data>(data) { body.eth1_data = eth1_data },
+		5 => if let Some(slashing) = decode::<ProposerSlashing>(data) {
+			body.proposer_slashings.push(slashing)
+		},
+		6 => if let Some(slashing) = decode::<AttesterSlashing<C>>(data) {
+			body.attester_slashings.push(slashing)
+		},
+		7 => if let Some(attestation) = decode::<Attestation<C>>(data) {
+			body.attestations.push(attestation)
+		},
+		8 => if let Some(deposit) = decode::<Deposit>(data) {
+			body.deposits.push(deposit)
+		},
+		9 => if let Some(exit) = decode::<VoluntaryExit>(data) {
+			body.voluntary_exits.push(exit)
+		},
+		10 => if let Some(decoded) = decode::<BeaconBlockBody<C>>(data) { *body = decoded },
+		_ => if let Some(decoded) = decode::<BeaconBlock<C>>(data) { *block = decoded },
+	}
+}
+
+/// Apply mutations encoded in `data` as a sequence of a kind byte, a length
+/// byte and the payload.
+pub fn mutate_block_with(block: &mut BeaconBlock<C>, mut data: &[u8]) {
+	while data.len() >= 2 {
+		let kind = data[0];
+		let len = std::cmp::min(data[1] as usize, data.len() - 2);
+		mutate_block(block, kind, &data[2..(2 + len)]);
+		data = &data[(2 + len)..];
+	}
+}
@@ -0,0 +1,171 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+//! Python bindings for the beacon chain.
+//!
+//! Containers cross the boundary SSZ-encoded as `bytes`, and can be
+//! converted to and from JSON for inspection. Containers are selected by
+//! their type name, and configurations by `"minimal"` or `"mainnet"`.
+
+use pyo3::prelude::*;
+use pyo3::exceptions::ValueError;
+use pyo3::types::PyBytes;
+use pyo3::wrap_pyfunction;
+use beacon::{BeaconState, BLSConfig, BLSNoVerification, Config, MainnetConfig, MinimalConfig};
+use beacon::types::*;
+use bm_le::{tree_root, IntoTree};
+use serde::{Serialize, de::DeserializeOwned};
+use shasper_crypto::bls::BLSVerification;
+use ssz::{Decode, Encode};
+
+type Result<T> = std::result::Result<T, String>;
+
+fn error<E: std::fmt::Debug>(err: E) -> String {
+	format!("{:?}", err)
+}
+
+fn value_error(err: String) -> PyErr {
+	PyErr::new::<ValueError, _>(err)
+}
+
+/// Dispatch a generic function over a configuration and a container type
+/// name, calling it as `$f::<C, T>($args)`.
+macro_rules! dispatch {
+	( $config:expr, $kind:expr, $f:ident ( $($arg:expr),* ) ) => {
+		match $config {
+			"minimal" => dispatch!(@kind MinimalConfig, $kind, $f($($arg),*)),
+			"mainnet" => dispatch!(@kind MainnetConfig, $kind, $f($($arg),*)),
+			config => Err(format!("unknown config {}", config)),
+		}
+	};
+	( @kind $c:ty, $kind:expr, $f:ident ( $($arg:expr),* ) ) => {
+		match $kind {
+			"BeaconState" => $f::<$c, BeaconState<$c>>($($arg),*),
+			"BeaconBlock" => $f::<$c, BeaconBlock<$c>>($($arg),*),
+			"BeaconBlockBody" => $f::<$c, BeaconBlockBody<$c>>($($arg),*),
+			"BeaconBlockHeader" => $f::<$c, BeaconBlockHeader>($($arg),*),
+			"Attestation" => $f::<$c, Attestation<$c>>($($arg),*),
+			"AttestationData" => $f::<$c, AttestationData>($($arg),*),
+			"IndexedAttestation" => $f::<$c, IndexedAttestation<$c>>($($arg),*),
+			"PendingAttestation" => $f::<$c, PendingAttestation<$c>>($($arg),*),
+			"AttesterSlashing" => $f::<$c, AttesterSlashing<$c>>($($arg),*),
+			"ProposerSlashing" => $f::<$c, ProposerSlashing>($($arg),*),
+			"Deposit" => $f::<$c, Deposit>($($arg),*),
+			"DepositData" => $f::<$c, DepositData>($($arg),*),
+			"VoluntaryExit" => $f::<$c, VoluntaryExit>($($arg),*),
+			"Validator" => $f::<$c, Validator>($($arg),*),
+			"Eth1Data" => $f::<$c, Eth1Data>($($arg),*),
+			"Checkpoint" => $f::<$c, Checkpoint>($($arg),*),
+			"Fork" => $f::<$c, Fork>($($arg),*),
+			"HistoricalBatch" => $f::<$c, HistoricalBatch<$c>>($($arg),*),
+			kind => Err(format!("unknown container {}", kind)),
+		}
+	};
+}
+
+fn to_json<C: Config, T: Decode + Serialize>(data: &[u8]) -> Result<String> {
+	let value = T::decode(data).map_err(error)?;
+	serde_json::to_string(&value).map_err(error)
+}
+
+fn from_json<C: Config, T: Encode + DeserializeOwned>(json: &str) -> Result<Vec<u8>> {
+	let value: T = serde_json::from_str(json).map_err(error)?;
+	Ok(value.encode())
+}
+
+fn root<C: Config, T: Decode + IntoTree>(data: &[u8]) -> Result<Vec<u8>> {
+	let value = T::decode(data).map_err(error)?;
+	Ok(tree_root::<C::Digest, _>(&value)[..].to_vec())
+}
+
+fn execute<C: Config, BLS: BLSConfig>(pre_state: &[u8], block: &[u8]) -> Result<Vec<u8>> {
+	let mut state = BeaconState::<C>::decode(pre_state).map_err(error)?;
+	let block = BeaconBlock::<C>::decode(block).map_err(error)?;
+	beacon::execute_block::<C, BLS>(&block, &mut state).map_err(error)?;
+	Ok(state.encode())
+}
+
+fn advance<C: Config>(state: &[u8], slot: u64) -> Result<Vec<u8>> {
+	let mut state = BeaconState::<C>::decode(state).map_err(error)?;
+	beacon::initialize_block::<C>(&mut state, slot.into()).map_err(error)?;
+	Ok(state.encode())
+}
+
+/// Decode an SSZ-encoded container into JSON.
+#[pyfunction]
+fn decode(py: Python, config: &str, kind: &str, data: &PyBytes) -> PyResult<String> {
+	let data = data.as_bytes();
+	py.allow_threads(|| dispatch!(config, kind, to_json(data))).map_err(value_error)
+}
+
+/// Encode a container given as JSON into SSZ.
+#[pyfunction]
+fn encode(py: Python, config: &str, kind: &str, json: &str) -> PyResult<PyObject> {
+	let encoded = py.allow_threads(|| dispatch!(config, kind, from_json(json))).map_err(value_error)?;
+	Ok(PyBytes::new(py, &encoded).into())
+}
+
+/// Hash tree root of an SSZ-encoded container.
+#[pyfunction]
+fn hash_tree_root(py: Python, config: &str, kind: &str, data: &PyBytes) -> PyResult<PyObject> {
+	let data = data.as_bytes();
+	let value = py.allow_threads(|| dispatch!(config, kind, root(data))).map_err(value_error)?;
+	Ok(PyBytes::new(py, &value).into())
+}
+
+/// Execute an SSZ-encoded block on an SSZ-encoded pre-state, returning the
+/// SSZ-encoded post-state.
+#[pyfunction(verify_signatures = "false")]
+fn execute_block(
+	py: Python,
+	config: &str,
+	pre_state: &PyBytes,
+	block: &PyBytes,
+	verify_signatures: bool,
+) -> PyResult<PyObject> {
+	let (pre_state, block) = (pre_state.as_bytes(), block.as_bytes());
+	let post_state = py.allow_threads(|| match (config, verify_signatures) {
+		("minimal", false) => execute::<MinimalConfig, BLSNoVerification>(pre_state, block),
+		("minimal", true) => execute::<MinimalConfig, BLSVerification>(pre_state, block),
+		("mainnet", false) => execute::<MainnetConfig, BLSNoVerification>(pre_state, block),
+		("mainnet", true) => execute::<MainnetConfig, BLSVerification>(pre_state, block),
+		(config, _) => Err(format!("unknown config {}", config)),
+	}).map_err(value_error)?;
+	Ok(PyBytes::new(py, &post_state).into())
+}
+
+/// Process empty slots of an SSZ-encoded state up to `slot`, returning the
+/// SSZ-encoded advanced state.
+#[pyfunction]
+fn process_slots(py: Python, config: &str, state: &PyBytes, slot: u64) -> PyResult<PyObject> {
+	let state = state.as_bytes();
+	let advanced = py.allow_threads(|| match config {
+		"minimal" => advance::<MinimalConfig>(state, slot),
+		"mainnet" => advance::<MainnetConfig>(state, slot),
+		config => Err(format!("unknown config {}", config)),
+	}).map_err(value_error)?;
+	Ok(PyBytes::new(py, &advanced).into())
+}
+
+/// Serenity beacon chain state transition.
+#[pymodule]
+fn shasper(_py: Python, m: &PyModule) -> PyResult<()> {
+	m.add_wrapped(wrap_pyfunction!(decode))?;
+	m.add_wrapped(wrap_pyfunction!(encode))?;
+	m.add_wrapped(wrap_pyfunction!(hash_tree_root))?;
+	m.add_wrapped(wrap_pyfunction!(execute_block))?;
+	m.add_wrapped(wrap_pyfunction!(process_slots))?;
+	Ok(())
+}
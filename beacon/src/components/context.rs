@@ -0,0 +1,258 @@
+//! Standalone Casper FFG finality gadget.
+//!
+//! `CasperContext` runs justification and finalization over votes pushed by
+//! any chain, not only the beacon chain. The chain feeds each validator vote
+//! with `push_vote`, and at the end of each epoch calls `advance_epoch` with
+//! the checkpoints of the previous and current epoch, and a `WeightStore`
+//! giving validator weights.
+
+use alloc::collections::BTreeMap;
+use core::convert::Infallible;
+use vecarray::VecArray;
+use crate::consts;
+use super::{Checkpoint, Justifier, JustifierRegistry, ValidatorIndex, Balance, Epoch};
+
+/// Store of validator weights, as of the epoch being processed.
+pub trait WeightStore {
+	/// Error type of the store.
+	type Error;
+
+	/// Total weight of all active validators.
+	fn total_weight(&self) -> Result<Balance, Self::Error>;
+	/// Weight of a validator. Zero if the validator is not active.
+	fn weight(&self, index: ValidatorIndex) -> Result<Balance, Self::Error>;
+}
+
+impl WeightStore for BTreeMap<ValidatorIndex, Balance> {
+	type Error = Infallible;
+
+	fn total_weight(&self) -> Result<Balance, Infallible> {
+		Ok(self.values().sum())
+	}
+
+	fn weight(&self, index: ValidatorIndex) -> Result<Balance, Infallible> {
+		Ok(self.get(&index).cloned().unwrap_or_default())
+	}
+}
+
+/// Error of vote ingestion.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VoteError {
+	/// Target is neither of the previous nor of the current epoch.
+	TargetEpochOutOfRange,
+	/// Source is not the justified checkpoint for the target epoch.
+	SourceNotJustified,
+	/// Validator already voted for the target epoch.
+	DuplicateVote,
+}
+
+/// Casper FFG finality gadget state.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CasperContext<C: Checkpoint> {
+	genesis_epoch: Epoch,
+	epoch: Epoch,
+	justification_bits: VecArray<bool, consts::JustificationBitsLength>,
+	previous_justified_checkpoint: C,
+	current_justified_checkpoint: C,
+	finalized_checkpoint: C,
+	previous_votes: BTreeMap<ValidatorIndex, C>,
+	current_votes: BTreeMap<ValidatorIndex, C>,
+}
+
+impl<C: Checkpoint + PartialEq> CasperContext<C> {
+	/// Create a new context, with the genesis checkpoint justified and
+	/// finalized.
+	pub fn new(genesis: C) -> Self {
+		Self {
+			genesis_epoch: genesis.epoch(),
+			epoch: genesis.epoch(),
+			justification_bits: Default::default(),
+			previous_justified_checkpoint: genesis.clone(),
+			current_justified_checkpoint: genesis.clone(),
+			finalized_checkpoint: genesis,
+			previous_votes: BTreeMap::new(),
+			current_votes: BTreeMap::new(),
+		}
+	}
+
+	/// Current epoch.
+	pub fn epoch(&self) -> Epoch {
+		self.epoch
+	}
+
+	/// Justification bits, for the current epoch first.
+	pub fn justification_bits(&self) -> &[bool] {
+		&self.justification_bits[..]
+	}
+
+	/// Justified checkpoint as of the previous epoch.
+	pub fn previous_justified_checkpoint(&self) -> &C {
+		&self.previous_justified_checkpoint
+	}
+
+	/// Latest justified checkpoint.
+	pub fn current_justified_checkpoint(&self) -> &C {
+		&self.current_justified_checkpoint
+	}
+
+	/// Latest finalized checkpoint.
+	pub fn finalized_checkpoint(&self) -> &C {
+		&self.finalized_checkpoint
+	}
+
+	/// Push a vote of a validator, from `source` to `target`. Only votes for
+	/// the target are kept, as the source is checked on ingestion.
+	///
+	/// The target must be of the previous or current epoch, and the source
+	/// must be the justified checkpoint as of that epoch. A validator votes
+	/// at most once per target epoch.
+	pub fn push_vote(
+		&mut self,
+		index: ValidatorIndex,
+		source: C,
+		target: C,
+	) -> Result<(), VoteError> {
+		let (justified, votes) = if target.epoch() == self.epoch {
+			(&self.current_justified_checkpoint, &mut self.current_votes)
+		} else if target.epoch() + 1 == self.epoch {
+			(&self.previous_justified_checkpoint, &mut self.previous_votes)
+		} else {
+			return Err(VoteError::TargetEpochOutOfRange)
+		};
+
+		if &source != justified {
+			return Err(VoteError::SourceNotJustified)
+		}
+		if votes.contains_key(&index) {
+			return Err(VoteError::DuplicateVote)
+		}
+
+		votes.insert(index, target);
+		Ok(())
+	}
+
+	/// Process justification and finalization at the end of the current
+	/// epoch, given the checkpoints of the previous and current epoch, and
+	/// move to the next epoch.
+	pub fn advance_epoch<S: WeightStore>(
+		&mut self,
+		store: &S,
+		previous_checkpoint: C,
+		current_checkpoint: C,
+	) -> Result<(), S::Error> {
+		// As on the beacon chain, the first two epochs have no previous
+		// epoch to justify.
+		if self.epoch > self.genesis_epoch + 1 {
+			let registry = VoteRegistry {
+				epoch: self.epoch,
+				total_weight: store.total_weight()?,
+				previous_votes: &self.previous_votes,
+				current_votes: &self.current_votes,
+				store,
+			};
+
+			let mut justifier = Justifier {
+				justification_bits: self.justification_bits.clone(),
+				current_justified_checkpoint: self.current_justified_checkpoint.clone(),
+				previous_justified_checkpoint: self.previous_justified_checkpoint.clone(),
+				finalized_checkpoint: self.finalized_checkpoint.clone(),
+			};
+			justifier.process(previous_checkpoint, current_checkpoint, &registry)?;
+
+			self.justification_bits = justifier.justification_bits;
+			self.current_justified_checkpoint = justifier.current_justified_checkpoint;
+			self.previous_justified_checkpoint = justifier.previous_justified_checkpoint;
+			self.finalized_checkpoint = justifier.finalized_checkpoint;
+		}
+
+		self.previous_votes = core::mem::replace(&mut self.current_votes, BTreeMap::new());
+		self.epoch += 1;
+
+		Ok(())
+	}
+}
+
+struct VoteRegistry<'a, C, S> {
+	epoch: Epoch,
+	total_weight: Balance,
+	previous_votes: &'a BTreeMap<ValidatorIndex, C>,
+	current_votes: &'a BTreeMap<ValidatorIndex, C>,
+	store: &'a S,
+}
+
+impl<'a, C: Checkpoint + PartialEq, S: WeightStore> JustifierRegistry for VoteRegistry<'a, C, S> {
+	type Checkpoint = C;
+	type Error = S::Error;
+
+	fn total_active_balance(&self) -> Balance {
+		self.total_weight
+	}
+
+	fn attesting_target_balance(&self, checkpoint: &C) -> Result<Balance, S::Error> {
+		let votes = if checkpoint.epoch() == self.epoch {
+			self.current_votes
+		} else {
+			self.previous_votes
+		};
+
+		let mut balance = Balance::default();
+		for (index, target) in votes {
+			if target == checkpoint {
+				balance += self.store.weight(*index)?;
+			}
+		}
+		Ok(balance)
+	}
+}
+
+/// Thread-safe wrapper of `CasperContext`, for sharing the gadget between
+/// the threads ingesting votes and the one driving epochs.
+#[cfg(feature = "std")]
+#[derive(Clone)]
+pub struct SharedCasperContext<C: Checkpoint>(
+	std::sync::Arc<std::sync::Mutex<CasperContext<C>>>
+);
+
+#[cfg(feature = "std")]
+impl<C: Checkpoint + PartialEq> SharedCasperContext<C> {
+	/// Create a new shared context from the genesis checkpoint.
+	pub fn new(genesis: C) -> Self {
+		Self(std::sync::Arc::new(std::sync::Mutex::new(CasperContext::new(genesis))))
+	}
+
+	/// Push a vote. See `CasperContext::push_vote`.
+	pub fn push_vote(
+		&self,
+		index: ValidatorIndex,
+		source: C,
+		target: C,
+	) -> Result<(), VoteError> {
+		self.0.lock().expect("Lock is poisoned").push_vote(index, source, target)
+	}
+
+	/// Advance the epoch. See `CasperContext::advance_epoch`.
+	pub fn advance_epoch<S: WeightStore>(
+		&self,
+		store: &S,
+		previous_checkpoint: C,
+		current_checkpoint: C,
+	) -> Result<(), S::Error> {
+		self.0.lock().expect("Lock is poisoned")
+			.advance_epoch(store, previous_checkpoint, current_checkpoint)
+	}
+
+	/// Latest justified checkpoint.
+	pub fn current_justified_checkpoint(&self) -> C {
+		self.0.lock().expect("Lock is poisoned").current_justified_checkpoint().clone()
+	}
+
+	/// Latest finalized checkpoint.
+	pub fn finalized_checkpoint(&self) -> C {
+		self.0.lock().expect("Lock is poisoned").finalized_checkpoint().clone()
+	}
+
+	/// Snapshot of the context.
+	pub fn snapshot(&self) -> CasperContext<C> {
+		self.0.lock().expect("Lock is poisoned").clone()
+	}
+}
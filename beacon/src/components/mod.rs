@@ -1,10 +1,20 @@
+mod context;
 mod justification;
+mod participation;
 pub mod reward;
+pub mod slashing;
 
+pub use self::context::{CasperContext, WeightStore, VoteError};
+#[cfg(feature = "std")]
+pub use self::context::SharedCasperContext;
 pub use self::justification::Justifier;
+pub use self::participation::ParticipationBits;
 
-type Epoch = u64;
-type Balance = u64;
+use alloc::vec::Vec;
+use alloc::boxed::Box;
+
+type Epoch = crate::primitives::Epoch;
+type Balance = crate::primitives::Gwei;
 type ValidatorIndex = u64;
 
 pub trait Validator {
@@ -42,6 +52,20 @@ pub trait Registry: JustifierRegistry {
 		source_checkpoint: &Self::Checkpoint,
 		index: ValidatorIndex,
 	) -> Result<Option<Self::Attestation>, Self::Error>;
+	fn min_inclusion_delay_attestations(
+		&self,
+		source_checkpoint: &Self::Checkpoint,
+	) -> Result<Vec<(ValidatorIndex, Self::Attestation)>, Self::Error> {
+		let mut ret = Vec::new();
+		for (index, _) in self.unslashed_attesting_validators(source_checkpoint)? {
+			if let Some(attestation) = self.min_inclusion_delay_attestation(
+				source_checkpoint, index
+			)? {
+				ret.push((index, attestation));
+			}
+		}
+		Ok(ret)
+	}
 
 	fn unslashed_attesting_balance(
 		&self,
@@ -51,6 +75,14 @@ pub trait Registry: JustifierRegistry {
 		&'a self,
 		source_checkpoint: &Self::Checkpoint,
 	) -> Result<Box<dyn Iterator<Item=(ValidatorIndex, &Self::Validator)> + 'a>, Self::Error>;
+	fn unslashed_attesting_participation(
+		&self,
+		source_checkpoint: &Self::Checkpoint,
+	) -> Result<ParticipationBits, Self::Error> {
+		Ok(self.unslashed_attesting_validators(source_checkpoint)?
+		   .map(|(index, _)| index)
+		   .collect())
+	}
 	fn unslashed_attesting_target_balance(
 		&self,
 		source_checkpoint: &Self::Checkpoint,
@@ -59,6 +91,14 @@ pub trait Registry: JustifierRegistry {
 		&'a self,
 		source_checkpoint: &Self::Checkpoint,
 	) -> Result<Box<dyn Iterator<Item=(ValidatorIndex, &Self::Validator)> + 'a>, Self::Error>;
+	fn unslashed_attesting_target_participation(
+		&self,
+		source_checkpoint: &Self::Checkpoint,
+	) -> Result<ParticipationBits, Self::Error> {
+		Ok(self.unslashed_attesting_target_validators(source_checkpoint)?
+		   .map(|(index, _)| index)
+		   .collect())
+	}
 	fn unslashed_attesting_matching_head_balance(
 		&self,
 		source_checkpoint: &Self::Checkpoint,
@@ -67,6 +107,14 @@ pub trait Registry: JustifierRegistry {
 		&'a self,
 		source_checkpoint: &Self::Checkpoint,
 	) -> Result<Box<dyn Iterator<Item=(ValidatorIndex, &Self::Validator)> + 'a>, Self::Error>;
+	fn unslashed_attesting_matching_head_participation(
+		&self,
+		source_checkpoint: &Self::Checkpoint,
+	) -> Result<ParticipationBits, Self::Error> {
+		Ok(self.unslashed_attesting_matching_head_validators(source_checkpoint)?
+		   .map(|(index, _)| index)
+		   .collect())
+	}
 
 	fn balance(
 		&self,
@@ -80,12 +128,12 @@ pub trait Registry: JustifierRegistry {
 		&mut self,
 		index: ValidatorIndex,
 		value: Balance,
-	);
+	) -> Result<(), Self::Error>;
 	fn decrease_balance(
 		&mut self,
 		index: ValidatorIndex,
 		value: Balance,
-	);
+	) -> Result<(), Self::Error>;
 	fn validators<'a>(
 		&'a self,
 	) -> Result<Box<dyn Iterator<Item=(ValidatorIndex, &Self::Validator)> + 'a>, Self::Error>;
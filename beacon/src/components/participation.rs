@@ -0,0 +1,53 @@
+use alloc::{vec, vec::Vec};
+use core::iter::FromIterator;
+use super::ValidatorIndex;
+
+/// Set of participating validators, stored as a bitfield indexed by
+/// validator index.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct ParticipationBits(Vec<bool>);
+
+impl ParticipationBits {
+	pub fn with_capacity(len: usize) -> Self {
+		Self(vec![false; len])
+	}
+
+	pub fn insert(&mut self, index: ValidatorIndex) {
+		let index = index as usize;
+		if index >= self.0.len() {
+			self.0.resize(index + 1, false);
+		}
+		self.0[index] = true;
+	}
+
+	pub fn contains(&self, index: ValidatorIndex) -> bool {
+		self.0.get(index as usize).cloned().unwrap_or(false)
+	}
+
+	pub fn remove(&mut self, index: ValidatorIndex) {
+		if let Some(bit) = self.0.get_mut(index as usize) {
+			*bit = false;
+		}
+	}
+
+	pub fn indices<'a>(&'a self) -> impl Iterator<Item=ValidatorIndex> + 'a {
+		self.0.iter()
+			.enumerate()
+			.filter(|(_, bit)| **bit)
+			.map(|(index, _)| index as ValidatorIndex)
+	}
+
+	pub fn count(&self) -> usize {
+		self.0.iter().filter(|bit| **bit).count()
+	}
+}
+
+impl FromIterator<ValidatorIndex> for ParticipationBits {
+	fn from_iter<I: IntoIterator<Item=ValidatorIndex>>(iter: I) -> Self {
+		let mut bits = Self::default();
+		for index in iter {
+			bits.insert(index);
+		}
+		bits
+	}
+}
@@ -1,92 +1,153 @@
+use alloc::vec::Vec;
 use alloc::collections::BTreeMap;
 use super::{Registry, Checkpoint, Validator, Attestation, ValidatorIndex, Balance};
 use crate::{Config, utils, consts};
+use crate::utils::{SafeArith, ArithError};
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
 
 pub fn base<R: Registry, C: Config>(
 	registry: &R,
 	index: ValidatorIndex,
-) -> Result<u64, R::Error> {
+) -> Result<Balance, R::Error> where
+	R::Error: From<ArithError>,
+{
 	let total_balance = registry.total_active_balance();
 	let effective_balance = registry.effective_balance(index)?;
 
-	Ok(effective_balance * C::base_reward_factor() /
-	   utils::integer_squareroot(total_balance) /
-	   consts::BASE_REWARDS_PER_EPOCH)
+	Ok(base_with_sqrt::<C>(
+		effective_balance,
+		utils::integer_squareroot(total_balance.as_u64()),
+	)?)
+}
+
+fn base_with_sqrt<C: Config>(
+	effective_balance: Balance,
+	total_balance_sqrt: u64,
+) -> Result<Balance, ArithError> {
+	effective_balance.safe_mul(C::base_reward_factor())?
+		.safe_div(total_balance_sqrt)?
+		.safe_div(consts::BASE_REWARDS_PER_EPOCH)
+}
+
+#[derive(Clone, Default)]
+struct Participation {
+	index: ValidatorIndex,
+	effective_balance: Balance,
+	source: bool,
+	target: bool,
+	head: bool,
+	inclusion_delay: Option<u64>,
 }
 
 pub fn process<R: Registry, C: Config>(
 	registry: &mut R,
 	previous_checkpoint: R::Checkpoint,
 	finalized_checkpoint: R::Checkpoint,
-) -> Result<(), R::Error> {
+) -> Result<(), R::Error> where
+	R::Error: From<ArithError>,
+{
 	let total_balance = registry.total_active_balance();
-	let mut rewards: BTreeMap<ValidatorIndex, Balance> = BTreeMap::new();
-	let mut penalties: BTreeMap<ValidatorIndex, Balance> = BTreeMap::new();
-
-	// Micro-incentives for matching FFG source, FFG target, and head
-	macro_rules! micro_incentives {
-		( $balance:tt, $validators:tt ) => {
-			let attesting_balance = registry.$balance(&previous_checkpoint)?;
-			for (index, validator) in registry.validators()? {
-				if validator.is_eligible(&previous_checkpoint) {
-					if registry.$validators(&previous_checkpoint)?
-						.find(|(i, _)| *i == index).is_some()
-					{
-						*rewards.entry(index).or_default() += base::<_, C>(registry, index)? *
-							attesting_balance / total_balance;
-					} else {
-						*penalties.entry(index).or_default() += base::<_, C>(registry, index)?;
-					}
-				}
-			}
+	let total_balance_sqrt = utils::integer_squareroot(total_balance.as_u64());
+	let source_balance = registry.unslashed_attesting_balance(&previous_checkpoint)?;
+	let target_balance = registry.unslashed_attesting_target_balance(&previous_checkpoint)?;
+	let head_balance = registry.unslashed_attesting_matching_head_balance(&previous_checkpoint)?;
+	let finality_delay = previous_checkpoint.epoch().safe_sub(finalized_checkpoint.epoch())?.as_u64();
+	let inactivity_leak = finality_delay > C::min_epochs_to_inactivity_penalty();
+
+	// Precompute participation flags of all eligible validators in one pass.
+	let source = registry.unslashed_attesting_participation(&previous_checkpoint)?;
+	let target = registry.unslashed_attesting_target_participation(&previous_checkpoint)?;
+	let head = registry.unslashed_attesting_matching_head_participation(&previous_checkpoint)?;
+
+	let mut participations = Vec::new();
+	let mut positions: BTreeMap<ValidatorIndex, usize> = BTreeMap::new();
+	for (index, validator) in registry.validators()? {
+		if validator.is_eligible(&previous_checkpoint) {
+			positions.insert(index, participations.len());
+			participations.push(Participation {
+				index,
+				effective_balance: registry.effective_balance(index)?,
+				source: source.contains(index),
+				target: target.contains(index),
+				head: head.contains(index),
+				inclusion_delay: None,
+			});
 		}
 	}
 
-	micro_incentives!(unslashed_attesting_balance, unslashed_attesting_validators);
-	micro_incentives!(unslashed_attesting_target_balance, unslashed_attesting_target_validators);
-	micro_incentives!(unslashed_attesting_matching_head_balance,
-					  unslashed_attesting_matching_head_validators);
-
-	// Proposer and inclusion delay micro-rewards
-	for (index, _) in registry.unslashed_attesting_validators(&previous_checkpoint)? {
-		let attestation = registry.min_inclusion_delay_attestation(
-			&previous_checkpoint, index
-		)?;
-
-		if let Some(attestation) = attestation {
-			let proposer_reward = base::<_, C>(registry, index)? / C::proposer_reward_quotient();
-			*rewards.entry(attestation.proposer_index()).or_default() += proposer_reward;
-			let max_attester_reward = base::<_, C>(registry, index)? - proposer_reward;
-			*rewards.entry(index).or_default() += max_attester_reward /
-				attestation.inclusion_delay();
+	// Proposer micro-rewards are credited to another validator, so they are
+	// collected here rather than in the per-validator pass.
+	let mut proposer_rewards: BTreeMap<ValidatorIndex, Balance> = BTreeMap::new();
+	for (index, attestation) in registry.min_inclusion_delay_attestations(
+		&previous_checkpoint
+	)? {
+		let proposer_reward = base::<_, C>(registry, index)?
+			.safe_div(C::proposer_reward_quotient())?;
+		proposer_rewards.entry(attestation.proposer_index()).or_default()
+			.safe_add_assign(proposer_reward)?;
+
+		if let Some(position) = positions.get(&index) {
+			participations[*position].inclusion_delay = Some(attestation.inclusion_delay());
 		}
 	}
 
-	// Inactivity penalty
-	let finality_delay = previous_checkpoint.epoch() - finalized_checkpoint.epoch();
-	if finality_delay > C::min_epochs_to_inactivity_penalty() {
-		for (index, validator) in registry.validators()? {
-			if validator.is_eligible(&previous_checkpoint) {
-				*penalties.entry(index).or_default() += base::<_, C>(registry, index)? *
-					consts::BASE_REWARDS_PER_EPOCH;
-
-				if !registry.unslashed_attesting_target_validators(&previous_checkpoint)?
-					.find(|(i, _)| *i == index).is_some()
-				{
-					*penalties.entry(index).or_default() +=
-						registry.effective_balance(index)? * finality_delay /
-						C::inactivity_penalty_quotient();
-				}
+	let delta = |participation: &Participation| -> Result<(Balance, Balance), ArithError> {
+		let base = base_with_sqrt::<C>(participation.effective_balance, total_balance_sqrt)?;
+		let mut reward = Balance::default();
+		let mut penalty = Balance::default();
+
+		// Micro-incentives for matching FFG source, FFG target, and head
+		for (attested, attesting_balance) in &[
+			(participation.source, source_balance),
+			(participation.target, target_balance),
+			(participation.head, head_balance),
+		] {
+			if *attested {
+				reward.safe_add_assign(
+					base.safe_mul_div(attesting_balance.as_u64(), total_balance.as_u64())?
+				)?;
+			} else {
+				penalty.safe_add_assign(base)?;
 			}
 		}
+
+		// Inclusion delay micro-rewards
+		if let Some(inclusion_delay) = participation.inclusion_delay {
+			let proposer_reward = base.safe_div(C::proposer_reward_quotient())?;
+			reward.safe_add_assign(base.safe_sub(proposer_reward)?.safe_div(inclusion_delay)?)?;
+		}
+
+		// Inactivity penalty
+		if inactivity_leak {
+			penalty.safe_add_assign(base.safe_mul(consts::BASE_REWARDS_PER_EPOCH)?)?;
+
+			if !participation.target {
+				penalty.safe_add_assign(
+					participation.effective_balance.safe_mul(finality_delay)?
+						.safe_div(C::inactivity_penalty_quotient())?
+				)?;
+			}
+		}
+
+		Ok((reward, penalty))
+	};
+
+	#[cfg(feature = "parallel")]
+	let deltas = participations.par_iter().map(delta).collect::<Result<Vec<_>, _>>()?;
+	#[cfg(not(feature = "parallel"))]
+	let deltas = participations.iter().map(delta).collect::<Result<Vec<_>, _>>()?;
+
+	for (index, reward) in proposer_rewards {
+		registry.increase_balance(index, reward)?;
 	}
 
-	for (index, delta) in rewards {
-		registry.increase_balance(index, delta);
+	for (participation, (reward, _)) in participations.iter().zip(deltas.iter()) {
+		registry.increase_balance(participation.index, *reward)?;
 	}
 
-	for (index, delta) in penalties {
-		registry.decrease_balance(index, delta);
+	for (participation, (_, penalty)) in participations.iter().zip(deltas.iter()) {
+		registry.decrease_balance(participation.index, *penalty)?;
 	}
 
 	Ok(())
@@ -0,0 +1,39 @@
+//! Casper FFG slashing conditions.
+
+use super::Epoch;
+
+/// Vote of an attestation, as seen by the slashing conditions.
+pub trait SlashableAttestationData {
+	/// Vote data. Attestations with equal data cast the same vote.
+	type Data: PartialEq;
+
+	/// Vote data.
+	fn data(&self) -> &Self::Data;
+	/// Epoch of the FFG source.
+	fn source_epoch(&self) -> Epoch;
+	/// Epoch of the FFG target.
+	fn target_epoch(&self) -> Epoch;
+}
+
+/// Kind of slashable offence.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SlashingKind {
+	/// Two different votes for the same target epoch.
+	DoubleVote,
+	/// The first vote surrounds the second one.
+	SurroundVote,
+}
+
+/// Check whether casting both votes is a slashable offence, with `a1` as
+/// the first attestation of an attester slashing. A surround vote is only
+/// reported if `a1` surrounds `a2`, so callers checking an arbitrary pair
+/// should check both orders.
+pub fn check<A: SlashableAttestationData + ?Sized>(a1: &A, a2: &A) -> Option<SlashingKind> {
+	if a1.data() != a2.data() && a1.target_epoch() == a2.target_epoch() {
+		Some(SlashingKind::DoubleVote)
+	} else if a1.source_epoch() < a2.source_epoch() && a2.target_epoch() < a1.target_epoch() {
+		Some(SlashingKind::SurroundVote)
+	} else {
+		None
+	}
+}
@@ -16,8 +16,13 @@
 
 use digest::Digest;
 use typenum::Unsigned;
+#[cfg(feature = "serde")]
 use serde::{Serialize, Deserialize};
-use crate::primitives::{H256, Uint, Signature, ValidatorId};
+use crate::primitives::{H256, Uint, Signature, ValidatorId, Slot, Epoch, Gwei};
+use crate::{DefaultRewardScheme, ParticipationFlagRewardScheme};
+use alloc::boxed::Box;
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicPtr, Ordering};
 
 /// BLS operations
 pub trait BLSConfig: Default + Clone + 'static {
@@ -51,10 +56,17 @@ impl BLSConfig for BLSNoVerification {
 	}
 }
 
+/// Default digest for hashing and tree hashing. This is always SHA-256;
+/// the `asm` feature only enables `sha2/asm`, which compiles the same type
+/// with the assembly backend where the target supports it.
+pub type DefaultDigest = sha2::Sha256;
+
 /// Constants used in beacon block.
 pub trait Config: Default + Clone + PartialEq + Eq + core::fmt::Debug + Send + Sync + 'static {
 	/// Digest hash function.
 	type Digest: Digest<OutputSize=typenum::U32>;
+	/// Scheme of rewards and penalties at epoch transitions.
+	type RewardScheme: crate::RewardScheme;
 	/// Max validators per committee.
 	type MaxValidatorsPerCommittee: Unsigned + core::fmt::Debug + Clone + Eq + PartialEq + Default + Send + Sync + 'static;
 	/// Slots per historical root.
@@ -106,23 +118,25 @@ pub trait Config: Default + Clone + PartialEq + Eq + core::fmt::Debug + Send + S
 
 	// == Gwei values ==
 	/// Minimum deposit amount.
-	fn min_deposit_amount() -> Uint;
+	fn min_deposit_amount() -> Gwei;
 	/// Maximum effective balance.
-	fn max_effective_balance() -> Uint;
+	fn max_effective_balance() -> Gwei;
 	/// Ejection balance.
-	fn ejection_balance() -> Uint;
+	fn ejection_balance() -> Gwei;
 	/// Effective balance increment.
-	fn effective_balance_increment() -> Uint;
+	fn effective_balance_increment() -> Gwei;
 
 	// == Initial values ==
 	/// Genesis slot.
-	fn genesis_slot() -> Uint;
+	fn genesis_slot() -> Slot;
 	/// Genesis epoch.
-	fn genesis_epoch() -> Uint;
+	fn genesis_epoch() -> Epoch;
 	/// BLS withdrawal prefix byte.
 	fn bls_withdrawal_prefix_byte() -> u8;
 
 	// == Time parameters ==
+	/// Seconds per slot.
+	fn seconds_per_slot() -> Uint;
 	/// Minimum attestation inclusion delay.
 	fn min_attestation_inclusion_delay() -> Uint;
 	/// Slots per epoch.
@@ -189,6 +203,10 @@ pub trait Config: Default + Clone + PartialEq + Eq + core::fmt::Debug + Send + S
 	fn domain_deposit() -> u32 { 3 }
 	/// Voluntary exit domain.
 	fn domain_voluntary_exit() -> u32 { 4 }
+	/// Aggregator selection proof domain.
+	fn domain_selection_proof() -> u32 { 5 }
+	/// Aggregate and proof domain.
+	fn domain_aggregate_and_proof() -> u32 { 6 }
 
 	// == Helpers ==
 	/// Hash function.
@@ -211,7 +229,8 @@ pub trait Config: Default + Clone + PartialEq + Eq + core::fmt::Debug + Send + S
 pub struct MinimalConfig;
 
 impl Config for MinimalConfig {
-	type Digest = sha2::Sha256;
+	type Digest = DefaultDigest;
+	type RewardScheme = DefaultRewardScheme;
 	type MaxValidatorsPerCommittee = typenum::U2048;
 	type SlotsPerHistoricalRoot = typenum::U64;
 	type MaxProposerSlashings = typenum::U16;
@@ -238,17 +257,18 @@ impl Config for MinimalConfig {
 	fn min_genesis_time() -> Uint { 1578009600 }
 
 	// == Gwei values ==
-	fn min_deposit_amount() -> Uint { 1000000000 }
-	fn max_effective_balance() -> Uint { 32000000000 }
-	fn ejection_balance() -> Uint { 16000000000 }
-	fn effective_balance_increment() -> Uint { 1000000000 }
+	fn min_deposit_amount() -> Gwei { Gwei::new(1000000000) }
+	fn max_effective_balance() -> Gwei { Gwei::new(32000000000) }
+	fn ejection_balance() -> Gwei { Gwei::new(16000000000) }
+	fn effective_balance_increment() -> Gwei { Gwei::new(1000000000) }
 
 	// == Initial values ==
-	fn genesis_slot() -> Uint { 0 }
-	fn genesis_epoch() -> Uint { 0 }
+	fn genesis_slot() -> Slot { Slot::new(0) }
+	fn genesis_epoch() -> Epoch { Epoch::new(0) }
 	fn bls_withdrawal_prefix_byte() -> u8 { 0x00 }
 
 	// == Time parameters ==
+	fn seconds_per_slot() -> Uint { 6 }
 	fn min_attestation_inclusion_delay() -> Uint { 1 }
 	fn min_seed_lookahead() -> Uint { 1 }
 	fn max_seed_lookahead() -> Uint { 4 }
@@ -273,7 +293,8 @@ impl Config for MinimalConfig {
 pub struct MainnetConfig;
 
 impl Config for MainnetConfig {
-	type Digest = sha2::Sha256;
+	type Digest = DefaultDigest;
+	type RewardScheme = DefaultRewardScheme;
 	type MaxValidatorsPerCommittee = typenum::U2048;
 	type SlotsPerHistoricalRoot = typenum::U8192;
 	type MaxProposerSlashings = typenum::U16;
@@ -300,17 +321,18 @@ impl Config for MainnetConfig {
 	fn min_genesis_time() -> Uint { 1578009600 }
 
 	// == Gwei values ==
-	fn min_deposit_amount() -> Uint { 1000000000 }
-	fn max_effective_balance() -> Uint { 32000000000 }
-	fn ejection_balance() -> Uint { 16000000000 }
-	fn effective_balance_increment() -> Uint { 1000000000 }
+	fn min_deposit_amount() -> Gwei { Gwei::new(1000000000) }
+	fn max_effective_balance() -> Gwei { Gwei::new(32000000000) }
+	fn ejection_balance() -> Gwei { Gwei::new(16000000000) }
+	fn effective_balance_increment() -> Gwei { Gwei::new(1000000000) }
 
 	// == Initial values ==
-	fn genesis_slot() -> Uint { 0 }
-	fn genesis_epoch() -> Uint { 0 }
+	fn genesis_slot() -> Slot { Slot::new(0) }
+	fn genesis_epoch() -> Epoch { Epoch::new(0) }
 	fn bls_withdrawal_prefix_byte() -> u8 { 0x00 }
 
 	// == Time parameters ==
+	fn seconds_per_slot() -> Uint { 12 }
 	fn min_attestation_inclusion_delay() -> Uint { 1 }
 	fn min_seed_lookahead() -> Uint { 1 }
 	fn max_seed_lookahead() -> Uint { 4 }
@@ -335,7 +357,8 @@ impl Config for MainnetConfig {
 pub struct SapphireConfig;
 
 impl Config for SapphireConfig {
-	type Digest = sha2::Sha256;
+	type Digest = DefaultDigest;
+	type RewardScheme = DefaultRewardScheme;
 	type MaxValidatorsPerCommittee = typenum::U2048;
 	type SlotsPerHistoricalRoot = typenum::U64;
 	type MaxProposerSlashings = typenum::U16;
@@ -362,17 +385,18 @@ impl Config for SapphireConfig {
 	fn min_genesis_time() -> Uint { 1578009600 }
 
 	// == Gwei values ==
-	fn min_deposit_amount() -> Uint { 100 }
-	fn max_effective_balance() -> Uint { 3200000000 }
-	fn ejection_balance() -> Uint { 3000000000 }
-	fn effective_balance_increment() -> Uint { 1000000000 }
+	fn min_deposit_amount() -> Gwei { Gwei::new(100) }
+	fn max_effective_balance() -> Gwei { Gwei::new(3200000000) }
+	fn ejection_balance() -> Gwei { Gwei::new(3000000000) }
+	fn effective_balance_increment() -> Gwei { Gwei::new(1000000000) }
 
 	// == Initial values ==
-	fn genesis_slot() -> Uint { 0 }
-	fn genesis_epoch() -> Uint { 0 }
+	fn genesis_slot() -> Slot { Slot::new(0) }
+	fn genesis_epoch() -> Epoch { Epoch::new(0) }
 	fn bls_withdrawal_prefix_byte() -> u8 { 0x00 }
 
 	// == Time parameters ==
+	fn seconds_per_slot() -> Uint { 12 }
 	fn min_attestation_inclusion_delay() -> Uint { 1 }
 	fn min_seed_lookahead() -> Uint { 1 }
 	fn max_seed_lookahead() -> Uint { 4 }
@@ -388,3 +412,298 @@ impl Config for SapphireConfig {
 	fn inactivity_penalty_quotient() -> Uint { 33554432 }
 	fn min_slashing_penalty_quotient() -> Uint { 32 }
 }
+
+/// Values of the non type-level parameters of a config, used by
+/// `DynamicConfig`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ConfigValues {
+	/// Maximum committees per slot.
+	pub max_committees_per_slot: Uint,
+	/// Target committee size.
+	pub target_committee_size: Uint,
+	/// Minimum per-epoch churn limit.
+	pub min_per_epoch_churn_limit: Uint,
+	/// Churn limit quotient.
+	pub churn_limit_quotient: Uint,
+	/// Shuffle round count.
+	pub shuffle_round_count: Uint,
+	/// Min genesis active validator count.
+	pub min_genesis_active_validator_count: Uint,
+	/// Min genesis time.
+	pub min_genesis_time: Uint,
+	/// Minimum deposit amount.
+	pub min_deposit_amount: Gwei,
+	/// Maximum effective balance.
+	pub max_effective_balance: Gwei,
+	/// Ejection balance.
+	pub ejection_balance: Gwei,
+	/// Effective balance increment.
+	pub effective_balance_increment: Gwei,
+	/// Genesis slot.
+	pub genesis_slot: Slot,
+	/// Genesis epoch.
+	pub genesis_epoch: Epoch,
+	/// BLS withdrawal prefix byte.
+	pub bls_withdrawal_prefix_byte: u8,
+	/// Seconds per slot.
+	pub seconds_per_slot: Uint,
+	/// Minimum attestation inclusion delay.
+	pub min_attestation_inclusion_delay: Uint,
+	/// Minimum seed lookahead.
+	pub min_seed_lookahead: Uint,
+	/// Maximum seed lookahead.
+	pub max_seed_lookahead: Uint,
+	/// Minimal validator withdrawability delay.
+	pub min_validator_withdrawability_delay: Uint,
+	/// Persistent committee period.
+	pub persistent_committee_period: Uint,
+	/// Maximum crosslink epochs.
+	pub max_epochs_per_crosslink: Uint,
+	/// Minimum epochs to inactivity penalty.
+	pub min_epochs_to_inactivity_penalty: Uint,
+	/// Base reward quotient.
+	pub base_reward_factor: Uint,
+	/// Whistleblowing reward quotient.
+	pub whistleblower_reward_quotient: Uint,
+	/// Proposer reward quotient.
+	pub proposer_reward_quotient: Uint,
+	/// Inactivity penalty quotient.
+	pub inactivity_penalty_quotient: Uint,
+	/// Minimal slashing penalty quotient.
+	pub min_slashing_penalty_quotient: Uint,
+}
+
+impl ConfigValues {
+	/// Values of a static config.
+	pub fn of<C: Config>() -> Self {
+		Self {
+			max_committees_per_slot: C::max_committees_per_slot(),
+			target_committee_size: C::target_committee_size(),
+			min_per_epoch_churn_limit: C::min_per_epoch_churn_limit(),
+			churn_limit_quotient: C::churn_limit_quotient(),
+			shuffle_round_count: C::shuffle_round_count(),
+			min_genesis_active_validator_count: C::min_genesis_active_validator_count(),
+			min_genesis_time: C::min_genesis_time(),
+			min_deposit_amount: C::min_deposit_amount(),
+			max_effective_balance: C::max_effective_balance(),
+			ejection_balance: C::ejection_balance(),
+			effective_balance_increment: C::effective_balance_increment(),
+			genesis_slot: C::genesis_slot(),
+			genesis_epoch: C::genesis_epoch(),
+			bls_withdrawal_prefix_byte: C::bls_withdrawal_prefix_byte(),
+			seconds_per_slot: C::seconds_per_slot(),
+			min_attestation_inclusion_delay: C::min_attestation_inclusion_delay(),
+			min_seed_lookahead: C::min_seed_lookahead(),
+			max_seed_lookahead: C::max_seed_lookahead(),
+			min_validator_withdrawability_delay: C::min_validator_withdrawability_delay(),
+			persistent_committee_period: C::persistent_committee_period(),
+			max_epochs_per_crosslink: C::max_epochs_per_crosslink(),
+			min_epochs_to_inactivity_penalty: C::min_epochs_to_inactivity_penalty(),
+			base_reward_factor: C::base_reward_factor(),
+			whistleblower_reward_quotient: C::whistleblower_reward_quotient(),
+			proposer_reward_quotient: C::proposer_reward_quotient(),
+			inactivity_penalty_quotient: C::inactivity_penalty_quotient(),
+			min_slashing_penalty_quotient: C::min_slashing_penalty_quotient(),
+		}
+	}
+
+	/// Install the values used by all `DynamicConfig` instances. Values can
+	/// only be installed once, before any use, and are returned back if
+	/// already installed.
+	pub fn install(self) -> Result<(), Self> {
+		let values = Box::into_raw(Box::new(self));
+		if DYNAMIC_CONFIG_VALUES.compare_exchange(
+			core::ptr::null_mut(), values, Ordering::SeqCst, Ordering::SeqCst
+		).is_ok() {
+			Ok(())
+		} else {
+			// Safe as the pointer was just created and never shared.
+			Err(*unsafe { Box::from_raw(values) })
+		}
+	}
+
+	/// Installed values, if any.
+	pub fn installed() -> Option<&'static ConfigValues> {
+		// Safe as installed values are never freed or modified.
+		unsafe { DYNAMIC_CONFIG_VALUES.load(Ordering::SeqCst).as_ref() }
+	}
+}
+
+static DYNAMIC_CONFIG_VALUES: AtomicPtr<ConfigValues> = AtomicPtr::new(core::ptr::null_mut());
+
+macro_rules! dynamic_value {
+	( $name:ident, $t:ty ) => {
+		fn $name() -> $t {
+			match ConfigValues::installed() {
+				Some(values) => values.$name,
+				None => Base::$name(),
+			}
+		}
+	}
+}
+
+#[derive(Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "parity-codec", derive(parity_codec::Encode, parity_codec::Decode))]
+/// Config with parameters chosen at runtime through `ConfigValues::install`,
+/// falling back to `Base` until installed. Lengths of ssz vectors and lists
+/// are type-level, and always taken from `Base`.
+pub struct DynamicConfig<Base: Config>(PhantomData<Base>);
+
+impl<Base: Config> Config for DynamicConfig<Base> {
+	type Digest = Base::Digest;
+	type RewardScheme = Base::RewardScheme;
+	type MaxValidatorsPerCommittee = Base::MaxValidatorsPerCommittee;
+	type SlotsPerHistoricalRoot = Base::SlotsPerHistoricalRoot;
+	type MaxProposerSlashings = Base::MaxProposerSlashings;
+	type MaxAttesterSlashings = Base::MaxAttesterSlashings;
+	type MaxAttestations = Base::MaxAttestations;
+	type MaxDeposits = Base::MaxDeposits;
+	type MaxVoluntaryExits = Base::MaxVoluntaryExits;
+	type HistoricalRootsLimit = Base::HistoricalRootsLimit;
+	type ShardCount = Base::ShardCount;
+	type SlotsPerEpoch = Base::SlotsPerEpoch;
+	type SlotsPerEth1VotingPeriod = Base::SlotsPerEth1VotingPeriod;
+	type ValidatorRegistryLimit = Base::ValidatorRegistryLimit;
+	type EpochsPerHistoricalVector = Base::EpochsPerHistoricalVector;
+	type EpochsPerSlashingsVector = Base::EpochsPerSlashingsVector;
+	type MaxAttestationsPerEpoch = Base::MaxAttestationsPerEpoch;
+
+	// === Runtime values ===
+	dynamic_value!(max_committees_per_slot, Uint);
+	dynamic_value!(target_committee_size, Uint);
+	dynamic_value!(min_per_epoch_churn_limit, Uint);
+	dynamic_value!(churn_limit_quotient, Uint);
+	dynamic_value!(shuffle_round_count, Uint);
+	dynamic_value!(min_genesis_active_validator_count, Uint);
+	dynamic_value!(min_genesis_time, Uint);
+	dynamic_value!(min_deposit_amount, Gwei);
+	dynamic_value!(max_effective_balance, Gwei);
+	dynamic_value!(ejection_balance, Gwei);
+	dynamic_value!(effective_balance_increment, Gwei);
+	dynamic_value!(genesis_slot, Slot);
+	dynamic_value!(genesis_epoch, Epoch);
+	dynamic_value!(bls_withdrawal_prefix_byte, u8);
+	dynamic_value!(seconds_per_slot, Uint);
+	dynamic_value!(min_attestation_inclusion_delay, Uint);
+	dynamic_value!(min_seed_lookahead, Uint);
+	dynamic_value!(max_seed_lookahead, Uint);
+	dynamic_value!(min_validator_withdrawability_delay, Uint);
+	dynamic_value!(persistent_committee_period, Uint);
+	dynamic_value!(max_epochs_per_crosslink, Uint);
+	dynamic_value!(min_epochs_to_inactivity_penalty, Uint);
+	dynamic_value!(base_reward_factor, Uint);
+	dynamic_value!(whistleblower_reward_quotient, Uint);
+	dynamic_value!(proposer_reward_quotient, Uint);
+	dynamic_value!(inactivity_penalty_quotient, Uint);
+	dynamic_value!(min_slashing_penalty_quotient, Uint);
+}
+
+macro_rules! base_value {
+	( $name:ident, $t:ty ) => {
+		fn $name() -> $t { Base::$name() }
+	}
+}
+
+#[derive(Default, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "parity-codec", derive(parity_codec::Encode, parity_codec::Decode))]
+/// Config with all parameters of `Base`, but rewards and penalties under the
+/// participation flag reward scheme.
+pub struct ParticipationFlagConfig<Base: Config>(PhantomData<Base>);
+
+impl<Base: Config> Config for ParticipationFlagConfig<Base> {
+	type Digest = Base::Digest;
+	type RewardScheme = ParticipationFlagRewardScheme;
+	type MaxValidatorsPerCommittee = Base::MaxValidatorsPerCommittee;
+	type SlotsPerHistoricalRoot = Base::SlotsPerHistoricalRoot;
+	type MaxProposerSlashings = Base::MaxProposerSlashings;
+	type MaxAttesterSlashings = Base::MaxAttesterSlashings;
+	type MaxAttestations = Base::MaxAttestations;
+	type MaxDeposits = Base::MaxDeposits;
+	type MaxVoluntaryExits = Base::MaxVoluntaryExits;
+	type HistoricalRootsLimit = Base::HistoricalRootsLimit;
+	type ShardCount = Base::ShardCount;
+	type SlotsPerEpoch = Base::SlotsPerEpoch;
+	type SlotsPerEth1VotingPeriod = Base::SlotsPerEth1VotingPeriod;
+	type ValidatorRegistryLimit = Base::ValidatorRegistryLimit;
+	type EpochsPerHistoricalVector = Base::EpochsPerHistoricalVector;
+	type EpochsPerSlashingsVector = Base::EpochsPerSlashingsVector;
+	type MaxAttestationsPerEpoch = Base::MaxAttestationsPerEpoch;
+
+	base_value!(max_committees_per_slot, Uint);
+	base_value!(target_committee_size, Uint);
+	base_value!(min_per_epoch_churn_limit, Uint);
+	base_value!(churn_limit_quotient, Uint);
+	base_value!(shuffle_round_count, Uint);
+	base_value!(min_genesis_active_validator_count, Uint);
+	base_value!(min_genesis_time, Uint);
+	base_value!(min_deposit_amount, Gwei);
+	base_value!(max_effective_balance, Gwei);
+	base_value!(ejection_balance, Gwei);
+	base_value!(effective_balance_increment, Gwei);
+	base_value!(genesis_slot, Slot);
+	base_value!(genesis_epoch, Epoch);
+	base_value!(bls_withdrawal_prefix_byte, u8);
+	base_value!(seconds_per_slot, Uint);
+	base_value!(min_attestation_inclusion_delay, Uint);
+	base_value!(min_seed_lookahead, Uint);
+	base_value!(max_seed_lookahead, Uint);
+	base_value!(min_validator_withdrawability_delay, Uint);
+	base_value!(persistent_committee_period, Uint);
+	base_value!(max_epochs_per_crosslink, Uint);
+	base_value!(min_epochs_to_inactivity_penalty, Uint);
+	base_value!(base_reward_factor, Uint);
+	base_value!(whistleblower_reward_quotient, Uint);
+	base_value!(proposer_reward_quotient, Uint);
+	base_value!(inactivity_penalty_quotient, Uint);
+	base_value!(min_slashing_penalty_quotient, Uint);
+}
+
+/// Parameters of the Altair fork.
+#[cfg(feature = "altair")]
+pub trait AltairConfig: Config {
+	/// Sync committee size.
+	type SyncCommitteeSize: Unsigned + core::fmt::Debug + Clone + Eq + PartialEq + Default + Send + Sync + 'static;
+
+	/// Sync committee size.
+	fn sync_committee_size() -> Uint { Self::SyncCommitteeSize::to_u64() }
+	/// Epochs per sync committee period.
+	fn epochs_per_sync_committee_period() -> Uint;
+	/// Sync committee domain.
+	fn domain_sync_committee() -> u32 { 7 }
+	/// Sync committee selection proof domain.
+	fn domain_sync_committee_selection_proof() -> u32 { 8 }
+	/// Contribution and proof domain.
+	fn domain_contribution_and_proof() -> u32 { 9 }
+}
+
+#[cfg(feature = "altair")]
+impl AltairConfig for MinimalConfig {
+	type SyncCommitteeSize = typenum::U32;
+
+	fn epochs_per_sync_committee_period() -> Uint { 8 }
+}
+
+#[cfg(feature = "altair")]
+impl AltairConfig for MainnetConfig {
+	type SyncCommitteeSize = typenum::U512;
+
+	fn epochs_per_sync_committee_period() -> Uint { 256 }
+}
+
+#[cfg(feature = "altair")]
+impl<Base: AltairConfig> AltairConfig for DynamicConfig<Base> {
+	type SyncCommitteeSize = Base::SyncCommitteeSize;
+
+	fn epochs_per_sync_committee_period() -> Uint { Base::epochs_per_sync_committee_period() }
+}
+
+#[cfg(feature = "altair")]
+impl<Base: AltairConfig> AltairConfig for ParticipationFlagConfig<Base> {
+	type SyncCommitteeSize = Base::SyncCommitteeSize;
+
+	fn epochs_per_sync_committee_period() -> Uint { Base::epochs_per_sync_committee_period() }
+}
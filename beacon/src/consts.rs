@@ -16,10 +16,10 @@
 
 //! Non-configurable constants used throughout the specification.
 
-use crate::primitives::Uint;
+use crate::primitives::{Uint, Epoch};
 
 /// Far future epoch.
-pub const FAR_FUTURE_EPOCH: Uint = Uint::max_value();
+pub const FAR_FUTURE_EPOCH: Epoch = Epoch::new(Uint::max_value());
 /// Base rewards per epoch.
 pub const BASE_REWARDS_PER_EPOCH: Uint = 4;
 /// Deposit contract tree depth.
@@ -28,5 +28,28 @@ pub const DEPOSIT_CONTRACT_TREE_DEPTH: Uint = 32;
 pub type DepositContractTreeDepth = typenum::U32;
 /// Seconds per day.
 pub const SECONDS_PER_DAY: Uint = 86400;
+/// Target number of aggregators per committee.
+pub const TARGET_AGGREGATORS_PER_COMMITTEE: Uint = 16;
+/// Gwei per Ether.
+pub const ETH_TO_GWEI: Uint = 1_000_000_000;
+/// Maximum percentage of safety decay tolerated within the weak subjectivity
+/// period.
+pub const SAFETY_DECAY: Uint = 10;
+/// Weight of the timely source flag, in participation flag rewards.
+pub const TIMELY_SOURCE_WEIGHT: Uint = 14;
+/// Weight of the timely target flag, in participation flag rewards.
+pub const TIMELY_TARGET_WEIGHT: Uint = 26;
+/// Weight of the timely head flag, in participation flag rewards.
+pub const TIMELY_HEAD_WEIGHT: Uint = 14;
+/// Weight of the proposer reward, in participation flag rewards.
+pub const PROPOSER_WEIGHT: Uint = 8;
+/// Denominator of participation flag reward weights.
+pub const WEIGHT_DENOMINATOR: Uint = 64;
+/// Maximum bytes of an execution transaction.
+pub type MaxBytesPerTransaction = typenum::U1073741824;
+/// Maximum transactions of an execution payload.
+pub type MaxTransactionsPerPayload = typenum::U1048576;
+/// Maximum extra data bytes of an execution payload.
+pub type MaxExtraDataBytes = typenum::U32;
 /// Justification bits length;
 pub type JustificationBitsLength = typenum::U4;
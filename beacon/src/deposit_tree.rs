@@ -0,0 +1,162 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use alloc::{vec, vec::Vec};
+use crate::primitives::H256;
+use crate::types::DepositData;
+use crate::{Config, consts};
+use core::marker::PhantomData;
+use bm_le::tree_root;
+
+const DEPTH: usize = consts::DEPOSIT_CONTRACT_TREE_DEPTH as usize;
+
+/// Incremental sparse merkle tree of deposits, as maintained by the deposit
+/// contract. Only completely filled nodes are stored, so appending is
+/// logarithmic, and roots and proofs can be generated for any earlier
+/// deposit count.
+#[derive(Clone, Debug)]
+pub struct DepositTree<C: Config> {
+	layers: Vec<Vec<H256>>,
+	zerohashes: Vec<H256>,
+	_marker: PhantomData<C>,
+}
+
+impl<C: Config> Default for DepositTree<C> {
+	fn default() -> Self {
+		let mut zerohashes = vec![H256::default()];
+		for layer in 1..(DEPTH + 1) {
+			zerohashes.push(C::hash(&[
+				zerohashes[layer - 1].as_ref(),
+				zerohashes[layer - 1].as_ref(),
+			]));
+		}
+
+		Self {
+			layers: vec![Vec::new(); DEPTH + 1],
+			zerohashes,
+			_marker: PhantomData,
+		}
+	}
+}
+
+impl<C: Config> DepositTree<C> {
+	/// Create a new empty tree.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Create a tree from deposits.
+	pub fn from_deposits(deposits: &[DepositData]) -> Self {
+		let mut tree = Self::new();
+		for deposit in deposits {
+			tree.push(deposit);
+		}
+		tree
+	}
+
+	/// Number of deposits in the tree.
+	pub fn len(&self) -> u64 {
+		self.layers[0].len() as u64
+	}
+
+	/// Whether the tree is empty.
+	pub fn is_empty(&self) -> bool {
+		self.layers[0].is_empty()
+	}
+
+	/// Append a deposit.
+	pub fn push(&mut self, deposit: &DepositData) {
+		self.push_leaf(tree_root::<C::Digest, _>(deposit))
+	}
+
+	/// Append a deposit leaf, updating the filled nodes above it.
+	pub fn push_leaf(&mut self, leaf: H256) {
+		self.layers[0].push(leaf);
+		for layer in 1..(DEPTH + 1) {
+			let below = &self.layers[layer - 1];
+			if below.len() % 2 == 1 {
+				break
+			}
+			let node = C::hash(&[
+				below[below.len() - 2].as_ref(),
+				below[below.len() - 1].as_ref(),
+			]);
+			self.layers[layer].push(node);
+		}
+	}
+
+	/// Deposit root of all deposits, including the length mix-in.
+	pub fn root(&self) -> H256 {
+		self.root_at(self.len()).expect("Deposit count is the tree length; qed")
+	}
+
+	/// Deposit root of the first `count` deposits, including the length
+	/// mix-in. Returns `None` if the tree has less deposits.
+	pub fn root_at(&self, count: u64) -> Option<H256> {
+		if count > self.len() {
+			return None
+		}
+
+		Some(C::hash(&[
+			self.node(DEPTH, 0, count).as_ref(),
+			length_mix_in(count).as_ref(),
+		]))
+	}
+
+	/// Proof of deposit `index` against the root of all deposits.
+	pub fn proof(&self, index: u64) -> Option<Vec<H256>> {
+		self.proof_at(index, self.len())
+	}
+
+	/// Proof of deposit `index` against the root of the first `count`
+	/// deposits, with `DEPOSIT_CONTRACT_TREE_DEPTH + 1` items. Returns `None`
+	/// if the index is not within the count, or the count not within the tree.
+	pub fn proof_at(&self, index: u64, count: u64) -> Option<Vec<H256>> {
+		if index >= count || count > self.len() {
+			return None
+		}
+
+		let mut proof = Vec::with_capacity(DEPTH + 1);
+		for layer in 0..DEPTH {
+			proof.push(self.node(layer, (index >> layer) ^ 1, count));
+		}
+		proof.push(length_mix_in(count));
+		Some(proof)
+	}
+
+	/// Node at `index` of `layer`, in the tree of the first `count` deposits.
+	fn node(&self, layer: usize, index: u64, count: u64) -> H256 {
+		let start = index << layer;
+		let end = (index + 1) << layer;
+
+		if start >= count {
+			self.zerohashes[layer]
+		} else if end <= count {
+			self.layers[layer][index as usize]
+		} else {
+			C::hash(&[
+				self.node(layer - 1, index * 2, count).as_ref(),
+				self.node(layer - 1, index * 2 + 1, count).as_ref(),
+			])
+		}
+	}
+}
+
+fn length_mix_in(count: u64) -> H256 {
+	let mut ret = [0u8; 32];
+	ret[..8].copy_from_slice(&count.to_le_bytes());
+	H256::from(ret)
+}
@@ -129,4 +129,26 @@ pub enum Error {
 	TooManyTransfers,
 	/// Invalid eth1 data.
 	InvalidEth1Data,
+	/// Light client attested header does not match the state.
+	LightClientAttestedStateMismatch,
+	/// Light client finalized header does not match the finalized checkpoint.
+	LightClientFinalizedHeaderMismatch,
+	/// Block is of a different fork than the state.
+	ForkMismatch,
+	/// Upgrade to an earlier fork.
+	ForkDowngrade,
+	/// Execution payload is not on the parent payload.
+	ExecutionPayloadParentHashMismatch,
+	/// Execution payload randao does not match the state.
+	ExecutionPayloadRandaoMismatch,
+	/// Execution payload timestamp does not match the slot.
+	ExecutionPayloadTimestampMismatch,
+	/// Execution engine rejected the payload.
+	ExecutionPayloadInvalid,
+	/// Execution engine could not be reached.
+	ExecutionEngineUnavailable,
+	/// Participation rate is over the basis points denominator.
+	ParticipationOutOfRange,
+	/// Arithmetic overflowed, underflowed or divided by zero.
+	ArithmeticOverflow,
 }
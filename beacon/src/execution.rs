@@ -0,0 +1,147 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+//! Interface to the execution engine, and production and verification of
+//! execution payloads against a beacon state.
+
+use crate::primitives::{H256, Slot, Uint, ExecutionAddress};
+use crate::types::{ExecutionPayload, ExecutionPayloadHeader};
+use crate::{BeaconExecutive, Config, Error};
+
+/// Validity of an execution payload, as reported by the execution engine.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PayloadStatus {
+	/// Payload is valid.
+	Valid,
+	/// Payload is invalid.
+	Invalid,
+	/// The engine is syncing, and cannot tell yet.
+	Syncing,
+}
+
+/// Attributes of a payload to build.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct PayloadAttributes {
+	/// Timestamp of the payload.
+	pub timestamp: Uint,
+	/// Randao mix of the beacon state.
+	pub prev_randao: H256,
+	/// Recipient of the transaction fees.
+	pub fee_recipient: ExecutionAddress,
+}
+
+/// Execution engine, following the engine API.
+pub trait ExecutionEngine {
+	/// Validate and import a new payload.
+	fn notify_new_payload(&self, payload: &ExecutionPayload) -> Result<PayloadStatus, Error>;
+	/// Update the head, safe and finalized execution blocks.
+	fn notify_forkchoice_updated(
+		&self,
+		head_block_hash: H256,
+		safe_block_hash: H256,
+		finalized_block_hash: H256,
+	) -> Result<PayloadStatus, Error>;
+	/// Build a payload on top of a parent execution block.
+	fn get_payload(
+		&self,
+		parent_hash: H256,
+		attributes: &PayloadAttributes,
+	) -> Result<ExecutionPayload, Error>;
+}
+
+/// Execution engine accepting all payloads and building empty ones, for
+/// running the beacon chain without an execution client.
+#[derive(Clone, Default, Debug)]
+pub struct StubExecutionEngine;
+
+impl ExecutionEngine for StubExecutionEngine {
+	fn notify_new_payload(&self, _payload: &ExecutionPayload) -> Result<PayloadStatus, Error> {
+		Ok(PayloadStatus::Valid)
+	}
+
+	fn notify_forkchoice_updated(
+		&self,
+		_head_block_hash: H256,
+		_safe_block_hash: H256,
+		_finalized_block_hash: H256,
+	) -> Result<PayloadStatus, Error> {
+		Ok(PayloadStatus::Valid)
+	}
+
+	fn get_payload(
+		&self,
+		parent_hash: H256,
+		attributes: &PayloadAttributes,
+	) -> Result<ExecutionPayload, Error> {
+		Ok(ExecutionPayload {
+			parent_hash,
+			fee_recipient: attributes.fee_recipient,
+			prev_randao: attributes.prev_randao,
+			timestamp: attributes.timestamp,
+			..Default::default()
+		})
+	}
+}
+
+/// Timestamp of a slot.
+pub fn compute_timestamp_at_slot<C: Config>(genesis_time: Uint, slot: Slot) -> Uint {
+	genesis_time + (slot - C::genesis_slot()).as_u64() * C::seconds_per_slot()
+}
+
+impl<'a, C: Config> BeaconExecutive<'a, C> {
+	/// Payload attributes of a block at the current slot.
+	pub fn payload_attributes(&self, fee_recipient: ExecutionAddress) -> PayloadAttributes {
+		PayloadAttributes {
+			timestamp: compute_timestamp_at_slot::<C>(self.genesis_time, self.slot),
+			prev_randao: self.randao_mix(self.current_epoch()),
+			fee_recipient,
+		}
+	}
+
+	/// Build the execution payload of a block at the current slot, on top of
+	/// the payload of the parent block.
+	pub fn produce_execution_payload<E: ExecutionEngine + ?Sized>(
+		&self,
+		engine: &E,
+		parent: &ExecutionPayloadHeader,
+		fee_recipient: ExecutionAddress,
+	) -> Result<ExecutionPayload, Error> {
+		engine.get_payload(parent.block_hash, &self.payload_attributes(fee_recipient))
+	}
+
+	/// Verify the execution payload of a block at the current slot against
+	/// the payload of the parent block, returning the header of the payload.
+	pub fn process_execution_payload<E: ExecutionEngine + ?Sized>(
+		&self,
+		engine: &E,
+		parent: &ExecutionPayloadHeader,
+		payload: &ExecutionPayload,
+	) -> Result<ExecutionPayloadHeader, Error> {
+		if payload.parent_hash != parent.block_hash {
+			return Err(Error::ExecutionPayloadParentHashMismatch)
+		}
+		if payload.prev_randao != self.randao_mix(self.current_epoch()) {
+			return Err(Error::ExecutionPayloadRandaoMismatch)
+		}
+		if payload.timestamp != compute_timestamp_at_slot::<C>(self.genesis_time, self.slot) {
+			return Err(Error::ExecutionPayloadTimestampMismatch)
+		}
+		if engine.notify_new_payload(payload)? == PayloadStatus::Invalid {
+			return Err(Error::ExecutionPayloadInvalid)
+		}
+
+		Ok(payload.header::<C>())
+	}
+}
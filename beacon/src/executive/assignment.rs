@@ -14,7 +14,8 @@
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
 
-use crate::primitives::ValidatorId;
+use alloc::vec::Vec;
+use crate::primitives::{ValidatorId, Slot, Epoch};
 use crate::{Config, BeaconExecutive, Error, utils};
 
 /// Committee assignment.
@@ -24,14 +25,22 @@ pub struct CommitteeAssignment {
 	/// Shard to which the committee is assigned.
 	pub index: u64,
 	/// Slot at which the committee is assigned.
-	pub slot: u64,
+	pub slot: Slot,
+}
+
+/// Proposer assignment.
+pub struct ProposerAssignment {
+	/// Slot of the proposal.
+	pub slot: Slot,
+	/// Index of the proposing validator.
+	pub validator_index: u64,
 }
 
 impl<'a, C: Config> BeaconExecutive<'a, C> {
 	/// Find committee assignment at slot.
 	pub fn committee_assignment(
 		&self,
-		epoch: u64,
+		epoch: Epoch,
 		validator_index: u64
 	) -> Result<Option<CommitteeAssignment>, Error> {
 		let next_epoch = self.current_epoch() + 1;
@@ -40,7 +49,7 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 		}
 
 		let epoch_start_slot = utils::start_slot_of_epoch::<C>(epoch);
-		for slot in epoch_start_slot..(epoch_start_slot + C::slots_per_epoch()) {
+		for slot in (0..C::slots_per_epoch()).map(|offset| epoch_start_slot + offset) {
 			let committee_count = self.committee_count_at_slot(slot);
 			for index in 0..committee_count {
 				let index = index as u64;
@@ -66,6 +75,21 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 		Some(validator.pubkey.clone())
 	}
 
+	/// Get proposer assignments of all slots in the current or next epoch.
+	pub fn proposer_assignments(
+		&self,
+		epoch: Epoch,
+	) -> Result<Vec<ProposerAssignment>, Error> {
+		let epoch_start_slot = utils::start_slot_of_epoch::<C>(epoch);
+		(0..C::slots_per_epoch())
+			.map(|offset| epoch_start_slot + offset)
+			.map(|slot| Ok(ProposerAssignment {
+				slot,
+				validator_index: self.proposer_index_at_slot(slot)?,
+			}))
+			.collect()
+	}
+
 	/// Get validator index from public key.
 	pub fn validator_index(&self, pubkey: &ValidatorId) -> Option<u64> {
 		let validator_pubkeys = self.validators.iter()
@@ -14,7 +14,8 @@
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
 
-use crate::primitives::H256;
+use alloc::vec::Vec;
+use crate::primitives::{H256, Epoch};
 use crate::types::BeaconBlock;
 use crate::{Config, BeaconExecutive, Error};
 
@@ -25,8 +26,9 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 		self.active_validator_indices(current_justified_epoch)
 	}
 
-	/// Get block attestation vote targets.
-	pub fn block_vote_targets(&self, block: &BeaconBlock<C>) -> Result<Vec<(u64, H256)>, Error> {
+	/// Get block attestation votes for head blocks, with their target
+	/// epochs.
+	pub fn block_vote_targets(&self, block: &BeaconBlock<C>) -> Result<Vec<(u64, H256, Epoch)>, Error> {
 		let mut ret = Vec::new();
 		for attestation in block.body.attestations.iter() {
 			let indexed = self.indexed_attestation(attestation.clone())?;
@@ -34,7 +36,7 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 			for v in indexed.custody_bit_0_indices.iter().cloned()
 				.chain(indexed.custody_bit_1_indices.iter().cloned())
 			{
-				ret.push((v, indexed.data.target.root));
+				ret.push((v, indexed.data.beacon_block_root, indexed.data.target.epoch));
 			}
 		}
 
@@ -14,9 +14,10 @@
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
 
+use alloc::vec::Vec;
 use crate::types::{AttestationData, Attestation, IndexedAttestation};
 use crate::primitives::{Epoch, H256, Uint, ValidatorIndex, Gwei, Slot};
-use crate::{BeaconExecutive, Config, Error, utils};
+use crate::{BeaconExecutive, Config, Error, utils, consts};
 use core::cmp::{max, min};
 
 impl<'a, C: Config> BeaconExecutive<'a, C> {
@@ -31,7 +32,7 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 		if current_epoch == C::genesis_epoch() {
 			C::genesis_epoch()
 		} else {
-			current_epoch.saturating_sub(1)
+			current_epoch.saturating_sub(1u64)
 		}
 	}
 
@@ -49,19 +50,19 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 		}
 
 		Ok(self.block_roots[
-			(slot % C::slots_per_historical_root()) as usize
+			(slot % C::slots_per_historical_root()).as_usize()
 		])
 	}
 
 	/// Get the randao mix at epoch.
 	pub fn randao_mix(&self, epoch: Epoch) -> H256 {
 		self.randao_mixes[
-			(epoch % C::epochs_per_historical_vector()) as usize
+			(epoch % C::epochs_per_historical_vector()).as_usize()
 		]
 	}
 
 	/// Get active validator indices at epoch.
-	pub fn active_validator_indices(&self, epoch: Uint) -> Vec<ValidatorIndex> {
+	pub fn active_validator_indices(&self, epoch: Epoch) -> Vec<ValidatorIndex> {
 		self.validators
 			.iter()
 			.enumerate()
@@ -71,7 +72,7 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 	}
 
 	/// Get active validator length.
-	pub fn active_validator_len(&self, epoch: Uint) -> usize {
+	pub fn active_validator_len(&self, epoch: Epoch) -> usize {
 		self.active_validator_indices(epoch).len()
 	}
 
@@ -84,6 +85,27 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 		)
 	}
 
+	/// Exit epoch of a validator initiating its exit now: the latest exit
+	/// epoch in the queue, unless its churn is already full.
+	pub fn exit_queue_epoch(&self) -> Epoch {
+		let mut exit_queue_epoch = max(
+			self.validators.iter()
+				.map(|v| v.exit_epoch)
+				.filter(|epoch| *epoch != consts::FAR_FUTURE_EPOCH)
+				.fold(Epoch::default(), max),
+			utils::activation_exit_epoch::<C>(self.current_epoch())
+		);
+		let exit_queue_churn = self.validators.iter()
+			.filter(|v| v.exit_epoch == exit_queue_epoch)
+			.count() as u64;
+
+		if exit_queue_churn >= self.validator_churn_limit() {
+			exit_queue_epoch += 1;
+		}
+
+		exit_queue_epoch
+	}
+
 	/// Get the random seed for epoch.
 	pub fn seed(&self, epoch: Epoch, domain_type: u32) -> H256 {
 		C::hash(&[
@@ -96,7 +118,7 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 	}
 
 	/// Get committee count for epoch.
-	pub fn committee_count_at_slot(&self, slot: Uint) -> Uint {
+	pub fn committee_count_at_slot(&self, slot: Slot) -> Uint {
 		let epoch = utils::epoch_of_slot::<C>(slot);
 		let active_validator_len = self.active_validator_len(epoch);
 		max(
@@ -112,13 +134,13 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 
 	/// Get the crosslink committee.
 	pub fn beacon_committee(
-		&self, slot: Uint, index: Uint,
+		&self, slot: Slot, index: Uint,
 	) -> Result<Vec<ValidatorIndex>, Error> {
 		let epoch = utils::epoch_of_slot::<C>(slot);
 		let committees_per_slot = self.committee_count_at_slot(slot);
 		let indices = self.active_validator_indices(epoch);
 		let seed = self.seed(epoch, C::domain_beacon_attester());
-		let index = (slot % C::slots_per_epoch()) * committees_per_slot + index;
+		let index = (slot % C::slots_per_epoch()).as_u64() * committees_per_slot + index;
 		let count = committees_per_slot * C::slots_per_epoch();
 
 		utils::compute_committee::<C>(&indices, seed, index, count)
@@ -126,10 +148,21 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 
 	/// Get the current beacon proposer index.
 	pub fn beacon_proposer_index(&self) -> Result<ValidatorIndex, Error> {
-		let epoch = self.current_epoch();
+		self.proposer_index_at_slot(self.slot)
+	}
+
+	/// Get the beacon proposer index at a slot of the current or next epoch.
+	/// For the next epoch, the result is a prediction, which may change with
+	/// validator activations and balance updates at the epoch transition.
+	pub fn proposer_index_at_slot(&self, slot: Slot) -> Result<ValidatorIndex, Error> {
+		let epoch = utils::epoch_of_slot::<C>(slot);
+		if epoch < self.current_epoch() || epoch > self.current_epoch() + 1 {
+			return Err(Error::EpochOutOfRange)
+		}
+
 		let seed = C::hash(&[
 			&self.seed(epoch, C::domain_beacon_proposer())[..],
-			&self.slot.to_le_bytes()[..8]
+			&slot.to_le_bytes()[..8]
 		]);
 		let indices = self.active_validator_indices(epoch);
 
@@ -160,10 +193,10 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 	/// Get total balance of validator indices.
 	pub fn total_balance(&self, indices: &[ValidatorIndex]) -> Gwei {
 		max(
-			indices.iter().fold(0, |sum, index| {
+			indices.iter().fold(Gwei::default(), |sum, index| {
 				sum + self.validators[*index as usize].effective_balance
 			}),
-			1
+			Gwei::new(1)
 		)
 	}
 
@@ -173,7 +206,7 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 	}
 
 	/// Get signing domain, given domain type and message epoch.
-	pub fn domain(&self, domain_type: u32, message_epoch: Option<Uint>) -> Uint {
+	pub fn domain(&self, domain_type: u32, message_epoch: Option<Epoch>) -> Uint {
 		let epoch = message_epoch.unwrap_or(self.current_epoch());
 		let fork_version = if epoch < self.fork.epoch {
 			self.fork.previous_version
@@ -181,7 +214,7 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 			self.fork.current_version
 		};
 
-		utils::bls_domain(domain_type, fork_version)
+		crate::compute_domain(domain_type, fork_version)
 	}
 
 	/// Convert an attestation to indexed attestation.
@@ -15,19 +15,23 @@
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::primitives::{ValidatorIndex, Gwei};
-use crate::{BeaconExecutive, Config, Error, utils, consts};
+use crate::{BeaconExecutive, Config, Error, consts};
+use crate::utils::SafeArith;
 use core::cmp::max;
 
 impl<'a, C: Config> BeaconExecutive<'a, C> {
 	/// Increase validator balance.
-	pub fn increase_balance(&mut self, index: ValidatorIndex, delta: Gwei) {
-		self.state.balances[index as usize] += delta;
+	pub fn increase_balance(&mut self, index: ValidatorIndex, delta: Gwei) -> Result<(), Error> {
+		self.state.balances.get_mut(index as usize).ok_or(Error::IndexOutOfRange)?
+			.safe_add_assign(delta.as_u64())?;
+		Ok(())
 	}
 
-	/// Decrease validator balance.
-	pub fn decrease_balance(&mut self, index: ValidatorIndex, delta: Gwei) {
-		self.state.balances[index as usize] =
-			self.balances[index as usize].saturating_sub(delta);
+	/// Decrease validator balance, to no less than zero.
+	pub fn decrease_balance(&mut self, index: ValidatorIndex, delta: Gwei) -> Result<(), Error> {
+		let balance = self.state.balances.get_mut(index as usize).ok_or(Error::IndexOutOfRange)?;
+		*balance = balance.saturating_sub(delta.as_u64());
+		Ok(())
 	}
 
 	/// Initiate validator exit.
@@ -38,22 +42,7 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 			return
 		}
 
-		let exit_epochs = self.validators.iter()
-			.map(|v| v.exit_epoch)
-			.filter(|epoch| *epoch != consts::FAR_FUTURE_EPOCH)
-			.collect::<Vec<_>>();
-		let mut exit_queue_epoch = max(
-			exit_epochs.iter().fold(0, |a, b| max(a, *b)),
-			utils::activation_exit_epoch::<C>(self.current_epoch())
-		);
-		let exit_queue_churn = self.validators.iter()
-			.filter(|v| v.exit_epoch == exit_queue_epoch)
-			.count() as u64;
-
-		if exit_queue_churn >= self.validator_churn_limit() {
-			exit_queue_epoch += 1;
-		}
-
+		let exit_queue_epoch = self.exit_queue_epoch();
 		let validator = &mut self.state.validators[index as usize];
 		validator.exit_epoch = exit_queue_epoch;
 		validator.withdrawable_epoch = validator.exit_epoch +
@@ -77,21 +66,23 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 		let slashed_balance =
 			self.validators[slashed_index as usize].effective_balance;
 		self.state.slashings[
-			(current_epoch % C::epochs_per_slashings_vector()) as usize
-		] += slashed_balance;
-		self.decrease_balance(slashed_index, slashed_balance / C::min_slashing_penalty_quotient());
+			(current_epoch % C::epochs_per_slashings_vector()).as_usize()
+		].safe_add_assign(slashed_balance.as_u64())?;
+		self.decrease_balance(
+			slashed_index, slashed_balance.safe_div(C::min_slashing_penalty_quotient())?
+		)?;
 
 		let proposer_index = self.beacon_proposer_index()?;
 		let whistleblower_index = whistleblower_index.unwrap_or(proposer_index);
 		let whistleblowing_reward =
-			slashed_balance / C::whistleblower_reward_quotient();
+			slashed_balance.safe_div(C::whistleblower_reward_quotient())?;
 		let proposer_reward =
-			whistleblowing_reward / C::proposer_reward_quotient();
+			whistleblowing_reward.safe_div(C::proposer_reward_quotient())?;
 
-		self.increase_balance(proposer_index, proposer_reward);
+		self.increase_balance(proposer_index, proposer_reward)?;
 		self.increase_balance(
-			whistleblower_index, whistleblowing_reward - proposer_reward
-		);
+			whistleblower_index, whistleblowing_reward.safe_sub(proposer_reward)?
+		)?;
 
 		Ok(())
 	}
@@ -14,9 +14,8 @@
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
 
-use crate::types::{IndexedAttestation, AttestationDataAndCustodyBit};
+use crate::types::IndexedAttestation;
 use crate::{BeaconExecutive, Config, BLSConfig};
-use bm_le::tree_root;
 
 impl<'a, C: Config> BeaconExecutive<'a, C> {
 	/// Check if ``indexed_attestation`` has valid indices and signature.
@@ -52,36 +51,7 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 			return false
 		}
 
-		BLS::verify_multiple(
-			&[
-				BLS::aggregate_pubkeys(
-					&bit_0_indices
-						.iter()
-						.map(|i| self.validators[*i as usize].pubkey)
-						.collect::<Vec<_>>()[..]
-				),
-				BLS::aggregate_pubkeys(
-					&bit_1_indices
-						.iter()
-						.map(|i| self.validators[*i as usize].pubkey)
-						.collect::<Vec<_>>()[..]
-				),
-			],
-			&[
-				tree_root::<C::Digest, _>(&AttestationDataAndCustodyBit {
-					data: indexed_attestation.data.clone(),
-					custody_bit: false,
-				}),
-				tree_root::<C::Digest, _>(&AttestationDataAndCustodyBit {
-					data: indexed_attestation.data.clone(),
-					custody_bit: true,
-				}),
-			],
-			&indexed_attestation.signature,
-			self.domain(
-				C::domain_beacon_attester(),
-				Some(indexed_attestation.data.target.epoch)
-			)
-		)
+		self.indexed_attestation_signature_set::<BLS>(indexed_attestation)
+			.verify::<BLS>()
 	}
 }
@@ -0,0 +1,121 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Light client updates.
+//!
+//! Phase 0 has no sync committee, so updates here carry no aggregate
+//! signature over the attested header. Instead, a consumer that already
+//! trusts an attested header (obtained from a trusted node, or checked
+//! against the proposer signature of a known validator) can follow the
+//! finalized header through the finality branch, without the full state.
+
+use alloc::{vec, vec::Vec};
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+use bm_le::tree_root;
+use crate::primitives::H256;
+use crate::types::{BeaconBlock, BeaconBlockHeader, SigningBeaconBlockHeader};
+use crate::{Config, BeaconExecutive, Error, utils};
+use super::proof::STATE_TREE_DEPTH;
+
+/// Index of the finalized checkpoint field in beacon state.
+pub const FINALIZED_CHECKPOINT_FIELD_INDEX: usize = 19;
+/// Depth of the finality branch, from the finalized checkpoint root up to
+/// the state root.
+pub const FINALITY_BRANCH_DEPTH: usize = STATE_TREE_DEPTH + 1;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(deny_unknown_fields))]
+/// Light client update proving a finalized header against an attested
+/// header.
+pub struct LightClientUpdate {
+	/// Header whose post-state the finality branch is against.
+	pub attested_header: BeaconBlockHeader,
+	/// Header of the finalized checkpoint block.
+	pub finalized_header: BeaconBlockHeader,
+	/// Merkle branch, from the finalized checkpoint root up to the attested
+	/// header state root.
+	pub finality_branch: Vec<H256>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(deny_unknown_fields))]
+/// Light client update of the latest header only, without finality.
+pub struct LightClientOptimisticUpdate {
+	/// Latest attested header.
+	pub attested_header: BeaconBlockHeader,
+}
+
+/// Header of a block.
+pub fn block_header<C: Config>(block: &BeaconBlock<C>) -> BeaconBlockHeader {
+	BeaconBlockHeader {
+		slot: block.slot,
+		parent_root: block.parent_root,
+		state_root: block.state_root,
+		body_root: tree_root::<C::Digest, _>(&block.body),
+		signature: block.signature.clone(),
+	}
+}
+
+/// Block root of a header, as referenced by checkpoints.
+pub fn header_root<C: Config>(header: &BeaconBlockHeader) -> H256 {
+	tree_root::<C::Digest, _>(&SigningBeaconBlockHeader::from(header.clone()))
+}
+
+impl LightClientUpdate {
+	/// Index of the finalized checkpoint root leaf, at the finality branch
+	/// depth.
+	pub fn leaf_index() -> u64 {
+		((FINALIZED_CHECKPOINT_FIELD_INDEX as u64) << 1) | 1
+	}
+
+	/// Verify that the finalized header is the finalized checkpoint of the
+	/// attested header post-state. This does not verify the attested header
+	/// itself.
+	pub fn verify<C: Config>(&self) -> bool {
+		self.finalized_header.slot <= self.attested_header.slot &&
+			utils::is_valid_merkle_branch::<C>(
+				header_root::<C>(&self.finalized_header),
+				&self.finality_branch,
+				FINALITY_BRANCH_DEPTH as u64,
+				Self::leaf_index(),
+				self.attested_header.state_root,
+			)
+	}
+}
+
+impl<'a, C: Config> BeaconExecutive<'a, C> {
+	/// Produce a light client update, with this state being the post-state
+	/// of `attested_header`, and `finalized_header` the header of its
+	/// finalized checkpoint block.
+	pub fn light_client_update(
+		&self,
+		attested_header: BeaconBlockHeader,
+		finalized_header: BeaconBlockHeader,
+	) -> Result<LightClientUpdate, Error> {
+		if tree_root::<C::Digest, _>(&*self.state) != attested_header.state_root {
+			return Err(Error::LightClientAttestedStateMismatch)
+		}
+		if header_root::<C>(&finalized_header) != self.finalized_checkpoint.root {
+			return Err(Error::LightClientFinalizedHeaderMismatch)
+		}
+
+		let mut finality_branch = vec![tree_root::<C::Digest, _>(&self.finalized_checkpoint.epoch)];
+		finality_branch.append(&mut self.state_field_branch(FINALIZED_CHECKPOINT_FIELD_INDEX));
+
+		Ok(LightClientUpdate { attested_header, finalized_header, finality_branch })
+	}
+}
@@ -18,9 +18,31 @@ mod helpers;
 mod transition;
 mod choice;
 mod assignment;
+mod proof;
+mod light_client;
+mod weak_subjectivity;
+#[cfg(feature = "altair")]
+mod sync_committee;
+
+pub use self::assignment::{CommitteeAssignment, ProposerAssignment};
+pub use self::proof::{
+	ValidatorProof, BalanceDeltas, BalanceDeltasCommitment, BalanceDeltaProof,
+};
+pub use self::light_client::{
+	LightClientUpdate, LightClientOptimisticUpdate, FINALIZED_CHECKPOINT_FIELD_INDEX,
+	FINALITY_BRANCH_DEPTH, block_header, header_root,
+};
+#[cfg(feature = "altair")]
+pub use self::sync_committee::{SyncCommitteeAssignment, sync_committee_period};
+pub use self::transition::{
+	SignatureSet, VerifiedSignatures, verify_signature_sets, RewardBreakdown,
+	RewardProjection, PARTICIPATION_BASIS_POINTS,
+	RewardScheme, DefaultRewardScheme, ParticipationFlagRewardScheme, ValidatorLifecycle,
+	ForkVersions, historical_block_signature_sets, verify_historical_blocks,
+};
 
-pub use self::assignment::CommitteeAssignment;
-
+use alloc::vec::Vec;
+use alloc::boxed::Box;
 use core::ops::Deref;
 #[cfg(feature = "serde")]
 use serde::{Serialize, Deserialize};
@@ -28,11 +50,13 @@ use ssz::{Codec, Encode, Decode};
 use bm_le::{IntoTree, FromTree, MaxVec};
 use vecarray::VecArray;
 use crate::{Config, Error};
-use crate::primitives::{H256, Uint, ValidatorIndex, Gwei};
+use crate::primitives::{H256, Uint, ValidatorIndex, Gwei, Slot};
 use crate::types::{
 	BeaconBlockHeader, Validator, Eth1Data, PendingAttestation, Checkpoint, Fork,
 };
-use crate::components::{JustifierRegistry, Registry, Checkpoint as CheckpointT};
+use crate::components::{
+	JustifierRegistry, Registry, ParticipationBits, Checkpoint as CheckpointT,
+};
 use crate::consts;
 
 #[derive(PartialEq, Eq, Debug)]
@@ -86,18 +110,25 @@ macro_rules! unslashed_validators {
 	})
 }
 
+macro_rules! unslashed_participation {
+	( $attestations:tt, $epoch:expr, $self:expr ) => ({
+		let attestations = $self.$attestations($epoch)?;
+		$self.unslashed_attesting_participation(&attestations)
+	})
+}
+
 impl<'a, C: Config> JustifierRegistry for BeaconExecutive<'a, C> {
 	type Error = Error;
 	type Checkpoint = Checkpoint;
 
-	fn total_active_balance(&self) -> u64 {
+	fn total_active_balance(&self) -> Gwei {
 		self.total_active_balance()
 	}
 
 	fn attesting_target_balance(
 		&self,
 		checkpoint: &Self::Checkpoint
-	) -> Result<u64, Self::Error> {
+	) -> Result<Gwei, Self::Error> {
 		self.attesting_balance(&self.matching_target_attestations(checkpoint.epoch)?)
 	}
 }
@@ -128,10 +159,21 @@ impl<'a, C: Config> Registry for BeaconExecutive<'a, C> {
 			})))
 	}
 
+	fn min_inclusion_delay_attestations(
+		&self,
+		source_checkpoint: &Self::Checkpoint,
+	) -> Result<Vec<(u64, Self::Attestation)>, Self::Error> {
+		let matching_source_attestations =
+			self.matching_source_attestations(source_checkpoint.epoch())?;
+		Ok(self.min_inclusion_delay_attestations(&matching_source_attestations)?
+		   .into_iter()
+		   .collect())
+	}
+
 	fn unslashed_attesting_balance(
 		&self,
 		source_checkpoint: &Self::Checkpoint,
-	) -> Result<u64, Self::Error> {
+	) -> Result<Gwei, Self::Error> {
 		unslashed_balance!(matching_source_attestations, source_checkpoint.epoch(), self)
 	}
 
@@ -142,10 +184,17 @@ impl<'a, C: Config> Registry for BeaconExecutive<'a, C> {
 		unslashed_validators!(matching_source_attestations, source_checkpoint.epoch(), self)
 	}
 
+	fn unslashed_attesting_participation(
+		&self,
+		source_checkpoint: &Self::Checkpoint,
+	) -> Result<ParticipationBits, Self::Error> {
+		unslashed_participation!(matching_source_attestations, source_checkpoint.epoch(), self)
+	}
+
 	fn unslashed_attesting_target_balance(
 		&self,
 		source_checkpoint: &Self::Checkpoint,
-	) -> Result<u64, Self::Error> {
+	) -> Result<Gwei, Self::Error> {
 		unslashed_balance!(matching_target_attestations, source_checkpoint.epoch(), self)
 	}
 
@@ -156,10 +205,17 @@ impl<'a, C: Config> Registry for BeaconExecutive<'a, C> {
 		unslashed_validators!(matching_target_attestations, source_checkpoint.epoch(), self)
 	}
 
+	fn unslashed_attesting_target_participation(
+		&self,
+		source_checkpoint: &Self::Checkpoint,
+	) -> Result<ParticipationBits, Self::Error> {
+		unslashed_participation!(matching_target_attestations, source_checkpoint.epoch(), self)
+	}
+
 	fn unslashed_attesting_matching_head_balance(
 		&self,
 		source_checkpoint: &Self::Checkpoint,
-	) -> Result<u64, Self::Error> {
+	) -> Result<Gwei, Self::Error> {
 		unslashed_balance!(matching_head_attestations, source_checkpoint.epoch(), self)
 	}
 
@@ -170,17 +226,24 @@ impl<'a, C: Config> Registry for BeaconExecutive<'a, C> {
 		unslashed_validators!(matching_head_attestations, source_checkpoint.epoch(), self)
 	}
 
+	fn unslashed_attesting_matching_head_participation(
+		&self,
+		source_checkpoint: &Self::Checkpoint,
+	) -> Result<ParticipationBits, Self::Error> {
+		unslashed_participation!(matching_head_attestations, source_checkpoint.epoch(), self)
+	}
+
 	fn balance(
 		&self,
 		index: u64,
-	) -> Result<u64, Self::Error> {
-		self.balances.get(index as usize).cloned().ok_or(Error::IndexOutOfRange)
+	) -> Result<Gwei, Self::Error> {
+		self.balances.get(index as usize).cloned().map(Gwei::from).ok_or(Error::IndexOutOfRange)
 	}
 
 	fn effective_balance(
 		&self,
 		index: u64,
-	) -> Result<u64, Self::Error> {
+	) -> Result<Gwei, Self::Error> {
 		self.validators.get(index as usize).map(|v| v.effective_balance)
 			.ok_or(Error::IndexOutOfRange)
 	}
@@ -188,16 +251,16 @@ impl<'a, C: Config> Registry for BeaconExecutive<'a, C> {
 	fn increase_balance(
 		&mut self,
 		index: u64,
-		value: u64,
-	) {
+		value: Gwei,
+	) -> Result<(), Self::Error> {
 		self.increase_balance(index, value)
 	}
 
 	fn decrease_balance(
 		&mut self,
 		index: u64,
-		value: u64,
-	) {
+		value: Gwei,
+	) -> Result<(), Self::Error> {
 		self.decrease_balance(index, value)
 	}
 
@@ -215,11 +278,11 @@ impl<'a, C: Config> Registry for BeaconExecutive<'a, C> {
 pub struct BeaconState<C: Config> {
 	// == Versioning ==
 	/// Genesis time as Unix timestamp.
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
 	pub genesis_time: Uint,
 	/// Current slot.
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
-	pub slot: Uint,
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub slot: Slot,
 	/// Fork version.
 	pub fork: Fork,
 
@@ -239,14 +302,16 @@ pub struct BeaconState<C: Config> {
 	/// Votes on eth1 data.
 	pub eth1_data_votes: MaxVec<Eth1Data, C::SlotsPerEth1VotingPeriod>,
 	/// Eth1 data deposit index.
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
 	pub eth1_deposit_index: Uint,
 
 	// == Registry ==
 	/// Validator registry.
 	pub validators: MaxVec<Validator, C::ValidatorRegistryLimit>,
 	#[bm(compact)]
-	/// Balance of the validators.
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint_list"))]
+	/// Balance of the validators, in Gwei. Kept as plain integers for the
+	/// packed list encoding.
 	pub balances: MaxVec<Uint, C::ValidatorRegistryLimit>,
 
 	// == Shuffling ==
@@ -255,7 +320,9 @@ pub struct BeaconState<C: Config> {
 
 	// == Slashings ==
 	#[bm(compact)]
-	/// Past slashings.
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint_vector"))]
+	/// Past slashings, in Gwei. Kept as plain integers for the packed vector
+	/// encoding.
 	pub slashings: VecArray<Uint, C::EpochsPerSlashingsVector>,
 
 	// == Attestations ==
@@ -0,0 +1,229 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use alloc::{vec, vec::Vec};
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+use bm_le::{tree_root, CompactRef};
+use crate::primitives::{H256, Uint, Epoch, Gwei, ValidatorIndex};
+use crate::types::Validator;
+use crate::{Config, BeaconExecutive, Error, utils};
+
+/// Depth of the beacon state container tree.
+pub const STATE_TREE_DEPTH: usize = 5;
+/// Index of the validator registry field in beacon state.
+pub const VALIDATORS_FIELD_INDEX: usize = 10;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(deny_unknown_fields))]
+/// Merkle proof of a validator record against a state root.
+pub struct ValidatorProof {
+	/// Validator index.
+	pub index: ValidatorIndex,
+	/// Validator record.
+	pub validator: Validator,
+	/// State root the proof is against.
+	pub state_root: H256,
+	/// Merkle branch, from the validator record up to the state root.
+	pub branch: Vec<H256>,
+}
+
+impl ValidatorProof {
+	/// Depth of the proof.
+	pub fn depth<C: Config>() -> usize {
+		STATE_TREE_DEPTH + 1 + registry_depth::<C>()
+	}
+
+	/// Index of the validator record leaf, at the proof depth.
+	pub fn leaf_index<C: Config>(index: ValidatorIndex) -> Uint {
+		((VALIDATORS_FIELD_INDEX as u64) << (1 + registry_depth::<C>())) | index
+	}
+
+	/// Verify the proof.
+	pub fn verify<C: Config>(&self) -> bool {
+		utils::is_valid_merkle_branch::<C>(
+			tree_root::<C::Digest, _>(&self.validator),
+			&self.branch,
+			Self::depth::<C>() as u64,
+			Self::leaf_index::<C>(self.index),
+			self.state_root,
+		)
+	}
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(deny_unknown_fields))]
+/// Per-validator balance deltas of an epoch.
+pub struct BalanceDeltas {
+	/// Epoch the deltas are for.
+	pub epoch: Epoch,
+	/// Rewards of each validator.
+	pub rewards: Vec<Gwei>,
+	/// Penalties of each validator.
+	pub penalties: Vec<Gwei>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(deny_unknown_fields))]
+/// Commitment over balance deltas of an epoch.
+pub struct BalanceDeltasCommitment {
+	/// Epoch the deltas are for.
+	pub epoch: Epoch,
+	/// Number of validators.
+	pub count: Uint,
+	/// Merkle root of the delta list.
+	pub root: H256,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(deny_unknown_fields))]
+/// Merkle proof of a single validator balance delta.
+pub struct BalanceDeltaProof {
+	/// Validator index.
+	pub index: ValidatorIndex,
+	/// Reward of the validator.
+	pub reward: Gwei,
+	/// Penalty of the validator.
+	pub penalty: Gwei,
+	/// Merkle branch, from the delta up to the commitment root.
+	pub branch: Vec<H256>,
+}
+
+impl BalanceDeltas {
+	fn leaves<C: Config>(&self) -> Vec<H256> {
+		self.rewards.iter().zip(self.penalties.iter())
+			.map(|(reward, penalty)| balance_delta_leaf::<C>(*reward, *penalty))
+			.collect()
+	}
+
+	/// Commitment over all deltas.
+	pub fn commitment<C: Config>(&self) -> BalanceDeltasCommitment {
+		let count = self.rewards.len() as u64;
+		BalanceDeltasCommitment {
+			epoch: self.epoch,
+			count,
+			root: utils::mix_in_length::<C>(
+				utils::merkle_root::<C>(&self.leaves::<C>(), balance_deltas_depth(count)),
+				count,
+			),
+		}
+	}
+
+	/// Proof of the delta of a single validator.
+	pub fn proof<C: Config>(&self, index: ValidatorIndex) -> Result<BalanceDeltaProof, Error> {
+		let count = self.rewards.len() as u64;
+		if index >= count {
+			return Err(Error::IndexOutOfRange)
+		}
+
+		let mut branch = utils::merkle_branch::<C>(
+			&self.leaves::<C>(), balance_deltas_depth(count), index as usize
+		);
+		branch.push(utils::to_bytes(count));
+
+		Ok(BalanceDeltaProof {
+			index,
+			reward: self.rewards[index as usize],
+			penalty: self.penalties[index as usize],
+			branch,
+		})
+	}
+}
+
+impl BalanceDeltaProof {
+	/// Verify the proof against a commitment.
+	pub fn verify<C: Config>(&self, commitment: &BalanceDeltasCommitment) -> bool {
+		utils::is_valid_merkle_branch::<C>(
+			balance_delta_leaf::<C>(self.reward, self.penalty),
+			&self.branch,
+			balance_deltas_depth(commitment.count) as u64 + 1,
+			self.index,
+			commitment.root,
+		)
+	}
+}
+
+fn balance_delta_leaf<C: Config>(reward: Gwei, penalty: Gwei) -> H256 {
+	C::hash(&[
+		utils::to_bytes(reward.as_u64()).as_ref(),
+		utils::to_bytes(penalty.as_u64()).as_ref(),
+	])
+}
+
+fn balance_deltas_depth(count: Uint) -> usize {
+	count.next_power_of_two().trailing_zeros() as usize
+}
+
+fn registry_depth<C: Config>() -> usize {
+	C::validator_registry_limit().trailing_zeros() as usize
+}
+
+impl<'a, C: Config> BeaconExecutive<'a, C> {
+	/// Roots of all beacon state fields, in order.
+	pub fn state_field_roots(&self) -> Vec<H256> {
+		vec![
+			tree_root::<C::Digest, _>(&self.genesis_time),
+			tree_root::<C::Digest, _>(&self.slot),
+			tree_root::<C::Digest, _>(&self.fork),
+			tree_root::<C::Digest, _>(&self.latest_block_header),
+			tree_root::<C::Digest, _>(&self.block_roots),
+			tree_root::<C::Digest, _>(&self.state_roots),
+			tree_root::<C::Digest, _>(&self.historical_roots),
+			tree_root::<C::Digest, _>(&self.eth1_data),
+			tree_root::<C::Digest, _>(&self.eth1_data_votes),
+			tree_root::<C::Digest, _>(&self.eth1_deposit_index),
+			tree_root::<C::Digest, _>(&self.validators),
+			tree_root::<C::Digest, _>(&CompactRef(&self.balances)),
+			tree_root::<C::Digest, _>(&self.randao_mixes),
+			tree_root::<C::Digest, _>(&CompactRef(&self.slashings)),
+			tree_root::<C::Digest, _>(&self.previous_epoch_attestations),
+			tree_root::<C::Digest, _>(&self.current_epoch_attestations),
+			tree_root::<C::Digest, _>(&CompactRef(&self.justification_bits)),
+			tree_root::<C::Digest, _>(&self.previous_justified_checkpoint),
+			tree_root::<C::Digest, _>(&self.current_justified_checkpoint),
+			tree_root::<C::Digest, _>(&self.finalized_checkpoint),
+		]
+	}
+
+	/// Merkle branch of a state field against the state root.
+	pub fn state_field_branch(&self, field_index: usize) -> Vec<H256> {
+		utils::merkle_branch::<C>(&self.state_field_roots(), STATE_TREE_DEPTH, field_index)
+	}
+
+	/// Generate a merkle proof of a validator record against the current
+	/// state root.
+	pub fn validator_proof(&self, index: ValidatorIndex) -> Result<ValidatorProof, Error> {
+		let validator = self.validators.get(index as usize)
+			.cloned().ok_or(Error::IndexOutOfRange)?;
+
+		let validator_roots = self.validators.iter()
+			.map(|v| tree_root::<C::Digest, _>(v))
+			.collect::<Vec<_>>();
+
+		let mut branch = utils::merkle_branch::<C>(
+			&validator_roots, registry_depth::<C>(), index as usize
+		);
+		branch.push(utils::to_bytes(self.validators.len() as u64));
+		branch.append(&mut self.state_field_branch(VALIDATORS_FIELD_INDEX));
+
+		Ok(ValidatorProof {
+			index,
+			validator,
+			state_root: tree_root::<C::Digest, _>(&*self.state),
+			branch,
+		})
+	}
+}
@@ -0,0 +1,110 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+//! Sync committee selection and duties of the Altair fork.
+
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use vecarray::VecArray;
+use crate::primitives::{Epoch, ValidatorIndex};
+use crate::types::SyncCommittee;
+use crate::{AltairConfig, BeaconExecutive, BLSConfig, Error, utils};
+
+/// Sync committee assignment of a validator.
+pub struct SyncCommitteeAssignment {
+	/// Index of the validator.
+	pub validator_index: ValidatorIndex,
+	/// Positions of the validator in the committee. A validator can be
+	/// selected more than once.
+	pub positions: Vec<u64>,
+}
+
+/// Sync committee period of an epoch.
+pub fn sync_committee_period<C: AltairConfig>(epoch: Epoch) -> u64 {
+	(epoch / C::epochs_per_sync_committee_period()).as_u64()
+}
+
+impl<'a, C: AltairConfig> BeaconExecutive<'a, C> {
+	/// Indices of the members of the next sync committee, selected from the
+	/// validators active at the next epoch with a probability proportional
+	/// to their effective balance.
+	pub fn next_sync_committee_indices(&self) -> Result<Vec<ValidatorIndex>, Error> {
+		let epoch = self.current_epoch() + 1;
+		let indices = self.active_validator_indices(epoch);
+		if indices.is_empty() {
+			return Err(Error::IndexOutOfRange)
+		}
+		let seed = self.seed(epoch, C::domain_sync_committee());
+
+		let mut members = Vec::with_capacity(C::sync_committee_size() as usize);
+		let mut i = 0;
+		while (members.len() as u64) < C::sync_committee_size() {
+			let candidate_index = indices[
+				utils::shuffled_index::<C>(
+					i % indices.len() as u64,
+					indices.len() as u64,
+					seed
+				)? as usize
+			];
+			let random_byte = C::hash(&[
+				&seed[..],
+				&utils::to_bytes(i / 32)[..8],
+			])[(i % 32) as usize];
+			let effective_balance = self.validators[candidate_index as usize].effective_balance;
+			if effective_balance * u8::max_value() as u64 >=
+				C::max_effective_balance() * random_byte as u64
+			{
+				members.push(candidate_index);
+			}
+
+			i += 1;
+		}
+		Ok(members)
+	}
+
+	/// Next sync committee.
+	pub fn next_sync_committee<BLS: BLSConfig>(&self) -> Result<SyncCommittee<C>, Error> {
+		let pubkeys = self.next_sync_committee_indices()?.into_iter()
+			.map(|index| self.validators[index as usize].pubkey)
+			.collect::<Vec<_>>();
+		let aggregate_pubkey = BLS::aggregate_pubkeys(&pubkeys);
+
+		Ok(SyncCommittee {
+			pubkeys: VecArray::try_from(pubkeys).map_err(|_| Error::IndexOutOfRange)?,
+			aggregate_pubkey,
+		})
+	}
+
+	/// Sync committee assignment of a validator, or `None` if the validator
+	/// is not in the committee.
+	pub fn sync_committee_assignment(
+		&self,
+		committee: &SyncCommittee<C>,
+		validator_index: ValidatorIndex,
+	) -> Option<SyncCommitteeAssignment> {
+		let pubkey = self.validator_pubkey(validator_index)?;
+		let positions = committee.pubkeys.iter()
+			.enumerate()
+			.filter(|(_, member)| **member == pubkey)
+			.map(|(position, _)| position as u64)
+			.collect::<Vec<_>>();
+
+		if positions.is_empty() {
+			None
+		} else {
+			Some(SyncCommitteeAssignment { validator_index, positions })
+		}
+	}
+}
@@ -17,7 +17,16 @@
 mod per_block;
 mod per_epoch;
 
-use crate::primitives::{Uint, H256};
+pub use self::per_block::{
+	SignatureSet, VerifiedSignatures, ForkVersions, verify_signature_sets,
+	historical_block_signature_sets, verify_historical_blocks,
+};
+pub use self::per_epoch::{
+	RewardBreakdown, RewardProjection, PARTICIPATION_BASIS_POINTS,
+	RewardScheme, DefaultRewardScheme, ParticipationFlagRewardScheme, ValidatorLifecycle,
+};
+
+use crate::primitives::{Slot, H256};
 use crate::types::{Block, SigningBeaconBlockHeader};
 use crate::{Error, Config, BeaconExecutive, BLSConfig};
 use bm_le::tree_root;
@@ -27,9 +36,37 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 	pub fn state_transition<B: Block<Config=C>, BLS: BLSConfig>(
 		&mut self,
 		block: &B,
+	) -> Result<(), Error> {
+		enter_span!("state_transition", slot = block.slot().as_u64());
+		self.apply_block::<_, BLS>(block)?;
+
+		if !(block.state_root() == &tree_root::<C::Digest, _>(self.state)) {
+			return Err(Error::BlockStateRootInvalid)
+		}
+
+		Ok(())
+	}
+
+	/// Process slots up to the block slot and the block, without checking
+	/// the resulting state root.
+	pub fn apply_block<B: Block<Config=C>, BLS: BLSConfig>(
+		&mut self,
+		block: &B,
 	) -> Result<(), Error> {
 		self.process_slots(block.slot())?;
-		self.process_block::<_, BLS>(block)?;
+		self.process_block::<_, BLS>(block)
+	}
+
+	/// Execute state transition of a block whose signatures were checked
+	/// with `verify_block_signatures` against this state advanced to the
+	/// block slot.
+	pub fn state_transition_verified<B: Block<Config=C>, BLS: BLSConfig>(
+		&mut self,
+		block: &B,
+		verified: VerifiedSignatures,
+	) -> Result<(), Error> {
+		enter_span!("state_transition", slot = block.slot().as_u64(), verified = true);
+		self.apply_verified_block::<_, BLS>(block, verified)?;
 
 		if !(block.state_root() == &tree_root::<C::Digest, _>(self.state)) {
 			return Err(Error::BlockStateRootInvalid)
@@ -38,12 +75,24 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 		Ok(())
 	}
 
+	/// Process slots up to the block slot and a block with signatures
+	/// verified ahead of time, without checking the resulting state root.
+	pub fn apply_verified_block<B: Block<Config=C>, BLS: BLSConfig>(
+		&mut self,
+		block: &B,
+		verified: VerifiedSignatures,
+	) -> Result<(), Error> {
+		self.process_slots(block.slot())?;
+		self.process_verified_block::<_, BLS>(block, verified)
+	}
+
 	/// Process slots, process epoch if at epoch boundary.
-	pub fn process_slots(&mut self, slot: Uint) -> Result<(), Error> {
+	pub fn process_slots(&mut self, slot: Slot) -> Result<(), Error> {
 		if self.slot > slot {
 			return Err(Error::SlotOutOfRange)
 		}
 
+		enter_span!("process_slots", from = self.slot.as_u64(), to = slot.as_u64());
 		while self.slot < slot {
 			self.process_slot();
 			if (self.slot + 1) % C::slots_per_epoch() == 0 {
@@ -59,7 +108,7 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 	pub fn process_slot(&mut self) {
 		let previous_state_root = tree_root::<C::Digest, _>(self.state);
 		self.state.state_roots[
-			(self.state.slot % C::slots_per_historical_root()) as usize
+			(self.state.slot % C::slots_per_historical_root()).as_usize()
 		] = previous_state_root;
 
 		if self.latest_block_header.state_root == H256::default() {
@@ -70,7 +119,7 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 			&SigningBeaconBlockHeader::from(self.latest_block_header.clone())
 		);
 		self.state.block_roots[
-			(self.state.slot % C::slots_per_historical_root()) as usize
+			(self.state.slot % C::slots_per_historical_root()).as_usize()
 		] = previous_block_root;
 	}
 }
@@ -19,8 +19,13 @@ mod randao;
 mod eth1;
 mod operations;
 
+pub use self::operations::{
+	SignatureSet, VerifiedSignatures, ForkVersions, verify_signature_sets,
+	historical_block_signature_sets, verify_historical_blocks,
+};
+
 use crate::types::{Block, UnsealedBeaconBlock};
-use crate::{Config, BLSConfig, BeaconExecutive, Error};
+use crate::{Config, BLSConfig, BLSNoVerification, BeaconExecutive, Error};
 
 impl<'a, C: Config> BeaconExecutive<'a, C> {
 	/// Process a block, assuming we are at given slot.
@@ -30,11 +35,31 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 	) -> Result<(), Error> where
 		UnsealedBeaconBlock<C>: From<&'b B>,
 	{
+		enter_span!("process_block", slot = block.slot().as_u64());
 		self.process_block_header::<_, BLS>(block)?;
 		self.process_randao::<BLS>(block.body())?;
 		self.process_eth1_data(block.body());
 		self.process_operations::<BLS>(block.body())?;
 
+		Ok(())
+	}
+	/// Process a block whose signatures were checked with
+	/// `verify_block_signatures` against this state. Only operation
+	/// signatures that could not be checked ahead of time are verified
+	/// with `BLS`.
+	pub fn process_verified_block<'b, B: Block<Config=C>, BLS: BLSConfig>(
+		&mut self,
+		block: &'b B,
+		verified: VerifiedSignatures,
+	) -> Result<(), Error> where
+		UnsealedBeaconBlock<C>: From<&'b B>,
+	{
+		enter_span!("process_block", slot = block.slot().as_u64(), verified = true);
+		self.process_block_header::<_, BLSNoVerification>(block)?;
+		self.process_randao::<BLSNoVerification>(block.body())?;
+		self.process_eth1_data(block.body());
+		self.process_operations_with::<BLS>(block.body(), verified)?;
+
 		Ok(())
 	}
 }
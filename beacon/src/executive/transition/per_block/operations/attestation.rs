@@ -14,12 +14,26 @@
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
 
-use crate::types::{Attestation, PendingAttestation};
+use crate::types::{Attestation, IndexedAttestation, PendingAttestation};
 use crate::{Config, BeaconExecutive, Error, BLSConfig};
 
 impl<'a, C: Config> BeaconExecutive<'a, C> {
 	/// Push a new `Attestation` to the state.
 	pub fn process_attestation<BLS: BLSConfig>(&mut self, attestation: Attestation<C>) -> Result<(), Error> {
+		self.process_attestation_with(attestation, |executive, indexed_attestation| {
+			executive.is_valid_indexed_attestation::<BLS>(indexed_attestation)
+		})
+	}
+
+	/// Push a new `Attestation` to the state, with a custom indexed
+	/// attestation validity check.
+	pub fn process_attestation_with<F>(
+		&mut self,
+		attestation: Attestation<C>,
+		is_valid_indexed_attestation: F,
+	) -> Result<(), Error> where
+		F: FnOnce(&Self, &IndexedAttestation<C>) -> bool,
+	{
 		let data = attestation.data.clone();
 		if !(data.index < self.committee_count_at_slot(data.slot)) {
 			return Err(Error::AttestationIncorrectCrosslinkData)
@@ -41,7 +55,7 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 		let pending_attestation = PendingAttestation {
 			data: data.clone(),
 			aggregation_bits: attestation.aggregation_bits.clone(),
-			inclusion_delay: self.slot - data.slot,
+			inclusion_delay: (self.slot - data.slot).as_u64(),
 			proposer_index: self.beacon_proposer_index()?,
 		};
 
@@ -56,7 +70,7 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 				false
 			};
 
-		if !self.is_valid_indexed_attestation::<BLS>(&self.indexed_attestation(attestation)?) {
+		if !is_valid_indexed_attestation(self, &self.indexed_attestation(attestation)?) {
 			return Err(Error::AttestationInvalidSignature)
 		}
 
@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
 
+use alloc::vec::Vec;
 use crate::types::AttesterSlashing;
 use crate::{Config, BeaconExecutive, Error, BLSConfig};
 
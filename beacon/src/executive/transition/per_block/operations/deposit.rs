@@ -14,6 +14,7 @@
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
 
+use alloc::vec::Vec;
 use crate::types::{Deposit, Validator, SigningDepositData};
 use crate::{Config, BeaconExecutive, Error, BLSConfig, utils, consts};
 use bm_le::tree_root;
@@ -22,6 +23,24 @@ use core::cmp::min;
 impl<'a, C: Config> BeaconExecutive<'a, C> {
 	/// Push a new `Deposit` to the state.
 	pub fn process_deposit<BLS: BLSConfig>(&mut self, deposit: Deposit) -> Result<(), Error> {
+		self.process_deposit_with(deposit, |executive, deposit| {
+			BLS::verify(
+				&deposit.data.pubkey,
+				&tree_root::<C::Digest, _>(&SigningDepositData::from(deposit.data.clone())),
+				&deposit.data.signature,
+				executive.domain(C::domain_deposit(), None)
+			)
+		})
+	}
+
+	/// Push a new `Deposit` to the state, with a custom signature check.
+	pub fn process_deposit_with<F>(
+		&mut self,
+		deposit: Deposit,
+		is_valid_signature: F,
+	) -> Result<(), Error> where
+		F: FnOnce(&Self, &Deposit) -> bool,
+	{
 		if !utils::is_valid_merkle_branch::<C>(
 			tree_root::<C::Digest, _>(&deposit.data),
 			&deposit.proof,
@@ -43,12 +62,7 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 			// Verify the deposit signature (proof of possession). Invalid
 			// signatures are allowed by the deposit contract, and hence
 			// included on-chain, but must not be processed.
-			if !BLS::verify(
-				&pubkey,
-				&tree_root::<C::Digest, _>(&SigningDepositData::from(deposit.data.clone())),
-				&deposit.data.signature,
-				self.domain(C::domain_deposit(), None)
-			) {
+			if !is_valid_signature(self, &deposit) {
 				return Ok(())
 			}
 
@@ -66,11 +80,11 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 				slashed: false,
 			};
 			self.state.validators.push(validator);
-			self.state.balances.push(amount);
+			self.state.balances.push(amount.as_u64());
 		} else {
 			let index = validator_pubkeys.iter().position(|v| v == &pubkey)
 				.expect("Registry contains the public key");
-			self.increase_balance(index as u64, amount);
+			self.increase_balance(index as u64, amount)?;
 		}
 
 		Ok(())
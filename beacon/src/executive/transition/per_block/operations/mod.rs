@@ -19,9 +19,15 @@ mod attester_slashing;
 mod attestation;
 mod deposit;
 mod voluntary_exit;
+mod signatures;
+
+pub use self::signatures::{
+	SignatureSet, VerifiedSignatures, ForkVersions, verify_signature_sets,
+	historical_block_signature_sets, verify_historical_blocks,
+};
 
 use crate::types::BeaconBlockBody;
-use crate::{Config, BLSConfig, BeaconExecutive, Error};
+use crate::{Config, BLSConfig, BLSNoVerification, BeaconExecutive, Error};
 use core::cmp::min;
 
 impl<'a, C: Config> BeaconExecutive<'a, C> {
@@ -30,6 +36,43 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 		&mut self,
 		body: &BeaconBlockBody<C>
 	) -> Result<(), Error> {
+		self.check_operation_counts(body)?;
+		let verified = self.verify_operation_signatures::<BLS>(body);
+		self.process_operations_with::<BLS>(body, verified)
+	}
+
+	/// Check that no block operation list exceeds its maximum length. This
+	/// must happen before any signature of the block is verified.
+	pub fn check_operation_counts(&self, body: &BeaconBlockBody<C>) -> Result<(), Error> {
+		if body.proposer_slashings.len() > C::max_proposer_slashings() as usize {
+			return Err(Error::TooManyProposerSlashings)
+		}
+		if body.attester_slashings.len() > C::max_attester_slashings() as usize {
+			return Err(Error::TooManyAttesterSlashings)
+		}
+		if body.attestations.len() > C::max_attestations() as usize {
+			return Err(Error::TooManyAttestations)
+		}
+		if body.deposits.len() > C::max_deposits() as usize {
+			return Err(Error::TooManyDeposits)
+		}
+		if body.voluntary_exits.len() > C::max_voluntary_exits() as usize {
+			return Err(Error::TooManyVoluntaryExits)
+		}
+
+		Ok(())
+	}
+
+	/// Process block operations, with signatures verified ahead of time.
+	/// Signatures missing from `verified`, and slashing signatures unless
+	/// `verified.slashings` is set, are checked with `BLS`.
+	pub fn process_operations_with<BLS: BLSConfig>(
+		&mut self,
+		body: &BeaconBlockBody<C>,
+		verified: VerifiedSignatures,
+	) -> Result<(), Error> {
+		self.check_operation_counts(body)?;
+
 		// Verify that outstanding deposits are processed up to the maximum
 		// number of deposits
 		if body.deposits.len() as u64 != min(
@@ -40,39 +83,68 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 			return Err(Error::TooManyDeposits)
 		}
 
-		if body.proposer_slashings.len() > C::max_proposer_slashings() as usize {
-			return Err(Error::TooManyProposerSlashings)
-		}
-		for slashing in body.proposer_slashings.iter() {
-			self.process_proposer_slashing::<BLS>(slashing.clone())?;
+		{
+			enter_span!("proposer_slashings", count = body.proposer_slashings.len());
+			for slashing in body.proposer_slashings.iter() {
+				if verified.slashings {
+					self.process_proposer_slashing::<BLSNoVerification>(slashing.clone())?;
+				} else {
+					self.process_proposer_slashing::<BLS>(slashing.clone())?;
+				}
+			}
 		}
 
-		if body.attester_slashings.len() > C::max_attester_slashings() as usize {
-			return Err(Error::TooManyAttesterSlashings)
-		}
-		for slashing in body.attester_slashings.iter() {
-			self.process_attester_slashing::<BLS>(slashing.clone())?;
+		{
+			enter_span!("attester_slashings", count = body.attester_slashings.len());
+			for slashing in body.attester_slashings.iter() {
+				if verified.slashings {
+					self.process_attester_slashing::<BLSNoVerification>(slashing.clone())?;
+				} else {
+					self.process_attester_slashing::<BLS>(slashing.clone())?;
+				}
+			}
 		}
 
-		if body.attestations.len() > C::max_attestations() as usize {
-			return Err(Error::TooManyAttestations)
-		}
-		for attestation in body.attestations.iter() {
-			self.process_attestation::<BLS>(attestation.clone())?;
+		{
+			enter_span!("attestations", count = body.attestations.len());
+			for (attestation, verified) in body.attestations.iter()
+				.zip(verified.attestations)
+			{
+				self.process_attestation_with(attestation.clone(), |executive, indexed_attestation| {
+					match verified {
+						Some(verified) => verified && executive
+							.is_valid_indexed_attestation::<BLSNoVerification>(indexed_attestation),
+						None => executive
+							.is_valid_indexed_attestation::<BLS>(indexed_attestation),
+					}
+				})?;
+			}
 		}
 
-		if body.deposits.len() > C::max_deposits() as usize {
-			return Err(Error::TooManyDeposits)
-		}
-		for deposit in body.deposits.iter() {
-			self.process_deposit::<BLS>(deposit.clone())?;
+		{
+			enter_span!("deposits", count = body.deposits.len());
+			for (deposit, verified) in body.deposits.iter().zip(verified.deposits) {
+				match verified {
+					Some(verified) => self.process_deposit_with(
+						deposit.clone(), |_, _| verified
+					)?,
+					None => self.process_deposit::<BLS>(deposit.clone())?,
+				}
+			}
 		}
 
-		if body.voluntary_exits.len() > C::max_voluntary_exits() as usize {
-			return Err(Error::TooManyVoluntaryExits)
-		}
-		for voluntary_exit in body.voluntary_exits.iter() {
-			self.process_voluntary_exit::<BLS>(voluntary_exit.clone())?;
+		{
+			enter_span!("voluntary_exits", count = body.voluntary_exits.len());
+			for (voluntary_exit, verified) in body.voluntary_exits.iter()
+				.zip(verified.voluntary_exits)
+			{
+				match verified {
+					Some(verified) => self.process_voluntary_exit_with(
+						voluntary_exit.clone(), |_, _| verified
+					)?,
+					None => self.process_voluntary_exit::<BLS>(voluntary_exit.clone())?,
+				}
+			}
 		}
 
 		Ok(())
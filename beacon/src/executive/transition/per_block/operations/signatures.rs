@@ -0,0 +1,444 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use alloc::{vec, vec::Vec};
+use crate::primitives::{H256, Signature, ValidatorId, Epoch, Slot, Version};
+use crate::types::{
+	Attestation, Deposit, VoluntaryExit, BeaconBlockBody, IndexedAttestation,
+	AttestationDataAndCustodyBit, SigningDepositData, SigningVoluntaryExit,
+	Block, Fork, UnsealedBeaconBlock, ProposerSlashing, AttesterSlashing,
+	SigningBeaconBlockHeader,
+};
+use crate::{Config, BeaconExecutive, BLSConfig, Error, utils};
+use bm_le::tree_root;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// A signature with everything needed to verify it, independent of state.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct SignatureSet {
+	/// Public keys, one for each message.
+	pub pubkeys: Vec<ValidatorId>,
+	/// Signed messages.
+	pub messages: Vec<H256>,
+	/// Signature.
+	pub signature: Signature,
+	/// Signature domain.
+	pub domain: u64,
+}
+
+impl SignatureSet {
+	/// Verify the signature set.
+	pub fn verify<BLS: BLSConfig>(&self) -> bool {
+		if self.pubkeys.len() == 1 && self.messages.len() == 1 {
+			BLS::verify(
+				&self.pubkeys[0], &self.messages[0], &self.signature, self.domain
+			)
+		} else {
+			BLS::verify_multiple(
+				&self.pubkeys, &self.messages, &self.signature, self.domain
+			)
+		}
+	}
+}
+
+/// Verify signature sets, concurrently if the `parallel` feature is enabled.
+/// Sets that are `None` are passed through.
+pub fn verify_signature_sets<BLS: BLSConfig>(
+	sets: &[Option<SignatureSet>]
+) -> Vec<Option<bool>> {
+	#[cfg(feature = "parallel")]
+	let iter = sets.par_iter();
+	#[cfg(not(feature = "parallel"))]
+	let iter = sets.iter();
+
+	iter.map(|set| set.as_ref().map(|set| set.verify::<BLS>())).collect()
+}
+
+/// Fork versions over the history of a chain, so that signatures made under
+/// earlier forks can be verified without a state at the signing slot.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ForkVersions {
+	versions: Vec<(Epoch, Version)>,
+}
+
+impl ForkVersions {
+	/// Create fork versions with only the genesis fork.
+	pub fn new(genesis_version: Version) -> Self {
+		Self { versions: vec![(0, genesis_version)] }
+	}
+
+	/// Create fork versions from the fork of a state. Versions before the
+	/// previous one are not known to the state, and must be added with
+	/// `push` for verifying older signatures.
+	pub fn from_fork(fork: &Fork) -> Self {
+		let mut versions = Self::new(fork.previous_version);
+		versions.push(fork.epoch, fork.current_version);
+		versions
+	}
+
+	/// Add a fork activated at `epoch`.
+	pub fn push(&mut self, epoch: Epoch, version: Version) {
+		let position = self.versions.iter()
+			.position(|(e, _)| *e >= epoch)
+			.unwrap_or(self.versions.len());
+		if self.versions.get(position).map(|(e, _)| *e == epoch).unwrap_or(false) {
+			self.versions[position].1 = version;
+		} else {
+			self.versions.insert(position, (epoch, version));
+		}
+	}
+
+	/// Fork version active at an epoch.
+	pub fn version_at_epoch(&self, epoch: Epoch) -> Version {
+		self.versions.iter()
+			.rev()
+			.find(|(e, _)| *e <= epoch)
+			.map(|(_, version)| *version)
+			.unwrap_or(self.versions[0].1)
+	}
+
+	/// Fork version active at a slot.
+	pub fn version_at_slot<C: Config>(&self, slot: Slot) -> Version {
+		self.version_at_epoch(utils::epoch_of_slot::<C>(slot))
+	}
+
+	/// Signature domain at an epoch.
+	pub fn domain(&self, domain_type: u32, epoch: Epoch) -> u64 {
+		crate::compute_domain(domain_type, self.version_at_epoch(epoch))
+	}
+}
+
+/// Signature sets of the proposal and the randao reveal of a block, built
+/// from the proposer public key and fork versions only.
+pub fn historical_block_signature_sets<'a, C: Config, B: Block<Config=C>>(
+	block: &'a B,
+	proposer: &ValidatorId,
+	forks: &ForkVersions,
+) -> Vec<SignatureSet> where
+	UnsealedBeaconBlock<C>: From<&'a B>,
+{
+	let epoch = utils::epoch_of_slot::<C>(block.slot());
+	let mut sets = Vec::new();
+
+	if let Some(signature) = block.signature() {
+		sets.push(SignatureSet {
+			pubkeys: vec![proposer.clone()],
+			messages: vec![tree_root::<C::Digest, _>(&UnsealedBeaconBlock::from(block))],
+			signature: signature.clone(),
+			domain: forks.domain(C::domain_beacon_proposer(), epoch),
+		});
+	}
+	sets.push(SignatureSet {
+		pubkeys: vec![proposer.clone()],
+		messages: vec![tree_root::<C::Digest, _>(&epoch)],
+		signature: block.body().randao_reveal.clone(),
+		domain: forks.domain(C::domain_randao(), epoch),
+	});
+
+	sets
+}
+
+/// Verify proposal and randao signatures of historical blocks, given the
+/// public key of each block proposer, in batch.
+pub fn verify_historical_blocks<'a, C: Config, BLS: BLSConfig, B: Block<Config=C>>(
+	blocks: &'a [(B, ValidatorId)],
+	forks: &ForkVersions,
+) -> Vec<bool> where
+	UnsealedBeaconBlock<C>: From<&'a B>,
+{
+	let sets = blocks.iter()
+		.map(|(block, proposer)| historical_block_signature_sets(block, proposer, forks))
+		.collect::<Vec<_>>();
+	let results = verify_signature_sets::<BLS>(
+		&sets.iter().flatten().cloned().map(Some).collect::<Vec<_>>()
+	);
+
+	let mut results = results.into_iter();
+	sets.iter()
+		.map(|block_sets| {
+			block_sets.iter().fold(true, |valid, _| {
+				results.next().and_then(|r| r).unwrap_or(false) && valid
+			})
+		})
+		.collect()
+}
+
+/// Pre-verified signatures of block operations. `None` means the signature
+/// could not be checked ahead of time, and must be verified during
+/// processing.
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+pub struct VerifiedSignatures {
+	/// Attestation signatures.
+	pub attestations: Vec<Option<bool>>,
+	/// Deposit signatures.
+	pub deposits: Vec<Option<bool>>,
+	/// Voluntary exit signatures.
+	pub voluntary_exits: Vec<Option<bool>>,
+	/// Whether all proposer and attester slashing signatures are valid.
+	pub slashings: bool,
+}
+
+impl<'a, C: Config> BeaconExecutive<'a, C> {
+	/// Signature set of an indexed attestation.
+	pub fn indexed_attestation_signature_set<BLS: BLSConfig>(
+		&self,
+		indexed_attestation: &IndexedAttestation<C>,
+	) -> SignatureSet {
+		SignatureSet {
+			pubkeys: vec![
+				BLS::aggregate_pubkeys(
+					&indexed_attestation.custody_bit_0_indices
+						.iter()
+						.map(|i| self.validators[*i as usize].pubkey)
+						.collect::<Vec<_>>()[..]
+				),
+				BLS::aggregate_pubkeys(
+					&indexed_attestation.custody_bit_1_indices
+						.iter()
+						.map(|i| self.validators[*i as usize].pubkey)
+						.collect::<Vec<_>>()[..]
+				),
+			],
+			messages: vec![
+				tree_root::<C::Digest, _>(&AttestationDataAndCustodyBit {
+					data: indexed_attestation.data.clone(),
+					custody_bit: false,
+				}),
+				tree_root::<C::Digest, _>(&AttestationDataAndCustodyBit {
+					data: indexed_attestation.data.clone(),
+					custody_bit: true,
+				}),
+			],
+			signature: indexed_attestation.signature,
+			domain: self.domain(
+				C::domain_beacon_attester(),
+				Some(indexed_attestation.data.target.epoch)
+			),
+		}
+	}
+
+	/// Signature set of an attestation, if it can be built from the current
+	/// state.
+	pub fn attestation_signature_set<BLS: BLSConfig>(
+		&self,
+		attestation: &Attestation<C>,
+	) -> Option<SignatureSet> {
+		let data = &attestation.data;
+		if !(data.target.epoch == self.current_epoch() ||
+			 data.target.epoch == self.previous_epoch())
+		{
+			return None
+		}
+		if !(data.slot + C::min_attestation_inclusion_delay() <= self.slot &&
+			 self.slot <= data.slot + C::slots_per_epoch())
+		{
+			return None
+		}
+		if !(data.index < self.committee_count_at_slot(data.slot)) {
+			return None
+		}
+
+		let indexed_attestation = self.indexed_attestation(attestation.clone()).ok()?;
+
+		Some(self.indexed_attestation_signature_set::<BLS>(&indexed_attestation))
+	}
+
+	/// Signature set of a deposit.
+	pub fn deposit_signature_set(&self, deposit: &Deposit) -> SignatureSet {
+		SignatureSet {
+			pubkeys: vec![deposit.data.pubkey.clone()],
+			messages: vec![
+				tree_root::<C::Digest, _>(&SigningDepositData::from(deposit.data.clone()))
+			],
+			signature: deposit.data.signature.clone(),
+			domain: self.domain(C::domain_deposit(), None),
+		}
+	}
+
+	/// Signature set of a voluntary exit, if the validator exists.
+	pub fn voluntary_exit_signature_set(
+		&self,
+		exit: &VoluntaryExit,
+	) -> Option<SignatureSet> {
+		let validator = self.validators.get(exit.validator_index as usize)?;
+
+		Some(SignatureSet {
+			pubkeys: vec![validator.pubkey.clone()],
+			messages: vec![
+				tree_root::<C::Digest, _>(&SigningVoluntaryExit::from(exit.clone()))
+			],
+			signature: exit.signature.clone(),
+			domain: self.domain(C::domain_voluntary_exit(), Some(exit.epoch)),
+		})
+	}
+
+	/// Verify signatures of attestations, deposits and voluntary exits of a
+	/// block body ahead of processing them.
+	///
+	/// None of the block operations modify validator public keys or
+	/// committees of the current and previous epoch, so the signature sets
+	/// can be built from the state before any operation is applied.
+	pub fn verify_operation_signatures<BLS: BLSConfig>(
+		&self,
+		body: &BeaconBlockBody<C>,
+	) -> VerifiedSignatures {
+		enter_span!("verify_operation_signatures");
+		let attestations = body.attestations.iter()
+			.map(|attestation| self.attestation_signature_set::<BLS>(attestation))
+			.collect::<Vec<_>>();
+		let deposits = body.deposits.iter()
+			.map(|deposit| Some(self.deposit_signature_set(deposit)))
+			.collect::<Vec<_>>();
+		let voluntary_exits = body.voluntary_exits.iter()
+			.map(|exit| self.voluntary_exit_signature_set(exit))
+			.collect::<Vec<_>>();
+
+		VerifiedSignatures {
+			attestations: verify_signature_sets::<BLS>(&attestations),
+			deposits: verify_signature_sets::<BLS>(&deposits),
+			voluntary_exits: verify_signature_sets::<BLS>(&voluntary_exits),
+			slashings: false,
+		}
+	}
+	/// Signature sets of both headers of a proposer slashing, if the
+	/// proposer exists.
+	pub fn proposer_slashing_signature_sets(
+		&self,
+		proposer_slashing: &ProposerSlashing,
+	) -> Option<Vec<SignatureSet>> {
+		let proposer = self.validators.get(proposer_slashing.proposer_index as usize)?;
+
+		Some([&proposer_slashing.header_1, &proposer_slashing.header_2].iter()
+			.map(|header| SignatureSet {
+				pubkeys: vec![proposer.pubkey.clone()],
+				messages: vec![
+					tree_root::<C::Digest, _>(&SigningBeaconBlockHeader::from((*header).clone()))
+				],
+				signature: header.signature.clone(),
+				domain: self.domain(
+					C::domain_beacon_proposer(),
+					Some(utils::epoch_of_slot::<C>(header.slot))
+				),
+			})
+			.collect())
+	}
+
+	/// Signature sets of both attestations of an attester slashing, if all
+	/// attesting validators exist.
+	pub fn attester_slashing_signature_sets<BLS: BLSConfig>(
+		&self,
+		attester_slashing: &AttesterSlashing<C>,
+	) -> Option<Vec<SignatureSet>> {
+		let attestations = [&attester_slashing.attestation_1, &attester_slashing.attestation_2];
+		let all_known = attestations.iter().all(|attestation| {
+			attestation.custody_bit_0_indices.iter()
+				.chain(attestation.custody_bit_1_indices.iter())
+				.all(|index| (*index as usize) < self.validators.len())
+		});
+		if !all_known {
+			return None
+		}
+
+		Some(attestations.iter()
+			.map(|attestation| self.indexed_attestation_signature_set::<BLS>(attestation))
+			.collect())
+	}
+
+	/// Verify all signatures of a block in one batch, ahead of applying it
+	/// with `process_verified_block`. The state must already be at the
+	/// block slot. Blocks with too many operations are rejected before any
+	/// signature is checked.
+	///
+	/// Invalid deposit signatures do not invalidate a block, so their
+	/// results are returned instead. Attestations and voluntary exits whose
+	/// signature sets cannot be built are left unverified, and are rejected
+	/// when processed.
+	pub fn verify_block_signatures<'b, B: Block<Config=C>, BLS: BLSConfig>(
+		&self,
+		block: &'b B,
+	) -> Result<VerifiedSignatures, Error> where
+		UnsealedBeaconBlock<C>: From<&'b B>,
+	{
+		enter_span!("verify_block_signatures", slot = block.slot().as_u64());
+		let body = block.body();
+		self.check_operation_counts(body)?;
+		let proposer = &self.validators[self.beacon_proposer_index()? as usize];
+		let mut checks = Vec::new();
+
+		if let Some(signature) = block.signature() {
+			checks.push((SignatureSet {
+				pubkeys: vec![proposer.pubkey.clone()],
+				messages: vec![tree_root::<C::Digest, _>(&UnsealedBeaconBlock::from(block))],
+				signature: signature.clone(),
+				domain: self.domain(C::domain_beacon_proposer(), None),
+			}, Error::BlockSignatureInvalid));
+		}
+		checks.push((SignatureSet {
+			pubkeys: vec![proposer.pubkey.clone()],
+			messages: vec![tree_root::<C::Digest, _>(&self.current_epoch())],
+			signature: body.randao_reveal.clone(),
+			domain: self.domain(C::domain_randao(), None),
+		}, Error::RandaoSignatureInvalid));
+
+		for slashing in body.proposer_slashings.iter() {
+			let sets = self.proposer_slashing_signature_sets(slashing)
+				.ok_or(Error::ProposerSlashingInvalidProposerIndex)?;
+			checks.extend(sets.into_iter()
+				.map(|set| (set, Error::ProposerSlashingInvalidSignature)));
+		}
+		for slashing in body.attester_slashings.iter() {
+			let sets = self.attester_slashing_signature_sets::<BLS>(slashing)
+				.ok_or(Error::AttesterSlashingInvalid)?;
+			checks.extend(sets.into_iter()
+				.map(|set| (set, Error::AttestationInvalidSignature)));
+		}
+
+		let attestations = body.attestations.iter()
+			.map(|attestation| self.attestation_signature_set::<BLS>(attestation))
+			.collect::<Vec<_>>();
+		let voluntary_exits = body.voluntary_exits.iter()
+			.map(|exit| self.voluntary_exit_signature_set(exit))
+			.collect::<Vec<_>>();
+		checks.extend(attestations.iter().flatten().cloned()
+			.map(|set| (set, Error::AttestationInvalidSignature)));
+		checks.extend(voluntary_exits.iter().flatten().cloned()
+			.map(|set| (set, Error::VoluntaryExitInvalidSignature)));
+
+		let deposits = body.deposits.iter()
+			.map(|deposit| Some(self.deposit_signature_set(deposit)))
+			.collect::<Vec<_>>();
+
+		let sets = checks.iter()
+			.map(|(set, _)| Some(set.clone()))
+			.chain(deposits.into_iter())
+			.collect::<Vec<_>>();
+		let mut results = verify_signature_sets::<BLS>(&sets).into_iter();
+
+		for (_, error) in checks {
+			if results.next() != Some(Some(true)) {
+				return Err(error)
+			}
+		}
+
+		Ok(VerifiedSignatures {
+			attestations: attestations.iter().map(|set| set.as_ref().map(|_| true)).collect(),
+			deposits: results.collect(),
+			voluntary_exits: voluntary_exits.iter().map(|set| set.as_ref().map(|_| true)).collect(),
+			slashings: true,
+		})
+	}
+}
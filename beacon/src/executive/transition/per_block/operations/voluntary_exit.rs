@@ -24,6 +24,30 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 		&mut self,
 		exit: VoluntaryExit
 	) -> Result<(), Error> {
+		self.process_voluntary_exit_with(exit, |executive, exit| {
+			let validator = &executive.validators[exit.validator_index as usize];
+			let domain = executive.domain(
+				C::domain_voluntary_exit(),
+				Some(exit.epoch)
+			);
+			BLS::verify(
+				&validator.pubkey,
+				&tree_root::<C::Digest, _>(&SigningVoluntaryExit::from(exit.clone())),
+				&exit.signature,
+				domain
+			)
+		})
+	}
+
+	/// Push a new `VoluntaryExit` to the state, with a custom signature
+	/// check.
+	pub fn process_voluntary_exit_with<F>(
+		&mut self,
+		exit: VoluntaryExit,
+		is_valid_signature: F,
+	) -> Result<(), Error> where
+		F: FnOnce(&Self, &VoluntaryExit) -> bool,
+	{
 		{
 			if exit.validator_index >= self.validators.len() as u64 {
 				return Err(Error::VoluntaryExitInvalidSignature)
@@ -47,16 +71,7 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 				return Err(Error::VoluntaryExitNotLongEnough)
 			}
 
-			let domain = self.domain(
-				C::domain_voluntary_exit(),
-				Some(exit.epoch)
-			);
-			if !BLS::verify(
-				&validator.pubkey,
-				&tree_root::<C::Digest, _>(&SigningVoluntaryExit::from(exit.clone())),
-				&exit.signature,
-				domain
-			) {
+			if !is_valid_signature(self, &exit) {
 				return Err(Error::VoluntaryExitInvalidSignature)
 			}
 		}
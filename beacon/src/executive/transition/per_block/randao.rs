@@ -36,7 +36,7 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 
 		let current_epoch = self.current_epoch();
 		self.state.randao_mixes[
-			(current_epoch % C::epochs_per_historical_vector()) as usize
+			(current_epoch % C::epochs_per_historical_vector()).as_usize()
 		] = self.randao_mix(current_epoch) ^
 			C::hash(&[&body.randao_reveal[..]]);
 
@@ -16,6 +16,7 @@
 
 use crate::types::HistoricalBatch;
 use crate::{Config, BeaconExecutive, Error};
+use crate::primitives::Gwei;
 use bm_le::tree_root;
 use core::cmp::min;
 
@@ -33,7 +34,8 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 		// Update effective balances with hysteresis
 		for index in 0..(self.validators.len() as u64) {
 			let validator = &mut self.state.validators[index as usize];
-			let balance = self.state.balances[index as usize];
+			let balance = self.state.balances.get(index as usize).cloned().map(Gwei::from)
+				.ok_or(Error::IndexOutOfRange)?;
 			let half_increment = C::effective_balance_increment() / 2;
 			if balance < validator.effective_balance ||
 				validator.effective_balance + 3 * half_increment < balance
@@ -47,12 +49,12 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 
 		// Set total slashed balances
 		self.state.slashings[
-			(next_epoch % C::epochs_per_slashings_vector()) as usize
+			(next_epoch % C::epochs_per_slashings_vector()).as_usize()
 		] = 0;
 
 		// Set randao mix
 		self.state.randao_mixes[
-			(next_epoch % C::epochs_per_historical_vector()) as usize
+			(next_epoch % C::epochs_per_historical_vector()).as_usize()
 		] = self.randao_mix(current_epoch);
 
 		// Set historical root accumulator
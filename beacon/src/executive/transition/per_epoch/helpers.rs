@@ -14,9 +14,12 @@
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
 
+use alloc::vec::Vec;
 use crate::primitives::{Epoch, Gwei, ValidatorIndex};
 use crate::types::PendingAttestation;
 use crate::{Config, BeaconExecutive, Error};
+use crate::components::ParticipationBits;
+use alloc::collections::BTreeMap;
 
 impl<'a, C: Config> BeaconExecutive<'a, C> {
 	/// Get attestations with matching source at given epoch.
@@ -64,22 +67,47 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 			})
 	}
 
+	/// Get participation of all unslashed validators within attestations.
+	pub fn unslashed_attesting_participation(
+		&self, attestations: &[PendingAttestation<C>]
+	) -> Result<ParticipationBits, Error> {
+		let mut ret = ParticipationBits::with_capacity(self.validators.len());
+		for a in attestations {
+			for index in self.attesting_indices(&a.data, &a.aggregation_bits)? {
+				if !self.validators[index as usize].slashed {
+					ret.insert(index);
+				}
+			}
+		}
+		Ok(ret)
+	}
+
 	/// Get indices of all unslashed validators within attestations.
 	pub fn unslashed_attesting_indices(
 		&self, attestations: &[PendingAttestation<C>]
 	) -> Result<Vec<ValidatorIndex>, Error> {
-		let mut ret = Vec::new();
+		Ok(self.unslashed_attesting_participation(attestations)?.indices().collect())
+	}
+
+	/// Get the attestation with minimal inclusion delay for each unslashed
+	/// attesting validator. Of attestations with equal delays, the first one
+	/// is taken.
+	pub fn min_inclusion_delay_attestations(
+		&self, attestations: &[PendingAttestation<C>]
+	) -> Result<BTreeMap<ValidatorIndex, PendingAttestation<C>>, Error> {
+		let mut ret: BTreeMap<ValidatorIndex, PendingAttestation<C>> = BTreeMap::new();
 		for a in attestations {
 			for index in self.attesting_indices(&a.data, &a.aggregation_bits)? {
-				if !ret.contains(&index) {
-					ret.push(index);
+				if self.validators[index as usize].slashed {
+					continue
+				}
+
+				let current = ret.entry(index).or_insert_with(|| a.clone());
+				if a.inclusion_delay < current.inclusion_delay {
+					*current = a.clone();
 				}
 			}
 		}
-		ret.retain(|index| {
-			!self.validators[*index as usize].slashed
-		});
-		ret.sort();
 		Ok(ret)
 	}
 
@@ -17,20 +17,41 @@
 mod helpers;
 mod justification;
 mod reward;
+mod scheme;
 mod registry;
 mod slashing;
 mod finalize;
 
+pub use self::reward::{RewardBreakdown, RewardProjection, PARTICIPATION_BASIS_POINTS};
+pub use self::registry::ValidatorLifecycle;
+pub use self::scheme::{RewardScheme, DefaultRewardScheme, ParticipationFlagRewardScheme};
+
 use crate::{Config, BeaconExecutive, Error};
 
 impl<'a, C: Config> BeaconExecutive<'a, C> {
 	/// Process an epoch.
 	pub fn process_epoch(&mut self) -> Result<(), Error> {
-		self.process_justification_and_finalization()?;
-		self.process_rewards_and_penalties()?;
-		self.process_registry_updates()?;
-		self.process_slashings();
-		self.process_final_updates()?;
+		enter_span!("process_epoch", epoch = self.current_epoch().as_u64());
+		{
+			enter_span!("justification_and_finalization");
+			self.process_justification_and_finalization()?;
+		}
+		{
+			enter_span!("rewards_and_penalties");
+			self.process_rewards_and_penalties()?;
+		}
+		{
+			enter_span!("registry_updates");
+			self.process_registry_updates()?;
+		}
+		{
+			enter_span!("slashings");
+			self.process_slashings()?;
+		}
+		{
+			enter_span!("final_updates");
+			self.process_final_updates()?;
+		}
 
 		Ok(())
 	}
@@ -14,26 +14,43 @@
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
 
+use alloc::vec::Vec;
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+use crate::primitives::{Epoch, ValidatorIndex};
+use crate::types::ValidatorStatus;
 use crate::{Config, BeaconExecutive, Error, consts, utils};
 use core::cmp::min;
 
-impl<'a, C: Config> BeaconExecutive<'a, C> {
-	/// Process registry updates
-	pub fn process_registry_updates(&mut self) -> Result<(), Error> {
-		for index in 0..self.validators.len() {
-			if self.validators[index].activation_eligibility_epoch == consts::FAR_FUTURE_EPOCH &&
-				self.validators[index].effective_balance == C::max_effective_balance()
-			{
-				self.state.validators[index].activation_eligibility_epoch = self.current_epoch();
-			}
-
-			if self.validators[index].is_active(self.current_epoch()) &&
-				self.validators[index].effective_balance <= C::ejection_balance()
-			{
-				self.initiate_validator_exit(index as u64);
-			}
-		}
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(deny_unknown_fields))]
+/// Lifecycle of a validator, as of the current epoch.
+pub struct ValidatorLifecycle {
+	/// Lifecycle status.
+	pub status: ValidatorStatus,
+	/// Epoch when the validator became eligible for activation.
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub activation_eligibility_epoch: Epoch,
+	/// Activation epoch, once dequeued.
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub activation_epoch: Epoch,
+	/// Exit epoch, once an exit is initiated.
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub exit_epoch: Epoch,
+	/// Withdrawable epoch, once an exit is initiated.
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub withdrawable_epoch: Epoch,
+	/// Position in the activation queue, if queued.
+	pub activation_queue_position: Option<u64>,
+	/// Estimated activation epoch, if queued, assuming the churn limit stays
+	/// the same and the queue is not blocked on finality.
+	pub estimated_activation_epoch: Option<Epoch>,
+}
 
+impl<'a, C: Config> BeaconExecutive<'a, C> {
+	/// Validators eligible for activation and not yet dequeued before the
+	/// finalized epoch, ordered by activation eligibility epoch.
+	pub fn activation_queue(&self) -> Vec<ValidatorIndex> {
 		let mut activation_queue = self.validators.iter()
 			.enumerate()
 			.filter(|(_, v)| {
@@ -46,18 +63,86 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 		activation_queue.sort_by_key(|index| {
 			self.validators[*index as usize].activation_eligibility_epoch
 		});
+		activation_queue
+	}
+
+	/// Mark validators at maximum effective balance as eligible for
+	/// activation.
+	pub fn process_activation_eligibility(&mut self) {
+		let current_epoch = self.current_epoch();
+		for validator in self.state.validators.iter_mut() {
+			if validator.activation_eligibility_epoch == consts::FAR_FUTURE_EPOCH &&
+				validator.effective_balance == C::max_effective_balance()
+			{
+				validator.activation_eligibility_epoch = current_epoch;
+			}
+		}
+	}
 
-		for index in &activation_queue[..min(activation_queue.len(),
-											 self.validator_churn_limit() as usize)]
-		{
-			let current_epoch = self.current_epoch();
+	/// Initiate the exit of active validators at or below the ejection
+	/// balance.
+	pub fn process_ejections(&mut self) {
+		for index in 0..self.validators.len() {
+			if self.validators[index].is_active(self.current_epoch()) &&
+				self.validators[index].effective_balance <= C::ejection_balance()
+			{
+				self.initiate_validator_exit(index as u64);
+			}
+		}
+	}
+
+	/// Dequeue activations, up to the churn limit.
+	pub fn process_activation_queue(&mut self) {
+		let activation_queue = self.activation_queue();
+		let churn_limit = self.validator_churn_limit() as usize;
+		let activation_epoch = utils::activation_exit_epoch::<C>(self.current_epoch());
+
+		for index in &activation_queue[..min(activation_queue.len(), churn_limit)] {
 			let validator = &mut self.state.validators[*index as usize];
 			if validator.activation_epoch == consts::FAR_FUTURE_EPOCH {
-				validator.activation_epoch =
-					utils::activation_exit_epoch::<C>(current_epoch);
+				validator.activation_epoch = activation_epoch;
 			}
 		}
+	}
+
+	/// Process registry updates
+	pub fn process_registry_updates(&mut self) -> Result<(), Error> {
+		self.process_activation_eligibility();
+		self.process_ejections();
+		self.process_activation_queue();
 
 		Ok(())
 	}
+
+	/// Lifecycle of a validator.
+	pub fn validator_lifecycle(
+		&self,
+		index: ValidatorIndex,
+	) -> Result<ValidatorLifecycle, Error> {
+		let validator = self.validators.get(index as usize).ok_or(Error::IndexOutOfRange)?;
+		let current_epoch = self.current_epoch();
+
+		// Validators already dequeued stay in the queue until finalized.
+		let activation_queue_position = self.activation_queue().into_iter()
+			.filter(|i| {
+				self.validators[*i as usize].activation_epoch == consts::FAR_FUTURE_EPOCH
+			})
+			.position(|i| i == index)
+			.map(|position| position as u64);
+		let estimated_activation_epoch = activation_queue_position.map(|position| {
+			utils::activation_exit_epoch::<C>(
+				current_epoch + position / self.validator_churn_limit()
+			)
+		});
+
+		Ok(ValidatorLifecycle {
+			status: validator.status(current_epoch),
+			activation_eligibility_epoch: validator.activation_eligibility_epoch,
+			activation_epoch: validator.activation_epoch,
+			exit_epoch: validator.exit_epoch,
+			withdrawable_epoch: validator.withdrawable_epoch,
+			activation_queue_position,
+			estimated_activation_epoch,
+		})
+	}
 }
@@ -14,100 +14,181 @@
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
 
+use alloc::{vec, vec::Vec};
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
 use crate::primitives::{ValidatorIndex, Gwei};
-use crate::{Config, BeaconExecutive, Error, utils, consts};
+use crate::{Config, BeaconExecutive, BalanceDeltas, Error, utils, consts};
+use crate::utils::SafeArith;
+use super::scheme::RewardScheme;
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(deny_unknown_fields))]
+/// Rewards and penalties of a validator, broken down by component.
+pub struct RewardBreakdown {
+	/// Reward for matching FFG source.
+	pub source_reward: Gwei,
+	/// Penalty for missing FFG source.
+	pub source_penalty: Gwei,
+	/// Reward for matching FFG target.
+	pub target_reward: Gwei,
+	/// Penalty for missing FFG target.
+	pub target_penalty: Gwei,
+	/// Reward for matching head.
+	pub head_reward: Gwei,
+	/// Penalty for missing head.
+	pub head_penalty: Gwei,
+	/// Attester reward for inclusion delay.
+	pub inclusion_reward: Gwei,
+	/// Proposer reward for including attestations.
+	pub proposer_reward: Gwei,
+	/// Inactivity leak penalty.
+	pub inactivity_penalty: Gwei,
+}
+
+impl RewardBreakdown {
+	/// Total reward.
+	pub fn reward(&self) -> Result<Gwei, Error> {
+		Ok(self.source_reward
+			.safe_add(self.target_reward)?
+			.safe_add(self.head_reward)?
+			.safe_add(self.inclusion_reward)?
+			.safe_add(self.proposer_reward)?)
+	}
+
+	/// Total penalty.
+	pub fn penalty(&self) -> Result<Gwei, Error> {
+		Ok(self.source_penalty
+			.safe_add(self.target_penalty)?
+			.safe_add(self.head_penalty)?
+			.safe_add(self.inactivity_penalty)?)
+	}
+}
+
+/// Denominator of participation rates, which are given in basis points.
+pub const PARTICIPATION_BASIS_POINTS: u64 = 10_000;
+
+const SECONDS_PER_YEAR: u64 = 31_557_600;
+
+#[derive(Clone, PartialEq, Eq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(deny_unknown_fields))]
+/// Projected rewards of a validator under a hypothetical participation rate.
+pub struct RewardProjection {
+	/// Validator index.
+	pub index: ValidatorIndex,
+	/// Effective balance of the validator.
+	pub effective_balance: Gwei,
+	/// Expected reward of one epoch transition.
+	pub epoch_reward: Gwei,
+	/// Expected penalty of one epoch transition.
+	pub epoch_penalty: Gwei,
+	/// Projected annual percentage rate, in basis points. Negative if
+	/// penalties outweigh rewards.
+	pub apr_basis_points: i64,
+}
 
 impl<'a, C: Config> BeaconExecutive<'a, C> {
-	fn base_reward(&self, index: ValidatorIndex) -> Gwei {
+	pub(crate) fn base_reward(&self, index: ValidatorIndex) -> Result<Gwei, Error> {
 		let total_balance = self.total_active_balance();
 
 		let effective_balance =
 			self.validators[index as usize].effective_balance;
 
-		effective_balance * C::base_reward_factor() /
-			utils::integer_squareroot(total_balance) /
-			consts::BASE_REWARDS_PER_EPOCH
+		Ok(effective_balance.safe_mul(C::base_reward_factor())?
+			.safe_div(utils::integer_squareroot(total_balance.as_u64()))?
+			.safe_div(consts::BASE_REWARDS_PER_EPOCH)?)
 	}
 
-	fn attestation_deltas(&self) -> Result<(Vec<Gwei>, Vec<Gwei>), Error> {
-		let previous_epoch = self.previous_epoch();
-		let total_balance = self.total_active_balance();
-		let mut rewards = (0..self.validators.len())
-			.map(|_| 0).collect::<Vec<_>>();
-		let mut penalties = (0..self.validators.len())
-			.map(|_| 0).collect::<Vec<_>>();
-		let eligible_validator_indices = self.validators.iter()
-			.enumerate()
-			.filter(|(_, v)| {
-				v.is_active(previous_epoch) ||
-					(v.slashed && previous_epoch + 1 < v.withdrawable_epoch)
-			})
-			.map(|(i, _)| i as u64)
-			.collect::<Vec<_>>();
-
-		// Micro-incentives for matching FFG source, FFG target, and head
-		let matching_source_attestations =
-			self.matching_source_attestations(previous_epoch)?;
-		let matching_target_attestations =
-			self.matching_target_attestations(previous_epoch)?;
-		let matching_head_attestations =
-			self.matching_head_attestations(previous_epoch)?;
-		for attestations in &[&matching_source_attestations,
-							  &matching_target_attestations,
-							  &matching_head_attestations]
-		{
-			let unslashed_attesting_indices =
-				self.unslashed_attesting_indices(attestations)?;
-			let attesting_balance = self.total_balance(&unslashed_attesting_indices);
-			for index in &eligible_validator_indices {
-				if unslashed_attesting_indices.contains(index) {
-					rewards[*index as usize] += self.base_reward(*index) *
-						attesting_balance / total_balance;
-				} else {
-					penalties[*index as usize] += self.base_reward(*index);
-				}
-			}
-		}
+	/// Rewards and penalties of each validator for attestations of the
+	/// previous epoch, broken down by component, under the reward scheme of
+	/// the config.
+	pub fn reward_breakdown(&self) -> Result<Vec<RewardBreakdown>, Error> {
+		C::RewardScheme::reward_breakdown(self)
+	}
 
-		// Proposer and inclusion delay micro-rewards
-		for index in self.unslashed_attesting_indices(&matching_source_attestations)? {
-			let attestation = matching_source_attestations.iter()
-				.map(|a| Ok((
-					a,
-					self.attesting_indices(&a.data, &a.aggregation_bits)?
-						.contains(&index)
-				)))
-				.collect::<Result<Vec<_>, _>>()?
-				.into_iter()
-				.filter(|(_, c)| *c)
-				.map(|(a, _)| a)
-				.fold(matching_source_attestations[0].clone(), |a, b| {
-					if a.inclusion_delay < b.inclusion_delay { a } else { b.clone() }
-				});
-
-			let proposer_reward = self.base_reward(index) / C::proposer_reward_quotient();
-			rewards[attestation.proposer_index as usize] += proposer_reward;
-			let max_attester_reward = self.base_reward(index) - proposer_reward;
-			rewards[index as usize] += max_attester_reward / attestation.inclusion_delay;
+	/// Project the rewards of each active validator, if `participation` basis
+	/// points of validators, the validator itself included, attested
+	/// correctly and were included with minimal delay.
+	///
+	/// The projection follows the reward scheme of the config, and is the
+	/// expected value over which validators participate, with proposer
+	/// rewards spread by effective balance, as proposers are selected. The
+	/// inactivity leak applies if finality is already delayed and
+	/// participation is too low to justify.
+	pub fn project_rewards(&self, participation: u64) -> Result<Vec<RewardProjection>, Error> {
+		if participation > PARTICIPATION_BASIS_POINTS {
+			return Err(Error::ParticipationOutOfRange)
 		}
 
-		// Inactivity penalty
-		let finality_delay = previous_epoch - self.finalized_checkpoint.epoch;
-		if finality_delay > C::min_epochs_to_inactivity_penalty() {
-			let matching_target_attesting_indices =
-				self.unslashed_attesting_indices(&matching_target_attestations)?;
-			for index in &eligible_validator_indices {
-				penalties[*index as usize] += consts::BASE_REWARDS_PER_EPOCH *
-					self.base_reward(*index);
-				if !matching_target_attesting_indices.contains(index) {
-					penalties[*index as usize] +=
-						self.validators[*index as usize].effective_balance *
-						finality_delay / C::inactivity_penalty_quotient();
-				}
+		let current_epoch = self.current_epoch();
+		let finality_delay = current_epoch.safe_sub(self.finalized_checkpoint.epoch)?.as_u64();
+		let inactivity_leak = finality_delay > C::min_epochs_to_inactivity_penalty() &&
+			participation * 3 < PARTICIPATION_BASIS_POINTS * 2;
+		let epochs_per_year = SECONDS_PER_YEAR / (C::seconds_per_slot() * C::slots_per_epoch());
+
+		self.active_validator_indices(current_epoch).into_iter().map(|index| -> Result<_, Error> {
+			let effective_balance = self.validators[index as usize].effective_balance;
+			let (reward, penalty) = C::RewardScheme::project_reward(
+				self, index, participation, finality_delay, inactivity_leak,
+			)?;
+
+			let apr_basis_points = if effective_balance == 0 {
+				0
+			} else {
+				((reward.as_u64() as i128 - penalty.as_u64() as i128) *
+				 epochs_per_year as i128 * PARTICIPATION_BASIS_POINTS as i128 /
+				 effective_balance.as_u64() as i128) as i64
+			};
+
+			Ok(RewardProjection {
+				index, effective_balance,
+				epoch_reward: reward,
+				epoch_penalty: penalty,
+				apr_basis_points,
+			})
+		}).collect()
+	}
+
+	/// Inactivity penalties each validator accumulates if finality only
+	/// resumes after `epochs` more epoch transitions, and the validator
+	/// misses the target meanwhile.
+	///
+	/// Base rewards are taken as of the current state, although they slowly
+	/// rise as the leak drains inactive balances.
+	pub fn project_inactivity_penalties(&self, epochs: u64) -> Result<Vec<Gwei>, Error> {
+		let current_epoch = self.current_epoch();
+		let finality_delay = self.previous_epoch().safe_sub(self.finalized_checkpoint.epoch)?.as_u64();
+		let mut penalties = vec![Gwei::default(); self.validators.len()];
+
+		for index in self.active_validator_indices(current_epoch) {
+			let base = self.base_reward(index)?;
+			let effective_balance = self.validators[index as usize].effective_balance;
+
+			for delay in (finality_delay..finality_delay.safe_add(epochs)?)
+				.filter(|delay| *delay > C::min_epochs_to_inactivity_penalty())
+			{
+				penalties[index as usize].safe_add_assign(
+					base.safe_mul(consts::BASE_REWARDS_PER_EPOCH)?.safe_add(
+						effective_balance.safe_mul(delay)?
+							.safe_div(C::inactivity_penalty_quotient())?
+					)?
+				)?;
 			}
 		}
 
-		Ok((rewards, penalties))
+		Ok(penalties)
+	}
+
+	/// Balance deltas that will be applied at the end of the current epoch,
+	/// for attestations of the previous epoch.
+	pub fn balance_deltas(&self) -> Result<BalanceDeltas, Error> {
+		let breakdowns = self.reward_breakdown()?;
+
+		Ok(BalanceDeltas {
+			epoch: self.previous_epoch(),
+			rewards: breakdowns.iter().map(|b| b.reward()).collect::<Result<_, _>>()?,
+			penalties: breakdowns.iter().map(|b| b.penalty()).collect::<Result<_, _>>()?,
+		})
 	}
 
 	/// Process rewards and penalties
@@ -116,10 +197,10 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 			return Ok(())
 		}
 
-		let (rewards, penalties) = self.attestation_deltas()?;
+		let deltas = self.balance_deltas()?;
 		for i in 0..self.validators.len() {
-			self.increase_balance(i as u64, rewards[i]);
-			self.decrease_balance(i as u64, penalties[i]);
+			self.increase_balance(i as u64, deltas.rewards[i])?;
+			self.decrease_balance(i as u64, deltas.penalties[i])?;
 		}
 
 		Ok(())
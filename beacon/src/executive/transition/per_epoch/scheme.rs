@@ -0,0 +1,365 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Reward schemes, selected by `Config::RewardScheme`.
+
+use alloc::{vec, vec::Vec};
+use crate::primitives::{ValidatorIndex, Gwei};
+use crate::components::ParticipationBits;
+use crate::{Config, BeaconExecutive, Error, utils, consts};
+use crate::utils::{SafeArith, ArithError};
+use super::reward::{RewardBreakdown, PARTICIPATION_BASIS_POINTS};
+
+/// Scheme of the rewards and penalties for attestations of the previous
+/// epoch, applied at each epoch transition.
+pub trait RewardScheme {
+	/// Rewards and penalties of each validator, broken down by component.
+	fn reward_breakdown<C: Config>(
+		executive: &BeaconExecutive<C>,
+	) -> Result<Vec<RewardBreakdown>, Error>;
+
+	/// Expected reward and penalty of the validator at `index` for one
+	/// epoch transition, if `participation` basis points of validators
+	/// attested correctly and were included with minimal delay. The
+	/// inactivity leak applies with `finality_delay` if `inactivity_leak`.
+	fn project_reward<C: Config>(
+		executive: &BeaconExecutive<C>,
+		index: ValidatorIndex,
+		participation: u64,
+		finality_delay: u64,
+		inactivity_leak: bool,
+	) -> Result<(Gwei, Gwei), Error>;
+}
+
+/// Validators eligible for rewards and penalties of the previous epoch:
+/// active ones, and slashed ones not yet withdrawable.
+fn eligible_validator_indices<C: Config>(executive: &BeaconExecutive<C>) -> Vec<ValidatorIndex> {
+	let previous_epoch = executive.previous_epoch();
+	executive.validators.iter()
+		.enumerate()
+		.filter(|(_, v)| {
+			v.is_active(previous_epoch) ||
+				(v.slashed && previous_epoch + 1 < v.withdrawable_epoch)
+		})
+		.map(|(i, _)| i as u64)
+		.collect()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+/// Phase 0 rewards: micro-incentives for matching source, target and head,
+/// inclusion delay rewards shared with the proposer, and the inactivity
+/// leak.
+pub struct DefaultRewardScheme;
+
+impl RewardScheme for DefaultRewardScheme {
+	fn reward_breakdown<C: Config>(
+		executive: &BeaconExecutive<C>,
+	) -> Result<Vec<RewardBreakdown>, Error> {
+		let previous_epoch = executive.previous_epoch();
+		let total_balance = executive.total_active_balance();
+		let mut breakdowns = vec![RewardBreakdown::default(); executive.validators.len()];
+		if executive.current_epoch() == C::genesis_epoch() {
+			return Ok(breakdowns)
+		}
+
+		let eligible_validator_indices = eligible_validator_indices(executive);
+
+		// Micro-incentives for matching FFG source, FFG target, and head
+		let matching_source_attestations =
+			executive.matching_source_attestations(previous_epoch)?;
+		let matching_target_attestations =
+			executive.matching_target_attestations(previous_epoch)?;
+		let matching_head_attestations =
+			executive.matching_head_attestations(previous_epoch)?;
+		macro_rules! micro_incentives {
+			( $attestations:expr, $reward:ident, $penalty:ident ) => {
+				let unslashed_attesting_participation =
+					executive.unslashed_attesting_participation($attestations)?;
+				let attesting_balance = executive.total_balance(
+					&unslashed_attesting_participation.indices().collect::<Vec<_>>()
+				);
+				for index in &eligible_validator_indices {
+					let base_reward = executive.base_reward(*index)?;
+					if unslashed_attesting_participation.contains(*index) {
+						breakdowns[*index as usize].$reward.safe_add_assign(
+							base_reward.safe_mul_div(
+								attesting_balance.as_u64(), total_balance.as_u64(),
+							)?
+						)?;
+					} else {
+						breakdowns[*index as usize].$penalty.safe_add_assign(base_reward)?;
+					}
+				}
+			}
+		}
+
+		micro_incentives!(&matching_source_attestations, source_reward, source_penalty);
+		micro_incentives!(&matching_target_attestations, target_reward, target_penalty);
+		micro_incentives!(&matching_head_attestations, head_reward, head_penalty);
+
+		// Proposer and inclusion delay micro-rewards
+		for (index, attestation) in executive.min_inclusion_delay_attestations(
+			&matching_source_attestations
+		)? {
+			let base_reward = executive.base_reward(index)?;
+			let proposer_reward = base_reward.safe_div(C::proposer_reward_quotient())?;
+			breakdowns[attestation.proposer_index as usize].proposer_reward
+				.safe_add_assign(proposer_reward)?;
+			let max_attester_reward = base_reward.safe_sub(proposer_reward)?;
+			breakdowns[index as usize].inclusion_reward
+				.safe_add_assign(max_attester_reward.safe_div(attestation.inclusion_delay)?)?;
+		}
+
+		// Inactivity penalty
+		let finality_delay = previous_epoch.safe_sub(executive.finalized_checkpoint.epoch)?.as_u64();
+		if finality_delay > C::min_epochs_to_inactivity_penalty() {
+			let matching_target_attesting_participation =
+				executive.unslashed_attesting_participation(&matching_target_attestations)?;
+			for index in &eligible_validator_indices {
+				breakdowns[*index as usize].inactivity_penalty.safe_add_assign(
+					executive.base_reward(*index)?.safe_mul(consts::BASE_REWARDS_PER_EPOCH)?
+				)?;
+				if !matching_target_attesting_participation.contains(*index) {
+					breakdowns[*index as usize].inactivity_penalty.safe_add_assign(
+						executive.validators[*index as usize].effective_balance
+							.safe_mul(finality_delay)?
+							.safe_div(C::inactivity_penalty_quotient())?
+					)?;
+				}
+			}
+		}
+
+		Ok(breakdowns)
+	}
+
+	/// Source, target and head votes are each rewarded in proportion to the
+	/// attesting balance, and inclusion and proposer rewards together
+	/// amount to one base reward.
+	fn project_reward<C: Config>(
+		executive: &BeaconExecutive<C>,
+		index: ValidatorIndex,
+		participation: u64,
+		finality_delay: u64,
+		inactivity_leak: bool,
+	) -> Result<(Gwei, Gwei), Error> {
+		let base = executive.base_reward(index)?;
+		let effective_balance = executive.validators[index as usize].effective_balance;
+		let missed = PARTICIPATION_BASIS_POINTS.safe_sub(participation)?;
+
+		let reward = base.safe_mul(3)?
+			.safe_mul(participation)?.safe_div(PARTICIPATION_BASIS_POINTS)?
+			.safe_mul(participation)?.safe_div(PARTICIPATION_BASIS_POINTS)?
+			.safe_add(base.safe_mul(participation)?.safe_div(PARTICIPATION_BASIS_POINTS)?)?;
+		let mut penalty = base.safe_mul(3)?
+			.safe_mul(missed)?.safe_div(PARTICIPATION_BASIS_POINTS)?;
+
+		if inactivity_leak {
+			penalty.safe_add_assign(base.safe_mul(consts::BASE_REWARDS_PER_EPOCH)?)?;
+			penalty.safe_add_assign(
+				effective_balance.safe_mul(finality_delay)?
+					.safe_div(C::inactivity_penalty_quotient())?
+					.safe_mul(missed)?.safe_div(PARTICIPATION_BASIS_POINTS)?
+			)?;
+		}
+
+		Ok((reward, penalty))
+	}
+}
+
+/// Base reward of the participation flag scheme per effective balance
+/// increment.
+fn base_reward_per_increment<C: Config>(executive: &BeaconExecutive<C>) -> Result<Gwei, ArithError> {
+	C::effective_balance_increment().safe_mul(C::base_reward_factor())?
+		.safe_div(utils::integer_squareroot(executive.total_active_balance().as_u64()))
+}
+
+/// Denominator of the proposer reward for the flags of an included
+/// attester.
+const PROPOSER_REWARD_DENOMINATOR: u64 =
+	(consts::WEIGHT_DENOMINATOR - consts::PROPOSER_WEIGHT) * consts::WEIGHT_DENOMINATOR /
+	consts::PROPOSER_WEIGHT;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+/// Altair-style rewards: weighted rewards for timely source, target and
+/// head participation flags, scaled by the participating balance, with the
+/// proposer rewarded in proportion to the flags it includes.
+///
+/// Timeliness is taken from the earliest inclusion of each attester. Flag
+/// rewards are withheld during the inactivity leak, and validators missing
+/// the target leak in proportion to the finality delay, as in phase 0.
+pub struct ParticipationFlagRewardScheme;
+
+impl RewardScheme for ParticipationFlagRewardScheme {
+	fn reward_breakdown<C: Config>(
+		executive: &BeaconExecutive<C>,
+	) -> Result<Vec<RewardBreakdown>, Error> {
+		let previous_epoch = executive.previous_epoch();
+		let total_balance = executive.total_active_balance();
+		let mut breakdowns = vec![RewardBreakdown::default(); executive.validators.len()];
+		if executive.current_epoch() == C::genesis_epoch() {
+			return Ok(breakdowns)
+		}
+
+		let increment = C::effective_balance_increment();
+		let active_increments = total_balance / increment;
+		let base_reward_per_increment = base_reward_per_increment(executive)?;
+		let base_reward = |index: ValidatorIndex| -> Result<Gwei, ArithError> {
+			base_reward_per_increment.safe_mul(
+				executive.validators[index as usize].effective_balance / increment
+			)
+		};
+		let finality_delay = previous_epoch.safe_sub(executive.finalized_checkpoint.epoch)?.as_u64();
+		let inactivity_leak = finality_delay > C::min_epochs_to_inactivity_penalty();
+
+		let matching_source_attestations =
+			executive.matching_source_attestations(previous_epoch)?;
+		let matching_target = executive.unslashed_attesting_participation(
+			&executive.matching_target_attestations(previous_epoch)?
+		)?;
+		let matching_head = executive.unslashed_attesting_participation(
+			&executive.matching_head_attestations(previous_epoch)?
+		)?;
+		let inclusions = executive.min_inclusion_delay_attestations(
+			&matching_source_attestations
+		)?;
+
+		let mut timely_source = ParticipationBits::with_capacity(executive.validators.len());
+		let mut timely_target = ParticipationBits::with_capacity(executive.validators.len());
+		let mut timely_head = ParticipationBits::with_capacity(executive.validators.len());
+		for (index, attestation) in &inclusions {
+			if attestation.inclusion_delay <= utils::integer_squareroot(C::slots_per_epoch()) {
+				timely_source.insert(*index);
+			}
+			if matching_target.contains(*index) &&
+				attestation.inclusion_delay <= C::slots_per_epoch()
+			{
+				timely_target.insert(*index);
+			}
+			if matching_head.contains(*index) &&
+				attestation.inclusion_delay == C::min_attestation_inclusion_delay()
+			{
+				timely_head.insert(*index);
+			}
+		}
+
+		let eligible_validator_indices = eligible_validator_indices(executive);
+		macro_rules! flag_incentives {
+			( $flag:expr, $weight:expr, $penalized:expr, $reward:ident, $penalty:ident ) => {
+				let participating_increments = executive.total_balance(
+					&$flag.indices().collect::<Vec<_>>()
+				) / increment;
+				for index in &eligible_validator_indices {
+					if $flag.contains(*index) {
+						if !inactivity_leak {
+							breakdowns[*index as usize].$reward.safe_add_assign(
+								base_reward(*index)?
+									.safe_mul($weight)?
+									.safe_mul(participating_increments)?
+									.safe_div(active_increments.safe_mul(consts::WEIGHT_DENOMINATOR)?)?
+							)?;
+						}
+					} else if $penalized {
+						breakdowns[*index as usize].$penalty.safe_add_assign(
+							base_reward(*index)?.safe_mul($weight)?.safe_div(consts::WEIGHT_DENOMINATOR)?
+						)?;
+					}
+				}
+			}
+		}
+
+		flag_incentives!(timely_source, consts::TIMELY_SOURCE_WEIGHT, true,
+						 source_reward, source_penalty);
+		flag_incentives!(timely_target, consts::TIMELY_TARGET_WEIGHT, true,
+						 target_reward, target_penalty);
+		flag_incentives!(timely_head, consts::TIMELY_HEAD_WEIGHT, false,
+						 head_reward, head_penalty);
+
+		// Proposer reward for the flags of each included attester
+		for (index, attestation) in &inclusions {
+			let mut numerator = Gwei::default();
+			for (flag, weight) in &[
+				(&timely_source, consts::TIMELY_SOURCE_WEIGHT),
+				(&timely_target, consts::TIMELY_TARGET_WEIGHT),
+				(&timely_head, consts::TIMELY_HEAD_WEIGHT),
+			] {
+				if flag.contains(*index) {
+					numerator.safe_add_assign(base_reward(*index)?.safe_mul(*weight)?)?;
+				}
+			}
+			breakdowns[attestation.proposer_index as usize].proposer_reward
+				.safe_add_assign(numerator.safe_div(PROPOSER_REWARD_DENOMINATOR)?)?;
+		}
+
+		// Inactivity penalty
+		if inactivity_leak {
+			for index in &eligible_validator_indices {
+				if !timely_target.contains(*index) {
+					breakdowns[*index as usize].inactivity_penalty.safe_add_assign(
+						executive.validators[*index as usize].effective_balance
+							.safe_mul(finality_delay)?
+							.safe_div(C::inactivity_penalty_quotient())?
+					)?;
+				}
+			}
+		}
+
+		Ok(breakdowns)
+	}
+
+	/// Participants meet all flags, each rewarded in proportion to the
+	/// participating balance outside the inactivity leak, and expected
+	/// proposer rewards amount to the flag weights of one base reward over
+	/// the proposer reward denominator. Missed source and target flags are
+	/// penalized, and the missed target leaks during the inactivity leak.
+	fn project_reward<C: Config>(
+		executive: &BeaconExecutive<C>,
+		index: ValidatorIndex,
+		participation: u64,
+		finality_delay: u64,
+		inactivity_leak: bool,
+	) -> Result<(Gwei, Gwei), Error> {
+		let effective_balance = executive.validators[index as usize].effective_balance;
+		let base = base_reward_per_increment(executive)?
+			.safe_mul(effective_balance / C::effective_balance_increment())?;
+		let missed = PARTICIPATION_BASIS_POINTS.safe_sub(participation)?;
+		let flag_weights = consts::TIMELY_SOURCE_WEIGHT + consts::TIMELY_TARGET_WEIGHT +
+			consts::TIMELY_HEAD_WEIGHT;
+		let penalized_weights = consts::TIMELY_SOURCE_WEIGHT + consts::TIMELY_TARGET_WEIGHT;
+
+		let mut reward = base.safe_mul(flag_weights)?
+			.safe_div(PROPOSER_REWARD_DENOMINATOR)?
+			.safe_mul(participation)?.safe_div(PARTICIPATION_BASIS_POINTS)?;
+		if !inactivity_leak {
+			reward.safe_add_assign(
+				base.safe_mul(flag_weights)?.safe_div(consts::WEIGHT_DENOMINATOR)?
+					.safe_mul(participation)?.safe_div(PARTICIPATION_BASIS_POINTS)?
+					.safe_mul(participation)?.safe_div(PARTICIPATION_BASIS_POINTS)?
+			)?;
+		}
+		let mut penalty = base.safe_mul(penalized_weights)?.safe_div(consts::WEIGHT_DENOMINATOR)?
+			.safe_mul(missed)?.safe_div(PARTICIPATION_BASIS_POINTS)?;
+
+		if inactivity_leak {
+			penalty.safe_add_assign(
+				effective_balance.safe_mul(finality_delay)?
+					.safe_div(C::inactivity_penalty_quotient())?
+					.safe_mul(missed)?.safe_div(PARTICIPATION_BASIS_POINTS)?
+			)?;
+		}
+
+		Ok((reward, penalty))
+	}
+}
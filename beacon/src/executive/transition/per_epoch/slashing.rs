@@ -14,14 +14,20 @@
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
 
-use crate::{Config, BeaconExecutive};
+use crate::{Config, BeaconExecutive, Error};
+use crate::primitives::Gwei;
+use crate::utils::SafeArith;
 use core::cmp::min;
 
 impl<'a, C: Config> BeaconExecutive<'a, C> {
 	/// Process slashings
-	pub fn process_slashings(&mut self) {
+	pub fn process_slashings(&mut self) -> Result<(), Error> {
 		let current_epoch = self.current_epoch();
 		let total_balance = self.total_active_balance();
+		let total_slashings = self.slashings.iter()
+			.try_fold(0u64, |acc, slashing| acc.safe_add(*slashing))?;
+		let adjusted_total_slashing_balance =
+			min(Gwei::from(total_slashings.safe_mul(3)?), total_balance);
 
 		for index in 0..(self.validators.len() as u64) {
 			let penalty = {
@@ -31,9 +37,12 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 					validator.withdrawable_epoch
 				{
 					let increment = C::effective_balance_increment();
-					let penalty_numerator = validator.effective_balance / increment *
-						min(self.slashings.iter().fold(0, |acc, x| acc + *x) * 3, total_balance);
-					let penalty = penalty_numerator / total_balance * increment;
+					let penalty_numerator = adjusted_total_slashing_balance.safe_mul(
+						validator.effective_balance.as_u64().safe_div(increment.as_u64())?
+					)?;
+					let penalty = increment.safe_mul(
+						penalty_numerator.as_u64().safe_div(total_balance.as_u64())?
+					)?;
 
 					Some(penalty)
 				} else {
@@ -41,8 +50,10 @@ impl<'a, C: Config> BeaconExecutive<'a, C> {
 				}
 			};
 			if let Some(penalty) = penalty {
-				self.decrease_balance(index, penalty);
+				self.decrease_balance(index, penalty)?;
 			}
 		}
+
+		Ok(())
 	}
 }
@@ -0,0 +1,62 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+//! Weak subjectivity period.
+//!
+//! A node whose latest finalized checkpoint is older than the weak
+//! subjectivity period can be fed a conflicting finalized chain by
+//! validators that have since exited, and must be synced from a trusted
+//! checkpoint instead.
+
+use core::cmp::max;
+use crate::primitives::Epoch;
+use crate::{BeaconExecutive, Config};
+use crate::consts::{ETH_TO_GWEI, SAFETY_DECAY};
+
+impl<'a, C: Config> BeaconExecutive<'a, C> {
+	/// Weak subjectivity period of the current state, in epochs.
+	pub fn weak_subjectivity_period(&self) -> Epoch {
+		let mut period = Epoch::from(C::min_validator_withdrawability_delay());
+
+		let n = self.active_validator_len(self.current_epoch()) as u64;
+		if n == 0 {
+			return period
+		}
+		let t = (self.total_active_balance() / n / ETH_TO_GWEI).as_u64();
+		let big_t = (C::max_effective_balance() / ETH_TO_GWEI).as_u64();
+		let delta = self.validator_churn_limit();
+		let big_delta = C::max_deposits() * C::slots_per_epoch();
+		let d = SAFETY_DECAY;
+
+		if big_t * (200 + 3 * d) < t * (200 + 12 * d) {
+			let epochs_for_validator_set_churn =
+				n * (t * (200 + 12 * d) - big_t * (200 + 3 * d)) /
+				(600 * delta * (2 * t + big_t));
+			let epochs_for_balance_top_ups =
+				n * (200 + 3 * d) / (600 * big_delta);
+			period += max(epochs_for_validator_set_churn, epochs_for_balance_top_ups);
+		} else {
+			period += 3 * n * d * t / (200 * big_delta * (big_t - t));
+		}
+
+		period
+	}
+
+	/// Whether the current state, taken as a weak subjectivity state, is still
+	/// within its weak subjectivity period at `current_epoch`.
+	pub fn is_within_weak_subjectivity_period(&self, current_epoch: Epoch) -> bool {
+		current_epoch <= self.current_epoch() + self.weak_subjectivity_period()
+	}
+}
@@ -0,0 +1,155 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+//! Fork schedule, and the fork digest and signing domain derived from it.
+
+use alloc::{vec, vec::Vec};
+use crate::primitives::{Epoch, H256, Uint, Version};
+use crate::types::Fork;
+use crate::{Config, ForkVersions, ForkName, utils};
+use crate::consts::FAR_FUTURE_EPOCH;
+
+/// Root of the fork data of a fork version on the chain of a genesis root.
+pub fn compute_fork_data_root<C: Config>(current_version: Version, genesis_root: H256) -> H256 {
+	let mut version = [0u8; 32];
+	(&mut version[..4]).copy_from_slice(current_version.as_ref());
+	C::hash(&[&version[..], genesis_root.as_ref()])
+}
+
+/// Fork digest, the first four bytes of the fork data root.
+pub fn compute_fork_digest<C: Config>(current_version: Version, genesis_root: H256) -> Version {
+	Version::from_slice(&compute_fork_data_root::<C>(current_version, genesis_root)[..4])
+}
+
+/// Signing domain of a domain type under a fork version.
+pub fn compute_domain(domain_type: u32, fork_version: Version) -> Uint {
+	utils::bls_domain(domain_type, fork_version)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// Fork scheduled at an epoch.
+pub struct ScheduledFork {
+	/// Activation epoch.
+	pub epoch: Epoch,
+	/// Fork version.
+	pub version: Version,
+	/// State transition of the fork.
+	pub name: ForkName,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+/// Fork versions of a chain keyed by their activation epoch, starting with
+/// the genesis version.
+pub struct ForkSchedule {
+	/// Root of the genesis block.
+	pub genesis_root: H256,
+	forks: Vec<ScheduledFork>,
+}
+
+impl ForkSchedule {
+	/// Create a schedule with only the genesis fork.
+	pub fn new(genesis_version: Version, genesis_root: H256) -> Self {
+		Self {
+			genesis_root,
+			forks: vec![ScheduledFork { epoch: Epoch::new(0), version: genesis_version, name: ForkName::Phase0 }],
+		}
+	}
+
+	/// Schedule a fork activated at `epoch` that only changes the fork
+	/// version, keeping the state transition of the fork before it.
+	pub fn with_fork(self, epoch: Epoch, version: Version) -> Self {
+		let name = self.fork_at(epoch).name;
+		self.with_upgrade(epoch, version, name)
+	}
+
+	/// Schedule a fork activated at `epoch` upgrading to the state transition
+	/// of `name`, replacing any fork already scheduled at that epoch.
+	pub fn with_upgrade(mut self, epoch: Epoch, version: Version, name: ForkName) -> Self {
+		let fork = ScheduledFork { epoch, version, name };
+		match self.forks.binary_search_by_key(&epoch, |f| f.epoch) {
+			Ok(position) => self.forks[position] = fork,
+			Err(position) => self.forks.insert(position, fork),
+		}
+		self
+	}
+
+	/// Scheduled forks, in order.
+	pub fn forks(&self) -> &[ScheduledFork] {
+		&self.forks
+	}
+
+	fn position_at_epoch(&self, epoch: Epoch) -> usize {
+		self.forks.iter().rposition(|f| f.epoch <= epoch).unwrap_or(0)
+	}
+
+	/// Scheduled fork active at an epoch.
+	pub fn fork_at(&self, epoch: Epoch) -> ScheduledFork {
+		self.forks[self.position_at_epoch(epoch)]
+	}
+
+	/// Fork active at an epoch, as recorded in a state.
+	pub fn fork_at_epoch(&self, epoch: Epoch) -> Fork {
+		let position = self.position_at_epoch(epoch);
+		let current = self.forks[position];
+		let previous = self.forks[position.saturating_sub(1)];
+		Fork {
+			previous_version: previous.version,
+			current_version: current.version,
+			epoch: current.epoch,
+		}
+	}
+
+	/// All forks of the schedule, as recorded in a state.
+	pub fn all_forks(&self) -> Vec<Fork> {
+		self.forks.iter().map(|f| self.fork_at_epoch(f.epoch)).collect()
+	}
+
+	/// Fork version active at an epoch.
+	pub fn version_at_epoch(&self, epoch: Epoch) -> Version {
+		self.fork_at(epoch).version
+	}
+
+	/// Next fork scheduled after `epoch`, if any.
+	pub fn next_fork(&self, epoch: Epoch) -> Option<ScheduledFork> {
+		self.forks.iter().find(|f| f.epoch > epoch).cloned()
+	}
+
+	/// Epoch and version of the next fork after `epoch`, or far future
+	/// and the current version if none is scheduled.
+	pub fn next_fork_or_current(&self, epoch: Epoch) -> (Epoch, Version) {
+		self.next_fork(epoch)
+			.map(|f| (f.epoch, f.version))
+			.unwrap_or((FAR_FUTURE_EPOCH, self.version_at_epoch(epoch)))
+	}
+
+	/// Fork digest at an epoch.
+	pub fn fork_digest_at_epoch<C: Config>(&self, epoch: Epoch) -> Version {
+		compute_fork_digest::<C>(self.version_at_epoch(epoch), self.genesis_root)
+	}
+
+	/// Signing domain of a domain type at a message epoch.
+	pub fn domain(&self, domain_type: u32, epoch: Epoch) -> Uint {
+		compute_domain(domain_type, self.version_at_epoch(epoch))
+	}
+
+	/// Fork versions for verifying signatures over the history of the chain.
+	pub fn fork_versions(&self) -> ForkVersions {
+		let mut versions = ForkVersions::new(self.forks[0].version);
+		for fork in &self.forks[1..] {
+			versions.push(fork.epoch, fork.version);
+		}
+		versions
+	}
+}
@@ -14,9 +14,10 @@
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
 
-use crate::primitives::Uint;
+use crate::primitives::{Uint, Gwei, H256};
 use crate::types::{Deposit, BeaconBlockHeader, BeaconBlock, Eth1Data, BeaconBlockBody};
-use crate::{Config, BeaconState, BeaconExecutive, BLSConfig, Error};
+use crate::{Config, BeaconState, BeaconExecutive, BLSConfig, DepositTree, Error, consts};
+use core::cmp::min;
 use bm_le::tree_root;
 
 /// Generate genesis state and genesis block from given deposits, timestamp and eth1 data.
@@ -73,3 +74,69 @@ pub fn genesis_beacon_state<C: Config, BLS: BLSConfig>(
 
 	Ok(state)
 }
+
+/// Generate genesis state from an eth1 block and all deposits up to it. The
+/// deposit root is updated with each deposit, so that proofs are checked
+/// against the deposits processed so far.
+pub fn initialize_beacon_state_from_eth1<C: Config, BLS: BLSConfig>(
+	eth1_block_hash: H256,
+	eth1_timestamp: Uint,
+	deposits: &[Deposit],
+) -> Result<BeaconState<C>, Error> {
+	let mut state = BeaconState {
+		genesis_time: eth1_timestamp - eth1_timestamp % consts::SECONDS_PER_DAY +
+			2 * consts::SECONDS_PER_DAY,
+		eth1_data: Eth1Data {
+			block_hash: eth1_block_hash,
+			deposit_count: deposits.len() as Uint,
+			..Default::default()
+		},
+		latest_block_header: BeaconBlockHeader {
+			body_root: tree_root::<C::Digest, _>(
+				&BeaconBlockBody::<C>::default()
+			),
+			..Default::default()
+		},
+		..BeaconState::<C>::default()
+	};
+
+	for i in 0..state.randao_mixes.len() {
+		state.randao_mixes[i] = eth1_block_hash;
+	}
+
+	let mut tree = DepositTree::<C>::new();
+	for deposit in deposits {
+		tree.push(&deposit.data);
+		state.eth1_data.deposit_root = tree.root();
+		BeaconExecutive::new(&mut state).process_deposit::<BLS>(deposit.clone())?;
+	}
+
+	for index in 0..state.validators.len() {
+		let balance = state.balances.get(index).cloned().map(Gwei::from)
+			.ok_or(Error::IndexOutOfRange)?;
+		let validator = &mut state.validators[index];
+		validator.effective_balance = min(
+			balance - balance % C::effective_balance_increment(),
+			C::max_effective_balance(),
+		);
+		if validator.effective_balance == C::max_effective_balance() {
+			validator.activation_eligibility_epoch = C::genesis_epoch();
+			validator.activation_epoch = C::genesis_epoch();
+		}
+	}
+
+	Ok(state)
+}
+
+/// Check whether a candidate genesis state has a late enough genesis time
+/// and enough active validators.
+pub fn is_valid_genesis_state<C: Config>(state: &BeaconState<C>) -> bool {
+	if state.genesis_time < C::min_genesis_time() {
+		return false
+	}
+
+	let mut state = state.clone();
+	let executive = BeaconExecutive::new(&mut state);
+	executive.active_validator_indices(C::genesis_epoch()).len() as Uint >=
+		C::min_genesis_active_validator_count()
+}
@@ -20,6 +20,17 @@
 
 extern crate alloc;
 
+/// Enter a debug level span until the end of the enclosing block, when the
+/// `tracing` feature is enabled.
+macro_rules! enter_span {
+	($($args:tt)*) => {
+		#[cfg(feature = "tracing")]
+		let span = tracing::debug_span!($($args)*);
+		#[cfg(feature = "tracing")]
+		let _enter = span.enter();
+	}
+}
+
 pub use beacon_primitives as primitives;
 
 /// Types for operations and blocks.
@@ -28,23 +39,60 @@ pub mod types;
 pub mod consts;
 /// Exported beacon chain utilities.
 pub mod utils;
-/// Components for reuse.
+/// Components for reuse, including a standalone Casper FFG finality gadget.
 pub mod components;
+/// YAML (de)serialization of beacon types.
+#[cfg(feature = "yaml")]
+pub mod yaml;
 
 mod error;
 mod config;
 mod executive;
 mod genesis;
+mod deposit_tree;
+mod fork;
+mod upgrade;
+#[cfg(feature = "merge")]
+mod execution;
 
 pub use self::error::Error;
 pub use self::config::{
-	BLSConfig, BLSNoVerification,
-	Config, MinimalConfig, MainnetConfig, SapphireConfig,
+	BLSConfig, BLSNoVerification, DefaultDigest,
+	Config, MinimalConfig, MainnetConfig, SapphireConfig, ConfigValues, DynamicConfig,
+	ParticipationFlagConfig,
+};
+#[cfg(feature = "altair")]
+pub use self::config::AltairConfig;
+pub use self::executive::{
+	BeaconState, BeaconExecutive, ValidatorProof, ProposerAssignment, CommitteeAssignment,
+	BalanceDeltas, BalanceDeltasCommitment, BalanceDeltaProof, RewardBreakdown,
+	RewardProjection, PARTICIPATION_BASIS_POINTS,
+	RewardScheme, DefaultRewardScheme, ParticipationFlagRewardScheme, ValidatorLifecycle,
+	SignatureSet, VerifiedSignatures, verify_signature_sets,
+	ForkVersions, historical_block_signature_sets, verify_historical_blocks,
+	LightClientUpdate, LightClientOptimisticUpdate, FINALIZED_CHECKPOINT_FIELD_INDEX,
+	FINALITY_BRANCH_DEPTH, block_header, header_root,
+};
+#[cfg(feature = "altair")]
+pub use self::executive::{SyncCommitteeAssignment, sync_committee_period};
+pub use self::genesis::{
+	genesis, genesis_beacon_state, initialize_beacon_state_from_eth1, is_valid_genesis_state,
+};
+pub use self::deposit_tree::DepositTree;
+pub use self::fork::{
+	ForkSchedule, ScheduledFork, compute_fork_data_root, compute_fork_digest, compute_domain,
+};
+#[cfg(feature = "merge")]
+pub use self::execution::{
+	ExecutionEngine, StubExecutionEngine, PayloadStatus, PayloadAttributes,
+	compute_timestamp_at_slot,
+};
+pub use self::upgrade::{
+	ForkName, ForkAwareState, ForkAwareBlock, process_slots_with_schedule,
+	execute_block_with_schedule,
 };
-pub use self::executive::{BeaconState, BeaconExecutive};
-pub use self::genesis::{genesis, genesis_beacon_state};
 
-use self::primitives::{H256, H768};
+use self::primitives::{H256, H768, Slot};
 use self::types::{
 	Attestation, UnsealedBeaconBlock, BeaconBlockBody, BeaconBlock, VoluntaryExit, Deposit,
 	Block, AttesterSlashing, ProposerSlashing, Eth1Data, SigningBeaconBlockHeader,
@@ -61,9 +109,47 @@ pub fn execute_block<C: Config, BLS: BLSConfig>(
 	executive.state_transition::<_, BLS>(block)
 }
 
+/// Given a block, apply it to a parent state without checking the resulting
+/// state root.
+pub fn apply_block<C: Config, BLS: BLSConfig>(
+	block: &BeaconBlock<C>,
+	state: &mut BeaconState<C>,
+	verified: Option<VerifiedSignatures>,
+) -> Result<(), Error> {
+	let mut executive = BeaconExecutive::new(state);
+	match verified {
+		Some(verified) => executive.apply_verified_block::<_, BLS>(block, verified),
+		None => executive.apply_block::<_, BLS>(block),
+	}
+}
+
+/// Verify all signatures of a block against its parent state, in batch.
+/// The parent state is not modified, so this can run concurrently with the
+/// application of other blocks. Pass the result to `execute_verified_block`.
+pub fn verify_block_signatures<C: Config, BLS: BLSConfig>(
+	block: &BeaconBlock<C>,
+	state: &BeaconState<C>,
+) -> Result<VerifiedSignatures, Error> {
+	let mut state = state.clone();
+	let mut executive = BeaconExecutive::new(&mut state);
+	executive.process_slots(block.slot)?;
+	executive.verify_block_signatures::<_, BLS>(block)
+}
+
+/// Given a block with signatures verified by `verify_block_signatures`,
+/// execute based on the same parent state.
+pub fn execute_verified_block<C: Config, BLS: BLSConfig>(
+	block: &BeaconBlock<C>,
+	state: &mut BeaconState<C>,
+	verified: VerifiedSignatures,
+) -> Result<(), Error> {
+	let mut executive = BeaconExecutive::new(state);
+	executive.state_transition_verified::<_, BLS>(block, verified)
+}
+
 /// Get genesis domain.
 pub fn genesis_domain(domain_type: u32) -> u64 {
-	utils::bls_domain(domain_type, Default::default())
+	compute_domain(domain_type, Default::default())
 }
 
 /// Beacon block inherent.
@@ -72,9 +158,12 @@ pub struct Inherent {
 	pub randao_reveal: H768,
 	/// New eth1 data.
 	pub eth1_data: Eth1Data,
+	/// Graffiti of the block.
+	pub graffiti: H256,
 }
 
 /// Beacon block transaction.
+#[derive(Clone)]
 pub enum Transaction<C: Config> {
 	/// Proposer slashing.
 	ProposerSlashing(ProposerSlashing),
@@ -91,7 +180,7 @@ pub enum Transaction<C: Config> {
 /// Initialize a block, and apply inherents.
 pub fn initialize_block<C: Config>(
 	state: &mut BeaconState<C>,
-	target_slot: u64
+	target_slot: Slot
 ) -> Result<(), Error> {
 	let mut executive = BeaconExecutive::new(state);
 	executive.process_slots(target_slot)
@@ -106,6 +195,7 @@ pub fn apply_inherent<C: Config, BLS: BLSConfig>(
 	let body = BeaconBlockBody {
 		randao_reveal: inherent.randao_reveal,
 		eth1_data: inherent.eth1_data,
+		graffiti: inherent.graffiti,
 		..Default::default()
 	};
 
@@ -127,6 +217,39 @@ pub fn apply_inherent<C: Config, BLS: BLSConfig>(
 	Ok(block)
 }
 
+/// Check whether a transaction would be valid on top of the state, without
+/// modifying it. Attestations are checked at their earliest inclusion slot.
+pub fn validate_transaction<C: Config, BLS: BLSConfig>(
+	state: &BeaconState<C>,
+	extrinsic: &Transaction<C>,
+) -> Result<(), Error> {
+	let mut state = state.clone();
+
+	if let Transaction::Attestation(ref attestation) = extrinsic {
+		let inclusion_slot = attestation.data.slot + C::min_attestation_inclusion_delay();
+		if inclusion_slot > state.slot + C::slots_per_epoch() {
+			return Err(Error::SlotOutOfRange)
+		}
+		if inclusion_slot > state.slot {
+			initialize_block::<C>(&mut state, inclusion_slot)?;
+		}
+	}
+
+	let mut executive = BeaconExecutive::new(&mut state);
+	match extrinsic {
+		Transaction::ProposerSlashing(slashing) =>
+			executive.process_proposer_slashing::<BLS>(slashing.clone()),
+		Transaction::AttesterSlashing(slashing) =>
+			executive.process_attester_slashing::<BLS>(slashing.clone()),
+		Transaction::Attestation(attestation) =>
+			executive.process_attestation::<BLS>(attestation.clone()),
+		Transaction::Deposit(deposit) =>
+			executive.process_deposit::<BLS>(deposit.clone()),
+		Transaction::VoluntaryExit(exit) =>
+			executive.process_voluntary_exit::<BLS>(exit.clone()),
+	}
+}
+
 /// Apply a transaction to the block.
 pub fn apply_transaction<C: Config, BLS: BLSConfig>(
 	block: &mut UnsealedBeaconBlock<C>,
@@ -21,7 +21,7 @@ use serde::{Serialize, Deserialize};
 use ssz::{Codec, Encode, Decode};
 use bm_le::{IntoTree, FromTree, MaxVec};
 use crate::Config;
-use crate::primitives::{H256, H768, Uint, Signature};
+use crate::primitives::{H256, H768, Slot, Signature};
 use super::operation::{AttesterSlashing, ProposerSlashing, Attestation, Deposit, VoluntaryExit};
 use super::misc::Eth1Data;
 
@@ -55,7 +55,7 @@ pub trait Block {
 	type Config: Config;
 
 	/// Slot of the block.
-	fn slot(&self) -> u64;
+	fn slot(&self) -> Slot;
 	/// Previous block root.
 	fn parent_root(&self) -> &H256;
 	/// State root.
@@ -73,8 +73,8 @@ pub trait Block {
 /// Beacon block.
 pub struct BeaconBlock<C: Config> {
 	/// Slot of the block.
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
-	pub slot: Uint,
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub slot: Slot,
 	/// Previous block root.
 	pub parent_root: H256,
 	/// State root.
@@ -88,7 +88,7 @@ pub struct BeaconBlock<C: Config> {
 impl<C: Config> Block for BeaconBlock<C> {
 	type Config = C;
 
-	fn slot(&self) -> u64 { self.slot }
+	fn slot(&self) -> Slot { self.slot }
 	fn parent_root(&self) -> &H256 { &self.parent_root }
 	fn state_root(&self) -> &H256 { &self.state_root }
 	fn body(&self) -> &BeaconBlockBody<C> { &self.body }
@@ -113,8 +113,8 @@ impl<'a, C: Config, T: Block<Config=C>> From<&'a T> for UnsealedBeaconBlock<C> {
 /// Unsealed Beacon block.
 pub struct UnsealedBeaconBlock<C: Config> {
 	/// Slot of the block.
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
-	pub slot: Uint,
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub slot: Slot,
 	/// Previous block root.
 	pub parent_root: H256,
 	/// State root.
@@ -126,7 +126,7 @@ pub struct UnsealedBeaconBlock<C: Config> {
 impl<C: Config> Block for UnsealedBeaconBlock<C> {
 	type Config = C;
 
-	fn slot(&self) -> u64 { self.slot }
+	fn slot(&self) -> Slot { self.slot }
 	fn parent_root(&self) -> &H256 { &self.parent_root }
 	fn state_root(&self) -> &H256 { &self.state_root }
 	fn body(&self) -> &BeaconBlockBody<C> { &self.body }
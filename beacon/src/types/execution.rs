@@ -0,0 +1,165 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+//! Execution payload types of the merge.
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+use ssz::{Codec, Encode, Decode, Compact, CompactRef, VariableSize};
+use bm_le::{IntoTree, FromTree, MaxVec, tree_root};
+use alloc::vec::Vec;
+use crate::Config;
+use crate::consts::{MaxBytesPerTransaction, MaxTransactionsPerPayload, MaxExtraDataBytes};
+use crate::primitives::{H256, Uint, ExecutionAddress, LogsBloom};
+
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "parity-codec", derive(parity_codec::Encode, parity_codec::Decode))]
+/// Opaque execution transaction, as a byte list.
+pub struct OpaqueTransaction(pub MaxVec<u8, MaxBytesPerTransaction>);
+
+impl Codec for OpaqueTransaction {
+	type Size = VariableSize;
+}
+
+impl Encode for OpaqueTransaction {
+	fn encode(&self) -> Vec<u8> {
+		CompactRef(&self.0).encode()
+	}
+}
+
+impl Decode for OpaqueTransaction {
+	fn decode(value: &[u8]) -> Result<Self, ssz::Error> {
+		Ok(Self(Compact::<MaxVec<u8, MaxBytesPerTransaction>>::decode(value)?.0))
+	}
+}
+
+impl IntoTree for OpaqueTransaction {
+	fn into_tree<DB: bm_le::WriteBackend>(&self, db: &mut DB) -> Result<<DB::Construct as bm_le::Construct>::Value, bm_le::Error<DB::Error>> where
+		DB::Construct: bm_le::CompatibleConstruct
+	{
+		CompactRef(&self.0).into_tree(db)
+	}
+}
+
+impl FromTree for OpaqueTransaction {
+	fn from_tree<DB: bm_le::ReadBackend>(root: &<DB::Construct as bm_le::Construct>::Value, db: &mut DB) -> Result<Self, bm_le::Error<DB::Error>> where
+		DB::Construct: bm_le::CompatibleConstruct
+	{
+		Ok(Self(Compact::<MaxVec<u8, MaxBytesPerTransaction>>::from_tree(root, db)?.0))
+	}
+}
+
+#[derive(Codec, Encode, Decode, IntoTree, FromTree, Clone, PartialEq, Eq, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(deny_unknown_fields))]
+#[cfg_attr(feature = "parity-codec", derive(parity_codec::Encode, parity_codec::Decode))]
+/// Execution block carried by a beacon block.
+pub struct ExecutionPayload {
+	/// Hash of the parent execution block.
+	pub parent_hash: H256,
+	/// Recipient of the transaction fees.
+	pub fee_recipient: ExecutionAddress,
+	/// Execution state root.
+	pub state_root: H256,
+	/// Receipts root.
+	pub receipts_root: H256,
+	/// Bloom filter of the logs.
+	pub logs_bloom: LogsBloom,
+	/// Randao mix of the beacon state the payload is built on.
+	pub prev_randao: H256,
+	/// Execution block number.
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub block_number: Uint,
+	/// Gas limit.
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub gas_limit: Uint,
+	/// Gas used.
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub gas_used: Uint,
+	/// Timestamp, matching the slot of the beacon block.
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub timestamp: Uint,
+	/// Extra data.
+	#[bm(compact)]
+	pub extra_data: MaxVec<u8, MaxExtraDataBytes>,
+	/// Base fee per gas, as a little endian 256-bit integer.
+	pub base_fee_per_gas: H256,
+	/// Hash of the execution block.
+	pub block_hash: H256,
+	/// Transactions.
+	pub transactions: MaxVec<OpaqueTransaction, MaxTransactionsPerPayload>,
+}
+
+#[derive(Codec, Encode, Decode, IntoTree, FromTree, Clone, PartialEq, Eq, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(deny_unknown_fields))]
+#[cfg_attr(feature = "parity-codec", derive(parity_codec::Encode, parity_codec::Decode))]
+/// Execution payload with transactions replaced by their root.
+pub struct ExecutionPayloadHeader {
+	/// Hash of the parent execution block.
+	pub parent_hash: H256,
+	/// Recipient of the transaction fees.
+	pub fee_recipient: ExecutionAddress,
+	/// Execution state root.
+	pub state_root: H256,
+	/// Receipts root.
+	pub receipts_root: H256,
+	/// Bloom filter of the logs.
+	pub logs_bloom: LogsBloom,
+	/// Randao mix of the beacon state the payload is built on.
+	pub prev_randao: H256,
+	/// Execution block number.
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub block_number: Uint,
+	/// Gas limit.
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub gas_limit: Uint,
+	/// Gas used.
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub gas_used: Uint,
+	/// Timestamp, matching the slot of the beacon block.
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub timestamp: Uint,
+	/// Extra data.
+	#[bm(compact)]
+	pub extra_data: MaxVec<u8, MaxExtraDataBytes>,
+	/// Base fee per gas, as a little endian 256-bit integer.
+	pub base_fee_per_gas: H256,
+	/// Hash of the execution block.
+	pub block_hash: H256,
+	/// Root of the transactions.
+	pub transactions_root: H256,
+}
+
+impl ExecutionPayload {
+	/// Header of the payload.
+	pub fn header<C: Config>(&self) -> ExecutionPayloadHeader {
+		ExecutionPayloadHeader {
+			parent_hash: self.parent_hash,
+			fee_recipient: self.fee_recipient,
+			state_root: self.state_root,
+			receipts_root: self.receipts_root,
+			logs_bloom: self.logs_bloom,
+			prev_randao: self.prev_randao,
+			block_number: self.block_number,
+			gas_limit: self.gas_limit,
+			gas_used: self.gas_used,
+			timestamp: self.timestamp,
+			extra_data: self.extra_data.clone(),
+			base_fee_per_gas: self.base_fee_per_gas,
+			block_hash: self.block_hash,
+			transactions_root: tree_root::<C::Digest, _>(&self.transactions),
+		}
+	}
+}
@@ -19,11 +19,12 @@ use serde::{Serialize, Deserialize};
 use ssz::{Codec, Encode, Decode};
 use bm_le::{IntoTree, FromTree, MaxVec};
 use vecarray::VecArray;
-use crate::Config;
+use crate::{Config, consts};
 use crate::components::{
 	Checkpoint as CheckpointT, Validator as ValidatorT, Attestation as AttestationT
 };
-use crate::primitives::{Version, Uint, H256, ValidatorId, Signature};
+use crate::components::slashing::{self, SlashableAttestationData};
+use crate::primitives::{Version, Uint, H256, ValidatorId, Signature, Slot, Epoch, Gwei};
 
 #[derive(Codec, Encode, Decode, IntoTree, FromTree, Clone, PartialEq, Eq, Default, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(deny_unknown_fields))]
@@ -35,8 +36,8 @@ pub struct Fork {
 	/// Current fork version
 	pub current_version: Version,
 	/// Fork epoch number
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
-	pub epoch: Uint,
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub epoch: Epoch,
 }
 
 #[derive(Codec, Encode, Decode, IntoTree, FromTree, Clone, PartialEq, Eq, Default, Debug)]
@@ -45,14 +46,14 @@ pub struct Fork {
 /// Checkpoint
 pub struct Checkpoint {
 	/// Epoch
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
-	pub epoch: Uint,
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub epoch: Epoch,
 	/// Root of the checkpoint
 	pub root: H256,
 }
 
 impl CheckpointT for Checkpoint {
-	fn epoch(&self) -> u64 {
+	fn epoch(&self) -> Epoch {
 		self.epoch
 	}
 }
@@ -67,37 +68,70 @@ pub struct Validator {
 	/// Withdrawal credentials
 	pub withdrawal_credentials: H256,
 	/// Effective balance
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
-	pub effective_balance: Uint,
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub effective_balance: Gwei,
 	/// Was the validator slashed
 	pub slashed: bool,
 
 	// == Status epochs ==
 	/// Epoch when became eligible for activation
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
-	pub activation_eligibility_epoch: Uint,
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub activation_eligibility_epoch: Epoch,
 	/// Epoch when validator activated
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
-	pub activation_epoch: Uint,
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub activation_epoch: Epoch,
 	/// Epoch when validator exited
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
-	pub exit_epoch: Uint,
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub exit_epoch: Epoch,
 	/// Epoch when validator is eligible to withdraw
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
-	pub withdrawable_epoch: Uint,
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub withdrawable_epoch: Epoch,
 }
 
 impl Validator {
 	/// Whether it is active validator.
-	pub fn is_active(&self, epoch: Uint) -> bool {
+	pub fn is_active(&self, epoch: Epoch) -> bool {
 		self.activation_epoch <= epoch && epoch < self.exit_epoch
 	}
 
 	/// Whether it is slashable.
-	pub fn is_slashable(&self, epoch: Uint) -> bool {
+	pub fn is_slashable(&self, epoch: Epoch) -> bool {
 		self.slashed == false &&
 			self.activation_epoch <= epoch && epoch < self.withdrawable_epoch
 	}
+
+	/// Lifecycle status at epoch.
+	pub fn status(&self, epoch: Epoch) -> ValidatorStatus {
+		if self.withdrawable_epoch <= epoch {
+			ValidatorStatus::Withdrawable
+		} else if self.slashed {
+			ValidatorStatus::Slashed
+		} else if self.exit_epoch != consts::FAR_FUTURE_EPOCH {
+			ValidatorStatus::Exiting
+		} else if self.is_active(epoch) {
+			ValidatorStatus::Active
+		} else {
+			ValidatorStatus::Pending
+		}
+	}
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(rename_all = "snake_case"))]
+/// Lifecycle status of a validator.
+pub enum ValidatorStatus {
+	/// Deposited, waiting for activation eligibility or in the activation
+	/// queue.
+	Pending,
+	/// Active, with no exit initiated.
+	Active,
+	/// Exit initiated, either still active until the exit epoch or exited,
+	/// and not yet withdrawable.
+	Exiting,
+	/// Slashed, and not yet withdrawable.
+	Slashed,
+	/// Withdrawable.
+	Withdrawable,
 }
 
 impl ValidatorT for Validator {
@@ -115,8 +149,10 @@ impl ValidatorT for Validator {
 /// Attestation data.
 pub struct AttestationData {
 	/// Voting slot.
-	pub slot: Uint,
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub slot: Slot,
 	/// Voting committee index.
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
 	pub index: Uint,
 
 	// == LMD-GHOST vote ==
@@ -133,9 +169,23 @@ pub struct AttestationData {
 impl AttestationData {
 	/// Is slashable.
 	pub fn is_slashable(&self, other: &AttestationData) -> bool {
-		(self != other && self.target.epoch == other.target.epoch) ||
-			(self.source.epoch < other.source.epoch &&
-			 other.target.epoch < self.target.epoch)
+		slashing::check(self, other).is_some()
+	}
+}
+
+impl SlashableAttestationData for AttestationData {
+	type Data = AttestationData;
+
+	fn data(&self) -> &AttestationData {
+		self
+	}
+
+	fn source_epoch(&self) -> Epoch {
+		self.source.epoch
+	}
+
+	fn target_epoch(&self) -> Epoch {
+		self.target.epoch
 	}
 }
 
@@ -158,9 +208,11 @@ pub struct AttestationDataAndCustodyBit {
 pub struct IndexedAttestation<C: Config> {
 	/// Validator indices of custody bit 0.
 	#[bm(compact)]
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint_list"))]
 	pub custody_bit_0_indices: MaxVec<Uint, C::MaxValidatorsPerCommittee>,
 	/// Validator indices of custody bit 1
 	#[bm(compact)]
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint_list"))]
 	pub custody_bit_1_indices: MaxVec<Uint, C::MaxValidatorsPerCommittee>,
 	/// Attestation data
 	pub data: AttestationData,
@@ -178,6 +230,22 @@ impl<C: Config> From<IndexedAttestation<C>> for SigningIndexedAttestation<C> {
 	}
 }
 
+impl<C: Config> SlashableAttestationData for IndexedAttestation<C> {
+	type Data = AttestationData;
+
+	fn data(&self) -> &AttestationData {
+		&self.data
+	}
+
+	fn source_epoch(&self) -> Epoch {
+		self.data.source.epoch
+	}
+
+	fn target_epoch(&self) -> Epoch {
+		self.data.target.epoch
+	}
+}
+
 #[derive(Codec, Encode, Decode, IntoTree, FromTree, Clone, PartialEq, Eq, Default, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(deny_unknown_fields))]
 #[cfg_attr(feature = "serde", serde(bound = "C: Config"))]
@@ -186,9 +254,11 @@ impl<C: Config> From<IndexedAttestation<C>> for SigningIndexedAttestation<C> {
 pub struct SigningIndexedAttestation<C: Config> {
 	/// Validator indices of custody bit 0.
 	#[bm(compact)]
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint_list"))]
 	pub custody_bit_0_indices: MaxVec<Uint, C::MaxValidatorsPerCommittee>,
 	/// Validator indices of custody bit 1
 	#[bm(compact)]
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint_list"))]
 	pub custody_bit_1_indices: MaxVec<Uint, C::MaxValidatorsPerCommittee>,
 	/// Attestation data
 	pub data: AttestationData,
@@ -208,10 +278,10 @@ pub struct PendingAttestation<C: Config> {
 	/// Attestation data
 	pub data: AttestationData,
 	/// Inclusion delay
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
 	pub inclusion_delay: Uint,
 	/// Proposer index
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
 	pub proposer_index: Uint,
 }
 
@@ -233,7 +303,7 @@ pub struct Eth1Data {
 	/// Root of the deposit tree
 	pub deposit_root: H256,
 	/// Total number of deposits
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
 	pub deposit_count: Uint,
 	/// Block hash
 	pub block_hash: H256,
@@ -261,8 +331,8 @@ pub struct DepositData {
 	/// Withdrawal credentials
 	pub withdrawal_credentials: H256,
 	/// Amount in Gwei
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
-	pub amount: Uint,
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub amount: Gwei,
 	/// Container self-signature
 	pub signature: Signature,
 }
@@ -287,8 +357,8 @@ pub struct SigningDepositData {
 	/// Withdrawal credentials
 	pub withdrawal_credentials: H256,
 	/// Amount in Gwei
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
-	pub amount: Uint,
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub amount: Gwei,
 }
 
 #[derive(Codec, Encode, Decode, FromTree, IntoTree, Clone, PartialEq, Eq, Default, Debug)]
@@ -297,8 +367,8 @@ pub struct SigningDepositData {
 /// Beacon block header.
 pub struct BeaconBlockHeader {
 	/// Slot of the block.
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
-    pub slot: Uint,
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+    pub slot: Slot,
 	/// Previous block root.
     pub parent_root: H256,
 	/// State root.
@@ -326,8 +396,8 @@ impl From<BeaconBlockHeader> for SigningBeaconBlockHeader {
 /// Beacon block header.
 pub struct SigningBeaconBlockHeader {
 	/// Slot of the block.
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
-    pub slot: Uint,
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+    pub slot: Slot,
 	/// Previous block root.
     pub parent_root: H256,
 	/// State root.
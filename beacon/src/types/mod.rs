@@ -17,17 +17,26 @@
 mod misc;
 mod operation;
 mod block;
+#[cfg(feature = "altair")]
+mod sync;
+#[cfg(feature = "merge")]
+mod execution;
 
 pub use self::misc::{
 	Fork, Checkpoint, Validator, AttestationData, AttestationDataAndCustodyBit,
 	IndexedAttestation, SigningIndexedAttestation, PendingAttestation, Eth1Data,
 	HistoricalBatch, DepositData, SigningDepositData, BeaconBlockHeader,
-	SigningBeaconBlockHeader,
+	SigningBeaconBlockHeader, ValidatorStatus,
 };
 pub use self::operation::{
 	ProposerSlashing, AttesterSlashing, Attestation, SigningAttestation,
+	AggregateAndProof, SignedAggregateAndProof,
 	Deposit, VoluntaryExit, SigningVoluntaryExit
 };
 pub use self::block::{
 	BeaconBlockBody, BeaconBlock, UnsealedBeaconBlock, Block,
 };
+#[cfg(feature = "altair")]
+pub use self::sync::{SyncCommittee, SyncAggregate};
+#[cfg(feature = "merge")]
+pub use self::execution::{OpaqueTransaction, ExecutionPayload, ExecutionPayloadHeader};
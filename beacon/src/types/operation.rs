@@ -22,7 +22,7 @@ use ssz::{Codec, Encode, Decode};
 use bm_le::{IntoTree, FromTree, MaxVec};
 use vecarray::VecArray;
 use crate::{Config, consts};
-use crate::primitives::{Uint, Signature, H256};
+use crate::primitives::{Uint, Signature, H256, Epoch};
 use super::misc::{IndexedAttestation, BeaconBlockHeader, DepositData, AttestationData};
 
 #[derive(Codec, Encode, Decode, IntoTree, FromTree, Clone, PartialEq, Eq, Default, Debug)]
@@ -31,7 +31,7 @@ use super::misc::{IndexedAttestation, BeaconBlockHeader, DepositData, Attestatio
 /// Block proposer slashing.
 pub struct ProposerSlashing {
 	/// Proposer index
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
 	pub proposer_index: Uint,
 	/// First proposal
 	pub header_1: BeaconBlockHeader,
@@ -103,6 +103,33 @@ pub struct SigningAttestation<C: Config> {
 	pub custody_bits: MaxVec<bool, C::MaxValidatorsPerCommittee>,
 }
 
+#[derive(Codec, Encode, Decode, IntoTree, FromTree, Clone, PartialEq, Eq, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", serde(bound = "C: Config"))]
+#[cfg_attr(feature = "parity-codec", derive(parity_codec::Encode, parity_codec::Decode))]
+/// Aggregate attestation with the proof that its sender is an aggregator.
+pub struct AggregateAndProof<C: Config> {
+	/// Index of the aggregator
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub aggregator_index: Uint,
+	/// Aggregate attestation
+	pub aggregate: Attestation<C>,
+	/// Aggregator signature of the aggregate slot
+	pub selection_proof: Signature,
+}
+
+#[derive(Codec, Encode, Decode, IntoTree, FromTree, Clone, PartialEq, Eq, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(deny_unknown_fields))]
+#[cfg_attr(feature = "serde", serde(bound = "C: Config"))]
+#[cfg_attr(feature = "parity-codec", derive(parity_codec::Encode, parity_codec::Decode))]
+/// Aggregate and proof signed by the aggregator.
+pub struct SignedAggregateAndProof<C: Config> {
+	/// Aggregate and proof
+	pub message: AggregateAndProof<C>,
+	/// Aggregator signature
+	pub signature: Signature,
+}
+
 #[derive(Codec, Encode, Decode, IntoTree, FromTree, Clone, PartialEq, Eq, Default, Debug)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(deny_unknown_fields))]
 #[cfg_attr(feature = "parity-codec", derive(parity_codec::Encode, parity_codec::Decode))]
@@ -120,10 +147,10 @@ pub struct Deposit {
 /// Block voluntary exit.
 pub struct VoluntaryExit {
 	/// Minimum epoch for processing exit
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
-	pub epoch: Uint,
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub epoch: Epoch,
 	/// Index of the exiting validator
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
 	pub validator_index: Uint,
 	/// Validator signature
 	pub signature: Signature,
@@ -144,9 +171,9 @@ impl From<VoluntaryExit> for SigningVoluntaryExit {
 /// Unsealed voluntary exit transaction.
 pub struct SigningVoluntaryExit {
 	/// Minimum epoch for processing exit
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
-	pub epoch: Uint,
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
+	pub epoch: Epoch,
 	/// Index of the exiting validator
-	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_uint"))]
+	#[cfg_attr(feature = "serde", serde(with = "crate::utils::uint"))]
 	pub validator_index: Uint,
 }
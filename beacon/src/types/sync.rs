@@ -0,0 +1,50 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+//! Sync committee types of the Altair fork.
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+use ssz::{Codec, Encode, Decode};
+use bm_le::{IntoTree, FromTree};
+use vecarray::VecArray;
+use crate::AltairConfig;
+use crate::primitives::{Signature, ValidatorId};
+
+#[derive(Codec, Encode, Decode, IntoTree, FromTree, Clone, PartialEq, Eq, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(deny_unknown_fields))]
+#[cfg_attr(feature = "parity-codec", derive(parity_codec::Encode, parity_codec::Decode))]
+/// Sync committee of a sync committee period.
+pub struct SyncCommittee<C: AltairConfig> {
+	/// Public keys of the members, in committee order.
+	pub pubkeys: VecArray<ValidatorId, C::SyncCommitteeSize>,
+	/// Aggregate of all member public keys.
+	pub aggregate_pubkey: ValidatorId,
+}
+
+#[derive(Codec, Encode, Decode, IntoTree, FromTree, Clone, PartialEq, Eq, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(deny_unknown_fields))]
+#[cfg_attr(feature = "parity-codec", derive(parity_codec::Encode, parity_codec::Decode))]
+/// Sync committee signature over the previous block root, included in a
+/// block.
+pub struct SyncAggregate<C: AltairConfig> {
+	/// Participating members of the sync committee.
+	#[bm(compact)]
+	#[cfg_attr(feature = "serde", serde(serialize_with = "crate::utils::serialize_bitvector"))]
+	#[cfg_attr(feature = "serde", serde(deserialize_with = "crate::utils::deserialize_bitvector"))]
+	pub sync_committee_bits: VecArray<bool, C::SyncCommitteeSize>,
+	/// Aggregate signature of the participating members.
+	pub sync_committee_signature: Signature,
+}
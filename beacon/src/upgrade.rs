@@ -0,0 +1,185 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+//! Dispatch of the state transition over forks.
+//!
+//! Each fork with its own state transition has a variant in `ForkName`,
+//! `ForkAwareState` and `ForkAwareBlock`. States are upgraded at the first
+//! slot of the epoch a fork is scheduled at, after the epoch transition
+//! into it.
+
+use crate::primitives::Slot;
+use crate::types::{BeaconBlock, Fork};
+use crate::{BeaconState, BLSConfig, Config, Error, ForkSchedule, ScheduledFork, utils};
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+/// Forks with their own state transition, in activation order.
+pub enum ForkName {
+	/// Phase 0.
+	Phase0,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+/// Beacon state of any fork.
+pub enum ForkAwareState<C: Config> {
+	/// Phase 0 state.
+	Phase0(BeaconState<C>),
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+/// Beacon block of any fork.
+pub enum ForkAwareBlock<C: Config> {
+	/// Phase 0 block.
+	Phase0(BeaconBlock<C>),
+}
+
+impl<C: Config> From<BeaconState<C>> for ForkAwareState<C> {
+	fn from(state: BeaconState<C>) -> Self {
+		ForkAwareState::Phase0(state)
+	}
+}
+
+impl<C: Config> From<BeaconBlock<C>> for ForkAwareBlock<C> {
+	fn from(block: BeaconBlock<C>) -> Self {
+		ForkAwareBlock::Phase0(block)
+	}
+}
+
+impl<C: Config> ForkAwareBlock<C> {
+	/// Fork of the block.
+	pub fn fork_name(&self) -> ForkName {
+		match self {
+			ForkAwareBlock::Phase0(_) => ForkName::Phase0,
+		}
+	}
+
+	/// Slot of the block.
+	pub fn slot(&self) -> Slot {
+		match self {
+			ForkAwareBlock::Phase0(block) => block.slot,
+		}
+	}
+}
+
+impl<C: Config> ForkAwareState<C> {
+	/// Fork of the state transition of the state.
+	pub fn fork_name(&self) -> ForkName {
+		match self {
+			ForkAwareState::Phase0(_) => ForkName::Phase0,
+		}
+	}
+
+	/// Slot of the state.
+	pub fn slot(&self) -> Slot {
+		match self {
+			ForkAwareState::Phase0(state) => state.slot,
+		}
+	}
+
+	/// Fork recorded in the state.
+	pub fn fork(&self) -> &Fork {
+		match self {
+			ForkAwareState::Phase0(state) => &state.fork,
+		}
+	}
+
+	/// Phase 0 state, or `Error::ForkMismatch` for states of later forks.
+	pub fn into_phase0(self) -> Result<BeaconState<C>, Error> {
+		match self {
+			ForkAwareState::Phase0(state) => Ok(state),
+		}
+	}
+
+	/// Upgrade the state to a scheduled fork.
+	pub fn upgrade_to(self, fork: ScheduledFork) -> Result<Self, Error> {
+		if fork.name < self.fork_name() {
+			return Err(Error::ForkDowngrade)
+		}
+
+		match (self, fork.name) {
+			(ForkAwareState::Phase0(mut state), ForkName::Phase0) => {
+				state.fork = Fork {
+					previous_version: state.fork.current_version,
+					current_version: fork.version,
+					epoch: fork.epoch,
+				};
+				Ok(ForkAwareState::Phase0(state))
+			},
+		}
+	}
+
+	/// Process slots within the fork of the state, up to `target_slot`.
+	fn process_slots_within_fork(&mut self, target_slot: Slot) -> Result<(), Error> {
+		match self {
+			ForkAwareState::Phase0(state) => crate::initialize_block::<C>(state, target_slot),
+		}
+	}
+}
+
+/// Upgrade the state if a fork is scheduled at or before its epoch that it
+/// has not been upgraded to.
+fn upgrade_if_scheduled<C: Config>(
+	state: ForkAwareState<C>,
+	schedule: &ForkSchedule,
+) -> Result<ForkAwareState<C>, Error> {
+	let scheduled = schedule.fork_at(utils::epoch_of_slot::<C>(state.slot()));
+	if scheduled.epoch > state.fork().epoch {
+		state.upgrade_to(scheduled)
+	} else {
+		Ok(state)
+	}
+}
+
+/// Process slots up to `target_slot`, upgrading the state at the forks of
+/// the schedule on the way.
+pub fn process_slots_with_schedule<C: Config>(
+	state: ForkAwareState<C>,
+	target_slot: Slot,
+	schedule: &ForkSchedule,
+) -> Result<ForkAwareState<C>, Error> {
+	let mut state = upgrade_if_scheduled(state, schedule)?;
+	while state.slot() < target_slot {
+		let epoch = utils::epoch_of_slot::<C>(state.slot());
+		let until = schedule.next_fork(epoch)
+			.map(|fork| utils::start_slot_of_epoch::<C>(fork.epoch))
+			.filter(|slot| *slot < target_slot)
+			.unwrap_or(target_slot);
+
+		state.process_slots_within_fork(until)?;
+		state = upgrade_if_scheduled(state, schedule)?;
+	}
+	Ok(state)
+}
+
+/// Execute a block of any fork, upgrading the state at the forks of the
+/// schedule up to the block slot. The block must be of the fork scheduled
+/// at its slot.
+pub fn execute_block_with_schedule<C: Config, BLS: BLSConfig>(
+	block: &ForkAwareBlock<C>,
+	state: ForkAwareState<C>,
+	schedule: &ForkSchedule,
+) -> Result<ForkAwareState<C>, Error> {
+	let scheduled = schedule.fork_at(utils::epoch_of_slot::<C>(block.slot()));
+	if block.fork_name() != scheduled.name {
+		return Err(Error::ForkMismatch)
+	}
+
+	let mut state = process_slots_with_schedule(state, block.slot(), schedule)?;
+	match (&mut state, block) {
+		(ForkAwareState::Phase0(state), ForkAwareBlock::Phase0(block)) =>
+			crate::execute_block::<C, BLS>(block, state)?,
+	}
+	Ok(state)
+}
@@ -16,12 +16,15 @@
 
 #[cfg(feature = "serde")]
 mod serde;
+mod safe_arith;
 
+use alloc::vec::Vec;
 #[cfg(feature = "serde")]
 pub use self::serde::*;
+pub use self::safe_arith::{SafeArith, ArithError};
 
-use crate::{Config, Error};
-use crate::primitives::{H256, Uint, ValidatorIndex, Version};
+use crate::{Config, Error, consts};
+use crate::primitives::{H256, Uint, ValidatorIndex, Version, Signature, Slot, Epoch};
 use core::cmp::max;
 use core::convert::TryInto;
 
@@ -116,18 +119,92 @@ pub fn compute_committee<C: Config>(
 	}).collect::<Result<Vec<_>, Error>>()
 }
 
+/// Shuffle a whole list with swap-or-not in O(n * rounds), instead of
+/// computing `shuffled_index` for each position. The result satisfies
+/// `ret[i] == list[shuffled_index(i)]`.
+pub fn shuffle_list<C: Config, T>(
+	mut list: Vec<T>,
+	seed: H256,
+) -> Result<Vec<T>, Error> {
+	let len = list.len();
+	if len as u64 > 2u64.pow(40) {
+		return Err(Error::IndexOutOfRange)
+	}
+	if len == 0 {
+		return Ok(list)
+	}
+
+	// Each round swaps the pairs of positions adding up to `pivot` or
+	// `pivot + len`, which is its own inverse. Applying the rounds to the
+	// list in reverse order composes them as `shuffled_index` does.
+	for round in (0..C::shuffle_round_count()).rev() {
+		let round_bytes = round.to_le_bytes();
+		let round = &round_bytes[..1];
+		let pivot = (to_uint(
+			&C::hash(&[&seed[..], round])[..8]
+		) % len as u64) as usize;
+		let source = |position: usize| C::hash(&[
+			&seed[..],
+			round,
+			&(position as u64 / 256).to_le_bytes()[..4]
+		]);
+
+		swap_or_not(&mut list, 0, pivot, (pivot + 1) / 2, &source);
+		swap_or_not(&mut list, pivot + 1, len - 1, (len - pivot - 1) / 2, &source);
+	}
+
+	Ok(list)
+}
+
+/// Swap `count` pairs of positions, from `(i, j)` inward, if the bit of
+/// position `j` in its source hash is set.
+fn swap_or_not<T, F: Fn(usize) -> H256>(
+	list: &mut [T],
+	mut i: usize,
+	mut j: usize,
+	count: usize,
+	source: &F,
+) {
+	let mut cached: Option<(usize, H256)> = None;
+	for _ in 0..count {
+		let hash = match cached {
+			Some((window, hash)) if window == j / 256 => hash,
+			_ => {
+				let hash = source(j);
+				cached = Some((j / 256, hash));
+				hash
+			},
+		};
+		let byte = hash[(j % 256) / 8];
+		if (byte >> (j % 8)) % 2 != 0 {
+			list.swap(i, j);
+		}
+		i += 1;
+		j -= 1;
+	}
+}
+
+/// Compute the shuffled list of indices, in committee order. Committees
+/// returned by `compute_committee` are consecutive slices of it.
+pub fn compute_shuffled_indices<C: Config>(
+	indices: &[ValidatorIndex],
+	seed: H256,
+) -> Result<Vec<ValidatorIndex>, Error> {
+	shuffle_list::<C, _>(indices.to_vec(), seed)
+}
+
 /// Get epoch of slot.
-pub fn epoch_of_slot<C: Config>(slot: Uint) -> Uint {
-	slot / C::slots_per_epoch()
+pub fn epoch_of_slot<C: Config>(slot: Slot) -> Epoch {
+	slot.epoch(C::slots_per_epoch())
 }
 
 /// Get start slot of epoch.
-pub fn start_slot_of_epoch<C: Config>(epoch: Uint) -> Uint {
-	epoch * C::slots_per_epoch()
+pub fn start_slot_of_epoch<C: Config>(epoch: Epoch) -> Slot {
+	epoch.start_slot(C::slots_per_epoch())
 }
 
 /// Get activation exit epoch.
-pub fn activation_exit_epoch<C: Config>(epoch: Uint) -> Uint {
+pub fn activation_exit_epoch<C: Config>(epoch: Epoch) -> Epoch {
 	epoch + 1 + C::max_seed_lookahead()
 }
 
@@ -158,6 +235,61 @@ pub fn is_valid_merkle_branch<C: Config>(
 	compact.root::<bm_le::DigestConstruct<C::Digest>>().0 == root
 }
 
+/// Compute the merkle branch of a leaf, in a tree of given depth with
+/// missing leaves set to zero.
+pub fn merkle_branch<C: Config>(
+	leaves: &[H256], depth: usize, mut index: usize
+) -> Vec<H256> {
+	let mut layer = leaves.to_vec();
+	let mut zero = H256::default();
+	let mut branch = Vec::new();
+
+	for _ in 0..depth {
+		branch.push(layer.get(index ^ 1).cloned().unwrap_or(zero));
+		layer = layer.chunks(2).map(|pair| {
+			C::hash(&[
+				pair[0].as_ref(),
+				pair.get(1).unwrap_or(&zero).as_ref(),
+			])
+		}).collect();
+		zero = C::hash(&[zero.as_ref(), zero.as_ref()]);
+		index /= 2;
+	}
+
+	branch
+}
+
+/// Compute the merkle root of leaves, in a tree of given depth with missing
+/// leaves set to zero.
+pub fn merkle_root<C: Config>(leaves: &[H256], depth: usize) -> H256 {
+	let mut layer = leaves.to_vec();
+	let mut zero = H256::default();
+
+	for _ in 0..depth {
+		layer = layer.chunks(2).map(|pair| {
+			C::hash(&[
+				pair[0].as_ref(),
+				pair.get(1).unwrap_or(&zero).as_ref(),
+			])
+		}).collect();
+		zero = C::hash(&[zero.as_ref(), zero.as_ref()]);
+	}
+
+	layer.get(0).cloned().unwrap_or(zero)
+}
+
+/// Mix in list length to a merkle root.
+pub fn mix_in_length<C: Config>(root: H256, length: Uint) -> H256 {
+	C::hash(&[root.as_ref(), to_bytes(length).as_ref()])
+}
+
+/// Whether the validator with the given selection proof, its signature of
+/// the slot, aggregates for a committee of the given size.
+pub fn is_aggregator<C: Config>(committee_len: usize, selection_proof: &Signature) -> bool {
+	let modulo = max(1, committee_len as Uint / consts::TARGET_AGGREGATORS_PER_COMMITTEE);
+	to_uint(&C::hash(&[selection_proof.as_ref()])[0..8]) % modulo == 0
+}
+
 /// BLS signing domain given a domain type and fork version.
 pub fn bls_domain(domain_type: u32, fork_version: Version) -> u64 {
 	let mut bytes = [0u8; 8];
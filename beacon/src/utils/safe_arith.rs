@@ -0,0 +1,115 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Checked arithmetic for state transition math.
+
+use crate::Error;
+use crate::primitives::{Gwei, Slot, Epoch};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+/// Arithmetic overflowed, underflowed or divided by zero.
+pub struct ArithError;
+
+impl From<ArithError> for Error {
+	fn from(_: ArithError) -> Error {
+		Error::ArithmeticOverflow
+	}
+}
+
+/// Arithmetic returning an error, instead of panicking or wrapping, on
+/// overflow, underflow and division by zero.
+pub trait SafeArith: Sized + Copy {
+	/// Safe addition.
+	fn safe_add<T: Into<Self>>(self, other: T) -> Result<Self, ArithError>;
+	/// Safe subtraction.
+	fn safe_sub<T: Into<Self>>(self, other: T) -> Result<Self, ArithError>;
+	/// Safe multiplication by a factor.
+	fn safe_mul(self, other: u64) -> Result<Self, ArithError>;
+	/// Safe division by a divisor.
+	fn safe_div(self, other: u64) -> Result<Self, ArithError>;
+	/// Safe multiplication by a factor then division by a divisor, without
+	/// overflowing on the intermediate product.
+	fn safe_mul_div(self, numerator: u64, denominator: u64) -> Result<Self, ArithError>;
+
+	/// Safe addition in place.
+	fn safe_add_assign<T: Into<Self>>(&mut self, other: T) -> Result<(), ArithError> {
+		*self = self.safe_add(other)?;
+		Ok(())
+	}
+
+	/// Safe subtraction in place.
+	fn safe_sub_assign<T: Into<Self>>(&mut self, other: T) -> Result<(), ArithError> {
+		*self = self.safe_sub(other)?;
+		Ok(())
+	}
+}
+
+impl SafeArith for u64 {
+	fn safe_add<T: Into<Self>>(self, other: T) -> Result<Self, ArithError> {
+		self.checked_add(other.into()).ok_or(ArithError)
+	}
+
+	fn safe_sub<T: Into<Self>>(self, other: T) -> Result<Self, ArithError> {
+		self.checked_sub(other.into()).ok_or(ArithError)
+	}
+
+	fn safe_mul(self, other: u64) -> Result<Self, ArithError> {
+		self.checked_mul(other).ok_or(ArithError)
+	}
+
+	fn safe_div(self, other: u64) -> Result<Self, ArithError> {
+		self.checked_div(other).ok_or(ArithError)
+	}
+
+	fn safe_mul_div(self, numerator: u64, denominator: u64) -> Result<Self, ArithError> {
+		let value = (self as u128 * numerator as u128)
+			.checked_div(denominator as u128).ok_or(ArithError)?;
+		if value > u64::max_value() as u128 {
+			return Err(ArithError)
+		}
+		Ok(value as u64)
+	}
+}
+
+macro_rules! impl_safe_arith {
+	( $t:ty ) => {
+		impl SafeArith for $t {
+			fn safe_add<T: Into<Self>>(self, other: T) -> Result<Self, ArithError> {
+				self.checked_add(other).ok_or(ArithError)
+			}
+
+			fn safe_sub<T: Into<Self>>(self, other: T) -> Result<Self, ArithError> {
+				self.checked_sub(other).ok_or(ArithError)
+			}
+
+			fn safe_mul(self, other: u64) -> Result<Self, ArithError> {
+				self.checked_mul(other).ok_or(ArithError)
+			}
+
+			fn safe_div(self, other: u64) -> Result<Self, ArithError> {
+				self.checked_div(other).ok_or(ArithError)
+			}
+
+			fn safe_mul_div(self, numerator: u64, denominator: u64) -> Result<Self, ArithError> {
+				Ok(Self::new(self.as_u64().safe_mul_div(numerator, denominator)?))
+			}
+		}
+	}
+}
+
+impl_safe_arith!(Gwei);
+impl_safe_arith!(Slot);
+impl_safe_arith!(Epoch);
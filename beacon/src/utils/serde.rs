@@ -14,7 +14,11 @@
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
 
-use serde::{Serializer, Deserializer};
+use alloc::vec::Vec;
+use alloc::string::{String, ToString};
+use alloc::format;
+use serde::{Serializer, Deserializer, Deserialize};
+use serde::ser::SerializeSeq;
 use serde::de::{Error, Visitor};
 use impl_serde::serialize;
 use core::fmt;
@@ -84,10 +88,10 @@ pub fn deserialize_bitvector<'a, 'de, L: typenum::Unsigned, D: Deserializer<'de>
 	vecarray::VecArray::try_from(seq).map_err(|_| D::Error::custom("Invalid bitlist"))
 }
 
-/// Deserialize u64 or string.
-pub fn deserialize_uint<'a, 'de, D: Deserializer<'de>>(
+/// Deserialize u64 or string, into u64 or one of the typed integers.
+pub fn deserialize_uint<'a, 'de, T: From<u64>, D: Deserializer<'de>>(
 	deserializer: D
-) -> Result<u64, D::Error> {
+) -> Result<T, D::Error> {
 	struct UintVisitor;
 
 	impl<'a> Visitor<'a> for UintVisitor {
@@ -121,5 +125,61 @@ pub fn deserialize_uint<'a, 'de, D: Deserializer<'de>>(
 		}
 	}
 
-	deserializer.deserialize_any(UintVisitor)
+	deserializer.deserialize_any(UintVisitor).map(T::from)
+}
+
+/// Serialize u64, or one of the typed integers, as a decimal string, as in
+/// the Eth2 API.
+pub fn serialize_uint<T: Copy + Into<u64>, S: Serializer>(
+	value: &T,
+	serializer: S
+) -> Result<S::Ok, S::Error> {
+	serializer.serialize_str(&(*value).into().to_string())
+}
+
+/// Serialize a sequence of u64 as decimal strings.
+pub fn serialize_uint_list<T: AsRef<[u64]>, S: Serializer>(
+	value: &T,
+	serializer: S
+) -> Result<S::Ok, S::Error> {
+	let mut seq = serializer.serialize_seq(Some(value.as_ref().len()))?;
+	for item in value.as_ref() {
+		seq.serialize_element(&item.to_string())?;
+	}
+	seq.end()
+}
+
+#[derive(Deserialize)]
+struct UintValue(#[serde(deserialize_with = "deserialize_uint")] u64);
+
+/// Deserialize a sequence of u64 or strings.
+pub fn deserialize_uint_list<'a, 'de, T: From<Vec<u64>>, D: Deserializer<'de>>(
+	deserializer: D
+) -> Result<T, D::Error> {
+	let values = Vec::<UintValue>::deserialize(deserializer)?;
+	Ok(T::from(values.into_iter().map(|v| v.0).collect::<Vec<_>>()))
+}
+
+/// Deserialize a fixed-length sequence of u64 or strings.
+pub fn deserialize_uint_vector<'a, 'de, L: typenum::Unsigned, D: Deserializer<'de>>(
+	deserializer: D
+) -> Result<vecarray::VecArray<u64, L>, D::Error> {
+	let values = Vec::<UintValue>::deserialize(deserializer)?;
+	vecarray::VecArray::try_from(values.into_iter().map(|v| v.0).collect::<Vec<_>>())
+		.map_err(|_| D::Error::custom("Invalid vector length"))
+}
+
+/// Serde helpers for u64 fields, serialized as decimal strings.
+pub mod uint {
+	pub use super::{serialize_uint as serialize, deserialize_uint as deserialize};
+}
+
+/// Serde helpers for lists of u64, serialized as decimal strings.
+pub mod uint_list {
+	pub use super::{serialize_uint_list as serialize, deserialize_uint_list as deserialize};
+}
+
+/// Serde helpers for vectors of u64, serialized as decimal strings.
+pub mod uint_vector {
+	pub use super::{serialize_uint_list as serialize, deserialize_uint_vector as deserialize};
 }
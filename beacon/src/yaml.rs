@@ -0,0 +1,85 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+//! YAML (de)serialization in the format of the eth2 spec tests and testnet
+//! genesis files.
+//!
+//! Integers are accepted both as numbers and as decimal or hex strings, and
+//! are written as numbers, unlike JSON where they are decimal strings.
+
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_yaml::Value;
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use std::path::Path;
+
+pub use serde_yaml::Error;
+
+/// Parse a value from a YAML string.
+pub fn from_str<T: DeserializeOwned>(s: &str) -> Result<T, Error> {
+	serde_yaml::from_str(s)
+}
+
+/// Parse a value from YAML bytes.
+pub fn from_slice<T: DeserializeOwned>(v: &[u8]) -> Result<T, Error> {
+	serde_yaml::from_slice(v)
+}
+
+/// Parse a value from a YAML reader.
+pub fn from_reader<R: Read, T: DeserializeOwned>(reader: R) -> Result<T, Error> {
+	serde_yaml::from_reader(reader)
+}
+
+/// Parse a value from a YAML file, such as a spec test `pre.yaml` or a
+/// testnet `genesis.yaml`.
+pub fn from_file<P: AsRef<Path>, T: DeserializeOwned>(path: P) -> Result<T, Error> {
+	let file = File::open(path).map_err(serde::de::Error::custom)?;
+	from_reader(BufReader::new(file))
+}
+
+/// Serialize a value as a YAML string.
+pub fn to_string<T: Serialize>(value: &T) -> Result<String, Error> {
+	serde_yaml::to_string(&to_value(value)?)
+}
+
+/// Serialize a value as YAML to a writer.
+pub fn to_writer<W: Write, T: Serialize>(writer: W, value: &T) -> Result<(), Error> {
+	serde_yaml::to_writer(writer, &to_value(value)?)
+}
+
+fn to_value<T: Serialize>(value: &T) -> Result<Value, Error> {
+	Ok(numeric_uints(serde_yaml::to_value(value)?))
+}
+
+/// Turn integers serialized as decimal strings back into numbers. Other
+/// strings, such as hex-encoded roots and keys, are kept.
+fn numeric_uints(value: Value) -> Value {
+	match value {
+		Value::String(s) => {
+			let is_decimal = !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()) &&
+				(s == "0" || !s.starts_with('0'));
+			match s.parse::<u64>() {
+				Ok(number) if is_decimal => Value::Number(number.into()),
+				_ => Value::String(s),
+			}
+		},
+		Value::Sequence(seq) => Value::Sequence(seq.into_iter().map(numeric_uints).collect()),
+		Value::Mapping(map) => Value::Mapping(
+			map.into_iter().map(|(k, v)| (k, numeric_uints(v))).collect()
+		),
+		value => value,
+	}
+}
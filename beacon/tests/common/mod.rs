@@ -0,0 +1,96 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Beacon state fixtures shared by the integration tests.
+
+#![allow(dead_code)]
+
+use beacon::{Config, BeaconState, BeaconExecutive};
+use beacon::primitives::{ValidatorId, Slot, Epoch, Gwei};
+use beacon::types::{AttestationData, Checkpoint, PendingAttestation, Validator};
+use beacon::consts::FAR_FUTURE_EPOCH;
+use beacon::utils::start_slot_of_epoch;
+use bm_le::MaxVec;
+
+/// Validator active since genesis, with the given public key seed and
+/// effective balance.
+pub fn validator(pubkey: u64, effective_balance: Gwei) -> Validator {
+	Validator {
+		pubkey: ValidatorId::from_low_u64_be(pubkey),
+		effective_balance,
+		activation_eligibility_epoch: Epoch::new(0),
+		activation_epoch: Epoch::new(0),
+		exit_epoch: FAR_FUTURE_EPOCH,
+		withdrawable_epoch: FAR_FUTURE_EPOCH,
+		..Default::default()
+	}
+}
+
+/// Genesis state with one validator per effective balance, whose balance
+/// equals its effective balance.
+pub fn state<C: Config, I: IntoIterator<Item=Gwei>>(effective_balances: I) -> BeaconState<C> {
+	let mut state = BeaconState::<C>::default();
+	for (index, effective_balance) in effective_balances.into_iter().enumerate() {
+		state.validators.push(validator(index as u64, effective_balance));
+		state.balances.push(effective_balance.as_u64());
+	}
+	state
+}
+
+/// Process slots up to the last slot of `epoch`.
+pub fn advance_to_last_slot<C: Config>(state: &mut BeaconState<C>, epoch: u64) {
+	beacon::initialize_block::<C>(state, Slot::new((epoch + 1) * C::slots_per_epoch() - 1))
+		.expect("Advancing state failed");
+}
+
+/// One attestation per committee of the previous epoch, voting for the
+/// matching source, target and head, included with the minimal delay by
+/// `proposer_index`. Committee members at positions for which `attesting`
+/// is true are set in the aggregation bits.
+pub fn matching_attestations<C: Config, F: Fn(usize) -> bool>(
+	state: &mut BeaconState<C>,
+	attesting: F,
+	proposer_index: u64,
+) -> Vec<PendingAttestation<C>> {
+	let executive = BeaconExecutive::new(state);
+	let previous_epoch = executive.previous_epoch();
+	let target_root = executive.block_root(previous_epoch).expect("Epoch is in range");
+	let start_slot = start_slot_of_epoch::<C>(previous_epoch);
+
+	let mut attestations = Vec::new();
+	for slot in (0..C::slots_per_epoch()).map(|offset| start_slot + offset) {
+		for index in 0..executive.committee_count_at_slot(slot) {
+			let committee = executive.beacon_committee(slot, index)
+				.expect("Committee is in range");
+			attestations.push(PendingAttestation {
+				aggregation_bits: MaxVec::from(
+					(0..committee.len()).map(&attesting).collect::<Vec<_>>()
+				),
+				data: AttestationData {
+					slot,
+					index,
+					beacon_block_root: executive.block_root_at_slot(slot)
+						.expect("Slot is in range"),
+					source: executive.current_justified_checkpoint.clone(),
+					target: Checkpoint { epoch: previous_epoch, root: target_root },
+				},
+				inclusion_delay: C::min_attestation_inclusion_delay(),
+				proposer_index,
+			});
+		}
+	}
+	attestations
+}
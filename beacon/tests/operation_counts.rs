@@ -0,0 +1,94 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Blocks with more operations than allowed must be rejected before any of
+//! their signatures is verified.
+
+use beacon::{Config, MinimalConfig, BLSConfig, BeaconState, BeaconExecutive, Error};
+use beacon::primitives::{H256, ValidatorId, Signature};
+use beacon::types::{BeaconBlock, BeaconBlockBody, ProposerSlashing, VoluntaryExit};
+use bm_le::MaxVec;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+type C = MinimalConfig;
+
+static VERIFICATIONS: AtomicUsize = AtomicUsize::new(0);
+
+/// BLS configuration counting every signature verification.
+#[derive(Default, Clone)]
+struct CountingBLS;
+
+impl BLSConfig for CountingBLS {
+	fn verify(_pubkey: &ValidatorId, _message: &H256, _signature: &Signature, _domain: u64) -> bool {
+		VERIFICATIONS.fetch_add(1, Ordering::SeqCst);
+		true
+	}
+	fn aggregate_pubkeys(_pubkeys: &[ValidatorId]) -> ValidatorId {
+		ValidatorId::default()
+	}
+	fn aggregate_signatures(_signatures: &[Signature]) -> Signature {
+		Signature::default()
+	}
+	fn verify_multiple(_pubkeys: &[ValidatorId], _messages: &[H256], _signature: &Signature, _domain: u64) -> bool {
+		VERIFICATIONS.fetch_add(1, Ordering::SeqCst);
+		true
+	}
+}
+
+fn oversized_exits() -> BeaconBlockBody<C> {
+	BeaconBlockBody {
+		voluntary_exits: MaxVec::from(
+			vec![VoluntaryExit::default(); C::max_voluntary_exits() as usize + 1]
+		),
+		..Default::default()
+	}
+}
+
+fn oversized_proposer_slashings() -> BeaconBlockBody<C> {
+	BeaconBlockBody {
+		proposer_slashings: MaxVec::from(
+			vec![ProposerSlashing::default(); C::max_proposer_slashings() as usize + 1]
+		),
+		..Default::default()
+	}
+}
+
+#[test]
+fn block_signatures_are_not_verified_for_oversized_blocks() {
+	let state = BeaconState::<C>::default();
+	for (body, error) in vec![
+		(oversized_exits(), Error::TooManyVoluntaryExits),
+		(oversized_proposer_slashings(), Error::TooManyProposerSlashings),
+	] {
+		let block = BeaconBlock { body, ..Default::default() };
+		assert_eq!(
+			beacon::verify_block_signatures::<C, CountingBLS>(&block, &state),
+			Err(error),
+		);
+	}
+	assert_eq!(VERIFICATIONS.load(Ordering::SeqCst), 0);
+}
+
+#[test]
+fn operation_signatures_are_not_verified_for_oversized_bodies() {
+	let mut state = BeaconState::<C>::default();
+	let mut executive = BeaconExecutive::new(&mut state);
+	assert_eq!(
+		executive.process_operations::<CountingBLS>(&oversized_exits()),
+		Err(Error::TooManyVoluntaryExits),
+	);
+	assert_eq!(VERIFICATIONS.load(Ordering::SeqCst), 0);
+}
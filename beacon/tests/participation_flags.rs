@@ -0,0 +1,144 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Tests of the participation flag reward scheme, as selected by
+//! `ParticipationFlagConfig`, and of its reward projection.
+
+use beacon::{
+	Config, MinimalConfig, ParticipationFlagConfig, BeaconState, BeaconExecutive, RewardScheme,
+	DefaultRewardScheme, ParticipationFlagRewardScheme, PARTICIPATION_BASIS_POINTS,
+};
+use beacon::primitives::{Epoch, Gwei};
+use beacon::consts::{
+	TIMELY_SOURCE_WEIGHT, TIMELY_TARGET_WEIGHT, TIMELY_HEAD_WEIGHT,
+	PROPOSER_WEIGHT, WEIGHT_DENOMINATOR,
+};
+use beacon::utils::integer_squareroot;
+use bm_le::MaxVec;
+
+mod common;
+
+type C = ParticipationFlagConfig<MinimalConfig>;
+
+const VALIDATORS: u64 = 64;
+/// Epoch the tested transitions end.
+const EPOCH: u64 = 4;
+const PROPOSER: u64 = 0;
+
+/// State in the last slot of `EPOCH`, with validators of maximum effective
+/// balance, where members at even committee positions attested timely in
+/// the previous epoch.
+fn state() -> BeaconState<C> {
+	let mut state = common::state::<C, _>(
+		(0..VALIDATORS).map(|_| C::max_effective_balance())
+	);
+	common::advance_to_last_slot(&mut state, EPOCH);
+	state.finalized_checkpoint.epoch = Epoch::new(EPOCH - 2);
+	state.previous_epoch_attestations = MaxVec::from(
+		common::matching_attestations(&mut state, |position| position % 2 == 0, PROPOSER)
+	);
+	state
+}
+
+/// Base reward of a validator of maximum effective balance.
+fn base_reward() -> Gwei {
+	let total_balance = C::max_effective_balance() * VALIDATORS;
+	C::effective_balance_increment() * C::base_reward_factor() /
+		integer_squareroot(total_balance.as_u64()) *
+		(C::max_effective_balance() / C::effective_balance_increment())
+}
+
+fn proposer_reward_denominator() -> u64 {
+	(WEIGHT_DENOMINATOR - PROPOSER_WEIGHT) * WEIGHT_DENOMINATOR / PROPOSER_WEIGHT
+}
+
+#[test]
+fn config_selects_participation_flag_scheme() {
+	let mut state = state();
+	let executive = BeaconExecutive::new(&mut state);
+
+	let breakdowns = executive.reward_breakdown().unwrap();
+	assert_eq!(breakdowns, ParticipationFlagRewardScheme::reward_breakdown(&executive).unwrap());
+	assert_ne!(breakdowns, DefaultRewardScheme::reward_breakdown(&executive).unwrap());
+}
+
+#[test]
+fn flags_reward_participants_and_penalize_missed_source_and_target() {
+	let mut state = state();
+	let executive = BeaconExecutive::new(&mut state);
+	let participation = executive.unslashed_attesting_participation(
+		&executive.matching_source_attestations(executive.previous_epoch()).unwrap()
+	).unwrap();
+	let attesters = participation.indices().collect::<Vec<_>>();
+	assert_eq!(attesters.len() as u64, VALIDATORS / 2);
+
+	let base = base_reward();
+	let active_increments = VALIDATORS * (C::max_effective_balance() / C::effective_balance_increment());
+	let participating_increments = active_increments / 2;
+	let flag_reward = |weight: u64| {
+		base * weight * participating_increments / (active_increments * WEIGHT_DENOMINATOR)
+	};
+	let breakdowns = executive.reward_breakdown().unwrap();
+	for (index, breakdown) in breakdowns.iter().enumerate() {
+		if attesters.contains(&(index as u64)) {
+			assert_eq!(breakdown.source_reward, flag_reward(TIMELY_SOURCE_WEIGHT));
+			assert_eq!(breakdown.target_reward, flag_reward(TIMELY_TARGET_WEIGHT));
+			assert_eq!(breakdown.head_reward, flag_reward(TIMELY_HEAD_WEIGHT));
+			assert_eq!(breakdown.penalty().unwrap(), Gwei::new(0));
+		} else {
+			assert_eq!(breakdown.reward().unwrap(), if index as u64 == PROPOSER {
+				breakdown.proposer_reward
+			} else {
+				Gwei::new(0)
+			});
+			assert_eq!(breakdown.source_penalty, base * TIMELY_SOURCE_WEIGHT / WEIGHT_DENOMINATOR);
+			assert_eq!(breakdown.target_penalty, base * TIMELY_TARGET_WEIGHT / WEIGHT_DENOMINATOR);
+			assert_eq!(breakdown.head_penalty, Gwei::new(0));
+			assert_eq!(breakdown.inactivity_penalty, Gwei::new(0));
+		}
+	}
+
+	let flag_weights = TIMELY_SOURCE_WEIGHT + TIMELY_TARGET_WEIGHT + TIMELY_HEAD_WEIGHT;
+	assert_eq!(
+		breakdowns[PROPOSER as usize].proposer_reward,
+		base * flag_weights / proposer_reward_denominator() * attesters.len() as u64,
+	);
+}
+
+#[test]
+fn projection_follows_participation_flag_scheme() {
+	let mut state = state();
+	let executive = BeaconExecutive::new(&mut state);
+	let base = base_reward();
+	let flag_weights = TIMELY_SOURCE_WEIGHT + TIMELY_TARGET_WEIGHT + TIMELY_HEAD_WEIGHT;
+
+	for projection in executive.project_rewards(PARTICIPATION_BASIS_POINTS).unwrap() {
+		assert_eq!(
+			projection.epoch_reward,
+			base * flag_weights / proposer_reward_denominator() +
+				base * flag_weights / WEIGHT_DENOMINATOR,
+		);
+		assert_eq!(projection.epoch_penalty, Gwei::new(0));
+	}
+
+	for projection in executive.project_rewards(0).unwrap() {
+		assert_eq!(projection.epoch_reward, Gwei::new(0));
+		assert_eq!(
+			projection.epoch_penalty,
+			base * (TIMELY_SOURCE_WEIGHT + TIMELY_TARGET_WEIGHT) / WEIGHT_DENOMINATOR,
+		);
+	}
+}
@@ -0,0 +1,181 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Merkle proofs of validator records, checked against the state tree
+//! root, and of balance deltas, checked against a reference ssz root.
+
+use beacon::{
+	Config, MinimalConfig, BeaconState, BeaconExecutive, ValidatorProof, BalanceDeltas, Error,
+};
+use beacon::primitives::{H256, Slot, Epoch, Gwei};
+use sha2::{Digest, Sha256};
+
+mod common;
+
+type C = MinimalConfig;
+
+const VALIDATORS: u64 = 11;
+
+/// State with distinct validator records and a few non-default fields.
+fn state() -> BeaconState<C> {
+	let mut state = common::state::<C, _>((0..VALIDATORS).map(|_| C::max_effective_balance()));
+	state.genesis_time = 1_578_009_600;
+	state.slot = Slot::new(3 * C::slots_per_epoch() + 2);
+	state.eth1_deposit_index = VALIDATORS;
+	for (i, validator) in state.validators.iter_mut().enumerate() {
+		validator.withdrawal_credentials = H256::from_low_u64_be(i as u64 * 7);
+		validator.activation_epoch = Epoch::new(i as u64 % 3);
+	}
+	for (i, balance) in state.balances.iter_mut().enumerate() {
+		*balance += i as u64;
+	}
+	state
+}
+
+#[test]
+fn validator_proofs_verify_against_state_root() {
+	let mut state = state();
+	let state_root = bm_le::tree_root::<<C as Config>::Digest, _>(&state);
+	let executive = BeaconExecutive::new(&mut state);
+
+	for index in vec![0, 1, 5, VALIDATORS - 2, VALIDATORS - 1] {
+		let proof = executive.validator_proof(index).expect("Index is in range");
+		assert_eq!(proof.state_root, state_root);
+		assert_eq!(proof.branch.len(), ValidatorProof::depth::<C>());
+		assert_eq!(proof.validator, executive.validators[index as usize]);
+		assert!(proof.verify::<C>(), "proof of validator {} is invalid", index);
+	}
+}
+
+#[test]
+fn validator_proofs_reject_tampering() {
+	let mut state = state();
+	let executive = BeaconExecutive::new(&mut state);
+	let proof = executive.validator_proof(5).expect("Index is in range");
+
+	let mut wrong_index = proof.clone();
+	wrong_index.index = 6;
+	assert!(!wrong_index.verify::<C>());
+
+	let mut wrong_record = proof.clone();
+	wrong_record.validator.slashed = true;
+	assert!(!wrong_record.verify::<C>());
+
+	let mut wrong_root = proof.clone();
+	wrong_root.state_root = H256::from_low_u64_be(1);
+	assert!(!wrong_root.verify::<C>());
+
+	let mut wrong_branch = proof.clone();
+	wrong_branch.branch.pop();
+	assert!(!wrong_branch.verify::<C>());
+}
+
+#[test]
+fn validator_proof_of_unknown_index_fails() {
+	let mut state = state();
+	let executive = BeaconExecutive::new(&mut state);
+	assert_eq!(executive.validator_proof(VALIDATORS), Err(Error::IndexOutOfRange));
+}
+
+fn sha256(left: &[u8], right: &[u8]) -> H256 {
+	let mut digest = Sha256::new();
+	digest.input(left);
+	digest.input(right);
+	H256::from_slice(digest.result().as_slice())
+}
+
+fn uint_chunk(value: u64) -> [u8; 32] {
+	let mut chunk = [0u8; 32];
+	chunk[..8].copy_from_slice(&value.to_le_bytes());
+	chunk
+}
+
+/// Reference root of the deltas as an ssz list of `(reward, penalty)`
+/// containers, limited to the next power of two of its length.
+fn reference_root(deltas: &BalanceDeltas) -> H256 {
+	let count = deltas.rewards.len();
+	let mut layer = deltas.rewards.iter().zip(deltas.penalties.iter())
+		.map(|(reward, penalty)| sha256(
+			&uint_chunk(reward.as_u64()), &uint_chunk(penalty.as_u64())
+		))
+		.collect::<Vec<_>>();
+	layer.resize(count.next_power_of_two(), H256::default());
+	while layer.len() > 1 {
+		layer = layer.chunks(2)
+			.map(|pair| sha256(pair[0].as_ref(), pair[1].as_ref()))
+			.collect();
+	}
+	sha256(layer[0].as_ref(), &uint_chunk(count as u64))
+}
+
+fn deltas(count: u64) -> BalanceDeltas {
+	BalanceDeltas {
+		epoch: Epoch::new(4),
+		rewards: (0..count).map(|i| Gwei::new(1_000 * i + 17)).collect(),
+		penalties: (0..count).map(|i| Gwei::new(if i % 3 == 0 { 250 * i } else { 0 })).collect(),
+	}
+}
+
+#[test]
+fn balance_delta_proofs_verify_against_commitment_root() {
+	for count in vec![1, 2, 5, 8, 13] {
+		let deltas = deltas(count);
+		let commitment = deltas.commitment::<C>();
+		assert_eq!(commitment.epoch, deltas.epoch);
+		assert_eq!(commitment.count, count);
+		assert_eq!(commitment.root, reference_root(&deltas));
+
+		for index in 0..count {
+			let proof = deltas.proof::<C>(index).expect("Index is in range");
+			assert_eq!(proof.reward, deltas.rewards[index as usize]);
+			assert_eq!(proof.penalty, deltas.penalties[index as usize]);
+			assert!(
+				proof.verify::<C>(&commitment),
+				"proof of delta {} out of {} is invalid", index, count,
+			);
+		}
+	}
+}
+
+#[test]
+fn balance_delta_proofs_reject_tampering() {
+	let deltas = deltas(5);
+	let commitment = deltas.commitment::<C>();
+	let proof = deltas.proof::<C>(3).expect("Index is in range");
+
+	let mut wrong_index = proof.clone();
+	wrong_index.index = 2;
+	assert!(!wrong_index.verify::<C>(&commitment));
+
+	let mut wrong_reward = proof.clone();
+	wrong_reward.reward += Gwei::new(1);
+	assert!(!wrong_reward.verify::<C>(&commitment));
+
+	let mut wrong_penalty = proof.clone();
+	wrong_penalty.penalty = Gwei::new(0);
+	assert!(!wrong_penalty.verify::<C>(&commitment));
+
+	let mut wrong_count = commitment.clone();
+	wrong_count.count = 16;
+	assert!(!proof.verify::<C>(&wrong_count));
+
+	assert!(!proof.verify::<C>(&self::deltas(6).commitment::<C>()));
+}
+
+#[test]
+fn balance_delta_proof_of_unknown_index_fails() {
+	assert_eq!(deltas(5).proof::<C>(5), Err(Error::IndexOutOfRange));
+}
@@ -0,0 +1,356 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Property tests of the epoch rewards and penalties, comparing
+//! `reward_breakdown` against a straightforward reference implementation
+//! across random attestation sets, slashings and finality delays.
+//!
+//! The number of cases is set by `REWARD_CASES`, and the random seed by
+//! `REWARD_SEED`. Large registry tests check that rewards stay exact, and
+//! that the parallel component path matches, beyond 64-bit intermediates.
+
+use beacon::{Config, MinimalConfig, BeaconState, BeaconExecutive, Error};
+use beacon::primitives::{H256, Slot, Epoch};
+use beacon::types::{AttestationData, Checkpoint, PendingAttestation};
+use beacon::consts::BASE_REWARDS_PER_EPOCH;
+use beacon::components::reward;
+use beacon::utils::{integer_squareroot, start_slot_of_epoch};
+use bm_le::MaxVec;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use std::collections::{BTreeMap, BTreeSet};
+
+mod common;
+
+type C = MinimalConfig;
+
+const VALIDATORS: u64 = 64;
+/// Epoch the tested transitions end, late enough for the inactivity leak.
+const EPOCH: u64 = 8;
+const DEFAULT_CASES: u64 = 32;
+/// Validators of the large registry, all at maximum effective balance. The
+/// product of a base reward and their total balance overflows 64 bits.
+const LARGE_VALIDATORS: u64 = 120_000;
+
+fn env_u64(name: &str, default: u64) -> u64 {
+	std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+/// State in the last slot of `EPOCH`, with validators of varying effective
+/// balance.
+fn base_state(rng: &mut StdRng) -> BeaconState<C> {
+	let increments = C::max_effective_balance() / C::effective_balance_increment();
+	let mut state = common::state::<C, _>((0..VALIDATORS).map(|_| {
+		rng.gen_range(increments / 2, increments + 1) * C::effective_balance_increment()
+	}));
+	common::advance_to_last_slot(&mut state, EPOCH);
+	state
+}
+
+/// State in the last slot of epoch 1 with `LARGE_VALIDATORS` validators, all
+/// of which attested timely for the matching source, target and head. The
+/// slot is set directly, as processing slots would hash the whole registry
+/// at each of them.
+fn large_state() -> BeaconState<C> {
+	let mut state = common::state::<C, _>(
+		(0..LARGE_VALIDATORS).map(|_| C::max_effective_balance())
+	);
+	state.slot = Slot::new(2 * C::slots_per_epoch() - 1);
+	state.previous_epoch_attestations = MaxVec::from(
+		common::matching_attestations(&mut state, |_| true, 0)
+	);
+	state
+}
+
+fn random_root(rng: &mut StdRng) -> H256 {
+	H256::from_low_u64_be(rng.gen())
+}
+
+/// Fill the previous epoch attestations with random votes, slash random
+/// validators, and set a random finalized checkpoint.
+fn randomize(state: &mut BeaconState<C>, rng: &mut StdRng) {
+	let mut attestations = Vec::new();
+	{
+		let executive = BeaconExecutive::new(state);
+		let previous_epoch = executive.previous_epoch();
+		let target_root = executive.block_root(previous_epoch).expect("Epoch is in range");
+		let start_slot = start_slot_of_epoch::<C>(previous_epoch);
+		for slot in (0..C::slots_per_epoch()).map(|offset| start_slot + offset) {
+			for index in 0..executive.committee_count_at_slot(slot) {
+				let committee = executive.beacon_committee(slot, index)
+					.expect("Committee is in range");
+				let head_root = executive.block_root_at_slot(slot).expect("Slot is in range");
+				for _ in 0..rng.gen_range(0, 3) {
+					let data = AttestationData {
+						slot,
+						index,
+						beacon_block_root: if rng.gen_bool(0.7) { head_root } else { random_root(rng) },
+						source: executive.current_justified_checkpoint.clone(),
+						target: Checkpoint {
+							epoch: previous_epoch,
+							root: if rng.gen_bool(0.8) { target_root } else { random_root(rng) },
+						},
+					};
+					attestations.push(PendingAttestation {
+						aggregation_bits: MaxVec::from(
+							committee.iter().map(|_| rng.gen_bool(0.6)).collect::<Vec<_>>()
+						),
+						data,
+						inclusion_delay: rng.gen_range(
+							C::min_attestation_inclusion_delay(), C::slots_per_epoch() + 1,
+						),
+						proposer_index: rng.gen_range(0, VALIDATORS),
+					});
+				}
+			}
+		}
+	}
+	state.previous_epoch_attestations = MaxVec::from(attestations);
+
+	for validator in state.validators.iter_mut() {
+		if rng.gen_bool(0.05) {
+			validator.slashed = true;
+			validator.exit_epoch = Epoch::new(EPOCH);
+			validator.withdrawable_epoch = Epoch::new(EPOCH + rng.gen_range(0, 4));
+		}
+	}
+
+	state.finalized_checkpoint.epoch = Epoch::new(rng.gen_range(0, EPOCH));
+}
+
+/// Reference rewards and penalties of each validator, as written in the
+/// phase 0 specification.
+fn reference_deltas(state: &mut BeaconState<C>) -> (Vec<u64>, Vec<u64>) {
+	let executive = BeaconExecutive::new(state);
+	let previous_epoch = executive.previous_epoch();
+	let current_epoch = executive.current_epoch();
+	let validators = executive.validators.iter().cloned().collect::<Vec<_>>();
+
+	let total_balance = validators.iter()
+		.filter(|v| v.activation_epoch <= current_epoch && current_epoch < v.exit_epoch)
+		.map(|v| v.effective_balance.as_u64())
+		.sum::<u64>();
+	let base_reward = |index: usize| {
+		validators[index].effective_balance.as_u64() * C::base_reward_factor() /
+			integer_squareroot(total_balance) / BASE_REWARDS_PER_EPOCH
+	};
+
+	let target_root = executive.block_root(previous_epoch).expect("Epoch is in range");
+	let mut source = BTreeSet::new();
+	let mut target = BTreeSet::new();
+	let mut head = BTreeSet::new();
+	let mut inclusions: BTreeMap<usize, (u64, u64)> = BTreeMap::new();
+	for attestation in executive.previous_epoch_attestations.iter() {
+		let committee = executive.beacon_committee(attestation.data.slot, attestation.data.index)
+			.expect("Committee is in range");
+		let head_root = executive.block_root_at_slot(attestation.data.slot)
+			.expect("Slot is in range");
+		for (position, index) in committee.into_iter().enumerate() {
+			let index = index as usize;
+			if !attestation.aggregation_bits[position] || validators[index].slashed {
+				continue
+			}
+			source.insert(index);
+			if attestation.data.target.root == target_root {
+				target.insert(index);
+			}
+			if attestation.data.beacon_block_root == head_root {
+				head.insert(index);
+			}
+			// The first attestation with the minimal inclusion delay counts.
+			let inclusion = inclusions.entry(index)
+				.or_insert((attestation.inclusion_delay, attestation.proposer_index));
+			if attestation.inclusion_delay < inclusion.0 {
+				*inclusion = (attestation.inclusion_delay, attestation.proposer_index);
+			}
+		}
+	}
+	let balance = |set: &BTreeSet<usize>| {
+		set.iter().map(|i| validators[*i].effective_balance.as_u64()).sum::<u64>()
+	};
+	let (source_balance, target_balance, head_balance) =
+		(balance(&source), balance(&target), balance(&head));
+
+	let mut rewards = vec![0; validators.len()];
+	let mut penalties = vec![0; validators.len()];
+	let finality_delay = (previous_epoch - executive.finalized_checkpoint.epoch).as_u64();
+	for (index, validator) in validators.iter().enumerate() {
+		let active = validator.activation_epoch <= previous_epoch &&
+			previous_epoch < validator.exit_epoch;
+		if !active && !(validator.slashed && previous_epoch + 1 < validator.withdrawable_epoch) {
+			continue
+		}
+
+		for (set, attesting_balance) in &[
+			(&source, source_balance), (&target, target_balance), (&head, head_balance),
+		] {
+			if set.contains(&index) {
+				rewards[index] += (base_reward(index) as u128 * *attesting_balance as u128 /
+					total_balance as u128) as u64;
+			} else {
+				penalties[index] += base_reward(index);
+			}
+		}
+
+		if finality_delay > C::min_epochs_to_inactivity_penalty() {
+			penalties[index] += BASE_REWARDS_PER_EPOCH * base_reward(index);
+			if !target.contains(&index) {
+				penalties[index] += validator.effective_balance.as_u64() * finality_delay /
+					C::inactivity_penalty_quotient();
+			}
+		}
+	}
+
+	for (index, (inclusion_delay, proposer_index)) in inclusions {
+		let proposer_reward = base_reward(index) / C::proposer_reward_quotient();
+		rewards[proposer_index as usize] += proposer_reward;
+		rewards[index] += (base_reward(index) - proposer_reward) / inclusion_delay;
+	}
+
+	(rewards, penalties)
+}
+
+#[test]
+fn rewards_match_reference() {
+	let cases = env_u64("REWARD_CASES", DEFAULT_CASES);
+	let seed = env_u64("REWARD_SEED", 42);
+	let mut rng = StdRng::seed_from_u64(seed);
+	let base = base_state(&mut rng);
+
+	for case in 0..cases {
+		let mut state = base.clone();
+		randomize(&mut state, &mut rng);
+
+		let (expected_rewards, expected_penalties) = reference_deltas(&mut state.clone());
+		let breakdowns = BeaconExecutive::new(&mut state).reward_breakdown()
+			.expect("Computing rewards failed");
+		let rewards = breakdowns.iter().map(|b| b.reward().unwrap().as_u64()).collect::<Vec<_>>();
+		let penalties = breakdowns.iter().map(|b| b.penalty().unwrap().as_u64()).collect::<Vec<_>>();
+
+		assert_eq!(
+			rewards, expected_rewards,
+			"Rewards differ in case {} of seed {}", case, seed,
+		);
+		assert_eq!(
+			penalties, expected_penalties,
+			"Penalties differ in case {} of seed {}", case, seed,
+		);
+	}
+}
+
+#[test]
+fn balance_overflow_is_an_error() {
+	let mut rng = StdRng::seed_from_u64(42);
+	let mut state = base_state(&mut rng);
+	randomize(&mut state, &mut rng);
+	for balance in state.balances.iter_mut() {
+		*balance = u64::max_value();
+	}
+
+	assert_eq!(
+		BeaconExecutive::new(&mut state).process_rewards_and_penalties(),
+		Err(Error::ArithmeticOverflow),
+	);
+}
+
+#[test]
+fn min_inclusion_delay_takes_first_attestation() {
+	let mut rng = StdRng::seed_from_u64(42);
+	let mut state = base_state(&mut rng);
+	let executive = BeaconExecutive::new(&mut state);
+	let previous_epoch = executive.previous_epoch();
+	let start_slot = start_slot_of_epoch::<C>(previous_epoch);
+	let target_root = executive.block_root(previous_epoch).expect("Epoch is in range");
+
+	let attestation = |executive: &BeaconExecutive<C>, slot, inclusion_delay, proposer_index| {
+		let committee = executive.beacon_committee(slot, 0).expect("Committee is in range");
+		PendingAttestation {
+			aggregation_bits: MaxVec::from(vec![true; committee.len()]),
+			data: AttestationData {
+				slot,
+				index: 0,
+				beacon_block_root: executive.block_root_at_slot(slot).expect("Slot is in range"),
+				source: executive.current_justified_checkpoint.clone(),
+				target: Checkpoint { epoch: previous_epoch, root: target_root },
+			},
+			inclusion_delay,
+			proposer_index,
+		}
+	};
+
+	// The first attestation is of another committee, and two attestations
+	// of the same committee are included with equal delays.
+	let attestations = vec![
+		attestation(&executive, start_slot + 1u64, 1, 1),
+		attestation(&executive, start_slot, 2, 2),
+		attestation(&executive, start_slot, 2, 3),
+	];
+	let committee = executive.beacon_committee(start_slot, 0).expect("Committee is in range");
+	let inclusions = executive.min_inclusion_delay_attestations(&attestations)
+		.expect("Attestations are valid");
+	for index in committee {
+		let inclusion = &inclusions[&index];
+		assert_eq!(inclusion.inclusion_delay, 2);
+		assert_eq!(inclusion.proposer_index, 2);
+	}
+}
+
+#[test]
+fn large_registry_rewards_are_exact() {
+	let mut state = large_state();
+	let breakdowns = BeaconExecutive::new(&mut state).reward_breakdown()
+		.expect("Computing rewards failed");
+
+	let total_balance = (C::max_effective_balance() * LARGE_VALIDATORS).as_u64();
+	let base_reward = C::max_effective_balance().as_u64() * C::base_reward_factor() /
+		integer_squareroot(total_balance) / BASE_REWARDS_PER_EPOCH;
+	let proposer_reward = base_reward / C::proposer_reward_quotient();
+	let attester_reward = 3 * base_reward + (base_reward - proposer_reward);
+
+	for (index, breakdown) in breakdowns.iter().enumerate() {
+		let expected = if index == 0 {
+			attester_reward + proposer_reward * LARGE_VALIDATORS
+		} else {
+			attester_reward
+		};
+		assert_eq!(breakdown.reward().unwrap().as_u64(), expected, "Reward of {} differs", index);
+		assert_eq!(breakdown.penalty().unwrap().as_u64(), 0, "Penalty of {} differs", index);
+	}
+}
+
+#[test]
+fn large_registry_component_rewards_match_sequential() {
+	let mut sequential = large_state();
+	let mut components = sequential.clone();
+
+	BeaconExecutive::new(&mut sequential).process_rewards_and_penalties()
+		.expect("Processing rewards failed");
+
+	let mut executive = BeaconExecutive::new(&mut components);
+	let previous_epoch = executive.previous_epoch();
+	let previous_checkpoint = Checkpoint {
+		epoch: previous_epoch,
+		root: executive.block_root(previous_epoch).expect("Epoch is in range"),
+	};
+	let finalized_checkpoint = executive.finalized_checkpoint.clone();
+	reward::process::<_, C>(&mut executive, previous_checkpoint, finalized_checkpoint)
+		.expect("Processing rewards failed");
+
+	assert!(components.balances.iter().any(|balance| {
+		*balance > C::max_effective_balance().as_u64()
+	}));
+	assert_eq!(components.balances, sequential.balances);
+}
@@ -0,0 +1,62 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Tests of the Casper FFG slashing conditions.
+
+use beacon::components::slashing::{self, SlashingKind};
+use beacon::primitives::{H256, Epoch};
+use beacon::types::{AttestationData, Checkpoint};
+
+fn vote(source: u64, target: u64, root: u64) -> AttestationData {
+	AttestationData {
+		source: Checkpoint { epoch: Epoch::new(source), root: H256::from_low_u64_be(source) },
+		target: Checkpoint { epoch: Epoch::new(target), root: H256::from_low_u64_be(root) },
+		..Default::default()
+	}
+}
+
+#[test]
+fn different_votes_for_same_target_are_double_votes() {
+	let a1 = vote(1, 3, 3);
+	let a2 = vote(2, 3, 4);
+	assert_eq!(slashing::check(&a1, &a2), Some(SlashingKind::DoubleVote));
+	assert_eq!(slashing::check(&a2, &a1), Some(SlashingKind::DoubleVote));
+	assert!(a1.is_slashable(&a2));
+}
+
+#[test]
+fn surround_vote_is_only_reported_with_the_surrounding_vote_first() {
+	let surrounding = vote(1, 4, 4);
+	let surrounded = vote(2, 3, 3);
+	assert_eq!(slashing::check(&surrounding, &surrounded), Some(SlashingKind::SurroundVote));
+	assert_eq!(slashing::check(&surrounded, &surrounding), None);
+	assert!(surrounding.is_slashable(&surrounded));
+	assert!(!surrounded.is_slashable(&surrounding));
+}
+
+#[test]
+fn consistent_votes_are_not_slashable() {
+	let a1 = vote(1, 2, 2);
+	let a2 = vote(2, 3, 3);
+	assert_eq!(slashing::check(&a1, &a2), None);
+	assert_eq!(slashing::check(&a2, &a1), None);
+
+	// The same vote cast twice.
+	assert_eq!(slashing::check(&a1, &a1.clone()), None);
+
+	// Votes sharing a source do not surround each other.
+	assert_eq!(slashing::check(&vote(1, 4, 4), &vote(1, 3, 3)), None);
+}
@@ -19,37 +19,36 @@ use std::sync::{Arc, RwLock};
 use blockchain::{Block, Auxiliary};
 use blockchain::backend::{Store, ChainQuery, SharedCommittable, ChainSettlement, Operation};
 use parity_codec::{Encode, Decode};
-use rocksdb::{DB, Options};
 
-use super::{RocksState, Error};
+use super::{RocksState, StorageMode, Error};
+use super::database::{Database, MemoryDatabase, Transaction};
+#[cfg(feature = "with-rocksdb")]
+use super::database::SyncPolicy;
+use super::freezer::Freezer;
+use super::migration::{SCHEMA_VERSION, migrations};
 use super::settlement::RocksSettlement;
 use super::utils::*;
 
+/// Default number of blocks between state snapshots kept in pruned mode.
+pub const DEFAULT_SNAPSHOT_INTERVAL: u64 = 256;
+
 pub struct RocksBackend<B: Block, A: Auxiliary<B>, S> {
-	db: Arc<DB>,
+	db: Arc<dyn Database>,
 	head: Arc<RwLock<B::Identifier>>,
 	genesis: Arc<B::Identifier>,
+	mode: StorageMode,
+	freezer: Option<Arc<Freezer>>,
 	_marker: PhantomData<(B, A, S)>,
 }
 
-impl<B: Block, A: Auxiliary<B>, S> RocksBackend<B, A, S> where
-	B::Identifier: Decode
-{
-	fn options() -> Options {
-		let mut db_opts = Options::default();
-		db_opts.create_missing_column_families(true);
-		db_opts.create_if_missing(true);
-
-		db_opts
-	}
-}
-
 impl<B: Block, A: Auxiliary<B>, S> Clone for RocksBackend<B, A, S> {
 	fn clone(&self) -> Self {
 		Self {
 			db: self.db.clone(),
 			head: self.head.clone(),
 			genesis: self.genesis.clone(),
+			mode: self.mode,
+			freezer: self.freezer.clone(),
 			_marker: PhantomData,
 		}
 	}
@@ -80,14 +79,14 @@ impl<B: Block, A: Auxiliary<B>, S: RocksState> ChainQuery for RocksBackend<B, A,
 		&self,
 		id: &B::Identifier
 	) -> Result<bool, Error> {
-		Ok(fetch_block_data::<B, S::Raw>(&self.db, id)?.is_some())
+		Ok(self.block_data(id)?.is_some())
 	}
 
 	fn is_canon(
 		&self,
 		id: &B::Identifier
 	) -> Result<bool, Error> {
-		Ok(fetch_block_data::<B, S::Raw>(&self.db, id)?.ok_or(Error::NotExist)?.is_canon)
+		Ok(self.block_data(id)?.ok_or(Error::NotExist)?.is_canon)
 	}
 
 	fn lookup_canon_depth(
@@ -96,8 +95,7 @@ impl<B: Block, A: Auxiliary<B>, S: RocksState> ChainQuery for RocksBackend<B, A,
 	) -> Result<Option<B::Identifier>, Error> {
 		let depth = depth as u64;
 
-		let cf = self.db.cf_handle(COLUMN_CANON_DEPTH_MAPPINGS).ok_or(Error::Corrupted)?;
-		match self.db.get_cf(cf, depth.encode())? {
+		match self.db.get(COLUMN_CANON_DEPTH_MAPPINGS, &depth.encode())? {
 			Some(hash) => Ok(Some(B::Identifier::decode(&mut hash.as_ref()).ok_or(Error::Corrupted)?)),
 			None => Ok(None),
 		}
@@ -107,8 +105,7 @@ impl<B: Block, A: Auxiliary<B>, S: RocksState> ChainQuery for RocksBackend<B, A,
 		&self,
 		key: &A::Key
 	) -> Result<Option<A>, Error> {
-		let cf = self.db.cf_handle(COLUMN_AUXILIARIES).ok_or(Error::Corrupted)?;
-		match self.db.get_cf(cf, key.encode())? {
+		match self.db.get(COLUMN_AUXILIARIES, &key.encode())? {
 			Some(v) => Ok(Some(A::decode(&mut v.as_ref()).ok_or(Error::Corrupted)?)),
 			None => Ok(None),
 		}
@@ -118,31 +115,41 @@ impl<B: Block, A: Auxiliary<B>, S: RocksState> ChainQuery for RocksBackend<B, A,
 		&self,
 		id: &B::Identifier,
 	) -> Result<Vec<B::Identifier>, Error> {
-		Ok(fetch_block_data::<B, S::Raw>(&self.db, id)?.ok_or(Error::NotExist)?.children)
+		if let Some(data) = fetch_block_data::<B, S::Raw>(self.db(), id)? {
+			return Ok(data.children)
+		}
+
+		// Frozen blocks are immutable, and keep listing children removed by
+		// `remove_branch` after freezing.
+		let data = self.block_data(id)?.ok_or(Error::NotExist)?;
+		let mut children = Vec::new();
+		for child in data.children {
+			if self.contains(&child)? {
+				children.push(child);
+			}
+		}
+		Ok(children)
 	}
 
 	fn depth_at(
 		&self,
 		id: &B::Identifier
 	) -> Result<usize, Error> {
-		Ok(fetch_block_data::<B, S::Raw>(&self.db, id)?.ok_or(Error::NotExist)?.depth as usize)
+		Ok(self.block_data(id)?.ok_or(Error::NotExist)?.depth as usize)
 	}
 
 	fn block_at(
 		&self,
 		id: &B::Identifier,
 	) -> Result<B, Error> {
-		Ok(fetch_block_data::<B, S::Raw>(&self.db, id)?.ok_or(Error::NotExist)?.block)
+		Ok(self.block_data(id)?.ok_or(Error::NotExist)?.block)
 	}
 
 	fn state_at(
 		&self,
 		id: &B::Identifier,
 	) -> Result<Self::State, Error> {
-		Ok(S::from_raw(
-			fetch_block_data::<B, S::Raw>(&self.db, id)?.ok_or(Error::NotExist)?.state,
-			self.db.clone()
-		))
+		self.stored_state_at(id)?.ok_or(Error::NotExist)
 	}
 }
 
@@ -178,23 +185,74 @@ impl<B: Block, A: Auxiliary<B>, S: RocksState> RocksBackend<B, A, S> where
 	A: Encode + Decode,
 	A::Key: Encode + Decode,
 {
+	#[cfg(feature = "with-rocksdb")]
 	pub fn open_or_create<P: AsRef<Path>, F>(path: P, f: F) -> Result<Self, Error> where
-		F: FnOnce(Arc<DB>) -> Result<(B, S), Error>
+		F: FnOnce(Arc<dyn Database>) -> Result<(B, S), Error>
+	{
+		Self::open_or_create_with_mode(path, StorageMode::Archive, SyncPolicy::default(), f)
+	}
+
+	/// Open or create a database with the given storage mode, syncing writes
+	/// according to the given policy. Opening an existing database created
+	/// with another mode fails with `Error::StorageModeMismatch`.
+	#[cfg(feature = "with-rocksdb")]
+	pub fn open_or_create_with_mode<P: AsRef<Path>, F>(
+		path: P,
+		mode: StorageMode,
+		sync: SyncPolicy,
+		f: F
+	) -> Result<Self, Error> where
+		F: FnOnce(Arc<dyn Database>) -> Result<(B, S), Error>
 	{
-		let db_opts = Self::options();
-		let db = Arc::new(DB::open_cf(&db_opts, path, &[
-			COLUMN_BLOCKS, COLUMN_CANON_DEPTH_MAPPINGS, COLUMN_AUXILIARIES, COLUMN_INFO,
-		])?);
+		let db = Arc::new(super::database::RocksDatabase::open(path, sync)?);
+		Self::open_database(db, Some(mode), f)
+	}
 
-		let head = fetch_head(&db)?;
-		let genesis = fetch_genesis(&db)?;
+	/// Create a backend kept in memory, for tests.
+	pub fn new_in_memory(block: B, state: S) -> Result<Self, Error> {
+		Self::open_database(Arc::new(MemoryDatabase::new()), None, |_| Ok((block, state)))
+	}
+
+	/// Open a backend stored in the given database engine, initializing it
+	/// with the genesis returned by `f` if it is empty. Without a requested
+	/// storage mode, the one the database was created with is used.
+	pub fn open_database<F>(
+		db: Arc<dyn Database>,
+		requested_mode: Option<StorageMode>,
+		f: F
+	) -> Result<Self, Error> where
+		F: FnOnce(Arc<dyn Database>) -> Result<(B, S), Error>
+	{
+		let head = fetch_head(db.as_ref())?;
+		let genesis = fetch_genesis(db.as_ref())?;
 
 		match (head, genesis) {
 			(Some(head), Some(genesis)) => {
+				let version = fetch_info::<u32>(db.as_ref(), KEY_SCHEMA_VERSION)?.unwrap_or(0);
+				migrations::<B, S::Raw>().migrate(db.as_ref(), version)?;
+
+				let stored_mode = fetch_info::<StorageMode>(db.as_ref(), KEY_STORAGE_MODE)?;
+				let mode = match (stored_mode, requested_mode) {
+					(Some(stored), Some(requested)) if stored != requested => {
+						return Err(Error::StorageModeMismatch { stored, requested })
+					},
+					(Some(stored), _) => stored,
+					// Databases created before storage modes kept all states.
+					(None, requested) => {
+						let mode = requested.unwrap_or(StorageMode::Archive);
+						let mut transaction = Transaction::new();
+						transaction.put(COLUMN_INFO, KEY_STORAGE_MODE.encode(), mode.encode());
+						db.write(transaction)?;
+						mode
+					},
+				};
+
 				Ok(Self {
 					db: db,
 					head: Arc::new(RwLock::new(head)),
 					genesis: Arc::new(genesis),
+					mode,
+					freezer: None,
 					_marker: PhantomData,
 				})
 			},
@@ -206,10 +264,13 @@ impl<B: Block, A: Auxiliary<B>, S: RocksState> RocksBackend<B, A, S> where
 				let head = block.id();
 				let genesis = head.clone();
 
+				let mode = requested_mode.unwrap_or(StorageMode::Archive);
 				let backend = Self {
 					db: db,
 					head: Arc::new(RwLock::new(head.clone())),
 					genesis: Arc::new(genesis.clone()),
+					mode,
+					freezer: None,
 					_marker: PhantomData,
 				};
 
@@ -225,6 +286,8 @@ impl<B: Block, A: Auxiliary<B>, S: RocksState> RocksBackend<B, A, S> where
 				settlement.insert_canon_depth_mapping(0, genesis.clone());
 				settlement.set_genesis(genesis.clone());
 				settlement.set_head(genesis.clone());
+				settlement.set_storage_mode(mode);
+				settlement.set_schema_version(SCHEMA_VERSION);
 				settlement.commit()?;
 
 				Ok(backend)
@@ -233,6 +296,7 @@ impl<B: Block, A: Auxiliary<B>, S: RocksState> RocksBackend<B, A, S> where
 		}
 	}
 
+	#[cfg(feature = "with-rocksdb")]
 	pub fn new_with_genesis<P: AsRef<Path>>(path: P, block: B, state: S) -> Result<Self, Error> {
 		let mut created = false;
 		let backend = Self::open_or_create(path, |_| {
@@ -245,11 +309,188 @@ impl<B: Block, A: Auxiliary<B>, S: RocksState> RocksBackend<B, A, S> where
 		Ok(backend)
 	}
 
+	/// Open an existing database, with the storage mode it was created
+	/// with.
+	#[cfg(feature = "with-rocksdb")]
 	pub fn from_existing<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
-		Self::open_or_create(path, |_| Err(Error::Corrupted))
+		let db = Arc::new(super::database::RocksDatabase::open(path, SyncPolicy::default())?);
+		Self::open_database(db, None, |_| Err(Error::Corrupted))
+	}
+
+	/// Storage mode of the database.
+	pub fn storage_mode(&self) -> StorageMode {
+		self.mode
+	}
+
+	/// Move finalized blocks into a freezer of flat files in the given
+	/// directory, keeping only recent blocks in the database.
+	pub fn with_freezer<P: AsRef<Path>>(mut self, path: P) -> Result<Self, Error> {
+		let freezer = Freezer::open(path)?;
+		let frozen_depth = fetch_info::<u64>(self.db(), KEY_FROZEN_DEPTH)?.unwrap_or(0);
+		if freezer.len()? < frozen_depth {
+			return Err(Error::Corrupted)
+		}
+
+		self.freezer = Some(Arc::new(freezer));
+		Ok(self)
+	}
+
+	/// Data of a block, from the database or the freezer.
+	fn block_data(&self, id: &B::Identifier) -> Result<Option<BlockData<B, S::Raw>>, Error> {
+		if let Some(data) = fetch_block_data::<B, S::Raw>(self.db(), id)? {
+			return Ok(Some(data))
+		}
+
+		let freezer = match self.freezer {
+			Some(ref freezer) => freezer,
+			None => return Ok(None),
+		};
+		let depth = match self.db.get(COLUMN_FROZEN, &id.encode())? {
+			Some(depth) => u64::decode(&mut depth.as_ref()).ok_or(Error::Corrupted)?,
+			None => return Ok(None),
+		};
+
+		let raw = freezer.block(depth)?.ok_or(Error::Corrupted)?;
+		let mut data = BlockData::<B, S::Raw>::decode(&mut raw.as_ref()).ok_or(Error::Corrupted)?;
+		data.state = match freezer.state(depth)? {
+			Some(raw) => Some(S::Raw::decode(&mut raw.as_ref()).ok_or(Error::Corrupted)?),
+			None => None,
+		};
+		Ok(Some(data))
+	}
+
+	/// Move canonical blocks before the finalized block, with their kept
+	/// states, from the database into the freezer. Returns the number of
+	/// frozen blocks. Does nothing without a freezer. Must not run
+	/// concurrently with block imports.
+	pub fn freeze(&self, finalized: &B::Identifier) -> Result<usize, Error> {
+		let freezer = match self.freezer {
+			Some(ref freezer) => freezer,
+			None => return Ok(0),
+		};
+
+		let finalized_data = self.block_data(finalized)?.ok_or(Error::NotExist)?;
+		if !finalized_data.is_canon {
+			return Err(Error::InvalidOperation)
+		}
+		let start_depth = fetch_info::<u64>(self.db(), KEY_FROZEN_DEPTH)?.unwrap_or(0);
+		if start_depth >= finalized_data.depth {
+			return Ok(0)
+		}
+
+		let mut transaction = Transaction::new();
+		let mut frozen = 0;
+		for depth in start_depth..finalized_data.depth {
+			let id = self.lookup_canon_depth(depth as usize)?.ok_or(Error::Corrupted)?;
+			let mut data = fetch_block_data::<B, S::Raw>(self.db(), &id)?
+				.ok_or(Error::Corrupted)?;
+
+			// Blocks already appended by an interrupted freeze are only
+			// removed from the database.
+			if freezer.len()? <= depth {
+				if let Some(state) = data.state.take() {
+					freezer.append_state(depth, &state.encode())?;
+				}
+				freezer.append_block(&data.encode())?;
+			}
+
+			transaction.delete(COLUMN_BLOCKS, id.encode());
+			transaction.put(COLUMN_FROZEN, id.encode(), depth.encode());
+			frozen += 1;
+		}
+		transaction.put(COLUMN_INFO, KEY_FROZEN_DEPTH.encode(), finalized_data.depth.encode());
+
+		self.db.write(transaction)?;
+		Ok(frozen)
+	}
+
+	/// Post-state of a block, or `None` if it has been pruned.
+	pub fn stored_state_at(&self, id: &B::Identifier) -> Result<Option<S>, Error> {
+		let data = self.block_data(id)?.ok_or(Error::NotExist)?;
+		Ok(data.state.map(|state| S::from_raw(state, self.db.clone())))
+	}
+
+	/// Drop the states of canonical blocks before the finalized block that
+	/// the storage mode does not keep, returning the number of dropped
+	/// states. The genesis state is always kept. Must not run concurrently
+	/// with block imports.
+	pub fn prune_states(&self, finalized: &B::Identifier) -> Result<usize, Error> {
+		let interval = match self.mode {
+			StorageMode::Archive => return Ok(0),
+			StorageMode::Prune(interval) => Some(std::cmp::max(interval, 1)),
+			StorageMode::Minimal => None,
+		};
+
+		let finalized_data = self.block_data(finalized)?.ok_or(Error::NotExist)?;
+		if !finalized_data.is_canon {
+			return Err(Error::InvalidOperation)
+		}
+		let start_depth = fetch_info::<u64>(self.db(), KEY_PRUNED_DEPTH)?.unwrap_or(1);
+		if start_depth >= finalized_data.depth {
+			return Ok(0)
+		}
+
+		let mut transaction = Transaction::new();
+		let mut pruned = 0;
+		for depth in start_depth..finalized_data.depth {
+			if interval.map(|interval| depth % interval == 0).unwrap_or(false) {
+				continue
+			}
+
+			let id = self.lookup_canon_depth(depth as usize)?.ok_or(Error::Corrupted)?;
+			// Frozen blocks have had their states pruned before freezing.
+			let mut data = match fetch_block_data::<B, S::Raw>(self.db(), &id)? {
+				Some(data) => data,
+				None => continue,
+			};
+			if data.state.take().is_some() {
+				transaction.put(COLUMN_BLOCKS, id.encode(), data.encode());
+				pruned += 1;
+			}
+		}
+		transaction.put(COLUMN_INFO, KEY_PRUNED_DEPTH.encode(), finalized_data.depth.encode());
+
+		self.db.write(transaction)?;
+		Ok(pruned)
+	}
+
+	/// Remove a non-canonical block together with all its descendants,
+	/// returning the number of removed blocks.
+	pub fn remove_branch(&self, id: &B::Identifier) -> Result<usize, Error> {
+		let data = fetch_block_data::<B, S::Raw>(self.db(), id)?.ok_or(Error::NotExist)?;
+		if data.is_canon {
+			return Err(Error::InvalidOperation)
+		}
+		let parent_id = data.block.parent_id().ok_or(Error::IsGenesis)?;
+
+		let mut transaction = Transaction::new();
+		let mut removed = 0;
+		let mut queue = vec![id.clone()];
+		while let Some(current) = queue.pop() {
+			let data = fetch_block_data::<B, S::Raw>(self.db(), &current)?
+				.ok_or(Error::Corrupted)?;
+			if data.is_canon {
+				return Err(Error::Corrupted)
+			}
+			queue.extend(data.children);
+			transaction.delete(COLUMN_BLOCKS, current.encode());
+			removed += 1;
+		}
+
+		// Frozen parents are immutable, and keep listing the removed child,
+		// which `children_at` filters out.
+		if let Some(mut parent) = fetch_block_data::<B, S::Raw>(self.db(), &parent_id)? {
+			parent.children.retain(|child| child != id);
+			transaction.put(COLUMN_BLOCKS, parent_id.encode(), parent.encode());
+		} else if !self.contains(&parent_id)? {
+			return Err(Error::Corrupted)
+		}
+
+		self.db.write(transaction)?;
+		Ok(removed)
 	}
 
-	pub(crate) fn db(&self) -> &DB {
+	pub(crate) fn db(&self) -> &dyn Database {
 		self.db.as_ref()
 	}
 }
@@ -0,0 +1,225 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Key-value database engines the backend can be stored in.
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::RwLock;
+#[cfg(feature = "with-rocksdb")]
+use std::path::Path;
+#[cfg(feature = "with-rocksdb")]
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use super::Error;
+
+/// Columns used by the backend.
+pub const COLUMNS: &[&str] = &[
+	super::utils::COLUMN_BLOCKS,
+	super::utils::COLUMN_CANON_DEPTH_MAPPINGS,
+	super::utils::COLUMN_AUXILIARIES,
+	super::utils::COLUMN_INFO,
+	super::utils::COLUMN_FROZEN,
+];
+
+/// When writes are synced to disk. Every write is atomic regardless of the
+/// policy, and survives a process crash through the write-ahead log; the
+/// policy decides what survives an operating system crash or power loss.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SyncPolicy {
+	/// Leave flushing to the operating system.
+	Never,
+	/// Sync every write before returning.
+	Always,
+	/// Sync one write out of every given number.
+	Every(u32),
+}
+
+impl Default for SyncPolicy {
+	fn default() -> Self {
+		SyncPolicy::Never
+	}
+}
+
+/// Change to a single key.
+pub enum Change {
+	/// Insert or replace a value.
+	Put(&'static str, Vec<u8>, Vec<u8>),
+	/// Delete a value.
+	Delete(&'static str, Vec<u8>),
+}
+
+/// Changes written atomically to a database.
+#[derive(Default)]
+pub struct Transaction {
+	changes: Vec<Change>,
+}
+
+impl Transaction {
+	/// Create an empty transaction.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Insert or replace a value in a column.
+	pub fn put(&mut self, column: &'static str, key: Vec<u8>, value: Vec<u8>) {
+		self.changes.push(Change::Put(column, key, value));
+	}
+
+	/// Delete a value from a column.
+	pub fn delete(&mut self, column: &'static str, key: Vec<u8>) {
+		self.changes.push(Change::Delete(column, key));
+	}
+
+	/// Whether the transaction has no change.
+	pub fn is_empty(&self) -> bool {
+		self.changes.is_empty()
+	}
+
+	/// Changes of the transaction, in order.
+	pub fn changes(&self) -> &[Change] {
+		&self.changes
+	}
+}
+
+/// Key-value database with columns.
+pub trait Database: Send + Sync + 'static {
+	/// Get a value in a column.
+	fn get(&self, column: &str, key: &[u8]) -> Result<Option<Vec<u8>>, Error>;
+
+	/// Write all changes of a transaction atomically.
+	fn write(&self, transaction: Transaction) -> Result<(), Error>;
+
+	/// All key-value pairs of a column.
+	fn iter(&self, column: &str) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Error>;
+}
+
+/// Database engine keeping everything in memory, for tests.
+#[derive(Default)]
+pub struct MemoryDatabase(RwLock<HashMap<String, BTreeMap<Vec<u8>, Vec<u8>>>>);
+
+impl MemoryDatabase {
+	/// Create an empty in-memory database.
+	pub fn new() -> Self {
+		Self::default()
+	}
+}
+
+impl Database for MemoryDatabase {
+	fn get(&self, column: &str, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+		let columns = self.0.read().expect("Lock is poisoned");
+		Ok(columns.get(column).and_then(|values| values.get(key)).cloned())
+	}
+
+	fn write(&self, transaction: Transaction) -> Result<(), Error> {
+		let mut columns = self.0.write().expect("Lock is poisoned");
+		for change in transaction.changes {
+			match change {
+				Change::Put(column, key, value) => {
+					columns.entry(column.to_string()).or_default().insert(key, value);
+				},
+				Change::Delete(column, key) => {
+					if let Some(values) = columns.get_mut(column) {
+						values.remove(&key);
+					}
+				},
+			}
+		}
+		Ok(())
+	}
+
+	fn iter(&self, column: &str) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Error> {
+		let columns = self.0.read().expect("Lock is poisoned");
+		Ok(columns.get(column)
+		   .map(|values| values.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+		   .unwrap_or_default())
+	}
+}
+
+/// RocksDB database with all backend columns. Each transaction is written
+/// as a single write batch.
+#[cfg(feature = "with-rocksdb")]
+pub struct RocksDatabase {
+	db: rocksdb::DB,
+	sync: SyncPolicy,
+	writes: AtomicU32,
+}
+
+#[cfg(feature = "with-rocksdb")]
+impl RocksDatabase {
+	/// Open or create a RocksDB database, syncing writes to disk according
+	/// to the given policy.
+	pub fn open<P: AsRef<Path>>(path: P, sync: SyncPolicy) -> Result<Self, Error> {
+		let mut db_opts = rocksdb::Options::default();
+		db_opts.create_missing_column_families(true);
+		db_opts.create_if_missing(true);
+
+		Ok(Self {
+			db: rocksdb::DB::open_cf(&db_opts, path, COLUMNS)?,
+			sync,
+			writes: AtomicU32::new(0),
+		})
+	}
+
+	fn should_sync(&self) -> bool {
+		match self.sync {
+			SyncPolicy::Never => false,
+			SyncPolicy::Always => true,
+			SyncPolicy::Every(interval) => {
+				let writes = self.writes.fetch_add(1, Ordering::Relaxed).wrapping_add(1);
+				interval == 0 || writes % interval == 0
+			},
+		}
+	}
+}
+
+#[cfg(feature = "with-rocksdb")]
+impl Database for RocksDatabase {
+	fn get(&self, column: &str, key: &[u8]) -> Result<Option<Vec<u8>>, Error> {
+		let cf = self.db.cf_handle(column).ok_or(Error::Corrupted)?;
+		Ok(self.db.get_cf(cf, key)?.map(|value| value.to_vec()))
+	}
+
+	fn write(&self, transaction: Transaction) -> Result<(), Error> {
+		if transaction.is_empty() {
+			return Ok(())
+		}
+
+		let mut batch = rocksdb::WriteBatch::default();
+		for change in transaction.changes {
+			match change {
+				Change::Put(column, key, value) => {
+					let cf = self.db.cf_handle(column).ok_or(Error::Corrupted)?;
+					batch.put_cf(cf, key, value)?;
+				},
+				Change::Delete(column, key) => {
+					let cf = self.db.cf_handle(column).ok_or(Error::Corrupted)?;
+					batch.delete_cf(cf, key)?;
+				},
+			}
+		}
+
+		let mut write_opts = rocksdb::WriteOptions::default();
+		write_opts.set_sync(self.should_sync());
+		Ok(self.db.write_opt(batch, &write_opts)?)
+	}
+
+	fn iter(&self, column: &str) -> Result<Vec<(Vec<u8>, Vec<u8>)>, Error> {
+		let cf = self.db.cf_handle(column).ok_or(Error::Corrupted)?;
+		Ok(self.db.iterator_cf(cf, rocksdb::IteratorMode::Start)?
+		   .map(|(key, value)| (key.to_vec(), value.to_vec()))
+		   .collect())
+	}
+}
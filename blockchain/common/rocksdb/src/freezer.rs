@@ -0,0 +1,174 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Freezer of finalized blocks and states in flat append-only files.
+//!
+//! Every file is a data file of length-prefixed records, and an index file
+//! of fixed-size entries pointing into it. Block entries are contiguous by
+//! depth, while state entries are sparse and carry their depth.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::Error;
+
+const BLOCKS_DATA: &str = "blocks.dat";
+const BLOCKS_INDEX: &str = "blocks.idx";
+const STATES_DATA: &str = "states.dat";
+const STATES_INDEX: &str = "states.idx";
+
+struct FlatFile {
+	data: File,
+	index: File,
+}
+
+impl FlatFile {
+	fn open(dir: &Path, data: &str, index: &str) -> Result<Self, Error> {
+		let open = |name: &str| OpenOptions::new()
+			.read(true).append(true).create(true).open(dir.join(name));
+		Ok(Self { data: open(data)?, index: open(index)? })
+	}
+
+	fn append(&mut self, value: &[u8]) -> Result<u64, Error> {
+		let offset = self.data.seek(SeekFrom::End(0))?;
+		self.data.write_all(&(value.len() as u32).to_le_bytes())?;
+		self.data.write_all(value)?;
+		self.data.sync_data()?;
+		Ok(offset)
+	}
+
+	fn read(&mut self, offset: u64) -> Result<Vec<u8>, Error> {
+		let mut len = [0u8; 4];
+		self.data.seek(SeekFrom::Start(offset))?;
+		self.data.read_exact(&mut len)?;
+		let mut value = vec![0u8; u32::from_le_bytes(len) as usize];
+		self.data.read_exact(&mut value)?;
+		Ok(value)
+	}
+
+	fn push_index(&mut self, entry: &[u8]) -> Result<(), Error> {
+		self.index.write_all(entry)?;
+		self.index.sync_data()?;
+		Ok(())
+	}
+
+	fn index_entry(&mut self, position: u64, size: u64) -> Result<[u8; 16], Error> {
+		let mut entry = [0u8; 16];
+		self.index.seek(SeekFrom::Start(position * size))?;
+		self.index.read_exact(&mut entry[..size as usize])?;
+		Ok(entry)
+	}
+
+	fn index_len(&self, size: u64) -> Result<u64, Error> {
+		Ok(self.index.metadata()?.len() / size)
+	}
+}
+
+struct Inner {
+	blocks: FlatFile,
+	states: FlatFile,
+	state_depths: Vec<u64>,
+}
+
+/// Append-only store of finalized blocks, by depth, and of the states kept
+/// for some of them.
+pub struct Freezer(Mutex<Inner>);
+
+impl Freezer {
+	/// Open or create a freezer in the given directory.
+	pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+		fs::create_dir_all(path.as_ref())?;
+		let blocks = FlatFile::open(path.as_ref(), BLOCKS_DATA, BLOCKS_INDEX)?;
+		let mut states = FlatFile::open(path.as_ref(), STATES_DATA, STATES_INDEX)?;
+
+		let mut state_depths = Vec::new();
+		for position in 0..states.index_len(16)? {
+			let entry = states.index_entry(position, 16)?;
+			state_depths.push(read_u64(&entry[0..8]));
+		}
+
+		Ok(Self(Mutex::new(Inner { blocks, states, state_depths })))
+	}
+
+	/// Number of frozen blocks, which is also the depth of the next one.
+	pub fn len(&self) -> Result<u64, Error> {
+		let inner = self.0.lock().expect("Lock is poisoned");
+		inner.blocks.index_len(8)
+	}
+
+	/// Whether no block is frozen.
+	pub fn is_empty(&self) -> Result<bool, Error> {
+		Ok(self.len()? == 0)
+	}
+
+	/// Append the block at the next depth.
+	pub fn append_block(&self, value: &[u8]) -> Result<(), Error> {
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+		let offset = inner.blocks.append(value)?;
+		inner.blocks.push_index(&offset.to_le_bytes())
+	}
+
+	/// Append the state of the block at `depth`, which must not be before
+	/// the depths of frozen states. Appending again the state at the last
+	/// depth, as an interrupted freeze does, is a no-op.
+	pub fn append_state(&self, depth: u64, value: &[u8]) -> Result<(), Error> {
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+		match inner.state_depths.last() {
+			Some(last) if *last == depth => return Ok(()),
+			Some(last) if *last > depth => return Err(Error::InvalidOperation),
+			_ => (),
+		}
+
+		let offset = inner.states.append(value)?;
+		let mut entry = [0u8; 16];
+		entry[0..8].copy_from_slice(&depth.to_le_bytes());
+		entry[8..16].copy_from_slice(&offset.to_le_bytes());
+		inner.states.push_index(&entry)?;
+		inner.state_depths.push(depth);
+		Ok(())
+	}
+
+	/// Frozen block at `depth`.
+	pub fn block(&self, depth: u64) -> Result<Option<Vec<u8>>, Error> {
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+		if depth >= inner.blocks.index_len(8)? {
+			return Ok(None)
+		}
+
+		let entry = inner.blocks.index_entry(depth, 8)?;
+		Ok(Some(inner.blocks.read(read_u64(&entry[0..8]))?))
+	}
+
+	/// Frozen state of the block at `depth`, if it was kept.
+	pub fn state(&self, depth: u64) -> Result<Option<Vec<u8>>, Error> {
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+		let position = match inner.state_depths.binary_search(&depth) {
+			Ok(position) => position as u64,
+			Err(_) => return Ok(None),
+		};
+
+		let entry = inner.states.index_entry(position, 16)?;
+		Ok(Some(inner.states.read(read_u64(&entry[8..16]))?))
+	}
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+	let mut buf = [0u8; 8];
+	buf.copy_from_slice(bytes);
+	u64::from_le_bytes(buf)
+}
@@ -16,15 +16,44 @@
 mod utils;
 mod settlement;
 mod backend;
+mod migration;
+mod freezer;
+mod database;
 
-pub use self::backend::RocksBackend;
+pub use self::backend::{RocksBackend, DEFAULT_SNAPSHOT_INTERVAL};
+pub use self::freezer::Freezer;
+pub use self::database::{Database, MemoryDatabase, Transaction, Change, SyncPolicy, COLUMNS};
+#[cfg(feature = "with-rocksdb")]
+pub use self::database::RocksDatabase;
+pub use self::migration::{
+	Migration, MigrationFn, MigrationRegistry, SCHEMA_VERSION, migrations,
+};
 
 use std::{fmt, error as stderror};
 use std::sync::Arc;
 use parity_codec::{Encode, Decode};
-use rocksdb::DB;
 use blockchain::backend::OperationError;
 
+/// States kept by the backend
+#[derive(Encode, Decode, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StorageMode {
+	/// Keep the states of all blocks
+	Archive,
+	/// Drop the states of finalized blocks, except a snapshot every given
+	/// number of blocks, from which the others can be regenerated
+	Prune(u64),
+	/// Drop the states of finalized blocks, except the genesis and the
+	/// latest finalized block. States of unfinalized blocks, including the
+	/// head, are kept
+	Minimal,
+}
+
+impl Default for StorageMode {
+	fn default() -> Self {
+		StorageMode::Archive
+	}
+}
+
 #[derive(Debug)]
 /// RocksDB backend errors
 pub enum Error {
@@ -36,16 +65,30 @@ pub enum Error {
 	NotExist,
 	/// Corrupted database,
 	Corrupted,
+	/// Database was created with another storage mode
+	StorageModeMismatch { stored: StorageMode, requested: StorageMode },
+	/// Database schema version cannot be migrated to the current one
+	UnsupportedSchemaVersion(u32),
 	/// RocksDB errors
+	#[cfg(feature = "with-rocksdb")]
 	Rocks(rocksdb::Error),
+	/// Freezer file errors
+	Io(std::io::Error),
 }
 
+#[cfg(feature = "with-rocksdb")]
 impl From<rocksdb::Error> for Error {
 	fn from(error: rocksdb::Error) -> Error {
 		Error::Rocks(error)
 	}
 }
 
+impl From<std::io::Error> for Error {
+	fn from(error: std::io::Error) -> Error {
+		Error::Io(error)
+	}
+}
+
 impl OperationError for Error {
 	fn invalid_operation() -> Self {
 		Error::InvalidOperation
@@ -67,6 +110,6 @@ impl stderror::Error for Error { }
 pub trait RocksState {
 	type Raw: Encode + Decode;
 
-	fn from_raw(raw: Self::Raw, db: Arc<DB>) -> Self;
+	fn from_raw(raw: Self::Raw, db: Arc<dyn Database>) -> Self;
 	fn into_raw(self) -> Self::Raw;
 }
@@ -0,0 +1,136 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Database schema versions and in-place migrations between them.
+
+use blockchain::Block;
+use parity_codec::{Encode, Decode};
+use super::Error;
+use super::database::{Database, Transaction};
+use super::utils::*;
+
+/// Schema version of databases created by this version of the backend.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Migration function, writing all changes into the given transaction.
+pub type MigrationFn = fn(&dyn Database, &mut Transaction) -> Result<(), Error>;
+
+/// Migration of a database from the previous schema version.
+pub struct Migration {
+	/// Schema version after the migration.
+	pub version: u32,
+	/// Description of the format change.
+	pub description: &'static str,
+	/// Migration function.
+	pub migrate: MigrationFn,
+}
+
+/// Registry of known migrations, applied in order when opening a database
+/// with an older schema version.
+#[derive(Default)]
+pub struct MigrationRegistry {
+	migrations: Vec<Migration>,
+}
+
+impl MigrationRegistry {
+	/// Create an empty registry.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Register the migration to `version` from the version before it.
+	pub fn register(&mut self, version: u32, description: &'static str, migrate: MigrationFn) {
+		self.migrations.retain(|migration| migration.version != version);
+		self.migrations.push(Migration { version, description, migrate });
+	}
+
+	/// Registered migration to `version`.
+	pub fn get(&self, version: u32) -> Option<&Migration> {
+		self.migrations.iter().find(|migration| migration.version == version)
+	}
+
+	/// Migrate a database at schema `version` up to `SCHEMA_VERSION`. Each
+	/// migration is written atomically together with the new version, so
+	/// an interrupted upgrade resumes from the last completed step.
+	/// Returns the descriptions of the applied migrations.
+	pub fn migrate(&self, db: &dyn Database, mut version: u32) -> Result<Vec<&'static str>, Error> {
+		if version > SCHEMA_VERSION {
+			return Err(Error::UnsupportedSchemaVersion(version))
+		}
+
+		let mut applied = Vec::new();
+		while version < SCHEMA_VERSION {
+			let migration = self.get(version + 1)
+				.ok_or(Error::UnsupportedSchemaVersion(version))?;
+
+			let mut transaction = Transaction::new();
+			(migration.migrate)(db, &mut transaction)?;
+			transaction.put(COLUMN_INFO, KEY_SCHEMA_VERSION.encode(), migration.version.encode());
+			db.write(transaction)?;
+
+			applied.push(migration.description);
+			version = migration.version;
+		}
+
+		Ok(applied)
+	}
+}
+
+/// Block data of schema version 0, where every block had its state.
+#[derive(Encode, Decode)]
+struct BlockDataV0<B: Block, S> {
+	block: B,
+	state: S,
+	depth: u64,
+	children: Vec<B::Identifier>,
+	is_canon: bool,
+}
+
+/// Wrap the state of every block into an option, so that storage modes can
+/// drop it.
+fn migrate_optional_states<B: Block, S>(
+	db: &dyn Database,
+	transaction: &mut Transaction,
+) -> Result<(), Error> where
+	B::Identifier: Encode + Decode,
+	B: Encode + Decode,
+	S: Encode + Decode,
+{
+	for (key, value) in db.iter(COLUMN_BLOCKS)? {
+		let old = BlockDataV0::<B, S>::decode(&mut value.as_ref()).ok_or(Error::Corrupted)?;
+		transaction.put(COLUMN_BLOCKS, key, BlockData {
+			block: old.block,
+			state: Some(old.state),
+			depth: old.depth,
+			children: old.children,
+			is_canon: old.is_canon,
+		}.encode());
+	}
+	Ok(())
+}
+
+/// Registry of all migrations of the backend.
+pub fn migrations<B: Block, S>() -> MigrationRegistry where
+	B::Identifier: Encode + Decode,
+	B: Encode + Decode,
+	S: Encode + Decode,
+{
+	let mut registry = MigrationRegistry::new();
+	registry.register(
+		1, "Make block states optional for storage modes", migrate_optional_states::<B, S>,
+	);
+	registry
+}
@@ -17,10 +17,10 @@ use std::collections::HashMap;
 use blockchain::{Block, Auxiliary};
 use blockchain::backend::{Store, ChainQuery, ChainSettlement};
 use parity_codec::{Encode, Decode};
-use rocksdb::WriteBatch;
 
-use super::{RocksBackend, RocksState, Error};
+use super::{RocksBackend, RocksState, StorageMode, Error};
 use super::utils::*;
+use super::database::Transaction;
 
 pub struct RocksSettlement<'a, B: Block, A: Auxiliary<B>, S> {
 	backend: &'a RocksBackend<B, A, S>,
@@ -118,7 +118,7 @@ impl<'a, B: Block, A: Auxiliary<B>, S: RocksState> ChainSettlement for RocksSett
 		}
 
 		self.changes.insert((COLUMN_BLOCKS, id.encode()), Some(BlockData {
-			block, state: state.into_raw(), depth: depth as u64, children, is_canon
+			block, state: Some(state.into_raw()), depth: depth as u64, children, is_canon
 		}.encode()));
 	}
 
@@ -131,7 +131,7 @@ impl<'a, B: Block, A: Auxiliary<B>, S: RocksState> ChainSettlement for RocksSett
 			return
 		}
 
-		let mut data = match fetch_block_data::<B, S::Raw>(self.backend.db(), &id) {
+		let mut data = match self.block_data(&id) {
 			Ok(Some(data)) => data,
 			Ok(None) => {
 				self.last_error = Some(Error::Corrupted);
@@ -156,7 +156,7 @@ impl<'a, B: Block, A: Auxiliary<B>, S: RocksState> ChainSettlement for RocksSett
 			return
 		}
 
-		let mut data = match fetch_block_data::<B, S::Raw>(self.backend.db(), &id) {
+		let mut data = match self.block_data(&id) {
 			Ok(Some(data)) => data,
 			Ok(None) => {
 				self.last_error = Some(Error::Corrupted);
@@ -248,6 +248,40 @@ impl<'a, B: Block, A: Auxiliary<B>, S: RocksState> RocksSettlement<'a, B, A, S>
 		}
 	}
 
+	pub(crate) fn set_storage_mode(
+		&mut self,
+		mode: StorageMode,
+	) {
+		if self.last_error.is_some() {
+			return
+		}
+
+		self.changes.insert((COLUMN_INFO, KEY_STORAGE_MODE.encode()), Some(mode.encode()));
+	}
+
+	/// Block data including the changes of this settlement, so that
+	/// several changes to one block end up in the same write.
+	fn block_data(&self, id: &B::Identifier) -> Result<Option<BlockData<B, S::Raw>>, Error> {
+		match self.changes.get(&(COLUMN_BLOCKS, id.encode())) {
+			Some(Some(raw)) => Ok(Some(
+				BlockData::decode(&mut raw.as_ref()).ok_or(Error::Corrupted)?
+			)),
+			Some(None) => Ok(None),
+			None => fetch_block_data::<B, S::Raw>(self.backend.db(), id),
+		}
+	}
+
+	pub(crate) fn set_schema_version(
+		&mut self,
+		version: u32,
+	) {
+		if self.last_error.is_some() {
+			return
+		}
+
+		self.changes.insert((COLUMN_INFO, KEY_SCHEMA_VERSION.encode()), Some(version.encode()));
+	}
+
 	pub(crate) fn set_genesis(
 		&mut self,
 		genesis: B::Identifier
@@ -264,21 +298,16 @@ impl<'a, B: Block, A: Auxiliary<B>, S: RocksState> RocksSettlement<'a, B, A, S>
 			return Err(error)
 		}
 
-		let mut batch = WriteBatch::default();
+		let mut transaction = Transaction::new();
 
 		for ((column, key), value) in self.changes {
-			let cf = self.backend.db().cf_handle(column).ok_or(Error::Corrupted)?;
 			match value {
-				Some(value) => {
-					batch.put_cf(cf, key, value)?;
-				},
-				None => {
-					batch.delete_cf(cf, key)?;
-				},
+				Some(value) => transaction.put(column, key, value),
+				None => transaction.delete(column, key),
 			}
 		}
 
-		self.backend.db().write(batch)?;
+		self.backend.db().write(transaction)?;
 		Ok(self.new_head)
 	}
 }
@@ -15,55 +15,57 @@
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
 use blockchain::Block;
 use parity_codec::{Encode, Decode};
-use rocksdb::DB;
 use super::Error;
+use super::database::Database;
 
 pub const COLUMN_BLOCKS: &str = "blocks";
 pub const COLUMN_CANON_DEPTH_MAPPINGS: &str = "canon_depth_mappings";
 pub const COLUMN_AUXILIARIES: &str = "auxiliaries";
 pub const COLUMN_INFO: &str = "info";
+pub const COLUMN_FROZEN: &str = "frozen";
 pub const KEY_HEAD: &str = "head";
 pub const KEY_GENESIS: &str = "genesis";
+pub const KEY_STORAGE_MODE: &str = "storage_mode";
+pub const KEY_PRUNED_DEPTH: &str = "pruned_depth";
+pub const KEY_SCHEMA_VERSION: &str = "schema_version";
+pub const KEY_FROZEN_DEPTH: &str = "frozen_depth";
 
 #[derive(Encode, Decode)]
 pub struct BlockData<B: Block, S> {
 	pub block: B,
-	pub state: S,
+	pub state: Option<S>,
 	pub depth: u64,
 	pub children: Vec<B::Identifier>,
 	pub is_canon: bool,
 }
 
 pub fn fetch_block_data<B: Block, S>(
-	db:
-	&DB, id: &B::Identifier
+	db: &dyn Database,
+	id: &B::Identifier
 ) -> Result<Option<BlockData<B, S>>, Error> where
 	B::Identifier: Encode + Decode,
 	B: Decode,
 	S: Decode
 {
-	let cf = db.cf_handle(COLUMN_BLOCKS).ok_or(Error::Corrupted)?;
-	let raw = match db.get_cf(cf, id.encode())? {
+	let raw = match db.get(COLUMN_BLOCKS, &id.encode())? {
 		Some(raw) => raw,
 		None => return Ok(None),
 	};
 	Ok(Some(BlockData::decode(&mut raw.as_ref()).ok_or(Error::Corrupted)?))
 }
 
-pub fn fetch_head<I: Decode>(db: &DB) -> Result<Option<I>, Error> {
-	let cf = db.cf_handle(COLUMN_INFO).ok_or(Error::Corrupted)?;
-	let raw = match db.get_cf(cf, KEY_HEAD.encode())? {
+pub fn fetch_info<I: Decode>(db: &dyn Database, key: &str) -> Result<Option<I>, Error> {
+	let raw = match db.get(COLUMN_INFO, &key.encode())? {
 		Some(raw) => raw,
 		None => return Ok(None),
 	};
 	Ok(Some(I::decode(&mut raw.as_ref()).ok_or(Error::Corrupted)?))
 }
 
-pub fn fetch_genesis<I: Decode>(db: &DB) -> Result<Option<I>, Error> {
-	let cf = db.cf_handle(COLUMN_INFO).ok_or(Error::Corrupted)?;
-	let raw = match db.get_cf(cf, KEY_GENESIS.encode())? {
-		Some(raw) => raw,
-		None => return Ok(None),
-	};
-	Ok(Some(I::decode(&mut raw.as_ref()).ok_or(Error::Corrupted)?))
+pub fn fetch_head<I: Decode>(db: &dyn Database) -> Result<Option<I>, Error> {
+	fetch_info(db, KEY_HEAD)
+}
+
+pub fn fetch_genesis<I: Decode>(db: &dyn Database) -> Result<Option<I>, Error> {
+	fetch_info(db, KEY_GENESIS)
 }
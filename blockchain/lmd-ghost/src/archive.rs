@@ -13,7 +13,8 @@
 
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
 use core::hash::Hash;
 use core::mem;
 use blockchain::{Block, Auxiliary, BlockExecutor, AsExternalities};
@@ -157,9 +158,82 @@ impl<Ba: AncestorQuery + ChainQuery, VI: Eq + Hash> ArchiveGhost<Ba, VI> {
 	}
 }
 
+/// Node of the fork choice block tree.
+#[derive(Clone, Debug)]
+pub struct TreeNode<Id> {
+	pub id: Id,
+	pub parent: Option<Id>,
+	pub depth: usize,
+	pub weight: usize,
+	pub justified: bool,
+	pub head: bool,
+}
+
+pub(crate) struct ForkChoiceSnapshot<Id> {
+	pub(crate) justified: Option<Id>,
+	pub(crate) head: Option<Id>,
+	pub(crate) targets: Vec<Id>,
+}
+
+/// Read-only view of the fork choice of an importer, usable from other
+/// threads.
+pub struct ForkChoiceView<Ba: Store> {
+	pub(crate) backend: Ba,
+	pub(crate) snapshot: Arc<RwLock<ForkChoiceSnapshot<<Ba::Block as Block>::Identifier>>>,
+}
+
+impl<Ba: Store + Clone> Clone for ForkChoiceView<Ba> {
+	fn clone(&self) -> Self {
+		Self { backend: self.backend.clone(), snapshot: self.snapshot.clone() }
+	}
+}
+
+impl<Ba: AncestorQuery + ChainQuery> ForkChoiceView<Ba> {
+	/// Dump the block tree descending from the justified block, with the
+	/// number of latest votes supporting each block.
+	pub fn tree(&self) -> Result<Vec<TreeNode<<Ba::Block as Block>::Identifier>>, Ba::Error> {
+		let snapshot = self.snapshot.read().expect("Lock is poisoned");
+		let justified = snapshot.justified.clone().unwrap_or_else(|| self.backend.genesis());
+		let head = snapshot.head.clone().unwrap_or_else(|| self.backend.head());
+
+		let mut nodes = Vec::new();
+		let mut queue = VecDeque::new();
+		queue.push_back((justified.clone(), self.backend.block_at(&justified)?.parent_id()));
+
+		while let Some((id, parent)) = queue.pop_front() {
+			let depth = self.backend.depth_at(&id)?;
+			let mut weight = 0;
+			for target in &snapshot.targets {
+				if self.backend.ancestor_at(target, depth)? == id {
+					weight += 1;
+				}
+			}
+			for child in self.backend.children_at(&id)? {
+				queue.push_back((child, Some(id.clone())));
+			}
+
+			nodes.push(TreeNode {
+				justified: id == justified,
+				head: id == head,
+				id, parent, depth, weight,
+			});
+		}
+
+		Ok(nodes)
+	}
+
+	/// Targets of the latest votes known to the fork choice.
+	pub fn vote_targets(&self) -> Vec<<Ba::Block as Block>::Identifier> {
+		self.snapshot.read().expect("Lock is poisoned").targets.clone()
+	}
+}
+
 #[derive(Debug)]
 pub enum Error {
 	IsGenesis,
+	UnknownBlock,
+	InvalidWeight,
+	NoViableHead,
 	Backend(Box<dyn std::error::Error>),
 	Executor(Box<dyn std::error::Error>),
 }
@@ -179,6 +253,7 @@ pub struct ArchiveGhostImporter<E: BlockExecutor, Ba: Store<Block=E::Block>> whe
 	ghost: ArchiveGhost<Ba, E::ValidatorIndex>,
 	import_lock: ImportLock,
 	executor: E,
+	snapshot: Arc<RwLock<ForkChoiceSnapshot<<Ba::Block as Block>::Identifier>>>,
 }
 
 impl<E: BlockExecutor, Ba: SharedCommittable + Store<Block=E::Block>> ArchiveGhostImporter<E, Ba> where
@@ -190,6 +265,21 @@ impl<E: BlockExecutor, Ba: SharedCommittable + Store<Block=E::Block>> ArchiveGho
 		Self {
 			executor, import_lock,
 			ghost: ArchiveGhost::new(backend),
+			snapshot: Arc::new(RwLock::new(ForkChoiceSnapshot {
+				justified: None,
+				head: None,
+				targets: Vec::new(),
+			})),
+		}
+	}
+
+	/// Get a read-only view of the fork choice, updated on each import.
+	pub fn fork_choice(&self) -> ForkChoiceView<Ba> where
+		Ba: Clone,
+	{
+		ForkChoiceView {
+			backend: self.ghost.backend.clone(),
+			snapshot: self.snapshot.clone(),
 		}
 	}
 }
@@ -256,7 +346,7 @@ impl<E: BlockExecutor, Ba: Store<Block=E::Block>> RawImporter for ArchiveGhostIm
 			(justified_active_validators, justified_block_id, votes)
 		};
 
-		for (k, v) in votes {
+		for (k, v, _) in votes {
 			self.ghost.update_overlay(k, v);
 		}
 		self.ghost.update_active(&justified_active_validators);
@@ -271,10 +361,17 @@ impl<E: BlockExecutor, Ba: Store<Block=E::Block>> RawImporter for ArchiveGhostIm
 		let mut importer = ImportAction::new(
 			&self.ghost.backend, self.import_lock.lock()
 		);
-		importer.set_head(new_head);
+		importer.set_head(new_head.clone());
 
 		match importer.commit() {
-			Ok(()) => { self.ghost.commit_overlay(); },
+			Ok(()) => {
+				self.ghost.commit_overlay();
+
+				let mut snapshot = self.snapshot.write().expect("Lock is poisoned");
+				snapshot.justified = Some(justified_block_id);
+				snapshot.head = Some(new_head);
+				snapshot.targets = self.ghost.votes.values().cloned().collect();
+			},
 			Err(_) => { self.ghost.reset_overlay(); },
 		}
 
@@ -0,0 +1,88 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Chain events emitted by fork choice importers.
+
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+/// Chain event.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ChainEvent<Id> {
+	/// A block was imported.
+	Imported {
+		/// Imported block.
+		block: Id,
+	},
+	/// The head moved to a descendant of the previous head.
+	NewHead {
+		/// New head.
+		head: Id,
+	},
+	/// The head moved to a block not descending from the previous head.
+	Reorg {
+		/// Previous head.
+		old_head: Id,
+		/// New head.
+		new_head: Id,
+		/// Number of blocks of the previous head not in the new chain.
+		depth: usize,
+	},
+	/// A new checkpoint was finalized.
+	Finalized {
+		/// Finalized epoch.
+		epoch: u64,
+		/// Finalized block.
+		root: Id,
+	},
+}
+
+/// Subscriptions to chain events, shared between an importer and its
+/// subscribers.
+pub struct ChainEvents<Id>(Arc<Mutex<Vec<Sender<ChainEvent<Id>>>>>);
+
+impl<Id> Clone for ChainEvents<Id> {
+	fn clone(&self) -> Self {
+		Self(self.0.clone())
+	}
+}
+
+impl<Id: Clone> ChainEvents<Id> {
+	/// Create new subscriptions.
+	pub fn new() -> Self {
+		Self(Arc::new(Mutex::new(Vec::new())))
+	}
+
+	/// Subscribe to chain events. Events are buffered until received, and
+	/// the subscription is dropped along with the receiver.
+	pub fn subscribe(&self) -> Receiver<ChainEvent<Id>> {
+		let (sender, receiver) = mpsc::channel();
+		self.0.lock().expect("Lock is poisoned").push(sender);
+		receiver
+	}
+
+	/// Send an event to all subscribers.
+	pub fn emit(&self, event: ChainEvent<Id>) {
+		self.0.lock().expect("Lock is poisoned")
+			.retain(|sender| sender.send(event.clone()).is_ok());
+	}
+}
+
+impl<Id: Clone> Default for ChainEvents<Id> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
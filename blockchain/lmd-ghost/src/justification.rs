@@ -0,0 +1,128 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Tracking of the fork choice justified checkpoint, following the spec's
+//! rules on when it may be updated within an epoch.
+
+/// Number of slots at the start of an epoch during which the justified
+/// checkpoint may be updated to any newer one. Later in the epoch, only
+/// descendants of the current justified block are accepted right away.
+pub const SAFE_SLOTS_TO_UPDATE_JUSTIFIED: u64 = 8;
+
+/// Justified and best justified checkpoints of the fork choice, as epoch
+/// and block identifier pairs.
+///
+/// A newer justified checkpoint seen late in an epoch that conflicts with
+/// the current one is only kept as the best justified checkpoint, and
+/// adopted at the start of the next epoch. This prevents bouncing attacks
+/// that keep the fork choice switching between justified checkpoints.
+#[derive(Clone, Debug)]
+pub struct JustificationTracker<Id> {
+	slots_per_epoch: Option<u64>,
+	safe_slots: u64,
+	epoch: u64,
+	justified: (u64, Id),
+	best_justified: (u64, Id),
+}
+
+impl<Id: Clone + PartialEq> JustificationTracker<Id> {
+	/// Create a tracker starting from the given justified checkpoint.
+	/// Without slot timing, newer justified checkpoints are always adopted
+	/// immediately.
+	pub fn new(justified: (u64, Id)) -> Self {
+		Self {
+			slots_per_epoch: None,
+			safe_slots: SAFE_SLOTS_TO_UPDATE_JUSTIFIED,
+			epoch: 0,
+			best_justified: justified.clone(),
+			justified,
+		}
+	}
+
+	/// Enforce the safe slots rule, with the given epoch length and number
+	/// of safe slots.
+	pub fn with_safe_slots(mut self, slots_per_epoch: u64, safe_slots: u64) -> Self {
+		self.slots_per_epoch = Some(std::cmp::max(slots_per_epoch, 1));
+		self.safe_slots = safe_slots;
+		self
+	}
+
+	/// Current justified checkpoint.
+	pub fn justified(&self) -> &(u64, Id) {
+		&self.justified
+	}
+
+	/// Best justified checkpoint seen, to be adopted at the next epoch.
+	pub fn best_justified(&self) -> &(u64, Id) {
+		&self.best_justified
+	}
+
+	/// Note the current slot. At the start of a new epoch, the best
+	/// justified checkpoint is adopted. Returns whether the justified
+	/// checkpoint changed.
+	pub fn on_tick(&mut self, slot: u64) -> bool {
+		let slots_per_epoch = match self.slots_per_epoch {
+			Some(slots_per_epoch) => slots_per_epoch,
+			None => return false,
+		};
+
+		let epoch = slot / slots_per_epoch;
+		if epoch <= self.epoch {
+			return false
+		}
+		self.epoch = epoch;
+
+		if self.best_justified.0 > self.justified.0 {
+			self.justified = self.best_justified.clone();
+			return true
+		}
+		false
+	}
+
+	/// Note the justified checkpoint of a new block's state at the current
+	/// `slot`. If the block also advanced finality, a newer justified
+	/// checkpoint is always adopted. Otherwise it is adopted during the
+	/// safe slots, or if `is_descendant` holds for the current justified
+	/// block and the new one. Returns whether the justified checkpoint
+	/// changed.
+	pub fn on_block<E, F>(
+		&mut self,
+		justified: (u64, Id),
+		finality_advanced: bool,
+		slot: u64,
+		is_descendant: F,
+	) -> Result<bool, E> where
+		F: FnOnce(&Id, &Id) -> Result<bool, E>,
+	{
+		if justified.0 <= self.justified.0 {
+			return Ok(false)
+		}
+
+		if justified.0 > self.best_justified.0 {
+			self.best_justified = justified.clone();
+		}
+
+		let safe = match self.slots_per_epoch {
+			Some(slots_per_epoch) => slot % slots_per_epoch < self.safe_slots,
+			None => true,
+		};
+		if finality_advanced || safe || is_descendant(&self.justified.1, &justified.1)? {
+			self.justified = justified;
+			return Ok(true)
+		}
+		Ok(false)
+	}
+}
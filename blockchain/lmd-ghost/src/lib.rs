@@ -14,6 +14,9 @@
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
 pub mod archive;
+pub mod events;
+pub mod justification;
+pub mod proto_array;
 
 use blockchain::{Block, BlockExecutor};
 use core::hash::Hash;
@@ -29,9 +32,21 @@ pub trait JustifiableExecutor: BlockExecutor {
 		&self,
 		state: &mut Self::Externalities, // FIXME: replace `&mut` with `&`.
 	) -> Result<Option<<Self::Block as Block>::Identifier>, Self::Error>;
+	fn finalized_block_id(
+		&self,
+		state: &mut Self::Externalities, // FIXME: replace `&mut` with `&`.
+	) -> Result<Option<<Self::Block as Block>::Identifier>, Self::Error>;
+	fn checkpoint_epochs(
+		&self,
+		state: &mut Self::Externalities, // FIXME: replace `&mut` with `&`.
+	) -> Result<(u64, u64), Self::Error>;
+	fn justified_balances(
+		&self,
+		state: &mut Self::Externalities, // FIXME: replace `&mut` with `&`.
+	) -> Result<Vec<(Self::ValidatorIndex, u64)>, Self::Error>;
 	fn votes(
 		&self,
 		block: &Self::Block,
 		state: &mut Self::Externalities, // FIXME: replace `&mut` with `&`.
-	) -> Result<Vec<(Self::ValidatorIndex, <Self::Block as Block>::Identifier)>, Self::Error>;
+	) -> Result<Vec<(Self::ValidatorIndex, <Self::Block as Block>::Identifier, u64)>, Self::Error>;
 }
@@ -0,0 +1,744 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Proto-array LMD-GHOST, keeping per-block weights and best descendants
+//! updated incrementally, so that finding the head does not walk the tree.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
+use core::hash::Hash;
+use blockchain::{Block, Auxiliary, BlockExecutor, AsExternalities};
+use blockchain::import::{BlockImporter, RawImporter, ImportAction};
+use blockchain::backend::{Store, SharedCommittable, ImportOperation, ChainQuery, ImportLock, Operation};
+use log::warn;
+use crate::JustifiableExecutor;
+use crate::archive::{AncestorQuery, ForkChoiceView, ForkChoiceSnapshot, Error};
+use crate::events::{ChainEvent, ChainEvents};
+use crate::justification::JustificationTracker;
+
+#[derive(Clone, Debug)]
+struct ProtoNode<Id> {
+	id: Id,
+	parent: Option<usize>,
+	justified_epoch: u64,
+	finalized_epoch: u64,
+	weight: u64,
+	best_child: Option<usize>,
+	best_descendant: Option<usize>,
+}
+
+/// Block tree stored as an array in insertion order, so that parents
+/// always come before their children.
+///
+/// Blocks whose state disagrees with the fork choice justified and
+/// finalized epochs are filtered out of head selection, as in the spec's
+/// `filter_block_tree`.
+#[derive(Clone, Debug)]
+pub struct ProtoArray<Id: Eq + Hash> {
+	nodes: Vec<ProtoNode<Id>>,
+	indices: HashMap<Id, usize>,
+	justified_epoch: u64,
+	finalized_epoch: u64,
+}
+
+impl<Id: Clone + Eq + Hash + Ord> ProtoArray<Id> {
+	/// Create a new empty proto-array.
+	pub fn new() -> Self {
+		Self {
+			nodes: Vec::new(),
+			indices: HashMap::new(),
+			justified_epoch: 0,
+			finalized_epoch: 0,
+		}
+	}
+
+	/// Number of blocks in the array.
+	pub fn len(&self) -> usize {
+		self.nodes.len()
+	}
+
+	/// Whether the array is empty.
+	pub fn is_empty(&self) -> bool {
+		self.nodes.is_empty()
+	}
+
+	/// Whether the block is known.
+	pub fn contains(&self, id: &Id) -> bool {
+		self.indices.contains_key(id)
+	}
+
+	/// Index of a block, used for weight deltas.
+	pub fn index_of(&self, id: &Id) -> Option<usize> {
+		self.indices.get(id).cloned()
+	}
+
+	/// Weight of a block, including all votes for its descendants.
+	pub fn weight(&self, id: &Id) -> Option<u64> {
+		self.index_of(id).map(|index| self.nodes[index].weight)
+	}
+
+	/// Add a new block, with the justified and finalized epochs of its
+	/// state. A block whose parent is unknown becomes a root.
+	pub fn on_block(
+		&mut self,
+		id: Id,
+		parent: Option<Id>,
+		justified_epoch: u64,
+		finalized_epoch: u64,
+	) {
+		if self.indices.contains_key(&id) {
+			return
+		}
+
+		let index = self.nodes.len();
+		let parent = parent.and_then(|parent| self.index_of(&parent));
+		self.indices.insert(id.clone(), index);
+		self.nodes.push(ProtoNode {
+			id, parent, justified_epoch, finalized_epoch,
+			weight: 0,
+			best_child: None,
+			best_descendant: None,
+		});
+
+		if let Some(parent) = parent {
+			self.update_best_child(parent, index);
+		}
+	}
+
+	/// Apply weight deltas, one per block index, and the fork choice
+	/// justified and finalized epochs, then update the best child and best
+	/// descendant of every block.
+	pub fn apply_score_changes(
+		&mut self,
+		mut deltas: Vec<i64>,
+		justified_epoch: u64,
+		finalized_epoch: u64,
+	) -> Result<(), Error> {
+		if deltas.len() != self.nodes.len() {
+			return Err(Error::InvalidWeight)
+		}
+		self.justified_epoch = justified_epoch;
+		self.finalized_epoch = finalized_epoch;
+
+		for index in (0..self.nodes.len()).rev() {
+			let delta = deltas[index];
+			let node = &mut self.nodes[index];
+			node.weight = if delta < 0 {
+				node.weight.checked_sub(delta.wrapping_neg() as u64)
+			} else {
+				node.weight.checked_add(delta as u64)
+			}.ok_or(Error::InvalidWeight)?;
+
+			if let Some(parent) = node.parent {
+				deltas[parent] += delta;
+			}
+		}
+
+		for index in (0..self.nodes.len()).rev() {
+			if let Some(parent) = self.nodes[index].parent {
+				self.update_best_child(parent, index);
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Whether a block is the given ancestor or one of its descendants.
+	pub fn is_descendant(&self, ancestor: &Id, id: &Id) -> bool {
+		let ancestor = match self.index_of(ancestor) {
+			Some(ancestor) => ancestor,
+			None => return false,
+		};
+
+		let mut current = self.index_of(id);
+		while let Some(index) = current {
+			// Parents always come before their children.
+			if index <= ancestor {
+				return index == ancestor
+			}
+			current = self.nodes[index].parent;
+		}
+		false
+	}
+
+	/// Find the head descending from the justified block.
+	pub fn find_head(&self, justified: &Id) -> Result<Id, Error> {
+		let index = self.index_of(justified).ok_or(Error::UnknownBlock)?;
+		let best = self.nodes[index].best_descendant.unwrap_or(index);
+		if !self.is_viable(best) {
+			return Err(Error::NoViableHead)
+		}
+		Ok(self.nodes[best].id.clone())
+	}
+
+	/// Remove all blocks not descending from the given root. Returns the
+	/// number of removed blocks.
+	pub fn prune(&mut self, root: &Id) -> Result<usize, Error> {
+		let root = self.index_of(root).ok_or(Error::UnknownBlock)?;
+
+		let mut remap: Vec<Option<usize>> = vec![None; self.nodes.len()];
+		let mut nodes = Vec::new();
+		for (index, node) in self.nodes.iter().enumerate() {
+			let parent = node.parent.and_then(|parent| remap[parent]);
+			if index != root && parent.is_none() {
+				continue
+			}
+
+			remap[index] = Some(nodes.len());
+			let mut node = node.clone();
+			node.parent = if index == root { None } else { parent };
+			nodes.push(node);
+		}
+
+		for node in &mut nodes {
+			node.best_child = node.best_child.and_then(|index| remap[index]);
+			node.best_descendant = node.best_descendant.and_then(|index| remap[index]);
+		}
+
+		let removed = self.nodes.len() - nodes.len();
+		self.indices = nodes.iter().enumerate()
+			.map(|(index, node)| (node.id.clone(), index))
+			.collect();
+		self.nodes = nodes;
+		Ok(removed)
+	}
+
+	/// Whether a block agrees with the fork choice justified and finalized
+	/// epochs. Any block is viable before the first justification.
+	fn is_viable(&self, index: usize) -> bool {
+		let node = &self.nodes[index];
+		(node.justified_epoch == self.justified_epoch || self.justified_epoch == 0) &&
+			(node.finalized_epoch == self.finalized_epoch || self.finalized_epoch == 0)
+	}
+
+	fn leads_to_viable_head(&self, index: usize) -> bool {
+		self.nodes[index].best_descendant.map(|best| self.is_viable(best)).unwrap_or(false) ||
+			self.is_viable(index)
+	}
+
+	fn update_best_child(&mut self, parent: usize, child: usize) {
+		let child_viable = self.leads_to_viable_head(child);
+		let to_child = (Some(child), Some(self.nodes[child].best_descendant.unwrap_or(child)));
+		let no_change = (self.nodes[parent].best_child, self.nodes[parent].best_descendant);
+
+		let (best_child, best_descendant) = match self.nodes[parent].best_child {
+			Some(best) if best == child => if child_viable { to_child } else { (None, None) },
+			Some(best) => {
+				let best_viable = self.leads_to_viable_head(best);
+				let (child_node, best_node) = (&self.nodes[child], &self.nodes[best]);
+				if child_viable != best_viable {
+					if child_viable { to_child } else { no_change }
+				// Ties are broken by the greater block identifier.
+				} else if (child_node.weight, &child_node.id) > (best_node.weight, &best_node.id) {
+					to_child
+				} else {
+					no_change
+				}
+			},
+			None => if child_viable { to_child } else { no_change },
+		};
+
+		self.nodes[parent].best_child = best_child;
+		self.nodes[parent].best_descendant = best_descendant;
+	}
+}
+
+impl<Id: Clone + Eq + Hash + Ord> Default for ProtoArray<Id> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Number of slots after which queued attestations for blocks that are
+/// still unknown are dropped.
+pub const QUEUE_EXPIRY_SLOTS: u64 = 64;
+
+struct QueuedVote<Id> {
+	slot: u64,
+	epoch: u64,
+	target: Id,
+}
+
+struct QueueInner<VI, Id> {
+	current_slot: u64,
+	votes: HashMap<VI, QueuedVote<Id>>,
+}
+
+/// Attestations received outside of blocks, held until the slot after their
+/// own before being applied to the fork choice on the next import. Only the
+/// latest attestation of each validator is kept.
+pub struct AttestationQueue<VI, Id>(Arc<Mutex<QueueInner<VI, Id>>>);
+
+impl<VI, Id> Clone for AttestationQueue<VI, Id> {
+	fn clone(&self) -> Self {
+		Self(self.0.clone())
+	}
+}
+
+impl<VI: Eq + Hash, Id> AttestationQueue<VI, Id> {
+	/// Create a new empty queue.
+	pub fn new() -> Self {
+		Self(Arc::new(Mutex::new(QueueInner {
+			current_slot: 0,
+			votes: HashMap::new(),
+		})))
+	}
+
+	/// Queue an attestation of the given validators at `slot`, voting for
+	/// the head block `target`, at target `epoch`.
+	pub fn push(&self, slot: u64, epoch: u64, validators: Vec<VI>, target: Id) where
+		Id: Clone,
+	{
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+		for validator in validators {
+			let newer = inner.votes.get(&validator).map(|vote| epoch > vote.epoch).unwrap_or(true);
+			if newer {
+				inner.votes.insert(validator, QueuedVote { slot, epoch, target: target.clone() });
+			}
+		}
+	}
+
+	/// Note the current slot, and drop expired attestations.
+	pub fn on_tick(&self, slot: u64) {
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+		inner.current_slot = slot;
+		inner.votes.retain(|_, vote| vote.slot + QUEUE_EXPIRY_SLOTS >= slot);
+	}
+
+	/// Number of queued attestations.
+	pub fn len(&self) -> usize {
+		self.0.lock().expect("Lock is poisoned").votes.len()
+	}
+
+	/// Whether the queue is empty.
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Take attestations whose slot is over, for which `is_known` holds.
+	fn take_ready<F: Fn(&Id) -> bool>(&self, is_known: F) -> Vec<(VI, Id, u64)> where
+		VI: Clone,
+	{
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+		let current_slot = inner.current_slot;
+		let ready = inner.votes.iter()
+			.filter(|(_, vote)| vote.slot < current_slot && is_known(&vote.target))
+			.map(|(validator, _)| validator.clone())
+			.collect::<Vec<_>>();
+		ready.into_iter()
+			.filter_map(|validator| inner.votes.remove(&validator)
+						.map(|vote| (validator, vote.target, vote.epoch)))
+			.collect()
+	}
+}
+
+impl<VI: Eq + Hash, Id> Default for AttestationQueue<VI, Id> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+struct Vote<Id> {
+	current: Option<Id>,
+	next: Id,
+	epoch: u64,
+}
+
+/// Block importer running LMD-GHOST fork choice over a proto-array. Votes
+/// are weighted by the balances of the justified validators, and come both
+/// from imported blocks and from the attestation queue.
+///
+/// Unless enabled with `with_safe_slots`, newer justified checkpoints are
+/// adopted as soon as they are seen.
+pub struct ProtoArrayGhostImporter<E: BlockExecutor, Ba: Store<Block=E::Block>> where
+	E: JustifiableExecutor,
+	Ba::Auxiliary: Auxiliary<E::Block>
+{
+	backend: Ba,
+	array: ProtoArray<<Ba::Block as Block>::Identifier>,
+	votes: HashMap<E::ValidatorIndex, Vote<<Ba::Block as Block>::Identifier>>,
+	justification: JustificationTracker<<Ba::Block as Block>::Identifier>,
+	finalized: (u64, <Ba::Block as Block>::Identifier),
+	events: ChainEvents<<Ba::Block as Block>::Identifier>,
+	balances: HashMap<E::ValidatorIndex, u64>,
+	balances_root: Option<<Ba::Block as Block>::Identifier>,
+	queue: AttestationQueue<E::ValidatorIndex, <Ba::Block as Block>::Identifier>,
+	import_lock: ImportLock,
+	executor: E,
+	slot_clock: Option<Box<dyn Fn() -> Option<u64> + Send + Sync>>,
+	snapshot: Arc<RwLock<ForkChoiceSnapshot<<Ba::Block as Block>::Identifier>>>,
+}
+
+impl<E: BlockExecutor, Ba: SharedCommittable + Store<Block=E::Block>> ProtoArrayGhostImporter<E, Ba> where
+	E: JustifiableExecutor,
+	E::ValidatorIndex: Clone,
+	Ba: AncestorQuery + ChainQuery,
+	Ba::Auxiliary: Auxiliary<E::Block>,
+	Ba::State: AsExternalities<E::Externalities>,
+	<Ba::Block as Block>::Identifier: Ord,
+{
+	/// Create a new importer, loading the finalized block of the head state
+	/// and all its known descendants into the proto-array. Only states of
+	/// those blocks are read, as states of earlier blocks may be pruned.
+	pub fn new(executor: E, backend: Ba, import_lock: ImportLock) -> Result<Self, Error> {
+		let mut head_state = backend.state_at(&backend.head())
+			.map_err(|e| Error::Backend(Box::new(e)))?;
+		let (mut justified, mut finalized) =
+			Self::checkpoints(&executor, &backend, head_state.as_externalities())?;
+
+		let mut array = ProtoArray::new();
+		let mut queue = VecDeque::new();
+		queue.push_back((finalized.1.clone(), None));
+		while let Some((id, parent)) = queue.pop_front() {
+			for child in backend.children_at(&id).map_err(|e| Error::Backend(Box::new(e)))? {
+				queue.push_back((child, Some(id.clone())));
+			}
+
+			let mut state = backend.state_at(&id).map_err(|e| Error::Backend(Box::new(e)))?;
+			let (block_justified, block_finalized) =
+				Self::checkpoints(&executor, &backend, state.as_externalities())?;
+			array.on_block(id, parent, block_justified.0, block_finalized.0);
+			if block_justified.0 > justified.0 {
+				justified = block_justified;
+			}
+			if block_finalized.0 > finalized.0 {
+				finalized = block_finalized;
+			}
+		}
+		array.apply_score_changes(vec![0; array.len()], justified.0, finalized.0)?;
+
+		Ok(Self {
+			executor, import_lock, backend, array, finalized,
+			justification: JustificationTracker::new(justified),
+			events: ChainEvents::new(),
+			votes: HashMap::new(),
+			balances: HashMap::new(),
+			balances_root: None,
+			queue: AttestationQueue::new(),
+			slot_clock: None,
+			snapshot: Arc::new(RwLock::new(ForkChoiceSnapshot {
+				justified: None,
+				head: None,
+				targets: Vec::new(),
+			})),
+		})
+	}
+
+	/// Only update the justified checkpoint to one that does not descend
+	/// from the current justified block during the first `safe_slots` of an
+	/// epoch, as the spec's `should_update_justified_checkpoint`. Later
+	/// ones are adopted at the start of the next epoch. The current slot is
+	/// read from `slot_clock` on each import, and is `None` before genesis.
+	pub fn with_safe_slots<F>(mut self, slots_per_epoch: u64, safe_slots: u64, slot_clock: F) -> Self where
+		F: Fn() -> Option<u64> + Send + Sync + 'static,
+	{
+		self.justification = self.justification.with_safe_slots(slots_per_epoch, safe_slots);
+		self.slot_clock = Some(Box::new(slot_clock));
+		self
+	}
+
+	/// Get a read-only view of the fork choice, updated on each import.
+	pub fn fork_choice(&self) -> ForkChoiceView<Ba> where
+		Ba: Clone,
+	{
+		ForkChoiceView {
+			backend: self.backend.clone(),
+			snapshot: self.snapshot.clone(),
+		}
+	}
+
+	/// Justified and finalized checkpoints of a state.
+	fn checkpoints(
+		executor: &E,
+		backend: &Ba,
+		state: &mut E::Externalities,
+	) -> Result<(
+		(u64, <Ba::Block as Block>::Identifier),
+		(u64, <Ba::Block as Block>::Identifier),
+	), Error> {
+		let (justified_epoch, finalized_epoch) = executor.checkpoint_epochs(state)
+			.map_err(|e| Error::Executor(Box::new(e)))?;
+		let justified_block_id = executor.justified_block_id(state)
+			.map_err(|e| Error::Executor(Box::new(e)))?
+			.unwrap_or_else(|| backend.genesis());
+		let finalized_block_id = executor.finalized_block_id(state)
+			.map_err(|e| Error::Executor(Box::new(e)))?
+			.unwrap_or_else(|| backend.genesis());
+		Ok(((justified_epoch, justified_block_id), (finalized_epoch, finalized_block_id)))
+	}
+
+	/// Number of blocks tracked by the fork choice, from the finalized
+	/// block on.
+	pub fn block_count(&self) -> usize {
+		self.array.len()
+	}
+
+	/// Subscriptions to imports, head changes, reorgs and finalization.
+	pub fn events(&self) -> ChainEvents<<Ba::Block as Block>::Identifier> {
+		self.events.clone()
+	}
+
+	/// Event of a head change, with the reorg depth if the new head does not
+	/// descend from the old one.
+	fn head_event(
+		&self,
+		old_head: <Ba::Block as Block>::Identifier,
+		new_head: <Ba::Block as Block>::Identifier,
+	) -> Result<ChainEvent<<Ba::Block as Block>::Identifier>, Ba::Error> {
+		let old_depth = self.backend.depth_at(&old_head)?;
+		let new_depth = self.backend.depth_at(&new_head)?;
+		if new_depth >= old_depth && self.backend.ancestor_at(&new_head, old_depth)? == old_head {
+			return Ok(ChainEvent::NewHead { head: new_head })
+		}
+
+		let mut common_depth = core::cmp::min(old_depth, new_depth);
+		while self.backend.ancestor_at(&old_head, common_depth)? !=
+			self.backend.ancestor_at(&new_head, common_depth)?
+		{
+			common_depth -= 1;
+		}
+		Ok(ChainEvent::Reorg { old_head, new_head, depth: old_depth - common_depth })
+	}
+
+	/// Queue of attestations received outside of blocks.
+	pub fn attestation_queue(
+		&self
+	) -> AttestationQueue<E::ValidatorIndex, <Ba::Block as Block>::Identifier> {
+		self.queue.clone()
+	}
+
+	/// Record a vote if it is the latest of its validator.
+	fn note_vote(
+		&mut self,
+		validator: E::ValidatorIndex,
+		target: <Ba::Block as Block>::Identifier,
+		epoch: u64,
+	) {
+		if !self.array.contains(&target) {
+			return
+		}
+
+		match self.votes.get_mut(&validator) {
+			Some(vote) => if epoch > vote.epoch {
+				vote.next = target;
+				vote.epoch = epoch;
+			},
+			None => {
+				self.votes.insert(validator, Vote { current: None, next: target, epoch });
+			},
+		}
+	}
+
+	/// Remove blocks not descending from the finalized block, and votes
+	/// that no longer weigh any block. Nothing is removed while the
+	/// justified block does not descend from the finalized block.
+	fn prune_finalized(&mut self) -> Result<(), Error> {
+		if !self.array.is_descendant(&self.finalized.1, &self.justification.justified().1) {
+			return Ok(())
+		}
+
+		if self.array.prune(&self.finalized.1)? > 0 {
+			// A vote whose next target was pruned is kept until its weight
+			// is taken off its current target.
+			let array = &self.array;
+			self.votes.retain(|_, vote| {
+				array.contains(&vote.next) ||
+					vote.current.as_ref().map(|current| array.contains(current)).unwrap_or(false)
+			});
+		}
+		Ok(())
+	}
+
+	/// Balances of the active validators of the justified checkpoint state,
+	/// if the justified checkpoint changed since they were last loaded.
+	fn updated_balances(
+		&mut self,
+		justified: &<Ba::Block as Block>::Identifier,
+	) -> Result<Option<HashMap<E::ValidatorIndex, u64>>, Error> {
+		if self.balances_root.as_ref() == Some(justified) {
+			return Ok(None)
+		}
+
+		let mut state = self.backend.state_at(justified)
+			.map_err(|e| Error::Backend(Box::new(e)))?;
+		let balances = self.executor.justified_balances(state.as_externalities())
+			.map_err(|e| Error::Executor(Box::new(e)))?;
+		self.balances_root = Some(justified.clone());
+		Ok(Some(balances.into_iter().collect()))
+	}
+
+	/// Move votes to their latest targets, and reweight them with the new
+	/// justified balances, if any, returning the resulting weight deltas.
+	fn vote_deltas(&mut self, balances: Option<HashMap<E::ValidatorIndex, u64>>) -> Vec<i64> {
+		let mut deltas = vec![0; self.array.len()];
+
+		for (validator, vote) in self.votes.iter_mut() {
+			let old_balance = self.balances.get(validator).cloned().unwrap_or(0);
+			let new_balance = balances.as_ref().unwrap_or(&self.balances)
+				.get(validator).cloned().unwrap_or(0);
+			if vote.current.as_ref() == Some(&vote.next) && old_balance == new_balance {
+				continue
+			}
+
+			if let Some(index) = vote.current.as_ref().and_then(|id| self.array.index_of(id)) {
+				deltas[index] -= old_balance as i64;
+			}
+			if let Some(index) = self.array.index_of(&vote.next) {
+				deltas[index] += new_balance as i64;
+			}
+			vote.current = Some(vote.next.clone());
+		}
+
+		if let Some(balances) = balances {
+			self.balances = balances;
+		}
+		deltas
+	}
+}
+
+impl<E: BlockExecutor, Ba: Store<Block=E::Block>> BlockImporter for ProtoArrayGhostImporter<E, Ba> where
+	E: JustifiableExecutor,
+	E::ValidatorIndex: Clone,
+	Ba: ChainQuery + AncestorQuery,
+	Ba: SharedCommittable<Operation=Operation<E::Block, <Ba as Store>::State, <Ba as Store>::Auxiliary>>,
+	Ba::Auxiliary: Auxiliary<E::Block>,
+	Ba::State: AsExternalities<E::Externalities>,
+	<Ba::Block as Block>::Identifier: Ord,
+{
+	type Block = Ba::Block;
+	type Error = Error;
+
+	fn import_block(&mut self, block: Ba::Block) -> Result<(), Self::Error> {
+		let mut state = self.backend
+			.state_at(
+				&block.parent_id().ok_or(Error::IsGenesis)?
+			)
+			.map_err(|e| Error::Backend(Box::new(e)))?;
+		self.executor.execute_block(&block, state.as_externalities())
+			.map_err(|e| Error::Executor(Box::new(e)))?;
+
+		self.import_raw(ImportOperation { block, state })
+	}
+}
+
+impl<E: BlockExecutor, Ba: Store<Block=E::Block>> RawImporter for ProtoArrayGhostImporter<E, Ba> where
+	E: JustifiableExecutor,
+	E::ValidatorIndex: Clone,
+	Ba: AncestorQuery + ChainQuery,
+	Ba: SharedCommittable<Operation=Operation<E::Block, <Ba as Store>::State, <Ba as Store>::Auxiliary>>,
+	Ba::Auxiliary: Auxiliary<E::Block>,
+	Ba::State: AsExternalities<E::Externalities>,
+	<Ba::Block as Block>::Identifier: Ord,
+{
+	type Operation = ImportOperation<Ba::Block, Ba::State>;
+	type Error = Error;
+
+	fn import_raw(
+		&mut self,
+		mut raw: ImportOperation<Ba::Block, Ba::State>
+	) -> Result<(), Self::Error> {
+		let span = tracing::debug_span!("import_block");
+		let _enter = span.enter();
+		let block_id = raw.block.id();
+		let parent_id = raw.block.parent_id();
+		let (checkpoints, votes) = {
+			let externalities = raw.state.as_externalities();
+			let checkpoints = Self::checkpoints(&self.executor, &self.backend, externalities)?;
+			let votes = self.executor
+				.votes(&raw.block, externalities)
+				.map_err(|e| Error::Executor(Box::new(e)))?;
+
+			let mut importer = ImportAction::new(
+				&self.backend, self.import_lock.lock()
+			);
+			importer.import_raw(raw);
+			{
+				let span = tracing::debug_span!("commit");
+				let _enter = span.enter();
+				importer.commit().map_err(|e| Error::Backend(Box::new(e)))?;
+			}
+
+			(checkpoints, votes)
+		};
+
+		self.events.emit(ChainEvent::Imported { block: block_id.clone() });
+
+		let (block_justified, block_finalized) = checkpoints;
+		self.array.on_block(block_id, parent_id, block_justified.0, block_finalized.0);
+
+		let current_slot = self.slot_clock.as_ref()
+			.and_then(|slot_clock| slot_clock())
+			.unwrap_or(0);
+		self.justification.on_tick(current_slot);
+		let backend = &self.backend;
+		self.justification.on_block(
+			block_justified,
+			block_finalized.0 > self.finalized.0,
+			current_slot,
+			|ancestor, id| {
+				let ancestor_depth = backend.depth_at(ancestor)?;
+				Ok(backend.depth_at(id)? >= ancestor_depth &&
+				   backend.ancestor_at(id, ancestor_depth)? == *ancestor)
+			},
+		).map_err(|e: Ba::Error| Error::Backend(Box::new(e)))?;
+
+		if block_finalized.0 > self.finalized.0 {
+			self.finalized = block_finalized;
+			self.events.emit(ChainEvent::Finalized {
+				epoch: self.finalized.0,
+				root: self.finalized.1.clone(),
+			});
+			self.prune_finalized()?;
+		}
+
+		let array = &self.array;
+		let queued = self.queue.take_ready(|id| array.contains(id));
+		for (validator, target, epoch) in votes.into_iter().chain(queued) {
+			self.note_vote(validator, target, epoch);
+		}
+		let justified = self.justification.justified().clone();
+		let balances = self.updated_balances(&justified.1)?;
+		let deltas = self.vote_deltas(balances);
+		self.array.apply_score_changes(deltas, justified.0, self.finalized.0)?;
+		let new_head = self.array.find_head(&justified.1)?;
+		let old_head = self.backend.head();
+
+		let mut importer = ImportAction::new(
+			&self.backend, self.import_lock.lock()
+		);
+		importer.set_head(new_head.clone());
+
+		if importer.commit().is_ok() {
+			{
+				let mut snapshot = self.snapshot.write().expect("Lock is poisoned");
+				snapshot.justified = Some(justified.1);
+				snapshot.head = Some(new_head.clone());
+				snapshot.targets = self.votes.values()
+					.filter_map(|vote| vote.current.clone())
+					.collect();
+			}
+
+			if new_head != old_head {
+				match self.head_event(old_head, new_head) {
+					Ok(event) => self.events.emit(event),
+					Err(e) => warn!("Unable to determine head change: {:?}", e),
+				}
+			}
+		}
+
+		Ok(())
+	}
+}
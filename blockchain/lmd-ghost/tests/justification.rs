@@ -0,0 +1,133 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Tests of the justified checkpoint updates within and across epochs.
+
+use lmd_ghost::justification::{JustificationTracker, SAFE_SLOTS_TO_UPDATE_JUSTIFIED};
+
+const SLOTS_PER_EPOCH: u64 = 8;
+const SAFE_SLOTS: u64 = 2;
+
+fn tracker() -> JustificationTracker<&'static str> {
+	JustificationTracker::new((0, "genesis")).with_safe_slots(SLOTS_PER_EPOCH, SAFE_SLOTS)
+}
+
+fn not_descendant(_: &&'static str, _: &&'static str) -> Result<bool, ()> {
+	Ok(false)
+}
+
+#[test]
+fn safe_slots_adopt_any_newer_checkpoint() {
+	let mut tracker = tracker();
+	tracker.on_tick(8);
+
+	let updated = tracker.on_block((1, "a"), false, 8 + SAFE_SLOTS - 1, not_descendant).unwrap();
+	assert!(updated);
+	assert_eq!(tracker.justified(), &(1, "a"));
+	assert_eq!(tracker.best_justified(), &(1, "a"));
+}
+
+#[test]
+fn late_conflicting_checkpoint_waits_for_next_epoch() {
+	let mut tracker = tracker();
+	tracker.on_tick(8 + SAFE_SLOTS);
+
+	let updated = tracker.on_block((1, "a"), false, 8 + SAFE_SLOTS, not_descendant).unwrap();
+	assert!(!updated);
+	assert_eq!(tracker.justified(), &(0, "genesis"));
+	assert_eq!(tracker.best_justified(), &(1, "a"));
+
+	// Later slots of the same epoch do not promote it.
+	assert!(!tracker.on_tick(15));
+	assert_eq!(tracker.justified(), &(0, "genesis"));
+
+	assert!(tracker.on_tick(16));
+	assert_eq!(tracker.justified(), &(1, "a"));
+	assert!(!tracker.on_tick(24));
+}
+
+#[test]
+fn late_descendant_checkpoint_is_adopted() {
+	let mut tracker = tracker();
+	tracker.on_tick(8 + SAFE_SLOTS);
+
+	let mut checked = None;
+	let updated = tracker.on_block((1, "a"), false, 8 + SAFE_SLOTS, |ancestor, id| {
+		checked = Some((*ancestor, *id));
+		Ok::<_, ()>(true)
+	}).unwrap();
+	assert!(updated);
+	assert_eq!(checked, Some(("genesis", "a")));
+	assert_eq!(tracker.justified(), &(1, "a"));
+}
+
+#[test]
+fn finality_advance_adopts_late_checkpoint() {
+	let mut tracker = tracker();
+	tracker.on_tick(8 + SAFE_SLOTS);
+
+	let updated = tracker.on_block((1, "a"), true, 8 + SAFE_SLOTS, not_descendant).unwrap();
+	assert!(updated);
+	assert_eq!(tracker.justified(), &(1, "a"));
+}
+
+#[test]
+fn older_checkpoints_are_ignored() {
+	let mut tracker = tracker();
+	tracker.on_tick(8);
+	tracker.on_block((2, "b"), false, 8, not_descendant).unwrap();
+
+	let updated = tracker.on_block((1, "a"), true, 8, |_, _| -> Result<bool, ()> {
+		panic!("Ancestry is not checked for older checkpoints")
+	}).unwrap();
+	assert!(!updated);
+	assert_eq!(tracker.justified(), &(2, "b"));
+	assert_eq!(tracker.best_justified(), &(2, "b"));
+}
+
+#[test]
+fn best_justified_keeps_newest_late_checkpoint() {
+	let mut tracker = tracker();
+	tracker.on_tick(8 + SAFE_SLOTS);
+	tracker.on_block((2, "b"), false, 8 + SAFE_SLOTS, not_descendant).unwrap();
+	tracker.on_block((1, "a"), false, 8 + SAFE_SLOTS, not_descendant).unwrap();
+	assert_eq!(tracker.best_justified(), &(2, "b"));
+
+	assert!(tracker.on_tick(16));
+	assert_eq!(tracker.justified(), &(2, "b"));
+}
+
+#[test]
+fn ancestry_errors_are_returned() {
+	let mut tracker = tracker();
+	tracker.on_tick(8 + SAFE_SLOTS);
+
+	let result = tracker.on_block((1, "a"), false, 8 + SAFE_SLOTS, |_, _| Err("unknown block"));
+	assert_eq!(result, Err("unknown block"));
+	assert_eq!(tracker.justified(), &(0, "genesis"));
+}
+
+#[test]
+fn without_safe_slots_checkpoints_are_adopted_immediately() {
+	let mut tracker = JustificationTracker::new((0, "genesis"));
+	assert!(!tracker.on_tick(SAFE_SLOTS_TO_UPDATE_JUSTIFIED + 1));
+
+	let updated = tracker.on_block(
+		(1, "a"), false, SAFE_SLOTS_TO_UPDATE_JUSTIFIED + 1, not_descendant,
+	).unwrap();
+	assert!(updated);
+	assert_eq!(tracker.justified(), &(1, "a"));
+}
@@ -0,0 +1,139 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Tests of the proto-array head selection, weights and pruning.
+
+use lmd_ghost::archive::Error;
+use lmd_ghost::proto_array::ProtoArray;
+
+/// Blocks `0 <- 1 <- 3` and `0 <- 2`, all with justified and finalized
+/// epochs of zero.
+fn fork() -> ProtoArray<u64> {
+	let mut array = ProtoArray::new();
+	array.on_block(0, None, 0, 0);
+	array.on_block(1, Some(0), 0, 0);
+	array.on_block(2, Some(0), 0, 0);
+	array.on_block(3, Some(1), 0, 0);
+	array
+}
+
+#[test]
+fn votes_move_between_branches() {
+	let mut array = fork();
+
+	array.apply_score_changes(vec![0, 0, 10, 0], 0, 0).unwrap();
+	assert_eq!(array.find_head(&0).unwrap(), 2);
+	assert_eq!(array.weight(&0), Some(10));
+
+	array.apply_score_changes(vec![0, 0, -10, 10], 0, 0).unwrap();
+	assert_eq!(array.find_head(&0).unwrap(), 3);
+	assert_eq!(array.weight(&0), Some(10));
+	assert_eq!(array.weight(&1), Some(10));
+	assert_eq!(array.weight(&2), Some(0));
+}
+
+#[test]
+fn balance_changes_reweight_branches() {
+	let mut array = fork();
+
+	array.apply_score_changes(vec![0, 0, 10, 15], 0, 0).unwrap();
+	assert_eq!(array.find_head(&0).unwrap(), 3);
+
+	// The validator voting for block 3 loses balance.
+	array.apply_score_changes(vec![0, 0, 0, -10], 0, 0).unwrap();
+	assert_eq!(array.find_head(&0).unwrap(), 2);
+	assert_eq!(array.weight(&0), Some(15));
+}
+
+#[test]
+fn ties_are_broken_by_greater_identifier() {
+	let mut array = fork();
+
+	array.apply_score_changes(vec![0, 0, 10, 10], 0, 0).unwrap();
+	assert_eq!(array.find_head(&0).unwrap(), 2);
+}
+
+#[test]
+fn weight_underflow_is_an_error() {
+	let mut array = fork();
+
+	assert!(match array.apply_score_changes(vec![0, 0, -1, 0], 0, 0) {
+		Err(Error::InvalidWeight) => true,
+		_ => false,
+	});
+}
+
+#[test]
+fn blocks_disagreeing_with_checkpoints_are_filtered() {
+	let mut array = ProtoArray::new();
+	array.on_block(0, None, 0, 0);
+	array.on_block(1, Some(0), 1, 0);
+	array.on_block(2, Some(0), 0, 0);
+	array.on_block(3, Some(1), 1, 0);
+
+	// Block 2 is heavier, but its state did not justify epoch 1.
+	array.apply_score_changes(vec![0, 0, 20, 5], 1, 0).unwrap();
+	assert_eq!(array.find_head(&0).unwrap(), 3);
+
+	// Any block is viable before the first justification.
+	array.apply_score_changes(vec![0, 0, 0, 0], 0, 0).unwrap();
+	assert_eq!(array.find_head(&0).unwrap(), 2);
+
+	// No block agrees with the finalized epoch.
+	array.apply_score_changes(vec![0, 0, 0, 0], 1, 1).unwrap();
+	assert!(match array.find_head(&0) {
+		Err(Error::NoViableHead) => true,
+		_ => false,
+	});
+}
+
+#[test]
+fn pruning_remaps_indices() {
+	let mut array = fork();
+	array.on_block(4, Some(3), 0, 0);
+	array.apply_score_changes(vec![0, 0, 0, 0, 7], 0, 0).unwrap();
+	assert_eq!(array.find_head(&0).unwrap(), 4);
+
+	assert_eq!(array.prune(&1).unwrap(), 2);
+	assert_eq!(array.len(), 3);
+	assert!(!array.contains(&0));
+	assert!(!array.contains(&2));
+	assert_eq!(array.index_of(&1), Some(0));
+	assert_eq!(array.index_of(&3), Some(1));
+	assert_eq!(array.index_of(&4), Some(2));
+
+	// Best descendants are remapped along with the blocks.
+	assert_eq!(array.find_head(&1).unwrap(), 4);
+	assert_eq!(array.weight(&1), Some(7));
+
+	// Deltas apply to the new indices.
+	array.on_block(5, Some(1), 0, 0);
+	array.apply_score_changes(vec![0, 0, -7, 9], 0, 0).unwrap();
+	assert_eq!(array.find_head(&1).unwrap(), 5);
+	assert_eq!(array.weight(&1), Some(9));
+	assert_eq!(array.weight(&4), Some(0));
+}
+
+#[test]
+fn descendants_follow_parents() {
+	let array = fork();
+	assert!(array.is_descendant(&0, &3));
+	assert!(array.is_descendant(&1, &3));
+	assert!(array.is_descendant(&3, &3));
+	assert!(!array.is_descendant(&2, &3));
+	assert!(!array.is_descendant(&3, &1));
+	assert!(!array.is_descendant(&5, &3));
+}
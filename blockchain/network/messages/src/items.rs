@@ -113,3 +113,16 @@ pub struct RecentBeaconBlocksRequest {
     /// The list of beacon block bodies being requested.
     pub block_roots: Vec<H256>,
 }
+
+/// The `eth2` ENR field, advertising the fork of a node.
+#[derive(Codec, Encode, Decode, Clone, Debug, Eq, PartialEq)]
+pub struct EnrForkId {
+    /// Digest of the current fork version and genesis.
+    pub fork_digest: Version,
+
+    /// Version of the next scheduled fork, or the current one if none.
+    pub next_fork_version: Version,
+
+    /// Epoch of the next scheduled fork, or far future if none.
+    pub next_fork_epoch: Epoch,
+}
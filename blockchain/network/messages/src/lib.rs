@@ -1,11 +1,16 @@
 mod items;
 mod codec;
 
-pub use items::{HelloMessage, GoodbyeReason, BeaconBlocksRequest, RecentBeaconBlocksRequest};
+pub use items::{
+	HelloMessage, GoodbyeReason, BeaconBlocksRequest, RecentBeaconBlocksRequest, EnrForkId,
+};
 pub use codec::{InboundCodec, OutboundCodec};
 
 use beacon::{
-	Config, types::{BeaconBlock, Attestation, VoluntaryExit, ProposerSlashing, AttesterSlashing},
+	Config, types::{
+		BeaconBlock, Attestation, SignedAggregateAndProof, VoluntaryExit, ProposerSlashing,
+		AttesterSlashing,
+	},
 };
 use libp2p::gossipsub;
 
@@ -86,16 +91,28 @@ pub enum RPCResponse<C: Config> {
 pub enum PubsubType {
 	Block,
 	Attestation,
+	AttestationSubnet(u64),
+	AggregateAndProof,
 	VoluntaryExit,
 	ProposerSlashing,
 	AttesterSlashing,
 }
 
+const SUBNET_TOPIC_PREFIX: &str = "/eth2/committee_index";
+const SUBNET_TOPIC_POSTFIX: &str = "_beacon_attestation/ssz";
+
 impl PubsubType {
 	pub fn from_gossipsub_topic_hash(topic: &gossipsub::TopicHash) -> Option<Self> {
-		match topic.as_str() {
+		let topic = topic.as_str();
+		if topic.starts_with(SUBNET_TOPIC_PREFIX) && topic.ends_with(SUBNET_TOPIC_POSTFIX) {
+			let subnet = &topic[SUBNET_TOPIC_PREFIX.len()..(topic.len() - SUBNET_TOPIC_POSTFIX.len())];
+			return subnet.parse().ok().map(Self::AttestationSubnet)
+		}
+
+		match topic {
 			"/eth2/beacon_block/ssz" => Some(Self::Block),
 			"/eth2/beacon_attestation/ssz" => Some(Self::Attestation),
+			"/eth2/beacon_aggregate_and_proof/ssz" => Some(Self::AggregateAndProof),
 			"/eth2/voluntary_exit/ssz" => Some(Self::VoluntaryExit),
 			"/eth2/proposer_slashing/ssz" => Some(Self::ProposerSlashing),
 			"/eth2/attester_slashing/ssz" => Some(Self::AttesterSlashing),
@@ -103,24 +120,25 @@ impl PubsubType {
 		}
 	}
 
-	pub fn gossipsub_topic_hash(&self) -> gossipsub::TopicHash {
-		gossipsub::TopicHash::from_raw(match self {
+	fn topic(&self) -> String {
+		match self {
 			Self::Block => "/eth2/beacon_block/ssz".to_string(),
 			Self::Attestation => "/eth2/beacon_attestation/ssz".to_string(),
+			Self::AttestationSubnet(subnet) =>
+				format!("{}{}{}", SUBNET_TOPIC_PREFIX, subnet, SUBNET_TOPIC_POSTFIX),
+			Self::AggregateAndProof => "/eth2/beacon_aggregate_and_proof/ssz".to_string(),
 			Self::VoluntaryExit => "/eth2/voluntary_exit/ssz".to_string(),
 			Self::ProposerSlashing => "/eth2/proposer_slashing/ssz".to_string(),
 			Self::AttesterSlashing => "/eth2/attester_slashing/ssz".to_string(),
-		})
+		}
+	}
+
+	pub fn gossipsub_topic_hash(&self) -> gossipsub::TopicHash {
+		gossipsub::TopicHash::from_raw(self.topic())
 	}
 
 	pub fn gossipsub_topic(&self) -> gossipsub::Topic {
-		gossipsub::Topic::new(match self {
-			Self::Block => "/eth2/beacon_block/ssz".to_string(),
-			Self::Attestation => "/eth2/beacon_attestation/ssz".to_string(),
-			Self::VoluntaryExit => "/eth2/voluntary_exit/ssz".to_string(),
-			Self::ProposerSlashing => "/eth2/proposer_slashing/ssz".to_string(),
-			Self::AttesterSlashing => "/eth2/attester_slashing/ssz".to_string(),
-		})
+		gossipsub::Topic::new(self.topic())
 	}
 }
 
@@ -129,6 +147,7 @@ impl<'a, C: Config> From<&'a PubsubMessage<C>> for PubsubType {
 		match message {
 			PubsubMessage::Block(_) => PubsubType::Block,
 			PubsubMessage::Attestation(_) => PubsubType::Attestation,
+			PubsubMessage::AggregateAndProof(_) => PubsubType::AggregateAndProof,
 			PubsubMessage::VoluntaryExit(_) => PubsubType::VoluntaryExit,
 			PubsubMessage::ProposerSlashing(_) => PubsubType::ProposerSlashing,
 			PubsubMessage::AttesterSlashing(_) => PubsubType::AttesterSlashing,
@@ -144,6 +163,8 @@ pub enum PubsubMessage<C: Config> {
     Block(BeaconBlock<C>),
     /// Gossipsub message providing notification of a new attestation.
     Attestation(Attestation<C>),
+    /// Gossipsub message providing notification of a new aggregate attestation.
+    AggregateAndProof(SignedAggregateAndProof<C>),
     /// Gossipsub message providing notification of a voluntary exit.
     VoluntaryExit(VoluntaryExit),
     /// Gossipsub message providing notification of a new proposer slashing.
@@ -157,6 +178,7 @@ impl<C: Config> PubsubMessage<C> {
 		match self {
 			Self::Block(item) => ssz::Encode::encode(item),
 			Self::Attestation(item) => ssz::Encode::encode(item),
+			Self::AggregateAndProof(item) => ssz::Encode::encode(item),
 			Self::VoluntaryExit(item) => ssz::Encode::encode(item),
 			Self::ProposerSlashing(item) => ssz::Encode::encode(item),
 			Self::AttesterSlashing(item) => ssz::Encode::encode(item),
@@ -166,7 +188,9 @@ impl<C: Config> PubsubMessage<C> {
 	pub fn from_ssz_data(typ: PubsubType, mut data: &[u8]) -> Result<Self, ssz::Error> {
 		Ok(match typ {
 			PubsubType::Block => Self::Block(ssz::Decode::decode(&mut data)?),
-			PubsubType::Attestation => Self::Attestation(ssz::Decode::decode(&mut data)?),
+			PubsubType::Attestation | PubsubType::AttestationSubnet(_) =>
+				Self::Attestation(ssz::Decode::decode(&mut data)?),
+			PubsubType::AggregateAndProof => Self::AggregateAndProof(ssz::Decode::decode(&mut data)?),
 			PubsubType::VoluntaryExit => Self::VoluntaryExit(ssz::Decode::decode(&mut data)?),
 			PubsubType::ProposerSlashing => Self::ProposerSlashing(ssz::Decode::decode(&mut data)?),
 			PubsubType::AttesterSlashing => Self::AttesterSlashing(ssz::Decode::decode(&mut data)?),
@@ -16,7 +16,7 @@
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
 
 use crate::discovery::Discovery;
-use crate::{Libp2pEvent, Error, NetworkConfig};
+use crate::{Libp2pEvent, Error, NetworkConfig, PeerAction};
 use crate::rpc::{RPC, RPCMessage, RPCEvent};
 use futures01::prelude::*;
 use libp2p::{
@@ -100,7 +100,7 @@ impl<C: Config, TSubstream: AsyncRead + AsyncWrite> NetworkBehaviourEventProcess
 {
     fn inject_event(&mut self, event: GossipsubEvent) {
         match event {
-            GossipsubEvent::Message(_, gs_msg) => {
+            GossipsubEvent::Message(propagation_source, gs_msg) => {
                 trace!("Received GossipEvent");
 
 				let typ = match gs_msg.topics.iter()
@@ -114,15 +114,20 @@ impl<C: Config, TSubstream: AsyncRead + AsyncWrite> NetworkBehaviourEventProcess
 						return
 					},
 				};
-				let msg = match PubsubMessage::from_ssz_data(typ, &gs_msg.data) {
+				let span = tracing::debug_span!("decode", topic = ?typ, len = gs_msg.data.len());
+				let decoded = span.in_scope(|| PubsubMessage::from_ssz_data(typ, &gs_msg.data));
+				let msg = match decoded {
 					Ok(msg) => msg,
 					Err(_) => {
 						warn!("Uninterpretable gossipsub message");
+						self.events.push(Libp2pEvent::Misbehaviour(
+							propagation_source, PeerAction::ProtocolViolation
+						));
 						return
 					},
 				};
 
-                self.events.push(Libp2pEvent::Pubsub(gs_msg.source, msg));
+                self.events.push(Libp2pEvent::Pubsub(gs_msg.source, typ, msg));
             }
             GossipsubEvent::Subscribed { .. } => {}
             GossipsubEvent::Unsubscribed { .. } => {}
@@ -214,6 +219,27 @@ impl<C: Config, TSubstream: AsyncRead + AsyncWrite> Behaviour<C, TSubstream> {
         self.gossipsub.subscribe(topic)
     }
 
+    /// Unsubscribes from a gossipsub topic.
+    pub fn unsubscribe(&mut self, topic: Topic) -> bool {
+        self.gossipsub.unsubscribe(topic)
+    }
+
+    /// Sets the attestation subnets local validators need, preferring peers
+    /// advertising them.
+    pub fn set_wanted_subnets(&mut self, subnets: std::collections::BTreeSet<u64>) {
+        self.discovery.set_wanted_subnets(subnets);
+    }
+
+    /// Sets the target number of connected peers.
+    pub fn set_max_peers(&mut self, max_peers: usize) {
+        self.discovery.set_max_peers(max_peers);
+    }
+
+    /// Advertises persistent attestation subnets in the local ENR.
+    pub fn update_attnets(&mut self, attnets: Vec<u8>) {
+        self.discovery.update_attnets(attnets);
+    }
+
     /// Publishes a message on the pubsub (gossipsub) behaviour.
     pub fn publish(&mut self, message: PubsubMessage<C>) {
         let data = message.ssz_data();
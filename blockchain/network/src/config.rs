@@ -16,6 +16,7 @@
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
 
 use enr::Enr;
+use network_messages::EnrForkId;
 use libp2p::gossipsub::{GossipsubConfig, GossipsubConfigBuilder};
 use libp2p::Multiaddr;
 use serde::{Deserialize, Serialize};
@@ -55,6 +56,11 @@ pub struct Config {
 
     /// List of extra topics to initially subscribe to as strings.
     pub topics: Vec<String>,
+
+    /// Fork identification advertised in the ENR. Discovered peers on other
+    /// forks are not dialed.
+    #[serde(skip)]
+    pub fork_id: Option<EnrForkId>,
 }
 
 impl Default for Config {
@@ -76,6 +82,7 @@ impl Default for Config {
             libp2p_nodes: vec![],
             client_version: crate::VERSION.to_string(),
             topics: Vec::new(),
+            fork_id: None,
         }
     }
 }
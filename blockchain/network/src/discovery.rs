@@ -21,6 +21,9 @@
 //!
 
 use crate::{Error, NetworkConfig};
+use crate::subnets::{ATTNETS_ENR_KEY, ATTESTATION_SUBNET_COUNT};
+use crate::fork::ETH2_ENR_KEY;
+use network_messages::EnrForkId;
 use futures01::prelude::*;
 use libp2p::core::{identity::Keypair, ConnectedPoint, Multiaddr, PeerId};
 use libp2p::discv5::{Discv5, Discv5Event};
@@ -28,7 +31,7 @@ use libp2p::enr::{Enr, EnrBuilder, NodeId};
 use libp2p::multiaddr::Protocol;
 use libp2p::swarm::{NetworkBehaviour, NetworkBehaviourAction, PollParameters, ProtocolsHandler};
 use log::*;
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::time::{Duration, Instant};
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_timer::Delay;
@@ -37,6 +40,13 @@ use tokio_timer::Delay;
 const MAX_TIME_BETWEEN_PEER_SEARCHES: u64 = 60;
 /// Initial delay between peer searches.
 const INITIAL_SEARCH_DELAY: u64 = 5;
+/// Seconds between checks of peer coverage of wanted subnets.
+const SUBNET_SEARCH_INTERVAL: u64 = 12;
+/// Minimum number of connected peers advertising a wanted subnet.
+const MIN_PEERS_PER_SUBNET: usize = 2;
+/// Number of peers over the target allowed, when they serve poorly covered
+/// subnets.
+const SUBNET_PEER_EXCESS: usize = 5;
 
 /// Lighthouse discovery behaviour. This provides peer management and discovery using the Discv5
 /// libp2p protocol.
@@ -61,6 +71,24 @@ pub struct Discovery<TSubstream> {
 
     /// The discovery behaviour used to discover new peers.
     discovery: Discv5<TSubstream>,
+
+    /// Attestation subnets advertised by discovered peers.
+    peer_attnets: HashMap<PeerId, Vec<u8>>,
+
+    /// Attestation subnets local validators need.
+    wanted_subnets: BTreeSet<u64>,
+
+    /// The delay between checks of wanted subnet coverage.
+    subnet_search_delay: Delay,
+
+    /// Discovered peers waiting to be dialed.
+    pending_dials: VecDeque<PeerId>,
+
+    /// Local fork identification, if any.
+    fork_id: Option<EnrForkId>,
+
+    /// Discovered peers advertising an incompatible fork.
+    incompatible_peers: HashSet<PeerId>,
 }
 
 impl<TSubstream> Discovery<TSubstream> {
@@ -93,6 +121,12 @@ impl<TSubstream> Discovery<TSubstream> {
             past_discovery_delay: INITIAL_SEARCH_DELAY,
             tcp_port: config.libp2p_port,
             discovery,
+            peer_attnets: HashMap::new(),
+            wanted_subnets: BTreeSet::new(),
+            subnet_search_delay: Delay::new(Instant::now()),
+            pending_dials: VecDeque::new(),
+            fork_id: config.fork_id.clone(),
+            incompatible_peers: HashSet::new(),
         })
     }
 
@@ -107,6 +141,88 @@ impl<TSubstream> Discovery<TSubstream> {
         self.find_peers();
     }
 
+    /// Update the persistent attestation subnets advertised in the local ENR.
+    pub fn update_attnets(&mut self, attnets: Vec<u8>) {
+        match self.discovery.enr_insert(ATTNETS_ENR_KEY, attnets) {
+            Ok(_) => info!("ENR attnets updated, sequence {}", self.local_enr().seq()),
+            Err(e) => warn!("Could not update ENR attnets: {:?}", e),
+        }
+    }
+
+    /// Set the attestation subnets local validators need, so that peers
+    /// advertising them are preferred.
+    pub fn set_wanted_subnets(&mut self, subnets: BTreeSet<u64>) {
+        self.wanted_subnets = subnets;
+    }
+
+    /// Set the target number of connected peers.
+    pub fn set_max_peers(&mut self, max_peers: usize) {
+        self.max_peers = max_peers;
+    }
+
+    /// Whether a peer advertises the given subnet in its ENR.
+    fn peer_has_subnet(&self, peer_id: &PeerId, subnet: u64) -> bool {
+        self.peer_attnets.get(peer_id)
+            .and_then(|attnets| attnets.get((subnet / 8) as usize))
+            .map(|byte| byte & (1 << (subnet % 8)) != 0)
+            .unwrap_or(false)
+    }
+
+    /// Wanted subnets with too few connected peers advertising them.
+    fn poor_subnets(&self) -> Vec<u64> {
+        self.wanted_subnets.iter()
+            .filter(|subnet| **subnet < ATTESTATION_SUBNET_COUNT)
+            .filter(|subnet| {
+                self.connected_peers.iter()
+                    .filter(|peer_id| self.peer_has_subnet(peer_id, **subnet))
+                    .count() < MIN_PEERS_PER_SUBNET
+            })
+            .cloned()
+            .collect()
+    }
+
+    /// Number of poorly covered subnets a peer would serve.
+    fn subnet_usefulness(&self, peer_id: &PeerId, poor_subnets: &[u64]) -> usize {
+        poor_subnets.iter().filter(|subnet| self.peer_has_subnet(peer_id, **subnet)).count()
+    }
+
+    /// Next discovered peer to dial. Peers over the target are only dialed if
+    /// they serve poorly covered subnets.
+    fn next_dial(&mut self) -> Option<PeerId> {
+        let poor_subnets = self.poor_subnets();
+        while let Some(peer_id) = self.pending_dials.pop_front() {
+            if self.connected_peers.contains(&peer_id) || self.incompatible_peers.contains(&peer_id) {
+                continue
+            }
+
+            let useful = self.subnet_usefulness(&peer_id, &poor_subnets) > 0;
+            if self.connected_peers.len() < self.max_peers ||
+                (useful && self.connected_peers.len() < self.max_peers + SUBNET_PEER_EXCESS)
+            {
+                debug!("Peer discovered {:?}", peer_id);
+                return Some(peer_id)
+            }
+        }
+        None
+    }
+
+    /// Whether a discovered node is on the same fork. Nodes not advertising
+    /// the `eth2` field are considered compatible.
+    fn is_fork_compatible(&self, enr: &Enr) -> bool {
+        let local = match self.fork_id {
+            Some(ref local) => local,
+            None => return true,
+        };
+
+        match enr.get(ETH2_ENR_KEY) {
+            Some(value) => match <EnrForkId as ssz::Decode>::decode(value) {
+                Ok(remote) => remote.fork_digest == local.fork_digest,
+                Err(_) => false,
+            },
+            None => true,
+        }
+    }
+
     /// Add an Enr to the routing table of the discovery mechanism.
     pub fn add_enr(&mut self, enr: Enr) {
         self.discovery.add_enr(enr);
@@ -209,14 +325,47 @@ where
             }
         }
 
+        // search for peers if wanted subnets are poorly covered
+        loop {
+            match self.subnet_search_delay.poll() {
+                Ok(Async::Ready(_)) => {
+                    let poor_subnets = self.poor_subnets();
+                    if !poor_subnets.is_empty() {
+                        debug!("Searching for peers on poorly covered subnets {:?}", poor_subnets);
+                        self.find_peers();
+                    }
+                    self.subnet_search_delay
+                        .reset(Instant::now() + Duration::from_secs(SUBNET_SEARCH_INTERVAL));
+                }
+                Ok(Async::NotReady) => break,
+                Err(e) => {
+                    warn!("Discovery subnet search failed {:?}", e);
+                }
+            }
+        }
+
+        // dial discovered peers, preferring those serving poorly covered subnets
+        if let Some(peer_id) = self.next_dial() {
+            return Async::Ready(NetworkBehaviourAction::DialPeer { peer_id });
+        }
+
         // Poll discovery
         loop {
             match self.discovery.poll(params) {
                 Async::Ready(NetworkBehaviourAction::GenerateEvent(event)) => {
                     match event {
-                        Discv5Event::Discovered(_enr) => {
-                            // not concerned about FINDNODE results, rather the result of an entire
-                            // query.
+                        Discv5Event::Discovered(enr) => {
+                            // only note advertised subnets, dialing happens on the result of an
+                            // entire query.
+                            if let Some(attnets) = enr.get(ATTNETS_ENR_KEY) {
+                                self.peer_attnets.insert(enr.peer_id(), attnets.clone());
+                            }
+                            if !self.is_fork_compatible(&enr) {
+                                debug!("Discovered peer on another fork {}", enr.node_id());
+                                self.incompatible_peers.insert(enr.peer_id());
+                            } else {
+                                self.incompatible_peers.remove(&enr.peer_id());
+                            }
                         }
                         Discv5Event::SocketUpdated(socket) => {
                             info!("Address updated (IP: {})", socket.ip());
@@ -232,16 +381,21 @@ where
                             if closer_peers.is_empty() {
                                 debug!("Discovery random query found no peers");
                             }
-                            for peer_id in closer_peers {
-                                // if we need more peers, attempt a connection
-                                if self.connected_peers.len() < self.max_peers
-                                    && self.connected_peers.get(&peer_id).is_none()
-                                {
-                                    debug!("Peer discovered {:?}", peer_id);
-                                    return Async::Ready(NetworkBehaviourAction::DialPeer {
-                                        peer_id,
-                                    });
-                                }
+                            let poor_subnets = self.poor_subnets();
+                            let mut candidates = closer_peers.into_iter()
+                                .filter(|peer_id| !self.connected_peers.contains(peer_id))
+                                .map(|peer_id| {
+                                    (self.subnet_usefulness(&peer_id, &poor_subnets), peer_id)
+                                })
+                                .collect::<Vec<_>>();
+                            candidates.sort_by(|a, b| b.0.cmp(&a.0));
+                            self.pending_dials = candidates.into_iter()
+                                .map(|(_, peer_id)| peer_id)
+                                .collect();
+                            if let Some(peer_id) = self.next_dial() {
+                                return Async::Ready(NetworkBehaviourAction::DialPeer {
+                                    peer_id,
+                                });
                             }
                         }
                         _ => {}
@@ -268,10 +422,15 @@ fn load_enr(
     // Build the local ENR.
     // Note: Discovery should update the ENR record's IP to the external IP as seen by the
     // majority of our peers.
-    let local_enr = EnrBuilder::new("v4")
+    let mut builder = EnrBuilder::new("v4");
+    builder
         .ip(config.discovery_address)
         .tcp(config.libp2p_port)
-        .udp(config.discovery_port)
+        .udp(config.discovery_port);
+    if let Some(ref fork_id) = config.fork_id {
+        builder.add_value(ETH2_ENR_KEY, ssz::Encode::encode(fork_id));
+    }
+    let local_enr = builder
         .build(&local_key)
         .map_err(|e| format!("Could not build Local ENR: {:?}", e))?;
 
@@ -0,0 +1,36 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Fork identification advertised in the `eth2` ENR field.
+
+use beacon::{Config, ForkSchedule};
+use beacon::primitives::Epoch;
+use network_messages::EnrForkId;
+
+pub use beacon::compute_fork_digest;
+
+/// ENR key of the fork identification.
+pub const ETH2_ENR_KEY: &str = "eth2";
+
+/// Fork identification at `epoch` under a fork schedule.
+pub fn enr_fork_id<C: Config>(schedule: &ForkSchedule, epoch: Epoch) -> EnrForkId {
+	let (next_fork_epoch, next_fork_version) = schedule.next_fork_or_current(epoch);
+	EnrForkId {
+		fork_digest: schedule.fork_digest_at_epoch::<C>(epoch),
+		next_fork_version,
+		next_fork_epoch,
+	}
+}
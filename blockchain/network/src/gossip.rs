@@ -0,0 +1,98 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Queue of messages to publish on gossip, filled from outside the
+//! networking thread, and validation of received gossip messages.
+
+use beacon::Config;
+use beacon::types::{Attestation, BeaconBlock, SignedAggregateAndProof};
+use network_messages::PubsubMessage;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+/// Maximum number of messages waiting to be published.
+pub const MAX_QUEUED_GOSSIP: usize = 1024;
+
+/// Shared queue of outgoing gossip messages, drained by the network on each
+/// poll.
+#[derive(Clone)]
+pub struct GossipQueue<C: Config>(Arc<Mutex<VecDeque<PubsubMessage<C>>>>);
+
+impl<C: Config> GossipQueue<C> {
+	/// Create a new empty queue.
+	pub fn new() -> Self {
+		Self(Arc::new(Mutex::new(VecDeque::new())))
+	}
+
+	/// Queue a message for publishing. Returns false if the queue is full
+	/// and the message was dropped.
+	pub fn publish(&self, message: PubsubMessage<C>) -> bool {
+		let mut queue = self.0.lock().expect("Lock is poisoned");
+		if queue.len() >= MAX_QUEUED_GOSSIP {
+			return false
+		}
+		queue.push_back(message);
+		true
+	}
+
+	/// Take all queued messages.
+	pub fn take(&self) -> Vec<PubsubMessage<C>> {
+		self.0.lock().expect("Lock is poisoned").drain(..).collect()
+	}
+}
+
+/// Outcome of validating a received gossip message, following the spec's
+/// gossip conditions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GossipVerdict {
+	/// Valid message, processed locally.
+	Accept,
+	/// Message that cannot be validated yet, or that is redundant.
+	Ignore,
+	/// Invalid message, penalizing the peer that sent it.
+	Reject,
+}
+
+/// Outcome of validating a block received on gossip.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockVerdict {
+	/// Outcome of the gossip conditions.
+	Gossip(GossipVerdict),
+	/// Block ignored because its parent is unknown. The block is parked in
+	/// the import queue, and its parent requested from the peer that sent
+	/// it.
+	UnknownParent,
+}
+
+/// Handler of blocks received on gossip.
+pub trait BlockHandler<C: Config> {
+	/// Validate a block before it is passed to the import queue. Accepted
+	/// blocks are queued for import.
+	fn handle_block(&self, block: &BeaconBlock<C>) -> BlockVerdict;
+}
+
+/// Handler of attestations and aggregates received on gossip.
+pub trait AttestationHandler<C: Config> {
+	/// Validate an attestation received on an attestation subnet, or on the
+	/// global attestation topic if `subnet` is `None`, and process it if it
+	/// is valid.
+	fn handle_attestation(&self, attestation: Attestation<C>, subnet: Option<u64>) -> GossipVerdict;
+
+	/// Validate an aggregate received on the aggregate and proof topic,
+	/// together with the proof that its sender is an aggregator, and process
+	/// it if it is valid.
+	fn handle_aggregate(&self, aggregate: SignedAggregateAndProof<C>) -> GossipVerdict;
+}
@@ -1,7 +1,7 @@
 use core::marker::PhantomData;
 use blockchain::{Auxiliary, Block as BlockT, AsExternalities};
 use blockchain::backend::{Store, SharedCommittable, ChainQuery, ImportLock};
-use beacon::{Config, types::BeaconBlock, primitives::H256};
+use beacon::{Config, types::BeaconBlock, primitives::{H256, Slot}};
 use network_messages::{HelloMessage, BeaconBlocksRequest};
 use shasper_runtime::{StateExternalities, Block};
 use log::*;
@@ -77,8 +77,16 @@ impl<C, Ba> Handler<C, Ba> where
 		self.blocks_by_depth_no_lock(start_depth, count)
 	}
 
+	pub fn blocks_by_root(&self, roots: &[H256]) -> Vec<BeaconBlock<C>> {
+		let _ = self.import_lock.lock();
+		roots.iter()
+			.filter_map(|root| self.backend.block_at(root).ok())
+			.map(Into::into)
+			.collect()
+	}
+
 	pub fn blocks_by_slot(
-		&self, mut start_hash: H256, start_slot: u64, count: usize
+		&self, mut start_hash: H256, start_slot: Slot, count: usize
 	) -> Vec<BeaconBlock<C>> {
 		let _ = self.import_lock.lock();
 
@@ -0,0 +1,241 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Queue of blocks received out of order, importing them once their parents
+//! are known.
+
+use blockchain::Block as BlockT;
+use blockchain::backend::Store;
+use blockchain::import::BlockImporter;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+use rayon::prelude::*;
+use log::*;
+
+/// Maximum number of blocks parked waiting for their parents.
+pub const MAX_PARKED_BLOCKS: usize = 1024;
+/// Time after which a block still waiting for its parent is dropped.
+pub const PARKED_BLOCK_TIMEOUT: Duration = Duration::from_secs(60);
+
+type Identifier<B> = <B as BlockT>::Identifier;
+
+/// Checks of a block against its parent state that do not modify any state,
+/// run on a worker pool ahead of sequential import.
+pub trait BlockVerifier {
+	/// Block type.
+	type Block: BlockT;
+	/// Verification error.
+	type Error: core::fmt::Debug;
+
+	/// Verify a block whose parent is already imported.
+	fn verify_block(&self, block: &Self::Block) -> Result<(), Self::Error>;
+}
+
+struct Parked<B, P> {
+	block: B,
+	origin: Option<P>,
+	since: Instant,
+}
+
+/// Result of importing a batch of ready blocks.
+#[derive(Debug)]
+pub struct ImportBatch<Id, P> {
+	/// Number of imported blocks.
+	pub imported: usize,
+	/// Blocks that failed to import, along with their origin. Their parked
+	/// descendants are dropped.
+	pub failed: Vec<(Id, Option<P>)>,
+}
+
+/// Block import queue. Blocks whose parent is known are imported in order,
+/// while the others are parked until their parent arrives, and the parent
+/// is requested from the peer that sent them.
+pub struct ImportQueue<Ba: Store, I, V, P> {
+	backend: Ba,
+	importer: I,
+	verifier: V,
+	ready: VecDeque<(Ba::Block, Option<P>)>,
+	parked: HashMap<Identifier<Ba::Block>, Vec<Parked<Ba::Block, P>>>,
+	parked_count: usize,
+	missing: Vec<(Identifier<Ba::Block>, Option<P>)>,
+	requested: HashSet<Identifier<Ba::Block>>,
+}
+
+impl<Ba, I, V, P> ImportQueue<Ba, I, V, P> where
+	Ba: Store,
+	Ba::Block: Send + Sync,
+	I: BlockImporter<Block=Ba::Block>,
+	I::Error: core::fmt::Debug,
+	V: BlockVerifier<Block=Ba::Block> + Sync,
+	P: Clone,
+{
+	/// Create a new import queue.
+	pub fn new(backend: Ba, importer: I, verifier: V) -> Self {
+		Self {
+			backend, importer, verifier,
+			ready: VecDeque::new(),
+			parked: HashMap::new(),
+			parked_count: 0,
+			missing: Vec::new(),
+			requested: HashSet::new(),
+		}
+	}
+
+	/// Number of blocks parked waiting for their parents.
+	pub fn parked_count(&self) -> usize {
+		self.parked_count
+	}
+
+	/// Whether a block is known, either imported or queued.
+	fn is_known(&self, id: &Identifier<Ba::Block>) -> bool {
+		self.backend.contains(id).unwrap_or(false) ||
+			self.ready.iter().any(|(block, _)| block.id() == *id) ||
+			self.parked.values().flatten().any(|parked| parked.block.id() == *id)
+	}
+
+	/// Add a block received from `origin`.
+	pub fn push(&mut self, block: Ba::Block, origin: Option<P>) {
+		let id = block.id();
+		if self.is_known(&id) {
+			return
+		}
+		self.requested.remove(&id);
+
+		let parent_id = match block.parent_id() {
+			Some(parent_id) => parent_id,
+			None => return,
+		};
+		if self.is_known(&parent_id) {
+			self.ready.push_back((block, origin));
+			return
+		}
+
+		if self.parked_count >= MAX_PARKED_BLOCKS {
+			debug!("Import queue full, dropping block {:?}", id);
+			return
+		}
+		if self.requested.insert(parent_id.clone()) {
+			self.missing.push((parent_id.clone(), origin.clone()));
+		}
+		self.parked.entry(parent_id).or_insert_with(Vec::new).push(Parked {
+			block, origin,
+			since: Instant::now(),
+		});
+		self.parked_count += 1;
+	}
+
+	/// Take the parents to request, along with the peer that sent their
+	/// child.
+	pub fn take_missing(&mut self) -> Vec<(Identifier<Ba::Block>, Option<P>)> {
+		std::mem::replace(&mut self.missing, Vec::new())
+	}
+
+	/// Import up to `max` ready blocks, in order, along with the parked
+	/// descendants they make ready.
+	///
+	/// Blocks whose parent is already imported are first verified
+	/// concurrently, and only those passing verification are applied, one
+	/// at a time.
+	pub fn import_ready(&mut self, max: usize) -> ImportBatch<Identifier<Ba::Block>, P> {
+		let mut batch = ImportBatch { imported: 0, failed: Vec::new() };
+		let mut rejected = self.verify_ready(max);
+
+		while batch.imported + batch.failed.len() < max {
+			let (block, origin) = match self.ready.pop_front() {
+				Some(ready) => ready,
+				None => break,
+			};
+			let id = block.id();
+
+			if rejected.remove(&id) {
+				self.drop_descendants(&id);
+				batch.failed.push((id, origin));
+				continue
+			}
+
+			match self.importer.import_block(block) {
+				Ok(()) => {
+					batch.imported += 1;
+					for parked in self.parked.remove(&id).unwrap_or_default().into_iter().rev() {
+						self.parked_count -= 1;
+						self.ready.push_front((parked.block, parked.origin));
+					}
+				},
+				Err(e) => {
+					warn!("Importing queued block {:?} failed: {:?}", id, e);
+					self.drop_descendants(&id);
+					batch.failed.push((id, origin));
+				},
+			}
+		}
+
+		batch
+	}
+
+	/// Verify the first `max` ready blocks whose parent is imported, on the
+	/// worker pool, returning the ones failing verification.
+	fn verify_ready(&self, max: usize) -> HashSet<Identifier<Ba::Block>> {
+		let backend = &self.backend;
+		let blocks = self.ready.iter()
+			.take(max)
+			.map(|(block, _)| block)
+			.filter(|block| {
+				block.parent_id()
+					.map(|parent_id| backend.contains(&parent_id).unwrap_or(false))
+					.unwrap_or(false)
+			})
+			.collect::<Vec<_>>();
+
+		let verifier = &self.verifier;
+		blocks.par_iter()
+			.filter_map(|block| match verifier.verify_block(block) {
+				Ok(()) => None,
+				Err(e) => {
+					warn!("Verifying queued block {:?} failed: {:?}", block.id(), e);
+					Some(block.id())
+				},
+			})
+			.collect()
+	}
+
+	/// Drop parked blocks waiting for too long.
+	pub fn prune(&mut self) {
+		let now = Instant::now();
+		let mut expired = Vec::new();
+		for (parent_id, blocks) in self.parked.iter_mut() {
+			blocks.retain(|parked| now.duration_since(parked.since) < PARKED_BLOCK_TIMEOUT);
+			if blocks.is_empty() {
+				expired.push(parent_id.clone());
+			}
+		}
+		for parent_id in expired {
+			self.parked.remove(&parent_id);
+			self.requested.remove(&parent_id);
+		}
+		self.parked_count = self.parked.values().map(|blocks| blocks.len()).sum();
+	}
+
+	fn drop_descendants(&mut self, id: &Identifier<Ba::Block>) {
+		let mut queue = vec![id.clone()];
+		while let Some(id) = queue.pop() {
+			self.requested.remove(&id);
+			for parked in self.parked.remove(&id).unwrap_or_default() {
+				self.parked_count -= 1;
+				queue.push(parked.block.id());
+			}
+		}
+	}
+}
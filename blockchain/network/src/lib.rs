@@ -18,9 +18,16 @@ mod behaviour;
 mod config;
 mod discovery;
 mod error;
+mod fork;
+mod gossip;
 mod rpc;
 mod service;
 mod handler;
+mod import_queue;
+mod peer_manager;
+mod status;
+mod subnets;
+mod validation;
 
 pub use behaviour::Behaviour;
 pub use config::Config as NetworkConfig;
@@ -32,25 +39,47 @@ pub use libp2p::{
 	PeerId, Swarm,
 };
 pub use error::Error;
+pub use fork::{ETH2_ENR_KEY, compute_fork_digest, enr_fork_id};
+pub use gossip::{
+	AttestationHandler, BlockHandler, BlockVerdict, GossipQueue, GossipVerdict, MAX_QUEUED_GOSSIP,
+};
+pub use network_messages::{EnrForkId, PubsubMessage, PubsubType};
 pub use service::Service;
 pub use handler::Handler;
+pub use import_queue::{
+	ImportQueue, ImportBatch, BlockVerifier, MAX_PARKED_BLOCKS, PARKED_BLOCK_TIMEOUT,
+};
+pub use peer_manager::{
+	PeerAction, PeerManager, PeerScore, BAN_SCORE, DISCONNECT_SCORE,
+};
+pub use status::NetworkStatus;
+pub use subnets::{
+	AttestationSubnets, SubnetDuty, compute_subnet_for_attestation, ATTESTATION_SUBNET_COUNT,
+};
+pub use validation::MAX_PENDING_VALIDATIONS;
 
 use log::*;
 use core::time::Duration;
 use libp2p::identity;
 use futures01::{Async, stream::Stream};
 use futures::{Poll, StreamExt as _};
-use blockchain::{Auxiliary, AsExternalities};
+use blockchain::{Auxiliary, AsExternalities, Block as BlockT};
 use blockchain::backend::{Store, SharedCommittable, ChainQuery, ImportLock};
 use blockchain::import::BlockImporter;
 use blockchain_network::sync::{NetworkSync, SyncConfig, SyncEvent};
 use beacon::Config;
 use shasper_runtime::{Block, StateExternalities};
-use network_messages::{HelloMessage, PubsubMessage};
+use network_messages::{HelloMessage, RecentBeaconBlocksRequest};
+use std::collections::{BTreeSet, HashMap};
+use libp2p_rpc::{RPCError, RequestId};
 use crate::rpc::{RPCEvent, RPCRequest, RPCResponse};
+use crate::validation::{GossipValidation, ValidationJob, ValidationResult};
 
 pub const VERSION: &str = "v0.1";
 
+/// Maximum number of queued blocks imported on each poll of the network.
+const IMPORT_BATCH_SIZE: usize = 16;
+
 /// Events that can be obtained from polling the Libp2p Service.
 #[derive(Debug)]
 pub enum Libp2pEvent<C: Config> {
@@ -60,22 +89,42 @@ pub enum Libp2pEvent<C: Config> {
     PeerDialed(PeerId),
     /// A peer has disconnected.
     PeerDisconnected(PeerId),
-    /// Received pubsub message.
-    Pubsub(PeerId, PubsubMessage<C>),
+    /// Received pubsub message, with the type of its topic.
+    Pubsub(PeerId, PubsubType, PubsubMessage<C>),
+    /// A peer misbehaved at the protocol level.
+    Misbehaviour(PeerId, PeerAction),
 }
 
-pub fn start_network_simple_sync<C, Ba, I>(
+/// Run the network with simple sync, until `should_exit` returns true.
+/// Blocks received on gossip are checked by `blocks` before being queued
+/// for import, and attestations and aggregates are passed to
+/// `attestations`. Both run on a validation worker, whose verdicts are
+/// handled once reported.
+pub fn start_network_simple_sync<C, Ba, I, V, G, A, E>(
 	backend: Ba,
 	import_lock: ImportLock,
 	importer: I,
+	verifier: V,
+	blocks: G,
+	attestations: A,
 	config: NetworkConfig,
+	status: NetworkStatus,
+	subnets: AttestationSubnets,
+	peers: PeerManager,
+	gossip: GossipQueue<C>,
+	should_exit: E,
 ) -> Result<(), Error> where
 	C: Config,
-	Ba: Store<Block=Block<C>> + SharedCommittable + ChainQuery + Send + Sync + 'static,
+	Ba: Store<Block=Block<C>> + SharedCommittable + ChainQuery + Clone + Send + Sync + 'static,
 	Ba::Block: Unpin + Send + Sync,
 	Ba::State: StateExternalities + AsExternalities<dyn StateExternalities<Config=C>>,
 	Ba::Auxiliary: Auxiliary<Block<C>> + Unpin,
-	I: BlockImporter<Block=Block<C>> + Unpin + Send + Sync + 'static,
+	I: BlockImporter<Block=Block<C>> + Clone + Unpin + Send + Sync + 'static,
+	I::Error: core::fmt::Debug,
+	V: BlockVerifier<Block=Block<C>> + Unpin + Send + Sync + 'static,
+	G: BlockHandler<C> + Unpin + Send + 'static,
+	A: AttestationHandler<C> + Unpin + Send + 'static,
+	E: Fn() -> bool + Unpin + Send + 'static,
 {
 	// Create a random PeerId
 	let local_key = identity::Keypair::generate_ed25519();
@@ -88,6 +137,7 @@ pub fn start_network_simple_sync<C, Ba, I>(
 		request_timeout: 4,
 	};
 
+	let mut import_queue = ImportQueue::new(backend.clone(), importer.clone(), verifier);
 	let handler = Handler::<C, Ba>::new(backend, import_lock);
 	let head_status = handler.status();
 	let mut sync = NetworkSync::<PeerId, HelloMessage, I>::new(
@@ -98,25 +148,109 @@ pub fn start_network_simple_sync<C, Ba, I>(
 	);
 
 	let mut service = Service::new(config)?;
+	let mut validation = GossipValidation::start(blocks, attestations);
 
 	let mut listening = false;
+	let mut request_ids = RequestIds::default();
+	// Parent lookups of orphan blocks, by request identifier.
+	let mut pending_parents = HashMap::new();
+	let mut subscribed_subnets = BTreeSet::new();
+	let mut attnets = Vec::new();
 
 	let poll = futures::future::poll_fn::<Result<(), ()>, _>(move |ctx| {
+		// Sync ticks every second, which bounds how long an exit goes unnoticed.
+		if should_exit() {
+			info!("Network shutting down");
+			return Poll::Ready(Ok(()))
+		}
+
+		let wanted_subnets = subnets.wanted();
+		for subnet in wanted_subnets.difference(&subscribed_subnets) {
+			let topic = PubsubType::AttestationSubnet(*subnet).gossipsub_topic();
+			if !service.swarm.subscribe(topic) {
+				warn!("Could not subscribe to attestation subnet {}", subnet);
+			}
+		}
+		for subnet in subscribed_subnets.difference(&wanted_subnets) {
+			service.swarm.unsubscribe(PubsubType::AttestationSubnet(*subnet).gossipsub_topic());
+		}
+		if wanted_subnets != subscribed_subnets {
+			debug!("Attestation subnets subscribed {:?}", wanted_subnets);
+			service.swarm.set_wanted_subnets(wanted_subnets.clone());
+			subscribed_subnets = wanted_subnets;
+		}
+
+		if let Some(max_peers) = peers.take_max_peers() {
+			info!("Target peer count changed to {}", max_peers);
+			service.swarm.set_max_peers(max_peers);
+		}
+
+		let (to_ban, to_unban) = peers.take_changes();
+		for peer in to_ban {
+			libp2p::Swarm::ban_peer_id(&mut service.swarm, peer);
+		}
+		for peer in to_unban {
+			libp2p::Swarm::unban_peer_id(&mut service.swarm, peer);
+		}
+
+		for message in gossip.take() {
+			trace!("Publishing gossip message {:?}", message);
+			service.swarm.publish(message);
+		}
+
+		let current_attnets = subnets.attnets();
+		if current_attnets != attnets {
+			service.swarm.update_attnets(current_attnets.clone());
+			attnets = current_attnets;
+		}
+
 		loop {
 			match service.poll().expect("Error while polling swarm") {
 				Async::Ready(Some(message)) => {
 					match message {
 						Libp2pEvent::PeerDialed(peer) => {
 							trace!("Peer noted to be dialed: {:?}", peer);
+							if peers.is_banned(&peer) {
+								libp2p::Swarm::ban_peer_id(&mut service.swarm, peer);
+								continue
+							}
+							status.note_connected();
 							sync.note_connected(peer);
 						},
 						Libp2pEvent::PeerDisconnected(peer) => {
 							trace!("Peer noted to disconnect: {:?}", peer);
+							status.note_disconnected();
+							pending_parents.retain(|_, (_, requested)| *requested != peer);
 							sync.note_disconnected(peer);
 						},
-						Libp2pEvent::Pubsub(peer, message) => {
+						Libp2pEvent::Pubsub(peer, _, PubsubMessage::Block(block)) => {
+							trace!("Received gossip block from {:?}", peer);
+							if !validation.submit(ValidationJob::Block(peer, block)) {
+								debug!("Gossip validation queue full, dropping block");
+							}
+						},
+						Libp2pEvent::Pubsub(peer, typ, PubsubMessage::Attestation(attestation)) => {
+							trace!("Received gossip attestation from {:?}", peer);
+							let subnet = match typ {
+								PubsubType::AttestationSubnet(subnet) => Some(subnet),
+								_ => None,
+							};
+							if !validation.submit(ValidationJob::Attestation(peer, attestation, subnet)) {
+								debug!("Gossip validation queue full, dropping attestation");
+							}
+						},
+						Libp2pEvent::Pubsub(peer, _, PubsubMessage::AggregateAndProof(aggregate)) => {
+							trace!("Received gossip aggregate from {:?}", peer);
+							if !validation.submit(ValidationJob::Aggregate(peer, aggregate)) {
+								debug!("Gossip validation queue full, dropping aggregate");
+							}
+						},
+						Libp2pEvent::Pubsub(peer, _, message) => {
 							warn!("Unhandled pubsub message {:?}, {:?}", peer, message);
 						},
+						Libp2pEvent::Misbehaviour(peer, action) => {
+							peers.report(&peer, action);
+						},
 						Libp2pEvent::RPC(peer, event) => {
 							trace!("Received RPC event {:?}, {:?}", peer, event);
 							match event {
@@ -131,6 +265,13 @@ pub fn start_network_simple_sync<C, Ba, I>(
 										)
 									));
 								},
+								RPCEvent::Request(request_id, RPCRequest::RecentBeaconBlocks(request)) => {
+									service.swarm.send_rpc(peer, RPCEvent::Response(
+										request_id, RPCResponse::RecentBeaconBlocks(
+											handler.blocks_by_root(&request.block_roots)
+										)
+									));
+								},
 								RPCEvent::Request(request_id, RPCRequest::Hello(hello)) => {
 									service.swarm.send_rpc(peer.clone(), RPCEvent::Response(
 										request_id, RPCResponse::Hello(
@@ -143,10 +284,39 @@ pub fn start_network_simple_sync<C, Ba, I>(
 									sync.note_peer_status(peer, hello);
 								},
 								RPCEvent::Response(_, RPCResponse::BeaconBlocks(blocks)) => {
-									sync.note_blocks(
-										blocks.into_iter().map(Into::into).collect(),
-										Some(peer)
-									);
+									let blocks: Vec<Block<C>> = blocks.into_iter()
+										.map(Into::into).collect();
+									if !is_linked_chain(&blocks) {
+										warn!("Peer {:?} sent unlinked blocks", peer);
+										peers.report(&peer, PeerAction::InvalidBlock);
+										continue
+									}
+									if !blocks.is_empty() {
+										peers.report(&peer, PeerAction::ValidMessage);
+									}
+									sync.note_blocks(blocks, Some(peer));
+								},
+								RPCEvent::Response(request_id, RPCResponse::RecentBeaconBlocks(blocks)) => {
+									match pending_parents.remove(&request_id) {
+										Some((_, requested)) if requested == peer => (),
+										_ => {
+											debug!("Unexpected blocks response {} from {:?}", request_id, peer);
+											continue
+										},
+									}
+									for block in blocks {
+										import_queue.push(Block(block), Some(peer.clone()));
+									}
+								},
+								RPCEvent::Error(request_id, RPCError::StreamTimeout) => {
+									debug!("RPC request to {:?} timed out", peer);
+									pending_parents.remove(&request_id);
+									peers.report(&peer, PeerAction::SlowResponse);
+								},
+								RPCEvent::Error(request_id, error) => {
+									debug!("RPC error from {:?}: {:?}", peer, error);
+									pending_parents.remove(&request_id);
+									peers.report(&peer, PeerAction::ProtocolViolation);
 								},
 								event => {
 									warn!("Unhandled RPC message {:?}, {:?}", peer, event);
@@ -167,6 +337,44 @@ pub fn start_network_simple_sync<C, Ba, I>(
 			}
 		}
 
+		while let Poll::Ready(Some(result)) = validation.poll_result(ctx) {
+			match result {
+				ValidationResult::Block(peer, block, verdict) => match verdict {
+					BlockVerdict::Gossip(GossipVerdict::Accept) |
+					BlockVerdict::UnknownParent => import_queue.push(Block(block), Some(peer)),
+					BlockVerdict::Gossip(GossipVerdict::Ignore) => (),
+					BlockVerdict::Gossip(GossipVerdict::Reject) =>
+						peers.report(&peer, PeerAction::InvalidBlock),
+				},
+				ValidationResult::Attestation(peer, verdict) => match verdict {
+					GossipVerdict::Accept => peers.report(&peer, PeerAction::ValidMessage),
+					GossipVerdict::Ignore => (),
+					GossipVerdict::Reject => peers.report(&peer, PeerAction::InvalidAttestation),
+				},
+			}
+		}
+
+		for (root, origin) in import_queue.take_missing() {
+			if let Some(peer) = origin {
+				let request_id = request_ids.next();
+				debug!("Requesting missing parent {:?} from {:?} as request {}", root, peer, request_id);
+				pending_parents.insert(request_id, (root, peer.clone()));
+				service.swarm.send_rpc(peer, RPCEvent::Request(
+					request_id,
+					RPCRequest::RecentBeaconBlocks(RecentBeaconBlocksRequest {
+						block_roots: vec![root],
+					})
+				));
+			}
+		}
+		let batch = import_queue.import_ready(IMPORT_BATCH_SIZE);
+		for (_, origin) in batch.failed {
+			if let Some(peer) = origin {
+				peers.report(&peer, PeerAction::InvalidBlock);
+			}
+		}
+		import_queue.prune();
+
 		loop {
 			match sync.poll_next_unpin(ctx) {
 				Poll::Pending | Poll::Ready(None) => break,
@@ -177,14 +385,14 @@ pub fn start_network_simple_sync<C, Ba, I>(
 				Poll::Ready(Some(SyncEvent::QueryPeerStatus(peer))) => {
 					trace!("Sync requested peer status query to {:?}", peer);
 					service.swarm.send_rpc(peer, RPCEvent::Request(
-						0,
+						request_ids.next(),
 						RPCRequest::Hello(handler.status())
 					));
 				},
 				Poll::Ready(Some(SyncEvent::QueryBlocks(peer))) => {
 					trace!("Sync requested blocks query to {:?}", peer);
 					service.swarm.send_rpc(peer, RPCEvent::Request(
-						0,
+						request_ids.next(),
 						RPCRequest::BeaconBlocks(handler.head_request(50))
 					));
 				},
@@ -196,5 +404,26 @@ pub fn start_network_simple_sync<C, Ba, I>(
 
 	tokio::run(futures::compat::Compat::new(poll));
 
-	Err(Error::Other("Shutdown".to_string()))
+	Ok(())
+}
+
+/// Allocator of identifiers of outgoing RPC requests, so that responses
+/// and errors can be matched to their request.
+#[derive(Default)]
+struct RequestIds(RequestId);
+
+impl RequestIds {
+	fn next(&mut self) -> RequestId {
+		// Zero is reserved for requests without a response.
+		self.0 = self.0.wrapping_add(1);
+		self.0
+	}
+}
+
+/// Whether blocks of a response are in ascending slot order, each building
+/// on the previous one.
+fn is_linked_chain<C: Config>(blocks: &[Block<C>]) -> bool {
+	blocks.windows(2).all(|pair| {
+		pair[1].0.slot > pair[0].0.slot && pair[1].parent_id() == Some(pair[0].id())
+	})
 }
@@ -0,0 +1,208 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Peer scoring and ban management.
+
+use libp2p::PeerId;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Score at or below which a peer is disconnected.
+pub const DISCONNECT_SCORE: f64 = -50.0;
+/// Score at or below which a peer is banned.
+pub const BAN_SCORE: f64 = -100.0;
+/// Duration a disconnected peer is kept away.
+pub const DISCONNECT_DURATION: Duration = Duration::from_secs(60);
+/// Duration a banned peer is kept away.
+pub const BAN_DURATION: Duration = Duration::from_secs(3600);
+/// Half life of peer scores, which decay towards zero.
+pub const SCORE_HALF_LIFE: Duration = Duration::from_secs(600);
+
+/// Peer behaviour affecting its score.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PeerAction {
+	/// Sent an invalid block.
+	InvalidBlock,
+	/// Sent an invalid attestation.
+	InvalidAttestation,
+	/// Did not respond in time.
+	SlowResponse,
+	/// Violated the wire protocol.
+	ProtocolViolation,
+	/// Sent a useful message.
+	ValidMessage,
+}
+
+impl PeerAction {
+	/// Score change of the action.
+	pub fn score_delta(&self) -> f64 {
+		match self {
+			PeerAction::InvalidBlock => -50.0,
+			PeerAction::InvalidAttestation => -10.0,
+			PeerAction::SlowResponse => -5.0,
+			PeerAction::ProtocolViolation => -100.0,
+			PeerAction::ValidMessage => 1.0,
+		}
+	}
+}
+
+/// Score information of a peer.
+#[derive(Clone, Debug)]
+pub struct PeerScore {
+	/// Peer id.
+	pub peer_id: PeerId,
+	/// Current score.
+	pub score: f64,
+	/// Whether the peer is currently kept away.
+	pub banned: bool,
+}
+
+struct PeerInfo {
+	score: f64,
+	updated: Instant,
+	banned_until: Option<Instant>,
+}
+
+impl PeerInfo {
+	fn decay(&mut self, now: Instant) {
+		let elapsed = now.duration_since(self.updated);
+		let half_lives = (elapsed.as_millis() as f64) / (SCORE_HALF_LIFE.as_millis() as f64);
+		self.score *= 0.5f64.powf(half_lives);
+		self.updated = now;
+	}
+}
+
+#[derive(Default)]
+struct Inner {
+	peers: HashMap<PeerId, PeerInfo>,
+	to_ban: Vec<PeerId>,
+	to_unban: Vec<PeerId>,
+	max_peers: Option<usize>,
+}
+
+/// Peer manager, scoring peers on their behaviour and deciding which ones to
+/// disconnect or ban. Shared so that scores can be reported and read from
+/// outside the networking thread.
+#[derive(Clone, Default)]
+pub struct PeerManager(Arc<Mutex<Inner>>);
+
+impl PeerManager {
+	/// Create a new peer manager.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Report an action of a peer.
+	pub fn report(&self, peer_id: &PeerId, action: PeerAction) {
+		let now = Instant::now();
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+		let (score, newly_banned) = {
+			let info = inner.peers.entry(peer_id.clone()).or_insert_with(|| PeerInfo {
+				score: 0.0,
+				updated: now,
+				banned_until: None,
+			});
+			info.decay(now);
+			info.score += action.score_delta();
+
+			let duration = if info.score <= BAN_SCORE {
+				Some(BAN_DURATION)
+			} else if info.score <= DISCONNECT_SCORE {
+				Some(DISCONNECT_DURATION)
+			} else {
+				None
+			};
+
+			let newly_banned = match duration {
+				Some(duration) => {
+					let was_banned = info.banned_until.is_some();
+					let until = now + duration;
+					if info.banned_until.map(|current| current < until).unwrap_or(true) {
+						info.banned_until = Some(until);
+					}
+					!was_banned
+				},
+				None => false,
+			};
+
+			(info.score, newly_banned)
+		};
+
+		log::debug!("Peer {:?} reported for {:?}, score {}", peer_id, action, score);
+		if newly_banned {
+			log::info!("Peer {:?} disconnected with score {}", peer_id, score);
+			inner.to_ban.push(peer_id.clone());
+		}
+	}
+
+	/// Whether a peer is currently kept away.
+	pub fn is_banned(&self, peer_id: &PeerId) -> bool {
+		let inner = self.0.lock().expect("Lock is poisoned");
+		inner.peers.get(peer_id)
+			.and_then(|info| info.banned_until)
+			.map(|until| until > Instant::now())
+			.unwrap_or(false)
+	}
+
+	/// Scores of all known peers.
+	pub fn scores(&self) -> Vec<PeerScore> {
+		let now = Instant::now();
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+		inner.peers.iter_mut().map(|(peer_id, info)| {
+			info.decay(now);
+			PeerScore {
+				peer_id: peer_id.clone(),
+				score: info.score,
+				banned: info.banned_until.map(|until| until > now).unwrap_or(false),
+			}
+		}).collect()
+	}
+
+	/// Change the target number of connected peers.
+	pub fn set_max_peers(&self, max_peers: usize) {
+		self.0.lock().expect("Lock is poisoned").max_peers = Some(max_peers);
+	}
+
+	/// Take a pending change of the target number of connected peers.
+	pub(crate) fn take_max_peers(&self) -> Option<usize> {
+		self.0.lock().expect("Lock is poisoned").max_peers.take()
+	}
+
+	/// Take peers to be banned, and peers whose ban expired, and forget
+	/// peers whose score decayed back to neutral.
+	pub(crate) fn take_changes(&self) -> (Vec<PeerId>, Vec<PeerId>) {
+		let now = Instant::now();
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+
+		let mut expired = Vec::new();
+		for (peer_id, info) in inner.peers.iter_mut() {
+			if info.banned_until.map(|until| until <= now).unwrap_or(false) {
+				info.banned_until = None;
+				expired.push(peer_id.clone());
+			}
+		}
+		inner.to_unban.extend(expired);
+		inner.peers.retain(|_, info| {
+			info.decay(now);
+			info.banned_until.is_some() || info.score.abs() >= 1.0
+		});
+
+		let to_ban = core::mem::replace(&mut inner.to_ban, Vec::new());
+		let to_unban = core::mem::replace(&mut inner.to_unban, Vec::new());
+		(to_ban, to_unban)
+	}
+}
@@ -96,7 +96,7 @@ impl<C: Config> Service<C> {
 
         // subscribe to default gossipsub topics
         let topics = vec![
-			PubsubType::Block, PubsubType::Attestation,
+			PubsubType::Block, PubsubType::Attestation, PubsubType::AggregateAndProof,
 			PubsubType::VoluntaryExit, PubsubType::ProposerSlashing,
 			PubsubType::AttesterSlashing,
 		];
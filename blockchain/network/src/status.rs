@@ -0,0 +1,53 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Shared view of the network state, readable from outside the networking
+/// thread.
+#[derive(Clone, Default, Debug)]
+pub struct NetworkStatus {
+	peers: Arc<AtomicUsize>,
+}
+
+impl NetworkStatus {
+	/// Create a new network status.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Number of currently connected peers.
+	pub fn peer_count(&self) -> usize {
+		self.peers.load(Ordering::Relaxed)
+	}
+
+	pub(crate) fn note_connected(&self) {
+		self.peers.fetch_add(1, Ordering::Relaxed);
+	}
+
+	pub(crate) fn note_disconnected(&self) {
+		let mut current = self.peers.load(Ordering::Relaxed);
+		while current > 0 {
+			match self.peers.compare_exchange(
+				current, current - 1, Ordering::Relaxed, Ordering::Relaxed
+			) {
+				Ok(_) => return,
+				Err(actual) => current = actual,
+			}
+		}
+	}
+}
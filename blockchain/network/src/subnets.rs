@@ -0,0 +1,128 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Attestation subnet subscriptions, derived from persistent random subnets
+//! and upcoming attestation duties.
+
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+use rand::seq::SliceRandom;
+use beacon::primitives::{Epoch, Slot};
+
+/// Number of attestation subnets.
+pub const ATTESTATION_SUBNET_COUNT: u64 = 64;
+/// Number of persistent random subnets per validator.
+pub const RANDOM_SUBNETS_PER_VALIDATOR: u64 = 1;
+/// Number of epochs before persistent random subnets are chosen again.
+pub const EPOCHS_PER_RANDOM_SUBNET_SUBSCRIPTION: u64 = 256;
+/// Number of slots ahead of an attestation duty to subscribe to its subnet.
+pub const SUBNET_SUBSCRIPTION_LOOKAHEAD_SLOTS: u64 = 2;
+/// ENR key advertising persistent attestation subnets.
+pub const ATTNETS_ENR_KEY: &str = "attnets";
+
+/// Subnet on which attestations of a committee are published.
+pub fn compute_subnet_for_attestation(committee_index: u64) -> u64 {
+	committee_index % ATTESTATION_SUBNET_COUNT
+}
+
+/// Attestation duty of a local validator.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct SubnetDuty {
+	/// Slot of the attestation.
+	pub slot: Slot,
+	/// Committee index of the attestation.
+	pub committee_index: u64,
+}
+
+#[derive(Default)]
+struct Inner {
+	validator_count: usize,
+	persistent: BTreeSet<u64>,
+	persistent_epoch: Option<Epoch>,
+	duties: BTreeSet<SubnetDuty>,
+	current_slot: Slot,
+}
+
+/// Shared attestation subnet subscription state. Validator duties are noted
+/// from outside the networking thread, which subscribes accordingly.
+#[derive(Clone, Default)]
+pub struct AttestationSubnets(Arc<Mutex<Inner>>);
+
+impl AttestationSubnets {
+	/// Create subnet subscriptions for the given number of local validators.
+	pub fn new(validator_count: usize) -> Self {
+		let subnets = Self::default();
+		subnets.0.lock().expect("Lock is poisoned").validator_count = validator_count;
+		subnets.note_slot(Slot::new(0), Epoch::new(0));
+		subnets
+	}
+
+	/// Note the current slot, dropping past duties, and choosing persistent
+	/// subnets again if the subscription period has passed.
+	pub fn note_slot(&self, slot: Slot, epoch: Epoch) {
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+		inner.current_slot = slot;
+		inner.duties = inner.duties.iter()
+			.filter(|duty| duty.slot + 1 >= slot)
+			.cloned()
+			.collect();
+
+		let period = epoch / EPOCHS_PER_RANDOM_SUBNET_SUBSCRIPTION;
+		if inner.persistent_epoch.map(|e| e / EPOCHS_PER_RANDOM_SUBNET_SUBSCRIPTION) != Some(period) {
+			let count = core::cmp::min(
+				inner.validator_count as u64 * RANDOM_SUBNETS_PER_VALIDATOR,
+				ATTESTATION_SUBNET_COUNT,
+			) as usize;
+			let all = (0..ATTESTATION_SUBNET_COUNT).collect::<Vec<_>>();
+			inner.persistent = all.choose_multiple(&mut rand::thread_rng(), count)
+				.cloned().collect();
+			inner.persistent_epoch = Some(epoch);
+		}
+	}
+
+	/// Note attestation duties of local validators.
+	pub fn note_duties<I: IntoIterator<Item=SubnetDuty>>(&self, duties: I) {
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+		inner.duties.extend(duties);
+	}
+
+	/// Persistent random subnets.
+	pub fn persistent(&self) -> BTreeSet<u64> {
+		self.0.lock().expect("Lock is poisoned").persistent.clone()
+	}
+
+	/// Persistent random subnets as the ENR `attnets` bitfield.
+	pub fn attnets(&self) -> Vec<u8> {
+		let mut bytes = vec![0u8; (ATTESTATION_SUBNET_COUNT / 8) as usize];
+		for subnet in self.persistent() {
+			bytes[(subnet / 8) as usize] |= 1 << (subnet % 8);
+		}
+		bytes
+	}
+
+	/// Subnets that should currently be subscribed to: persistent ones, and
+	/// those of duties within the lookahead window.
+	pub fn wanted(&self) -> BTreeSet<u64> {
+		let inner = self.0.lock().expect("Lock is poisoned");
+		let mut wanted = inner.persistent.clone();
+		for duty in &inner.duties {
+			if duty.slot <= inner.current_slot + SUBNET_SUBSCRIPTION_LOOKAHEAD_SLOTS {
+				wanted.insert(compute_subnet_for_attestation(duty.committee_index));
+			}
+		}
+		wanted
+	}
+}
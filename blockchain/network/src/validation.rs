@@ -0,0 +1,128 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+
+//! Validation of gossip messages on a worker thread, so that state access
+//! and signature checks do not stall the network poll loop.
+
+use beacon::Config;
+use beacon::types::{Attestation, BeaconBlock, SignedAggregateAndProof};
+use futures::channel::mpsc::{unbounded, UnboundedReceiver, UnboundedSender};
+use futures::task::Context;
+use futures::{Poll, StreamExt as _};
+use libp2p::PeerId;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender, TrySendError};
+use std::thread;
+use log::*;
+use crate::gossip::{AttestationHandler, BlockHandler, BlockVerdict, GossipVerdict};
+
+/// Maximum number of gossip messages waiting for validation. Messages
+/// received while the queue is full are dropped.
+pub const MAX_PENDING_VALIDATIONS: usize = 1024;
+
+/// Gossip message to validate, along with the peer that sent it.
+pub enum ValidationJob<C: Config> {
+	/// Block received on the block topic.
+	Block(PeerId, BeaconBlock<C>),
+	/// Attestation received on a subnet, or on the global topic if the
+	/// subnet is `None`.
+	Attestation(PeerId, Attestation<C>, Option<u64>),
+	/// Aggregate received on the aggregate and proof topic.
+	Aggregate(PeerId, SignedAggregateAndProof<C>),
+}
+
+/// Outcome of the validation of a gossip message.
+pub enum ValidationResult<C: Config> {
+	/// Verdict on a block, which is returned for import.
+	Block(PeerId, BeaconBlock<C>, BlockVerdict),
+	/// Verdict on an attestation or an aggregate.
+	Attestation(PeerId, GossipVerdict),
+}
+
+/// Queue of gossip messages validated on a worker thread. Results are
+/// reported asynchronously, and the worker stops once the queue is
+/// dropped.
+pub struct GossipValidation<C: Config> {
+	jobs: SyncSender<ValidationJob<C>>,
+	results: UnboundedReceiver<ValidationResult<C>>,
+}
+
+impl<C: Config> GossipValidation<C> {
+	/// Start the validation worker.
+	pub fn start<G, A>(blocks: G, attestations: A) -> Self where
+		G: BlockHandler<C> + Send + 'static,
+		A: AttestationHandler<C> + Send + 'static,
+	{
+		let (jobs, receiver) = sync_channel(MAX_PENDING_VALIDATIONS);
+		let (sender, results) = unbounded();
+
+		thread::Builder::new()
+			.name("gossip-validation".to_string())
+			.spawn(move || run_validation(blocks, attestations, receiver, sender))
+			.expect("Spawning a thread does not fail");
+
+		Self { jobs, results }
+	}
+
+	/// Queue a message for validation. Returns false if the queue is full
+	/// and the message was dropped.
+	pub fn submit(&self, job: ValidationJob<C>) -> bool {
+		match self.jobs.try_send(job) {
+			Ok(()) => true,
+			Err(TrySendError::Full(_)) => false,
+			Err(TrySendError::Disconnected(_)) => {
+				warn!("Gossip validation worker stopped");
+				false
+			},
+		}
+	}
+
+	/// Poll for the next validation result, waking the task once one is
+	/// available.
+	pub fn poll_result(&mut self, ctx: &mut Context) -> Poll<Option<ValidationResult<C>>> {
+		self.results.poll_next_unpin(ctx)
+	}
+}
+
+fn run_validation<C, G, A>(
+	blocks: G,
+	attestations: A,
+	jobs: Receiver<ValidationJob<C>>,
+	results: UnboundedSender<ValidationResult<C>>,
+) where
+	C: Config,
+	G: BlockHandler<C>,
+	A: AttestationHandler<C>,
+{
+	for job in jobs {
+		let result = match job {
+			ValidationJob::Block(peer, block) => {
+				let verdict = blocks.handle_block(&block);
+				ValidationResult::Block(peer, block, verdict)
+			},
+			ValidationJob::Attestation(peer, attestation, subnet) => ValidationResult::Attestation(
+				peer, attestations.handle_attestation(attestation, subnet),
+			),
+			ValidationJob::Aggregate(peer, aggregate) => ValidationResult::Attestation(
+				peer, attestations.handle_aggregate(aggregate),
+			),
+		};
+		if results.unbounded_send(result).is_err() {
+			break
+		}
+	}
+	debug!("Gossip validation worker stopped");
+}
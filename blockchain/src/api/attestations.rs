@@ -0,0 +1,127 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use beacon::{BeaconExecutive, BLSConfig, Config};
+use beacon::primitives::Slot;
+use beacon::types::{AttestationData, Checkpoint};
+use blockchain::AsExternalities;
+use blockchain::backend::{Store, ChainQuery};
+use serde::Serialize;
+use core::str::FromStr;
+use std::sync::Arc;
+use crate::{Block, Executor, StateExternalities};
+use crate::clock::SlotClock;
+use super::{ApiServer, Method, Request, Response};
+
+#[derive(Serialize)]
+struct Data<T> {
+	data: T,
+}
+
+fn parse_query<T: FromStr>(request: &Request, name: &str) -> Result<T, Response> {
+	request.query(name)
+		.and_then(|value| T::from_str(value).ok())
+		.ok_or_else(|| Response::error(400, &format!("Invalid query parameter {}", name)))
+}
+
+/// Compute the data a committee attests to at `slot`, voting for the head
+/// of the fork choice.
+fn produce_attestation_data<B, C, BLS>(
+	backend: &B,
+	executor: &Executor<C, BLS>,
+	slot: Slot,
+	index: u64,
+) -> Result<AttestationData, Response> where
+	B: ChainQuery + Store<Block=Block<C>>,
+	B::State: StateExternalities<Config=C> + AsExternalities<dyn StateExternalities<Config=C>>,
+	C: Config,
+	BLS: BLSConfig,
+{
+	let unavailable = |e: B::Error| Response::error(503, &format!("Database unavailable: {}", e));
+	let head = backend.head();
+	let head_block = backend.block_at(&head).map_err(unavailable)?;
+	if slot < head_block.0.slot {
+		return Err(Response::error(400, "Slot is before the head block"))
+	}
+
+	let mut state = backend.state_at(&head).map_err(unavailable)?;
+	let externalities = state.as_externalities();
+	executor.advance_slots(externalities, slot)
+		.map_err(|e| Response::error(500, &format!("Advancing head state failed: {:?}", e)))?;
+
+	let epoch = beacon::utils::epoch_of_slot::<C>(slot);
+	let committees = executor.committees().committees(externalities.state_mut(), epoch)
+		.map_err(|e| Response::error(500, &format!("{:?}", e)))?;
+	if committees.committee::<C>(slot, index).is_none() {
+		return Err(Response::error(400, "Invalid committee index"))
+	}
+
+	let executive = BeaconExecutive::new(externalities.state_mut());
+	let target_slot = beacon::utils::start_slot_of_epoch::<C>(epoch);
+	let target_root = if target_slot >= head_block.0.slot {
+		head
+	} else {
+		executive.block_root(epoch)
+			.map_err(|e| Response::error(500, &format!("{:?}", e)))?
+	};
+
+	Ok(AttestationData {
+		slot,
+		index,
+		beacon_block_root: head,
+		source: executive.current_justified_checkpoint.clone(),
+		target: Checkpoint { epoch, root: target_root },
+	})
+}
+
+/// Register the `/eth/v1/validator/attestation_data` endpoint, the standard
+/// API's `produceAttestationData`, taking `slot` and `committee_index` query
+/// parameters.
+///
+/// The head vote is the fork choice head, the target is the epoch boundary
+/// block of its chain and the source its justified checkpoint, with the
+/// committee checked through the executor committee cache. Signed
+/// attestations are submitted back through the pool endpoint.
+pub fn register_attestations<B, C, BLS>(
+	server: &mut ApiServer,
+	backend: B,
+	executor: Executor<C, BLS>,
+	clock: Arc<dyn SlotClock>,
+) where
+	B: ChainQuery + Store<Block=Block<C>> + Clone + Send + Sync + 'static,
+	B::State: StateExternalities<Config=C> + AsExternalities<dyn StateExternalities<Config=C>>,
+	C: Config + Send + Sync + 'static,
+	BLS: BLSConfig + Send + Sync + 'static,
+{
+	server.route(Method::Get, "/eth/v1/validator/attestation_data", move |request| {
+		let slot = match parse_query::<Slot>(request, "slot") {
+			Ok(slot) => slot,
+			Err(response) => return response,
+		};
+		let index = match parse_query::<u64>(request, "committee_index") {
+			Ok(index) => index,
+			Err(response) => return response,
+		};
+		if slot > clock.now().unwrap_or_default() + 1 {
+			return Response::error(400, "Slot is too far in the future")
+		}
+
+		match produce_attestation_data(&backend, &executor, slot, index) {
+			Ok(data) => Response::json(200, &Data { data }),
+			Err(response) => response,
+		}
+	});
+}
@@ -0,0 +1,256 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use beacon::{BeaconExecutive, Config, BLSConfig, Inherent};
+use beacon::primitives::{H256, H768, Slot};
+use beacon::types::{BeaconBlock, UnsealedBeaconBlock};
+use blockchain::AsExternalities;
+use blockchain::backend::{Store, ChainQuery};
+use blockchain::import::SharedBlockImporter;
+use log::{info, trace};
+use serde::Serialize;
+use core::str::FromStr;
+use std::sync::Arc;
+use std::time::Instant;
+use crate::{Block, Executor, StateExternalities, SharedOperationPool};
+use crate::backend::CanonSlotQuery;
+use crate::clock::SlotClock;
+use crate::eth1::{Eth1Provider, voting_period_timestamp};
+use crate::graffiti::parse_graffiti;
+use crate::settings::LiveSettings;
+use super::{ApiServer, Method, Request, Response};
+
+#[derive(Serialize)]
+struct Data<T> {
+	data: T,
+}
+
+fn parse_hex(value: &str) -> Option<Vec<u8>> {
+	let value = if value.starts_with("0x") { &value[2..] } else { value };
+	hex::decode(value).ok()
+}
+
+/// Build an unsealed block at `slot` on top of the head, including pending
+/// deposits and pool operations that apply cleanly until `deadline`.
+/// Without an explicit graffiti, the graffiti configured for the proposer
+/// is used.
+fn produce_block_with_deadline<B, C, BLS>(
+	backend: &B,
+	executor: &Executor<C, BLS>,
+	pool: &SharedOperationPool<C, BLS>,
+	eth1_provider: &dyn Eth1Provider<C>,
+	settings: &LiveSettings,
+	slot: Slot,
+	deadline: Instant,
+	randao_reveal: H768,
+	graffiti: Option<H256>,
+) -> Result<UnsealedBeaconBlock<C>, Response> where
+	B: ChainQuery + Store<Block=Block<C>>,
+	B::State: StateExternalities<Config=C> + AsExternalities<dyn StateExternalities<Config=C>>,
+	C: Config,
+	BLS: BLSConfig,
+{
+	let unavailable = |e: B::Error| Response::error(503, &format!("Database unavailable: {}", e));
+	let head = backend.head();
+	let head_block = backend.block_at(&head).map_err(unavailable)?;
+	if slot <= head_block.0.slot {
+		return Err(Response::error(400, "Slot is not after the head block"))
+	}
+
+	let mut state = backend.state_at(&head).map_err(unavailable)?;
+	executor.initialize_block(state.as_externalities(), slot)
+		.map_err(|e| Response::error(500, &format!("Advancing head state failed: {:?}", e)))?;
+
+	let graffiti = match graffiti {
+		Some(graffiti) => graffiti,
+		None => {
+			let executive = BeaconExecutive::new(state.as_externalities().state_mut());
+			let proposer_index = executive.beacon_proposer_index()
+				.map_err(|e| Response::error(500, &format!("{:?}", e)))?;
+			settings.graffiti_for(&executive.validators[proposer_index as usize].pubkey)
+		},
+	};
+
+	let eth1_data = eth1_provider.eth1_data(voting_period_timestamp::<C>(
+		state.state().genesis_time, slot,
+	));
+	let mut block = executor.apply_inherent(
+		&head_block, state.as_externalities(),
+		Inherent { randao_reveal, eth1_data, graffiti },
+	).map_err(|e| Response::error(400, &format!("{:?}", e)))?;
+
+	let deposit_index = state.state().eth1_deposit_index;
+	let deposit_count = state.state().eth1_data.deposit_count;
+	let deposits_to = core::cmp::min(deposit_count, deposit_index + C::max_deposits());
+	let deposits = eth1_provider.deposits(deposit_index, deposits_to, deposit_count)
+		.map_err(|e| Response::error(503, &format!("Fetching deposits failed: {:?}", e)))?;
+
+	let transactions = {
+		let mut pool = pool.lock();
+		for (offset, deposit) in deposits.into_iter().enumerate() {
+			pool.insert_deposit(deposit_index + offset as u64, deposit);
+		}
+		pool.transactions(state.state())
+	};
+
+	let (included, skipped) = executor.apply_extrinsics_until(
+		&mut block, state.as_externalities(), transactions, deadline,
+	);
+	for (_, e) in skipped {
+		trace!("Skipping operation in produced block: {:?}", e);
+	}
+	info!("Produced block at slot {} with {} operations", slot, included);
+
+	executor.finalize_block(&mut block, state.as_externalities())
+		.map_err(|e| Response::error(500, &format!("{:?}", e)))?;
+	Ok(block)
+}
+
+fn parse_slot(request: &Request) -> Result<Slot, Response> {
+	request.param("slot")
+		.and_then(|value| Slot::from_str(value).ok())
+		.ok_or_else(|| Response::error(400, "Invalid slot"))
+}
+
+/// Resolve a block identifier of the standard API, one of `head`,
+/// `genesis`, a slot or a hex encoded block root, to a canonical block root.
+/// Slots are looked up through the canonical slot index.
+fn resolve_block_id<B>(backend: &B, block_id: &str) -> Result<H256, Response> where
+	B: CanonSlotQuery + Store,
+	B::Block: blockchain::Block<Identifier=H256>,
+{
+	let unavailable = |e: B::Error| Response::error(503, &format!("Database unavailable: {}", e));
+	let not_found = || Response::error(404, "Block not found");
+
+	match block_id {
+		"head" => Ok(backend.head()),
+		"genesis" => Ok(backend.genesis()),
+		_ if block_id.starts_with("0x") => {
+			let root = match parse_hex(block_id) {
+				Some(ref bytes) if bytes.len() == H256::len_bytes() => H256::from_slice(bytes),
+				_ => return Err(Response::error(400, "Invalid block id")),
+			};
+			if backend.contains(&root).map_err(unavailable)? {
+				Ok(root)
+			} else {
+				Err(not_found())
+			}
+		},
+		_ => {
+			let slot = Slot::from_str(block_id)
+				.map_err(|_| Response::error(400, "Invalid block id"))?;
+			backend.lookup_canon_slot(slot).map_err(unavailable)?.ok_or_else(not_found)
+		},
+	}
+}
+
+/// Register `/eth/v1/validator/blocks/:slot` and `/eth/v1/beacon/blocks`
+/// endpoints, the standard API's `produceBlock` and `publishBlock`, and
+/// the `/eth/v1/beacon/blocks/:block_id/root` and
+/// `/eth/v2/beacon/blocks/:block_id` lookups.
+///
+/// Produced blocks are built on the head with operations from the pool,
+/// and returned unsigned for a remote validator client to sign. The
+/// graffiti query parameter defaults to the graffiti configured for the
+/// proposer. Published blocks
+/// are imported, which validates them fully.
+pub fn register_blocks<B, C, BLS, I>(
+	server: &mut ApiServer,
+	backend: B,
+	importer: I,
+	executor: Executor<C, BLS>,
+	pool: SharedOperationPool<C, BLS>,
+	eth1_provider: Arc<dyn Eth1Provider<C>>,
+	settings: LiveSettings,
+	clock: Arc<dyn SlotClock>,
+) where
+	B: CanonSlotQuery + Store<Block=Block<C>> + Clone + Send + Sync + 'static,
+	B::State: StateExternalities<Config=C> + AsExternalities<dyn StateExternalities<Config=C>>,
+	C: Config + Send + Sync + 'static,
+	BLS: BLSConfig + Send + Sync + 'static,
+	I: SharedBlockImporter<Block=Block<C>> + Send + Sync + 'static,
+	I::Error: core::fmt::Debug,
+{
+	let root_backend = backend.clone();
+	server.route(Method::Get, "/eth/v1/beacon/blocks/:block_id/root", move |request| {
+		#[derive(Serialize)]
+		struct Root {
+			root: H256,
+		}
+
+		let block_id = request.param("block_id").unwrap_or("");
+		match resolve_block_id(&root_backend, block_id) {
+			Ok(root) => Response::json(200, &Data { data: Root { root } }),
+			Err(response) => response,
+		}
+	});
+
+	let block_backend = backend.clone();
+	server.route(Method::Get, "/eth/v2/beacon/blocks/:block_id", move |request| {
+		let block_id = request.param("block_id").unwrap_or("");
+		let root = match resolve_block_id(&block_backend, block_id) {
+			Ok(root) => root,
+			Err(response) => return response,
+		};
+		match block_backend.block_at(&root) {
+			Ok(block) => Response::json(200, &Data { data: block.0 }),
+			Err(e) => Response::error(503, &format!("Database unavailable: {}", e)),
+		}
+	});
+
+	server.route(Method::Get, "/eth/v1/validator/blocks/:slot", move |request| {
+		let slot = match parse_slot(request) {
+			Ok(slot) => slot,
+			Err(response) => return response,
+		};
+		if slot > clock.now().unwrap_or_default() + 1 {
+			return Response::error(400, "Slot is too far in the future")
+		}
+
+		let randao_reveal = match request.query("randao_reveal").and_then(parse_hex) {
+			Some(ref bytes) if bytes.len() == H768::len_bytes() => H768::from_slice(bytes),
+			_ => return Response::error(400, "Invalid randao reveal"),
+		};
+		let graffiti = match request.query("graffiti") {
+			Some(value) => match parse_graffiti(value) {
+				Some(graffiti) => Some(graffiti),
+				None => return Response::error(400, "Invalid graffiti"),
+			},
+			None => None,
+		};
+
+		let deadline = clock.instant_at(slot, settings.block_deadline());
+		match produce_block_with_deadline(
+			&backend, &executor, &pool, eth1_provider.as_ref(), &settings,
+			slot, deadline, randao_reveal, graffiti,
+		) {
+			Ok(block) => Response::json(200, &Data { data: block }),
+			Err(response) => response,
+		}
+	});
+
+	server.route(Method::Post, "/eth/v1/beacon/blocks", move |request| {
+		let block = match serde_json::from_slice::<BeaconBlock<C>>(&request.body) {
+			Ok(block) => block,
+			Err(e) => return Response::error(400, &format!("Invalid request body: {}", e)),
+		};
+
+		match importer.import_block(Block(block)) {
+			Ok(()) => Response::json(200, &Data { data: () }),
+			Err(e) => Response::error(400, &format!("Importing block failed: {:?}", e)),
+		}
+	});
+}
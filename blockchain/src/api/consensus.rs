@@ -0,0 +1,271 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use beacon::{Config, ForkSchedule};
+use beacon::primitives::{H256, Epoch, Slot};
+use beacon::types::Checkpoint;
+use blockchain::Block as BlockT;
+use blockchain::backend::{Store, ChainQuery};
+use serde::{Serialize, Deserialize};
+use core::str::FromStr;
+use crate::{Block, StateExternalities};
+use crate::preset::fetch;
+use super::{ApiServer, Method, Response};
+
+/// Default number of recent epochs to compare.
+pub const DEFAULT_COMPARE_EPOCHS: u64 = 4;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+struct SlotRoots {
+	slot: Slot,
+	block_root: H256,
+	state_root: H256,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+struct EpochSummary {
+	epoch: Epoch,
+	blocks: Vec<SlotRoots>,
+	current_justified_checkpoint: Checkpoint,
+	finalized_checkpoint: Checkpoint,
+}
+
+/// Finality checkpoints of a node's head state.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct FinalityCheckpoints {
+	/// Previous justified checkpoint.
+	pub previous_justified: Checkpoint,
+	/// Current justified checkpoint.
+	pub current_justified: Checkpoint,
+	/// Finalized checkpoint.
+	pub finalized: Checkpoint,
+}
+
+#[derive(Serialize)]
+struct Divergence {
+	epoch: Epoch,
+	slot: Option<Slot>,
+	field: &'static str,
+	local: Option<String>,
+	remote: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Comparison {
+	from_epoch: Epoch,
+	to_epoch: Epoch,
+	divergence: Option<Divergence>,
+}
+
+/// Summarize canonical blocks and finality of `epoch`, walking back from
+/// the head. Returns `None` if the epoch is after the head.
+fn epoch_summary<B, C>(backend: &B, epoch: Epoch) -> Result<Option<EpochSummary>, B::Error> where
+	B: ChainQuery + Store<Block=Block<C>>,
+	B::State: StateExternalities<Config=C>,
+	C: Config,
+{
+	let start_slot = beacon::utils::start_slot_of_epoch::<C>(epoch);
+	let end_slot = start_slot + C::slots_per_epoch();
+
+	let mut current = backend.block_at(&backend.head())?;
+	if current.0.slot < start_slot {
+		return Ok(None)
+	}
+	while current.0.slot >= end_slot {
+		match current.parent_id() {
+			Some(parent) => current = backend.block_at(&parent)?,
+			None => return Ok(None),
+		}
+	}
+
+	let state = backend.state_at(&current.id())?;
+	let current_justified_checkpoint = state.state().current_justified_checkpoint.clone();
+	let finalized_checkpoint = state.state().finalized_checkpoint.clone();
+
+	let mut blocks = Vec::new();
+	while current.0.slot >= start_slot {
+		blocks.push(SlotRoots {
+			slot: current.0.slot,
+			block_root: current.id(),
+			state_root: current.0.state_root,
+		});
+		match current.parent_id() {
+			Some(parent) => current = backend.block_at(&parent)?,
+			None => break,
+		}
+	}
+	blocks.reverse();
+
+	Ok(Some(EpochSummary {
+		epoch, blocks, current_justified_checkpoint, finalized_checkpoint,
+	}))
+}
+
+/// Find the first point where two summaries of the same epoch diverge.
+fn first_divergence(local: &EpochSummary, remote: &EpochSummary) -> Option<Divergence> {
+	let epoch = local.epoch;
+	let hex = |root: &H256| format!("{:?}", root);
+	let divergence = |slot, field, l: Option<String>, r: Option<String>| Divergence {
+		epoch, slot, field, local: l, remote: r,
+	};
+
+	let mut local_blocks = local.blocks.iter().peekable();
+	let mut remote_blocks = remote.blocks.iter().peekable();
+	let found = loop {
+		match (local_blocks.peek(), remote_blocks.peek()) {
+			(Some(l), Some(r)) if l.slot == r.slot => {
+				if l.block_root != r.block_root {
+					break Some(divergence(
+						Some(l.slot), "block_root", Some(hex(&l.block_root)), Some(hex(&r.block_root)),
+					))
+				}
+				if l.state_root != r.state_root {
+					break Some(divergence(
+						Some(l.slot), "state_root", Some(hex(&l.state_root)), Some(hex(&r.state_root)),
+					))
+				}
+				local_blocks.next();
+				remote_blocks.next();
+			},
+			(Some(l), Some(r)) if l.slot > r.slot => {
+				break Some(divergence(Some(r.slot), "block_root", None, Some(hex(&r.block_root))))
+			},
+			(Some(l), _) => {
+				break Some(divergence(Some(l.slot), "block_root", Some(hex(&l.block_root)), None))
+			},
+			(None, Some(r)) => {
+				break Some(divergence(Some(r.slot), "block_root", None, Some(hex(&r.block_root))))
+			},
+			(None, None) => break None,
+		}
+	};
+
+	if found.is_some() {
+		return found
+	}
+	if local.current_justified_checkpoint != remote.current_justified_checkpoint {
+		return Some(divergence(
+			None, "current_justified_checkpoint",
+			Some(format!("{:?}", local.current_justified_checkpoint)),
+			Some(format!("{:?}", remote.current_justified_checkpoint)),
+		))
+	}
+	if local.finalized_checkpoint != remote.finalized_checkpoint {
+		return Some(divergence(
+			None, "finalized_checkpoint",
+			Some(format!("{:?}", local.finalized_checkpoint)),
+			Some(format!("{:?}", remote.finalized_checkpoint)),
+		))
+	}
+
+	None
+}
+
+/// Register the consensus comparison endpoints.
+///
+/// `/eth/v1/beacon/states/head/finality_checkpoints` returns the finality
+/// checkpoints of the head state. `/eth/v1/debug/consensus/:epoch` summarizes the canonical block and state
+/// roots and finality checkpoints of an epoch. `/eth/v1/debug/compare` walks
+/// the `epochs` most recent epochs comparing these summaries against the
+/// node at `endpoint`, and reports the first divergence point.
+pub fn register_consensus<B, C>(
+	server: &mut ApiServer,
+	backend: B,
+) where
+	B: ChainQuery + Store<Block=Block<C>> + Clone + Send + Sync + 'static,
+	B::State: StateExternalities<Config=C>,
+	C: Config,
+{
+	let finality_backend = backend.clone();
+	server.route(Method::Get, "/eth/v1/beacon/states/head/finality_checkpoints", move |_| {
+		match finality_backend.state_at(&finality_backend.head()) {
+			Ok(state) => Response::json(200, &FinalityCheckpoints {
+				previous_justified: state.state().previous_justified_checkpoint.clone(),
+				current_justified: state.state().current_justified_checkpoint.clone(),
+				finalized: state.state().finalized_checkpoint.clone(),
+			}),
+			Err(e) => Response::error(503, &format!("Database unavailable: {}", e)),
+		}
+	});
+
+	let summary_backend = backend.clone();
+	server.route(Method::Get, "/eth/v1/debug/consensus/:epoch", move |request| {
+		let epoch = match request.param("epoch").and_then(|v| Epoch::from_str(v).ok()) {
+			Some(epoch) => epoch,
+			None => return Response::error(400, "Invalid epoch"),
+		};
+
+		match epoch_summary(&summary_backend, epoch) {
+			Ok(Some(summary)) => Response::json(200, &summary),
+			Ok(None) => Response::error(404, "Epoch is after the head"),
+			Err(e) => Response::error(503, &format!("Database unavailable: {}", e)),
+		}
+	});
+
+	server.route(Method::Get, "/eth/v1/debug/compare", move |request| {
+		let endpoint = match request.query("endpoint") {
+			Some(endpoint) => endpoint.trim_end_matches('/').to_string(),
+			None => return Response::error(400, "Missing endpoint"),
+		};
+		let epochs = match request.query("epochs").map(u64::from_str) {
+			Some(Ok(epochs)) => epochs,
+			Some(Err(_)) => return Response::error(400, "Invalid epochs"),
+			None => DEFAULT_COMPARE_EPOCHS,
+		};
+
+		let head_slot = match backend.block_at(&backend.head()) {
+			Ok(block) => block.0.slot,
+			Err(e) => return Response::error(503, &format!("Database unavailable: {}", e)),
+		};
+		let to_epoch = beacon::utils::epoch_of_slot::<C>(head_slot);
+		let from_epoch = (to_epoch + 1).saturating_sub(epochs);
+
+		for epoch in (from_epoch.as_u64()..=to_epoch.as_u64()).map(Epoch::new) {
+			let local = match epoch_summary(&backend, epoch) {
+				Ok(Some(summary)) => summary,
+				Ok(None) => break,
+				Err(e) => return Response::error(503, &format!("Database unavailable: {}", e)),
+			};
+			let remote = match fetch(&format!("{}/eth/v1/debug/consensus/{}", endpoint, epoch))
+				.map_err(|e| format!("{:?}", e))
+				.and_then(|data| serde_json::from_slice::<EpochSummary>(&data)
+						  .map_err(|e| format!("{}", e)))
+			{
+				Ok(remote) => remote,
+				Err(e) => return Response::error(502, &format!("Remote node unavailable: {}", e)),
+			};
+
+			if let Some(divergence) = first_divergence(&local, &remote) {
+				return Response::json(200, &Comparison {
+					from_epoch, to_epoch, divergence: Some(divergence),
+				})
+			}
+		}
+
+		Response::json(200, &Comparison { from_epoch, to_epoch, divergence: None })
+	});
+}
+
+/// Register the `/eth/v1/config/fork_schedule` endpoint, listing the forks
+/// of the chain as recorded in states.
+pub fn register_fork_schedule(
+	server: &mut ApiServer,
+	schedule: ForkSchedule,
+) {
+	server.route(Method::Get, "/eth/v1/config/fork_schedule", move |_| {
+		Response::json(200, &schedule.all_forks())
+	});
+}
@@ -0,0 +1,308 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use beacon::{BeaconExecutive, BeaconState, BLSConfig, Config};
+use beacon::primitives::{Epoch, Gwei, Slot, ValidatorId, ValidatorIndex};
+use beacon::types::{Validator, ValidatorStatus};
+use blockchain::AsExternalities;
+use blockchain::backend::{Store, ChainQuery};
+use serde::{Serialize, Deserialize};
+use core::str::FromStr;
+use std::sync::Arc;
+use crate::{Block, Executor, StateExternalities};
+use crate::clock::SlotClock;
+use crate::duties::{DutyHistory, EpochDuty};
+use super::{ApiServer, Method, Request, Response};
+
+#[derive(Serialize)]
+struct ValidatorDuties {
+	current_slot: Slot,
+	next_proposal: Option<Slot>,
+	history: Vec<EpochDuty>,
+}
+
+#[derive(Serialize)]
+struct Data<T> {
+	data: T,
+}
+
+#[derive(Serialize)]
+struct ProposerDuty {
+	pubkey: ValidatorId,
+	#[serde(with = "beacon::utils::uint")]
+	validator_index: ValidatorIndex,
+	#[serde(with = "beacon::utils::uint")]
+	slot: Slot,
+}
+
+#[derive(Serialize)]
+struct AttesterDuty {
+	pubkey: ValidatorId,
+	#[serde(with = "beacon::utils::uint")]
+	validator_index: ValidatorIndex,
+	#[serde(with = "beacon::utils::uint")]
+	committee_index: u64,
+	#[serde(with = "beacon::utils::uint")]
+	committee_length: u64,
+	#[serde(with = "beacon::utils::uint")]
+	committees_at_slot: u64,
+	#[serde(with = "beacon::utils::uint")]
+	validator_committee_index: u64,
+	#[serde(with = "beacon::utils::uint")]
+	slot: Slot,
+}
+
+#[derive(Serialize)]
+struct ValidatorData {
+	#[serde(with = "beacon::utils::uint")]
+	index: ValidatorIndex,
+	#[serde(with = "beacon::utils::uint")]
+	balance: Gwei,
+	status: ValidatorStatus,
+	validator: Validator,
+}
+
+#[derive(Deserialize)]
+struct ValidatorIndices(
+	#[serde(deserialize_with = "beacon::utils::deserialize_uint_list")]
+	Vec<ValidatorIndex>,
+);
+
+fn parse_epoch(request: &Request) -> Result<Epoch, Response> {
+	request.param("epoch")
+		.and_then(|value| Epoch::from_str(value).ok())
+		.ok_or_else(|| Response::error(400, "Invalid epoch"))
+}
+
+/// Head state advanced to the start of `epoch`, or of the current epoch of
+/// the clock if `epoch` is in the future.
+fn epoch_state<B, C, BLS>(
+	backend: &B,
+	executor: &Executor<C, BLS>,
+	clock: &dyn SlotClock,
+	epoch: Epoch,
+) -> Result<B::State, Response> where
+	B: ChainQuery + Store<Block=Block<C>>,
+	B::State: StateExternalities<Config=C> + AsExternalities<dyn StateExternalities<Config=C>>,
+	C: Config,
+	BLS: BLSConfig,
+{
+	let mut state = backend.state_at(&backend.head())
+		.map_err(|e| Response::error(503, &format!("Database unavailable: {}", e)))?;
+	let current_epoch = beacon::utils::epoch_of_slot::<C>(clock.now().unwrap_or_default());
+	let target_slot = beacon::utils::start_slot_of_epoch::<C>(
+		core::cmp::min(epoch, current_epoch)
+	);
+	executor.advance_slots(state.as_externalities(), target_slot)
+		.map_err(|e| Response::error(500, &format!("Advancing head state failed: {:?}", e)))?;
+	Ok(state)
+}
+
+fn parse_pubkey(value: &str) -> Option<ValidatorId> {
+	let value = if value.starts_with("0x") { &value[2..] } else { value };
+	let bytes = hex::decode(value).ok()?;
+	if bytes.len() != ValidatorId::len_bytes() {
+		return None
+	}
+	Some(ValidatorId::from_slice(&bytes))
+}
+
+/// Resolve a validator id, either a public key or an index, to an index
+/// within the state.
+fn validator_index<C: Config, BLS: BLSConfig>(
+	executor: &Executor<C, BLS>,
+	state: &BeaconState<C>,
+	validator_id: &str,
+) -> Result<Option<ValidatorIndex>, Response> {
+	if validator_id.starts_with("0x") {
+		match parse_pubkey(validator_id) {
+			Some(pubkey) => Ok(executor.pubkeys().index_of(state, &pubkey)),
+			None => Err(Response::error(400, "Invalid validator id")),
+		}
+	} else {
+		match ValidatorIndex::from_str(validator_id) {
+			Ok(index) if (index as usize) < state.validators.len() => Ok(Some(index)),
+			Ok(_) => Ok(None),
+			Err(_) => Err(Response::error(400, "Invalid validator id")),
+		}
+	}
+}
+
+/// Register `/eth/v1/validator/proposer_lookahead` and
+/// `/eth/v1/validator/duties/:pubkey` endpoints, serving the proposer
+/// lookahead and the recorded duties of local validators.
+pub fn register_duties(
+	server: &mut ApiServer,
+	duties: DutyHistory,
+	clock: Arc<dyn SlotClock>,
+) {
+	let lookahead_duties = duties.clone();
+	server.route(Method::Get, "/eth/v1/validator/proposer_lookahead", move |_| {
+		Response::json(200, &lookahead_duties.lookahead())
+	});
+
+	server.route(Method::Get, "/eth/v1/validator/duties/:pubkey", move |request| {
+		let validator = match request.param("pubkey").and_then(parse_pubkey) {
+			Some(validator) => validator,
+			None => return Response::error(400, "Invalid validator public key"),
+		};
+
+		let current_slot = clock.now().unwrap_or_default();
+		Response::json(200, &ValidatorDuties {
+			current_slot,
+			next_proposal: duties.next_proposal(&validator, current_slot),
+			history: duties.history(&validator),
+		})
+	});
+}
+
+/// Register `/eth/v1/validator/duties/proposer/:epoch` and
+/// `/eth/v1/validator/duties/attester/:epoch` endpoints, the standard API's
+/// `getProposerDuties` and `getAttesterDuties`, so that remote validator
+/// clients can plan their work. Attester duties are computed from the
+/// executor committee cache, for the validator indices posted in the body.
+///
+/// Also registers `/eth/v1/beacon/states/head/validators/:validator_id`,
+/// the standard API's `getStateValidator`, so that validator clients can
+/// find their indices and status, and its `/lifecycle` subpath, reporting
+/// the activation queue position and lifecycle epochs. Public keys are
+/// resolved through the executor validator index cache.
+pub fn register_validator_duties<B, C, BLS>(
+	server: &mut ApiServer,
+	backend: B,
+	executor: Executor<C, BLS>,
+	clock: Arc<dyn SlotClock>,
+) where
+	B: ChainQuery + Store<Block=Block<C>> + Clone + Send + Sync + 'static,
+	B::State: StateExternalities<Config=C> + AsExternalities<dyn StateExternalities<Config=C>>,
+	C: Config + Send + Sync + 'static,
+	BLS: BLSConfig + Send + Sync + 'static,
+{
+	let validator_backend = backend.clone();
+	let validator_executor = executor.clone();
+	server.route(Method::Get, "/eth/v1/beacon/states/head/validators/:validator_id", move |request| {
+		let validator_id = request.param("validator_id").unwrap_or("");
+		let state = match validator_backend.state_at(&validator_backend.head()) {
+			Ok(state) => state,
+			Err(e) => return Response::error(503, &format!("Database unavailable: {}", e)),
+		};
+		let state = state.state();
+
+		match validator_index(&validator_executor, state, validator_id) {
+			Ok(Some(index)) => Response::json(200, &Data { data: ValidatorData {
+				index,
+				balance: Gwei::from(state.balances[index as usize]),
+				status: state.validators[index as usize].status(
+					beacon::utils::epoch_of_slot::<C>(state.slot)
+				),
+				validator: state.validators[index as usize].clone(),
+			} }),
+			Ok(None) => Response::error(404, "Validator not found"),
+			Err(response) => response,
+		}
+	});
+
+	let lifecycle_backend = backend.clone();
+	let lifecycle_executor = executor.clone();
+	server.route(
+		Method::Get, "/eth/v1/beacon/states/head/validators/:validator_id/lifecycle",
+		move |request| {
+			let validator_id = request.param("validator_id").unwrap_or("");
+			let mut state = match lifecycle_backend.state_at(&lifecycle_backend.head()) {
+				Ok(state) => state,
+				Err(e) => return Response::error(503, &format!("Database unavailable: {}", e)),
+			};
+
+			let index = match validator_index(&lifecycle_executor, state.state(), validator_id) {
+				Ok(Some(index)) => index,
+				Ok(None) => return Response::error(404, "Validator not found"),
+				Err(response) => return response,
+			};
+			match BeaconExecutive::new(state.state_mut()).validator_lifecycle(index) {
+				Ok(lifecycle) => Response::json(200, &Data { data: lifecycle }),
+				Err(e) => Response::error(500, &format!("{:?}", e)),
+			}
+		},
+	);
+
+	let proposer_backend = backend.clone();
+	let proposer_executor = executor.clone();
+	let proposer_clock = clock.clone();
+	server.route(Method::Get, "/eth/v1/validator/duties/proposer/:epoch", move |request| {
+		let epoch = match parse_epoch(request) {
+			Ok(epoch) => epoch,
+			Err(response) => return response,
+		};
+		let mut state = match epoch_state(
+			&proposer_backend, &proposer_executor, proposer_clock.as_ref(), epoch,
+		) {
+			Ok(state) => state,
+			Err(response) => return response,
+		};
+
+		let executive = BeaconExecutive::new(state.as_externalities().state_mut());
+		let assignments = match executive.proposer_assignments(epoch) {
+			Ok(assignments) => assignments,
+			Err(e) => return Response::error(400, &format!("{:?}", e)),
+		};
+		let data = assignments.into_iter().map(|assignment| ProposerDuty {
+			pubkey: executive.validators[assignment.validator_index as usize].pubkey.clone(),
+			validator_index: assignment.validator_index,
+			slot: assignment.slot,
+		}).collect::<Vec<_>>();
+		Response::json(200, &Data { data })
+	});
+
+	server.route(Method::Post, "/eth/v1/validator/duties/attester/:epoch", move |request| {
+		let epoch = match parse_epoch(request) {
+			Ok(epoch) => epoch,
+			Err(response) => return response,
+		};
+		let indices = match serde_json::from_slice::<ValidatorIndices>(&request.body) {
+			Ok(indices) => indices.0,
+			Err(e) => return Response::error(400, &format!("Invalid request body: {}", e)),
+		};
+		let mut state = match epoch_state(&backend, &executor, clock.as_ref(), epoch) {
+			Ok(state) => state,
+			Err(response) => return response,
+		};
+
+		let externalities = state.as_externalities();
+		let committees = match executor.committees().committees(externalities.state_mut(), epoch) {
+			Ok(committees) => committees,
+			Err(e) => return Response::error(400, &format!("{:?}", e)),
+		};
+		let validators = &externalities.state().validators;
+		let data = indices.into_iter()
+			.filter(|index| (*index as usize) < validators.len())
+			.filter_map(|index| {
+				let assignment = committees.assignment::<C>(index)?;
+				let validator_committee_index = assignment.validators.iter()
+					.position(|v| *v == index)? as u64;
+				Some(AttesterDuty {
+					pubkey: validators[index as usize].pubkey.clone(),
+					validator_index: index,
+					committee_index: assignment.index,
+					committee_length: assignment.validators.len() as u64,
+					committees_at_slot: committees.committees_per_slot(),
+					validator_committee_index,
+					slot: assignment.slot,
+				})
+			})
+			.collect::<Vec<_>>();
+		Response::json(200, &Data { data })
+	});
+}
@@ -0,0 +1,231 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use beacon::Config;
+use beacon::primitives::{H256, Epoch, Slot};
+use blockchain::backend::{Store, ChainQuery};
+use lmd_ghost::events::ChainEvent;
+use serde::Serialize;
+use std::io::Read;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+use crate::Block;
+use crate::service::Exit;
+use super::{ApiServer, Method, Response};
+
+/// Topics of the event stream.
+pub const EVENT_TOPICS: &[&str] = &[
+	"head", "block", "attestation", "finalized_checkpoint", "chain_reorg",
+];
+
+/// Interval of keep-alive comments on an idle event stream, which also
+/// detects disconnected clients.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Event sent on the event stream, with its data encoded as JSON.
+#[derive(Clone, Debug)]
+pub struct ApiEvent {
+	/// Topic of the event.
+	pub topic: &'static str,
+	/// JSON data of the event.
+	pub data: String,
+}
+
+#[derive(Serialize)]
+struct HeadEvent {
+	#[serde(with = "beacon::utils::uint")]
+	slot: Slot,
+	block: H256,
+	state: H256,
+	epoch_transition: bool,
+}
+
+#[derive(Serialize)]
+struct BlockEvent {
+	#[serde(with = "beacon::utils::uint")]
+	slot: Slot,
+	block: H256,
+}
+
+#[derive(Serialize)]
+struct FinalizedCheckpointEvent {
+	block: H256,
+	state: H256,
+	#[serde(with = "beacon::utils::uint")]
+	epoch: Epoch,
+}
+
+#[derive(Serialize)]
+struct ChainReorgEvent {
+	#[serde(with = "beacon::utils::uint")]
+	slot: Slot,
+	#[serde(with = "beacon::utils::uint")]
+	depth: u64,
+	old_head_block: H256,
+	new_head_block: H256,
+	old_head_state: H256,
+	new_head_state: H256,
+	#[serde(with = "beacon::utils::uint")]
+	epoch: Epoch,
+}
+
+/// Subscriptions to the event stream, shared between the event sources and
+/// the API.
+#[derive(Clone, Default)]
+pub struct ApiEvents(Arc<Mutex<Vec<Sender<ApiEvent>>>>);
+
+impl ApiEvents {
+	/// Create new subscriptions.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Subscribe to events. The subscription is dropped along with the
+	/// receiver.
+	pub fn subscribe(&self) -> Receiver<ApiEvent> {
+		let (sender, receiver) = mpsc::channel();
+		self.0.lock().expect("Lock is poisoned").push(sender);
+		receiver
+	}
+
+	/// Send an event to all subscribers.
+	pub fn emit<T: Serialize>(&self, topic: &'static str, data: &T) {
+		let mut senders = self.0.lock().expect("Lock is poisoned");
+		if senders.is_empty() {
+			return
+		}
+
+		let event = match serde_json::to_string(data) {
+			Ok(data) => ApiEvent { topic, data },
+			Err(_) => return,
+		};
+		senders.retain(|sender| sender.send(event.clone()).is_ok());
+	}
+
+	/// Convert chain events of the importer to events, until `exit` is
+	/// signaled.
+	pub fn forward_chain_events<B, C>(
+		&self,
+		backend: &B,
+		events: Receiver<ChainEvent<H256>>,
+		exit: &Exit,
+	) where
+		B: ChainQuery + Store<Block=Block<C>>,
+		C: Config,
+	{
+		while !exit.is_signaled() {
+			let event = match events.recv_timeout(Duration::from_millis(500)) {
+				Ok(event) => event,
+				Err(RecvTimeoutError::Timeout) => continue,
+				Err(RecvTimeoutError::Disconnected) => return,
+			};
+
+			let block = |id: &H256| backend.block_at(id).ok();
+			match event {
+				ChainEvent::Imported { block: id } => if let Some(block) = block(&id) {
+					self.emit("block", &BlockEvent { slot: block.0.slot, block: id });
+				},
+				ChainEvent::NewHead { head } => if let Some(block) = block(&head) {
+					self.emit("head", &HeadEvent {
+						slot: block.0.slot,
+						block: head,
+						state: block.0.state_root,
+						epoch_transition: block.0.slot % C::slots_per_epoch() == 0,
+					});
+				},
+				ChainEvent::Reorg { old_head, new_head, depth } => {
+					if let (Some(old_block), Some(new_block)) = (block(&old_head), block(&new_head)) {
+						self.emit("chain_reorg", &ChainReorgEvent {
+							slot: new_block.0.slot,
+							depth: depth as u64,
+							old_head_block: old_head,
+							new_head_block: new_head,
+							old_head_state: old_block.0.state_root,
+							new_head_state: new_block.0.state_root,
+							epoch: beacon::utils::epoch_of_slot::<C>(new_block.0.slot),
+						});
+						self.emit("head", &HeadEvent {
+							slot: new_block.0.slot,
+							block: new_head,
+							state: new_block.0.state_root,
+							epoch_transition: new_block.0.slot % C::slots_per_epoch() == 0,
+						});
+					}
+				},
+				ChainEvent::Finalized { epoch, root } => if let Some(block) = block(&root) {
+					self.emit("finalized_checkpoint", &FinalizedCheckpointEvent {
+						block: root,
+						state: block.0.state_root,
+						epoch: Epoch::new(epoch),
+					});
+				},
+			}
+		}
+	}
+}
+
+/// Reader of subscribed events, formatted as server-sent events.
+struct EventReader {
+	receiver: Receiver<ApiEvent>,
+	topics: Vec<String>,
+	buffer: Vec<u8>,
+	position: usize,
+}
+
+impl Read for EventReader {
+	fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+		while self.position >= self.buffer.len() {
+			self.buffer = match self.receiver.recv_timeout(KEEP_ALIVE_INTERVAL) {
+				Ok(event) => {
+					if !self.topics.iter().any(|topic| topic == event.topic) {
+						continue
+					}
+					format!("event: {}\ndata: {}\n\n", event.topic, event.data).into_bytes()
+				},
+				Err(RecvTimeoutError::Timeout) => b":\n\n".to_vec(),
+				Err(RecvTimeoutError::Disconnected) => return Ok(0),
+			};
+			self.position = 0;
+		}
+
+		let len = core::cmp::min(buf.len(), self.buffer.len() - self.position);
+		buf[..len].copy_from_slice(&self.buffer[self.position..(self.position + len)]);
+		self.position += len;
+		Ok(len)
+	}
+}
+
+/// Register the standard `/eth/v1/events` endpoint, streaming events of the
+/// comma separated `topics` query parameter as server-sent events.
+pub fn register_events(server: &mut ApiServer, events: ApiEvents) {
+	server.route(Method::Get, "/eth/v1/events", move |request| {
+		let topics = match request.query("topics") {
+			Some(topics) => topics.split(',').map(|topic| topic.to_string()).collect::<Vec<_>>(),
+			None => return Response::error(400, "Missing query parameter topics"),
+		};
+		if let Some(topic) = topics.iter().find(|topic| !EVENT_TOPICS.contains(&topic.as_str())) {
+			return Response::error(400, &format!("Unknown topic {}", topic))
+		}
+
+		Response::event_stream(Box::new(EventReader {
+			receiver: events.subscribe(),
+			topics,
+			buffer: Vec::new(),
+			position: 0,
+		}))
+	});
+}
@@ -0,0 +1,76 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use beacon::Config;
+use beacon::primitives::{H256, Slot};
+use blockchain::backend::{Store, ChainQuery};
+use lmd_ghost::archive::{AncestorQuery, ForkChoiceView};
+use serde::Serialize;
+use crate::{Block, StateExternalities};
+use super::{ApiServer, Method, Response};
+
+#[derive(Serialize)]
+struct ForkChoiceNode {
+	slot: Slot,
+	root: H256,
+	parent_root: Option<H256>,
+	weight: usize,
+	justified: bool,
+	finalized: bool,
+	head: bool,
+}
+
+/// Register the `/eth/v1/debug/fork_choice` endpoint, dumping the fork
+/// choice block tree from the justified block.
+pub fn register_fork_choice<B, C>(
+	server: &mut ApiServer,
+	backend: B,
+	fork_choice: ForkChoiceView<B>,
+) where
+	B: ChainQuery + AncestorQuery + Store<Block=Block<C>> + Send + Sync + 'static,
+	B::State: StateExternalities<Config=C>,
+	C: Config,
+{
+	server.route(Method::Get, "/eth/v1/debug/fork_choice", move |_| {
+		let finalized_root = match backend.state_at(&backend.head()) {
+			Ok(state) => state.state().finalized_checkpoint.root,
+			Err(e) => return Response::error(503, &format!("Database unavailable: {}", e)),
+		};
+		let tree = match fork_choice.tree() {
+			Ok(tree) => tree,
+			Err(e) => return Response::error(503, &format!("Database unavailable: {}", e)),
+		};
+
+		let mut nodes = Vec::new();
+		for node in tree {
+			let slot = match backend.block_at(&node.id) {
+				Ok(block) => block.0.slot,
+				Err(e) => return Response::error(503, &format!("Database unavailable: {}", e)),
+			};
+			nodes.push(ForkChoiceNode {
+				slot,
+				root: node.id,
+				parent_root: node.parent,
+				weight: node.weight,
+				justified: node.justified,
+				finalized: node.id == finalized_root,
+				head: node.head,
+			});
+		}
+
+		Response::json(200, &nodes)
+	});
+}
@@ -0,0 +1,97 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use beacon::Config;
+use beacon::primitives::Slot;
+use blockchain::backend::{Store, ChainQuery};
+use shasper_network::NetworkStatus;
+use std::sync::Arc;
+use serde::Serialize;
+use crate::Block;
+use crate::clock::SlotClock;
+use super::{ApiServer, Method, Response};
+
+/// Thresholds for the readiness check.
+#[derive(Clone, Debug)]
+pub struct HealthConfig {
+	/// Maximum number of slots the head can lag behind the wall clock.
+	pub max_head_slot_distance: u64,
+	/// Minimum number of connected peers.
+	pub min_peers: usize,
+}
+
+impl Default for HealthConfig {
+	fn default() -> Self {
+		Self {
+			max_head_slot_distance: 8,
+			min_peers: 1,
+		}
+	}
+}
+
+#[derive(Serialize)]
+struct Health {
+	status: &'static str,
+}
+
+#[derive(Serialize)]
+struct Readiness {
+	ready: bool,
+	synced: bool,
+	head_slot: Slot,
+	current_slot: Slot,
+	peers: usize,
+}
+
+/// Register `/health` and `/ready` endpoints.
+///
+/// `/health` succeeds as long as the process is up and the database can be
+/// read. `/ready` additionally requires the head to be within the configured
+/// distance of the wall clock slot, and enough connected peers.
+pub fn register_health<B, C>(
+	server: &mut ApiServer,
+	backend: B,
+	network: NetworkStatus,
+	clock: Arc<dyn SlotClock>,
+	config: HealthConfig,
+) where
+	B: ChainQuery + Store<Block=Block<C>> + Clone + Send + Sync + 'static,
+	C: Config,
+{
+	let health_backend = backend.clone();
+	server.route(Method::Get, "/health", move |_| {
+		match health_backend.block_at(&health_backend.head()) {
+			Ok(_) => Response::json(200, &Health { status: "ok" }),
+			Err(e) => Response::error(503, &format!("Database unavailable: {}", e)),
+		}
+	});
+
+	server.route(Method::Get, "/ready", move |_| {
+		let head_slot = match backend.block_at(&backend.head()) {
+			Ok(block) => block.0.slot,
+			Err(e) => return Response::error(503, &format!("Database unavailable: {}", e)),
+		};
+
+		let current_slot = clock.now().unwrap_or_default();
+		let synced = current_slot <= head_slot + config.max_head_slot_distance;
+		let peers = network.peer_count();
+		let ready = synced && peers >= config.min_peers;
+
+		Response::json(if ready { 200 } else { 503 }, &Readiness {
+			ready, synced, head_slot, current_slot, peers,
+		})
+	});
+}
@@ -0,0 +1,114 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use beacon::{BeaconExecutive, Config};
+use beacon::primitives::{Epoch, Gwei, ValidatorIndex};
+use blockchain::backend::{Store, ChainQuery};
+use serde::Serialize;
+use core::str::FromStr;
+use crate::{Block, StateExternalities};
+use crate::inactivity::InactivityTracker;
+use super::{ApiServer, Method, Response};
+
+#[derive(Serialize)]
+struct EpochLeak {
+	epoch: Epoch,
+	total_penalty: Gwei,
+}
+
+#[derive(Serialize)]
+struct ValidatorLeak {
+	index: ValidatorIndex,
+	balance: Gwei,
+	accumulated_penalty: Gwei,
+	projected_balance: Gwei,
+}
+
+#[derive(Serialize)]
+struct InactivityLeak {
+	current_epoch: Epoch,
+	finality_delay: Epoch,
+	leak_start: Option<Epoch>,
+	recovery_epochs: u64,
+	epochs: Vec<EpochLeak>,
+	validators: Vec<ValidatorLeak>,
+}
+
+/// Register the `/eth/v1/debug/inactivity_leak` endpoint, reporting the
+/// inactivity penalties of the ongoing leak, per epoch and per validator.
+///
+/// The projected balance of each validator is its balance once finality
+/// resumes after `recovery_epochs` more epochs, if it keeps missing the
+/// target. Validators can be narrowed to a single `validator` index.
+pub fn register_inactivity<B, C>(
+	server: &mut ApiServer,
+	backend: B,
+	tracker: InactivityTracker,
+) where
+	B: ChainQuery + Store<Block=Block<C>> + Send + Sync + 'static,
+	B::State: StateExternalities<Config=C>,
+	C: Config,
+{
+	server.route(Method::Get, "/eth/v1/debug/inactivity_leak", move |request| {
+		let recovery_epochs = match request.query("recovery_epochs").map(u64::from_str) {
+			Some(Ok(epochs)) => epochs,
+			Some(Err(_)) => return Response::error(400, "Invalid recovery epochs"),
+			None => 0,
+		};
+		let validator = match request.query("validator").map(ValidatorIndex::from_str) {
+			Some(Ok(index)) => Some(index),
+			Some(Err(_)) => return Response::error(400, "Invalid validator index"),
+			None => None,
+		};
+
+		let mut state = match backend.state_at(&backend.head()) {
+			Ok(state) => state,
+			Err(e) => return Response::error(503, &format!("Database unavailable: {}", e)),
+		};
+		let executive = BeaconExecutive::new(state.state_mut());
+		let projected = match executive.project_inactivity_penalties(recovery_epochs) {
+			Ok(projected) => projected,
+			Err(e) => return Response::error(500, &format!("Projection failed: {:?}", e)),
+		};
+		let report = tracker.report();
+
+		let validators = executive.balances.iter()
+			.zip(projected)
+			.enumerate()
+			.map(|(index, (balance, projected))| ValidatorLeak {
+				index: index as ValidatorIndex,
+				balance: Gwei::from(*balance),
+				accumulated_penalty: report.accumulated.get(index).cloned().unwrap_or_default(),
+				projected_balance: Gwei::from(*balance).saturating_sub(projected),
+			})
+			.filter(|leak| validator.map(|index| index == leak.index).unwrap_or(true))
+			.collect::<Vec<_>>();
+		if validator.is_some() && validators.is_empty() {
+			return Response::error(404, "Validator not found")
+		}
+
+		Response::json(200, &InactivityLeak {
+			current_epoch: executive.current_epoch(),
+			finality_delay: executive.previous_epoch() - executive.finalized_checkpoint.epoch,
+			leak_start: report.leak_start,
+			recovery_epochs,
+			epochs: report.totals.into_iter()
+				.map(|(epoch, total_penalty)| EpochLeak { epoch, total_penalty })
+				.collect(),
+			validators,
+		})
+	});
+}
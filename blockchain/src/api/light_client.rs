@@ -0,0 +1,87 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use beacon::{BeaconExecutive, Config, LightClientOptimisticUpdate, block_header};
+use beacon::primitives::H256;
+use blockchain::AsExternalities;
+use blockchain::backend::{Store, ChainQuery};
+use serde::Serialize;
+use crate::{Block, StateExternalities};
+use super::{ApiServer, Method, Response};
+
+#[derive(Serialize)]
+struct Data<T> {
+	data: T,
+}
+
+/// Register the light client update endpoints.
+///
+/// `/eth/v1/beacon/light_client/finality_update` returns the head block
+/// header together with the header of its finalized checkpoint block and
+/// the finality branch against the head state root.
+/// `/eth/v1/beacon/light_client/optimistic_update` returns the head block
+/// header only. Without a sync committee, consumers need to trust the
+/// attested header through other means.
+pub fn register_light_client<B, C>(
+	server: &mut ApiServer,
+	backend: B,
+) where
+	B: ChainQuery + Store<Block=Block<C>> + Clone + Send + Sync + 'static,
+	B::State: StateExternalities<Config=C> + AsExternalities<dyn StateExternalities<Config=C>>,
+	C: Config,
+{
+	let finality_backend = backend.clone();
+	server.route(Method::Get, "/eth/v1/beacon/light_client/finality_update", move |_| {
+		let backend = &finality_backend;
+		let unavailable = |e: B::Error| Response::error(503, &format!("Database unavailable: {}", e));
+		let head = backend.head();
+		let head_block = match backend.block_at(&head) {
+			Ok(block) => block,
+			Err(e) => return unavailable(e),
+		};
+		let mut state = match backend.state_at(&head) {
+			Ok(state) => state,
+			Err(e) => return unavailable(e),
+		};
+		let externalities = state.as_externalities();
+		let executive = BeaconExecutive::new(externalities.state_mut());
+
+		let finalized_root = executive.finalized_checkpoint.root;
+		if finalized_root == H256::default() {
+			return Response::error(404, "No finalized block")
+		}
+		let finalized_block = match backend.block_at(&finalized_root) {
+			Ok(block) => block,
+			Err(e) => return unavailable(e),
+		};
+
+		match executive.light_client_update(
+			block_header(&head_block.0), block_header(&finalized_block.0),
+		) {
+			Ok(data) => Response::json(200, &Data { data }),
+			Err(e) => Response::error(500, &format!("{:?}", e)),
+		}
+	});
+
+	server.route(Method::Get, "/eth/v1/beacon/light_client/optimistic_update", move |_| {
+		match backend.block_at(&backend.head()) {
+			Ok(block) => Response::json(200, &Data {
+				data: LightClientOptimisticUpdate { attested_header: block_header(&block.0) },
+			}),
+			Err(e) => Response::error(503, &format!("Database unavailable: {}", e)),
+		}
+	});
+}
@@ -0,0 +1,205 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use beacon::{Config, BeaconExecutive};
+use blockchain::backend::{Store, ChainQuery};
+use std::fmt::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use crate::{Block, PubkeyCache, StateExternalities};
+use crate::settings::LiveSettings;
+use super::{ApiServer, Method, Response};
+
+/// Gauge shared between the component updating it and the metrics endpoint.
+#[derive(Clone, Default, Debug)]
+pub struct Gauge(Arc<AtomicUsize>);
+
+impl Gauge {
+	/// Create a new gauge starting at zero.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Set the gauge value.
+	pub fn set(&self, value: usize) {
+		self.0.store(value, Ordering::Relaxed);
+	}
+
+	/// Increase the gauge value by one.
+	pub fn inc(&self) {
+		self.0.fetch_add(1, Ordering::Relaxed);
+	}
+
+	/// Increase the gauge value by `value`.
+	pub fn add(&self, value: usize) {
+		self.0.fetch_add(value, Ordering::Relaxed);
+	}
+
+	/// Get the gauge value.
+	pub fn get(&self) -> usize {
+		self.0.load(Ordering::Relaxed)
+	}
+}
+
+/// Monotonic counter shared between the component increasing it and the
+/// metrics endpoint.
+#[derive(Clone, Default, Debug)]
+pub struct Counter(Arc<AtomicUsize>);
+
+impl Counter {
+	/// Create a new counter starting at zero.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Increase the counter by one.
+	pub fn inc(&self) {
+		self.0.fetch_add(1, Ordering::Relaxed);
+	}
+
+	/// Increase the counter by `value`.
+	pub fn add(&self, value: usize) {
+		self.0.fetch_add(value, Ordering::Relaxed);
+	}
+
+	/// Get the counter value.
+	pub fn get(&self) -> usize {
+		self.0.load(Ordering::Relaxed)
+	}
+}
+
+/// Metrics not derivable from the chain database.
+#[derive(Clone, Default, Debug)]
+pub struct NodeMetrics {
+	/// Number of attestations waiting in the attestation pool.
+	pub attestation_pool_size: Gauge,
+	/// Number of attestations dropped from the attestation pool by pruning.
+	pub attestation_pool_pruned: Counter,
+	/// Number of operations of all kinds waiting in the operation pool.
+	pub operation_pool_size: Gauge,
+	/// Whether the finality safety switch is tripped.
+	pub safety_switch_tripped: Gauge,
+	/// Number of blocks produced without operations, after gathering them failed.
+	pub degraded_blocks: Gauge,
+}
+
+fn gauge<V: core::fmt::Display>(out: &mut String, name: &str, help: &str, value: V) {
+	let _ = writeln!(out, "# HELP {} {}", name, help);
+	let _ = writeln!(out, "# TYPE {} gauge", name);
+	let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: usize) {
+	let _ = writeln!(out, "# HELP {}_total {}", name, help);
+	let _ = writeln!(out, "# TYPE {}_total counter", name);
+	let _ = writeln!(out, "{}_total {}", name, value);
+}
+
+/// Register the Prometheus `/metrics` endpoint.
+///
+/// Chain metrics are computed from the current head on every scrape, and
+/// balances are exported for each of the currently tracked validators,
+/// found through the validator index cache.
+pub fn register_metrics<B, C>(
+	server: &mut ApiServer,
+	backend: B,
+	pubkeys: PubkeyCache,
+	node: NodeMetrics,
+	settings: LiveSettings,
+) where
+	B: ChainQuery + Store<Block=Block<C>> + Send + Sync + 'static,
+	B::State: StateExternalities<Config=C>,
+	C: Config,
+{
+	server.route(Method::Get, "/metrics", move |_| {
+		let head = backend.head();
+		let (head_block, mut head_state) = match (
+			backend.block_at(&head), backend.state_at(&head)
+		) {
+			(Ok(block), Ok(state)) => (block, state),
+			(Err(e), _) | (_, Err(e)) =>
+				return Response::error(503, &format!("Database unavailable: {}", e)),
+		};
+		let executive = BeaconExecutive::new(head_state.state_mut());
+
+		let current_epoch = executive.current_epoch();
+		let previous_epoch = executive.previous_epoch();
+		let justified_epoch = executive.current_justified_checkpoint.epoch;
+		let finalized_epoch = executive.finalized_checkpoint.epoch;
+		let active_validators = executive.active_validator_indices(current_epoch).len();
+		let total_active_balance = executive.total_active_balance();
+		let target_balance = executive.matching_target_attestations(previous_epoch)
+			.and_then(|attestations| executive.attesting_balance(&attestations))
+			.unwrap_or_default();
+
+		let mut out = String::new();
+		gauge(&mut out, "beacon_head_slot",
+			  "Slot of the current head block.", head_block.0.slot);
+		gauge(&mut out, "beacon_current_epoch",
+			  "Epoch of the current head state.", current_epoch);
+		gauge(&mut out, "beacon_current_justified_epoch",
+			  "Current justified epoch.", justified_epoch);
+		gauge(&mut out, "beacon_finalized_epoch",
+			  "Finalized epoch.", finalized_epoch);
+		gauge(&mut out, "beacon_justification_distance",
+			  "Number of epochs since the current justified epoch.",
+			  current_epoch.saturating_sub(justified_epoch));
+		gauge(&mut out, "beacon_active_validators",
+			  "Number of active validators in the current epoch.", active_validators);
+		gauge(&mut out, "beacon_total_active_balance_gwei",
+			  "Total effective balance of active validators.", total_active_balance);
+		gauge(&mut out, "beacon_previous_epoch_target_attesting_balance_gwei",
+			  "Unslashed balance attesting to the previous epoch target.", target_balance);
+		gauge(&mut out, "beacon_attestation_pool_size",
+			  "Number of attestations waiting in the attestation pool.",
+			  node.attestation_pool_size.get());
+		counter(&mut out, "beacon_attestation_pool_pruned",
+			  "Number of attestations dropped from the attestation pool by pruning.",
+			  node.attestation_pool_pruned.get());
+		gauge(&mut out, "beacon_operation_pool_size",
+			  "Number of operations of all kinds waiting in the operation pool.",
+			  node.operation_pool_size.get());
+		gauge(&mut out, "beacon_safety_switch_tripped",
+			  "Whether block production is stopped due to finality divergence.",
+			  node.safety_switch_tripped.get());
+		gauge(&mut out, "beacon_degraded_blocks",
+			  "Number of blocks produced without operations after gathering them failed.",
+			  node.degraded_blocks.get());
+
+		let tracked_validators = settings.tracked_validators();
+		if !tracked_validators.is_empty() {
+			let _ = writeln!(out, "# HELP beacon_validator_balance_gwei Balance of a tracked validator.");
+			let _ = writeln!(out, "# TYPE beacon_validator_balance_gwei gauge");
+			let _ = writeln!(out, "# HELP beacon_validator_effective_balance_gwei Effective balance of a tracked validator.");
+			let _ = writeln!(out, "# TYPE beacon_validator_effective_balance_gwei gauge");
+			for pubkey in &tracked_validators {
+				if let Some(index) = pubkeys.index_of::<C>(&executive, pubkey) {
+					let index = index as usize;
+					let _ = writeln!(
+						out, "beacon_validator_balance_gwei{{pubkey=\"{:#x}\",index=\"{}\"}} {}",
+						pubkey, index, executive.balances[index],
+					);
+					let _ = writeln!(
+						out, "beacon_validator_effective_balance_gwei{{pubkey=\"{:#x}\",index=\"{}\"}} {}",
+						pubkey, index, executive.validators[index].effective_balance,
+					);
+				}
+			}
+		}
+
+		Response::raw(200, "text/plain; version=0.0.4", out.into_bytes())
+	});
+}
@@ -0,0 +1,350 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Minimal HTTP API server for node introspection and orchestration.
+
+mod attestations;
+mod blocks;
+mod consensus;
+mod duties;
+mod events;
+mod fork_choice;
+mod health;
+mod inactivity;
+mod light_client;
+mod metrics;
+mod peers;
+mod pool;
+mod randao;
+mod rewards;
+mod settings;
+
+pub use self::attestations::register_attestations;
+pub use self::blocks::register_blocks;
+pub use self::consensus::{FinalityCheckpoints, register_consensus, register_fork_schedule};
+pub use self::duties::{register_duties, register_validator_duties};
+pub use self::events::{ApiEvent, ApiEvents, EVENT_TOPICS, register_events};
+pub use self::fork_choice::register_fork_choice;
+pub use self::health::{HealthConfig, register_health};
+pub use self::inactivity::register_inactivity;
+pub use self::light_client::register_light_client;
+pub use self::metrics::{Counter, Gauge, NodeMetrics, register_metrics};
+pub use self::peers::register_peers;
+pub use self::pool::register_pool;
+pub use self::randao::register_randao;
+pub use self::rewards::register_rewards;
+pub use self::settings::register_settings;
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::io::{Read, Write};
+use std::sync::{mpsc::{self, TrySendError}, Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+use serde::Serialize;
+use log::*;
+use crate::service::ServiceContext;
+
+/// Number of threads handling API requests.
+pub const API_WORKERS: usize = 4;
+/// Maximum number of requests waiting for a worker.
+pub const MAX_QUEUED_REQUESTS: usize = 64;
+/// Maximum number of concurrent event streams.
+pub const MAX_EVENT_STREAMS: usize = 16;
+/// Maximum size of a request body, in bytes.
+pub const MAX_BODY_SIZE: usize = 4 * 1024 * 1024;
+
+/// HTTP method.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Method {
+	/// GET method.
+	Get,
+	/// POST method.
+	Post,
+}
+
+/// API request.
+#[derive(Clone, Debug, Default)]
+pub struct Request {
+	/// Path parameters, captured by `:name` route segments.
+	pub params: HashMap<String, String>,
+	/// Query parameters.
+	pub query: HashMap<String, String>,
+	/// Request body.
+	pub body: Vec<u8>,
+}
+
+impl Request {
+	/// Get a path parameter.
+	pub fn param(&self, name: &str) -> Option<&str> {
+		self.params.get(name).map(|v| v.as_str())
+	}
+
+	/// Get a query parameter.
+	pub fn query(&self, name: &str) -> Option<&str> {
+		self.query.get(name).map(|v| v.as_str())
+	}
+}
+
+/// API response.
+pub struct Response {
+	/// HTTP status code.
+	pub status: u16,
+	/// Content type header.
+	pub content_type: &'static str,
+	/// Response body.
+	pub body: Box<dyn Read + Send>,
+	/// Whether the body is a stream, written out as soon as it is read.
+	pub stream: bool,
+}
+
+impl Response {
+	/// Create a JSON response.
+	pub fn json<T: Serialize>(status: u16, value: &T) -> Self {
+		match serde_json::to_vec(value) {
+			Ok(body) => Self::raw(status, "application/json", body),
+			Err(e) => Self::error(500, &format!("{}", e)),
+		}
+	}
+
+	/// Create a plain text response.
+	pub fn text(status: u16, text: String) -> Self {
+		Self::raw(status, "text/plain; charset=utf-8", text.into_bytes())
+	}
+
+	/// Create a response from raw bytes.
+	pub fn raw(status: u16, content_type: &'static str, body: Vec<u8>) -> Self {
+		Self { status, content_type, body: Box::new(std::io::Cursor::new(body)), stream: false }
+	}
+
+	/// Create a server-sent events response, streaming events read from
+	/// `body` until it ends or the client disconnects.
+	pub fn event_stream(body: Box<dyn Read + Send>) -> Self {
+		Self { status: 200, content_type: "text/event-stream", body, stream: true }
+	}
+
+	/// Create an error response.
+	pub fn error(status: u16, message: &str) -> Self {
+		#[derive(Serialize)]
+		struct ErrorMessage<'a> {
+			code: u16,
+			message: &'a str,
+		}
+
+		match serde_json::to_vec(&ErrorMessage { code: status, message }) {
+			Ok(body) => Self::raw(status, "application/json", body),
+			Err(_) => Self::text(status, message.to_string()),
+		}
+	}
+}
+
+type Handler = Box<dyn Fn(&Request) -> Response + Send + Sync>;
+
+struct Route {
+	method: Method,
+	segments: Vec<String>,
+	handler: Handler,
+}
+
+impl Route {
+	fn matches(&self, method: Method, path: &[&str]) -> Option<HashMap<String, String>> {
+		if self.method != method || self.segments.len() != path.len() {
+			return None
+		}
+
+		let mut params = HashMap::new();
+		for (segment, part) in self.segments.iter().zip(path.iter()) {
+			if segment.starts_with(':') {
+				params.insert(segment[1..].to_string(), part.to_string());
+			} else if segment != part {
+				return None
+			}
+		}
+		Some(params)
+	}
+}
+
+fn split_path(path: &str) -> Vec<&str> {
+	path.split('/').filter(|s| !s.is_empty()).collect()
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+	query.split('&')
+		.filter(|s| !s.is_empty())
+		.map(|pair| match pair.find('=') {
+			Some(index) => (pair[..index].to_string(), pair[(index + 1)..].to_string()),
+			None => (pair.to_string(), String::new()),
+		})
+		.collect()
+}
+
+/// Write a streaming response without a content length, flushing each read
+/// of the body to the client. The connection is closed at the end.
+fn write_stream(mut writer: Box<dyn Write + Send>, mut response: Response) -> std::io::Result<()> {
+	write!(
+		writer,
+		"HTTP/1.1 {} OK\r\nContent-Type: {}\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+		response.status, response.content_type,
+	)?;
+	writer.flush()?;
+
+	let mut buffer = [0u8; 4096];
+	loop {
+		let read = response.body.read(&mut buffer)?;
+		if read == 0 {
+			return Ok(())
+		}
+		writer.write_all(&buffer[..read])?;
+		writer.flush()?;
+	}
+}
+
+/// HTTP API server, dispatching requests to registered routes.
+#[derive(Default)]
+pub struct ApiServer {
+	routes: Vec<Route>,
+}
+
+impl ApiServer {
+	/// Create a new server without any routes.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Register a route. Path segments starting with `:` capture a path
+	/// parameter.
+	pub fn route<F>(&mut self, method: Method, path: &str, handler: F) where
+		F: Fn(&Request) -> Response + Send + Sync + 'static,
+	{
+		self.routes.push(Route {
+			method,
+			segments: split_path(path).into_iter().map(|s| s.to_string()).collect(),
+			handler: Box::new(handler),
+		});
+	}
+
+	fn dispatch(&self, method: Method, url: &str, body: Vec<u8>) -> Response {
+		let (path, query) = match url.find('?') {
+			Some(index) => (&url[..index], parse_query(&url[(index + 1)..])),
+			None => (url, HashMap::new()),
+		};
+		let path = split_path(path);
+
+		for route in &self.routes {
+			if let Some(params) = route.matches(method, &path) {
+				return (route.handler)(&Request { params, query, body })
+			}
+		}
+
+		Response::error(404, "Not found")
+	}
+
+	/// Serve on the given address until the service is asked to exit.
+	/// Requests are handled by a pool of `API_WORKERS` threads, and answered
+	/// with 503 when `MAX_QUEUED_REQUESTS` are already waiting. Event
+	/// streams get their own thread, up to `MAX_EVENT_STREAMS`.
+	pub fn serve(self, address: SocketAddr, context: &mut ServiceContext) -> Result<(), String> {
+		let server = tiny_http::Server::http(address).map_err(|e| format!("{}", e))?;
+		let api = Arc::new(self);
+		let streams = Arc::new(AtomicUsize::new(0));
+		let (sender, receiver) = mpsc::sync_channel::<tiny_http::Request>(MAX_QUEUED_REQUESTS);
+		let receiver = Arc::new(Mutex::new(receiver));
+		for _ in 0..API_WORKERS {
+			let api = api.clone();
+			let streams = streams.clone();
+			let receiver = receiver.clone();
+			thread::spawn(move || loop {
+				let request = match receiver.lock().expect("Lock is poisoned").recv() {
+					Ok(request) => request,
+					Err(_) => return,
+				};
+				api.handle(request, &streams);
+			});
+		}
+		info!("API server listening on {}", address);
+		context.ready();
+
+		while !context.is_exiting() {
+			let request = match server.recv_timeout(Duration::from_millis(500)) {
+				Ok(Some(request)) => request,
+				Ok(None) => continue,
+				Err(e) => return Err(format!("{}", e)),
+			};
+
+			if let Err(TrySendError::Full(request)) = sender.try_send(request) {
+				let _ = request.respond(tiny_http::Response::empty(503));
+			}
+		}
+
+		Ok(())
+	}
+
+	fn handle(&self, mut request: tiny_http::Request, streams: &Arc<AtomicUsize>) {
+		let method = match request.method() {
+			tiny_http::Method::Get => Method::Get,
+			tiny_http::Method::Post => Method::Post,
+			_ => {
+				let _ = request.respond(tiny_http::Response::empty(405));
+				return
+			},
+		};
+		if request.body_length().map(|length| length > MAX_BODY_SIZE).unwrap_or(false) {
+			let _ = request.respond(tiny_http::Response::empty(413));
+			return
+		}
+		let mut body = Vec::new();
+		if let Err(e) = request.as_reader().take(MAX_BODY_SIZE as u64 + 1).read_to_end(&mut body) {
+			warn!("Failed to read API request body: {}", e);
+			return
+		}
+		if body.len() > MAX_BODY_SIZE {
+			let _ = request.respond(tiny_http::Response::empty(413));
+			return
+		}
+
+		let response = self.dispatch(method, &request.url().to_string(), body);
+		if response.stream {
+			if streams.fetch_add(1, Ordering::SeqCst) >= MAX_EVENT_STREAMS {
+				streams.fetch_sub(1, Ordering::SeqCst);
+				let _ = request.respond(tiny_http::Response::empty(503));
+				return
+			}
+			let streams = streams.clone();
+			thread::spawn(move || {
+				if let Err(e) = write_stream(request.into_writer(), response) {
+					trace!("API stream closed: {}", e);
+				}
+				streams.fetch_sub(1, Ordering::SeqCst);
+			});
+			return
+		}
+
+		let header = tiny_http::Header::from_bytes(
+			&b"Content-Type"[..], response.content_type.as_bytes()
+		).expect("Content type is a valid header; qed");
+		let response = tiny_http::Response::new(
+			tiny_http::StatusCode(response.status),
+			vec![header],
+			response.body,
+			None,
+			None,
+		);
+		if let Err(e) = request.respond(response) {
+			trace!("Failed to send API response: {}", e);
+		}
+	}
+}
@@ -0,0 +1,41 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use shasper_network::PeerManager;
+use serde::Serialize;
+use super::{ApiServer, Method, Response};
+
+#[derive(Serialize)]
+struct PeerScoreInfo {
+	peer_id: String,
+	score: f64,
+	banned: bool,
+}
+
+/// Register the `/eth/v1/node/peer_scores` endpoint, listing scores of peers
+/// known to the peer manager.
+pub fn register_peers(server: &mut ApiServer, peers: PeerManager) {
+	server.route(Method::Get, "/eth/v1/node/peer_scores", move |_| {
+		let mut scores = peers.scores().into_iter().map(|score| PeerScoreInfo {
+			peer_id: score.peer_id.to_base58(),
+			score: score.score,
+			banned: score.banned,
+		}).collect::<Vec<_>>();
+		scores.sort_by(|a, b| a.peer_id.cmp(&b.peer_id));
+
+		Response::json(200, &scores)
+	});
+}
@@ -0,0 +1,311 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use beacon::{BeaconState, Config, BLSConfig, Transaction};
+use beacon::primitives::Slot;
+use beacon::types::{Attestation, ProposerSlashing, AttesterSlashing, VoluntaryExit};
+use blockchain::AsExternalities;
+use blockchain::backend::{Store, ChainQuery};
+use log::warn;
+use shasper_network::{GossipQueue, PubsubMessage};
+use serde::{Serialize, Deserialize};
+use core::str::FromStr;
+use std::collections::HashMap;
+use crate::{Block, StateExternalities, SharedOperationPool, AttestationQueue, Executor};
+use crate::checkpoint::{CheckpointCache, CheckpointCheck};
+use super::{ApiEvents, ApiServer, Method, Request, Response};
+
+#[derive(Serialize)]
+struct Data<T> {
+	data: T,
+}
+
+#[derive(Serialize)]
+struct Failure {
+	index: usize,
+	message: String,
+}
+
+#[derive(Serialize)]
+struct Failures {
+	code: u16,
+	message: &'static str,
+	failures: Vec<Failure>,
+}
+
+fn parse_body<T: for<'de> Deserialize<'de>>(request: &Request) -> Result<T, Response> {
+	serde_json::from_slice(&request.body)
+		.map_err(|e| Response::error(400, &format!("Invalid request body: {}", e)))
+}
+
+fn parse_query<T: FromStr>(request: &Request, name: &str) -> Result<Option<T>, Response> {
+	match request.query(name) {
+		Some(value) => T::from_str(value).map(Some)
+			.map_err(|_| Response::error(400, &format!("Invalid query parameter {}", name))),
+		None => Ok(None),
+	}
+}
+
+fn head_state<B, C>(backend: &B) -> Result<B::State, Response> where
+	B: ChainQuery + Store<Block=Block<C>>,
+	B::State: StateExternalities<Config=C>,
+	C: Config,
+{
+	backend.state_at(&backend.head())
+		.map_err(|e| Response::error(503, &format!("Database unavailable: {}", e)))
+}
+
+/// Validate operations against the given state, calling `insert` for each
+/// valid one. `precheck` runs before the full validation, to reject
+/// operations cheaply. Responds with the list of failures if any is invalid.
+fn submit<C, BLS, T, P, F, I>(
+	state: &BeaconState<C>,
+	operations: Vec<T>,
+	precheck: P,
+	into_transaction: F,
+	mut insert: I,
+) -> Response where
+	C: Config,
+	BLS: BLSConfig,
+	T: Clone,
+	P: Fn(&T) -> Result<(), String>,
+	F: Fn(T) -> Transaction<C>,
+	I: FnMut(T),
+{
+	let mut failures = Vec::new();
+	for (index, operation) in operations.into_iter().enumerate() {
+		if let Err(message) = precheck(&operation) {
+			failures.push(Failure { index, message });
+			continue
+		}
+
+		match beacon::validate_transaction::<C, BLS>(
+			state, &into_transaction(operation.clone())
+		) {
+			Ok(()) => insert(operation),
+			Err(e) => failures.push(Failure { index, message: format!("{:?}", e) }),
+		}
+	}
+
+	if failures.is_empty() {
+		Response::json(200, &Data { data: () })
+	} else {
+		Response::json(400, &Failures {
+			code: 400,
+			message: "Some operations failed validation",
+			failures,
+		})
+	}
+}
+
+/// Register the pool endpoints, under the standard API's
+/// `/eth/v1/beacon/pool` namespace.
+///
+/// Submitted operations are fully validated against the head state before
+/// being added to the pool. Attestation checkpoints are checked first
+/// through the checkpoint cache, and accepted attestations are also queued
+/// for the fork choice, published on gossip and sent on the event stream.
+/// Accepted voluntary exits are published on gossip as well.
+/// Aggregation bits are checked against committees from the executor
+/// committee cache. If an attestation targets an epoch after the head
+/// state, the head state is advanced to it through empty slots first.
+pub fn register_pool<B, C, BLS>(
+	server: &mut ApiServer,
+	backend: B,
+	pool: SharedOperationPool<C, BLS>,
+	checkpoints: CheckpointCache,
+	fork_choice: AttestationQueue,
+	executor: Executor<C, BLS>,
+	gossip: GossipQueue<C>,
+	events: ApiEvents,
+) where
+	B: ChainQuery + Store<Block=Block<C>> + Clone + Send + Sync + 'static,
+	B::State: StateExternalities<Config=C> + AsExternalities<dyn StateExternalities<Config=C>>,
+	C: Config + Send + Sync + 'static,
+	BLS: BLSConfig + Send + Sync + 'static,
+{
+	let attestations = pool.clone();
+	server.route(Method::Get, "/eth/v1/beacon/pool/attestations", move |request| {
+		let slot = match parse_query::<Slot>(request, "slot") {
+			Ok(slot) => slot,
+			Err(response) => return response,
+		};
+		let committee_index = match parse_query::<u64>(request, "committee_index") {
+			Ok(committee_index) => committee_index,
+			Err(response) => return response,
+		};
+
+		let pool = attestations.lock();
+		let data = pool.attestations().iter()
+			.map(|(_, attestation)| attestation)
+			.filter(|attestation| slot.map(|slot| attestation.data.slot == slot).unwrap_or(true))
+			.filter(|attestation| {
+				committee_index.map(|index| attestation.data.index == index).unwrap_or(true)
+			})
+			.cloned()
+			.collect::<Vec<_>>();
+		Response::json(200, &Data { data })
+	});
+
+	let attestations = pool.clone();
+	let attestations_backend = backend.clone();
+	let attestations_gossip = gossip.clone();
+	server.route(Method::Post, "/eth/v1/beacon/pool/attestations", move |request| {
+		let operations = match parse_body::<Vec<Attestation<C>>>(request) {
+			Ok(operations) => operations,
+			Err(response) => return response,
+		};
+		let mut head_state = match head_state(&attestations_backend) {
+			Ok(state) => state,
+			Err(response) => return response,
+		};
+		let current_epoch = beacon::utils::epoch_of_slot::<C>(head_state.state().slot);
+		let target_slot = operations.iter()
+			.map(|attestation| attestation.data.target.epoch)
+			.filter(|epoch| *epoch <= current_epoch + 1)
+			.max()
+			.map(|epoch| beacon::utils::start_slot_of_epoch::<C>(epoch));
+		if let Some(target_slot) = target_slot {
+			if let Err(e) = executor.advance_slots(head_state.as_externalities(), target_slot) {
+				return Response::error(500, &format!("Advancing head state failed: {:?}", e))
+			}
+		}
+
+		let mut committees = HashMap::new();
+		for attestation in &operations {
+			let epoch = beacon::utils::epoch_of_slot::<C>(attestation.data.slot);
+			if !committees.contains_key(&epoch) {
+				if let Ok(epoch_committees) = executor.committees()
+					.committees(head_state.state_mut(), epoch)
+				{
+					committees.insert(epoch, epoch_committees);
+				}
+			}
+		}
+
+		let state = head_state.state().clone();
+		submit::<_, BLS, _, _, _, _>(
+			&state, operations,
+			|attestation| {
+				let epoch = beacon::utils::epoch_of_slot::<C>(attestation.data.slot);
+				match committees.get(&epoch) {
+					Some(committees) => {
+						committees.attesting_indices::<C>(
+							&attestation.data, &attestation.aggregation_bits,
+						).map_err(|e| format!("{:?}", e))?;
+					},
+					None => return Err("Attestation epoch is out of range".to_string()),
+				}
+
+				match checkpoints.check(
+					&attestations_backend, &attestations_backend.head(), &attestation.data,
+				) {
+					Ok(CheckpointCheck::WrongSource) =>
+						Err("Source does not match the justified checkpoint".to_string()),
+					Ok(CheckpointCheck::EpochOutOfRange) =>
+						Err("Target epoch is out of range".to_string()),
+					Ok(CheckpointCheck::Valid) | Ok(CheckpointCheck::WrongTarget) => Ok(()),
+					Err(e) => Err(format!("{:?}", e)),
+				}
+			},
+			Transaction::Attestation,
+			|attestation| {
+				if let Err(e) = executor.queue_attestation(
+					&fork_choice, head_state.as_externalities(), &attestation,
+				) {
+					warn!("Queueing attestation for fork choice failed: {:?}", e);
+				}
+				if !attestations_gossip.publish(PubsubMessage::Attestation(attestation.clone())) {
+					warn!("Gossip queue is full, not publishing attestation");
+				}
+				events.emit("attestation", &attestation);
+				attestations.lock().insert_attestation(attestation)
+			},
+		)
+	});
+
+	let proposer_slashings = pool.clone();
+	server.route(Method::Get, "/eth/v1/beacon/pool/proposer_slashings", move |_| {
+		let data = proposer_slashings.lock().proposer_slashings().to_vec();
+		Response::json(200, &Data { data })
+	});
+
+	let proposer_slashings = pool.clone();
+	let proposer_slashings_backend = backend.clone();
+	server.route(Method::Post, "/eth/v1/beacon/pool/proposer_slashings", move |request| {
+		let operation = match parse_body::<ProposerSlashing>(request) {
+			Ok(operation) => operation,
+			Err(response) => return response,
+		};
+		let state = match head_state(&proposer_slashings_backend) {
+			Ok(state) => state,
+			Err(response) => return response,
+		};
+		submit::<_, BLS, _, _, _, _>(
+			state.state(), vec![operation], |_| Ok(()), Transaction::ProposerSlashing,
+			|slashing| proposer_slashings.lock().insert_proposer_slashing(slashing),
+		)
+	});
+
+	let attester_slashings = pool.clone();
+	server.route(Method::Get, "/eth/v1/beacon/pool/attester_slashings", move |_| {
+		let data = attester_slashings.lock().attester_slashings().to_vec();
+		Response::json(200, &Data { data })
+	});
+
+	let attester_slashings = pool.clone();
+	let attester_slashings_backend = backend.clone();
+	server.route(Method::Post, "/eth/v1/beacon/pool/attester_slashings", move |request| {
+		let operation = match parse_body::<AttesterSlashing<C>>(request) {
+			Ok(operation) => operation,
+			Err(response) => return response,
+		};
+		let state = match head_state(&attester_slashings_backend) {
+			Ok(state) => state,
+			Err(response) => return response,
+		};
+		submit::<_, BLS, _, _, _, _>(
+			state.state(), vec![operation], |_| Ok(()), Transaction::AttesterSlashing,
+			|slashing| attester_slashings.lock().insert_attester_slashing(slashing),
+		)
+	});
+
+	let voluntary_exits = pool.clone();
+	server.route(Method::Get, "/eth/v1/beacon/pool/voluntary_exits", move |_| {
+		let data = voluntary_exits.lock().voluntary_exits().to_vec();
+		Response::json(200, &Data { data })
+	});
+
+	server.route(Method::Post, "/eth/v1/beacon/pool/voluntary_exits", move |request| {
+		let operation = match parse_body::<VoluntaryExit>(request) {
+			Ok(operation) => operation,
+			Err(response) => return response,
+		};
+		let state = match head_state(&backend) {
+			Ok(state) => state,
+			Err(response) => return response,
+		};
+		submit::<_, BLS, _, _, _, _>(
+			state.state(), vec![operation], |_| Ok(()), Transaction::VoluntaryExit,
+			|exit| {
+				if !gossip.publish(PubsubMessage::VoluntaryExit(exit.clone())) {
+					warn!("Gossip queue is full, not publishing voluntary exit");
+				}
+				pool.lock().insert_voluntary_exit(exit)
+			},
+		)
+	});
+}
@@ -0,0 +1,90 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use beacon::{BeaconExecutive, Config};
+use beacon::primitives::{H256, Epoch};
+use blockchain::backend::{Store, ChainQuery};
+use serde::Serialize;
+use core::str::FromStr;
+use crate::{Block, StateExternalities};
+use super::{ApiServer, Method, Response};
+
+#[derive(Serialize)]
+struct Randao {
+	current_epoch: Epoch,
+	epoch: Epoch,
+	randao_mix: Option<H256>,
+	attester_seed: H256,
+	proposer_seed: H256,
+	seed_final: bool,
+	next_epoch_proposer_seed: H256,
+}
+
+/// Register the `/eth/v1/debug/randao` endpoint, exposing the randao mix and
+/// derived seeds of the head state, for the current epoch or the `epoch`
+/// query parameter.
+///
+/// Seeds are final once the randao mix they derive from can no longer
+/// change, which is the case up to `MIN_SEED_LOOKAHEAD` epochs ahead.
+pub fn register_randao<B, C>(
+	server: &mut ApiServer,
+	backend: B,
+) where
+	B: ChainQuery + Store<Block=Block<C>> + Send + Sync + 'static,
+	B::State: StateExternalities<Config=C>,
+	C: Config,
+{
+	server.route(Method::Get, "/eth/v1/debug/randao", move |request| {
+		let mut state = match backend.state_at(&backend.head()) {
+			Ok(state) => state,
+			Err(e) => return Response::error(503, &format!("Database unavailable: {}", e)),
+		};
+		let executive = BeaconExecutive::new(state.state_mut());
+		let current_epoch = executive.current_epoch();
+
+		let epoch = match request.query("epoch").map(Epoch::from_str) {
+			Some(Ok(epoch)) => epoch,
+			Some(Err(_)) => return Response::error(400, "Invalid epoch"),
+			None => current_epoch,
+		};
+		// The seed of an epoch derives from the mix of `MIN_SEED_LOOKAHEAD + 1`
+		// epochs before, which must still be in the historical vector.
+		if epoch > current_epoch + C::min_seed_lookahead() + 1 ||
+			epoch + C::epochs_per_historical_vector() <=
+				current_epoch + C::min_seed_lookahead() + 1
+		{
+			return Response::error(400, "Epoch out of range")
+		}
+
+		let randao_mix = if epoch <= current_epoch &&
+			epoch + C::epochs_per_historical_vector() > current_epoch
+		{
+			Some(executive.randao_mix(epoch))
+		} else {
+			None
+		};
+
+		Response::json(200, &Randao {
+			current_epoch,
+			epoch,
+			randao_mix,
+			attester_seed: executive.seed(epoch, C::domain_beacon_attester()),
+			proposer_seed: executive.seed(epoch, C::domain_beacon_proposer()),
+			seed_final: epoch <= current_epoch + C::min_seed_lookahead(),
+			next_epoch_proposer_seed: executive.seed(current_epoch + 1, C::domain_beacon_proposer()),
+		})
+	});
+}
@@ -0,0 +1,92 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use beacon::{BLSConfig, Config, RewardProjection, PARTICIPATION_BASIS_POINTS};
+use beacon::primitives::ValidatorIndex;
+use blockchain::AsExternalities;
+use blockchain::backend::{Store, ChainQuery};
+use serde::Serialize;
+use core::str::FromStr;
+use crate::{Block, Executor, StateExternalities};
+use super::{ApiServer, Method, Response};
+
+#[derive(Serialize)]
+struct Projection {
+	participation: u64,
+	/// Mean projected APR, weighted by effective balance, in basis points.
+	apr_basis_points: i64,
+	validators: Vec<RewardProjection>,
+}
+
+/// Register the `/eth/v1/debug/rewards/projection` endpoint, projecting the
+/// rewards and APR of each active validator at the head state if the
+/// `participation` query parameter, in basis points, of validators attested.
+/// Defaults to full participation, and can be narrowed to a single
+/// `validator` index.
+pub fn register_rewards<B, C, BLS>(
+	server: &mut ApiServer,
+	backend: B,
+	executor: Executor<C, BLS>,
+) where
+	B: ChainQuery + Store<Block=Block<C>> + Send + Sync + 'static,
+	B::State: StateExternalities<Config=C> + AsExternalities<dyn StateExternalities<Config=C>>,
+	C: Config + Send + Sync + 'static,
+	BLS: BLSConfig + Send + Sync + 'static,
+{
+	server.route(Method::Get, "/eth/v1/debug/rewards/projection", move |request| {
+		let participation = match request.query("participation").map(u64::from_str) {
+			Some(Ok(participation)) if participation <= PARTICIPATION_BASIS_POINTS =>
+				participation,
+			Some(_) => return Response::error(400, "Invalid participation"),
+			None => PARTICIPATION_BASIS_POINTS,
+		};
+		let validator = match request.query("validator").map(ValidatorIndex::from_str) {
+			Some(Ok(index)) => Some(index),
+			Some(Err(_)) => return Response::error(400, "Invalid validator index"),
+			None => None,
+		};
+
+		let mut state = match backend.state_at(&backend.head()) {
+			Ok(state) => state,
+			Err(e) => return Response::error(503, &format!("Database unavailable: {}", e)),
+		};
+		let mut validators = match executor.project_rewards(
+			state.as_externalities(), participation
+		) {
+			Ok(validators) => validators,
+			Err(e) => return Response::error(500, &format!("Projection failed: {:?}", e)),
+		};
+
+		let total_balance = validators.iter()
+			.map(|v| v.effective_balance.as_u64() as i128).sum::<i128>();
+		let apr_basis_points = if total_balance == 0 {
+			0
+		} else {
+			(validators.iter()
+			 .map(|v| v.apr_basis_points as i128 * v.effective_balance.as_u64() as i128)
+			 .sum::<i128>() / total_balance) as i64
+		};
+
+		if let Some(index) = validator {
+			validators.retain(|v| v.index == index);
+			if validators.is_empty() {
+				return Response::error(404, "Validator is not active")
+			}
+		}
+
+		Response::json(200, &Projection { participation, apr_basis_points, validators })
+	});
+}
@@ -0,0 +1,29 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+use crate::settings::SettingsFile;
+use super::{ApiServer, Method, Response};
+
+/// Register the `/admin/reload` endpoint, reloading the settings file and
+/// reporting which settings were applied and which require a restart.
+pub fn register_settings(server: &mut ApiServer, settings: SettingsFile) {
+	server.route(Method::Post, "/admin/reload", move |_| {
+		match settings.reload() {
+			Ok(report) => Response::json(200, &report),
+			Err(e) => Response::error(400, &format!("Reloading settings failed: {}", e)),
+		}
+	});
+}
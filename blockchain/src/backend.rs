@@ -13,21 +13,78 @@
 
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use beacon::Config;
+use beacon::primitives::Slot;
 use blockchain::{Block, Auxiliary};
-use blockchain::backend::{Store, SharedCommittable, ChainQuery, Operation};
+use blockchain::backend::{
+	Store, SharedCommittable, ChainQuery, Operation, OperationError, SharedMemoryBackend,
+};
+use blockchain_rocksdb::{RocksBackend, RocksState};
+use parity_codec::{Encode, Decode};
 use lmd_ghost::archive::{AncestorQuery, NoCacheAncestorQuery};
+use crate::Block as ShasperBlock;
 
-pub struct ShasperBackend<Ba>(Ba);
+/// Regeneration of states that are not stored by a backend.
+pub trait StateRegeneration<Ba: Store>: Send + Sync {
+	/// Regenerate the post-state of a block.
+	fn regenerate(
+		&self,
+		backend: &Ba,
+		id: &<Ba::Block as Block>::Identifier,
+	) -> Result<Ba::State, Ba::Error>;
+}
+
+/// Block with a slot number.
+pub trait SlotBlock: Block {
+	/// Slot of the block.
+	fn slot(&self) -> Slot;
+}
+
+impl<C: Config> SlotBlock for ShasperBlock<C> {
+	fn slot(&self) -> Slot {
+		self.0.slot
+	}
+}
 
-impl<Ba> ShasperBackend<Ba> {
+/// Index of canonical block identifiers by slot, valid for the chain of
+/// `head`.
+struct CanonSlots<H> {
+	head: Option<H>,
+	ids: BTreeMap<Slot, H>,
+}
+
+impl<H> Default for CanonSlots<H> {
+	fn default() -> Self {
+		Self { head: None, ids: BTreeMap::new() }
+	}
+}
+
+pub struct ShasperBackend<Ba: Store>(
+	Ba,
+	Option<Arc<dyn StateRegeneration<Ba>>>,
+	Arc<RwLock<CanonSlots<<Ba::Block as Block>::Identifier>>>,
+);
+
+impl<Ba: Store> ShasperBackend<Ba> {
 	pub fn new(backend: Ba) -> Self {
-		Self(backend)
+		Self(backend, None, Default::default())
+	}
+
+	/// Regenerate states that the underlying backend does not store when
+	/// they are queried, instead of failing.
+	pub fn with_regenerator<R>(mut self, regenerator: R) -> Self where
+		R: StateRegeneration<Ba> + 'static,
+	{
+		self.1 = Some(Arc::new(regenerator));
+		self
 	}
 }
 
-impl<Ba: Clone> Clone for ShasperBackend<Ba> {
+impl<Ba: Store + Clone> Clone for ShasperBackend<Ba> {
 	fn clone(&self) -> Self {
-		Self(self.0.clone())
+		Self(self.0.clone(), self.1.clone(), self.2.clone())
 	}
 }
 
@@ -38,7 +95,7 @@ impl<Ba: Store> Store for ShasperBackend<Ba> {
 	type Error = Ba::Error;
 }
 
-impl<Ba: ChainQuery> ChainQuery for ShasperBackend<Ba> {
+impl<Ba: StateStorage> ChainQuery for ShasperBackend<Ba> {
 	fn genesis(&self) -> <Self::Block as Block>::Identifier {
 		self.0.genesis()
 	}
@@ -85,7 +142,14 @@ impl<Ba: ChainQuery> ChainQuery for ShasperBackend<Ba> {
 		&self,
 		hash: &<Self::Block as Block>::Identifier,
 	) -> Result<Self::State, Self::Error> {
-		Ok(self.0.state_at(hash)?)
+		if let Some(state) = self.0.stored_state_at(hash)? {
+			return Ok(state)
+		}
+
+		match self.1 {
+			Some(ref regenerator) => regenerator.regenerate(&self.0, hash),
+			None => Err(Ba::state_unavailable()),
+		}
 	}
 	fn block_at(
 		&self,
@@ -95,7 +159,7 @@ impl<Ba: ChainQuery> ChainQuery for ShasperBackend<Ba> {
 	}
 }
 
-impl<Ba: ChainQuery> AncestorQuery for ShasperBackend<Ba> {
+impl<Ba: StateStorage> AncestorQuery for ShasperBackend<Ba> {
 	fn ancestor_at(
 		&self,
 		id: &<Self::Block as Block>::Identifier,
@@ -105,7 +169,7 @@ impl<Ba: ChainQuery> AncestorQuery for ShasperBackend<Ba> {
 	}
 }
 
-impl<Ba> SharedCommittable for ShasperBackend<Ba> where
+impl<Ba: Store> SharedCommittable for ShasperBackend<Ba> where
 	Ba: SharedCommittable<Operation=Operation<Self::Block, Self::State, Self::Auxiliary>>
 {
 	type Operation = Operation<Self::Block, Self::State, Self::Auxiliary>;
@@ -117,3 +181,233 @@ impl<Ba> SharedCommittable for ShasperBackend<Ba> where
 		self.0.commit(operation)
 	}
 }
+
+/// Backend able to look up canonical blocks by slot.
+pub trait CanonSlotQuery: ChainQuery {
+	/// Canonical block at the given slot, or `None` if the slot is empty
+	/// or after the head.
+	fn lookup_canon_slot(
+		&self,
+		slot: Slot,
+	) -> Result<Option<<Self::Block as Block>::Identifier>, Self::Error>;
+}
+
+impl<Ba: StateStorage> CanonSlotQuery for ShasperBackend<Ba> where
+	Ba::Block: SlotBlock,
+{
+	/// The index is brought up to date with the head on lookup. After a
+	/// reorg, only the blocks since the common ancestor are walked.
+	fn lookup_canon_slot(
+		&self,
+		slot: Slot,
+	) -> Result<Option<<Self::Block as Block>::Identifier>, Self::Error> {
+		let head = self.0.head();
+		{
+			let index = self.2.read().expect("Lock is poisoned");
+			if index.head.as_ref() == Some(&head) {
+				return Ok(index.ids.get(&slot).cloned())
+			}
+		}
+
+		let mut index = self.2.write().expect("Lock is poisoned");
+		if index.head.as_ref() != Some(&head) {
+			let mut added = Vec::new();
+			let mut common_slot = None;
+			let mut current = self.0.block_at(&head)?;
+			loop {
+				let id = current.id();
+				if index.ids.get(&current.slot()) == Some(&id) {
+					common_slot = Some(current.slot());
+					break
+				}
+				added.push((current.slot(), id));
+
+				match current.parent_id() {
+					Some(parent) => current = self.0.block_at(&parent)?,
+					None => break,
+				}
+			}
+
+			match common_slot {
+				Some(common_slot) => { index.ids.split_off(&(common_slot + 1)); },
+				None => index.ids.clear(),
+			}
+			index.ids.extend(added);
+			index.head = Some(head);
+		}
+
+		Ok(index.ids.get(&slot).cloned())
+	}
+}
+
+/// Backend that may store the states of only some blocks.
+pub trait StateStorage: ChainQuery {
+	/// Stored post-state of a block, or `None` if it was not kept.
+	fn stored_state_at(
+		&self,
+		id: &<Self::Block as Block>::Identifier,
+	) -> Result<Option<Self::State>, Self::Error>;
+
+	/// Error reported for a state that is neither stored nor regenerated.
+	fn state_unavailable() -> Self::Error;
+
+	/// Drop the states of blocks before the finalized block that are not
+	/// kept by the storage mode, returning the number of dropped states.
+	fn prune_states(
+		&self,
+		finalized: &<Self::Block as Block>::Identifier,
+	) -> Result<usize, Self::Error>;
+}
+
+impl<Ba: StateStorage> StateStorage for ShasperBackend<Ba> {
+	fn stored_state_at(
+		&self,
+		id: &<Self::Block as Block>::Identifier,
+	) -> Result<Option<Self::State>, Self::Error> {
+		self.0.stored_state_at(id)
+	}
+
+	fn state_unavailable() -> Self::Error {
+		Ba::state_unavailable()
+	}
+
+	fn prune_states(
+		&self,
+		finalized: &<Self::Block as Block>::Identifier,
+	) -> Result<usize, Self::Error> {
+		self.0.prune_states(finalized)
+	}
+}
+
+impl<B: Block, A: Auxiliary<B>, S: RocksState> StateStorage for RocksBackend<B, A, S> where
+	B::Identifier: Encode + Decode,
+	B: Encode + Decode,
+	A: Encode + Decode,
+	A::Key: Encode + Decode,
+{
+	fn stored_state_at(
+		&self,
+		id: &<Self::Block as Block>::Identifier,
+	) -> Result<Option<Self::State>, Self::Error> {
+		RocksBackend::stored_state_at(self, id)
+	}
+
+	fn state_unavailable() -> Self::Error {
+		blockchain_rocksdb::Error::NotExist
+	}
+
+	fn prune_states(
+		&self,
+		finalized: &<Self::Block as Block>::Identifier,
+	) -> Result<usize, Self::Error> {
+		RocksBackend::prune_states(self, finalized)
+	}
+}
+
+impl<B: Block, A: Auxiliary<B>, S: Clone> StateStorage for SharedMemoryBackend<B, A, S> {
+	/// The in-memory backend keeps the states of all blocks.
+	fn stored_state_at(
+		&self,
+		id: &<Self::Block as Block>::Identifier,
+	) -> Result<Option<Self::State>, Self::Error> {
+		self.state_at(id).map(Some)
+	}
+
+	fn state_unavailable() -> Self::Error {
+		Self::Error::invalid_operation()
+	}
+
+	fn prune_states(
+		&self,
+		_finalized: &<Self::Block as Block>::Identifier,
+	) -> Result<usize, Self::Error> {
+		Ok(0)
+	}
+}
+
+/// Backend able to move finalized blocks out of its hot database.
+pub trait Freezing: Store {
+	/// Move canonical blocks before the finalized block into cold storage,
+	/// returning the number of moved blocks.
+	fn freeze(
+		&self,
+		finalized: &<Self::Block as Block>::Identifier,
+	) -> Result<usize, Self::Error>;
+}
+
+impl<Ba: Freezing> Freezing for ShasperBackend<Ba> {
+	fn freeze(
+		&self,
+		finalized: &<Self::Block as Block>::Identifier,
+	) -> Result<usize, Self::Error> {
+		self.0.freeze(finalized)
+	}
+}
+
+impl<B: Block, A: Auxiliary<B>, S: RocksState> Freezing for RocksBackend<B, A, S> where
+	B::Identifier: Encode + Decode,
+	B: Encode + Decode,
+	A: Encode + Decode,
+	A::Key: Encode + Decode,
+{
+	fn freeze(
+		&self,
+		finalized: &<Self::Block as Block>::Identifier,
+	) -> Result<usize, Self::Error> {
+		RocksBackend::freeze(self, finalized)
+	}
+}
+
+impl<B: Block, A: Auxiliary<B>, S: Clone> Freezing for SharedMemoryBackend<B, A, S> {
+	/// The in-memory backend has no cold storage.
+	fn freeze(
+		&self,
+		_finalized: &<Self::Block as Block>::Identifier,
+	) -> Result<usize, Self::Error> {
+		Ok(0)
+	}
+}
+
+/// Backend able to remove abandoned forks.
+pub trait BranchPruning: Store {
+	/// Remove a non-canonical block and all its descendants, returning the
+	/// number of removed blocks.
+	fn remove_branch(
+		&self,
+		id: &<Self::Block as Block>::Identifier,
+	) -> Result<usize, Self::Error>;
+}
+
+impl<Ba: BranchPruning> BranchPruning for ShasperBackend<Ba> {
+	fn remove_branch(
+		&self,
+		id: &<Self::Block as Block>::Identifier,
+	) -> Result<usize, Self::Error> {
+		self.0.remove_branch(id)
+	}
+}
+
+impl<B: Block, A: Auxiliary<B>, S: RocksState> BranchPruning for RocksBackend<B, A, S> where
+	B::Identifier: Encode + Decode,
+	B: Encode + Decode,
+	A: Encode + Decode,
+	A::Key: Encode + Decode,
+{
+	fn remove_branch(
+		&self,
+		id: &<Self::Block as Block>::Identifier,
+	) -> Result<usize, Self::Error> {
+		RocksBackend::remove_branch(self, id)
+	}
+}
+
+impl<B: Block, A: Auxiliary<B>, S: Clone> BranchPruning for SharedMemoryBackend<B, A, S> {
+	/// The in-memory backend does not support removal, and keeps all blocks
+	/// for the lifetime of the process.
+	fn remove_branch(
+		&self,
+		_id: &<Self::Block as Block>::Identifier,
+	) -> Result<usize, Self::Error> {
+		Ok(0)
+	}
+}
@@ -0,0 +1,116 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Cache of epoch-boundary ("advanced") states per fork tip.
+
+use beacon::{BeaconState, Config};
+use beacon::primitives::{H256, Epoch};
+use blockchain::backend::{Store, ChainQuery};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use crate::{Block, Error, StateExternalities};
+
+struct Inner<C: Config> {
+	states: HashMap<(H256, Epoch), BeaconState<C>>,
+	order: VecDeque<(H256, Epoch)>,
+	capacity: usize,
+}
+
+/// Cache of block states advanced to the start of an epoch, so that
+/// attestation target states do not need to be replayed in the hot path.
+#[derive(Clone)]
+pub struct AdvancedStateCache<C: Config>(Arc<Mutex<Inner<C>>>);
+
+impl<C: Config> AdvancedStateCache<C> {
+	/// Create a new cache holding at most `capacity` states.
+	pub fn new(capacity: usize) -> Self {
+		Self(Arc::new(Mutex::new(Inner {
+			states: HashMap::new(),
+			order: VecDeque::new(),
+			capacity,
+		})))
+	}
+
+	/// Get a cached advanced state.
+	pub fn get(&self, block_id: &H256, epoch: Epoch) -> Option<BeaconState<C>> {
+		let inner = self.0.lock().expect("Lock is poisoned");
+		inner.states.get(&(*block_id, epoch)).cloned()
+	}
+
+	/// Insert an advanced state, evicting the oldest one if full.
+	pub fn insert(&self, block_id: H256, epoch: Epoch, state: BeaconState<C>) {
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+		if inner.states.insert((block_id, epoch), state).is_none() {
+			inner.order.push_back((block_id, epoch));
+		}
+		while inner.order.len() > inner.capacity {
+			if let Some(key) = inner.order.pop_front() {
+				inner.states.remove(&key);
+			}
+		}
+	}
+
+	/// Get the state of a block advanced to the start slot of `epoch`,
+	/// replaying empty slots and caching the result on a miss.
+	pub fn get_or_advance<B>(
+		&self,
+		backend: &B,
+		block_id: &H256,
+		epoch: Epoch,
+	) -> Result<BeaconState<C>, Error> where
+		B: ChainQuery + Store<Block=Block<C>>,
+		B::State: StateExternalities<Config=C>,
+	{
+		if let Some(state) = self.get(block_id, epoch) {
+			return Ok(state)
+		}
+
+		let mut state = backend.state_at(block_id)
+			.map_err(|e| Error::Backend(Box::new(e)))?
+			.state().clone();
+		let target_slot = beacon::utils::start_slot_of_epoch::<C>(epoch);
+		if state.slot < target_slot {
+			beacon::initialize_block::<C>(&mut state, target_slot)?;
+		}
+
+		self.insert(*block_id, epoch, state.clone());
+		Ok(state)
+	}
+
+	/// Drop states before the finalized epoch, or of blocks no longer known
+	/// to the backend.
+	pub fn prune<B>(&self, backend: &B, finalized_epoch: Epoch) where
+		B: ChainQuery + Store<Block=Block<C>>,
+	{
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+		let mut removed = Vec::new();
+		for key in &inner.order {
+			if key.1 < finalized_epoch || !backend.contains(&key.0).unwrap_or(false) {
+				removed.push(*key);
+			}
+		}
+		for key in removed {
+			inner.states.remove(&key);
+			inner.order.retain(|k| k != &key);
+		}
+	}
+}
+
+impl<C: Config> Default for AdvancedStateCache<C> {
+	fn default() -> Self {
+		Self::new(16)
+	}
+}
@@ -0,0 +1,161 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Cache of expected attestation checkpoints per fork tip.
+
+use beacon::{BeaconExecutive, Config};
+use beacon::primitives::{H256, Epoch};
+use beacon::types::{AttestationData, Checkpoint};
+use blockchain::backend::{Store, ChainQuery};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use crate::{Block, Error, StateExternalities};
+
+/// Checkpoints an attestation of an epoch must have to be valid on a fork.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExpectedCheckpoints {
+	/// Justified checkpoint to use as source.
+	pub source: Checkpoint,
+	/// Epoch boundary block of the fork.
+	pub target: Checkpoint,
+}
+
+/// Result of checking attestation checkpoints against a fork tip.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CheckpointCheck {
+	/// Source and target both match.
+	Valid,
+	/// Source does not match the justified checkpoint, so the attestation
+	/// cannot be included on this fork.
+	WrongSource,
+	/// Target is not the epoch boundary block of this fork. The attestation
+	/// can still be included, but votes for another fork.
+	WrongTarget,
+	/// Target epoch is neither the current nor the previous epoch.
+	EpochOutOfRange,
+}
+
+struct Inner {
+	checkpoints: HashMap<(H256, Epoch), Option<ExpectedCheckpoints>>,
+	order: VecDeque<(H256, Epoch)>,
+	capacity: usize,
+}
+
+/// Cache of the expected source and target checkpoints per fork tip and
+/// epoch, so that attestation validation of the checkpoint fields does not
+/// need a state access per attestation.
+#[derive(Clone)]
+pub struct CheckpointCache(Arc<Mutex<Inner>>);
+
+impl CheckpointCache {
+	/// Create a new cache holding at most `capacity` entries.
+	pub fn new(capacity: usize) -> Self {
+		Self(Arc::new(Mutex::new(Inner {
+			checkpoints: HashMap::new(),
+			order: VecDeque::new(),
+			capacity,
+		})))
+	}
+
+	/// Get the expected checkpoints of attestations targeting `epoch`, on
+	/// the fork of `head`. Returns `None` if the epoch is out of range.
+	pub fn expected<B, C>(
+		&self,
+		backend: &B,
+		head: &H256,
+		epoch: Epoch,
+	) -> Result<Option<ExpectedCheckpoints>, Error> where
+		B: ChainQuery + Store<Block=Block<C>>,
+		B::State: StateExternalities<Config=C>,
+		C: Config,
+	{
+		if let Some(expected) = self.0.lock().expect("Lock is poisoned")
+			.checkpoints.get(&(*head, epoch))
+		{
+			return Ok(expected.clone())
+		}
+
+		let mut state = backend.state_at(head)
+			.map_err(|e| Error::Backend(Box::new(e)))?
+			.state().clone();
+		let target_slot = beacon::utils::start_slot_of_epoch::<C>(epoch);
+		if state.slot < target_slot {
+			beacon::initialize_block::<C>(&mut state, target_slot)?;
+		}
+		let executive = BeaconExecutive::new(&mut state);
+
+		let source = if epoch == executive.current_epoch() {
+			Some(executive.current_justified_checkpoint.clone())
+		} else if epoch == executive.previous_epoch() {
+			Some(executive.previous_justified_checkpoint.clone())
+		} else {
+			None
+		};
+		let expected = match source {
+			Some(source) => {
+				let root = if target_slot >= executive.slot {
+					*head
+				} else {
+					executive.block_root(epoch)?
+				};
+				Some(ExpectedCheckpoints { source, target: Checkpoint { epoch, root } })
+			},
+			None => None,
+		};
+
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+		if inner.checkpoints.insert((*head, epoch), expected.clone()).is_none() {
+			inner.order.push_back((*head, epoch));
+		}
+		while inner.order.len() > inner.capacity {
+			if let Some(key) = inner.order.pop_front() {
+				inner.checkpoints.remove(&key);
+			}
+		}
+
+		Ok(expected)
+	}
+
+	/// Check the source and target of attestation data against the fork
+	/// of `head`.
+	pub fn check<B, C>(
+		&self,
+		backend: &B,
+		head: &H256,
+		data: &AttestationData,
+	) -> Result<CheckpointCheck, Error> where
+		B: ChainQuery + Store<Block=Block<C>>,
+		B::State: StateExternalities<Config=C>,
+		C: Config,
+	{
+		Ok(match self.expected(backend, head, data.target.epoch)? {
+			None => CheckpointCheck::EpochOutOfRange,
+			Some(expected) => if data.source != expected.source {
+				CheckpointCheck::WrongSource
+			} else if data.target != expected.target {
+				CheckpointCheck::WrongTarget
+			} else {
+				CheckpointCheck::Valid
+			},
+		})
+	}
+}
+
+impl Default for CheckpointCache {
+	fn default() -> Self {
+		Self::new(64)
+	}
+}
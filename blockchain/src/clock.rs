@@ -0,0 +1,225 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Slot clock, and a driver ticking at the points of a slot where the
+//! validator needs to act.
+
+use beacon::primitives::Slot;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Source of the current slot.
+pub trait SlotClock: Send + Sync {
+	/// Duration of a slot.
+	fn slot_duration(&self) -> Duration;
+
+	/// Time elapsed since genesis, `None` if genesis has not been reached.
+	fn since_genesis(&self) -> Option<Duration>;
+
+	/// Wait for the given duration.
+	fn wait(&self, duration: Duration) {
+		thread::sleep(duration);
+	}
+
+	/// Current slot, `None` if genesis has not been reached.
+	fn now(&self) -> Option<Slot> {
+		let since_genesis = self.since_genesis()?;
+		Some(Slot::new(
+			duration_to_millis(since_genesis) / duration_to_millis(self.slot_duration())
+		))
+	}
+
+	/// Start time of the slot, relative to genesis.
+	fn slot_start(&self, slot: Slot) -> Duration {
+		Duration::from_millis(slot.as_u64() * duration_to_millis(self.slot_duration()))
+	}
+
+	/// Instant at which `offset` into `slot` is reached, now if it is
+	/// already past.
+	fn instant_at(&self, slot: Slot, offset: Duration) -> Instant {
+		let target = self.slot_start(slot) + offset;
+		let remaining = match self.since_genesis() {
+			Some(elapsed) => target.checked_sub(elapsed).unwrap_or_default(),
+			None => self.until_genesis() + target,
+		};
+		Instant::now() + remaining
+	}
+
+	/// Next slot tick, and the duration until it happens.
+	fn next_tick(&self) -> (SlotTick, Duration) {
+		let slot_duration = duration_to_millis(self.slot_duration());
+		let third = slot_duration / 3;
+
+		let elapsed = match self.since_genesis() {
+			Some(elapsed) => duration_to_millis(elapsed),
+			None => {
+				let until = self.until_genesis();
+				return (SlotTick::Start(Slot::new(0)), until)
+			},
+		};
+		let slot = Slot::new(elapsed / slot_duration);
+		let offset = elapsed % slot_duration;
+
+		let (tick, at) = if offset < third {
+			(SlotTick::Attestation(slot), third)
+		} else if offset < 2 * third {
+			(SlotTick::Aggregation(slot), 2 * third)
+		} else {
+			(SlotTick::Start(slot + 1), slot_duration)
+		};
+
+		(tick, Duration::from_millis(at - offset))
+	}
+
+	/// Duration until genesis, zero if it has been reached.
+	fn until_genesis(&self) -> Duration {
+		Duration::from_secs(0)
+	}
+}
+
+fn duration_to_millis(duration: Duration) -> u64 {
+	duration.as_secs() * 1000 + duration.subsec_millis() as u64
+}
+
+/// Slot clock based on system time.
+#[derive(Clone, Debug)]
+pub struct SystemTimeSlotClock {
+	genesis: Duration,
+	slot_duration: Duration,
+}
+
+impl SystemTimeSlotClock {
+	/// Create a new clock given genesis time in seconds since unix epoch.
+	pub fn new(genesis_time: u64, slot_duration: Duration) -> Self {
+		Self { genesis: Duration::from_secs(genesis_time), slot_duration }
+	}
+
+	fn since_unix_epoch() -> Duration {
+		SystemTime::now().duration_since(UNIX_EPOCH)
+			.unwrap_or(Duration::from_secs(0))
+	}
+}
+
+impl SlotClock for SystemTimeSlotClock {
+	fn slot_duration(&self) -> Duration {
+		self.slot_duration
+	}
+
+	fn since_genesis(&self) -> Option<Duration> {
+		let now = Self::since_unix_epoch();
+		if now >= self.genesis {
+			Some(now - self.genesis)
+		} else {
+			None
+		}
+	}
+
+	fn until_genesis(&self) -> Duration {
+		let now = Self::since_unix_epoch();
+		if now >= self.genesis {
+			Duration::from_secs(0)
+		} else {
+			self.genesis - now
+		}
+	}
+}
+
+/// Manually driven slot clock, for tests and simulations. Waiting on this
+/// clock advances it instead of sleeping.
+#[derive(Clone, Debug)]
+pub struct ManualSlotClock {
+	since_genesis: Arc<Mutex<Duration>>,
+	slot_duration: Duration,
+}
+
+impl ManualSlotClock {
+	/// Create a new clock at genesis.
+	pub fn new(slot_duration: Duration) -> Self {
+		Self {
+			since_genesis: Arc::new(Mutex::new(Duration::from_secs(0))),
+			slot_duration,
+		}
+	}
+
+	/// Set the clock to the start of a slot.
+	pub fn set_slot(&self, slot: Slot) {
+		let start = self.slot_start(slot);
+		*self.since_genesis.lock().expect("Lock is poisoned") = start;
+	}
+
+	/// Advance the clock by the given duration.
+	pub fn advance(&self, duration: Duration) {
+		*self.since_genesis.lock().expect("Lock is poisoned") += duration;
+	}
+}
+
+impl SlotClock for ManualSlotClock {
+	fn slot_duration(&self) -> Duration {
+		self.slot_duration
+	}
+
+	fn since_genesis(&self) -> Option<Duration> {
+		Some(*self.since_genesis.lock().expect("Lock is poisoned"))
+	}
+
+	fn wait(&self, duration: Duration) {
+		self.advance(duration);
+	}
+}
+
+/// Point in a slot where validator duties are due.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SlotTick {
+	/// Start of the slot, time to propose a block.
+	Start(Slot),
+	/// One third into the slot, time to attest.
+	Attestation(Slot),
+	/// Two thirds into the slot, time to aggregate.
+	Aggregation(Slot),
+}
+
+impl SlotTick {
+	/// Slot of the tick.
+	pub fn slot(&self) -> Slot {
+		match self {
+			SlotTick::Start(slot) |
+			SlotTick::Attestation(slot) |
+			SlotTick::Aggregation(slot) => *slot,
+		}
+	}
+}
+
+/// Start a background thread sending slot ticks as they happen. Ticks are
+/// not queued up while the receiver is busy, and the thread stops once the
+/// receiver is dropped.
+pub fn start_slot_ticks(clock: Arc<dyn SlotClock>) -> Receiver<SlotTick> {
+	let (sender, receiver) = sync_channel(0);
+
+	thread::spawn(move || {
+		loop {
+			let (tick, until) = clock.next_tick();
+			clock.wait(until);
+
+			if sender.send(tick).is_err() {
+				break
+			}
+		}
+	});
+
+	receiver
+}
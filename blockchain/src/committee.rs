@@ -0,0 +1,176 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Cache of shuffled beacon committees per epoch.
+
+use beacon::{BeaconExecutive, BeaconState, Config, CommitteeAssignment, Error as BeaconError};
+use beacon::primitives::{H256, Epoch, Slot, ValidatorIndex};
+use beacon::types::AttestationData;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use crate::Error;
+
+/// Beacon committees of an epoch, stored as the shuffled list of active
+/// validator indices.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EpochCommittees {
+	epoch: Epoch,
+	committees_per_slot: u64,
+	shuffling: Vec<ValidatorIndex>,
+}
+
+impl EpochCommittees {
+	/// Epoch of the committees.
+	pub fn epoch(&self) -> Epoch {
+		self.epoch
+	}
+
+	/// Number of committees at each slot of the epoch.
+	pub fn committees_per_slot(&self) -> u64 {
+		self.committees_per_slot
+	}
+
+	/// Get the committee at `slot` with `index`.
+	pub fn committee<C: Config>(&self, slot: Slot, index: u64) -> Option<&[ValidatorIndex]> {
+		if beacon::utils::epoch_of_slot::<C>(slot) != self.epoch ||
+			index >= self.committees_per_slot
+		{
+			return None
+		}
+
+		let count = self.committees_per_slot * C::slots_per_epoch();
+		let index = (slot % C::slots_per_epoch()).as_u64() * self.committees_per_slot + index;
+		let len = self.shuffling.len() as u64;
+		let start = (len * index) / count;
+		let end = (len * (index + 1)) / count;
+		Some(&self.shuffling[start as usize..end as usize])
+	}
+
+	/// Get the sorted attesting indices of attestation data with the given
+	/// aggregation bits.
+	pub fn attesting_indices<C: Config>(
+		&self,
+		data: &AttestationData,
+		bitfield: &[bool],
+	) -> Result<Vec<ValidatorIndex>, BeaconError> {
+		let committee = self.committee::<C>(data.slot, data.index)
+			.ok_or(BeaconError::EpochOutOfRange)?;
+		if committee.len() != bitfield.len() {
+			return Err(BeaconError::AttestationBitFieldInvalid)
+		}
+
+		let mut ret = committee.iter()
+			.zip(bitfield)
+			.filter(|(_, bit)| **bit)
+			.map(|(index, _)| *index)
+			.collect::<Vec<_>>();
+		ret.sort();
+		Ok(ret)
+	}
+
+	/// Find the committee assignment of a validator in the epoch.
+	pub fn assignment<C: Config>(&self, validator_index: ValidatorIndex) -> Option<CommitteeAssignment> {
+		let start_slot = beacon::utils::start_slot_of_epoch::<C>(self.epoch);
+		for slot in (0..C::slots_per_epoch()).map(|offset| start_slot + offset) {
+			for index in 0..self.committees_per_slot {
+				let committee = self.committee::<C>(slot, index)?;
+				if committee.contains(&validator_index) {
+					return Some(CommitteeAssignment {
+						validators: committee.to_vec(),
+						index, slot,
+					})
+				}
+			}
+		}
+		None
+	}
+}
+
+struct Inner {
+	committees: HashMap<(Epoch, H256), Arc<EpochCommittees>>,
+	order: VecDeque<(Epoch, H256)>,
+	capacity: usize,
+}
+
+/// Cache of beacon committees keyed by epoch and attester seed, so that the
+/// shuffling is computed once per epoch and shared by pool validation, the
+/// fork choice and validator duties. Forks with the same seed share
+/// committees.
+#[derive(Clone)]
+pub struct CommitteeCache(Arc<Mutex<Inner>>);
+
+impl CommitteeCache {
+	/// Create a new cache holding at most `capacity` epochs.
+	pub fn new(capacity: usize) -> Self {
+		Self(Arc::new(Mutex::new(Inner {
+			committees: HashMap::new(),
+			order: VecDeque::new(),
+			capacity,
+		})))
+	}
+
+	/// Get cached committees.
+	pub fn get(&self, epoch: Epoch, seed: &H256) -> Option<Arc<EpochCommittees>> {
+		let inner = self.0.lock().expect("Lock is poisoned");
+		inner.committees.get(&(epoch, *seed)).cloned()
+	}
+
+	/// Get the committees of `epoch` using the given state, which must be
+	/// in the previous, current or next epoch. The shuffling is computed
+	/// and cached on a miss.
+	pub fn committees<C: Config>(
+		&self,
+		state: &mut BeaconState<C>,
+		epoch: Epoch,
+	) -> Result<Arc<EpochCommittees>, Error> {
+		let executive = BeaconExecutive::new(state);
+		if epoch > executive.current_epoch() + 1 || epoch < executive.previous_epoch() {
+			return Err(BeaconError::EpochOutOfRange.into())
+		}
+
+		let seed = executive.seed(epoch, C::domain_beacon_attester());
+		if let Some(committees) = self.get(epoch, &seed) {
+			return Ok(committees)
+		}
+
+		let indices = executive.active_validator_indices(epoch);
+		let committees = Arc::new(EpochCommittees {
+			epoch,
+			committees_per_slot: executive.committee_count_at_slot(
+				beacon::utils::start_slot_of_epoch::<C>(epoch)
+			),
+			shuffling: beacon::utils::compute_shuffled_indices::<C>(&indices, seed)?,
+		});
+
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+		if inner.committees.insert((epoch, seed), committees.clone()).is_none() {
+			inner.order.push_back((epoch, seed));
+		}
+		while inner.order.len() > inner.capacity {
+			if let Some(key) = inner.order.pop_front() {
+				inner.committees.remove(&key);
+			}
+		}
+
+		Ok(committees)
+	}
+}
+
+impl Default for CommitteeCache {
+	fn default() -> Self {
+		Self::new(8)
+	}
+}
@@ -0,0 +1,216 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Duty history of local validators and proposer lookahead.
+
+use beacon::primitives::{Epoch, Slot, ValidatorId, ValidatorIndex};
+use beacon::ProposerAssignment;
+use serde::{Serialize, Deserialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+/// Number of epochs of duty history kept.
+pub const HISTORY_EPOCHS: u64 = 1024;
+
+#[derive(Debug)]
+pub enum Error {
+	Io(std::io::Error),
+	Json(serde_json::Error),
+}
+
+impl From<std::io::Error> for Error {
+	fn from(error: std::io::Error) -> Error {
+		Error::Io(error)
+	}
+}
+
+impl From<serde_json::Error> for Error {
+	fn from(error: serde_json::Error) -> Error {
+		Error::Json(error)
+	}
+}
+
+/// Duties of a validator in an epoch.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct EpochDuty {
+	/// Public key of the validator.
+	pub validator: ValidatorId,
+	/// Index of the validator.
+	pub validator_index: ValidatorIndex,
+	/// Epoch of the duties.
+	pub epoch: Epoch,
+	/// Slot the validator attests at.
+	pub attestation_slot: Option<Slot>,
+	/// Committee index the validator attests in.
+	pub committee_index: Option<u64>,
+	/// Slots the validator proposes at.
+	pub proposal_slots: Vec<Slot>,
+}
+
+/// Proposer of a slot.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+pub struct SlotProposer {
+	/// Slot of the proposal.
+	pub slot: Slot,
+	/// Index of the proposing validator.
+	pub validator_index: ValidatorIndex,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct Persisted {
+	duties: Vec<EpochDuty>,
+	lookahead: Vec<SlotProposer>,
+}
+
+struct Inner {
+	path: Option<PathBuf>,
+	duties: BTreeMap<(Epoch, ValidatorId), EpochDuty>,
+	lookahead: BTreeMap<Slot, ValidatorIndex>,
+}
+
+impl Inner {
+	fn persist(&self) -> Result<(), Error> {
+		let path = match self.path {
+			Some(ref path) => path,
+			None => return Ok(()),
+		};
+
+		let persisted = Persisted {
+			duties: self.duties.values().cloned().collect(),
+			lookahead: self.lookahead.iter().map(|(slot, validator_index)| SlotProposer {
+				slot: *slot,
+				validator_index: *validator_index,
+			}).collect(),
+		};
+
+		// Write to a temporary file first so that a crash never leaves a
+		// truncated history behind.
+		let tmp_path = path.with_extension("tmp");
+		serde_json::to_writer(File::create(&tmp_path)?, &persisted)?;
+		fs::rename(tmp_path, path)?;
+		Ok(())
+	}
+}
+
+/// Duty history of local validators, together with the proposer lookahead
+/// of the current and next epoch. Optionally persisted to a file.
+#[derive(Clone)]
+pub struct DutyHistory(Arc<Mutex<Inner>>);
+
+impl DutyHistory {
+	/// Create a new in-memory duty history.
+	pub fn new() -> Self {
+		Self(Arc::new(Mutex::new(Inner {
+			path: None,
+			duties: BTreeMap::new(),
+			lookahead: BTreeMap::new(),
+		})))
+	}
+
+	/// Open a duty history persisted at `path`, creating it if it does not
+	/// exist yet.
+	pub fn open(path: PathBuf) -> Result<Self, Error> {
+		let persisted = if path.exists() {
+			serde_json::from_reader::<_, Persisted>(BufReader::new(File::open(&path)?))?
+		} else {
+			Persisted::default()
+		};
+
+		Ok(Self(Arc::new(Mutex::new(Inner {
+			path: Some(path),
+			duties: persisted.duties.into_iter()
+				.map(|duty| ((duty.epoch, duty.validator.clone()), duty))
+				.collect(),
+			lookahead: persisted.lookahead.into_iter()
+				.map(|proposer| (proposer.slot, proposer.validator_index))
+				.collect(),
+		}))))
+	}
+
+	/// Record duties computed for an epoch, and the proposer lookahead
+	/// starting at that epoch.
+	pub fn record(
+		&self,
+		epoch: Epoch,
+		duties: Vec<EpochDuty>,
+		proposers: Vec<ProposerAssignment>,
+	) -> Result<(), Error> {
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+
+		for duty in duties {
+			inner.duties.insert((duty.epoch, duty.validator.clone()), duty);
+		}
+		let min_epoch = epoch.saturating_sub(HISTORY_EPOCHS);
+		inner.duties = inner.duties.split_off(&(min_epoch, ValidatorId::default()));
+
+		inner.lookahead.clear();
+		for proposer in proposers {
+			inner.lookahead.insert(proposer.slot, proposer.validator_index);
+		}
+
+		inner.persist()
+	}
+
+	/// Recorded duties of a validator, oldest first.
+	pub fn history(&self, validator: &ValidatorId) -> Vec<EpochDuty> {
+		let inner = self.0.lock().expect("Lock is poisoned");
+		inner.duties.values()
+			.filter(|duty| &duty.validator == validator)
+			.cloned()
+			.collect()
+	}
+
+	/// Proposer lookahead, ordered by slot.
+	pub fn lookahead(&self) -> Vec<SlotProposer> {
+		let inner = self.0.lock().expect("Lock is poisoned");
+		inner.lookahead.iter().map(|(slot, validator_index)| SlotProposer {
+			slot: *slot,
+			validator_index: *validator_index,
+		}).collect()
+	}
+
+	/// Next known proposal slot of a validator at or after `current_slot`.
+	pub fn next_proposal(&self, validator: &ValidatorId, current_slot: Slot) -> Option<Slot> {
+		let inner = self.0.lock().expect("Lock is poisoned");
+		inner.duties.values()
+			.filter(|duty| &duty.validator == validator)
+			.flat_map(|duty| duty.proposal_slots.iter().cloned())
+			.filter(|slot| *slot >= current_slot)
+			.min()
+	}
+
+	/// Proposals of local validators after `current_slot`, within `distance`
+	/// slots of it, ordered by slot.
+	pub fn upcoming_proposals(&self, current_slot: Slot, distance: u64) -> Vec<(Slot, ValidatorId)> {
+		let inner = self.0.lock().expect("Lock is poisoned");
+		let mut proposals = inner.duties.values()
+			.flat_map(|duty| duty.proposal_slots.iter().map(move |slot| (*slot, duty.validator.clone())))
+			.filter(|(slot, _)| *slot > current_slot && *slot <= current_slot + distance)
+			.collect::<Vec<_>>();
+		proposals.sort();
+		proposals.dedup();
+		proposals
+	}
+}
+
+impl Default for DutyHistory {
+	fn default() -> Self {
+		Self::new()
+	}
+}
@@ -0,0 +1,218 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Export and import of finalized chain history as era files.
+//!
+//! An era file holds the canonical blocks of `slots_per_historical_root`
+//! slots, followed by the post-state of the last of them. It is a sequence
+//! of records, each a `[type: u16][length: u32][reserved: u16]` little
+//! endian header followed by an SSZ payload, starting with a version
+//! record of empty payload.
+
+use beacon::{BeaconState, Config};
+use beacon::primitives::H256;
+use beacon::types::BeaconBlock;
+use blockchain::Block as BlockT;
+use blockchain::backend::{Store, ChainQuery};
+use blockchain::import::SharedBlockImporter;
+use bm_le::tree_root;
+use log::*;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use crate::{Block, StateExternalities};
+
+/// Type of the version record.
+pub const RECORD_VERSION: u16 = 0x3265;
+/// Type of block records.
+pub const RECORD_BLOCK: u16 = 0x0001;
+/// Type of state records.
+pub const RECORD_STATE: u16 = 0x0002;
+
+/// Era file errors.
+#[derive(Debug)]
+pub enum Error {
+	/// Io error.
+	Io(std::io::Error),
+	/// Backend error.
+	Backend(String),
+	/// Importing a block failed.
+	Import(String),
+	/// A record could not be parsed.
+	InvalidRecord,
+	/// A state record does not match the block before it.
+	StateMismatch { expected: H256, actual: H256 },
+}
+
+impl From<std::io::Error> for Error {
+	fn from(error: std::io::Error) -> Error {
+		Error::Io(error)
+	}
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{:?}", self)
+	}
+}
+
+/// File name of an era.
+pub fn era_file_name(era: u64) -> String {
+	format!("shasper-{:05}.era", era)
+}
+
+fn write_record<W: Write>(writer: &mut W, record_type: u16, payload: &[u8]) -> Result<(), Error> {
+	writer.write_all(&record_type.to_le_bytes())?;
+	writer.write_all(&(payload.len() as u32).to_le_bytes())?;
+	writer.write_all(&0u16.to_le_bytes())?;
+	writer.write_all(payload)?;
+	Ok(())
+}
+
+fn read_record<R: Read>(reader: &mut R) -> Result<Option<(u16, Vec<u8>)>, Error> {
+	let mut header = [0u8; 8];
+	match reader.read_exact(&mut header) {
+		Ok(()) => (),
+		Err(ref e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+		Err(e) => return Err(e.into()),
+	}
+
+	let record_type = u16::from_le_bytes([header[0], header[1]]);
+	let length = u32::from_le_bytes([header[2], header[3], header[4], header[5]]);
+	let mut payload = vec![0u8; length as usize];
+	reader.read_exact(&mut payload)?;
+	Ok(Some((record_type, payload)))
+}
+
+struct EraWriter {
+	era: u64,
+	writer: BufWriter<File>,
+	last: H256,
+}
+
+/// Export the canonical blocks up to the finalized block of the head state
+/// into era files in `dir`, each followed by the state of its last block.
+/// Returns the number of written files.
+pub fn export_eras<B, C>(backend: &B, dir: &Path) -> Result<usize, Error> where
+	B: ChainQuery + Store<Block=Block<C>>,
+	B::State: StateExternalities<Config=C>,
+	C: Config,
+{
+	let backend_error = |e: B::Error| Error::Backend(format!("{}", e));
+	let finalized = backend.state_at(&backend.head()).map_err(backend_error)?
+		.state().finalized_checkpoint.root;
+	if finalized == H256::default() {
+		return Ok(0)
+	}
+	let finalized_depth = backend.depth_at(&finalized).map_err(backend_error)?;
+
+	fs::create_dir_all(dir)?;
+	let finish = |current: EraWriter| -> Result<(), Error> {
+		let mut writer = current.writer;
+		let state = backend.state_at(&current.last).map_err(backend_error)?;
+		write_record(&mut writer, RECORD_STATE, &ssz::Encode::encode(state.state()))?;
+		writer.flush()?;
+		Ok(())
+	};
+
+	let mut written = 0;
+	let mut current: Option<EraWriter> = None;
+	for depth in 1..=finalized_depth {
+		let id = backend.lookup_canon_depth(depth).map_err(backend_error)?
+			.ok_or_else(|| Error::Backend(format!("Missing canonical block at depth {}", depth)))?;
+		let block = backend.block_at(&id).map_err(backend_error)?;
+		let era = (block.0.slot / C::slots_per_historical_root()).as_u64();
+
+		if current.as_ref().map(|current| current.era != era).unwrap_or(true) {
+			if let Some(current) = current.take() {
+				finish(current)?;
+			}
+			let mut writer = BufWriter::new(File::create(dir.join(era_file_name(era)))?);
+			write_record(&mut writer, RECORD_VERSION, &[])?;
+			current = Some(EraWriter { era, writer, last: id });
+			written += 1;
+		}
+
+		let current = current.as_mut().expect("Era writer is set above; qed");
+		write_record(&mut current.writer, RECORD_BLOCK, &ssz::Encode::encode(&block))?;
+		current.last = id;
+	}
+	if let Some(current) = current.take() {
+		finish(current)?;
+	}
+
+	info!("Exported {} finalized blocks into {} era files", finalized_depth, written);
+	Ok(written)
+}
+
+/// Era files in `dir`, in order.
+pub fn era_files(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+	let mut files = fs::read_dir(dir)?
+		.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+		.filter(|path| path.extension().map(|ext| ext == "era").unwrap_or(false))
+		.collect::<Vec<_>>();
+	files.sort();
+	Ok(files)
+}
+
+/// Import the blocks of all era files in `dir` through the importer,
+/// skipping already known blocks. States recorded in the files are checked
+/// against the state root of the block before them. Returns the number of
+/// imported blocks.
+pub fn import_eras<B, I, C>(backend: &B, importer: &I, dir: &Path) -> Result<usize, Error> where
+	B: ChainQuery + Store<Block=Block<C>>,
+	I: SharedBlockImporter<Block=Block<C>>,
+	I::Error: core::fmt::Debug,
+	C: Config,
+{
+	let mut imported = 0;
+	for path in era_files(dir)? {
+		let mut reader = BufReader::new(File::open(&path)?);
+		match read_record(&mut reader)? {
+			Some((RECORD_VERSION, _)) => (),
+			_ => return Err(Error::InvalidRecord),
+		}
+
+		let mut last_state_root = None;
+		while let Some((record_type, payload)) = read_record(&mut reader)? {
+			match record_type {
+				RECORD_BLOCK => {
+					let block = Block(<BeaconBlock<C> as ssz::Decode>::decode(&payload)
+						.map_err(|_| Error::InvalidRecord)?);
+					last_state_root = Some(block.0.state_root);
+					if backend.contains(&block.id()).map_err(|e| Error::Backend(format!("{}", e)))? {
+						continue
+					}
+					importer.import_block(block).map_err(|e| Error::Import(format!("{:?}", e)))?;
+					imported += 1;
+				},
+				RECORD_STATE => {
+					let state = <BeaconState<C> as ssz::Decode>::decode(&payload)
+						.map_err(|_| Error::InvalidRecord)?;
+					let actual = tree_root::<C::Digest, _>(&state);
+					let expected = last_state_root.ok_or(Error::InvalidRecord)?;
+					if actual != expected {
+						return Err(Error::StateMismatch { expected, actual })
+					}
+				},
+				_ => trace!("Skipping era record of unknown type {}", record_type),
+			}
+		}
+		info!("Imported era file {}", path.display());
+	}
+
+	Ok(imported)
+}
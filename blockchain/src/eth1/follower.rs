@@ -0,0 +1,313 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Eth1 deposit contract follower over JSON-RPC.
+
+use beacon::{Config, DepositTree};
+use beacon::primitives::{H256, ValidatorId, Signature};
+use beacon::types::{Deposit, DepositData, Eth1Data};
+use core::convert::TryInto;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::{Arc, Mutex};
+use serde_json::{json, Value};
+use log::*;
+use super::{Error, Eth1Provider};
+
+/// Topic of the deposit contract `DepositEvent(bytes,bytes,bytes,bytes,bytes)` log.
+pub const DEPOSIT_EVENT_TOPIC: &str =
+	"0x649bbc62d0e31342afea4e5cd82d4049e7e1ee912fc0889aa790803be39038c5";
+/// Default number of eth1 blocks to stay behind the eth1 head.
+pub const ETH1_FOLLOW_DISTANCE: u64 = 1024;
+/// Maximum number of eth1 blocks queried for logs at once.
+pub const LOG_QUERY_BLOCKS: u64 = 1000;
+/// Number of followed eth1 blocks kept as voting candidates.
+pub const CANDIDATE_BLOCKS: usize = 1024;
+
+/// Configuration of the eth1 follower.
+#[derive(Clone, Debug)]
+pub struct Eth1FollowerConfig {
+	/// HTTP JSON-RPC endpoint of the eth1 node.
+	pub endpoint: String,
+	/// Address of the deposit contract.
+	pub deposit_contract: String,
+	/// Eth1 block at which the deposit contract was deployed.
+	pub deploy_block: u64,
+	/// Number of eth1 blocks to stay behind the eth1 head.
+	pub follow_distance: u64,
+}
+
+#[derive(Clone, Debug)]
+struct Eth1Block {
+	hash: H256,
+	timestamp: u64,
+	deposit_count: u64,
+}
+
+struct Inner<C: Config> {
+	deposits: Vec<DepositData>,
+	tree: DepositTree<C>,
+	blocks: BTreeMap<u64, Eth1Block>,
+	next_log_block: u64,
+}
+
+/// Eth1 provider following the deposit contract of an eth1 node. Deposit
+/// logs and followed block hashes are cached, and `update` must be called
+/// regularly to catch up with the eth1 chain.
+pub struct Eth1Follower<C: Config> {
+	config: Eth1FollowerConfig,
+	fallback: Eth1Data,
+	inner: Arc<Mutex<Inner<C>>>,
+}
+
+impl<C: Config> Clone for Eth1Follower<C> {
+	fn clone(&self) -> Self {
+		Self {
+			config: self.config.clone(),
+			fallback: self.fallback.clone(),
+			inner: self.inner.clone(),
+		}
+	}
+}
+
+impl<C: Config> Eth1Follower<C> {
+	/// Create a new follower. `fallback` is voted for until a followed
+	/// eth1 block is available, usually the genesis eth1 data.
+	pub fn new(config: Eth1FollowerConfig, fallback: Eth1Data) -> Self {
+		let next_log_block = config.deploy_block;
+		Self {
+			config, fallback,
+			inner: Arc::new(Mutex::new(Inner {
+				deposits: Vec::new(),
+				tree: DepositTree::new(),
+				blocks: BTreeMap::new(),
+				next_log_block,
+			})),
+		}
+	}
+
+	/// Number of deposits seen so far.
+	pub fn deposit_count(&self) -> u64 {
+		self.inner.lock().expect("Lock is poisoned").deposits.len() as u64
+	}
+
+	/// Catch up with the eth1 chain, fetching new deposit logs up to the
+	/// followed block.
+	pub fn update(&self) -> Result<(), Error> {
+		let head = parse_quantity(&self.call("eth_blockNumber", json!([]))?)?;
+		let follow_block = match head.checked_sub(self.config.follow_distance) {
+			Some(follow_block) => follow_block,
+			None => return Ok(()),
+		};
+
+		let mut next_log_block = self.inner.lock().expect("Lock is poisoned").next_log_block;
+		while next_log_block <= follow_block {
+			let to_block = core::cmp::min(follow_block, next_log_block + LOG_QUERY_BLOCKS - 1);
+			let logs = self.call("eth_getLogs", json!([{
+				"fromBlock": format!("0x{:x}", next_log_block),
+				"toBlock": format!("0x{:x}", to_block),
+				"address": self.config.deposit_contract,
+				"topics": [DEPOSIT_EVENT_TOPIC],
+			}]))?;
+
+			let mut inner = self.inner.lock().expect("Lock is poisoned");
+			for log in logs.as_array().ok_or_else(|| invalid("logs"))? {
+				let data = parse_bytes(log.get("data").ok_or_else(|| invalid("log data"))?)?;
+				let (index, deposit) = parse_deposit_log(&data)?;
+				if index != inner.deposits.len() as u64 {
+					return Err(Error::Rpc(format!(
+						"Deposit log index {} out of order, expected {}", index, inner.deposits.len()
+					)))
+				}
+				inner.tree.push(&deposit);
+				inner.deposits.push(deposit);
+			}
+			next_log_block = to_block + 1;
+			inner.next_log_block = next_log_block;
+		}
+
+		let block = self.call(
+			"eth_getBlockByNumber", json!([format!("0x{:x}", follow_block), false]),
+		)?;
+		let hash = parse_h256(block.get("hash").ok_or_else(|| invalid("block hash"))?)?;
+		let timestamp = parse_quantity(
+			block.get("timestamp").ok_or_else(|| invalid("block timestamp"))?
+		)?;
+
+		let mut inner = self.inner.lock().expect("Lock is poisoned");
+		let deposit_count = inner.deposits.len() as u64;
+		inner.blocks.insert(follow_block, Eth1Block { hash, timestamp, deposit_count });
+		while inner.blocks.len() > CANDIDATE_BLOCKS {
+			let first = *inner.blocks.keys().next().expect("Blocks are not empty; qed");
+			inner.blocks.remove(&first);
+		}
+		debug!("Followed eth1 block {} ({}), {} deposits", follow_block, hash, deposit_count);
+
+		Ok(())
+	}
+
+	fn call(&self, method: &str, params: Value) -> Result<Value, Error> {
+		let body = json!({
+			"jsonrpc": "2.0",
+			"id": 1,
+			"method": method,
+			"params": params,
+		}).to_string();
+
+		let response = post(&self.config.endpoint, body.as_bytes())?;
+		let mut response = serde_json::from_slice::<Value>(&response)
+			.map_err(|e| Error::Rpc(format!("{}", e)))?;
+		if let Some(error) = response.get("error") {
+			return Err(Error::Rpc(format!("{}", error)))
+		}
+		response.get_mut("result").map(Value::take).ok_or_else(|| invalid("result"))
+	}
+}
+
+impl<C: Config> Eth1Provider<C> for Eth1Follower<C> {
+	fn eth1_data(&self, timestamp: u64) -> Eth1Data {
+		let inner = self.inner.lock().expect("Lock is poisoned");
+		let block = match inner.blocks.values().rev().find(|b| b.timestamp <= timestamp) {
+			Some(block) => block.clone(),
+			None => return self.fallback.clone(),
+		};
+		if block.deposit_count < self.fallback.deposit_count {
+			return self.fallback.clone()
+		}
+
+		Eth1Data {
+			deposit_root: inner.tree.root_at(block.deposit_count)
+				.expect("Followed blocks only count known deposits; qed"),
+			deposit_count: block.deposit_count,
+			block_hash: block.hash,
+		}
+	}
+
+	fn deposits(
+		&self,
+		from: u64,
+		to: u64,
+		deposit_count: u64,
+	) -> Result<Vec<Deposit>, Error> {
+		let inner = self.inner.lock().expect("Lock is poisoned");
+		if from > to || to > deposit_count || deposit_count > inner.deposits.len() as u64 {
+			return Err(Error::DepositsUnavailable)
+		}
+
+		(from..to).map(|index| {
+			Ok(Deposit {
+				proof: inner.tree.proof_at(index, deposit_count)
+					.ok_or(Error::InvalidProof)?
+					.try_into().map_err(|_| Error::InvalidProof)?,
+				data: inner.deposits[index as usize].clone(),
+			})
+		}).collect()
+	}
+}
+
+fn invalid(what: &str) -> Error {
+	Error::Rpc(format!("Invalid {} in response", what))
+}
+
+fn parse_quantity(value: &Value) -> Result<u64, Error> {
+	let value = value.as_str().ok_or_else(|| invalid("quantity"))?;
+	let value = if value.starts_with("0x") { &value[2..] } else { value };
+	u64::from_str_radix(value, 16).map_err(|_| invalid("quantity"))
+}
+
+fn parse_bytes(value: &Value) -> Result<Vec<u8>, Error> {
+	let value = value.as_str().ok_or_else(|| invalid("bytes"))?;
+	let value = if value.starts_with("0x") { &value[2..] } else { value };
+	hex::decode(value).map_err(|_| invalid("bytes"))
+}
+
+fn parse_h256(value: &Value) -> Result<H256, Error> {
+	let bytes = parse_bytes(value)?;
+	if bytes.len() != 32 {
+		return Err(invalid("hash"))
+	}
+	Ok(H256::from_slice(&bytes))
+}
+
+/// Parse the ABI encoded data of a deposit log, consisting of the dynamic
+/// byte fields pubkey, withdrawal credentials, amount, signature and index.
+fn parse_deposit_log(data: &[u8]) -> Result<(u64, DepositData), Error> {
+	let word = |offset: usize| -> Result<usize, Error> {
+		let word = data.get(offset..(offset + 32)).ok_or_else(|| invalid("deposit log"))?;
+		if word[..24].iter().any(|b| *b != 0) {
+			return Err(invalid("deposit log"))
+		}
+		let mut bytes = [0u8; 8];
+		bytes.copy_from_slice(&word[24..]);
+		Ok(u64::from_be_bytes(bytes) as usize)
+	};
+	let field = |index: usize, len: usize| -> Result<&[u8], Error> {
+		let offset = word(index * 32)?;
+		if word(offset)? != len {
+			return Err(invalid("deposit log"))
+		}
+		data.get((offset + 32)..(offset + 32 + len)).ok_or_else(|| invalid("deposit log"))
+	};
+	let le_u64 = |bytes: &[u8]| {
+		let mut value = [0u8; 8];
+		value.copy_from_slice(bytes);
+		u64::from_le_bytes(value)
+	};
+
+	let deposit = DepositData {
+		pubkey: ValidatorId::from_slice(field(0, 48)?),
+		withdrawal_credentials: H256::from_slice(field(1, 32)?),
+		amount: le_u64(field(2, 8)?).into(),
+		signature: Signature::from_slice(field(3, 96)?),
+	};
+	let index = le_u64(field(4, 8)?);
+
+	Ok((index, deposit))
+}
+
+fn post(url: &str, body: &[u8]) -> Result<Vec<u8>, Error> {
+	if !url.starts_with("http://") {
+		return Err(Error::Rpc(format!("Unsupported endpoint {}", url)))
+	}
+
+	let rest = &url[7..];
+	let (host, path) = match rest.find('/') {
+		Some(index) => (&rest[..index], &rest[index..]),
+		None => (rest, "/"),
+	};
+	let address = if host.contains(':') {
+		host.to_string()
+	} else {
+		format!("{}:80", host)
+	};
+
+	let io_error = |e: std::io::Error| Error::Rpc(format!("{}", e));
+	let mut stream = TcpStream::connect(address).map_err(io_error)?;
+	write!(
+		stream,
+		"POST {} HTTP/1.0\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+		path, host, body.len(),
+	).map_err(io_error)?;
+	stream.write_all(body).map_err(io_error)?;
+	let mut response = Vec::new();
+	stream.read_to_end(&mut response).map_err(io_error)?;
+
+	let header_end = response.windows(4)
+		.position(|w| w == b"\r\n\r\n")
+		.ok_or_else(|| invalid("http response"))?;
+	Ok(response[(header_end + 4)..].to_vec())
+}
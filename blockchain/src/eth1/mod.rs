@@ -0,0 +1,194 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Eth1 data providers used by block authoring.
+
+mod follower;
+
+pub use self::follower::{Eth1Follower, Eth1FollowerConfig, ETH1_FOLLOW_DISTANCE};
+
+use beacon::{Config, DepositTree};
+use beacon::primitives::{H256, ValidatorId, Signature, Slot, Gwei};
+use beacon::types::{Deposit, DepositData, SigningDepositData, Eth1Data};
+use core::convert::TryInto;
+use core::marker::PhantomData;
+use bm_le::tree_root;
+use crypto::bls;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Eth1 provider errors.
+pub enum Error {
+	/// Requested deposit range is not known to the provider.
+	DepositsUnavailable,
+	/// Deposit proof cannot be constructed.
+	InvalidProof,
+	/// Eth1 JSON-RPC request failed.
+	Rpc(String),
+}
+
+/// Source of eth1 data votes and deposits for block authoring.
+pub trait Eth1Provider<C: Config>: Send + Sync {
+	/// Eth1 data to vote for, given the eth1 timestamp of the voting period.
+	fn eth1_data(&self, timestamp: u64) -> Eth1Data;
+	/// Deposits with index in `[from, to)`, with proofs against the deposit
+	/// root of the first `deposit_count` deposits.
+	fn deposits(
+		&self,
+		from: u64,
+		to: u64,
+		deposit_count: u64,
+	) -> Result<Vec<Deposit>, Error>;
+}
+
+/// Eth1 timestamp used for voting in the eth1 voting period of `slot`.
+pub fn voting_period_timestamp<C: Config>(genesis_time: u64, slot: Slot) -> u64 {
+	let period_start_slot = slot - slot % C::slots_per_eth1_voting_period();
+	genesis_time + period_start_slot.as_u64() * C::seconds_per_slot()
+}
+
+/// Provider that always votes for the same eth1 data, and has no new deposits.
+pub struct StaticEth1Provider<C: Config> {
+	eth1_data: Eth1Data,
+	_marker: PhantomData<C>,
+}
+
+impl<C: Config> StaticEth1Provider<C> {
+	/// Create a new static provider.
+	pub fn new(eth1_data: Eth1Data) -> Self {
+		Self { eth1_data, _marker: PhantomData }
+	}
+}
+
+impl<C: Config> Eth1Provider<C> for StaticEth1Provider<C> {
+	fn eth1_data(&self, _timestamp: u64) -> Eth1Data {
+		self.eth1_data.clone()
+	}
+
+	fn deposits(
+		&self,
+		from: u64,
+		to: u64,
+		_deposit_count: u64,
+	) -> Result<Vec<Deposit>, Error> {
+		if from == to {
+			Ok(Vec::new())
+		} else {
+			Err(Error::DepositsUnavailable)
+		}
+	}
+}
+
+/// A deposit that appears on the mock eth1 chain at a given timestamp.
+#[derive(Debug, Clone)]
+pub struct ScheduledDeposit {
+	/// Eth1 timestamp at which the deposit becomes visible.
+	pub timestamp: u64,
+	/// Deposit data.
+	pub data: DepositData,
+}
+
+/// Mock eth1 chain serving a fixed deposit schedule, for devnets without a
+/// real eth1 chain.
+pub struct MockEth1Provider<C: Config> {
+	schedule: Vec<ScheduledDeposit>,
+	_marker: PhantomData<C>,
+}
+
+impl<C: Config> MockEth1Provider<C> {
+	/// Create a new mock provider. Deposits of the schedule are ordered by
+	/// timestamp. Deposits already included in genesis must come first, at
+	/// timestamp zero.
+	pub fn new(mut schedule: Vec<ScheduledDeposit>) -> Self {
+		schedule.sort_by_key(|d| d.timestamp);
+		Self { schedule, _marker: PhantomData }
+	}
+
+	/// Number of deposits visible at given timestamp.
+	pub fn deposit_count(&self, timestamp: u64) -> u64 {
+		self.schedule.iter().filter(|d| d.timestamp <= timestamp).count() as u64
+	}
+
+	fn deposit_datas(&self, count: u64) -> Vec<DepositData> {
+		self.schedule.iter()
+			.take(count as usize)
+			.map(|d| d.data.clone())
+			.collect()
+	}
+}
+
+impl<C: Config> Eth1Provider<C> for MockEth1Provider<C> {
+	fn eth1_data(&self, timestamp: u64) -> Eth1Data {
+		let deposit_count = self.deposit_count(timestamp);
+		let deposit_root = DepositTree::<C>::from_deposits(&self.deposit_datas(deposit_count)).root();
+
+		Eth1Data {
+			deposit_root,
+			deposit_count,
+			block_hash: C::hash(&[
+				&deposit_root[..],
+				&deposit_count.to_le_bytes()[..],
+			]),
+		}
+	}
+
+	fn deposits(
+		&self,
+		from: u64,
+		to: u64,
+		deposit_count: u64,
+	) -> Result<Vec<Deposit>, Error> {
+		if from > to || to > deposit_count ||
+			deposit_count > self.schedule.len() as u64
+		{
+			return Err(Error::DepositsUnavailable)
+		}
+
+		let datas = self.deposit_datas(deposit_count);
+		let tree = DepositTree::<C>::from_deposits(&datas);
+
+		(from..to).map(|index| {
+			Ok(Deposit {
+				proof: tree.proof(index)
+					.ok_or(Error::InvalidProof)?
+					.try_into().map_err(|_| Error::InvalidProof)?,
+				data: datas[index as usize].clone(),
+			})
+		}).collect()
+	}
+}
+
+/// Create a signed deposit data from a secret key.
+pub fn signed_deposit_data<C: Config>(
+	seckey: &bls::Secret,
+	withdrawal_credentials: H256,
+	amount: Gwei,
+) -> DepositData {
+	let pubkey = ValidatorId::from_slice(
+		&bls::Public::from_secret_key(seckey).as_bytes()[..]
+	);
+	let mut data = DepositData {
+		pubkey,
+		withdrawal_credentials,
+		amount,
+		signature: Default::default(),
+	};
+	data.signature = Signature::from_slice(&bls::Signature::new(
+		&tree_root::<C::Digest, _>(&SigningDepositData::from(data.clone()))[..],
+		beacon::genesis_domain(C::domain_deposit()),
+		seckey
+	).as_bytes()[..]);
+	data
+}
@@ -0,0 +1,104 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Voluntary exits of validators whose keys are managed by the node.
+
+use beacon::{BeaconExecutive, BeaconState, BLSConfig, Config, Transaction, Error as BeaconError};
+use beacon::primitives::{Epoch, Signature, ValidatorId, ValidatorIndex};
+use beacon::types::{SigningVoluntaryExit, VoluntaryExit};
+use bm_le::tree_root;
+use crypto::bls;
+use crate::pubkey::PubkeyCache;
+
+/// Voluntary exit errors.
+#[derive(Debug)]
+pub enum Error {
+	/// The validator is not in the state's registry.
+	UnknownValidator(ValidatorId),
+	/// The exit does not pass validation against the state.
+	Invalid(BeaconError),
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{:?}", self)
+	}
+}
+
+/// Index of the validator with the given public key in the state's
+/// registry, looked up in the pubkey cache.
+pub fn validator_index<C: Config>(
+	pubkeys: &PubkeyCache,
+	state: &BeaconState<C>,
+	pubkey: &ValidatorId,
+) -> Option<ValidatorIndex> {
+	pubkeys.index_of::<C>(state, pubkey)
+}
+
+/// Sign a voluntary exit of the validator at `validator_index` for `epoch`.
+pub fn sign_voluntary_exit<C: Config>(
+	state: &mut BeaconState<C>,
+	validator_index: ValidatorIndex,
+	epoch: Epoch,
+	seckey: &bls::Secret,
+) -> VoluntaryExit {
+	let executive = BeaconExecutive::new(state);
+	let domain = executive.domain(C::domain_voluntary_exit(), Some(epoch));
+	let mut exit = VoluntaryExit {
+		epoch,
+		validator_index,
+		signature: Default::default(),
+	};
+	exit.signature = Signature::from_slice(&bls::Signature::new(
+		&tree_root::<C::Digest, _>(&SigningVoluntaryExit::from(exit.clone()))[..],
+		domain,
+		seckey,
+	).as_bytes()[..]);
+	exit
+}
+
+/// Build a signed voluntary exit for the validator of `seckey`, valid
+/// against `state`. The exit epoch defaults to the state's current epoch.
+///
+/// The exit is validated as it would be in a block, so this fails if the
+/// validator is not active, already exiting, or has not yet served the
+/// persistent committee period.
+pub fn build_voluntary_exit<C: Config, BLS: BLSConfig>(
+	pubkeys: &PubkeyCache,
+	state: &mut BeaconState<C>,
+	seckey: &bls::Secret,
+	epoch: Option<Epoch>,
+) -> Result<VoluntaryExit, Error> {
+	let pubkey = ValidatorId::from_slice(
+		&bls::Public::from_secret_key(seckey).as_bytes()[..]
+	);
+	let validator_index = validator_index(pubkeys, state, &pubkey)
+		.ok_or(Error::UnknownValidator(pubkey))?;
+	let epoch = epoch.unwrap_or_else(|| BeaconExecutive::new(state).current_epoch());
+
+	let exit = sign_voluntary_exit(state, validator_index, epoch, seckey);
+	beacon::validate_transaction::<C, BLS>(state, &Transaction::VoluntaryExit(exit.clone()))
+		.map_err(Error::Invalid)?;
+	Ok(exit)
+}
+
+/// Whether the validator at `validator_index` has an exit initiated in
+/// `state`.
+pub fn is_exiting<C: Config>(state: &BeaconState<C>, validator_index: ValidatorIndex) -> bool {
+	state.validators.get(validator_index as usize)
+		.map(|validator| validator.exit_epoch != beacon::consts::FAR_FUTURE_EPOCH)
+		.unwrap_or(false)
+}
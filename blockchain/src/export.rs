@@ -0,0 +1,114 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+//! Export of canonical states as SSZ, for checkpoint sync of other clients.
+
+use beacon::{BeaconState, Config};
+use beacon::primitives::{H256, Slot};
+use blockchain::backend::{Store, ChainQuery};
+use bm_le::tree_root;
+use log::*;
+use ssz::Encode;
+use std::io::Write;
+use crate::{Block, StateExternalities};
+use crate::backend::CanonSlotQuery;
+
+/// Export errors.
+#[derive(Debug)]
+pub enum Error {
+	/// Io error.
+	Io(std::io::Error),
+	/// Backend error.
+	Backend(String),
+	/// Advancing the state to the requested slot failed.
+	Execution(String),
+	/// The requested slot is after the head.
+	SlotAfterHead { slot: Slot, head: Slot },
+}
+
+impl From<std::io::Error> for Error {
+	fn from(error: std::io::Error) -> Error {
+		Error::Io(error)
+	}
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{:?}", self)
+	}
+}
+
+/// Summary of an exported state.
+#[derive(Clone, Debug)]
+pub struct ExportedState {
+	/// Slot of the state.
+	pub slot: Slot,
+	/// Latest canonical block applied to the state.
+	pub block_root: H256,
+	/// Hash tree root of the state.
+	pub state_root: H256,
+	/// Size of the SSZ encoding.
+	pub size: usize,
+}
+
+/// Write the SSZ encoding of the canonical state at `slot` to `output`. The
+/// state is the post-state of the latest canonical block at or before
+/// `slot`, advanced through empty slots up to `slot`. States that are not
+/// stored are regenerated by the backend.
+pub fn export_state<B, C>(
+	backend: &B,
+	slot: Slot,
+	output: &mut dyn Write,
+) -> Result<ExportedState, Error> where
+	B: CanonSlotQuery + Store<Block=Block<C>>,
+	B::State: StateExternalities<Config=C>,
+	C: Config,
+{
+	let backend_error = |e: B::Error| Error::Backend(format!("{}", e));
+	let head = backend.block_at(&backend.head()).map_err(backend_error)?.0.slot;
+	if slot > head {
+		return Err(Error::SlotAfterHead { slot, head })
+	}
+
+	let mut block_slot = slot;
+	let block_root = loop {
+		if let Some(id) = backend.lookup_canon_slot(block_slot).map_err(backend_error)? {
+			break id
+		}
+		block_slot = block_slot.checked_sub(1u64)
+			.ok_or_else(|| Error::Backend("No canonical block found".to_string()))?;
+	};
+
+	let mut state: BeaconState<C> = backend.state_at(&block_root).map_err(backend_error)?
+		.state().clone();
+	if state.slot < slot {
+		beacon::initialize_block::<C>(&mut state, slot)
+			.map_err(|e| Error::Execution(format!("{:?}", e)))?;
+	}
+
+	let encoded = state.encode();
+	output.write_all(&encoded)?;
+	output.flush()?;
+
+	let exported = ExportedState {
+		slot,
+		block_root,
+		state_root: tree_root::<C::Digest, _>(&state),
+		size: encoded.len(),
+	};
+	info!("Exported state at slot {} on block {}, state root {}, {} bytes",
+		  slot, block_root, exported.state_root, exported.size);
+	Ok(exported)
+}
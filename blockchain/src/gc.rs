@@ -0,0 +1,192 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Garbage collection of abandoned forks, and cleanup of finalized states
+//! and blocks.
+
+use beacon::Config;
+use beacon::primitives::H256;
+use blockchain::Block as BlockT;
+use blockchain::backend::{Store, ChainQuery, ImportLock};
+use lmd_ghost::archive::{AncestorQuery, ForkChoiceView};
+use std::collections::BTreeSet;
+use log::*;
+use crate::{Block, StateExternalities};
+use crate::backend::{BranchPruning, StateStorage, Freezing};
+use crate::settings::LiveSettings;
+
+/// Default number of blocks a fork tip must be behind the head before the
+/// fork is considered abandoned.
+pub const ABANDONED_FORK_DEPTH: usize = 64;
+/// Number of canonical blocks behind the last scanned depth to re-scan for
+/// late forks.
+pub const RESCAN_DEPTH: usize = 64;
+
+/// Garbage collector removing forks that fell far behind the canonical
+/// chain, ahead of any finalization-based pruning.
+pub struct ForkPruner<B: Store> {
+	backend: B,
+	import_lock: ImportLock,
+	fork_choice: ForkChoiceView<B>,
+	settings: LiveSettings,
+	forks: BTreeSet<(usize, <B::Block as BlockT>::Identifier)>,
+	scanned_depth: Option<usize>,
+}
+
+impl<B> ForkPruner<B> where
+	B: ChainQuery + AncestorQuery + BranchPruning,
+	<B::Block as BlockT>::Identifier: Ord,
+{
+	/// Create a new fork pruner.
+	pub fn new(
+		backend: B,
+		import_lock: ImportLock,
+		fork_choice: ForkChoiceView<B>,
+		settings: LiveSettings,
+	) -> Self {
+		Self {
+			backend, import_lock, fork_choice, settings,
+			forks: BTreeSet::new(),
+			scanned_depth: None,
+		}
+	}
+
+	/// Find new forks off the canonical chain, and remove the abandoned
+	/// ones. Returns the number of removed blocks.
+	pub fn prune(&mut self) -> Result<usize, B::Error> {
+		// Hold the import lock so that no block or vote lands on a branch
+		// while it is being removed.
+		let _guard = self.import_lock.lock();
+
+		let head_depth = self.backend.depth_at(&self.backend.head())?;
+		let start_depth = self.scanned_depth
+			.map(|depth| depth.saturating_sub(RESCAN_DEPTH))
+			.unwrap_or(0);
+		for depth in start_depth..head_depth {
+			let canon = match self.backend.lookup_canon_depth(depth)? {
+				Some(canon) => canon,
+				None => continue,
+			};
+			for child in self.backend.children_at(&canon)? {
+				if !self.backend.is_canon(&child)? {
+					self.forks.insert((depth + 1, child));
+				}
+			}
+		}
+		self.scanned_depth = Some(head_depth);
+
+		let targets = self.fork_choice.vote_targets();
+		let abandoned_fork_depth = self.settings.abandoned_fork_depth();
+		let mut removed = 0;
+		for (depth, fork) in self.forks.clone() {
+			if !self.backend.contains(&fork)? || self.backend.is_canon(&fork)? {
+				self.forks.remove(&(depth, fork));
+				continue
+			}
+
+			let (tip_depth, branch) = self.branch(&fork, depth)?;
+			if tip_depth + abandoned_fork_depth > head_depth {
+				continue
+			}
+			if targets.iter().any(|target| branch.contains(target)) {
+				continue
+			}
+
+			let count = self.backend.remove_branch(&fork)?;
+			debug!("Removed abandoned fork {:?} of {} blocks", fork, count);
+			removed += count;
+			self.forks.remove(&(depth, fork));
+		}
+
+		if removed > 0 {
+			info!("Garbage collected {} blocks of abandoned forks", removed);
+		}
+		Ok(removed)
+	}
+
+	fn branch(
+		&self,
+		fork: &<B::Block as BlockT>::Identifier,
+		depth: usize,
+	) -> Result<(usize, Vec<<B::Block as BlockT>::Identifier>), B::Error> {
+		let mut tip_depth = depth;
+		let mut branch = Vec::new();
+		let mut queue = vec![(fork.clone(), depth)];
+		while let Some((id, depth)) = queue.pop() {
+			tip_depth = std::cmp::max(tip_depth, depth);
+			for child in self.backend.children_at(&id)? {
+				queue.push((child, depth + 1));
+			}
+			branch.push(id);
+		}
+		Ok((tip_depth, branch))
+	}
+}
+
+fn finalized_root<B, C>(backend: &B) -> Result<Option<H256>, B::Error> where
+	B: ChainQuery + Store<Block=Block<C>>,
+	B::State: StateExternalities<Config=C>,
+	C: Config,
+{
+	let finalized = backend.state_at(&backend.head())?.state().finalized_checkpoint.root;
+	Ok(if finalized == H256::default() { None } else { Some(finalized) })
+}
+
+/// Drop the states of blocks finalized by the head state that the storage
+/// mode of the backend does not keep. Returns the number of dropped states.
+pub fn prune_finalized_states<B, C>(
+	backend: &B,
+	import_lock: &ImportLock,
+) -> Result<usize, B::Error> where
+	B: StateStorage + Store<Block=Block<C>>,
+	B::State: StateExternalities<Config=C>,
+	C: Config,
+{
+	let finalized = match finalized_root(backend)? {
+		Some(finalized) => finalized,
+		None => return Ok(0),
+	};
+
+	let _guard = import_lock.lock();
+	let pruned = backend.prune_states(&finalized)?;
+	if pruned > 0 {
+		info!("Pruned {} finalized states", pruned);
+	}
+	Ok(pruned)
+}
+
+/// Move the blocks finalized by the head state into the cold storage of
+/// the backend. Returns the number of moved blocks.
+pub fn freeze_finalized_blocks<B, C>(
+	backend: &B,
+	import_lock: &ImportLock,
+) -> Result<usize, B::Error> where
+	B: ChainQuery + Freezing + Store<Block=Block<C>>,
+	B::State: StateExternalities<Config=C>,
+	C: Config,
+{
+	let finalized = match finalized_root(backend)? {
+		Some(finalized) => finalized,
+		None => return Ok(0),
+	};
+
+	let _guard = import_lock.lock();
+	let frozen = backend.freeze(&finalized)?;
+	if frozen > 0 {
+		info!("Moved {} finalized blocks to the freezer", frozen);
+	}
+	Ok(frozen)
+}
@@ -0,0 +1,583 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Validation of blocks and operations received on gossip, following the
+//! spec's gossip conditions, before they reach the import queue or the
+//! operation pool.
+
+use beacon::{BeaconExecutive, BeaconState, BLSConfig, Config};
+use beacon::primitives::{H256, Epoch, Slot, ValidatorIndex};
+use beacon::types::{
+	Attestation, BeaconBlock, IndexedAttestation, SignedAggregateAndProof, UnsealedBeaconBlock,
+};
+use bm_le::tree_root;
+use blockchain::backend::{Store, ChainQuery};
+use shasper_network::{
+	AttestationHandler, BlockHandler, BlockVerdict, GossipVerdict, compute_subnet_for_attestation,
+};
+use std::collections::{BTreeMap, HashSet};
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use log::*;
+use crate::{AdvancedStateCache, AttestationQueue, Block, Executor, SharedOperationPool, StateExternalities};
+use crate::checkpoint::{CheckpointCache, CheckpointCheck};
+use crate::clock::SlotClock;
+
+/// Number of slots during which an attestation may be propagated.
+pub const ATTESTATION_PROPAGATION_SLOT_RANGE: u64 = 32;
+/// Tolerated clock difference with peers when checking message slots.
+pub const MAXIMUM_GOSSIP_CLOCK_DISPARITY: Duration = Duration::from_millis(500);
+/// Maximum number of non-canonical ancestors walked when checking that a
+/// block descends from the finalized block.
+pub const MAX_FORK_DEPTH: usize = 64;
+
+/// Reasons for an attestation to fail gossip validation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AttestationError {
+	/// Attestation slot has not started yet, even allowing for clock
+	/// disparity.
+	FutureSlot { slot: Slot, current_slot: Slot },
+	/// Attestation slot is too old to be propagated.
+	PastSlot { slot: Slot, current_slot: Slot },
+	/// Attestation was received on the subnet of another committee.
+	WrongSubnet { expected: u64, received: u64 },
+	/// Attestation does not have exactly one aggregation bit set.
+	NotUnaggregated,
+	/// Attestation has custody bits set.
+	CustodyBitsSet,
+	/// Attestation slot is not in its target epoch.
+	TargetEpochMismatch,
+	/// Block voted for as head is not known.
+	UnknownHeadBlock(H256),
+	/// Target is not the epoch boundary block of the voted head.
+	InvalidTarget,
+	/// Target epoch is too far from the voted head to be checked.
+	EpochOutOfRange,
+	/// Committee index is out of range for the slot.
+	InvalidCommitteeIndex,
+	/// Aggregation bits do not match the committee size.
+	InvalidBitfield,
+	/// Attester already had an attestation for the target epoch validated.
+	AlreadySeen { validator: ValidatorIndex, epoch: Epoch },
+	/// Attestation signature is invalid.
+	InvalidSignature,
+	/// Aggregate does not have any aggregation bit set.
+	EmptyAggregate,
+	/// Aggregator is not a member of the aggregate committee.
+	AggregatorNotInCommittee(ValidatorIndex),
+	/// Selection proof does not select the aggregator.
+	NotAggregator(ValidatorIndex),
+	/// Selection proof is not a valid signature of the slot.
+	InvalidSelectionProof,
+	/// Aggregator signature of the aggregate and proof is invalid.
+	InvalidAggregatorSignature,
+	/// Aggregator already had an aggregate for the slot validated.
+	AggregatorAlreadySeen { aggregator: ValidatorIndex, slot: Slot },
+	/// State needed for validation is unavailable.
+	Unavailable(String),
+}
+
+impl AttestationError {
+	/// Whether the attestation is invalid, as opposed to being ignored
+	/// because it cannot be validated now or is redundant.
+	pub fn is_rejected(&self) -> bool {
+		match self {
+			AttestationError::FutureSlot { .. } |
+			AttestationError::PastSlot { .. } |
+			AttestationError::UnknownHeadBlock(_) |
+			AttestationError::EpochOutOfRange |
+			AttestationError::AlreadySeen { .. } |
+			AttestationError::AggregatorAlreadySeen { .. } |
+			AttestationError::Unavailable(_) => false,
+			_ => true,
+		}
+	}
+}
+
+/// Validators with a validated message, per target epoch for attesters
+/// and per slot for aggregators and proposers.
+#[derive(Default)]
+struct SeenAttesters<K>(BTreeMap<K, HashSet<ValidatorIndex>>);
+
+impl<K: Ord> SeenAttesters<K> {
+	fn contains(&self, validator: ValidatorIndex, key: K) -> bool {
+		self.0.get(&key).map(|seen| seen.contains(&validator)).unwrap_or(false)
+	}
+
+	/// Note a validator, returning false if it was already seen.
+	fn insert(&mut self, validator: ValidatorIndex, key: K) -> bool {
+		self.0.entry(key).or_insert_with(HashSet::new).insert(validator)
+	}
+
+	/// Forget validators noted before `key`.
+	fn prune(&mut self, key: K) {
+		self.0 = self.0.split_off(&key);
+	}
+}
+
+/// Validates unaggregated attestations and aggregates received on gossip,
+/// so that only attestations passing the spec's gossip conditions are added
+/// to the pool and forwarded.
+///
+/// Committees come from the executor committee cache, targets from the
+/// checkpoint cache, and the state of the voted head is advanced to the
+/// attestation epoch through the advanced state cache.
+#[derive(Clone)]
+pub struct AttestationValidator<C: Config, BLS: BLSConfig, B> {
+	backend: B,
+	executor: Executor<C, BLS>,
+	checkpoints: CheckpointCache,
+	states: AdvancedStateCache<C>,
+	clock: Arc<dyn SlotClock>,
+	seen: Arc<Mutex<SeenAttesters<Epoch>>>,
+	seen_aggregators: Arc<Mutex<SeenAttesters<Slot>>>,
+}
+
+impl<C: Config, BLS: BLSConfig, B> AttestationValidator<C, BLS, B> where
+	B: ChainQuery + Store<Block=Block<C>>,
+	B::State: StateExternalities<Config=C>,
+{
+	/// Create a new validator.
+	pub fn new(
+		backend: B,
+		executor: Executor<C, BLS>,
+		checkpoints: CheckpointCache,
+		states: AdvancedStateCache<C>,
+		clock: Arc<dyn SlotClock>,
+	) -> Self {
+		Self {
+			backend, executor, checkpoints, states, clock,
+			seen: Arc::new(Mutex::new(SeenAttesters::default())),
+			seen_aggregators: Arc::new(Mutex::new(SeenAttesters::default())),
+		}
+	}
+
+	/// Check the attestation data against the clock and the voted head, and
+	/// get the state at the attestation epoch together with the committee.
+	fn check_data(
+		&self,
+		attestation: &Attestation<C>,
+	) -> Result<(BeaconState<C>, Vec<ValidatorIndex>), AttestationError> {
+		let data = &attestation.data;
+		let current_slot = self.clock.now().unwrap_or_default();
+		let since_genesis = self.clock.since_genesis().unwrap_or_default();
+		if self.clock.slot_start(data.slot) > since_genesis + MAXIMUM_GOSSIP_CLOCK_DISPARITY {
+			return Err(AttestationError::FutureSlot { slot: data.slot, current_slot })
+		}
+		let propagation_end = self.clock.slot_start(
+			data.slot + ATTESTATION_PROPAGATION_SLOT_RANGE + 1
+		);
+		if propagation_end + MAXIMUM_GOSSIP_CLOCK_DISPARITY < since_genesis {
+			return Err(AttestationError::PastSlot { slot: data.slot, current_slot })
+		}
+
+		if attestation.custody_bits.iter().any(|bit| *bit) {
+			return Err(AttestationError::CustodyBitsSet)
+		}
+		let epoch = beacon::utils::epoch_of_slot::<C>(data.slot);
+		if data.target.epoch != epoch {
+			return Err(AttestationError::TargetEpochMismatch)
+		}
+
+		let unavailable = |e: crate::Error| AttestationError::Unavailable(format!("{:?}", e));
+		if !self.backend.contains(&data.beacon_block_root)
+			.map_err(|e| AttestationError::Unavailable(format!("{}", e)))?
+		{
+			return Err(AttestationError::UnknownHeadBlock(data.beacon_block_root))
+		}
+		match self.checkpoints.check(&self.backend, &data.beacon_block_root, data)
+			.map_err(unavailable)?
+		{
+			CheckpointCheck::WrongTarget => return Err(AttestationError::InvalidTarget),
+			CheckpointCheck::EpochOutOfRange => return Err(AttestationError::EpochOutOfRange),
+			CheckpointCheck::Valid | CheckpointCheck::WrongSource => (),
+		}
+
+		let mut state = self.states.get_or_advance(&self.backend, &data.beacon_block_root, epoch)
+			.map_err(unavailable)?;
+		let committees = self.executor.committees().committees(&mut state, epoch)
+			.map_err(unavailable)?;
+		let committee = committees.committee::<C>(data.slot, data.index)
+			.ok_or(AttestationError::InvalidCommitteeIndex)?
+			.to_vec();
+		if committee.len() != attestation.aggregation_bits.len() {
+			return Err(AttestationError::InvalidBitfield)
+		}
+
+		Ok((state, committee))
+	}
+
+	/// Validate an unaggregated attestation received on the given subnet,
+	/// or on the global attestation topic if `subnet` is `None`. Returns
+	/// the index of the attester.
+	pub fn validate(
+		&self,
+		attestation: &Attestation<C>,
+		subnet: Option<u64>,
+	) -> Result<ValidatorIndex, AttestationError> {
+		let data = &attestation.data;
+		if attestation.aggregation_bits.iter().filter(|bit| **bit).count() != 1 {
+			return Err(AttestationError::NotUnaggregated)
+		}
+
+		if let Some(subnet) = subnet {
+			let expected = compute_subnet_for_attestation(data.index);
+			if expected != subnet {
+				return Err(AttestationError::WrongSubnet { expected, received: subnet })
+			}
+		}
+
+		let (mut state, committee) = self.check_data(attestation)?;
+		let epoch = data.target.epoch;
+		let validator = committee.iter()
+			.zip(attestation.aggregation_bits.iter())
+			.find(|(_, bit)| **bit)
+			.map(|(validator, _)| *validator)
+			.ok_or(AttestationError::NotUnaggregated)?;
+
+		if self.seen.lock().expect("Lock is poisoned").contains(validator, epoch) {
+			return Err(AttestationError::AlreadySeen { validator, epoch })
+		}
+
+		let indexed = IndexedAttestation {
+			data: data.clone(),
+			signature: attestation.signature,
+			custody_bit_0_indices: vec![validator].into(),
+			custody_bit_1_indices: Vec::new().into(),
+		};
+		if !BeaconExecutive::new(&mut state).is_valid_indexed_attestation::<BLS>(&indexed) {
+			return Err(AttestationError::InvalidSignature)
+		}
+
+		let mut seen = self.seen.lock().expect("Lock is poisoned");
+		if !seen.insert(validator, epoch) {
+			return Err(AttestationError::AlreadySeen { validator, epoch })
+		}
+		// Attestations from before the propagation range are rejected as
+		// past, so only their target epochs can be forgotten.
+		let current_slot = self.clock.now().unwrap_or_default();
+		seen.prune(beacon::utils::epoch_of_slot::<C>(
+			current_slot.saturating_sub(ATTESTATION_PROPAGATION_SLOT_RANGE)
+		));
+		Ok(validator)
+	}
+
+	/// Validate an aggregate received on the aggregate and proof topic,
+	/// checking that its sender is a member of the committee selected as
+	/// aggregator, and its signatures. Returns the indices of the attesters.
+	pub fn validate_aggregate(
+		&self,
+		signed: &SignedAggregateAndProof<C>,
+	) -> Result<Vec<ValidatorIndex>, AttestationError> {
+		let message = &signed.message;
+		let aggregate = &message.aggregate;
+		let aggregator = message.aggregator_index;
+		let slot = aggregate.data.slot;
+		if !aggregate.aggregation_bits.iter().any(|bit| *bit) {
+			return Err(AttestationError::EmptyAggregate)
+		}
+		if self.seen_aggregators.lock().expect("Lock is poisoned").contains(aggregator, slot) {
+			return Err(AttestationError::AggregatorAlreadySeen { aggregator, slot })
+		}
+
+		let (mut state, committee) = self.check_data(aggregate)?;
+		if !committee.contains(&aggregator) {
+			return Err(AttestationError::AggregatorNotInCommittee(aggregator))
+		}
+		if !beacon::utils::is_aggregator::<C>(committee.len(), &message.selection_proof) {
+			return Err(AttestationError::NotAggregator(aggregator))
+		}
+
+		let mut attesters = committee.iter()
+			.zip(aggregate.aggregation_bits.iter())
+			.filter(|(_, bit)| **bit)
+			.map(|(validator, _)| *validator)
+			.collect::<Vec<_>>();
+		attesters.sort();
+
+		let executive = BeaconExecutive::new(&mut state);
+		let epoch = aggregate.data.target.epoch;
+		let pubkey = &executive.validators[aggregator as usize].pubkey;
+		if !BLS::verify(
+			pubkey,
+			&tree_root::<C::Digest, _>(&slot),
+			&message.selection_proof,
+			executive.domain(C::domain_selection_proof(), Some(epoch)),
+		) {
+			return Err(AttestationError::InvalidSelectionProof)
+		}
+		if !BLS::verify(
+			pubkey,
+			&tree_root::<C::Digest, _>(message),
+			&signed.signature,
+			executive.domain(C::domain_aggregate_and_proof(), Some(epoch)),
+		) {
+			return Err(AttestationError::InvalidAggregatorSignature)
+		}
+
+		let indexed = IndexedAttestation {
+			data: aggregate.data.clone(),
+			signature: aggregate.signature,
+			custody_bit_0_indices: attesters.clone().into(),
+			custody_bit_1_indices: Vec::new().into(),
+		};
+		if !executive.is_valid_indexed_attestation::<BLS>(&indexed) {
+			return Err(AttestationError::InvalidSignature)
+		}
+
+		let mut seen = self.seen_aggregators.lock().expect("Lock is poisoned");
+		if !seen.insert(aggregator, slot) {
+			return Err(AttestationError::AggregatorAlreadySeen { aggregator, slot })
+		}
+		seen.prune(slot.saturating_sub(ATTESTATION_PROPAGATION_SLOT_RANGE));
+		Ok(attesters)
+	}
+}
+
+/// Gossip attestation handler, adding attestations and aggregates that pass
+/// validation to the operation pool and queueing them for the fork choice.
+#[derive(Clone)]
+pub struct GossipAttestations<C: Config, BLS: BLSConfig, B> {
+	validator: AttestationValidator<C, BLS, B>,
+	pool: SharedOperationPool<C, BLS>,
+	fork_choice: AttestationQueue,
+}
+
+impl<C: Config, BLS: BLSConfig, B> GossipAttestations<C, BLS, B> {
+	/// Create a new handler.
+	pub fn new(
+		validator: AttestationValidator<C, BLS, B>,
+		pool: SharedOperationPool<C, BLS>,
+		fork_choice: AttestationQueue,
+	) -> Self {
+		Self { validator, pool, fork_choice }
+	}
+}
+
+impl<C: Config, BLS: BLSConfig, B> AttestationHandler<C> for GossipAttestations<C, BLS, B> where
+	B: ChainQuery + Store<Block=Block<C>>,
+	B::State: StateExternalities<Config=C>,
+{
+	fn handle_attestation(&self, attestation: Attestation<C>, subnet: Option<u64>) -> GossipVerdict {
+		match self.validator.validate(&attestation, subnet) {
+			Ok(validator) => {
+				self.fork_choice.push(
+					attestation.data.slot.as_u64(), attestation.data.target.epoch.as_u64(),
+					vec![validator], attestation.data.beacon_block_root,
+				);
+				self.pool.lock().insert_attestation(attestation);
+				GossipVerdict::Accept
+			},
+			Err(e) if e.is_rejected() => {
+				debug!("Rejected gossip attestation: {:?}", e);
+				GossipVerdict::Reject
+			},
+			Err(e) => {
+				trace!("Ignored gossip attestation: {:?}", e);
+				GossipVerdict::Ignore
+			},
+		}
+	}
+
+	fn handle_aggregate(&self, aggregate: SignedAggregateAndProof<C>) -> GossipVerdict {
+		match self.validator.validate_aggregate(&aggregate) {
+			Ok(attesters) => {
+				let attestation = aggregate.message.aggregate;
+				self.fork_choice.push(
+					attestation.data.slot.as_u64(), attestation.data.target.epoch.as_u64(),
+					attesters, attestation.data.beacon_block_root,
+				);
+				self.pool.lock().insert_attestation(attestation);
+				GossipVerdict::Accept
+			},
+			Err(e) if e.is_rejected() => {
+				debug!("Rejected gossip aggregate: {:?}", e);
+				GossipVerdict::Reject
+			},
+			Err(e) => {
+				trace!("Ignored gossip aggregate: {:?}", e);
+				GossipVerdict::Ignore
+			},
+		}
+	}
+}
+
+/// Reasons for a block to fail gossip validation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BlockError {
+	/// Block slot has not started yet, even allowing for clock disparity.
+	FutureSlot { slot: Slot, current_slot: Slot },
+	/// Block slot is not after the finalized slot.
+	FinalizedSlot { slot: Slot, finalized_slot: Slot },
+	/// Parent block is not known, so the block cannot be checked yet.
+	UnknownParent(H256),
+	/// Block slot is not after its parent slot.
+	SlotNotAfterParent { slot: Slot, parent_slot: Slot },
+	/// Block does not descend from the finalized block.
+	NotFinalizedDescendant,
+	/// Block is on a fork too deep to be checked on gossip.
+	DeepFork,
+	/// Proposer already had a block for the slot validated.
+	AlreadySeen { proposer: ValidatorIndex, slot: Slot },
+	/// Proposer signature is invalid.
+	InvalidSignature,
+	/// State needed for validation is unavailable.
+	Unavailable(String),
+}
+
+impl BlockError {
+	/// Whether the block is invalid, as opposed to being ignored because it
+	/// cannot be validated now or is redundant.
+	pub fn is_rejected(&self) -> bool {
+		match self {
+			BlockError::SlotNotAfterParent { .. } |
+			BlockError::NotFinalizedDescendant |
+			BlockError::InvalidSignature => true,
+			_ => false,
+		}
+	}
+}
+
+/// Validates blocks received on gossip, so that only blocks passing the
+/// spec's gossip conditions reach the import queue and are forwarded.
+///
+/// The proposer is computed from the parent state advanced to the block
+/// epoch through the advanced state cache. Blocks whose parent is unknown
+/// cannot be checked, and are ignored, while the import queue fetches the
+/// parent and fully verifies them on import. Descent from the finalized
+/// block is checked by walking back to the canonical chain, for at most
+/// `MAX_FORK_DEPTH` blocks.
+#[derive(Clone)]
+pub struct BlockValidator<C: Config, BLS: BLSConfig, B> {
+	backend: B,
+	states: AdvancedStateCache<C>,
+	clock: Arc<dyn SlotClock>,
+	seen: Arc<Mutex<SeenAttesters<Slot>>>,
+	_marker: PhantomData<BLS>,
+}
+
+impl<C: Config, BLS: BLSConfig, B> BlockValidator<C, BLS, B> where
+	B: ChainQuery + Store<Block=Block<C>>,
+	B::State: StateExternalities<Config=C>,
+{
+	/// Create a new validator.
+	pub fn new(backend: B, states: AdvancedStateCache<C>, clock: Arc<dyn SlotClock>) -> Self {
+		Self {
+			backend, states, clock,
+			seen: Arc::new(Mutex::new(SeenAttesters::default())),
+			_marker: PhantomData,
+		}
+	}
+
+	/// Validate a block received on gossip. Returns the index of its
+	/// proposer.
+	pub fn validate(&self, block: &BeaconBlock<C>) -> Result<ValidatorIndex, BlockError> {
+		let unavailable = |e: B::Error| BlockError::Unavailable(format!("{}", e));
+		let slot = block.slot;
+		let since_genesis = self.clock.since_genesis().unwrap_or_default();
+		if self.clock.slot_start(slot) > since_genesis + MAXIMUM_GOSSIP_CLOCK_DISPARITY {
+			return Err(BlockError::FutureSlot {
+				slot, current_slot: self.clock.now().unwrap_or_default(),
+			})
+		}
+
+		let finalized = self.backend.state_at(&self.backend.head()).map_err(unavailable)?
+			.state().finalized_checkpoint.clone();
+		let finalized_slot = beacon::utils::start_slot_of_epoch::<C>(finalized.epoch);
+		if slot <= finalized_slot {
+			return Err(BlockError::FinalizedSlot { slot, finalized_slot })
+		}
+
+		if !self.backend.contains(&block.parent_root).map_err(unavailable)? {
+			return Err(BlockError::UnknownParent(block.parent_root))
+		}
+		let parent = self.backend.block_at(&block.parent_root).map_err(unavailable)?;
+		if slot <= parent.0.slot {
+			return Err(BlockError::SlotNotAfterParent { slot, parent_slot: parent.0.slot })
+		}
+
+		if finalized.root != H256::default() {
+			// Canonical blocks after the finalized slot descend from the
+			// finalized block, so only the fork part is walked.
+			let mut ancestor = block.parent_root;
+			let mut ancestor_block = parent;
+			let mut depth = 0;
+			while ancestor_block.0.slot > finalized_slot &&
+				!self.backend.is_canon(&ancestor).map_err(unavailable)?
+			{
+				if depth >= MAX_FORK_DEPTH {
+					return Err(BlockError::DeepFork)
+				}
+				depth += 1;
+				ancestor = ancestor_block.0.parent_root;
+				ancestor_block = self.backend.block_at(&ancestor).map_err(unavailable)?;
+			}
+			if ancestor_block.0.slot <= finalized_slot && ancestor != finalized.root {
+				return Err(BlockError::NotFinalizedDescendant)
+			}
+		}
+
+		let epoch = beacon::utils::epoch_of_slot::<C>(slot);
+		let mut state = self.states.get_or_advance(&self.backend, &block.parent_root, epoch)
+			.map_err(|e| BlockError::Unavailable(format!("{:?}", e)))?;
+		let executive = BeaconExecutive::new(&mut state);
+		let proposer = executive.proposer_index_at_slot(slot)
+			.map_err(|e| BlockError::Unavailable(format!("{:?}", e)))?;
+		if self.seen.lock().expect("Lock is poisoned").contains(proposer, slot) {
+			return Err(BlockError::AlreadySeen { proposer, slot })
+		}
+
+		if !BLS::verify(
+			&executive.validators[proposer as usize].pubkey,
+			&tree_root::<C::Digest, _>(&UnsealedBeaconBlock::from(block)),
+			&block.signature,
+			executive.domain(C::domain_beacon_proposer(), Some(epoch)),
+		) {
+			return Err(BlockError::InvalidSignature)
+		}
+
+		let mut seen = self.seen.lock().expect("Lock is poisoned");
+		if !seen.insert(proposer, slot) {
+			return Err(BlockError::AlreadySeen { proposer, slot })
+		}
+		seen.prune(finalized_slot);
+		Ok(proposer)
+	}
+}
+
+impl<C: Config, BLS: BLSConfig, B> BlockHandler<C> for BlockValidator<C, BLS, B> where
+	B: ChainQuery + Store<Block=Block<C>>,
+	B::State: StateExternalities<Config=C>,
+{
+	fn handle_block(&self, block: &BeaconBlock<C>) -> BlockVerdict {
+		match self.validate(block) {
+			Ok(_) => BlockVerdict::Gossip(GossipVerdict::Accept),
+			Err(BlockError::UnknownParent(parent)) => {
+				trace!("Ignored gossip block with unknown parent {:?}", parent);
+				BlockVerdict::UnknownParent
+			},
+			Err(e) if e.is_rejected() => {
+				debug!("Rejected gossip block: {:?}", e);
+				BlockVerdict::Gossip(GossipVerdict::Reject)
+			},
+			Err(e) => {
+				trace!("Ignored gossip block: {:?}", e);
+				BlockVerdict::Gossip(GossipVerdict::Ignore)
+			},
+		}
+	}
+}
@@ -0,0 +1,108 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Graffiti written into produced blocks.
+
+use beacon::primitives::{H256, ValidatorId};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Graffiti errors.
+#[derive(Debug)]
+pub enum Error {
+	/// Io error.
+	Io(std::io::Error),
+	/// A line of the graffiti file is not a valid graffiti.
+	InvalidLine(usize),
+}
+
+impl From<std::io::Error> for Error {
+	fn from(error: std::io::Error) -> Error {
+		Error::Io(error)
+	}
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Error::Io(e) => write!(f, "{}", e),
+			Error::InvalidLine(line) => write!(f, "Invalid graffiti at line {}", line),
+		}
+	}
+}
+
+/// Parse graffiti, either as 32 hex encoded bytes with a `0x` prefix, or as
+/// text of at most 32 bytes.
+pub fn parse_graffiti(value: &str) -> Option<H256> {
+	if value.starts_with("0x") {
+		let bytes = hex::decode(&value[2..]).ok()?;
+		if bytes.len() != 32 {
+			return None
+		}
+		return Some(H256::from_slice(&bytes))
+	}
+
+	if value.len() > 32 {
+		return None
+	}
+	let mut bytes = [0u8; 32];
+	bytes[..value.len()].copy_from_slice(value.as_bytes());
+	Some(H256::from(bytes))
+}
+
+/// Graffiti configured at startup.
+#[derive(Clone, Debug, Default)]
+pub struct GraffitiConfig {
+	/// Graffiti of the node, until overridden by the settings file.
+	pub graffiti: Option<H256>,
+	/// Graffiti of individual validators.
+	pub validators: HashMap<ValidatorId, H256>,
+	/// File of graffiti rotated through in produced blocks.
+	pub file: Option<PathBuf>,
+}
+
+/// File of graffiti used in turn by produced blocks, one per line. Blank
+/// lines are skipped. The file is read again for each block, so that it can
+/// be edited while the node runs.
+pub struct RotatingGraffiti {
+	path: PathBuf,
+	next: usize,
+}
+
+impl RotatingGraffiti {
+	/// Create a new rotating graffiti from a file path.
+	pub fn new(path: PathBuf) -> Self {
+		Self { path, next: 0 }
+	}
+
+	/// Next graffiti of the file, or `None` if the file has none.
+	pub fn next_graffiti(&mut self) -> Result<Option<H256>, Error> {
+		let content = fs::read_to_string(&self.path)?;
+		let lines = content.lines()
+			.enumerate()
+			.map(|(index, line)| (index, line.trim()))
+			.filter(|(_, line)| !line.is_empty())
+			.collect::<Vec<_>>();
+		if lines.is_empty() {
+			return Ok(None)
+		}
+
+		let (index, line) = lines[self.next % lines.len()];
+		self.next = (self.next + 1) % lines.len();
+		parse_graffiti(line).map(Some).ok_or(Error::InvalidLine(index + 1))
+	}
+}
@@ -0,0 +1,149 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Tracking of inactivity penalties during long non-finality.
+
+use beacon::{BeaconExecutive, BeaconState, Config, Error, RewardBreakdown};
+use beacon::primitives::{Epoch, Gwei};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use log::*;
+
+/// Number of epochs of inactivity leak totals kept.
+pub const INACTIVITY_HISTORY_EPOCHS: u64 = 4096;
+
+struct Inner {
+	leak_start: Option<Epoch>,
+	totals: BTreeMap<Epoch, Gwei>,
+	accumulated: Vec<Gwei>,
+	pending: Option<(Epoch, Vec<Gwei>)>,
+}
+
+/// Inactivity penalties of the ongoing inactivity leak, as of the latest
+/// recorded epoch.
+#[derive(Clone, Debug, Default)]
+pub struct InactivityReport {
+	/// First epoch transition of the leak, if leaking.
+	pub leak_start: Option<Epoch>,
+	/// Total inactivity penalty of each epoch transition of the leak.
+	pub totals: Vec<(Epoch, Gwei)>,
+	/// Inactivity penalty accumulated by each validator during the leak.
+	pub accumulated: Vec<Gwei>,
+}
+
+/// Accumulated inactivity penalties of each validator, since finality was
+/// last delayed enough for the inactivity leak to apply.
+#[derive(Clone)]
+pub struct InactivityTracker(Arc<Mutex<Inner>>);
+
+impl InactivityTracker {
+	/// Create a new tracker, with no leak recorded.
+	pub fn new() -> Self {
+		Self(Arc::new(Mutex::new(Inner {
+			leak_start: None,
+			totals: BTreeMap::new(),
+			accumulated: Vec::new(),
+			pending: None,
+		})))
+	}
+
+	/// Record the inactivity penalties that the epoch transition at the end
+	/// of `epoch` applies. Penalties of an epoch can be recorded again as
+	/// more attestations are included, and are only accumulated once a
+	/// later epoch is recorded.
+	pub fn record(&self, epoch: Epoch, breakdowns: &[RewardBreakdown]) {
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+		let penalties = breakdowns.iter().map(|b| b.inactivity_penalty).collect::<Vec<_>>();
+
+		if let Some((pending_epoch, _)) = &inner.pending {
+			if *pending_epoch > epoch {
+				return
+			}
+		}
+		match inner.pending.take() {
+			Some((pending_epoch, pending)) if pending_epoch < epoch => {
+				if inner.accumulated.len() < pending.len() {
+					inner.accumulated.resize(pending.len(), Gwei::default());
+				}
+				for (total, penalty) in inner.accumulated.iter_mut().zip(pending) {
+					*total += penalty;
+				}
+			},
+			_ => (),
+		}
+
+		let total = penalties.iter().sum::<Gwei>();
+		if total == 0 {
+			if let Some(leak_start) = inner.leak_start.take() {
+				info!(
+					"Inactivity leak from epoch {} ended at epoch {}, with {} Gwei penalized",
+					leak_start, epoch, inner.accumulated.iter().sum::<Gwei>(),
+				);
+			}
+			inner.totals.clear();
+			inner.accumulated.clear();
+			return
+		}
+
+		if inner.leak_start.is_none() {
+			warn!("Inactivity leak started at epoch {}", epoch);
+			inner.leak_start = Some(epoch);
+		}
+		inner.totals.insert(epoch, total);
+		while inner.totals.len() as u64 > INACTIVITY_HISTORY_EPOCHS {
+			let oldest = *inner.totals.keys().next().expect("Totals are not empty");
+			inner.totals.remove(&oldest);
+		}
+		inner.pending = Some((epoch, penalties));
+	}
+
+	/// Record the inactivity penalties of the epoch transition at the end of
+	/// the current epoch of `state`.
+	pub fn record_state<C: Config>(&self, state: &mut BeaconState<C>) -> Result<(), Error> {
+		let executive = BeaconExecutive::new(state);
+		let epoch = executive.current_epoch();
+		let breakdowns = executive.reward_breakdown()?;
+		self.record(epoch, &breakdowns);
+		Ok(())
+	}
+
+	/// Report of the ongoing leak, including the penalties of the latest
+	/// recorded epoch.
+	pub fn report(&self) -> InactivityReport {
+		let inner = self.0.lock().expect("Lock is poisoned");
+		let mut accumulated = inner.accumulated.clone();
+		if let Some((_, pending)) = &inner.pending {
+			if accumulated.len() < pending.len() {
+				accumulated.resize(pending.len(), Gwei::default());
+			}
+			for (total, penalty) in accumulated.iter_mut().zip(pending) {
+				*total += *penalty;
+			}
+		}
+
+		InactivityReport {
+			leak_start: inner.leak_start,
+			totals: inner.totals.iter().map(|(epoch, total)| (*epoch, *total)).collect(),
+			accumulated,
+		}
+	}
+}
+
+impl Default for InactivityTracker {
+	fn default() -> Self {
+		Self::new()
+	}
+}
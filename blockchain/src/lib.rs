@@ -16,20 +16,62 @@
 mod pool;
 pub mod backend;
 pub mod preset;
-
-pub use pool::AttestationPool;
+pub mod spec;
+pub mod eth1;
+pub mod cache;
+pub mod checkpoint;
+pub mod duties;
+pub mod gc;
+pub mod api;
+pub mod clock;
+pub mod service;
+pub mod slasher;
+pub mod safety;
+pub mod settings;
+pub mod verifier;
+pub mod committee;
+pub mod pubkey;
+pub mod graffiti;
+pub mod regen;
+pub mod era;
+pub mod replay;
+pub mod export;
+pub mod exit;
+pub mod weak_subjectivity;
+pub mod inactivity;
+pub mod logging;
+pub mod gossip;
+
+pub use pool::{AttestationPool, OperationPool, SharedOperationPool};
+pub use cache::AdvancedStateCache;
+pub use committee::CommitteeCache;
+pub use pubkey::PubkeyCache;
+pub use verifier::SignatureVerifier;
+pub use regen::StateRegenerator;
 pub use shasper_runtime::{Block, StateExternalities};
 
-use beacon::primitives::H256;
+/// Queue of attestations received outside of blocks, applied to the fork
+/// choice from the slot after their own.
+pub type AttestationQueue = lmd_ghost::proto_array::AttestationQueue<u64, H256>;
+
+use beacon::primitives::{H256, Slot, ValidatorId};
 use beacon::types::*;
 use beacon::{Error as BeaconError, BeaconState, BeaconExecutive, Config,
-			 BLSConfig, Inherent, Transaction};
-use std::sync::Arc;
+			 BLSConfig, Inherent, Transaction, RewardBreakdown, RewardProjection, ForkVersions,
+			 VerifiedSignatures, ForkSchedule, ForkAwareState};
+#[cfg(feature = "merge")]
+use beacon::{ExecutionEngine, StubExecutionEngine};
+#[cfg(feature = "merge")]
+use beacon::primitives::ExecutionAddress;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use blockchain::{Block as BlockT, BlockExecutor, AsExternalities};
 use lmd_ghost::JustifiableExecutor;
 use core::marker::PhantomData;
+use bm_le::tree_root;
 
-use blockchain_rocksdb::RocksState as RocksStateT;
+use blockchain_rocksdb::{RocksState as RocksStateT, Database};
 
 #[derive(Clone)]
 pub struct MemoryState<C: Config> {
@@ -104,7 +146,7 @@ impl<C: Config> AsExternalities<dyn StateExternalities<Config=C>> for RocksState
 impl<C: Config> RocksStateT for RocksState<C> {
 	type Raw = BeaconState<C>;
 
-	fn from_raw(state: BeaconState<C>, _db: Arc<::rocksdb::DB>) -> Self {
+	fn from_raw(state: BeaconState<C>, _db: Arc<dyn Database>) -> Self {
 		Self { state }
 	}
 
@@ -116,6 +158,10 @@ impl<C: Config> RocksStateT for RocksState<C> {
 #[derive(Debug)]
 pub enum Error {
 	Beacon(BeaconError),
+	Eth1(eth1::Error),
+	Backend(Box<dyn std::error::Error>),
+	/// Post-state root does not match the one committed in the block.
+	StateRootMismatch { expected: H256, actual: H256 },
 }
 
 impl std::fmt::Display for Error {
@@ -132,22 +178,152 @@ impl From<BeaconError> for Error {
 	}
 }
 
+impl From<eth1::Error> for Error {
+	fn from(error: eth1::Error) -> Error {
+		Error::Eth1(error)
+	}
+}
+
+/// Maximum number of blocks verified ahead of time waiting to be executed.
+const MAX_VERIFIED_BLOCKS: usize = 64;
+
 #[derive(Clone)]
 pub struct Executor<C: Config, BLS: BLSConfig> {
+	verified: Arc<Mutex<HashMap<H256, VerifiedSignatures>>>,
+	committees: CommitteeCache,
+	pubkeys: PubkeyCache,
+	check_state_root: bool,
+	fork_schedule: Option<ForkSchedule>,
+	#[cfg(feature = "merge")]
+	execution_engine: Arc<dyn ExecutionEngine + Send + Sync>,
 	_marker: PhantomData<(C, BLS)>,
 }
 
 impl<C: Config, BLS: BLSConfig> Executor<C, BLS> {
 	pub fn new() -> Self {
-		Self { _marker: PhantomData }
+		Self {
+			verified: Arc::new(Mutex::new(HashMap::new())),
+			committees: CommitteeCache::default(),
+			pubkeys: PubkeyCache::new(),
+			check_state_root: false,
+			fork_schedule: None,
+			#[cfg(feature = "merge")]
+			execution_engine: Arc::new(StubExecutionEngine),
+			_marker: PhantomData,
+		}
+	}
+
+	/// Recompute the post-state root after applying each block, and report
+	/// both roots as `Error::StateRootMismatch` if it differs from the block
+	/// state root.
+	pub fn with_state_root_check(mut self) -> Self {
+		self.check_state_root = true;
+		self
+	}
+
+	/// Upgrade states at the forks of `schedule` when processing slots.
+	/// Without a schedule, states stay on the fork they are at.
+	pub fn with_fork_schedule(mut self, schedule: ForkSchedule) -> Self {
+		self.fork_schedule = Some(schedule);
+		self
+	}
+
+	/// Use `engine` for producing and verifying execution payloads, instead
+	/// of the stub engine accepting all payloads.
+	#[cfg(feature = "merge")]
+	pub fn with_execution_engine(mut self, engine: Arc<dyn ExecutionEngine + Send + Sync>) -> Self {
+		self.execution_engine = engine;
+		self
+	}
+
+	/// Build the execution payload of a block at the slot of the state, on
+	/// top of the payload of the parent block.
+	#[cfg(feature = "merge")]
+	pub fn produce_execution_payload(
+		&self,
+		state: &mut <Self as BlockExecutor>::Externalities,
+		parent: &ExecutionPayloadHeader,
+		fee_recipient: ExecutionAddress,
+	) -> Result<ExecutionPayload, Error> {
+		let executive = BeaconExecutive::new(state.state_mut());
+		Ok(executive.produce_execution_payload(&*self.execution_engine, parent, fee_recipient)?)
+	}
+
+	/// Verify the execution payload of a block at the slot of the state
+	/// with the execution engine, returning the header of the payload.
+	#[cfg(feature = "merge")]
+	pub fn verify_execution_payload(
+		&self,
+		state: &mut <Self as BlockExecutor>::Externalities,
+		parent: &ExecutionPayloadHeader,
+		payload: &ExecutionPayload,
+	) -> Result<ExecutionPayloadHeader, Error> {
+		let executive = BeaconExecutive::new(state.state_mut());
+		Ok(executive.process_execution_payload(&*self.execution_engine, parent, payload)?)
+	}
+
+	/// Process slots up to `target_slot`, upgrading the state at scheduled
+	/// forks. The state is only replaced if processing succeeds.
+	fn process_slots(&self, state: &mut BeaconState<C>, target_slot: Slot) -> Result<(), Error> {
+		match self.fork_schedule.as_ref() {
+			Some(schedule) => {
+				let fork_aware = ForkAwareState::from(state.clone());
+				*state = beacon::process_slots_with_schedule(fork_aware, target_slot, schedule)?
+					.into_phase0()?;
+				Ok(())
+			},
+			None => Ok(beacon::initialize_block::<C>(state, target_slot)?),
+		}
+	}
+
+	/// Committee cache shared by all clones of this executor.
+	pub fn committees(&self) -> &CommitteeCache {
+		&self.committees
+	}
+
+	/// Validator index cache shared by all clones of this executor, kept up
+	/// to date with the deposits of executed blocks.
+	pub fn pubkeys(&self) -> &PubkeyCache {
+		&self.pubkeys
+	}
+
+	/// Verify all signatures of a block against its parent state, without
+	/// modifying it. The result is kept until the block is executed, so
+	/// that its signatures are not checked again.
+	pub fn verify_block_signatures(
+		&self,
+		block: &Block<C>,
+		state: &BeaconState<C>,
+	) -> Result<(), Error> {
+		let verified = beacon::verify_block_signatures::<C, BLS>(&block.0, state)?;
+
+		let mut cache = self.verified.lock().expect("Lock is poisoned");
+		if cache.len() < MAX_VERIFIED_BLOCKS {
+			cache.insert(block.id(), verified);
+		}
+		Ok(())
 	}
 
 	pub fn initialize_block(
 		&self,
 		state: &mut <Self as BlockExecutor>::Externalities,
-		target_slot: u64,
+		target_slot: Slot,
+	) -> Result<(), Error> {
+		self.process_slots(state.state_mut(), target_slot)
+	}
+
+	/// Perform per-slot processing up to `target_slot` without a block,
+	/// caching block and state roots and processing epoch transitions on
+	/// the way. Does nothing if the state is already at or past the slot.
+	pub fn advance_slots(
+		&self,
+		state: &mut <Self as BlockExecutor>::Externalities,
+		target_slot: Slot,
 	) -> Result<(), Error> {
-		Ok(beacon::initialize_block::<C>(state.state_mut(), target_slot)?)
+		if state.state().slot >= target_slot {
+			return Ok(())
+		}
+		self.process_slots(state.state_mut(), target_slot)
 	}
 
 	pub fn apply_inherent(
@@ -168,6 +344,37 @@ impl<C: Config, BLS: BLSConfig> Executor<C, BLS> {
 		Ok(beacon::apply_transaction::<C, BLS>(block, state.state_mut(), extrinsic)?)
 	}
 
+	/// Apply transactions to a block in order, until `deadline` so that
+	/// the block can still be finalized and published in time. Deposits,
+	/// which a block must include, are applied even past the deadline.
+	/// Returns the number of applied transactions, and the transactions
+	/// that failed along with their error.
+	pub fn apply_extrinsics_until(
+		&self,
+		block: &mut UnsealedBeaconBlock<C>,
+		state: &mut <Self as BlockExecutor>::Externalities,
+		transactions: Vec<Transaction<C>>,
+		deadline: Instant,
+	) -> (usize, Vec<(Transaction<C>, Error)>) {
+		let mut applied = 0;
+		let mut failed = Vec::new();
+		for transaction in transactions {
+			let is_deposit = match transaction {
+				Transaction::Deposit(_) => true,
+				_ => false,
+			};
+			if !is_deposit && Instant::now() >= deadline {
+				continue
+			}
+
+			match self.apply_extrinsic(block, state, transaction.clone()) {
+				Ok(()) => applied += 1,
+				Err(e) => failed.push((transaction, e)),
+			}
+		}
+		(applied, failed)
+	}
+
 	pub fn finalize_block(
 		&self,
 		block: &mut UnsealedBeaconBlock<C>,
@@ -175,6 +382,64 @@ impl<C: Config, BLS: BLSConfig> Executor<C, BLS> {
 	) -> Result<(), Error> {
 		Ok(beacon::finalize_block::<C, BLS>(block, state.state_mut())?)
 	}
+
+	/// Rewards and penalties of each validator that will be applied at the
+	/// end of the current epoch, broken down by component.
+	pub fn reward_breakdown(
+		&self,
+		state: &mut <Self as BlockExecutor>::Externalities,
+	) -> Result<Vec<RewardBreakdown>, Error> {
+		let executive = BeaconExecutive::new(state.state_mut());
+		Ok(executive.reward_breakdown()?)
+	}
+
+	/// Projected rewards of each active validator, if `participation` basis
+	/// points of validators attested in the current epoch.
+	pub fn project_rewards(
+		&self,
+		state: &mut <Self as BlockExecutor>::Externalities,
+		participation: u64,
+	) -> Result<Vec<RewardProjection>, Error> {
+		let executive = BeaconExecutive::new(state.state_mut());
+		Ok(executive.project_rewards(participation)?)
+	}
+
+	/// Queue an attestation for the fork choice, resolving its attesters
+	/// with the given state.
+	pub fn queue_attestation(
+		&self,
+		queue: &AttestationQueue,
+		state: &mut <Self as BlockExecutor>::Externalities,
+		attestation: &Attestation<C>,
+	) -> Result<(), Error> {
+		let committees = self.committees.committees(
+			state.state_mut(),
+			beacon::utils::epoch_of_slot::<C>(attestation.data.slot),
+		)?;
+		let validators = committees.attesting_indices::<C>(
+			&attestation.data, &attestation.aggregation_bits,
+		)?;
+		queue.push(
+			attestation.data.slot.as_u64(), attestation.data.target.epoch.as_u64(),
+			validators, attestation.data.beacon_block_root,
+		);
+		Ok(())
+	}
+
+	/// Verify proposal and randao signatures of historical blocks, given
+	/// the public key of each block proposer and the fork versions of the
+	/// chain. Used for backfilling, where no state is available at the
+	/// block slots.
+	pub fn verify_historical_blocks(
+		&self,
+		blocks: &[(Block<C>, ValidatorId)],
+		forks: &ForkVersions,
+	) -> Vec<bool> {
+		let blocks = blocks.iter()
+			.map(|(block, proposer)| (block.0.clone(), proposer.clone()))
+			.collect::<Vec<_>>();
+		beacon::verify_historical_blocks::<C, BLS, _>(&blocks, forks)
+	}
 }
 
 impl<C: Config, BLS: BLSConfig> BlockExecutor for Executor<C, BLS> {
@@ -187,7 +452,36 @@ impl<C: Config, BLS: BLSConfig> BlockExecutor for Executor<C, BLS> {
 		block: &Block<C>,
 		state: &mut Self::Externalities,
 	) -> Result<(), Error> {
-		Ok(beacon::execute_block::<C, BLS>(&block.0, state.state_mut())?)
+		let span = tracing::debug_span!("execute_block", slot = block.0.slot.as_u64());
+		let _enter = span.enter();
+		let verified = self.verified.lock().expect("Lock is poisoned").remove(&block.id());
+		if self.fork_schedule.is_some() && state.state().slot < block.0.slot {
+			self.process_slots(state.state_mut(), block.0.slot)?;
+		}
+
+		if self.check_state_root {
+			beacon::apply_block::<C, BLS>(&block.0, state.state_mut(), verified)?;
+
+			let actual = tree_root::<C::Digest, _>(state.state());
+			if actual != block.0.state_root {
+				return Err(Error::StateRootMismatch {
+					expected: block.0.state_root,
+					actual,
+				})
+			}
+		} else {
+			match verified {
+				Some(verified) => beacon::execute_verified_block::<C, BLS>(
+					&block.0, state.state_mut(), verified,
+				)?,
+				None => beacon::execute_block::<C, BLS>(&block.0, state.state_mut())?,
+			}
+		}
+
+		if !block.0.body.deposits.is_empty() {
+			self.pubkeys.import(state.state());
+		}
+		Ok(())
 	}
 }
 
@@ -214,12 +508,57 @@ impl<C: Config, BLS: BLSConfig> JustifiableExecutor for Executor<C, BLS> {
 		}
 	}
 
+	fn finalized_block_id(
+		&self,
+		state: &mut Self::Externalities,
+	) -> Result<Option<<Self::Block as BlockT>::Identifier>, Self::Error> {
+		let finalized_root = state.state().finalized_checkpoint.root;
+		if finalized_root == H256::default() {
+			Ok(None)
+		} else {
+			Ok(Some(finalized_root))
+		}
+	}
+
+	fn checkpoint_epochs(
+		&self,
+		state: &mut Self::Externalities,
+	) -> Result<(u64, u64), Self::Error> {
+		let state = state.state();
+		Ok((
+			state.current_justified_checkpoint.epoch.as_u64(),
+			state.finalized_checkpoint.epoch.as_u64(),
+		))
+	}
+
+	fn justified_balances(
+		&self,
+		state: &mut Self::Externalities,
+	) -> Result<Vec<(Self::ValidatorIndex, u64)>, Self::Error> {
+		let executive = BeaconExecutive::new(state.state_mut());
+		Ok(executive.justified_active_validators().into_iter()
+		   .map(|index| (index, executive.validators[index as usize].effective_balance.as_u64()))
+		   .collect())
+	}
+
 	fn votes(
 		&self,
 		block: &Self::Block,
 		state: &mut Self::Externalities,
-	) -> Result<Vec<(Self::ValidatorIndex, <Self::Block as BlockT>::Identifier)>, Self::Error> {
-		let executive = BeaconExecutive::new(state.state_mut());
-		Ok(executive.block_vote_targets(&block.0)?)
+	) -> Result<Vec<(Self::ValidatorIndex, <Self::Block as BlockT>::Identifier, u64)>, Self::Error> {
+		let mut ret = Vec::new();
+		for attestation in block.0.body.attestations.iter() {
+			let committees = self.committees.committees(
+				state.state_mut(),
+				beacon::utils::epoch_of_slot::<C>(attestation.data.slot),
+			)?;
+			let validators = committees.attesting_indices::<C>(
+				&attestation.data, &attestation.aggregation_bits,
+			)?;
+			ret.extend(validators.into_iter().map(|index| {
+				(index, attestation.data.beacon_block_root, attestation.data.target.epoch.as_u64())
+			}));
+		}
+		Ok(ret)
 	}
 }
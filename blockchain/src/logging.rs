@@ -0,0 +1,37 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+//! Log output, through `tracing`. Records of the `log` crate are forwarded
+//! to the same subscriber, and closed spans are logged with their timing.
+
+use tracing_subscriber::EnvFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+/// Install the global subscriber, filtered by `RUST_LOG` and writing to
+/// stderr, as JSON lines if `json` is set.
+pub fn init(json: bool) {
+	let filter = EnvFilter::try_from_default_env()
+		.unwrap_or_else(|_| EnvFilter::new("error"));
+	let builder = tracing_subscriber::fmt()
+		.with_env_filter(filter)
+		.with_span_events(FmtSpan::CLOSE)
+		.with_writer(std::io::stderr);
+
+	if json {
+		builder.json().init();
+	} else {
+		builder.init();
+	}
+}
@@ -13,108 +13,125 @@
 
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
-use beacon::{genesis_beacon_state, Config, Inherent, Transaction, BeaconExecutive};
+use beacon::{
+	genesis_beacon_state, Config, Inherent, BeaconExecutive, BeaconState, Transaction, ForkSchedule,
+};
 use beacon::primitives::*;
 use beacon::types::*;
 use blockchain::{AsExternalities, Auxiliary, Block as BlockT};
 use blockchain::backend::{SharedMemoryBackend, SharedCommittable, ChainQuery, Store, ImportLock, Operation};
 use blockchain::import::{SharedBlockImporter, MutexImporter};
-use blockchain_rocksdb::RocksBackend;
-use shasper_blockchain::{Block, Executor, MemoryState, RocksState, Error, StateExternalities, AttestationPool};
-use shasper_blockchain::preset::Preset;
-use shasper_blockchain::backend::ShasperBackend;
-use shasper_network::NetworkConfig;
-use lmd_ghost::archive::{ArchiveGhostImporter, AncestorQuery};
-use clap::{App, Arg, ArgMatches};
+use blockchain_rocksdb::{RocksBackend, StorageMode, SyncPolicy, DEFAULT_SNAPSHOT_INTERVAL};
+use shasper_blockchain::{
+	Block, Executor, MemoryState, RocksState, Error, StateExternalities, SharedOperationPool,
+	AdvancedStateCache, AttestationQueue, SignatureVerifier, StateRegenerator,
+};
+use shasper_blockchain::preset::{Preset, interop_secret, quickstart};
+use shasper_blockchain::spec::SpecConfig;
+use shasper_blockchain::duties::{DutyHistory, EpochDuty};
+use shasper_blockchain::clock::{SlotClock, SlotTick, SystemTimeSlotClock, start_slot_ticks};
+use shasper_blockchain::service::{Exit, ServiceManager};
+use shasper_blockchain::eth1::{
+	Eth1Provider, StaticEth1Provider, MockEth1Provider, ScheduledDeposit,
+	Eth1Follower, Eth1FollowerConfig, ETH1_FOLLOW_DISTANCE,
+	signed_deposit_data, voting_period_timestamp,
+};
+use shasper_blockchain::backend::{
+	ShasperBackend, BranchPruning, StateStorage, Freezing, CanonSlotQuery,
+};
+use shasper_blockchain::era::{export_eras, import_eras};
+use shasper_blockchain::replay::replay_blocks;
+use shasper_blockchain::export::export_state;
+use shasper_blockchain::exit::{Error as ExitError, build_voluntary_exit, is_exiting, validator_index};
+use shasper_blockchain::gc::{ForkPruner, prune_finalized_states, freeze_finalized_blocks};
+use shasper_blockchain::checkpoint::CheckpointCache;
+use shasper_blockchain::gossip::{AttestationValidator, BlockValidator, GossipAttestations};
+use shasper_blockchain::slasher::{ChainSlasher, Slasher};
+use shasper_blockchain::safety::{FinalityMonitor, SafetySwitch};
+use shasper_blockchain::weak_subjectivity::{WeakSubjectivityPolicy, check_weak_subjectivity};
+use shasper_blockchain::inactivity::InactivityTracker;
+use shasper_blockchain::settings::{LiveSettings, SettingsFile};
+use shasper_blockchain::graffiti::{GraffitiConfig, parse_graffiti};
+use shasper_network::{
+	NetworkConfig, NetworkStatus, AttestationSubnets, PeerManager, SubnetDuty, GossipQueue,
+	PubsubMessage,
+};
+use shasper_blockchain::api::{
+	ApiEvents, ApiServer, HealthConfig, NodeMetrics,
+	register_attestations, register_blocks, register_consensus, register_duties, register_events,
+	register_fork_choice, register_fork_schedule, register_health, register_inactivity,
+	register_light_client, register_metrics, register_peers, register_pool, register_randao,
+	register_rewards, register_settings, register_validator_duties,
+};
+use lmd_ghost::archive::AncestorQuery;
+use lmd_ghost::proto_array::ProtoArrayGhostImporter;
+use lmd_ghost::justification::SAFE_SLOTS_TO_UPDATE_JUSTIFIED;
+use lmd_ghost::events::ChainEvent;
+use clap::{App, Arg, ArgMatches, SubCommand};
 use libp2p::Multiaddr;
 use std::thread;
 use std::str::FromStr;
 use std::fs::File;
 use std::io::{BufReader, Read};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Receiver;
+use std::path::{Path, PathBuf};
+use std::net::SocketAddr;
 use ssz::Decode;
 use core::time::Duration;
-use core::convert::TryInto;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
 use serde::{Serialize, Deserialize};
-use log::{info, warn, trace};
+use log::{info, warn, trace, error};
 use bm_le::tree_root;
 use crypto::bls;
 
 type BLS = bls::BLSVerification;
 
-fn deposit_tree<C: Config>(deposits: &[DepositData]) -> Vec<Vec<H256>> {
-	let mut zerohashes = vec![H256::default()];
-	for layer in 1..32 {
-		zerohashes.push(C::hash(&[
-			zerohashes[layer - 1].as_ref(),
-			zerohashes[layer - 1].as_ref(),
-		]));
-	}
+struct EraConfig {
+	export: Option<PathBuf>,
+	import: Option<PathBuf>,
+}
 
-	let mut values = deposits.iter().map(|d| {
-		tree_root::<C::Digest, _>(d)
-	}).collect::<Vec<_>>();
-	let values_len = values.len();
-	let mut tree = vec![values.clone()];
-
-	for h in 0..(beacon::consts::DEPOSIT_CONTRACT_TREE_DEPTH as usize) {
-		if values.len() % 2 == 1 {
-			values.push(zerohashes[h]);
-		}
-		let mut new_values = Vec::new();
-		for i in 0..(values.len() / 2) {
-			new_values.push(C::hash(&[
-				values[i * 2].as_ref(),
-				values[i * 2 + 1].as_ref()
-			]));
-		}
-		values = new_values;
-		tree.push(values.clone());
-	}
-	assert!(values.len() == 1);
-	values.push({
-		let mut ret = values_len.to_le_bytes().to_vec();
-		while ret.len() < 32 {
-			ret.push(0);
-		}
-		H256::from_slice(&ret[..])
-	});
-	tree[32].push(values[1]);
-	tree.push(vec![C::hash(&[
-		values[0].as_ref(),
-		values[1].as_ref(),
-	])]);
-	assert!(tree.len() == 34);
-
-	tree
+struct ReplayConfig {
+	from: Slot,
+	to: Slot,
+	dump: Option<PathBuf>,
 }
 
-fn deposit_root(tree: &Vec<Vec<H256>>) -> H256 {
-	tree.last().expect("Merkle tree cannot be empty; qed")[0]
+struct ExportStateConfig {
+	slot: Slot,
+	output: PathBuf,
 }
 
-fn deposit_proof<C: Config>(tree: &Vec<Vec<H256>>, item_index: usize) -> Vec<H256> {
-	let mut zerohashes = vec![H256::default()];
-	for layer in 1..32 {
-		zerohashes.push(C::hash(&[
-			zerohashes[layer - 1].as_ref(),
-			zerohashes[layer - 1].as_ref(),
-		]));
-	}
+struct ExitConfig {
+	validator: ValidatorId,
+	epoch: Option<Epoch>,
+}
 
-	let mut proof = Vec::new();
-	for i in 0..(beacon::consts::DEPOSIT_CONTRACT_TREE_DEPTH as usize) {
-		let subindex = (item_index / 2usize.pow(i as u32)) ^ 1;
-		if subindex < tree[i].len() {
-			proof.push(tree[i][subindex]);
-		} else {
-			proof.push(zerohashes[i]);
-		}
-	}
-	proof.push(tree[32][1]);
-	proof
+struct ApiConfig {
+	address: SocketAddr,
+	health: HealthConfig,
+	tracked_validators: Vec<ValidatorId>,
 }
 
+struct SettingsConfig {
+	path: PathBuf,
+	startup: BTreeMap<String, Option<String>>,
+}
+
+/// Command line settings compared on settings reload, reported as requiring
+/// a restart when changed.
+const STARTUP_VALUES: &[&str] = &[
+	"port", "data", "libp2p-nodes", "genesis-state", "validator-keys", "chain", "config",
+	"config-file", "eth1-mock-deposits", "eth1-endpoint", "eth1-deposit-contract",
+	"eth1-deploy-block", "eth1-follow-distance", "quickstart-validators", "genesis-time",
+	"api-port", "api-address", "ready-max-slot-distance", "ready-min-peers", "chain-spec",
+	"weak-subjectivity",
+];
+const STARTUP_FLAGS: &[&str] = &["author", "slasher"];
+
 #[derive(Serialize, Deserialize, Debug)]
 #[serde(deny_unknown_fields)]
 struct ValidatorKey {
@@ -122,6 +139,14 @@ struct ValidatorKey {
 	pub pubkey: String,
 }
 
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct MockDeposit {
+	pub timestamp: u64,
+	pub privkey: String,
+	pub amount: Gwei,
+}
+
 fn string_to_bytes(string: &str) -> Result<Vec<u8>, String> {
     let string = if string.starts_with("0x") {
         &string[2..]
@@ -132,9 +157,28 @@ fn string_to_bytes(string: &str) -> Result<Vec<u8>, String> {
     hex::decode(string).map_err(|e| format!("Unable to decode public or private key: {}", e))
 }
 
-fn main() {
-	pretty_env_logger::init();
+fn string_to_validator_id(string: &str) -> Result<ValidatorId, String> {
+	let bytes = string_to_bytes(string)?;
+	if bytes.len() != ValidatorId::len_bytes() {
+		return Err(format!(
+			"Public key must be {} bytes, got {}", ValidatorId::len_bytes(), bytes.len(),
+		))
+	}
+	Ok(ValidatorId::from_slice(&bytes))
+}
+
+fn string_to_secret(string: &str) -> bls::Secret {
+	const PRIVATE_KEY_BYTES: usize = 48;
 
+	let privkey = string_to_bytes(string).unwrap();
+	let mut bytes = vec![0; PRIVATE_KEY_BYTES - privkey.len()];
+	bytes.extend_from_slice(&privkey);
+	bls::Secret::from_bytes(&bytes)
+		.map_err(|e| format!("Failed to decode bytes into secret key: {:?}", e))
+		.unwrap()
+}
+
+fn main() {
 	let matches = App::new("Shasper blockchain client")
 		.arg(Arg::with_name("port")
 			 .short("p")
@@ -147,6 +191,44 @@ fn main() {
 			 .long("data")
 			 .takes_value(true)
 			 .help("Use rocksdb instead of in-memory database"))
+		.arg(Arg::with_name("storage-mode")
+			 .long("storage-mode")
+			 .takes_value(true)
+			 .possible_values(&["archive", "prune", "minimal"])
+			 .help("States kept by the rocksdb database; cannot be changed once created"))
+		.arg(Arg::with_name("db-sync")
+			 .long("db-sync")
+			 .takes_value(true)
+			 .help("When rocksdb writes are synced to disk: never, always, or every given number of writes"))
+		.arg(Arg::with_name("snapshot-interval")
+			 .long("snapshot-interval")
+			 .takes_value(true)
+			 .help("Number of blocks between finalized states kept in prune storage mode"))
+		.arg(Arg::with_name("freezer")
+			 .long("freezer")
+			 .takes_value(true)
+			 .help("Directory to move finalized blocks of the rocksdb database into"))
+		.arg(Arg::with_name("export-era")
+			 .long("export-era")
+			 .takes_value(true)
+			 .help("Export finalized blocks and states as era files into a directory, then exit"))
+		.arg(Arg::with_name("import-era")
+			 .long("import-era")
+			 .takes_value(true)
+			 .help("Import blocks of era files in a directory before starting"))
+		.arg(Arg::with_name("replay")
+			 .long("replay")
+			 .takes_value(true)
+			 .help("Re-execute stored canonical blocks of a slot range <from>:<to>, then exit"))
+		.arg(Arg::with_name("replay-dump")
+			 .long("replay-dump")
+			 .takes_value(true)
+			 .requires("replay")
+			 .help("File to write the state root and execution time of each replayed block to"))
+		.arg(Arg::with_name("max-state-replay")
+			 .long("max-state-replay")
+			 .takes_value(true)
+			 .help("Maximum number of blocks replayed to regenerate a state that is not stored"))
 		.arg(Arg::with_name("libp2p-nodes")
 			 .long("libp2p-nodes")
 			 .takes_value(true)
@@ -154,6 +236,17 @@ fn main() {
 		.arg(Arg::with_name("author")
 			 .long("author")
 			 .help("Whether to author blocks"))
+		.arg(Arg::with_name("slasher")
+			 .long("slasher")
+			 .help("Whether to detect slashable blocks and attestations"))
+		.arg(Arg::with_name("check-state-root")
+			 .long("check-state-root")
+			 .help("Report both roots when an imported block state root mismatches"))
+		.arg(Arg::with_name("trusted-monitor")
+			 .long("trusted-monitor")
+			 .takes_value(true)
+			 .multiple(true)
+			 .help("API endpoint of a trusted node; block production stops if finality conflicts with it"))
 		.arg(Arg::with_name("genesis-state")
 			 .long("genesis-state")
 			 .takes_value(true)
@@ -170,25 +263,164 @@ fn main() {
 			 .long("config")
 			 .takes_value(true)
 			 .help("Config to use"))
+		.arg(Arg::with_name("config-file")
+			 .long("config-file")
+			 .takes_value(true)
+			 .help("Yaml spec configuration file, selecting the matching config or a dynamic config over a compatible one"))
+		.arg(Arg::with_name("eth1-mock-deposits")
+			 .long("eth1-mock-deposits")
+			 .takes_value(true)
+			 .help("Yaml deposit schedule for the mock eth1 chain, timestamps relative to genesis"))
+		.arg(Arg::with_name("eth1-endpoint")
+			 .long("eth1-endpoint")
+			 .takes_value(true)
+			 .help("HTTP JSON-RPC endpoint of an eth1 node to follow deposits from"))
+		.arg(Arg::with_name("eth1-deposit-contract")
+			 .long("eth1-deposit-contract")
+			 .takes_value(true)
+			 .help("Deposit contract address, defaults to the one of the preset chain"))
+		.arg(Arg::with_name("eth1-deploy-block")
+			 .long("eth1-deploy-block")
+			 .takes_value(true)
+			 .help("Eth1 block the deposit contract was deployed at"))
+		.arg(Arg::with_name("eth1-follow-distance")
+			 .long("eth1-follow-distance")
+			 .takes_value(true)
+			 .help("Number of eth1 blocks to stay behind the eth1 head"))
+		.arg(Arg::with_name("quickstart-validators")
+			 .long("quickstart-validators")
+			 .takes_value(true)
+			 .help("Generate interop genesis with the given number of deterministic validators"))
+		.arg(Arg::with_name("genesis-time")
+			 .long("genesis-time")
+			 .takes_value(true)
+			 .help("Genesis time of generated genesis, defaults to now"))
+		.arg(Arg::with_name("api-port")
+			 .long("api-port")
+			 .takes_value(true)
+			 .help("Port for the HTTP API server, disabled if not set"))
+		.arg(Arg::with_name("api-address")
+			 .long("api-address")
+			 .takes_value(true)
+			 .validator(|value| value.parse::<std::net::IpAddr>().map(|_| ()).map_err(|e| format!("{}", e)))
+			 .help("IP address for the HTTP API server to listen on, defaults to 127.0.0.1"))
+		.arg(Arg::with_name("ready-max-slot-distance")
+			 .long("ready-max-slot-distance")
+			 .takes_value(true)
+			 .help("Maximum head distance from wall clock slot to be ready"))
+		.arg(Arg::with_name("ready-min-peers")
+			 .long("ready-min-peers")
+			 .takes_value(true)
+			 .help("Minimum connected peers to be ready"))
+		.arg(Arg::with_name("track-validator")
+			 .long("track-validator")
+			 .takes_value(true)
+			 .multiple(true)
+			 .validator(|value| string_to_validator_id(&value).map(|_| ()))
+			 .help("Public key of a validator whose balance is exported as metrics"))
+		.arg(Arg::with_name("chain-spec")
+			 .long("chain-spec")
+			 .takes_value(true)
+			 .help("Yaml network bundle to register as a custom preset"))
+		.arg(Arg::with_name("graffiti")
+			 .long("graffiti")
+			 .takes_value(true)
+			 .help("Graffiti of produced blocks, as text or 0x-prefixed hex"))
+		.arg(Arg::with_name("validator-graffiti")
+			 .long("validator-graffiti")
+			 .takes_value(true)
+			 .multiple(true)
+			 .validator(|value| {
+				 let pubkey = value.splitn(2, '=').next().expect("Split always has a first part; qed");
+				 string_to_validator_id(pubkey).map(|_| ())
+			 })
+			 .help("Graffiti of blocks produced by one validator, as <pubkey>=<graffiti>"))
+		.arg(Arg::with_name("graffiti-file")
+			 .long("graffiti-file")
+			 .takes_value(true)
+			 .help("File of graffiti, one per line, used in turn by produced blocks"))
+		.arg(Arg::with_name("settings-file")
+			 .long("settings-file")
+			 .takes_value(true)
+			 .help("Yaml file of settings reloaded on SIGHUP or through the API"))
+		.arg(Arg::with_name("weak-subjectivity")
+			 .long("weak-subjectivity")
+			 .takes_value(true)
+			 .possible_values(&["warn", "refuse"])
+			 .help("Whether to warn or refuse to run when the finalized checkpoint is older than the weak subjectivity period"))
+		.arg(Arg::with_name("log-json")
+			 .long("log-json")
+			 .help("Write logs and span timings as JSON lines"))
+		.subcommand(SubCommand::with_name("export-state")
+			 .about("Export the canonical state at a slot as SSZ, then exit")
+			 .arg(Arg::with_name("slot")
+				  .long("slot")
+				  .takes_value(true)
+				  .required(true)
+				  .help("Slot of the exported state"))
+			 .arg(Arg::with_name("output")
+				  .long("output")
+				  .takes_value(true)
+				  .required(true)
+				  .help("File to write the SSZ-encoded state to")))
+		.subcommand(SubCommand::with_name("exit")
+			 .about("Broadcast a voluntary exit for a local validator, then exit once it is included")
+			 .arg(Arg::with_name("validator")
+				  .long("validator")
+				  .takes_value(true)
+				  .required(true)
+				  .validator(|value| string_to_validator_id(&value).map(|_| ()))
+				  .help("Public key of the exiting validator, whose key must be in the validator keys"))
+			 .arg(Arg::with_name("epoch")
+				  .long("epoch")
+				  .takes_value(true)
+				  .help("Epoch of the exit, defaults to the current epoch")))
 		.get_matches();
 
-	let preset = matches.value_of("chain").map(|name| {
-		shasper_blockchain::preset::presets().get(&name)
-			.expect("Unknown preset").clone()
+	shasper_blockchain::logging::init(matches.is_present("log-json"));
+
+	let mut presets = shasper_blockchain::preset::presets();
+	let custom_chain = matches.value_of("chain-spec").map(|path| {
+		presets.register_file(path).expect("Loading chain spec failed")
 	});
 
-	let config_name = matches.value_of("config")
-		.unwrap_or(if matches.value_of("chain") == Some("sapphire") {
-			"sapphire"
-		} else {
-			"minimal"
+	let preset = matches.value_of("chain").map(|name| name.to_string())
+		.or(custom_chain)
+		.map(|name| {
+			info!("Using preset chain: {}", name);
+			presets.get(&name).expect("Unknown preset").clone()
 		});
 
+	let config_file = matches.value_of("config-file").map(|path| {
+		let spec = SpecConfig::load(path).expect("Loading spec configuration failed");
+		match spec.matching_config() {
+			Ok(name) => name.to_string(),
+			Err(_) => {
+				let (base, values) = spec.dynamic_config()
+					.expect("Spec configuration does not match any base config");
+				values.install().expect("Config values are only installed once; qed");
+				format!("dynamic-{}", base)
+			},
+		}
+	});
+
+	let config_name = matches.value_of("config")
+		.map(|name| name.to_string())
+		.or(config_file)
+		.or(preset.as_ref().map(|preset| preset.config.clone()))
+		.unwrap_or("minimal".to_string());
+
 	info!("Using chain config: {}", config_name);
-	match config_name {
+	match config_name.as_str() {
 		"minimal" => main_with_config::<beacon::MinimalConfig>(matches, preset),
 		"mainnet" => main_with_config::<beacon::MainnetConfig>(matches, preset),
 		"sapphire" => main_with_config::<beacon::SapphireConfig>(matches, preset),
+		"dynamic-minimal" =>
+			main_with_config::<beacon::DynamicConfig<beacon::MinimalConfig>>(matches, preset),
+		"dynamic-mainnet" =>
+			main_with_config::<beacon::DynamicConfig<beacon::MainnetConfig>>(matches, preset),
+		"dynamic-sapphire" =>
+			main_with_config::<beacon::DynamicConfig<beacon::SapphireConfig>>(matches, preset),
 		e => panic!("Unknown config name: {:?}", e),
 	}
 }
@@ -200,28 +432,37 @@ fn main_with_config<C: Config>(matches: ArgMatches, preset: Option<Preset>) wher
 	let mut keys: HashMap<ValidatorId, bls::Secret> = HashMap::new();
 
 	if let Some(validator_keys) = matches.value_of("validator-keys") {
-		const PRIVATE_KEY_BYTES: usize = 48;
-
 		let file = File::open(validator_keys).unwrap();
 		let coll = serde_yaml::from_reader::<_, Vec<ValidatorKey>>(BufReader::new(file)).unwrap();
 
 		for key in coll {
-			let privkey = string_to_bytes(&key.privkey).unwrap();
-
-			let sk = {
-				let mut bytes = vec![0; PRIVATE_KEY_BYTES - privkey.len()];
-				bytes.extend_from_slice(&privkey);
-				bls::Secret::from_bytes(&bytes)
-					.map_err(|e| format!("Failed to decode bytes into secret key: {:?}", e))
-					.unwrap()
-			};
-
+			let sk = string_to_secret(&key.privkey);
 			let pubkey = ValidatorId::from_slice(&bls::Public::from_secret_key(&sk).as_bytes()[..]);
 
 			keys.insert(pubkey, sk);
 		}
 	}
 
+	if matches.is_present("eth1-mock-deposits") &&
+		(matches.is_present("genesis-state") || preset.is_some())
+	{
+		panic!("Mock eth1 deposits can only be used with generated genesis");
+	}
+	if matches.is_present("eth1-mock-deposits") && matches.is_present("eth1-endpoint") {
+		panic!("Mock eth1 deposits cannot be used with an eth1 endpoint");
+	}
+
+	if matches.is_present("quickstart-validators") && matches.is_present("eth1-mock-deposits") {
+		panic!("Mock eth1 deposits cannot be used with quickstart genesis");
+	}
+
+	let genesis_time = match matches.value_of("genesis-time") {
+		Some(genesis_time) => u64::from_str(genesis_time).unwrap(),
+		None => SystemTime::now().duration_since(UNIX_EPOCH)
+			.expect("System time is after unix epoch; qed").as_secs(),
+	};
+
+	let mut eth1_provider = None;
 	let genesis_state = if let Some(genesis_file) = matches.value_of("genesis-state") {
 		let mut file = File::open(genesis_file).unwrap();
 		let mut data = Vec::new();
@@ -229,47 +470,61 @@ fn main_with_config<C: Config>(matches: ArgMatches, preset: Option<Preset>) wher
 
 		Decode::decode(&mut &data[..]).unwrap()
 	} else if let Some(preset) = preset.as_ref() {
-		Decode::decode(&mut &preset.genesis_state).unwrap()
+		preset.genesis_state::<C, BLS>().expect("Loading genesis state failed")
+	} else if let Some(count) = matches.value_of("quickstart-validators") {
+		let count = u64::from_str(count).unwrap();
+		for index in 0..count {
+			let seckey = interop_secret(index);
+			let pubkey = ValidatorId::from_slice(&bls::Public::from_secret_key(&seckey).as_bytes()[..]);
+			keys.insert(pubkey, seckey);
+		}
+
+		quickstart::<C, BLS>(count, genesis_time).expect("Generating quickstart genesis failed")
 	} else {
 		let mut deposit_datas = Vec::new();
 		for i in 0..10 {
 			let seckey = bls::Secret::random(&mut rand::thread_rng());
-			let pubkey = ValidatorId::from_slice(&bls::Public::from_secret_key(&seckey).as_bytes()[..]);
-			let mut data = DepositData {
-				pubkey: pubkey.clone(),
-				withdrawal_credentials: H256::from_low_u64_le(i as u64),
-				amount: 32000000000,
-				signature: Default::default(),
-			};
-			let signature = Signature::from_slice(&bls::Signature::new(
-				&tree_root::<sha2::Sha256, _>(&SigningDepositData::from(data.clone()))[..],
-				beacon::genesis_domain(C::domain_deposit()),
-				&seckey
-			).as_bytes()[..]);
-			data.signature = signature;
+			let data = signed_deposit_data::<C>(
+				&seckey, H256::from_low_u64_le(i as u64), Gwei::new(32000000000),
+			);
+			keys.insert(data.pubkey.clone(), seckey);
 			deposit_datas.push(data);
-			keys.insert(pubkey, seckey);
 		}
 
-		let deposit_tree = deposit_tree::<C>(&deposit_datas);
-		let deposits = deposit_datas.clone().into_iter()
-			.enumerate()
-			.map(|(i, deposit_data)| {
-				Deposit {
-					proof: deposit_proof::<C>(&deposit_tree, i).try_into().ok().unwrap(),
-					data: deposit_data,
-				}
-			})
-			.collect::<Vec<_>>();
-		let deposit_root = deposit_root(&deposit_tree);
-		let eth1_data = Eth1Data {
-			deposit_root,
-			deposit_count: deposits.len() as u64,
-			block_hash: Default::default(),
-		};
+		let mut schedule = deposit_datas.iter().map(|data| ScheduledDeposit {
+			timestamp: genesis_time,
+			data: data.clone(),
+		}).collect::<Vec<_>>();
+
+		if let Some(mock_deposits) = matches.value_of("eth1-mock-deposits") {
+			let file = File::open(mock_deposits).unwrap();
+			let coll = serde_yaml::from_reader::<_, Vec<MockDeposit>>(BufReader::new(file)).unwrap();
+
+			for (i, deposit) in coll.into_iter().enumerate() {
+				let seckey = string_to_secret(&deposit.privkey);
+				let data = signed_deposit_data::<C>(
+					&seckey,
+					H256::from_low_u64_le((deposit_datas.len() + i) as u64),
+					deposit.amount,
+				);
+				keys.insert(data.pubkey.clone(), seckey);
+				schedule.push(ScheduledDeposit {
+					timestamp: genesis_time + deposit.timestamp,
+					data,
+				});
+			}
+		}
+
+		let provider = MockEth1Provider::<C>::new(schedule);
+		let eth1_data = provider.eth1_data(genesis_time);
+		let deposits = provider.deposits(
+			0, eth1_data.deposit_count, eth1_data.deposit_count,
+		).unwrap();
+		eth1_provider = Some(Arc::new(provider) as Arc<dyn Eth1Provider<C>>);
+
 		let genesis_state =
 			genesis_beacon_state::<C, BLS>(
-				&deposits, 0, eth1_data.clone()
+				&deposits, genesis_time, eth1_data
 			).unwrap();
 
 		genesis_state
@@ -278,9 +533,37 @@ fn main_with_config<C: Config>(matches: ArgMatches, preset: Option<Preset>) wher
 		state_root: tree_root::<<C as Config>::Digest, _>(&genesis_state),
 		..Default::default()
 	});
-	let eth1_data = genesis_state.eth1_data.clone();
+	let eth1_follower = matches.value_of("eth1-endpoint").map(|endpoint| {
+		let deposit_contract = matches.value_of("eth1-deposit-contract")
+			.map(|address| address.to_string())
+			.or(preset.as_ref().and_then(|preset| preset.deposit_contract_address.clone()))
+			.expect("Deposit contract address must be set to follow eth1");
+
+		Eth1Follower::<C>::new(Eth1FollowerConfig {
+			endpoint: endpoint.to_string(),
+			deposit_contract,
+			deploy_block: matches.value_of("eth1-deploy-block")
+				.map(|block| u64::from_str(block).unwrap())
+				.unwrap_or(0),
+			follow_distance: matches.value_of("eth1-follow-distance")
+				.map(|distance| u64::from_str(distance).unwrap())
+				.unwrap_or(ETH1_FOLLOW_DISTANCE),
+		}, genesis_state.eth1_data.clone())
+	});
+	if let Some(follower) = eth1_follower.as_ref() {
+		eth1_provider = Some(Arc::new(follower.clone()) as Arc<dyn Eth1Provider<C>>);
+	}
+	let eth1_provider = eth1_provider.unwrap_or_else(|| {
+		Arc::new(StaticEth1Provider::<C>::new(genesis_state.eth1_data.clone()))
+	});
+
+	let fork_schedule = ForkSchedule::new(genesis_state.fork.current_version, genesis_block.id());
+	let current_epoch = Epoch::new(SystemTime::now().duration_since(UNIX_EPOCH)
+		.expect("System time is after unix epoch; qed").as_secs()
+		.saturating_sub(genesis_state.genesis_time) / C::seconds_per_slot() / C::slots_per_epoch());
 
 	let mut network_config = NetworkConfig::default();
+	network_config.fork_id = Some(shasper_network::enr_fork_id::<C>(&fork_schedule, current_epoch));
 	network_config.libp2p_port = u16::from_str(matches.value_of("port").unwrap()).unwrap();
 	network_config.discovery_port = u16::from_str(matches.value_of("port").unwrap()).unwrap();
 	network_config.libp2p_nodes = if let Some(nodes) = matches.value_of("libp2p-nodes") {
@@ -296,21 +579,156 @@ fn main_with_config<C: Config>(matches: ArgMatches, preset: Option<Preset>) wher
 		Vec::new()
 	};
 
+	let monitors = matches.values_of("trusted-monitor")
+		.map(|values| values.map(|value| value.to_string()).collect())
+		.unwrap_or_default();
+
+	let api_config = matches.value_of("api-port").map(|port| {
+		let mut health = HealthConfig::default();
+		if let Some(distance) = matches.value_of("ready-max-slot-distance") {
+			health.max_head_slot_distance = u64::from_str(distance).unwrap();
+		}
+		if let Some(peers) = matches.value_of("ready-min-peers") {
+			health.min_peers = usize::from_str(peers).unwrap();
+		}
+
+		let tracked_validators = matches.values_of("track-validator")
+			.map(|values| values.map(|value| {
+				string_to_validator_id(value).expect("Validated by clap; qed")
+			}).collect())
+			.unwrap_or_default();
+
+		ApiConfig {
+			address: SocketAddr::new(
+				matches.value_of("api-address").unwrap_or("127.0.0.1")
+					.parse().expect("Validated by clap; qed"),
+				u16::from_str(port).unwrap(),
+			),
+			health,
+			tracked_validators,
+		}
+	});
+
+	let settings_config = matches.value_of("settings-file").map(|path| {
+		let mut startup = BTreeMap::new();
+		for name in STARTUP_VALUES {
+			startup.insert(name.to_string(), matches.value_of(name).map(|value| value.to_string()));
+		}
+		for name in STARTUP_FLAGS {
+			startup.insert(name.to_string(), Some(matches.is_present(name).to_string()));
+		}
+		SettingsConfig { path: PathBuf::from(path), startup }
+	});
+
+	let graffiti_config = GraffitiConfig {
+		graffiti: matches.value_of("graffiti")
+			.map(|value| parse_graffiti(value).expect("Invalid graffiti")),
+		validators: matches.values_of("validator-graffiti")
+			.map(|values| values.map(|value| {
+				let mut parts = value.splitn(2, '=');
+				let pubkey = parts.next().expect("Split always has a first part; qed");
+				let graffiti = parts.next().expect("Validator graffiti must be <pubkey>=<graffiti>");
+				(
+					string_to_validator_id(pubkey).expect("Validated by clap; qed"),
+					parse_graffiti(graffiti).expect("Invalid graffiti"),
+				)
+			}).collect())
+			.unwrap_or_default(),
+		file: matches.value_of("graffiti-file").map(PathBuf::from),
+	};
+
+	let max_state_replay = matches.value_of("max-state-replay")
+		.map(|max| usize::from_str(max).expect("Invalid max state replay"))
+		.unwrap_or(shasper_blockchain::regen::DEFAULT_MAX_REPLAY);
+
+	let era_config = EraConfig {
+		export: matches.value_of("export-era").map(PathBuf::from),
+		import: matches.value_of("import-era").map(PathBuf::from),
+	};
+
+	let replay_config = matches.value_of("replay").map(|range| {
+		let mut parts = range.splitn(2, ':');
+		let from = parts.next().expect("Split always has a first part; qed");
+		let to = parts.next().expect("Replay range must be <from>:<to>");
+		ReplayConfig {
+			from: Slot::from_str(from).expect("Invalid replay start slot"),
+			to: Slot::from_str(to).expect("Invalid replay end slot"),
+			dump: matches.value_of("replay-dump").map(PathBuf::from),
+		}
+	});
+
+	let export_state_config = matches.subcommand_matches("export-state").map(|matches| {
+		ExportStateConfig {
+			slot: matches.value_of("slot").map(Slot::from_str)
+				.expect("Slot is required; qed")
+				.expect("Invalid export slot"),
+			output: matches.value_of("output").map(PathBuf::from)
+				.expect("Output is required; qed"),
+		}
+	});
+
+	let exit_config = matches.subcommand_matches("exit").map(|matches| {
+		let validator = matches.value_of("validator").expect("Validator is required; qed");
+		ExitConfig {
+			validator: string_to_validator_id(validator).expect("Validated by clap; qed"),
+			epoch: matches.value_of("epoch")
+				.map(|epoch| Epoch::from_str(epoch).expect("Invalid exit epoch")),
+		}
+	});
+
+	let ws_policy = matches.value_of("weak-subjectivity")
+		.map(|policy| WeakSubjectivityPolicy::from_str(policy).expect("Invalid weak subjectivity policy"))
+		.unwrap_or_default();
+
 	if let Some(path) = matches.value_of("data") {
 		info!("Using RocksDB backend");
+		let storage_mode = match matches.value_of("storage-mode") {
+			Some("prune") => StorageMode::Prune(
+				matches.value_of("snapshot-interval")
+					.map(|interval| u64::from_str(interval).expect("Invalid snapshot interval"))
+					.unwrap_or(DEFAULT_SNAPSHOT_INTERVAL)
+			),
+			Some("minimal") => StorageMode::Minimal,
+			_ => StorageMode::Archive,
+		};
+		let sync_policy = match matches.value_of("db-sync") {
+			Some("always") => SyncPolicy::Always,
+			Some("never") | None => SyncPolicy::Never,
+			Some(writes) => SyncPolicy::Every(u32::from_str(writes).expect("Invalid db sync policy")),
+		};
+		info!("Using storage mode {:?}, syncing writes {:?}", storage_mode, sync_policy);
 		let backend = ShasperBackend::new(
-			RocksBackend::<_, (), RocksState<C>>::open_or_create(path, |_| {
-				Ok((genesis_block.clone(), genesis_state.into()))
-			}).unwrap()
-		);
+			RocksBackend::<_, (), RocksState<C>>::open_or_create_with_mode(
+				path, storage_mode, sync_policy, |_| Ok((genesis_block.clone(), genesis_state.into())),
+			).and_then(|backend| match matches.value_of("freezer") {
+				Some(freezer) => backend.with_freezer(freezer),
+				None => Ok(backend),
+			}).expect("Opening database failed")
+		).with_regenerator(StateRegenerator::<C, BLS>::new(max_state_replay));
 		let lock = ImportLock::new();
+		let duties = DutyHistory::open(Path::new(path).join("duties.json"))
+			.expect("Loading duty history failed");
 
 		run(network_config,
 			matches.is_present("author"),
+			matches.is_present("slasher"),
+			matches.is_present("check-state-root"),
+			monitors,
 			backend,
 			lock,
-			eth1_data,
-			keys);
+			eth1_provider,
+			eth1_follower,
+			keys,
+			duties,
+			api_config,
+			settings_config,
+			graffiti_config,
+			era_config,
+			replay_config,
+			export_state_config,
+			exit_config,
+			ws_policy,
+			fork_schedule);
 	} else {
 		info!("Using in-memory backend");
 		let backend = ShasperBackend::new(
@@ -318,218 +736,869 @@ fn main_with_config<C: Config>(matches: ArgMatches, preset: Option<Preset>) wher
 				genesis_block.clone(),
 				genesis_state.into(),
 			)
-		);
+		).with_regenerator(StateRegenerator::<C, BLS>::new(max_state_replay));
 		let lock = ImportLock::new();
 
 		run(network_config,
 			matches.is_present("author"),
+			matches.is_present("slasher"),
+			matches.is_present("check-state-root"),
+			monitors,
 			backend,
 			lock,
-			eth1_data,
-			keys);
+			eth1_provider,
+			eth1_follower,
+			keys,
+			DutyHistory::new(),
+			api_config,
+			settings_config,
+			graffiti_config,
+			era_config,
+			replay_config,
+			export_state_config,
+			exit_config,
+			ws_policy,
+			fork_schedule);
 	}
 }
 
 fn run<B, C: Config>(
 	config: NetworkConfig,
 	author: bool,
+	slasher: bool,
+	check_state_root: bool,
+	monitors: Vec<String>,
 	backend: B,
 	import_lock: ImportLock,
-	eth1_data: Eth1Data,
+	eth1_provider: Arc<dyn Eth1Provider<C>>,
+	eth1_follower: Option<Eth1Follower<C>>,
 	keys: HashMap<ValidatorId, bls::Secret>,
+	duties: DutyHistory,
+	api_config: Option<ApiConfig>,
+	settings_config: Option<SettingsConfig>,
+	graffiti_config: GraffitiConfig,
+	era_config: EraConfig,
+	replay_config: Option<ReplayConfig>,
+	export_state_config: Option<ExportStateConfig>,
+	exit_config: Option<ExitConfig>,
+	ws_policy: WeakSubjectivityPolicy,
+	fork_schedule: ForkSchedule,
 ) where
 	Block<C>: ssz::Encode + ssz::Decode + Unpin + Send + Sync,
-	B: ChainQuery + AncestorQuery + Store<Block=Block<C>>,
-	B::State: StateExternalities + AsExternalities<dyn StateExternalities<Config=C>>,
+	B: ChainQuery + AncestorQuery + BranchPruning + StateStorage + Freezing + CanonSlotQuery,
+	B: Store<Block=Block<C>>,
+	B::State: StateExternalities<Config=C> + AsExternalities<dyn StateExternalities<Config=C>>,
+	B::State: From<BeaconState<C>>,
 	B::Auxiliary: Auxiliary<Block<C>> + Unpin,
 	B: SharedCommittable<Operation=Operation<<B as Store>::Block, <B as Store>::State, <B as Store>::Auxiliary>>,
 	B: Send + Sync + 'static,
 	C: Unpin + Clone + Send + Sync + 'static,
 {
-	let executor = Executor::<C, BLS>::new();
-	let importer = MutexImporter::new(
-		ArchiveGhostImporter::new(executor, backend.clone(), import_lock.clone())
-	);
-
-	if author {
-		let backend_build = backend.clone();
-		let importer_build = importer.clone();
-		thread::spawn(move || {
-			builder_thread(backend_build, importer_build, eth1_data, keys);
-		});
+	let executor = if check_state_root {
+		Executor::<C, BLS>::new().with_state_root_check()
+	} else {
+		Executor::<C, BLS>::new()
+	}.with_fork_schedule(fork_schedule.clone());
+	let verifier = SignatureVerifier::new(executor.clone(), backend.clone());
+	let genesis_time = backend.state_at(&backend.genesis())
+		.expect("Genesis state must exist")
+		.state().genesis_time;
+	let clock: Arc<dyn SlotClock> = Arc::new(SystemTimeSlotClock::new(
+		genesis_time, Duration::from_secs(C::seconds_per_slot()),
+	));
+	let ghost_clock = clock.clone();
+	let ghost = ProtoArrayGhostImporter::new(executor.clone(), backend.clone(), import_lock.clone())
+		.expect("Loading fork choice failed")
+		.with_safe_slots(
+			C::slots_per_epoch(), SAFE_SLOTS_TO_UPDATE_JUSTIFIED,
+			move || ghost_clock.now().map(|slot| slot.as_u64()),
+		);
+	let fork_choice = ghost.fork_choice();
+	let attestation_queue = ghost.attestation_queue();
+	let chain_events = ghost.events();
+	let importer = MutexImporter::new(ghost);
+
+	if let Some(dir) = era_config.import.as_ref() {
+		let imported = import_eras(&backend, &importer, dir).expect("Importing era files failed");
+		info!("Imported {} blocks from era files", imported);
+	}
+	if let Some(dir) = era_config.export.as_ref() {
+		export_eras(&backend, dir).expect("Exporting era files failed");
+		return
+	}
+	if let Some(replay_config) = replay_config {
+		let mut dump = replay_config.dump.as_ref()
+			.map(|path| File::create(path).expect("Creating replay dump file failed"));
+		let report = replay_blocks::<_, C, BLS>(
+			&backend, replay_config.from, replay_config.to,
+			dump.as_mut().map(|file| file as &mut dyn std::io::Write),
+		);
+		match report {
+			Ok(report) => info!("Replay succeeded: {:?}", report),
+			Err(e) => error!("Replay failed: {}", e),
+		}
+		return
+	}
+	if let Some(export_state_config) = export_state_config {
+		let partial = export_state_config.output.with_extension("partial");
+		let mut file = File::create(&partial).expect("Creating state export file failed");
+		match export_state::<_, C>(&backend, export_state_config.slot, &mut file) {
+			Ok(_) => std::fs::rename(&partial, &export_state_config.output)
+				.expect("Moving state export file failed"),
+			Err(e) => {
+				error!("Exporting state failed: {}", e);
+				let _ = std::fs::remove_file(&partial);
+			},
+		}
+		return
 	}
+	let exit_key = match exit_config.as_ref() {
+		Some(exit_config) => match keys.get(&exit_config.validator) {
+			Some(seckey) => Some(seckey.clone()),
+			None => {
+				error!("No validator key for exiting validator {}", exit_config.validator);
+				return
+			},
+		},
+		None => None,
+	};
+
+	let network_status = NetworkStatus::new();
+	let peers = PeerManager::new();
+	let node_metrics = NodeMetrics::default();
+	let pool = SharedOperationPool::<C, BLS>::new();
+	let subnets = AttestationSubnets::new(keys.len());
+	let gossip = GossipQueue::<C>::new();
+	let safety = SafetySwitch::new();
+	let inactivity = InactivityTracker::new();
+	let mut local_validators = keys.keys().cloned().collect::<Vec<_>>();
+	if let Some(api_config) = api_config.as_ref() {
+		local_validators.extend(api_config.tracked_validators.iter().cloned());
+	}
+	let settings = LiveSettings::new(local_validators);
+	settings.set_graffiti_config(graffiti_config);
+	let settings_file = settings_config.map(|settings_config| {
+		let file = SettingsFile::new(
+			settings_config.path, settings_config.startup, settings.clone(), peers.clone(),
+		);
+		file.reload().expect("Loading settings file failed");
+		file
+	});
+
+	if let Some(current_slot) = clock.now() {
+		let status = check_weak_subjectivity(&backend, current_slot)
+			.expect("Checking weak subjectivity failed");
+		if status.is_stale() && ws_policy == WeakSubjectivityPolicy::Refuse {
+			error!("Refusing to start with a finalized checkpoint outside of the weak subjectivity period");
+			return
+		}
+	}
+
+	// Database and chain are set up above, the remaining services are
+	// started in order and stopped in reverse order.
+	let mut services = ServiceManager::new();
+	let started = (|| {
+		{
+			let backend = backend.clone();
+			let importer = importer.clone();
+			let network_status = network_status.clone();
+			let subnets = subnets.clone();
+			let import_lock = import_lock.clone();
+			let peers = peers.clone();
+			let gossip = gossip.clone();
+			let attestations = GossipAttestations::new(
+				AttestationValidator::new(
+					backend.clone(), executor.clone(), CheckpointCache::default(),
+					AdvancedStateCache::default(), clock.clone(),
+				),
+				pool.clone(),
+				attestation_queue.clone(),
+			);
+			let blocks = BlockValidator::<_, BLS, _>::new(
+				backend.clone(), AdvancedStateCache::default(), clock.clone(),
+			);
+			services.start("network", Duration::from_secs(5), move |context| {
+				context.ready();
+				let exit = context.exit().clone();
+				shasper_network::start_network_simple_sync(
+					backend, import_lock, importer, verifier, blocks, attestations, config,
+					network_status, subnets, peers, gossip,
+					move || exit.is_signaled(),
+				).map_err(|e| format!("{:?}", e))
+			})?;
+		}
+
+		{
+			let attestation_queue = attestation_queue.clone();
+			let clock = clock.clone();
+			services.start("fork-choice", Duration::from_secs(5), move |context| {
+				context.ready();
+				let exit = context.exit().clone();
+				while !exit.wait_timeout(Duration::from_millis(500)) {
+					if let Some(slot) = clock.now() {
+						attestation_queue.on_tick(slot.as_u64());
+					}
+				}
+				Ok(())
+			})?;
+		}
+
+		{
+			let mut pruner = ForkPruner::new(
+				backend.clone(), import_lock.clone(), fork_choice.clone(), settings.clone(),
+			);
+			let backend = backend.clone();
+			let import_lock = import_lock.clone();
+			let interval = Duration::from_secs(C::seconds_per_slot() * C::slots_per_epoch());
+			services.start("gc", Duration::from_secs(5), move |context| {
+				context.ready();
+				let exit = context.exit().clone();
+				while !exit.wait_timeout(interval) {
+					if let Err(e) = pruner.prune() {
+						warn!("Pruning abandoned forks failed: {:?}", e);
+					}
+					if let Err(e) = prune_finalized_states(&backend, &import_lock) {
+						warn!("Pruning finalized states failed: {:?}", e);
+					}
+					if let Err(e) = freeze_finalized_blocks(&backend, &import_lock) {
+						warn!("Freezing finalized blocks failed: {:?}", e);
+					}
+				}
+				Ok(())
+			})?;
+		}
+
+		if let Some(follower) = eth1_follower {
+			let interval = Duration::from_secs(C::seconds_per_slot());
+			services.start("eth1", Duration::from_secs(5), move |context| {
+				context.ready();
+				let exit = context.exit().clone();
+				loop {
+					if let Err(e) = follower.update() {
+						warn!("Following eth1 chain failed: {:?}", e);
+					}
+					if exit.wait_timeout(interval) {
+						break
+					}
+				}
+				Ok(())
+			})?;
+		}
+
+		if !monitors.is_empty() {
+			let monitor = FinalityMonitor::new(backend.clone(), monitors, safety.clone());
+			let safety = safety.clone();
+			let node_metrics = node_metrics.clone();
+			let interval = Duration::from_secs(C::seconds_per_slot() * C::slots_per_epoch());
+			services.start("safety", Duration::from_secs(5), move |context| {
+				context.ready();
+				let exit = context.exit().clone();
+				loop {
+					if let Err(e) = monitor.check() {
+						warn!("Checking finality against monitors failed: {:?}", e);
+					}
+					node_metrics.safety_switch_tripped.set(safety.is_tripped() as usize);
+					if exit.wait_timeout(interval) {
+						break
+					}
+				}
+				Ok(())
+			})?;
+		}
+
+		{
+			let backend = backend.clone();
+			let clock = clock.clone();
+			let safety = safety.clone();
+			let interval = Duration::from_secs(C::seconds_per_slot() * C::slots_per_epoch());
+			services.start("weak-subjectivity", Duration::from_secs(5), move |context| {
+				context.ready();
+				let exit = context.exit().clone();
+				while !exit.wait_timeout(interval) {
+					let current_slot = match clock.now() {
+						Some(current_slot) => current_slot,
+						None => continue,
+					};
+					match check_weak_subjectivity(&backend, current_slot) {
+						Ok(status) => if status.is_stale() && ws_policy == WeakSubjectivityPolicy::Refuse {
+							safety.trip(format!(
+								"Finalized checkpoint at epoch {} is outside of the weak subjectivity period",
+								status.finalized.epoch,
+							));
+						},
+						Err(e) => warn!("Checking weak subjectivity failed: {:?}", e),
+					}
+				}
+				Ok(())
+			})?;
+		}
+
+		{
+			let backend = backend.clone();
+			let inactivity = inactivity.clone();
+			let interval = Duration::from_secs(C::seconds_per_slot() * C::slots_per_epoch());
+			services.start("inactivity", Duration::from_secs(5), move |context| {
+				context.ready();
+				let exit = context.exit().clone();
+				while !exit.wait_timeout(interval) {
+					let mut state = match backend.state_at(&backend.head()) {
+						Ok(state) => state,
+						Err(e) => {
+							warn!("Reading head state for inactivity tracking failed: {:?}", e);
+							continue
+						},
+					};
+					if let Err(e) = inactivity.record_state(state.state_mut()) {
+						warn!("Tracking inactivity penalties failed: {:?}", e);
+					}
+				}
+				Ok(())
+			})?;
+		}
+
+		if slasher {
+			let mut chain_slasher = ChainSlasher::new(
+				backend.clone(), Slasher::<C>::new(), pool.clone(),
+			);
+			let interval = Duration::from_secs(C::seconds_per_slot());
+			services.start("slasher", Duration::from_secs(5), move |context| {
+				context.ready();
+				let exit = context.exit().clone();
+				while !exit.wait_timeout(interval) {
+					if let Err(e) = chain_slasher.process() {
+						warn!("Slasher failed to process chain: {:?}", e);
+					}
+				}
+				Ok(())
+			})?;
+		}
+
+		if let Some(settings_file) = settings_file.clone() {
+			let reload = Arc::new(AtomicBool::new(false));
+			signal_hook::flag::register(signal_hook::SIGHUP, reload.clone())
+				.map_err(|e| format!("Registering SIGHUP handler failed: {}", e))?;
+			services.start("settings", Duration::from_secs(2), move |context| {
+				context.ready();
+				let exit = context.exit().clone();
+				while !exit.wait_timeout(Duration::from_secs(1)) {
+					if reload.swap(false, Ordering::SeqCst) {
+						if let Err(e) = settings_file.reload() {
+							warn!("Reloading settings failed: {}", e);
+						}
+					}
+				}
+				Ok(())
+			})?;
+		}
+
+		if let Some(api_config) = api_config {
+			let api_events = ApiEvents::new();
+			{
+				let backend = backend.clone();
+				let api_events = api_events.clone();
+				let events = chain_events.subscribe();
+				services.start("events", Duration::from_secs(2), move |context| {
+					context.ready();
+					api_events.forward_chain_events(&backend, events, context.exit());
+					Ok(())
+				})?;
+			}
+
+			let mut server = ApiServer::new();
+			register_health(
+				&mut server, backend.clone(), network_status.clone(), clock.clone(), api_config.health,
+			);
+			register_metrics(
+				&mut server, backend.clone(), executor.pubkeys().clone(), node_metrics.clone(),
+				settings.clone(),
+			);
+			register_pool(
+				&mut server, backend.clone(), pool.clone(), CheckpointCache::default(),
+				attestation_queue.clone(), executor.clone(), gossip.clone(), api_events.clone(),
+			);
+			register_events(&mut server, api_events);
+			register_attestations(
+				&mut server, backend.clone(), executor.clone(), clock.clone(),
+			);
+			register_peers(&mut server, peers.clone());
+			register_fork_choice(&mut server, backend.clone(), fork_choice);
+			register_duties(&mut server, duties.clone(), clock.clone());
+			register_validator_duties(&mut server, backend.clone(), executor.clone(), clock.clone());
+			register_blocks(
+				&mut server, backend.clone(), importer.clone(), executor.clone(), pool.clone(),
+				eth1_provider.clone(), settings.clone(), clock.clone(),
+			);
+			register_consensus(&mut server, backend.clone());
+			register_fork_schedule(&mut server, fork_schedule.clone());
+			register_light_client(&mut server, backend.clone());
+			register_randao(&mut server, backend.clone());
+			register_rewards(&mut server, backend.clone(), executor.clone());
+			register_inactivity(&mut server, backend.clone(), inactivity.clone());
+			if let Some(settings_file) = settings_file {
+				register_settings(&mut server, settings_file);
+			}
+			services.start("api", Duration::from_secs(2), move |context| {
+				server.serve(api_config.address, context)
+			})?;
+		}
+
+		if author {
+			let backend = backend.clone();
+			let importer = importer.clone();
+			let node_metrics = node_metrics.clone();
+			let clock = clock.clone();
+			let pool = pool.clone();
+			let subnets = subnets.clone();
+			let duties = duties.clone();
+			let safety = safety.clone();
+			let settings = settings.clone();
+			let attestation_queue = attestation_queue.clone();
+			let events = chain_events.subscribe();
+			let executor = executor.clone();
+			services.start("validator", Duration::from_secs(5), move |context| {
+				context.ready();
+				builder_thread(
+					backend, importer, executor, eth1_provider, keys, clock, pool, subnets, duties,
+					safety, settings, attestation_queue, events, node_metrics, context.exit().clone(),
+				);
+				Ok(())
+			})?;
+		}
+
+		if let (Some(exit_config), Some(seckey)) = (exit_config, exit_key) {
+			let backend = backend.clone();
+			let network_status = network_status.clone();
+			let pool = pool.clone();
+			let gossip = gossip.clone();
+			let pubkeys = executor.pubkeys().clone();
+			let interval = Duration::from_secs(C::seconds_per_slot());
+			services.start("exit", Duration::from_secs(5), move |context| {
+				context.ready();
+				let exit = context.exit().clone();
+				let mut published_at = None;
+				while !exit.wait_timeout(interval) {
+					let mut state = match backend.state_at(&backend.head()) {
+						Ok(state) => state,
+						Err(e) => {
+							warn!("Reading head state for voluntary exit failed: {:?}", e);
+							continue
+						},
+					};
+					let slot = state.state().slot;
+					if let Some(index) = validator_index(&pubkeys, state.state(), &exit_config.validator) {
+						if is_exiting(state.state(), index) {
+							info!("Validator {} is exiting as of slot {}", exit_config.validator, slot);
+							return Ok(())
+						}
+					}
+					if network_status.peer_count() == 0 {
+						continue
+					}
+					// Republish once per epoch until the exit is included.
+					if published_at.map(|published: Slot| slot < published + C::slots_per_epoch())
+						.unwrap_or(false)
+					{
+						continue
+					}
+
+					match build_voluntary_exit::<C, BLS>(&pubkeys, state.state_mut(), &seckey, exit_config.epoch) {
+						Ok(voluntary_exit) => {
+							pool.lock().insert_voluntary_exit(voluntary_exit.clone());
+							if gossip.publish(PubsubMessage::VoluntaryExit(voluntary_exit.clone())) {
+								info!(
+									"Published voluntary exit of validator {} for epoch {}",
+									voluntary_exit.validator_index, voluntary_exit.epoch,
+								);
+								published_at = Some(slot);
+							} else {
+								warn!("Gossip queue is full, not publishing voluntary exit");
+							}
+						},
+						Err(ExitError::Invalid(beacon::Error::VoluntaryExitNotYetValid)) => (),
+						Err(e) => return Err(format!("Building voluntary exit failed: {}", e)),
+					}
+				}
+				Ok(())
+			})?;
+		}
+
+		Ok::<(), String>(())
+	})();
 
-	shasper_network::start_network_simple_sync(backend, import_lock, importer, config)
-		.expect("Starting networking thread failed");
+	match started {
+		Ok(()) => services.wait(),
+		Err(e) => {
+			error!("Startup failed: {}", e);
+			services.shutdown();
+		},
+	}
 }
 
+/// Number of slots ahead of a local proposal to start alerting about it.
+const PROPOSAL_ALERT_SLOTS: u64 = 2;
+
+/// Maximum time to wait for the operation pool while building a block,
+/// before falling back to a block without operations.
+const OPERATION_POOL_TIMEOUT: Duration = Duration::from_millis(500);
+
 fn builder_thread<B, I, C: Config + Clone>(
 	backend: B,
 	importer: I,
-	eth1_data: Eth1Data,
+	executor: Executor<C, BLS>,
+	eth1_provider: Arc<dyn Eth1Provider<C>>,
 	keys: HashMap<ValidatorId, bls::Secret>,
+	clock: Arc<dyn SlotClock>,
+	pool: SharedOperationPool<C, BLS>,
+	subnets: AttestationSubnets,
+	duties: DutyHistory,
+	safety: SafetySwitch,
+	settings: LiveSettings,
+	attestation_queue: AttestationQueue,
+	events: Receiver<ChainEvent<H256>>,
+	node_metrics: NodeMetrics,
+	exit: Exit,
 ) where
 	B: ChainQuery + Store<Block=Block<C>>,
-	B::State: StateExternalities + AsExternalities<dyn StateExternalities<Config=C>>,
+	B::State: StateExternalities<Config=C> + AsExternalities<dyn StateExternalities<Config=C>>,
+	B::State: From<BeaconState<C>>,
 	B::Auxiliary: Auxiliary<Block<C>>,
 	I: SharedBlockImporter<Block=Block<C>>
 {
-	let executor = Executor::<C, BLS>::new();
-	let mut attestations = AttestationPool::<C, BLS>::new();
+	let advanced_states = AdvancedStateCache::<C>::default();
+	let mut duties_epoch = None;
+
+	for tick in start_slot_ticks(clock.clone()) {
+		if exit.is_signaled() {
+			break
+		}
 
-	loop {
-		thread::sleep(Duration::new(1, 0));
+		while let Ok(event) = events.try_recv() {
+			if let ChainEvent::Reorg { old_head, new_head, depth } = event {
+				info!("Reorg of depth {} from {} to {}, recomputing duties", depth, old_head, new_head);
+				duties_epoch = None;
+			}
+		}
 
 		let head = backend.head();
-		info!("Building on top of {}", head);
-
-		let block = {
-			let head_block = backend.block_at(&head).unwrap();
-			let head_state = backend.state_at(&head).unwrap();
-			trace!("Justified epoch {}, finalized epoch {}",
-				   { head_state.state().current_justified_checkpoint.epoch },
-				   { head_state.state().finalized_checkpoint.epoch });
-
-			let mut state = backend.state_at(&head).unwrap();
-			let externalities = state.as_externalities();
-			let current_slot = head_block.0.slot + 1;
-			executor.initialize_block(externalities, current_slot).unwrap();
-			let executive = BeaconExecutive::new(externalities.state_mut());
-			let current_epoch = executive.current_epoch();
-
-			let randao_domain = executive.domain(C::domain_randao(), None);
-			let proposer_domain = executive.domain(C::domain_beacon_proposer(), None);
-			let attestation_domain = executive.domain(C::domain_beacon_attester(), None);
-
-			for (validator_id, validator_seckey) in &keys {
-				let validator_index = executive.validator_index(validator_id);
-
-				if let Some(validator_index) = validator_index {
-					let committee_assignment = executive
-						.committee_assignment(current_epoch, validator_index).unwrap();
-					if let Some(committee_assignment) = committee_assignment {
-						if committee_assignment.slot == current_slot {
-							trace!(
-								"Found validator {} attesting slot {} with index {}",
-								validator_id, current_slot, committee_assignment.index);
-							let committee = committee_assignment.validators;
-
-							let target_epoch = current_epoch;
-							let target_slot = beacon::utils::start_slot_of_epoch::<C>(target_epoch);
-							let target_root = if target_slot == current_slot {
-								head
-							} else {
-								executive.block_root(target_epoch).unwrap()
-							};
-							let source_epoch = executive.current_justified_checkpoint.epoch;
-							let source_root = executive.current_justified_checkpoint.root;
-							trace!(
-								"Casper source {} ({}) to target {} ({})",
-								source_epoch, source_root, target_epoch, target_root,
-							);
-
-							let data = AttestationData {
-								beacon_block_root: head_block.id(),
-								source: Checkpoint {
-									epoch: source_epoch,
-									root: source_root,
-								},
-								target: Checkpoint {
-									epoch: target_epoch,
-									root: target_root,
-								},
-								slot: committee_assignment.slot,
-								index: committee_assignment.index,
-							};
-							let signature = Signature::from_slice(&bls::Signature::new(
-								&tree_root::<C::Digest, _>(&AttestationDataAndCustodyBit {
-									data: data.clone(),
-									custody_bit: false,
-								})[..],
-								attestation_domain,
-								&validator_seckey,
-							).as_bytes()[..]);
-
-							let index_into_committee = committee.iter()
-								.position(|v| *v == validator_index).unwrap();
-							let mut aggregation_bitfield = Vec::new();
-							aggregation_bitfield.resize(committee.len(), false);
-							aggregation_bitfield[index_into_committee] = true;
-							let mut custody_bitfield = Vec::new();
-							custody_bitfield.resize(committee.len(), false);
-
-							let attestation = Attestation {
-								aggregation_bits: aggregation_bitfield.into(),
-								data,
-								custody_bits: custody_bitfield.into(),
-								signature
-							};
-
-							attestations.push(attestation);
-						}
+		let head_block = backend.block_at(&head).unwrap();
+		let current_slot = tick.slot();
+
+		if head_block.0.slot > current_slot {
+			warn!("Head {} is ahead of slot {}, skipping {:?}", head, current_slot, tick);
+			continue
+		}
+
+		let current_epoch = beacon::utils::epoch_of_slot::<C>(current_slot);
+		subnets.note_slot(current_slot, current_epoch);
+		if duties_epoch != Some(current_epoch) {
+			let mut state = advanced_states.get_or_advance(
+				&backend, &head, current_epoch,
+			).unwrap();
+			let committees = vec![current_epoch, current_epoch + 1].into_iter()
+				.map(|epoch| executor.committees().committees(&mut state, epoch).unwrap())
+				.collect::<Vec<_>>();
+			let executive = BeaconExecutive::new(&mut state);
+
+			let mut proposers = executive.proposer_assignments(current_epoch).unwrap();
+			proposers.extend(executive.proposer_assignments(current_epoch + 1).unwrap());
+
+			let mut subnet_duties = Vec::new();
+			let mut epoch_duties = Vec::new();
+			for validator_id in keys.keys() {
+				let validator_index = match executor.pubkeys().index_of::<C>(&executive, validator_id) {
+					Some(validator_index) => validator_index,
+					None => continue,
+				};
+				for committees in &committees {
+					let epoch = committees.epoch();
+					let assignment = committees.assignment::<C>(validator_index);
+					if let Some(ref assignment) = assignment {
+						subnet_duties.push(SubnetDuty {
+							slot: assignment.slot,
+							committee_index: assignment.index,
+						});
 					}
+					epoch_duties.push(EpochDuty {
+						validator: validator_id.clone(),
+						validator_index,
+						epoch,
+						attestation_slot: assignment.as_ref().map(|a| a.slot),
+						committee_index: assignment.as_ref().map(|a| a.index),
+						proposal_slots: proposers.iter()
+							.filter(|p| p.validator_index == validator_index &&
+									beacon::utils::epoch_of_slot::<C>(p.slot) == epoch)
+							.map(|p| p.slot)
+							.collect(),
+					});
 				}
 			}
+			subnets.note_duties(subnet_duties);
+			if let Err(e) = duties.record(current_epoch, epoch_duties, proposers) {
+				warn!("Failed to persist duty history: {:?}", e);
+			}
+			duties_epoch = Some(current_epoch);
+		}
 
-			let proposer_index = executive.beacon_proposer_index().unwrap();
-			let proposer_pubkey = executive.validator_pubkey(proposer_index).unwrap();
-			trace!("Current proposer {} ({}) on epoch {}", proposer_index, proposer_pubkey, current_epoch);
-
-			let seckey = match keys.get(&proposer_pubkey) {
-				Some(value) => value.clone(),
-				None => {
-					warn!("No secret key, skip building block.");
-					continue;
-				},
-			};
-			let randao_reveal = Signature::from_slice(&bls::Signature::new(
-				&tree_root::<C::Digest, _>(&current_epoch)[..],
-				randao_domain,
-				&seckey
-			).as_bytes()[..]);
-
-			let mut unsealed_block = executor.apply_inherent(
-				&head_block, state.as_externalities(),
-				Inherent {
-					randao_reveal,
-					eth1_data: eth1_data.clone(),
+		match tick {
+			SlotTick::Attestation(_) => {
+				if safety.is_tripped() {
+					continue
 				}
-			).unwrap();
 
-			let mut collected_attestations = Vec::new();
-			for (hash, attestation) in attestations.iter() {
-				match executor.apply_extrinsic(
-					&mut unsealed_block, state.as_externalities(),
-					Transaction::Attestation(attestation.clone())
+				let mut state: B::State = advanced_states.get_or_advance(
+					&backend, &head, beacon::utils::epoch_of_slot::<C>(current_slot),
+				).unwrap().into();
+				let externalities = state.as_externalities();
+				executor.initialize_block(externalities, current_slot).unwrap();
+				let committees = executor.committees()
+					.committees(externalities.state_mut(), current_epoch).unwrap();
+				let executive = BeaconExecutive::new(externalities.state_mut());
+				let attestation_domain = executive.domain(C::domain_beacon_attester(), None);
+
+				for (validator_id, validator_seckey) in &keys {
+					let validator_index = match executor.pubkeys()
+						.index_of::<C>(&executive, validator_id)
+					{
+						Some(validator_index) => validator_index,
+						None => continue,
+					};
+					let committee_assignment = match committees.assignment::<C>(validator_index) {
+						Some(committee_assignment) => committee_assignment,
+						None => continue,
+					};
+					if committee_assignment.slot != current_slot {
+						continue
+					}
+
+					trace!(
+						"Found validator {} attesting slot {} with index {}",
+						validator_id, current_slot, committee_assignment.index);
+					let committee = committee_assignment.validators;
+
+					let target_epoch = current_epoch;
+					let target_slot = beacon::utils::start_slot_of_epoch::<C>(target_epoch);
+					let target_root = if target_slot == current_slot {
+						head
+					} else {
+						executive.block_root(target_epoch).unwrap()
+					};
+					let source_epoch = executive.current_justified_checkpoint.epoch;
+					let source_root = executive.current_justified_checkpoint.root;
+					trace!(
+						"Casper source {} ({}) to target {} ({})",
+						source_epoch, source_root, target_epoch, target_root,
+					);
+
+					let data = AttestationData {
+						beacon_block_root: head_block.id(),
+						source: Checkpoint {
+							epoch: source_epoch,
+							root: source_root,
+						},
+						target: Checkpoint {
+							epoch: target_epoch,
+							root: target_root,
+						},
+						slot: committee_assignment.slot,
+						index: committee_assignment.index,
+					};
+					let signature = Signature::from_slice(&bls::Signature::new(
+						&tree_root::<C::Digest, _>(&AttestationDataAndCustodyBit {
+							data: data.clone(),
+							custody_bit: false,
+						})[..],
+						attestation_domain,
+						&validator_seckey,
+					).as_bytes()[..]);
+
+					let index_into_committee = committee.iter()
+						.position(|v| *v == validator_index).unwrap();
+					let mut aggregation_bitfield = Vec::new();
+					aggregation_bitfield.resize(committee.len(), false);
+					aggregation_bitfield[index_into_committee] = true;
+					let mut custody_bitfield = Vec::new();
+					custody_bitfield.resize(committee.len(), false);
+
+					let attestation = Attestation {
+						aggregation_bits: aggregation_bitfield.into(),
+						data,
+						custody_bits: custody_bitfield.into(),
+						signature
+					};
+
+					attestation_queue.push(
+						attestation.data.slot.as_u64(), attestation.data.target.epoch.as_u64(),
+						vec![validator_index], attestation.data.beacon_block_root,
+					);
+					pool.lock().insert_attestation(attestation);
+				}
+
+				node_metrics.attestation_pool_size.set(pool.lock().attestations().len());
+			},
+			SlotTick::Aggregation(_) => {
+				let head_state = backend.state_at(&head).unwrap();
+				let mut pool = pool.lock();
+				let pruned = pool.prune(head_state.state(), current_slot);
+				node_metrics.attestation_pool_pruned.add(pruned);
+				node_metrics.attestation_pool_size.set(pool.attestations().len());
+				node_metrics.operation_pool_size.set(pool.len());
+			},
+			SlotTick::Start(_) => {
+				for (slot, validator_id) in duties.upcoming_proposals(
+					current_slot, PROPOSAL_ALERT_SLOTS,
 				) {
-					Ok(()) => {
-						collected_attestations.push(*hash);
+					info!("Validator {} proposes at slot {}, in {} slots",
+						  validator_id, slot, slot - current_slot);
+				}
+
+				if head_block.0.slot == current_slot {
+					continue
+				}
+				if let Some(reason) = safety.reason() {
+					warn!("Not building slot {}, safety switch tripped: {}", current_slot, reason);
+					continue
+				}
+				info!("Building slot {} on top of {}", current_slot, head);
+
+				let head_state = backend.state_at(&head).unwrap();
+				trace!("Justified epoch {}, finalized epoch {}",
+					   { head_state.state().current_justified_checkpoint.epoch },
+					   { head_state.state().finalized_checkpoint.epoch });
+				advanced_states.prune(&backend, head_state.state().finalized_checkpoint.epoch);
+
+				let mut state: B::State = advanced_states.get_or_advance(
+					&backend, &head, beacon::utils::epoch_of_slot::<C>(current_slot),
+				).unwrap().into();
+				let externalities = state.as_externalities();
+				executor.initialize_block(externalities, current_slot).unwrap();
+				let executive = BeaconExecutive::new(externalities.state_mut());
+				let current_epoch = executive.current_epoch();
+
+				let randao_domain = executive.domain(C::domain_randao(), None);
+				let proposer_domain = executive.domain(C::domain_beacon_proposer(), None);
+
+				let proposer_index = executive.beacon_proposer_index().unwrap();
+				let proposer_pubkey = executive.validator_pubkey(proposer_index).unwrap();
+				trace!("Current proposer {} ({}) on epoch {}", proposer_index, proposer_pubkey, current_epoch);
+
+				let seckey = match keys.get(&proposer_pubkey) {
+					Some(value) => value.clone(),
+					None => {
+						trace!("No secret key, skip building block.");
+						continue;
 					},
-					Err(Error::Beacon(ref err)) if err == &beacon::Error::AttestationSubmittedTooQuickly => {},
+				};
+				let randao_reveal = Signature::from_slice(&bls::Signature::new(
+					&tree_root::<C::Digest, _>(&current_epoch)[..],
+					randao_domain,
+					&seckey
+				).as_bytes()[..]);
+
+				let eth1_data = eth1_provider.eth1_data(voting_period_timestamp::<C>(
+					state.state().genesis_time, current_slot,
+				));
+
+				let mut unsealed_block = executor.apply_inherent(
+					&head_block, state.as_externalities(),
+					Inherent {
+						randao_reveal,
+						eth1_data,
+						graffiti: settings.graffiti_for(&proposer_pubkey),
+					}
+				).unwrap();
+
+				let inherent_state = state.state().clone();
+				let inherent_block = unsealed_block.clone();
+
+				let deposit_index = state.state().eth1_deposit_index;
+				let deposit_count = state.state().eth1_data.deposit_count;
+				let deposits_to = core::cmp::min(
+					deposit_count,
+					deposit_index + C::max_deposits(),
+				);
+				let deposits = match eth1_provider.deposits(deposit_index, deposits_to, deposit_count) {
+					Ok(deposits) => deposits,
 					Err(err) => {
-						warn!("Error when submitting an attestation: {}", err);
+						warn!("Unable to fetch pending deposits: {:?}", err);
+						Vec::new()
 					},
-				}
-			}
-			info!("Pushed {} attestations", collected_attestations.len());
-			for hash in collected_attestations {
-				attestations.pop(&hash);
-			}
+				};
 
-			executor.finalize_block(
-				&mut unsealed_block, state.as_externalities()
-			).unwrap();
+				let deadline = clock.instant_at(current_slot, settings.block_deadline());
+				let lock_timeout = core::cmp::min(
+					OPERATION_POOL_TIMEOUT,
+					deadline.saturating_duration_since(Instant::now()),
+				);
+				let built = match pool.try_lock_for(lock_timeout) {
+					Some(mut locked) => {
+						for (offset, deposit) in deposits.iter().cloned().enumerate() {
+							locked.insert_deposit(deposit_index + offset as u64, deposit);
+						}
+						let transactions = locked.transactions(state.state());
+						drop(locked);
 
-			let mut block = unsealed_block.fake_seal();
-			let signature = Signature::from_slice(&bls::Signature::new(
-				&tree_root::<C::Digest, _>(&UnsealedBeaconBlock::<C>::from(&block))[..],
-				proposer_domain,
-				&seckey
-			).as_bytes()[..]);
-			block.signature = signature;
-			Block(block)
-		};
+						let (included, failed) = executor.apply_extrinsics_until(
+							&mut unsealed_block, state.as_externalities(), transactions, deadline,
+						);
+						for (transaction, err) in failed {
+							match err {
+								Error::Beacon(beacon::Error::TooManyProposerSlashings) |
+								Error::Beacon(beacon::Error::TooManyAttesterSlashings) |
+								Error::Beacon(beacon::Error::TooManyVoluntaryExits) |
+								Error::Beacon(beacon::Error::AttestationSubmittedTooQuickly) => (),
+								err => {
+									warn!("Dropping invalid operation: {}", err);
+									pool.lock().remove(&transaction);
+								},
+							}
+						}
+						if Instant::now() >= deadline {
+							warn!("Block production deadline reached at slot {}", current_slot);
+						}
+						info!("Pushed {} operations", included);
+
+						executor.finalize_block(&mut unsealed_block, state.as_externalities())
+							.map_err(|e| format!("{}", e))
+					},
+					None => Err("operation pool is busy".to_string()),
+				};
+
+				if let Err(reason) = built {
+					warn!("Building slot {} with operations failed ({}), falling back to a minimal block",
+						  current_slot, reason);
+					node_metrics.degraded_blocks.inc();
 
-		importer.import_block(block).unwrap();
+					state = B::State::from(inherent_state);
+					unsealed_block = inherent_block;
+					for deposit in deposits {
+						if let Err(err) = executor.apply_extrinsic(
+							&mut unsealed_block, state.as_externalities(), Transaction::Deposit(deposit),
+						) {
+							warn!("Dropping invalid deposit: {}", err);
+						}
+					}
+					if let Err(err) = executor.finalize_block(
+						&mut unsealed_block, state.as_externalities()
+					) {
+						error!("Building minimal block for slot {} failed: {}", current_slot, err);
+						continue
+					}
+				}
+
+				let mut block = unsealed_block.fake_seal();
+				let signature = Signature::from_slice(&bls::Signature::new(
+					&tree_root::<C::Digest, _>(&UnsealedBeaconBlock::<C>::from(&block))[..],
+					proposer_domain,
+					&seckey
+				).as_bytes()[..]);
+				block.signature = signature;
+
+				if let Some(mut pool) = pool.try_lock_for(OPERATION_POOL_TIMEOUT) {
+					pool.note_included(&block.body);
+					node_metrics.attestation_pool_size.set(pool.attestations().len());
+				}
+				importer.import_block(Block(block)).unwrap();
+			},
+		}
 	}
 }
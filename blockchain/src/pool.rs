@@ -13,15 +13,24 @@
 
 // You should have received a copy of the GNU General Public License along with
 // Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
-use beacon::{Config, BLSConfig};
-use beacon::primitives::H256;
-use beacon::types::{Attestation, AttestationDataAndCustodyBit};
-use std::collections::HashMap;
+use beacon::{Config, BLSConfig, BeaconState, Transaction};
+use beacon::primitives::{H256, Slot};
+use beacon::types::{
+	Attestation, AttestationDataAndCustodyBit, ProposerSlashing, AttesterSlashing, VoluntaryExit,
+	Deposit, BeaconBlockBody,
+};
+use std::collections::{HashMap, HashSet, BTreeMap};
+use std::sync::{Arc, Mutex, MutexGuard, TryLockError};
+use std::thread;
+use std::time::{Duration, Instant};
 use core::marker::PhantomData;
 use bm_le::tree_root;
 
 pub struct AttestationPool<C: Config, BLS: BLSConfig> {
 	pool: HashMap<H256, Vec<Attestation<C>>>,
+	/// Attesters already included on the canonical chain, per attestation
+	/// data root, as of the last pruning.
+	included: HashMap<H256, Vec<bool>>,
 	_marker: PhantomData<BLS>,
 }
 
@@ -29,15 +38,30 @@ impl<C: Config, BLS: BLSConfig> AttestationPool<C, BLS> {
 	pub fn new() -> Self {
 		Self {
 			pool: Default::default(),
+			included: Default::default(),
 			_marker: PhantomData,
 		}
 	}
 
+	/// Whether all attesters of an attestation are already included on the
+	/// canonical chain.
+	fn is_included(&self, hash: &H256, attestation: &Attestation<C>) -> bool {
+		match self.included.get(hash) {
+			Some(included) => included.len() == attestation.aggregation_bits.len() &&
+				attestation.aggregation_bits.iter().zip(included.iter())
+					.all(|(bit, included_bit)| !*bit || *included_bit),
+			None => false,
+		}
+	}
+
 	pub fn push(&mut self, attestation: Attestation<C>) {
 		let hash = tree_root::<C::Digest, _>(&AttestationDataAndCustodyBit {
 			data: attestation.data.clone(),
 			custody_bit: false,
 		});
+		if self.is_included(&hash, &attestation) {
+			return
+		}
 
 		self.pool.entry(hash)
 			.and_modify(|existings| {
@@ -45,15 +69,12 @@ impl<C: Config, BLS: BLSConfig> AttestationPool<C, BLS> {
 				let mut aggregated = false;
 
 				for existing in existings.iter_mut() {
-					let has_duplicate = {
-						let mut has_duplicate = false;
-						for i in 0..existing.aggregation_bits.len() {
-							if attestation.aggregation_bits[i] {
-								has_duplicate = true;
-							}
-						}
-						has_duplicate
-					};
+					// Naive aggregation: only attestations with disjoint
+					// attesters are merged.
+					let has_duplicate = existing.aggregation_bits.len() !=
+						attestation.aggregation_bits.len() ||
+						existing.aggregation_bits.iter().zip(attestation.aggregation_bits.iter())
+							.any(|(bit, other)| *bit && *other);
 
 					if has_duplicate {
 						continue
@@ -87,7 +108,326 @@ impl<C: Config, BLS: BLSConfig> AttestationPool<C, BLS> {
 		self.pool.remove(key);
 	}
 
+	/// Drop attestations that are too old to be included at `current_slot`,
+	/// that target an epoch before the finalized epoch of the canonical
+	/// head `state`, or whose attesters are all included on its chain.
+	/// Returns the number of dropped attestations.
+	pub fn prune(&mut self, state: &BeaconState<C>, current_slot: Slot) -> usize {
+		self.included.clear();
+		for pending in state.previous_epoch_attestations.iter()
+			.chain(state.current_epoch_attestations.iter())
+		{
+			let hash = tree_root::<C::Digest, _>(&AttestationDataAndCustodyBit {
+				data: pending.data.clone(),
+				custody_bit: false,
+			});
+			let included = self.included.entry(hash)
+				.or_insert_with(|| vec![false; pending.aggregation_bits.len()]);
+			if included.len() == pending.aggregation_bits.len() {
+				for (included_bit, bit) in included.iter_mut().zip(pending.aggregation_bits.iter()) {
+					*included_bit |= *bit;
+				}
+			}
+		}
+
+		let finalized_epoch = state.finalized_checkpoint.epoch;
+		let before = self.len();
+		let pool = core::mem::replace(&mut self.pool, HashMap::new());
+		for (hash, mut ats) in pool {
+			ats.retain(|at| {
+				at.data.slot + C::slots_per_epoch() >= current_slot &&
+					at.data.target.epoch >= finalized_epoch &&
+					!self.is_included(&hash, at)
+			});
+			if !ats.is_empty() {
+				self.pool.insert(hash, ats);
+			}
+		}
+		before - self.len()
+	}
+
+	pub fn len(&self) -> usize {
+		self.pool.values().map(|ats| ats.len()).sum()
+	}
+
 	pub fn iter(&self) -> impl Iterator<Item=(&H256, &Attestation<C>)> {
 		self.pool.iter().flat_map(|(h, ats)| ats.iter().map(move |at| (h, at)))
 	}
+
+	/// Attestations to include in a block on top of `state`, at most
+	/// `max_attestations` of them.
+	///
+	/// Picking the aggregates is a maximum coverage problem over attesters,
+	/// approximated greedily: the aggregate with the most attesters not yet
+	/// included in the state or in the picked aggregates is taken until none
+	/// adds any. Attesters are identified by their committee and position
+	/// in it, so that committees need not be computed.
+	pub fn max_coverage(&self, state: &BeaconState<C>, max_attestations: usize) -> Vec<Attestation<C>> {
+		let current_epoch = beacon::utils::epoch_of_slot::<C>(state.slot);
+		let mut covered = HashSet::new();
+		for pending in state.previous_epoch_attestations.iter()
+			.chain(state.current_epoch_attestations.iter())
+		{
+			covered.extend(attesters(pending.data.slot, pending.data.index, &pending.aggregation_bits));
+		}
+
+		let mut candidates = self.pool.values()
+			.flat_map(|ats| ats.iter())
+			.filter(|at| {
+				at.data.slot + C::min_attestation_inclusion_delay() <= state.slot &&
+					state.slot <= at.data.slot + C::slots_per_epoch() &&
+					(at.data.target.epoch == current_epoch ||
+					 at.data.target.epoch + 1 == current_epoch)
+			})
+			.map(|at| {
+				let new = attesters(at.data.slot, at.data.index, &at.aggregation_bits)
+					.filter(|attester| !covered.contains(attester))
+					.collect::<HashSet<_>>();
+				(at, new)
+			})
+			.collect::<Vec<_>>();
+
+		let mut packed = Vec::new();
+		while packed.len() < max_attestations {
+			let best = candidates.iter()
+				.enumerate()
+				.max_by_key(|(_, (_, new))| new.len())
+				.map(|(index, (_, new))| (index, new.len()));
+			let index = match best {
+				Some((index, count)) if count > 0 => index,
+				_ => break,
+			};
+
+			let (attestation, new) = candidates.swap_remove(index);
+			for (_, other) in candidates.iter_mut() {
+				other.retain(|attester| !new.contains(attester));
+			}
+			packed.push(attestation.clone());
+		}
+		packed
+	}
+
+	/// Drop pooled attestations whose attesters are all covered by an
+	/// included attestation of the same data.
+	pub fn remove_included(&mut self, included: &Attestation<C>) {
+		let hash = tree_root::<C::Digest, _>(&AttestationDataAndCustodyBit {
+			data: included.data.clone(),
+			custody_bit: false,
+		});
+
+		if let Some(ats) = self.pool.get_mut(&hash) {
+			ats.retain(|at| {
+				at.aggregation_bits.len() != included.aggregation_bits.len() ||
+					at.aggregation_bits.iter().zip(included.aggregation_bits.iter())
+						.any(|(bit, included_bit)| *bit && !*included_bit)
+			});
+			if ats.is_empty() {
+				self.pool.remove(&hash);
+			}
+		}
+	}
+}
+
+/// Pool of operations of all kinds waiting for inclusion in a block.
+pub struct OperationPool<C: Config, BLS: BLSConfig> {
+	attestations: AttestationPool<C, BLS>,
+	proposer_slashings: Vec<ProposerSlashing>,
+	attester_slashings: Vec<AttesterSlashing<C>>,
+	voluntary_exits: Vec<VoluntaryExit>,
+	deposits: BTreeMap<u64, Deposit>,
+}
+
+impl<C: Config, BLS: BLSConfig> OperationPool<C, BLS> {
+	pub fn new() -> Self {
+		Self {
+			attestations: AttestationPool::new(),
+			proposer_slashings: Vec::new(),
+			attester_slashings: Vec::new(),
+			voluntary_exits: Vec::new(),
+			deposits: BTreeMap::new(),
+		}
+	}
+
+	pub fn attestations(&self) -> &AttestationPool<C, BLS> {
+		&self.attestations
+	}
+
+	pub fn proposer_slashings(&self) -> &[ProposerSlashing] {
+		&self.proposer_slashings
+	}
+
+	pub fn attester_slashings(&self) -> &[AttesterSlashing<C>] {
+		&self.attester_slashings
+	}
+
+	pub fn voluntary_exits(&self) -> &[VoluntaryExit] {
+		&self.voluntary_exits
+	}
+
+	pub fn insert_attestation(&mut self, attestation: Attestation<C>) {
+		self.attestations.push(attestation);
+	}
+
+	pub fn insert_proposer_slashing(&mut self, slashing: ProposerSlashing) {
+		insert_unique(&mut self.proposer_slashings, slashing);
+	}
+
+	pub fn insert_attester_slashing(&mut self, slashing: AttesterSlashing<C>) {
+		insert_unique(&mut self.attester_slashings, slashing);
+	}
+
+	pub fn insert_voluntary_exit(&mut self, exit: VoluntaryExit) {
+		insert_unique(&mut self.voluntary_exits, exit);
+	}
+
+	/// Insert a deposit with its index in the deposit contract.
+	pub fn insert_deposit(&mut self, index: u64, deposit: Deposit) {
+		self.deposits.insert(index, deposit);
+	}
+
+	/// Candidate transactions for a block on top of `state`, in processing
+	/// order. Deposits are only included contiguously from the state deposit
+	/// index, and attestations are packed for maximum coverage.
+	pub fn transactions(&self, state: &BeaconState<C>) -> Vec<Transaction<C>> {
+		let deposit_index = state.eth1_deposit_index;
+		let mut transactions = Vec::new();
+		transactions.extend(
+			self.proposer_slashings.iter().cloned().map(Transaction::ProposerSlashing)
+		);
+		transactions.extend(
+			self.attester_slashings.iter().cloned().map(Transaction::AttesterSlashing)
+		);
+		let mut next_index = deposit_index;
+		for (index, deposit) in self.deposits.range(deposit_index..) {
+			if *index != next_index {
+				break
+			}
+			transactions.push(Transaction::Deposit(deposit.clone()));
+			next_index += 1;
+		}
+		transactions.extend(
+			self.attestations.max_coverage(state, C::max_attestations() as usize)
+				.into_iter()
+				.map(Transaction::Attestation)
+		);
+		transactions.extend(
+			self.voluntary_exits.iter().cloned().map(Transaction::VoluntaryExit)
+		);
+		transactions
+	}
+
+	/// Remove a transaction that turned out to be invalid.
+	pub fn remove(&mut self, transaction: &Transaction<C>) {
+		match transaction {
+			Transaction::ProposerSlashing(slashing) =>
+				self.proposer_slashings.retain(|s| s != slashing),
+			Transaction::AttesterSlashing(slashing) =>
+				self.attester_slashings.retain(|s| s != slashing),
+			Transaction::Attestation(attestation) =>
+				self.attestations.remove_included(attestation),
+			Transaction::Deposit(deposit) =>
+				self.deposits.retain(|_, d| d != deposit),
+			Transaction::VoluntaryExit(exit) =>
+				self.voluntary_exits.retain(|e| e != exit),
+		}
+	}
+
+	/// Remove operations included in a block.
+	pub fn note_included(&mut self, body: &BeaconBlockBody<C>) {
+		for slashing in body.proposer_slashings.iter() {
+			self.proposer_slashings.retain(|s| s != slashing);
+		}
+		for slashing in body.attester_slashings.iter() {
+			self.attester_slashings.retain(|s| s != slashing);
+		}
+		for attestation in body.attestations.iter() {
+			self.attestations.remove_included(attestation);
+		}
+		for deposit in body.deposits.iter() {
+			self.deposits.retain(|_, d| d != deposit);
+		}
+		for exit in body.voluntary_exits.iter() {
+			self.voluntary_exits.retain(|e| e != exit);
+		}
+	}
+
+	/// Drop operations that can no longer be included on top of `state`
+	/// at `current_slot`. Returns the number of dropped attestations.
+	pub fn prune(&mut self, state: &BeaconState<C>, current_slot: Slot) -> usize {
+		let is_slashed = |index: u64| {
+			state.validators.get(index as usize).map(|v| v.slashed).unwrap_or(true)
+		};
+
+		let pruned = self.attestations.prune(state, current_slot);
+		self.proposer_slashings.retain(|s| !is_slashed(s.proposer_index));
+		self.attester_slashings.retain(|s| {
+			s.attestation_1.custody_bit_0_indices.iter()
+				.chain(s.attestation_1.custody_bit_1_indices.iter())
+				.any(|index| !is_slashed(*index))
+		});
+		self.voluntary_exits.retain(|e| {
+			state.validators.get(e.validator_index as usize)
+				.map(|v| v.exit_epoch == beacon::consts::FAR_FUTURE_EPOCH)
+				.unwrap_or(false)
+		});
+		self.deposits = self.deposits.split_off(&state.eth1_deposit_index);
+		pruned
+	}
+
+	/// Total number of pooled operations.
+	pub fn len(&self) -> usize {
+		self.attestations.len() + self.proposer_slashings.len() +
+			self.attester_slashings.len() + self.voluntary_exits.len() + self.deposits.len()
+	}
+}
+
+/// Operation pool shared between the block builder and the API.
+pub struct SharedOperationPool<C: Config, BLS: BLSConfig>(Arc<Mutex<OperationPool<C, BLS>>>);
+
+impl<C: Config, BLS: BLSConfig> SharedOperationPool<C, BLS> {
+	pub fn new() -> Self {
+		Self(Arc::new(Mutex::new(OperationPool::new())))
+	}
+
+	pub fn lock(&self) -> MutexGuard<OperationPool<C, BLS>> {
+		self.0.lock().expect("Lock is poisoned")
+	}
+
+	/// Try to lock the pool, giving up after `timeout`.
+	pub fn try_lock_for(&self, timeout: Duration) -> Option<MutexGuard<OperationPool<C, BLS>>> {
+		let deadline = Instant::now() + timeout;
+		loop {
+			match self.0.try_lock() {
+				Ok(guard) => return Some(guard),
+				Err(TryLockError::Poisoned(_)) => panic!("Lock is poisoned"),
+				Err(TryLockError::WouldBlock) => (),
+			}
+			if Instant::now() >= deadline {
+				return None
+			}
+			thread::sleep(Duration::from_millis(5));
+		}
+	}
+}
+
+impl<C: Config, BLS: BLSConfig> Clone for SharedOperationPool<C, BLS> {
+	fn clone(&self) -> Self {
+		Self(self.0.clone())
+	}
+}
+
+/// Attesters of an aggregation bitfield, as committee slot, committee index
+/// and position in the committee.
+fn attesters<'a>(
+	slot: Slot,
+	index: u64,
+	bits: &'a [bool],
+) -> impl Iterator<Item=(Slot, u64, usize)> + 'a {
+	bits.iter().enumerate().filter(|(_, bit)| **bit).map(move |(i, _)| (slot, index, i))
+}
+
+fn insert_unique<T: PartialEq>(pool: &mut Vec<T>, operation: T) {
+	if !pool.contains(&operation) {
+		pool.push(operation);
+	}
 }
@@ -1,18 +1,342 @@
 use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::path::Path;
+use beacon::{BeaconState, BLSConfig, Config, DepositTree};
+use beacon::primitives::H256;
+use beacon::types::{Deposit, DepositData};
+use core::convert::TryInto;
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use crypto::bls;
+use crate::eth1::signed_deposit_data;
+
+/// Timeout for establishing a connection when fetching a remote resource.
+const FETCH_CONNECT_TIMEOUT_MS: u64 = 10_000;
+/// Timeout for each read when fetching a remote resource.
+const FETCH_READ_TIMEOUT_MS: u64 = 30_000;
+/// Largest response body accepted when fetching a remote resource.
+const MAX_FETCH_SIZE: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug)]
+pub enum Error {
+	Io(std::io::Error),
+	Yaml(serde_yaml::Error),
+	UnsupportedUrl(String),
+	InvalidResponse,
+	Http(String),
+	MissingGenesisRoot,
+	InvalidHex(String),
+	MissingGenesis,
+	InvalidGenesis,
+	Beacon(beacon::Error),
+}
+
+impl From<std::io::Error> for Error {
+	fn from(error: std::io::Error) -> Error {
+		Error::Io(error)
+	}
+}
+
+impl From<beacon::Error> for Error {
+	fn from(error: beacon::Error) -> Error {
+		Error::Beacon(error)
+	}
+}
+
+impl From<serde_yaml::Error> for Error {
+	fn from(error: serde_yaml::Error) -> Error {
+		Error::Yaml(error)
+	}
+}
+
+#[derive(Debug, Clone)]
+pub enum Genesis {
+	/// Raw ssz genesis state bundled with the preset.
+	State(Vec<u8>),
+	/// Genesis state fetched from a bootstrap URL.
+	Url(String),
+	/// Genesis state built from the eth1 genesis block and its deposits.
+	Eth1 {
+		/// Eth1 block hash.
+		block_hash: H256,
+		/// Eth1 block timestamp.
+		timestamp: u64,
+		/// All deposits up to the eth1 block, in order.
+		deposits: Vec<DepositData>,
+	},
+}
 
 #[derive(Debug, Clone)]
 pub struct Preset {
+	pub config: String,
+	pub bootnodes: Vec<String>,
+	pub genesis: Genesis,
+	pub genesis_state_root: Option<H256>,
+	pub deposit_contract_address: Option<String>,
+}
+
+impl Preset {
+	/// Get the genesis state of this preset, fetching or building it if
+	/// needed, and checking it against the expected genesis state root.
+	pub fn genesis_state<C: Config, BLS: BLSConfig>(&self) -> Result<BeaconState<C>, Error> {
+		let decode = |data: &[u8]| {
+			<BeaconState<C> as ssz::Decode>::decode(&mut &data[..])
+				.map_err(|_| Error::InvalidGenesis)
+		};
+
+		let state = match self.genesis {
+			Genesis::State(ref state) => decode(state)?,
+			Genesis::Url(ref url) => decode(&fetch(url)?)?,
+			Genesis::Eth1 { block_hash, timestamp, ref deposits } => {
+				let state = beacon::initialize_beacon_state_from_eth1::<C, BLS>(
+					block_hash, timestamp, &genesis_deposits::<C>(deposits)?,
+				)?;
+				if !beacon::is_valid_genesis_state(&state) {
+					return Err(Error::InvalidGenesis)
+				}
+				state
+			},
+		};
+
+		match (self.genesis_state_root, &self.genesis) {
+			(Some(expected_root), _) => {
+				if bm_le::tree_root::<C::Digest, _>(&state) != expected_root {
+					return Err(Error::InvalidGenesis)
+				}
+			},
+			// A remotely fetched genesis state is only trusted when pinned.
+			(None, Genesis::Url(_)) => return Err(Error::MissingGenesisRoot),
+			(None, _) => (),
+		}
+
+		Ok(state)
+	}
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct PresetFile {
+	pub name: String,
+	pub config: String,
+	#[serde(default)]
 	pub bootnodes: Vec<String>,
-	pub genesis_state: Vec<u8>,
+	#[serde(default)]
+	pub genesis_state: Option<String>,
+	#[serde(default)]
+	pub genesis_state_url: Option<String>,
+	#[serde(default)]
+	pub genesis_state_root: Option<String>,
+	#[serde(default)]
+	pub genesis_eth1_block_hash: Option<String>,
+	#[serde(default)]
+	pub genesis_eth1_timestamp: Option<u64>,
+	#[serde(default)]
+	pub genesis_deposits: Option<String>,
+	#[serde(default)]
+	pub deposit_contract_address: Option<String>,
 }
 
-pub fn presets() -> HashMap<&'static str, Preset> {
-	let mut presets = HashMap::new();
+/// Registry of named network presets.
+#[derive(Debug, Clone, Default)]
+pub struct Presets {
+	presets: HashMap<String, Preset>,
+}
+
+impl Presets {
+	/// Create a registry with only built-in presets.
+	pub fn new() -> Self {
+		let mut presets = Self::default();
+
+		presets.register("sapphire", Preset {
+			config: "sapphire".to_string(),
+			bootnodes: vec!["/dns4/prylabs.net/tcp/30001".to_string()],
+			genesis: Genesis::State(include_bytes!("../res/eth2-testnets/prysm/Sapphire(v0.9.0)/genesis.ssz").to_vec()),
+			genesis_state_root: None,
+			deposit_contract_address: None,
+		});
+
+		presets
+	}
+
+	/// Register a custom preset, replacing any existing one of the same name.
+	pub fn register(&mut self, name: &str, preset: Preset) {
+		self.presets.insert(name.to_string(), preset);
+	}
+
+	/// Register a custom preset from a yaml bundle file. Relative genesis
+	/// state paths are resolved against the bundle file location.
+	pub fn register_file<P: AsRef<Path>>(&mut self, path: P) -> Result<String, Error> {
+		let path = path.as_ref();
+		let file = File::open(path)?;
+		let bundle = serde_yaml::from_reader::<_, PresetFile>(BufReader::new(file))?;
+
+		let resolve = |relative: &str| path.parent()
+			.map(|parent| parent.join(relative))
+			.unwrap_or_else(|| relative.into());
+		let genesis = match (
+			bundle.genesis_state,
+			bundle.genesis_state_url,
+			bundle.genesis_eth1_block_hash,
+			bundle.genesis_eth1_timestamp,
+			bundle.genesis_deposits,
+		) {
+			(Some(state_path), _, _, _, _) => {
+				let mut file = File::open(resolve(&state_path))?;
+				let mut data = Vec::new();
+				file.read_to_end(&mut data)?;
+				Genesis::State(data)
+			},
+			(None, Some(url), _, _, _) => Genesis::Url(url),
+			(None, None, Some(block_hash), Some(timestamp), Some(deposits_path)) => {
+				let file = File::open(resolve(&deposits_path))?;
+				Genesis::Eth1 {
+					block_hash: parse_h256(&block_hash)?,
+					timestamp,
+					deposits: serde_yaml::from_reader(BufReader::new(file))?,
+				}
+			},
+			_ => return Err(Error::MissingGenesis),
+		};
+
+		let genesis_state_root = match bundle.genesis_state_root {
+			Some(root) => Some(parse_h256(&root)?),
+			None => None,
+		};
+		if let (Genesis::Url(_), None) = (&genesis, genesis_state_root) {
+			return Err(Error::MissingGenesisRoot)
+		}
+
+		self.register(&bundle.name, Preset {
+			config: bundle.config,
+			bootnodes: bundle.bootnodes,
+			genesis,
+			genesis_state_root,
+			deposit_contract_address: bundle.deposit_contract_address,
+		});
+
+		Ok(bundle.name)
+	}
+
+	/// Get a preset by name.
+	pub fn get(&self, name: &str) -> Option<&Preset> {
+		self.presets.get(name)
+	}
+}
+
+pub fn presets() -> Presets {
+	Presets::new()
+}
+
+/// Order of the BLS12-381 curve subgroup, big endian.
+const CURVE_ORDER: [u8; 32] = [
+	0x73, 0xed, 0xa7, 0x53, 0x29, 0x9d, 0x7d, 0x48, 0x33, 0x39, 0xd8, 0x08, 0x09, 0xa1, 0xd8, 0x05,
+	0x53, 0xbd, 0xa4, 0x02, 0xff, 0xfe, 0x5b, 0xfe, 0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x01,
+];
+
+/// Deterministic secret key of validator `index`, following the interop
+/// keygen scheme.
+pub fn interop_secret(index: u64) -> bls::Secret {
+	let mut preimage = [0u8; 32];
+	preimage[..8].copy_from_slice(&index.to_le_bytes());
+	let mut value = [0u8; 32];
+	value.copy_from_slice(&Sha256::digest(&preimage[..])[..]);
+	// The hash is interpreted as little endian, but reduced big endian.
+	value.reverse();
+
+	while value >= CURVE_ORDER {
+		let mut borrow = 0i16;
+		for i in (0..32).rev() {
+			let diff = value[i] as i16 - CURVE_ORDER[i] as i16 - borrow;
+			borrow = if diff < 0 { 1 } else { 0 };
+			value[i] = (diff + (borrow << 8)) as u8;
+		}
+	}
+
+	let mut bytes = vec![0u8; 16];
+	bytes.extend_from_slice(&value);
+	bls::Secret::from_bytes(&bytes).expect("Reduced value is a valid secret key; qed")
+}
+
+/// Interop genesis state of `validator_count` validators with keys from
+/// `interop_secret`, for local devnets and multi-client testnets.
+pub fn quickstart<C: Config, BLS: BLSConfig>(
+	validator_count: u64,
+	genesis_time: u64,
+) -> Result<BeaconState<C>, Error> {
+	let datas = (0..validator_count).map(|index| {
+		let seckey = interop_secret(index);
+		let pubkey = bls::Public::from_secret_key(&seckey).as_bytes();
+		let mut withdrawal_credentials = H256::from_slice(&Sha256::digest(&pubkey[..])[..]);
+		withdrawal_credentials[0] = C::bls_withdrawal_prefix_byte();
+
+		signed_deposit_data::<C>(&seckey, withdrawal_credentials, C::max_effective_balance())
+	}).collect::<Vec<_>>();
+
+	let mut state = beacon::initialize_beacon_state_from_eth1::<C, BLS>(
+		H256::repeat_byte(0x42), 1 << 40, &genesis_deposits::<C>(&datas)?,
+	)?;
+	state.genesis_time = genesis_time;
+
+	Ok(state)
+}
+
+/// Attach proofs to genesis deposits, each against the deposit root of the
+/// deposits up to and including it.
+fn genesis_deposits<C: Config>(datas: &[DepositData]) -> Result<Vec<Deposit>, Error> {
+	let mut tree = DepositTree::<C>::new();
+	let mut deposits = Vec::new();
+	for (index, data) in datas.iter().enumerate() {
+		tree.push(data);
+		deposits.push(Deposit {
+			proof: tree.proof(index as u64)
+				.ok_or(Error::InvalidGenesis)?
+				.try_into().map_err(|_| Error::InvalidGenesis)?,
+			data: data.clone(),
+		});
+	}
+	Ok(deposits)
+}
+
+fn parse_h256(value: &str) -> Result<H256, Error> {
+	let value = if value.starts_with("0x") { &value[2..] } else { value };
+	let bytes = hex::decode(value).map_err(|_| Error::InvalidHex(value.to_string()))?;
+	if bytes.len() != 32 {
+		return Err(Error::InvalidHex(value.to_string()))
+	}
+	Ok(H256::from_slice(&bytes))
+}
+
+pub(crate) fn fetch(url: &str) -> Result<Vec<u8>, Error> {
+	if url.starts_with("file://") {
+		let mut file = File::open(&url[7..])?;
+		let mut data = Vec::new();
+		file.read_to_end(&mut data)?;
+		return Ok(data)
+	}
+
+	if !url.starts_with("https://") && !url.starts_with("http://") {
+		return Err(Error::UnsupportedUrl(url.to_string()))
+	}
+
+	let response = ureq::get(url)
+		.timeout_connect(FETCH_CONNECT_TIMEOUT_MS)
+		.timeout_read(FETCH_READ_TIMEOUT_MS)
+		.call();
+	if let Some(error) = response.synthetic_error() {
+		return Err(Error::Http(error.to_string()))
+	}
+	if !response.ok() {
+		return Err(Error::InvalidResponse)
+	}
 
-	presets.insert("sapphire", Preset {
-		bootnodes: vec!["/dns4/prylabs.net/tcp/30001".to_string()],
-		genesis_state: include_bytes!("../res/eth2-testnets/prysm/Sapphire(v0.9.0)/genesis.ssz").to_vec(),
-	});
+	let mut data = Vec::new();
+	response.into_reader()
+		.take(MAX_FETCH_SIZE + 1)
+		.read_to_end(&mut data)?;
+	if data.len() as u64 > MAX_FETCH_SIZE {
+		return Err(Error::InvalidResponse)
+	}
 
-	presets
+	Ok(data)
 }
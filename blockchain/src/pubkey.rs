@@ -0,0 +1,90 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Cache of validator indices by public key.
+
+use beacon::{BeaconState, Config};
+use beacon::primitives::{ValidatorId, ValidatorIndex};
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+#[derive(Default)]
+struct Inner {
+	indices: HashMap<ValidatorId, ValidatorIndex>,
+	pubkeys: Vec<ValidatorId>,
+}
+
+/// Map of validator public keys to their index in the registry, extended
+/// as deposits are processed.
+///
+/// Validators are only ever appended to the registry, in deposit order, so
+/// that one map is valid for all forks. Lookups check the index against
+/// the length of the queried state, since a validator may not have been
+/// deposited yet on its fork.
+#[derive(Clone, Default)]
+pub struct PubkeyCache(Arc<RwLock<Inner>>);
+
+impl PubkeyCache {
+	/// Create a new empty cache.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Add the validators of `state` that are not yet in the cache.
+	pub fn import<C: Config>(&self, state: &BeaconState<C>) {
+		{
+			let inner = self.0.read().expect("Lock is poisoned");
+			if inner.pubkeys.len() >= state.validators.len() {
+				return
+			}
+		}
+
+		let mut inner = self.0.write().expect("Lock is poisoned");
+		// A registry that disagrees with the cache can only come from
+		// another deposit contract, so the cache is rebuilt.
+		let known = inner.pubkeys.len();
+		if known > 0 && state.validators[known - 1].pubkey != inner.pubkeys[known - 1] {
+			inner.indices.clear();
+			inner.pubkeys.clear();
+		}
+
+		for index in inner.pubkeys.len()..state.validators.len() {
+			let pubkey = state.validators[index].pubkey.clone();
+			inner.indices.insert(pubkey.clone(), index as ValidatorIndex);
+			inner.pubkeys.push(pubkey);
+		}
+	}
+
+	/// Get the index of the validator with `pubkey` in `state`, importing
+	/// new validators of the state first.
+	pub fn index_of<C: Config>(
+		&self,
+		state: &BeaconState<C>,
+		pubkey: &ValidatorId,
+	) -> Option<ValidatorIndex> {
+		self.import(state);
+
+		let inner = self.0.read().expect("Lock is poisoned");
+		inner.indices.get(pubkey).cloned()
+			.filter(|index| (*index as usize) < state.validators.len())
+			.filter(|index| state.validators[*index as usize].pubkey == *pubkey)
+	}
+
+	/// Number of cached validators.
+	pub fn len(&self) -> usize {
+		self.0.read().expect("Lock is poisoned").pubkeys.len()
+	}
+}
@@ -0,0 +1,86 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Regeneration of historical states that are not stored by the backend.
+
+use beacon::{BLSConfig, Config};
+use beacon::primitives::H256;
+use blockchain::{Block as BlockT, BlockExecutor, AsExternalities};
+use blockchain::backend::{Store, ChainQuery};
+use log::*;
+use crate::{Block, Executor, StateExternalities};
+use crate::backend::{StateStorage, StateRegeneration};
+
+/// Default maximum number of blocks replayed to regenerate a state.
+pub const DEFAULT_MAX_REPLAY: usize = 512;
+
+/// Regenerates a state from the nearest stored ancestor state, replaying
+/// the blocks in between through the executor.
+pub struct StateRegenerator<C: Config, BLS: BLSConfig> {
+	executor: Executor<C, BLS>,
+	max_replay: usize,
+}
+
+impl<C: Config, BLS: BLSConfig> StateRegenerator<C, BLS> {
+	/// Create a new regenerator, replaying at most `max_replay` blocks.
+	pub fn new(max_replay: usize) -> Self {
+		Self { executor: Executor::new(), max_replay }
+	}
+}
+
+impl<C: Config, BLS: BLSConfig> Default for StateRegenerator<C, BLS> {
+	fn default() -> Self {
+		Self::new(DEFAULT_MAX_REPLAY)
+	}
+}
+
+impl<Ba, C, BLS> StateRegeneration<Ba> for StateRegenerator<C, BLS> where
+	Ba: StateStorage + Store<Block=Block<C>>,
+	Ba::State: StateExternalities<Config=C> + AsExternalities<dyn StateExternalities<Config=C>>,
+	C: Config + Send + Sync,
+	BLS: BLSConfig + Send + Sync,
+{
+	fn regenerate(&self, backend: &Ba, id: &H256) -> Result<Ba::State, Ba::Error> {
+		let mut blocks = Vec::new();
+		let mut current = *id;
+		let mut state = loop {
+			if let Some(state) = backend.stored_state_at(&current)? {
+				break state
+			}
+			if blocks.len() >= self.max_replay {
+				warn!("State of block {} is more than {} blocks from a stored state", id, self.max_replay);
+				return Err(Ba::state_unavailable())
+			}
+
+			let block = backend.block_at(&current)?;
+			current = match block.parent_id() {
+				Some(parent_id) => parent_id,
+				None => return Err(Ba::state_unavailable()),
+			};
+			blocks.push(block);
+		};
+
+		trace!("Regenerating state of block {}, replaying {} blocks", id, blocks.len());
+		for block in blocks.iter().rev() {
+			if let Err(e) = self.executor.execute_block(block, state.as_externalities()) {
+				warn!("Replaying block {} for state regeneration failed: {:?}", block.id(), e);
+				return Err(Ba::state_unavailable())
+			}
+		}
+
+		Ok(state)
+	}
+}
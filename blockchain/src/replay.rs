@@ -0,0 +1,132 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+//! Re-execution of stored canonical blocks, to diagnose state mismatches
+//! and measure block processing performance.
+
+use beacon::{BeaconState, BLSConfig, Config};
+use beacon::primitives::{H256, Slot};
+use blockchain::Block as BlockT;
+use blockchain::backend::{Store, ChainQuery};
+use bm_le::tree_root;
+use log::*;
+use std::io::Write;
+use std::time::{Duration, Instant};
+use crate::{Block, StateExternalities};
+use crate::backend::CanonSlotQuery;
+
+/// Replay errors.
+#[derive(Debug)]
+pub enum Error {
+	/// Io error.
+	Io(std::io::Error),
+	/// Backend error.
+	Backend(String),
+	/// Executing a block failed.
+	Execution { slot: Slot, error: String },
+	/// The replayed state root does not match the one of the block.
+	StateMismatch { slot: Slot, expected: H256, actual: H256 },
+	/// No canonical block in the range.
+	EmptyRange,
+}
+
+impl From<std::io::Error> for Error {
+	fn from(error: std::io::Error) -> Error {
+		Error::Io(error)
+	}
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{:?}", self)
+	}
+}
+
+/// Outcome of a replay.
+#[derive(Clone, Debug, Default)]
+pub struct ReplayReport {
+	/// Number of replayed blocks.
+	pub blocks: usize,
+	/// Time spent executing blocks, excluding database reads.
+	pub execution_time: Duration,
+	/// Slowest block, with its execution time.
+	pub slowest: Option<(Slot, Duration)>,
+}
+
+/// Re-execute the canonical blocks of slots `from` to `to` inclusive on top
+/// of the stored state of the parent of the first one, checking the state
+/// root after each block. If `dump` is given, a `slot,block_root,state_root,
+/// micros` CSV line is written to it for each block.
+pub fn replay_blocks<B, C, BLS>(
+	backend: &B,
+	from: Slot,
+	to: Slot,
+	mut dump: Option<&mut dyn Write>,
+) -> Result<ReplayReport, Error> where
+	B: CanonSlotQuery + Store<Block=Block<C>>,
+	B::State: StateExternalities<Config=C>,
+	C: Config,
+	BLS: BLSConfig,
+{
+	let backend_error = |e: B::Error| Error::Backend(format!("{}", e));
+	let mut ids = Vec::new();
+	for slot in (from.as_u64()..=to.as_u64()).map(Slot::new) {
+		if let Some(id) = backend.lookup_canon_slot(slot).map_err(backend_error)? {
+			ids.push(id);
+		}
+	}
+	let first = match ids.first() {
+		Some(first) => backend.block_at(first).map_err(backend_error)?,
+		None => return Err(Error::EmptyRange),
+	};
+	let parent = first.parent_id()
+		.ok_or_else(|| Error::Backend("Genesis block can not be replayed".to_string()))?;
+	let mut state: BeaconState<C> = backend.state_at(&parent).map_err(backend_error)?
+		.state().clone();
+
+	if let Some(dump) = dump.as_mut() {
+		writeln!(dump, "slot,block_root,state_root,micros")?;
+	}
+
+	let mut report = ReplayReport::default();
+	for id in ids {
+		let block = backend.block_at(&id).map_err(backend_error)?;
+		let slot = block.0.slot;
+
+		let start = Instant::now();
+		beacon::apply_block::<C, BLS>(&block.0, &mut state, None)
+			.map_err(|e| Error::Execution { slot, error: format!("{:?}", e) })?;
+		let elapsed = start.elapsed();
+
+		let actual = tree_root::<C::Digest, _>(&state);
+		if let Some(dump) = dump.as_mut() {
+			writeln!(dump, "{},{:?},{:?},{}", slot, id, actual, elapsed.as_micros())?;
+		}
+		if actual != block.0.state_root {
+			return Err(Error::StateMismatch { slot, expected: block.0.state_root, actual })
+		}
+
+		report.blocks += 1;
+		report.execution_time += elapsed;
+		if report.slowest.map(|(_, slowest)| elapsed > slowest).unwrap_or(true) {
+			report.slowest = Some((slot, elapsed));
+		}
+		trace!("Replayed block {} at slot {} in {:?}", id, slot, elapsed);
+	}
+
+	info!("Replayed {} blocks from slot {} to {} in {:?}, slowest {:?}",
+		  report.blocks, from, to, report.execution_time, report.slowest);
+	Ok(report)
+}
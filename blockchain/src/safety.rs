@@ -0,0 +1,151 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Circuit breaker stopping block production and signing when the local
+//! finalized checkpoint conflicts with trusted monitors.
+
+use beacon::Config;
+use beacon::primitives::H256;
+use beacon::types::Checkpoint;
+use blockchain::Block as BlockT;
+use blockchain::backend::{Store, ChainQuery};
+use core::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use log::*;
+use crate::{Block, StateExternalities};
+use crate::api::FinalityCheckpoints;
+use crate::preset::fetch;
+
+/// Switch shared between the finality monitor and the block producer. Once
+/// tripped, it stays tripped until the node is restarted.
+#[derive(Clone, Default)]
+pub struct SafetySwitch(Arc<Mutex<Option<String>>>);
+
+impl SafetySwitch {
+	/// Create a new, untripped switch.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Trip the switch, recording the reason.
+	pub fn trip(&self, reason: String) {
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+		if inner.is_none() {
+			error!("Safety switch tripped, stopping block production and signing: {}", reason);
+			*inner = Some(reason);
+		}
+	}
+
+	/// Whether the switch is tripped.
+	pub fn is_tripped(&self) -> bool {
+		self.0.lock().expect("Lock is poisoned").is_some()
+	}
+
+	/// Reason the switch was tripped for.
+	pub fn reason(&self) -> Option<String> {
+		self.0.lock().expect("Lock is poisoned").clone()
+	}
+}
+
+/// Compares the local finalized checkpoint with the ones of trusted monitor
+/// nodes, tripping the safety switch on conflict.
+pub struct FinalityMonitor<B, C> {
+	backend: B,
+	monitors: Vec<String>,
+	switch: SafetySwitch,
+	_marker: PhantomData<C>,
+}
+
+impl<B, C> FinalityMonitor<B, C> where
+	B: ChainQuery + Store<Block=Block<C>>,
+	B::State: StateExternalities<Config=C>,
+	C: Config,
+{
+	/// Create a new monitor, given API endpoints of the trusted nodes.
+	pub fn new(backend: B, monitors: Vec<String>, switch: SafetySwitch) -> Self {
+		Self {
+			backend,
+			monitors: monitors.into_iter()
+				.map(|endpoint| endpoint.trim_end_matches('/').to_string())
+				.collect(),
+			switch,
+			_marker: PhantomData,
+		}
+	}
+
+	/// Check the local finalized checkpoint against all monitors.
+	/// Unreachable monitors are skipped.
+	pub fn check(&self) -> Result<(), B::Error> {
+		let local = self.backend.state_at(&self.backend.head())?
+			.state().finalized_checkpoint.clone();
+
+		for monitor in &self.monitors {
+			let remote = match fetch(
+				&format!("{}/eth/v1/beacon/states/head/finality_checkpoints", monitor)
+			).map_err(|e| format!("{:?}", e)).and_then(|data| {
+				serde_json::from_slice::<FinalityCheckpoints>(&data).map_err(|e| format!("{}", e))
+			}) {
+				Ok(remote) => remote.finalized,
+				Err(e) => {
+					warn!("Finality monitor {} unavailable: {}", monitor, e);
+					continue
+				},
+			};
+
+			if self.conflicts(&local, &remote)? {
+				self.switch.trip(format!(
+					"Finalized checkpoint {:?} conflicts with {:?} of monitor {}",
+					local, remote, monitor,
+				));
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Two finalized checkpoints conflict if the later one does not descend
+	/// from the earlier one. Descent can only be checked if the later block
+	/// is known locally, otherwise it is assumed.
+	fn conflicts(&self, local: &Checkpoint, remote: &Checkpoint) -> Result<bool, B::Error> {
+		// The genesis checkpoint has an empty root.
+		if local.root == H256::default() || remote.root == H256::default() {
+			return Ok(false)
+		}
+		if local.epoch == remote.epoch {
+			return Ok(local.root != remote.root)
+		}
+
+		let (earlier, later) = if local.epoch < remote.epoch {
+			(local, remote)
+		} else {
+			(remote, local)
+		};
+		if !self.backend.contains(&later.root)? {
+			return Ok(false)
+		}
+
+		let earlier_slot = beacon::utils::start_slot_of_epoch::<C>(earlier.epoch);
+		let mut current = self.backend.block_at(&later.root)?;
+		while current.0.slot > earlier_slot {
+			match current.parent_id() {
+				Some(parent) => current = self.backend.block_at(&parent)?,
+				None => break,
+			}
+		}
+
+		Ok(current.id() != earlier.root)
+	}
+}
@@ -0,0 +1,230 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Service lifecycle management, with ordered startup, reverse-order
+//! graceful shutdown and panic isolation.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{Arc, Mutex, Condvar};
+use std::sync::mpsc::{channel, Sender, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+use log::*;
+
+/// Shutdown signal shared between the service manager and a service.
+#[derive(Clone, Default)]
+pub struct Exit(Arc<(Mutex<bool>, Condvar)>);
+
+impl Exit {
+	/// Create a new unsignaled exit.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Signal the exit.
+	pub fn signal(&self) {
+		let (ref lock, ref condvar) = *self.0;
+		*lock.lock().expect("Lock is poisoned") = true;
+		condvar.notify_all();
+	}
+
+	/// Whether the exit has been signaled.
+	pub fn is_signaled(&self) -> bool {
+		*(self.0).0.lock().expect("Lock is poisoned")
+	}
+
+	/// Wait for the exit to be signaled, up to the given timeout. Returns
+	/// whether the exit has been signaled.
+	pub fn wait_timeout(&self, timeout: Duration) -> bool {
+		let (ref lock, ref condvar) = *self.0;
+		let deadline = Instant::now() + timeout;
+		let mut signaled = lock.lock().expect("Lock is poisoned");
+		while !*signaled {
+			let now = Instant::now();
+			if now >= deadline {
+				break
+			}
+			signaled = condvar.wait_timeout(signaled, deadline - now)
+				.expect("Lock is poisoned").0;
+		}
+		*signaled
+	}
+}
+
+/// Context passed to a running service.
+pub struct ServiceContext {
+	exit: Exit,
+	ready: Option<Sender<()>>,
+}
+
+impl ServiceContext {
+	/// Shutdown signal of the service.
+	pub fn exit(&self) -> &Exit {
+		&self.exit
+	}
+
+	/// Whether the service has been asked to shut down.
+	pub fn is_exiting(&self) -> bool {
+		self.exit.is_signaled()
+	}
+
+	/// Notify the manager that the service has started, so that the next
+	/// service can be started.
+	pub fn ready(&mut self) {
+		if let Some(ready) = self.ready.take() {
+			let _ = ready.send(());
+		}
+	}
+}
+
+/// Outcome of a service.
+#[derive(Debug)]
+pub enum Outcome {
+	/// Service stopped.
+	Stopped,
+	/// Service failed with an error.
+	Failed(String),
+	/// Service panicked.
+	Panicked,
+}
+
+struct RunningService {
+	name: &'static str,
+	exit: Exit,
+	shutdown_timeout: Duration,
+	outcome: Option<Outcome>,
+}
+
+/// Manager of services. Services are started in the order they are added,
+/// each one after the previous one reports ready, and stopped in reverse
+/// order.
+pub struct ServiceManager {
+	services: Vec<RunningService>,
+	sender: Sender<(usize, Outcome)>,
+	receiver: Receiver<(usize, Outcome)>,
+}
+
+impl ServiceManager {
+	/// Create a new manager with no services.
+	pub fn new() -> Self {
+		let (sender, receiver) = channel();
+		Self { services: Vec::new(), sender, receiver }
+	}
+
+	/// Start a service in its own thread, and wait until it reports ready or
+	/// stops. A panic in the service is caught and reported as its outcome.
+	pub fn start<F>(
+		&mut self,
+		name: &'static str,
+		shutdown_timeout: Duration,
+		f: F,
+	) -> Result<(), String> where
+		F: FnOnce(&mut ServiceContext) -> Result<(), String> + Send + 'static,
+	{
+		let index = self.services.len();
+		let exit = Exit::new();
+		let (ready_sender, ready_receiver) = channel();
+		let mut context = ServiceContext { exit: exit.clone(), ready: Some(ready_sender) };
+		let sender = self.sender.clone();
+
+		self.services.push(RunningService {
+			name, exit, shutdown_timeout,
+			outcome: None,
+		});
+
+		info!("Starting service {}", name);
+		thread::Builder::new()
+			.name(name.to_string())
+			.spawn(move || {
+				let outcome = match panic::catch_unwind(AssertUnwindSafe(|| f(&mut context))) {
+					Ok(Ok(())) => Outcome::Stopped,
+					Ok(Err(e)) => Outcome::Failed(e),
+					Err(_) => Outcome::Panicked,
+				};
+				let _ = sender.send((index, outcome));
+			})
+			.map_err(|e| format!("Spawning service {} failed: {}", name, e))?;
+
+		// The ready sender is only dropped without sending after the outcome
+		// has been reported, if the service stops without being ready.
+		match ready_receiver.recv() {
+			Ok(()) => Ok(()),
+			Err(_) => {
+				self.collect();
+				Err(format!("Service {} stopped during startup: {:?}",
+							name, self.services[index].outcome))
+			},
+		}
+	}
+
+	fn note(&mut self, index: usize, outcome: Outcome) {
+		let service = &mut self.services[index];
+		match outcome {
+			Outcome::Stopped => info!("Service {} stopped", service.name),
+			Outcome::Failed(ref e) => error!("Service {} failed: {}", service.name, e),
+			Outcome::Panicked => error!("Service {} panicked", service.name),
+		}
+		service.outcome = Some(outcome);
+	}
+
+	fn collect(&mut self) {
+		while let Ok((index, outcome)) = self.receiver.try_recv() {
+			self.note(index, outcome);
+		}
+	}
+
+	/// Block until any service stops, then shut down all services.
+	pub fn wait(mut self) {
+		if let Ok((index, outcome)) = self.receiver.recv() {
+			self.note(index, outcome);
+		}
+		self.shutdown();
+	}
+
+	/// Shut down all running services in reverse startup order, waiting for
+	/// each one up to its shutdown timeout.
+	pub fn shutdown(mut self) {
+		for index in (0..self.services.len()).rev() {
+			if self.services[index].outcome.is_some() {
+				continue
+			}
+
+			let name = self.services[index].name;
+			info!("Stopping service {}", name);
+			self.services[index].exit.signal();
+
+			let deadline = Instant::now() + self.services[index].shutdown_timeout;
+			while self.services[index].outcome.is_none() {
+				let now = Instant::now();
+				let timeout = if deadline > now { deadline - now } else { Duration::from_secs(0) };
+				match self.receiver.recv_timeout(timeout) {
+					Ok((index, outcome)) => self.note(index, outcome),
+					Err(RecvTimeoutError::Timeout) => {
+						warn!("Service {} did not stop in time, abandoning it", name);
+						break
+					},
+					Err(RecvTimeoutError::Disconnected) => break,
+				}
+			}
+		}
+	}
+}
+
+impl Default for ServiceManager {
+	fn default() -> Self {
+		Self::new()
+	}
+}
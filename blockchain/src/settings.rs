@@ -0,0 +1,312 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Settings reloadable without restarting the node.
+
+use beacon::primitives::{H256, ValidatorId};
+use shasper_network::PeerManager;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use log::{info, warn, LevelFilter};
+use serde::Serialize;
+use crate::gc::ABANDONED_FORK_DEPTH;
+use crate::graffiti::{GraffitiConfig, RotatingGraffiti};
+
+/// Names of the settings applied on reload. Any other setting in the file
+/// is compared against the value the node was started with.
+pub const RELOADABLE_SETTINGS: &[&str] = &[
+	"log-level", "max-peers", "graffiti", "abandoned-fork-depth", "track-validator",
+	"block-deadline",
+];
+
+/// Default time into the slot by which a proposed block must be built.
+pub const DEFAULT_BLOCK_DEADLINE: Duration = Duration::from_millis(1000);
+
+/// Settings errors.
+#[derive(Debug)]
+pub enum Error {
+	/// Io error.
+	Io(std::io::Error),
+	/// Yaml error.
+	Yaml(serde_yaml::Error),
+	/// A setting has an invalid value.
+	InvalidValue(String),
+}
+
+impl From<std::io::Error> for Error {
+	fn from(error: std::io::Error) -> Error {
+		Error::Io(error)
+	}
+}
+
+impl From<serde_yaml::Error> for Error {
+	fn from(error: serde_yaml::Error) -> Error {
+		Error::Yaml(error)
+	}
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Error::Io(e) => write!(f, "{}", e),
+			Error::Yaml(e) => write!(f, "{}", e),
+			Error::InvalidValue(name) => write!(f, "Invalid value of setting {}", name),
+		}
+	}
+}
+
+struct Inner {
+	graffiti: H256,
+	validator_graffiti: HashMap<ValidatorId, H256>,
+	graffiti_file: Option<RotatingGraffiti>,
+	abandoned_fork_depth: usize,
+	local_validators: Vec<ValidatorId>,
+	tracked_validators: Vec<ValidatorId>,
+	block_deadline: Duration,
+}
+
+/// Current values of the reloadable settings, shared with the services
+/// using them.
+#[derive(Clone)]
+pub struct LiveSettings(Arc<Mutex<Inner>>);
+
+impl LiveSettings {
+	/// Create new settings with defaults. Local validators are always
+	/// tracked, in addition to the reloadable watch-list.
+	pub fn new(local_validators: Vec<ValidatorId>) -> Self {
+		Self(Arc::new(Mutex::new(Inner {
+			graffiti: H256::default(),
+			validator_graffiti: HashMap::new(),
+			graffiti_file: None,
+			abandoned_fork_depth: ABANDONED_FORK_DEPTH,
+			local_validators,
+			tracked_validators: Vec::new(),
+			block_deadline: DEFAULT_BLOCK_DEADLINE,
+		})))
+	}
+
+	/// Graffiti of produced blocks.
+	pub fn graffiti(&self) -> H256 {
+		self.0.lock().expect("Lock is poisoned").graffiti
+	}
+
+	/// Apply graffiti configured at startup.
+	pub fn set_graffiti_config(&self, config: GraffitiConfig) {
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+		if let Some(graffiti) = config.graffiti {
+			inner.graffiti = graffiti;
+		}
+		inner.validator_graffiti = config.validators;
+		inner.graffiti_file = config.file.map(RotatingGraffiti::new);
+	}
+
+	/// Graffiti of a block produced by `validator`. This is the graffiti of
+	/// the validator if configured, else the next one of the rotating
+	/// graffiti file if any, else the node graffiti.
+	pub fn graffiti_for(&self, validator: &ValidatorId) -> H256 {
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+		if let Some(graffiti) = inner.validator_graffiti.get(validator) {
+			return *graffiti
+		}
+		if let Some(file) = inner.graffiti_file.as_mut() {
+			match file.next_graffiti() {
+				Ok(Some(graffiti)) => return graffiti,
+				Ok(None) => (),
+				Err(e) => warn!("Reading graffiti file failed: {}", e),
+			}
+		}
+		inner.graffiti
+	}
+
+	/// Number of blocks a fork tip must be behind the head before the fork
+	/// is garbage collected.
+	pub fn abandoned_fork_depth(&self) -> usize {
+		self.0.lock().expect("Lock is poisoned").abandoned_fork_depth
+	}
+
+	/// Time into the slot by which a proposed block must be built. Pool
+	/// operations are no longer added to the block past it.
+	pub fn block_deadline(&self) -> Duration {
+		self.0.lock().expect("Lock is poisoned").block_deadline
+	}
+
+	/// Validators monitored through metrics.
+	pub fn tracked_validators(&self) -> Vec<ValidatorId> {
+		let inner = self.0.lock().expect("Lock is poisoned");
+		let mut validators = inner.local_validators.clone();
+		for validator in &inner.tracked_validators {
+			if !validators.contains(validator) {
+				validators.push(validator.clone());
+			}
+		}
+		validators
+	}
+}
+
+/// Outcome of a settings reload.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct ReloadReport {
+	/// Settings applied to the running node.
+	pub applied: Vec<String>,
+	/// Settings changed from the startup value, taking effect only after a
+	/// restart.
+	pub requires_restart: Vec<String>,
+	/// Settings not known to the node.
+	pub unknown: Vec<String>,
+}
+
+/// Settings file, reloaded on demand.
+#[derive(Clone)]
+pub struct SettingsFile {
+	path: PathBuf,
+	startup: BTreeMap<String, Option<String>>,
+	settings: LiveSettings,
+	peers: PeerManager,
+}
+
+impl SettingsFile {
+	/// Create a new settings file. `startup` holds the values of the
+	/// remaining node settings as started, keyed by command line flag.
+	pub fn new<P: AsRef<Path>>(
+		path: P,
+		startup: BTreeMap<String, Option<String>>,
+		settings: LiveSettings,
+		peers: PeerManager,
+	) -> Self {
+		Self { path: path.as_ref().to_path_buf(), startup, settings, peers }
+	}
+
+	/// Settings shared with the services.
+	pub fn settings(&self) -> &LiveSettings {
+		&self.settings
+	}
+
+	/// Read the settings file, and apply the reloadable settings. Nothing is
+	/// applied if any value is invalid. The log level can not be raised over
+	/// the `RUST_LOG` filters.
+	pub fn reload(&self) -> Result<ReloadReport, Error> {
+		let file = File::open(&self.path)?;
+		let values = serde_yaml::from_reader::<_, BTreeMap<String, serde_yaml::Value>>(
+			BufReader::new(file)
+		)?;
+
+		let mut report = ReloadReport::default();
+		let mut log_level = None;
+		let mut max_peers = None;
+		let mut graffiti = None;
+		let mut abandoned_fork_depth = None;
+		let mut tracked_validators = None;
+		let mut block_deadline = None;
+
+		for (name, value) in &values {
+			let invalid = || Error::InvalidValue(name.clone());
+			match name.as_str() {
+				"log-level" => {
+					log_level = Some(LevelFilter::from_str(value.as_str().ok_or_else(invalid)?)
+						.map_err(|_| invalid())?);
+				},
+				"max-peers" => {
+					max_peers = Some(value.as_u64().ok_or_else(invalid)? as usize);
+				},
+				"graffiti" => {
+					let string = value.as_str().ok_or_else(invalid)?;
+					if string.len() > 32 {
+						return Err(invalid())
+					}
+					let mut bytes = [0u8; 32];
+					bytes[..string.len()].copy_from_slice(string.as_bytes());
+					graffiti = Some(H256::from(bytes));
+				},
+				"abandoned-fork-depth" => {
+					abandoned_fork_depth = Some(value.as_u64().ok_or_else(invalid)? as usize);
+				},
+				"block-deadline" => {
+					block_deadline = Some(Duration::from_millis(value.as_u64().ok_or_else(invalid)?));
+				},
+				"track-validator" => {
+					let strings = match value {
+						serde_yaml::Value::String(string) => vec![string.as_str()],
+						serde_yaml::Value::Sequence(values) => values.iter()
+							.map(|value| value.as_str().ok_or_else(invalid))
+							.collect::<Result<Vec<_>, _>>()?,
+						_ => return Err(invalid()),
+					};
+					tracked_validators = Some(strings.into_iter().map(|string| {
+						let string = if string.starts_with("0x") { &string[2..] } else { string };
+						let bytes = hex::decode(string).map_err(|_| invalid())?;
+						if bytes.len() != 48 {
+							return Err(invalid())
+						}
+						Ok(ValidatorId::from_slice(&bytes))
+					}).collect::<Result<Vec<_>, _>>()?);
+				},
+				_ => match self.startup.get(name) {
+					Some(startup) => if startup != &setting_string(value) {
+						report.requires_restart.push(name.clone());
+					},
+					None => report.unknown.push(name.clone()),
+				},
+			}
+		}
+
+		if let Some(log_level) = log_level {
+			log::set_max_level(log_level);
+			report.applied.push("log-level".to_string());
+		}
+		if let Some(max_peers) = max_peers {
+			self.peers.set_max_peers(max_peers);
+			report.applied.push("max-peers".to_string());
+		}
+		{
+			let mut inner = self.settings.0.lock().expect("Lock is poisoned");
+			if let Some(graffiti) = graffiti {
+				inner.graffiti = graffiti;
+				report.applied.push("graffiti".to_string());
+			}
+			if let Some(abandoned_fork_depth) = abandoned_fork_depth {
+				inner.abandoned_fork_depth = abandoned_fork_depth;
+				report.applied.push("abandoned-fork-depth".to_string());
+			}
+			if let Some(tracked_validators) = tracked_validators {
+				inner.tracked_validators = tracked_validators;
+				report.applied.push("track-validator".to_string());
+			}
+			if let Some(block_deadline) = block_deadline {
+				inner.block_deadline = block_deadline;
+				report.applied.push("block-deadline".to_string());
+			}
+		}
+
+		info!("Reloaded settings from {}: applied {:?}, requires restart {:?}, unknown {:?}",
+			  self.path.display(), report.applied, report.requires_restart, report.unknown);
+		Ok(report)
+	}
+}
+
+fn setting_string(value: &serde_yaml::Value) -> Option<String> {
+	match value {
+		serde_yaml::Value::Null => None,
+		serde_yaml::Value::Bool(value) => Some(value.to_string()),
+		serde_yaml::Value::Number(value) => Some(value.to_string()),
+		serde_yaml::Value::String(value) => Some(value.clone()),
+		_ => Some(serde_yaml::to_string(value).unwrap_or_default()),
+	}
+}
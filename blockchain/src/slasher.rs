@@ -0,0 +1,296 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Slashing detection from seen blocks and attestations.
+
+use beacon::{BeaconExecutive, BLSConfig, Config};
+use beacon::components::slashing;
+use beacon::primitives::{H256, Epoch, Slot, ValidatorIndex};
+use beacon::types::{
+	AttesterSlashing, BeaconBlockHeader, IndexedAttestation, ProposerSlashing,
+};
+use blockchain::Block as BlockT;
+use blockchain::backend::{Store, ChainQuery};
+use bm_le::tree_root;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use log::*;
+use crate::{Block, SharedOperationPool, StateExternalities};
+
+/// Number of epochs of attestation and proposal history kept.
+pub const SLASHER_HISTORY_EPOCHS: u64 = 4096;
+
+struct Inner<C: Config> {
+	proposals: HashMap<(ValidatorIndex, Slot), BeaconBlockHeader>,
+	attestations: HashMap<ValidatorIndex, BTreeMap<Epoch, Arc<IndexedAttestation<C>>>>,
+	slashed: HashSet<ValidatorIndex>,
+}
+
+/// Index of seen attestations and block headers by validator and epoch,
+/// detecting double proposals, double votes and surround votes.
+#[derive(Clone)]
+pub struct Slasher<C: Config>(Arc<Mutex<Inner<C>>>);
+
+impl<C: Config> Slasher<C> {
+	/// Create a new empty slasher.
+	pub fn new() -> Self {
+		Self(Arc::new(Mutex::new(Inner {
+			proposals: HashMap::new(),
+			attestations: HashMap::new(),
+			slashed: HashSet::new(),
+		})))
+	}
+
+	/// Ingest a block header of the given proposer, returning a proposer
+	/// slashing if it conflicts with a previously seen header.
+	pub fn ingest_header(
+		&self,
+		proposer_index: ValidatorIndex,
+		header: BeaconBlockHeader,
+	) -> Option<ProposerSlashing> {
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+		if inner.slashed.contains(&proposer_index) {
+			return None
+		}
+
+		let existing = inner.proposals.entry((proposer_index, header.slot))
+			.or_insert_with(|| header.clone())
+			.clone();
+		if existing == header {
+			return None
+		}
+
+		inner.slashed.insert(proposer_index);
+		Some(ProposerSlashing {
+			proposer_index,
+			header_1: existing,
+			header_2: header,
+		})
+	}
+
+	/// Ingest an indexed attestation, returning attester slashings for
+	/// every attester that previously cast a conflicting vote.
+	pub fn ingest_attestation(
+		&self,
+		attestation: IndexedAttestation<C>,
+	) -> Vec<AttesterSlashing<C>> {
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+		let attestation = Arc::new(attestation);
+		let target_epoch = attestation.data.target.epoch;
+		let attesters = attestation.custody_bit_0_indices.iter()
+			.chain(attestation.custody_bit_1_indices.iter())
+			.cloned()
+			.collect::<Vec<_>>();
+
+		let mut slashings = Vec::new();
+		for index in attesters {
+			if inner.slashed.contains(&index) {
+				continue
+			}
+
+			// Order each pair so that the first attestation is the
+			// surrounding one, as required by the slashing condition.
+			let conflict = inner.attestations.get(&index).and_then(|records| {
+				records.values().find_map(|existing| {
+					if let Some(kind) = slashing::check(&**existing, &*attestation) {
+						Some((kind, existing.clone(), attestation.clone()))
+					} else if let Some(kind) = slashing::check(&*attestation, &**existing) {
+						Some((kind, attestation.clone(), existing.clone()))
+					} else {
+						None
+					}
+				})
+			});
+
+			match conflict {
+				Some((kind, attestation_1, attestation_2)) => {
+					let attestation_1 = (*attestation_1).clone();
+					let attestation_2 = (*attestation_2).clone();
+					info!("Detected {:?} of validator {} at target epoch {}",
+						  kind, index, target_epoch);
+					inner.slashed.insert(index);
+					slashings.push(AttesterSlashing { attestation_1, attestation_2 });
+				},
+				None => {
+					inner.attestations.entry(index).or_default()
+						.entry(target_epoch)
+						.or_insert_with(|| attestation.clone());
+				},
+			}
+		}
+
+		// Each aggregate may slash many validators at once. Keep only one
+		// slashing per conflicting pair.
+		slashings.dedup();
+		slashings
+	}
+
+	/// Drop history before the given epoch.
+	pub fn prune(&self, current_epoch: Epoch) {
+		let min_epoch = current_epoch.saturating_sub(SLASHER_HISTORY_EPOCHS);
+		let min_slot = beacon::utils::start_slot_of_epoch::<C>(min_epoch);
+		let mut inner = self.0.lock().expect("Lock is poisoned");
+		inner.proposals.retain(|(_, slot), _| *slot >= min_slot);
+		for records in inner.attestations.values_mut() {
+			*records = records.split_off(&min_epoch);
+		}
+		inner.attestations.retain(|_, records| !records.is_empty());
+	}
+}
+
+impl<C: Config> Default for Slasher<C> {
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+/// Number of blocks behind the head whose new children are still looked
+/// for.
+pub const FOLLOW_DEPTH: usize = 64;
+
+/// Follower feeding all imported blocks, including those on forks, and
+/// all pooled attestations into a slasher, and putting detected slashings
+/// into the operation pool.
+pub struct ChainSlasher<B: Store, C: Config, BLS: BLSConfig> {
+	backend: B,
+	slasher: Slasher<C>,
+	pool: SharedOperationPool<C, BLS>,
+	frontier: HashMap<H256, usize>,
+	seen_pool: HashSet<H256>,
+}
+
+impl<B, C, BLS> ChainSlasher<B, C, BLS> where
+	B: ChainQuery + Store<Block=Block<C>>,
+	B::State: StateExternalities<Config=C>,
+	C: Config,
+	BLS: BLSConfig,
+{
+	/// Create a new follower, starting at the current head.
+	pub fn new(backend: B, slasher: Slasher<C>, pool: SharedOperationPool<C, BLS>) -> Self {
+		let head = backend.head();
+		let head_depth = backend.depth_at(&head).unwrap_or(0);
+		let mut frontier = HashMap::new();
+		frontier.insert(head, head_depth);
+
+		Self { backend, slasher, pool, frontier, seen_pool: HashSet::new() }
+	}
+
+	/// Process blocks imported and attestations pooled since the last call.
+	pub fn process(&mut self) -> Result<(), B::Error> {
+		let mut queue = self.frontier.iter()
+			.map(|(id, depth)| (*id, *depth))
+			.collect::<Vec<_>>();
+		while let Some((id, depth)) = queue.pop() {
+			// Blocks may be removed by fork garbage collection meanwhile.
+			let children = match self.backend.children_at(&id) {
+				Ok(children) => children,
+				Err(_) => {
+					self.frontier.remove(&id);
+					continue
+				},
+			};
+
+			for child in children {
+				if self.frontier.contains_key(&child) {
+					continue
+				}
+				self.process_block(&child)?;
+				self.frontier.insert(child, depth + 1);
+				queue.push((child, depth + 1));
+			}
+		}
+
+		let head_depth = self.backend.depth_at(&self.backend.head())?;
+		self.frontier.retain(|_, depth| *depth + FOLLOW_DEPTH >= head_depth);
+
+		self.process_pool()?;
+
+		let head_state = self.backend.state_at(&self.backend.head())?;
+		let current_epoch = beacon::utils::epoch_of_slot::<C>(head_state.state().slot);
+		self.slasher.prune(current_epoch);
+
+		Ok(())
+	}
+
+	fn process_block(&mut self, id: &H256) -> Result<(), B::Error> {
+		let block = self.backend.block_at(id)?;
+		let mut state = self.backend.state_at(id)?.state().clone();
+		let executive = BeaconExecutive::new(&mut state);
+
+		let header = BeaconBlockHeader {
+			slot: block.0.slot,
+			parent_root: block.0.parent_root,
+			state_root: block.0.state_root,
+			body_root: tree_root::<C::Digest, _>(&block.0.body),
+			signature: block.0.signature.clone(),
+		};
+		match executive.beacon_proposer_index() {
+			Ok(proposer_index) => {
+				if let Some(slashing) = self.slasher.ingest_header(proposer_index, header) {
+					info!("Detected double proposal of validator {}", proposer_index);
+					self.pool.lock().insert_proposer_slashing(slashing);
+				}
+			},
+			Err(e) => warn!("Unable to get proposer of block {}: {:?}", id, e),
+		}
+
+		for attestation in block.0.body.attestations.iter() {
+			match executive.indexed_attestation(attestation.clone()) {
+				Ok(indexed) => self.ingest_attestation(indexed),
+				Err(e) => warn!("Unable to index attestation in block {}: {:?}", id, e),
+			}
+		}
+
+		Ok(())
+	}
+
+	fn process_pool(&mut self) -> Result<(), B::Error> {
+		let attestations = self.pool.lock().attestations()
+			.iter()
+			.map(|(_, attestation)| attestation.clone())
+			.collect::<Vec<_>>();
+		let mut state = self.backend.state_at(&self.backend.head())?.state().clone();
+		let executive = BeaconExecutive::new(&mut state);
+
+		let mut seen_pool = HashSet::new();
+		for attestation in attestations {
+			let root = tree_root::<C::Digest, _>(&attestation);
+			seen_pool.insert(root);
+			if self.seen_pool.contains(&root) {
+				continue
+			}
+
+			// Attestations of far away slots are skipped, as the head state
+			// cannot compute their committees.
+			if let Ok(indexed) = executive.indexed_attestation(attestation) {
+				self.ingest_attestation(indexed);
+			}
+		}
+		self.seen_pool = seen_pool;
+
+		Ok(())
+	}
+
+	fn ingest_attestation(&self, attestation: IndexedAttestation<C>) {
+		let slashings = self.slasher.ingest_attestation(attestation);
+		if !slashings.is_empty() {
+			let mut pool = self.pool.lock();
+			for slashing in slashings {
+				pool.insert_attester_slashing(slashing);
+			}
+		}
+	}
+}
@@ -0,0 +1,283 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Runtime loading of spec configuration from standard `config.yaml` files.
+
+use beacon::{Config, ConfigValues};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Spec configuration loading errors.
+#[derive(Debug)]
+pub enum Error {
+	/// Io error.
+	Io(std::io::Error),
+	/// Yaml error.
+	Yaml(serde_yaml::Error),
+	/// A value is neither an integer nor a hex string.
+	InvalidValue(String),
+	/// No built-in config matches the loaded values.
+	NoMatchingConfig(Vec<Mismatch>),
+}
+
+impl From<std::io::Error> for Error {
+	fn from(error: std::io::Error) -> Error {
+		Error::Io(error)
+	}
+}
+
+impl From<serde_yaml::Error> for Error {
+	fn from(error: serde_yaml::Error) -> Error {
+		Error::Yaml(error)
+	}
+}
+
+/// Value differing between a spec file and a config.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+	/// Spec constant name.
+	pub name: String,
+	/// Value of the config.
+	pub expected: u64,
+	/// Value of the spec file.
+	pub found: u64,
+}
+
+/// Values of a config, keyed by spec constant name.
+pub fn config_values<C: Config>() -> BTreeMap<&'static str, u64> {
+	vec![
+		("MAX_COMMITTEES_PER_SLOT", C::max_committees_per_slot() as u64),
+		("TARGET_COMMITTEE_SIZE", C::target_committee_size() as u64),
+		("MAX_VALIDATORS_PER_COMMITTEE", C::max_validators_per_committee() as u64),
+		("MIN_PER_EPOCH_CHURN_LIMIT", C::min_per_epoch_churn_limit() as u64),
+		("CHURN_LIMIT_QUOTIENT", C::churn_limit_quotient() as u64),
+		("SHUFFLE_ROUND_COUNT", C::shuffle_round_count() as u64),
+		("MIN_GENESIS_ACTIVE_VALIDATOR_COUNT", C::min_genesis_active_validator_count() as u64),
+		("MIN_GENESIS_TIME", C::min_genesis_time() as u64),
+		("MIN_DEPOSIT_AMOUNT", C::min_deposit_amount().as_u64()),
+		("MAX_EFFECTIVE_BALANCE", C::max_effective_balance().as_u64()),
+		("EJECTION_BALANCE", C::ejection_balance().as_u64()),
+		("EFFECTIVE_BALANCE_INCREMENT", C::effective_balance_increment().as_u64()),
+		("GENESIS_SLOT", C::genesis_slot().as_u64()),
+		("GENESIS_EPOCH", C::genesis_epoch().as_u64()),
+		("BLS_WITHDRAWAL_PREFIX", C::bls_withdrawal_prefix_byte() as u64),
+		("SECONDS_PER_SLOT", C::seconds_per_slot() as u64),
+		("MIN_ATTESTATION_INCLUSION_DELAY", C::min_attestation_inclusion_delay() as u64),
+		("SLOTS_PER_EPOCH", C::slots_per_epoch() as u64),
+		("MIN_SEED_LOOKAHEAD", C::min_seed_lookahead() as u64),
+		("MAX_SEED_LOOKAHEAD", C::max_seed_lookahead() as u64),
+		("SLOTS_PER_ETH1_VOTING_PERIOD", C::slots_per_eth1_voting_period() as u64),
+		("SLOTS_PER_HISTORICAL_ROOT", C::slots_per_historical_root() as u64),
+		("MIN_VALIDATOR_WITHDRAWABILITY_DELAY", C::min_validator_withdrawability_delay() as u64),
+		("PERSISTENT_COMMITTEE_PERIOD", C::persistent_committee_period() as u64),
+		("MIN_EPOCHS_TO_INACTIVITY_PENALTY", C::min_epochs_to_inactivity_penalty() as u64),
+		("EPOCHS_PER_HISTORICAL_VECTOR", C::epochs_per_historical_vector() as u64),
+		("EPOCHS_PER_SLASHINGS_VECTOR", C::epochs_per_slashings_vector() as u64),
+		("HISTORICAL_ROOTS_LIMIT", C::historical_roots_limit() as u64),
+		("VALIDATOR_REGISTRY_LIMIT", C::validator_registry_limit() as u64),
+		("BASE_REWARD_FACTOR", C::base_reward_factor() as u64),
+		("WHISTLEBLOWER_REWARD_QUOTIENT", C::whistleblower_reward_quotient() as u64),
+		("PROPOSER_REWARD_QUOTIENT", C::proposer_reward_quotient() as u64),
+		("INACTIVITY_PENALTY_QUOTIENT", C::inactivity_penalty_quotient() as u64),
+		("MIN_SLASHING_PENALTY_QUOTIENT", C::min_slashing_penalty_quotient() as u64),
+		("MAX_PROPOSER_SLASHINGS", C::max_proposer_slashings() as u64),
+		("MAX_ATTESTER_SLASHINGS", C::max_attester_slashings() as u64),
+		("MAX_ATTESTATIONS", C::max_attestations() as u64),
+		("MAX_DEPOSITS", C::max_deposits() as u64),
+		("MAX_VOLUNTARY_EXITS", C::max_voluntary_exits() as u64),
+		("DOMAIN_BEACON_PROPOSER", C::domain_beacon_proposer() as u64),
+		("DOMAIN_BEACON_ATTESTER", C::domain_beacon_attester() as u64),
+		("DOMAIN_RANDAO", C::domain_randao() as u64),
+		("DOMAIN_DEPOSIT", C::domain_deposit() as u64),
+		("DOMAIN_VOLUNTARY_EXIT", C::domain_voluntary_exit() as u64),
+	].into_iter().collect()
+}
+
+/// Spec constants fixed at the type level or as defaults of `Config`, which
+/// cannot be overridden at runtime by `DynamicConfig`.
+pub const FIXED_CONSTANTS: &[&str] = &[
+	"MAX_VALIDATORS_PER_COMMITTEE", "SLOTS_PER_EPOCH", "SLOTS_PER_ETH1_VOTING_PERIOD",
+	"SLOTS_PER_HISTORICAL_ROOT", "EPOCHS_PER_HISTORICAL_VECTOR",
+	"EPOCHS_PER_SLASHINGS_VECTOR", "HISTORICAL_ROOTS_LIMIT", "VALIDATOR_REGISTRY_LIMIT",
+	"MAX_PROPOSER_SLASHINGS", "MAX_ATTESTER_SLASHINGS", "MAX_ATTESTATIONS", "MAX_DEPOSITS",
+	"MAX_VOLUNTARY_EXITS", "DOMAIN_BEACON_PROPOSER", "DOMAIN_BEACON_ATTESTER",
+	"DOMAIN_RANDAO", "DOMAIN_DEPOSIT", "DOMAIN_VOLUNTARY_EXIT",
+];
+
+/// Spec configuration loaded from a `config.yaml` file. Constants not used
+/// by this implementation are ignored.
+#[derive(Debug, Clone, Default)]
+pub struct SpecConfig {
+	values: BTreeMap<String, u64>,
+}
+
+impl SpecConfig {
+	/// Load a spec configuration file.
+	pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+		let file = File::open(path)?;
+		let raw = serde_yaml::from_reader::<_, BTreeMap<String, serde_yaml::Value>>(
+			BufReader::new(file)
+		)?;
+
+		let mut values = BTreeMap::new();
+		for (name, value) in raw {
+			let value = match value {
+				serde_yaml::Value::Number(ref number) => number.as_u64(),
+				serde_yaml::Value::String(ref string) if string.starts_with("0x") => {
+					let bytes = hex::decode(&string[2..])
+						.map_err(|_| Error::InvalidValue(name.clone()))?;
+					if bytes.len() > 8 {
+						return Err(Error::InvalidValue(name))
+					}
+					// Hex values (prefixes and domain types) are little endian bytes.
+					Some(bytes.iter().rev().fold(0u64, |acc, b| (acc << 8) | *b as u64))
+				},
+				_ => None,
+			};
+			match value {
+				Some(value) => { values.insert(name, value); },
+				None => return Err(Error::InvalidValue(name)),
+			}
+		}
+
+		Ok(Self { values })
+	}
+
+	/// Get a loaded value.
+	pub fn get(&self, name: &str) -> Option<u64> {
+		self.values.get(name).cloned()
+	}
+
+	/// Values of the spec file differing from the config.
+	pub fn mismatches<C: Config>(&self) -> Vec<Mismatch> {
+		config_values::<C>().into_iter()
+			.filter_map(|(name, expected)| match self.get(name) {
+				Some(found) if found != expected => Some(Mismatch {
+					name: name.to_string(), expected, found,
+				}),
+				_ => None,
+			})
+			.collect()
+	}
+
+	/// Values of the spec file differing from the config, restricted to
+	/// constants that cannot be overridden at runtime.
+	pub fn fixed_mismatches<C: Config>(&self) -> Vec<Mismatch> {
+		self.mismatches::<C>().into_iter()
+			.filter(|mismatch| FIXED_CONSTANTS.contains(&mismatch.name.as_str()))
+			.collect()
+	}
+
+	/// Runtime values of the config, overridden by the loaded values.
+	pub fn values<C: Config>(&self) -> ConfigValues {
+		let mut values = ConfigValues::of::<C>();
+		macro_rules! set {
+			( $( $name:expr => $field:ident ),* $(,)* ) => {
+				$( if let Some(value) = self.get($name) {
+					values.$field = value as _;
+				} )*
+			}
+		}
+		macro_rules! set_typed {
+			( $( $name:expr => $field:ident ),* $(,)* ) => {
+				$( if let Some(value) = self.get($name) {
+					values.$field = value.into();
+				} )*
+			}
+		}
+		set! {
+			"MAX_COMMITTEES_PER_SLOT" => max_committees_per_slot,
+			"TARGET_COMMITTEE_SIZE" => target_committee_size,
+			"MIN_PER_EPOCH_CHURN_LIMIT" => min_per_epoch_churn_limit,
+			"CHURN_LIMIT_QUOTIENT" => churn_limit_quotient,
+			"SHUFFLE_ROUND_COUNT" => shuffle_round_count,
+			"MIN_GENESIS_ACTIVE_VALIDATOR_COUNT" => min_genesis_active_validator_count,
+			"MIN_GENESIS_TIME" => min_genesis_time,
+			"BLS_WITHDRAWAL_PREFIX" => bls_withdrawal_prefix_byte,
+			"SECONDS_PER_SLOT" => seconds_per_slot,
+			"MIN_ATTESTATION_INCLUSION_DELAY" => min_attestation_inclusion_delay,
+			"MIN_SEED_LOOKAHEAD" => min_seed_lookahead,
+			"MAX_SEED_LOOKAHEAD" => max_seed_lookahead,
+			"MIN_VALIDATOR_WITHDRAWABILITY_DELAY" => min_validator_withdrawability_delay,
+			"PERSISTENT_COMMITTEE_PERIOD" => persistent_committee_period,
+			"MAX_EPOCHS_PER_CROSSLINK" => max_epochs_per_crosslink,
+			"MIN_EPOCHS_TO_INACTIVITY_PENALTY" => min_epochs_to_inactivity_penalty,
+			"BASE_REWARD_FACTOR" => base_reward_factor,
+			"WHISTLEBLOWER_REWARD_QUOTIENT" => whistleblower_reward_quotient,
+			"PROPOSER_REWARD_QUOTIENT" => proposer_reward_quotient,
+			"INACTIVITY_PENALTY_QUOTIENT" => inactivity_penalty_quotient,
+			"MIN_SLASHING_PENALTY_QUOTIENT" => min_slashing_penalty_quotient,
+		}
+		set_typed! {
+			"MIN_DEPOSIT_AMOUNT" => min_deposit_amount,
+			"MAX_EFFECTIVE_BALANCE" => max_effective_balance,
+			"EJECTION_BALANCE" => ejection_balance,
+			"EFFECTIVE_BALANCE_INCREMENT" => effective_balance_increment,
+			"GENESIS_SLOT" => genesis_slot,
+			"GENESIS_EPOCH" => genesis_epoch,
+		}
+		values
+	}
+
+	/// Name of the built-in config whose fixed constants match the loaded
+	/// values, to be used as the base of a `DynamicConfig`, together with the
+	/// runtime values.
+	pub fn dynamic_config(&self) -> Result<(&'static str, ConfigValues), Error> {
+		let candidates = vec![
+			("minimal", self.fixed_mismatches::<beacon::MinimalConfig>(),
+			 self.values::<beacon::MinimalConfig>()),
+			("mainnet", self.fixed_mismatches::<beacon::MainnetConfig>(),
+			 self.values::<beacon::MainnetConfig>()),
+			("sapphire", self.fixed_mismatches::<beacon::SapphireConfig>(),
+			 self.values::<beacon::SapphireConfig>()),
+		];
+
+		let mut closest: Option<Vec<Mismatch>> = None;
+		for (name, mismatches, values) in candidates {
+			if mismatches.is_empty() {
+				return Ok((name, values))
+			}
+			if closest.as_ref().map(|c| mismatches.len() < c.len()).unwrap_or(true) {
+				closest = Some(mismatches);
+			}
+		}
+
+		Err(Error::NoMatchingConfig(closest.unwrap_or_default()))
+	}
+
+	/// Name of the built-in config matching all loaded values.
+	pub fn matching_config(&self) -> Result<&'static str, Error> {
+		let candidates = vec![
+			("minimal", self.mismatches::<beacon::MinimalConfig>()),
+			("mainnet", self.mismatches::<beacon::MainnetConfig>()),
+			("sapphire", self.mismatches::<beacon::SapphireConfig>()),
+		];
+
+		let mut closest: Option<Vec<Mismatch>> = None;
+		for (name, mismatches) in candidates {
+			if mismatches.is_empty() {
+				return Ok(name)
+			}
+			if closest.as_ref().map(|c| mismatches.len() < c.len()).unwrap_or(true) {
+				closest = Some(mismatches);
+			}
+		}
+
+		Err(Error::NoMatchingConfig(closest.unwrap_or_default()))
+	}
+}
@@ -0,0 +1,59 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+//! Block verification run ahead of sequential import.
+
+use beacon::{Config, BLSConfig};
+use blockchain::Block as BlockT;
+use blockchain::backend::{Store, ChainQuery};
+use shasper_network::BlockVerifier;
+use crate::{Block, Error, Executor, StateExternalities};
+
+/// Verifies block signatures against the parent state, so that the import
+/// queue can check several blocks concurrently. Verified blocks are then
+/// executed by the same executor without checking signatures again.
+#[derive(Clone)]
+pub struct SignatureVerifier<C: Config, BLS: BLSConfig, B> {
+	executor: Executor<C, BLS>,
+	backend: B,
+}
+
+impl<C: Config, BLS: BLSConfig, B> SignatureVerifier<C, BLS, B> {
+	/// Create a new verifier, sharing verification results with `executor`.
+	pub fn new(executor: Executor<C, BLS>, backend: B) -> Self {
+		Self { executor, backend }
+	}
+}
+
+impl<C: Config, BLS: BLSConfig, B> BlockVerifier for SignatureVerifier<C, BLS, B> where
+	B: ChainQuery + Store<Block=Block<C>>,
+	B::State: StateExternalities<Config=C>,
+{
+	type Block = Block<C>;
+	type Error = Error;
+
+	fn verify_block(&self, block: &Block<C>) -> Result<(), Error> {
+		let span = tracing::debug_span!("verify_block", slot = block.0.slot.as_u64());
+		let _enter = span.enter();
+		let parent_id = match block.parent_id() {
+			Some(parent_id) => parent_id,
+			None => return Ok(()),
+		};
+		let state = self.backend.state_at(&parent_id)
+			.map_err(|e| Error::Backend(Box::new(e)))?;
+
+		self.executor.verify_block_signatures(block, state.state())
+	}
+}
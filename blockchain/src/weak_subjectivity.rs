@@ -0,0 +1,119 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+//! Checks of the local finalized checkpoint against the weak subjectivity
+//! period.
+
+use beacon::{BeaconExecutive, BeaconState, Config};
+use beacon::primitives::{Epoch, Slot};
+use beacon::types::Checkpoint;
+use blockchain::backend::{Store, ChainQuery};
+use log::*;
+use std::str::FromStr;
+use crate::{Block, StateExternalities};
+
+/// What to do when the finalized checkpoint is outside of the weak
+/// subjectivity period.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WeakSubjectivityPolicy {
+	/// Log a warning.
+	Warn,
+	/// Refuse to start, and stop block production and signing once running.
+	Refuse,
+}
+
+impl Default for WeakSubjectivityPolicy {
+	fn default() -> Self {
+		WeakSubjectivityPolicy::Warn
+	}
+}
+
+impl FromStr for WeakSubjectivityPolicy {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, String> {
+		match s {
+			"warn" => Ok(WeakSubjectivityPolicy::Warn),
+			"refuse" => Ok(WeakSubjectivityPolicy::Refuse),
+			_ => Err(format!("Unknown weak subjectivity policy {}", s)),
+		}
+	}
+}
+
+/// Finalized checkpoint compared with the weak subjectivity period.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WeakSubjectivityStatus {
+	/// Local finalized checkpoint.
+	pub finalized: Checkpoint,
+	/// Weak subjectivity period of the finalized state, in epochs.
+	pub period: Epoch,
+	/// Wall clock epoch.
+	pub current_epoch: Epoch,
+}
+
+impl WeakSubjectivityStatus {
+	/// Whether the finalized checkpoint is older than the weak subjectivity
+	/// period.
+	pub fn is_stale(&self) -> bool {
+		self.current_epoch > self.finalized.epoch + self.period
+	}
+}
+
+/// Compare the finalized checkpoint of the head with the weak subjectivity
+/// period of its state at the wall clock slot `current_slot`.
+pub fn weak_subjectivity_status<B, C>(
+	backend: &B,
+	current_slot: Slot,
+) -> Result<WeakSubjectivityStatus, B::Error> where
+	B: ChainQuery + Store<Block=Block<C>>,
+	B::State: StateExternalities<Config=C>,
+	C: Config,
+{
+	let finalized = backend.state_at(&backend.head())?.state().finalized_checkpoint.clone();
+	// The genesis checkpoint has an empty root.
+	let finalized_root = if finalized.root == Default::default() {
+		backend.genesis()
+	} else {
+		finalized.root
+	};
+	let mut state: BeaconState<C> = backend.state_at(&finalized_root)?.state().clone();
+	let period = BeaconExecutive::new(&mut state).weak_subjectivity_period();
+
+	Ok(WeakSubjectivityStatus {
+		finalized,
+		period,
+		current_epoch: beacon::utils::epoch_of_slot::<C>(current_slot),
+	})
+}
+
+/// Log a stale finalized checkpoint, returning the status.
+pub fn check_weak_subjectivity<B, C>(
+	backend: &B,
+	current_slot: Slot,
+) -> Result<WeakSubjectivityStatus, B::Error> where
+	B: ChainQuery + Store<Block=Block<C>>,
+	B::State: StateExternalities<Config=C>,
+	C: Config,
+{
+	let status = weak_subjectivity_status(backend, current_slot)?;
+	if status.is_stale() {
+		warn!(
+			"Finalized checkpoint at epoch {} is outside of the weak subjectivity period of {} epochs \
+			 at epoch {}, sync from a trusted checkpoint",
+			status.finalized.epoch, status.period, status.current_epoch,
+		);
+	}
+	Ok(status)
+}
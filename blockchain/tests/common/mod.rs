@@ -0,0 +1,189 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Block production and attestation for chains run in-process by the
+//! integration tests, with all signatures left empty.
+
+#![allow(dead_code)]
+
+use beacon::{BeaconExecutive, BeaconState, BLSNoVerification, Inherent, MinimalConfig, Config};
+use beacon::primitives::{H256, Slot};
+use beacon::types::{Attestation, AttestationData, Checkpoint};
+use blockchain::AsExternalities;
+use blockchain::backend::{ChainQuery, ImportLock};
+use blockchain::import::MutexImporter;
+use blockchain_rocksdb::RocksBackend;
+use lmd_ghost::proto_array::ProtoArrayGhostImporter;
+use shasper_blockchain::{Block, Executor, OperationPool, RocksState, StateExternalities};
+use shasper_blockchain::backend::ShasperBackend;
+use shasper_blockchain::preset::quickstart;
+
+pub type C = MinimalConfig;
+pub type BLS = BLSNoVerification;
+pub type Backend = ShasperBackend<RocksBackend<Block<C>, (), RocksState<C>>>;
+pub type Importer = MutexImporter<ProtoArrayGhostImporter<Executor<C, BLS>, Backend>>;
+
+/// Genesis block and state with the given number of validators.
+pub fn genesis(validators: u64) -> (Block<C>, BeaconState<C>) {
+	let genesis_state = quickstart::<C, BLS>(validators, 0).expect("Generating genesis failed");
+	let genesis_block = Block(beacon::types::BeaconBlock {
+		state_root: bm_le::tree_root::<<C as Config>::Digest, _>(&genesis_state),
+		..Default::default()
+	});
+	(genesis_block, genesis_state)
+}
+
+/// Fork choice importer over the blocks known to the backend.
+pub fn importer(backend: &Backend, import_lock: &ImportLock) -> Importer {
+	MutexImporter::new(ProtoArrayGhostImporter::new(
+		Executor::new(), backend.clone(), import_lock.clone(),
+	).expect("Loading fork choice failed"))
+}
+
+/// Produce a block at `slot` on top of the head, with operations from the
+/// pool, if `owns` holds for its proposer.
+pub fn propose<F: Fn(u64) -> bool>(
+	backend: &Backend,
+	pool: &OperationPool<C, BLS>,
+	slot: Slot,
+	owns: F,
+) -> Result<Option<Block<C>>, String> {
+	propose_on(backend, &backend.head(), pool, slot, owns)
+}
+
+/// Produce a block at `slot` on top of `parent`, with operations from the
+/// pool, if `owns` holds for its proposer.
+pub fn propose_on<F: Fn(u64) -> bool>(
+	backend: &Backend,
+	parent: &H256,
+	pool: &OperationPool<C, BLS>,
+	slot: Slot,
+	owns: F,
+) -> Result<Option<Block<C>>, String> {
+	let executor = Executor::<C, BLS>::new();
+	let parent_block = backend.block_at(parent).map_err(|e| format!("{:?}", e))?;
+	if parent_block.0.slot >= slot {
+		return Ok(None)
+	}
+
+	let mut state = backend.state_at(parent).map_err(|e| format!("{:?}", e))?;
+	executor.initialize_block(state.as_externalities(), slot).map_err(|e| format!("{:?}", e))?;
+	let proposer = BeaconExecutive::new(state.state_mut()).beacon_proposer_index()
+		.map_err(|e| format!("{:?}", e))?;
+	if !owns(proposer) {
+		return Ok(None)
+	}
+
+	let eth1_data = state.state().eth1_data.clone();
+	let mut block = executor.apply_inherent(&parent_block, state.as_externalities(), Inherent {
+		randao_reveal: Default::default(),
+		eth1_data,
+		graffiti: Default::default(),
+	}).map_err(|e| format!("{:?}", e))?;
+	for transaction in pool.transactions(state.state()) {
+		// Invalid and excess operations are skipped.
+		let _ = executor.apply_extrinsic(&mut block, state.as_externalities(), transaction);
+	}
+	executor.finalize_block(&mut block, state.as_externalities())
+		.map_err(|e| format!("{:?}", e))?;
+
+	Ok(Some(Block(block.fake_seal())))
+}
+
+/// Attestations for the head of validators for which `owns` holds, that
+/// are assigned to `slot`.
+pub fn attest<F: Fn(u64) -> bool>(
+	backend: &Backend,
+	slot: Slot,
+	owns: F,
+) -> Result<Vec<Attestation<C>>, String> {
+	let head = backend.head();
+	let mut state = backend.state_at(&head).map_err(|e| format!("{:?}", e))?;
+	if state.state().slot < slot {
+		beacon::initialize_block::<C>(state.state_mut(), slot).map_err(|e| format!("{:?}", e))?;
+	}
+	let executive = BeaconExecutive::new(state.state_mut());
+	let epoch = executive.current_epoch();
+	let target_slot = beacon::utils::start_slot_of_epoch::<C>(epoch);
+	let target_root = if target_slot == slot {
+		head
+	} else {
+		executive.block_root(epoch).map_err(|e| format!("{:?}", e))?
+	};
+
+	let mut attestations = Vec::new();
+	for validator_index in 0..(executive.validators.len() as u64) {
+		if !owns(validator_index) {
+			continue
+		}
+		let assignment = match executive.committee_assignment(epoch, validator_index)
+			.map_err(|e| format!("{:?}", e))?
+		{
+			Some(assignment) if assignment.slot == slot => assignment,
+			_ => continue,
+		};
+
+		let position = assignment.validators.iter()
+			.position(|v| *v == validator_index)
+			.expect("Validator is in its committee; qed");
+		let mut aggregation_bits = vec![false; assignment.validators.len()];
+		aggregation_bits[position] = true;
+
+		attestations.push(Attestation {
+			aggregation_bits: aggregation_bits.into(),
+			data: AttestationData {
+				beacon_block_root: head,
+				source: executive.current_justified_checkpoint.clone(),
+				target: Checkpoint { epoch, root: target_root },
+				slot,
+				index: assignment.index,
+			},
+			custody_bits: vec![false; assignment.validators.len()].into(),
+			signature: Default::default(),
+		});
+	}
+	Ok(attestations)
+}
+
+/// Finalized epoch of the head state.
+pub fn finalized_epoch(backend: &Backend) -> Result<u64, String> {
+	let state = backend.state_at(&backend.head()).map_err(|e| format!("{:?}", e))?;
+	Ok(state.state().finalized_checkpoint.epoch.as_u64())
+}
+
+/// Propose and attest every slot in `slots`, passing proposed blocks to
+/// `import`.
+pub fn run<F: FnMut(Block<C>)>(
+	backend: &Backend,
+	pool: &mut OperationPool<C, BLS>,
+	slots: std::ops::Range<u64>,
+	mut import: F,
+) {
+	for slot in slots {
+		let slot = Slot::new(slot);
+		if let Some(block) = propose(backend, pool, slot, |_| true)
+			.expect("Proposing failed")
+		{
+			pool.note_included(&block.0.body);
+			import(block);
+		}
+		for attestation in attest(backend, slot, |_| true).expect("Attesting failed") {
+			pool.insert_attestation(attestation);
+		}
+		let head_state = backend.state_at(&backend.head()).expect("Head state exists");
+		pool.prune(head_state.state(), slot + 1);
+	}
+}
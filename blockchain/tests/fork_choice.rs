@@ -0,0 +1,109 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Fork choice of a single node owning all validators, run past
+//! finalization with the minimal storage mode.
+
+mod common;
+
+use beacon::Config;
+use blockchain::backend::{ChainQuery, ImportLock};
+use blockchain::import::{BlockImporter, SharedBlockImporter};
+use blockchain_rocksdb::{RocksBackend, StorageMode, SyncPolicy};
+use lmd_ghost::proto_array::ProtoArrayGhostImporter;
+use shasper_blockchain::{Executor, OperationPool, RocksState};
+use shasper_blockchain::backend::ShasperBackend;
+use shasper_blockchain::gc;
+use std::fs;
+use std::path::Path;
+
+use common::{Backend, C};
+
+const VALIDATORS: u64 = 64;
+
+fn open(path: &Path) -> Backend {
+	let (genesis_block, genesis_state) = common::genesis(VALIDATORS);
+	ShasperBackend::new(
+		RocksBackend::<_, (), RocksState<C>>::open_or_create_with_mode(
+			path, StorageMode::Minimal, SyncPolicy::default(),
+			|_| Ok((genesis_block, genesis_state.into())),
+		).expect("Opening database failed")
+	)
+}
+
+#[test]
+fn restarts_after_finalized_states_are_pruned() {
+	let path = std::env::temp_dir()
+		.join(format!("shasper-fork-choice-restart-{}", std::process::id()));
+	let backend = open(&path);
+	let import_lock = ImportLock::new();
+	let mut pool = OperationPool::new();
+
+	let importer = common::importer(&backend, &import_lock);
+	common::run(&backend, &mut pool, 1..(6 * C::slots_per_epoch()), |block| {
+		importer.import_block(block).expect("Importing failed");
+	});
+	let finalized = common::finalized_epoch(&backend).expect("Head state exists");
+	assert!(finalized >= 2, "Finalized epoch {} after 6 epochs", finalized);
+	assert!(gc::prune_finalized_states(&backend, &import_lock).expect("Pruning failed") > 0);
+	drop(importer);
+
+	let head = backend.head();
+	let importer = common::importer(&backend, &import_lock);
+	let slots = (6 * C::slots_per_epoch())..(8 * C::slots_per_epoch());
+	common::run(&backend, &mut pool, slots, |block| {
+		importer.import_block(block).expect("Importing failed");
+	});
+	assert_ne!(backend.head(), head);
+	assert!(common::finalized_epoch(&backend).expect("Head state exists") > finalized);
+
+	drop(importer);
+	drop(backend);
+	let _ = fs::remove_dir_all(&path);
+}
+
+#[test]
+fn blocks_before_finalized_block_are_pruned() {
+	let path = std::env::temp_dir()
+		.join(format!("shasper-fork-choice-prune-{}", std::process::id()));
+	let backend = open(&path);
+	let mut ghost = ProtoArrayGhostImporter::new(
+		Executor::new(), backend.clone(), ImportLock::new(),
+	).expect("Loading fork choice failed");
+	let mut pool = OperationPool::new();
+
+	let mut imported = 0;
+	let mut max_count = 0;
+	common::run(&backend, &mut pool, 1..(10 * C::slots_per_epoch()), |block| {
+		ghost.import_block(block).expect("Importing failed");
+		imported += 1;
+		max_count = std::cmp::max(max_count, ghost.block_count());
+	});
+
+	let finalized = common::finalized_epoch(&backend).expect("Head state exists");
+	assert!(finalized >= 6, "Finalized epoch {} after 10 epochs", finalized);
+	// Finality lags the head by a few epochs, so the blocks since the
+	// finalized block stay well below the imported ones.
+	assert!(
+		max_count <= 4 * C::slots_per_epoch() as usize + 1,
+		"Fork choice tracked {} of {} imported blocks", max_count, imported,
+	);
+	assert!(ghost.block_count() < imported);
+
+	drop(ghost);
+	drop(backend);
+	let _ = fs::remove_dir_all(&path);
+}
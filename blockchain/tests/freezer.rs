@@ -0,0 +1,75 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Removal of forks off blocks moved into the freezer.
+
+mod common;
+
+use beacon::Config;
+use beacon::primitives::Slot;
+use blockchain::Block as BlockT;
+use blockchain::backend::{ChainQuery, ImportLock};
+use blockchain::import::SharedBlockImporter;
+use blockchain_rocksdb::RocksBackend;
+use shasper_blockchain::{OperationPool, RocksState};
+use shasper_blockchain::backend::{BranchPruning, ShasperBackend};
+use shasper_blockchain::gc;
+use std::fs;
+
+use common::C;
+
+const VALIDATORS: u64 = 64;
+
+#[test]
+fn removed_fork_of_frozen_block_is_not_listed() {
+	let path = std::env::temp_dir()
+		.join(format!("shasper-freezer-fork-{}", std::process::id()));
+	let (genesis_block, genesis_state) = common::genesis(VALIDATORS);
+	let backend = ShasperBackend::new(
+		RocksBackend::<_, (), RocksState<C>>::open_or_create(&path.join("db"), |_| {
+			Ok((genesis_block, genesis_state.into()))
+		}).and_then(|backend| backend.with_freezer(path.join("freezer")))
+			.expect("Opening database failed")
+	);
+	let import_lock = ImportLock::new();
+	let importer = common::importer(&backend, &import_lock);
+	let mut pool = OperationPool::new();
+
+	common::run(&backend, &mut pool, 1..5, |block| {
+		importer.import_block(block).expect("Importing failed");
+	});
+	let parent = backend.lookup_canon_depth(1).expect("Lookup failed").expect("Block 1 exists");
+	let fork = common::propose_on(&backend, &parent, &OperationPool::new(), Slot::new(3), |_| true)
+		.expect("Proposing failed")
+		.expect("All validators are owned");
+	let fork_id = fork.id();
+	importer.import_block(fork).expect("Importing fork failed");
+	assert!(!backend.is_canon(&fork_id).expect("Fork exists"));
+
+	common::run(&backend, &mut pool, 5..(6 * C::slots_per_epoch()), |block| {
+		importer.import_block(block).expect("Importing failed");
+	});
+	assert!(gc::freeze_finalized_blocks(&backend, &import_lock).expect("Freezing failed") > 1);
+
+	assert_eq!(backend.remove_branch(&fork_id).expect("Removing fork failed"), 1);
+	assert!(!backend.contains(&fork_id).expect("Lookup failed"));
+	let children = backend.children_at(&parent).expect("Frozen block exists");
+	assert_eq!(children, vec![backend.lookup_canon_depth(2).expect("Lookup failed").expect("Block 2 exists")]);
+
+	drop(importer);
+	drop(backend);
+	let _ = fs::remove_dir_all(&path);
+}
@@ -0,0 +1,324 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Long-running soak test of a small in-process network with fault
+//! injection. Nodes are periodically killed and restarted, their databases
+//! corrupted, and the network partitioned, and finality must resume after
+//! each fault heals.
+//!
+//! Run with `cargo test --release --test soak -- --ignored`. The number of
+//! simulated epochs is set by `SOAK_EPOCHS`, and the random seed by
+//! `SOAK_SEED`.
+
+mod common;
+
+use beacon::Config;
+use beacon::primitives::Slot;
+use beacon::types::Attestation;
+use blockchain::Block as BlockT;
+use blockchain::backend::{ChainQuery, ImportLock};
+use blockchain::import::SharedBlockImporter;
+use blockchain_rocksdb::RocksBackend;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use shasper_blockchain::{Block, OperationPool, RocksState, StateExternalities};
+use shasper_blockchain::backend::ShasperBackend;
+use std::fs::{self, OpenOptions};
+use std::io::{Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use common::{Backend, Importer, BLS, C};
+
+const NODES: usize = 4;
+const VALIDATORS: u64 = 64;
+const DEFAULT_EPOCHS: u64 = 2000;
+/// Maximum number of epochs a fault lasts.
+const MAX_FAULT_EPOCHS: u64 = 4;
+/// Number of fault-free epochs within which finality must advance.
+const QUIET_EPOCHS: u64 = 8;
+
+enum Fault {
+	/// Node is stopped, and its database possibly corrupted.
+	Killed(usize),
+	/// Nodes are split into two groups that cannot reach each other.
+	Partition(Vec<usize>),
+}
+
+struct Node {
+	index: usize,
+	path: PathBuf,
+	genesis: (Block<C>, beacon::BeaconState<C>),
+	running: Option<(Backend, Importer)>,
+	pool: OperationPool<C, BLS>,
+}
+
+impl Node {
+	fn new(index: usize, root: &Path, genesis: (Block<C>, beacon::BeaconState<C>)) -> Self {
+		let mut node = Self {
+			index,
+			path: root.join(format!("node{}", index)),
+			genesis,
+			running: None,
+			pool: OperationPool::new(),
+		};
+		node.start();
+		node
+	}
+
+	/// Open the database, wiping it if it cannot be opened or read after
+	/// corruption. Wiped nodes resync from their peers.
+	fn start(&mut self) {
+		let backend = match self.open() {
+			Some(backend) => backend,
+			None => {
+				eprintln!("Node {} database unusable, wiping", self.index);
+				fs::remove_dir_all(&self.path).expect("Removing database failed");
+				self.open().expect("Fresh database must open")
+			},
+		};
+		let importer = common::importer(&backend, &ImportLock::new());
+		self.pool = OperationPool::new();
+		self.running = Some((backend, importer));
+	}
+
+	fn open(&self) -> Option<Backend> {
+		let (genesis_block, genesis_state) = self.genesis.clone();
+		let backend = ShasperBackend::new(
+			RocksBackend::<_, (), RocksState<C>>::open_or_create(&self.path, |_| {
+				Ok((genesis_block, genesis_state.into()))
+			}).ok()?
+		);
+		let head = backend.head();
+		backend.block_at(&head).ok()?;
+		backend.state_at(&head).ok()?;
+		Some(backend)
+	}
+
+	fn stop(&mut self) {
+		self.running = None;
+	}
+
+	/// Overwrite a random range of a random database file.
+	fn corrupt(&self, rng: &mut StdRng) {
+		let files = fs::read_dir(&self.path).expect("Database directory exists")
+			.filter_map(|entry| entry.ok())
+			.map(|entry| entry.path())
+			.filter(|path| path.is_file())
+			.collect::<Vec<_>>();
+		if files.is_empty() {
+			return
+		}
+
+		let path = &files[rng.gen_range(0, files.len())];
+		let len = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+		let mut file = OpenOptions::new().write(true).open(path).expect("Opening file failed");
+		let offset = if len > 0 { rng.gen_range(0, len) } else { 0 };
+		let garbage = (0..64).map(|_| rng.gen::<u8>()).collect::<Vec<_>>();
+		file.seek(SeekFrom::Start(offset)).expect("Seeking file failed");
+		file.write_all(&garbage).expect("Corrupting file failed");
+		eprintln!("Node {} corrupted {:?} at offset {}", self.index, path, offset);
+	}
+
+	fn backend(&self) -> Option<&Backend> {
+		self.running.as_ref().map(|(backend, _)| backend)
+	}
+
+	fn finalized_epoch(&self) -> Option<u64> {
+		common::finalized_epoch(self.backend()?).ok()
+	}
+
+	fn owns(&self, validator_index: u64) -> bool {
+		validator_index as usize % NODES == self.index
+	}
+
+	/// Produce and import a block at `slot` if one of this node's
+	/// validators is the proposer.
+	fn propose(&mut self, slot: Slot) -> Result<(), String> {
+		let (backend, importer) = match self.running.as_ref() {
+			Some(running) => running,
+			None => return Ok(()),
+		};
+		let block = match common::propose(backend, &self.pool, slot, |v| self.owns(v))? {
+			Some(block) => block,
+			None => return Ok(()),
+		};
+		self.pool.note_included(&block.0.body);
+		importer.import_block(block).map_err(|e| format!("{:?}", e))
+	}
+
+	/// Attestations of this node's validators assigned to `slot`.
+	fn attest(&self, slot: Slot) -> Result<Vec<Attestation<C>>, String> {
+		match self.backend() {
+			Some(backend) => common::attest(backend, slot, |v| self.owns(v)),
+			None => Ok(Vec::new()),
+		}
+	}
+
+	/// Import blocks of `other`'s canonical chain that this node does not
+	/// have yet.
+	fn sync_from(&self, other: &Node) -> Result<(), String> {
+		let ((backend, importer), other_backend) = match (self.running.as_ref(), other.backend()) {
+			(Some(running), Some(other_backend)) => (running, other_backend),
+			_ => return Ok(()),
+		};
+
+		let mut missing = Vec::new();
+		let mut current = other_backend.head();
+		while !backend.contains(&current).map_err(|e| format!("{:?}", e))? {
+			let block = other_backend.block_at(&current).map_err(|e| format!("{:?}", e))?;
+			current = block.parent_id().ok_or("Genesis mismatch")?;
+			missing.push(block);
+		}
+		for block in missing.into_iter().rev() {
+			importer.import_block(block).map_err(|e| format!("{:?}", e))?;
+		}
+		Ok(())
+	}
+}
+
+fn env_u64(name: &str, default: u64) -> u64 {
+	std::env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+fn reachable(fault: &Option<Fault>, a: usize, b: usize) -> bool {
+	match fault {
+		Some(Fault::Partition(group)) => group.contains(&a) == group.contains(&b),
+		_ => true,
+	}
+}
+
+#[test]
+#[ignore]
+fn soak() {
+	let epochs = env_u64("SOAK_EPOCHS", DEFAULT_EPOCHS);
+	let seed = env_u64("SOAK_SEED", 42);
+	let mut rng = StdRng::seed_from_u64(seed);
+	let root = std::env::temp_dir().join(format!("shasper-soak-{}-{}", std::process::id(), seed));
+
+	let (genesis_block, genesis_state) = common::genesis(VALIDATORS);
+	let mut nodes = (0..NODES)
+		.map(|index| Node::new(index, &root, (genesis_block.clone(), genesis_state.clone())))
+		.collect::<Vec<_>>();
+
+	let mut fault: Option<Fault> = None;
+	let mut fault_until = 0;
+	let mut quiet_since = Some((0, 0));
+
+	for slot in 1..(epochs * C::slots_per_epoch()) {
+		let epoch = slot / C::slots_per_epoch();
+
+		if slot % C::slots_per_epoch() == 0 {
+			// Heal the current fault, or inject a new one once finality
+			// advanced during the previous quiet period.
+			if fault.is_some() && epoch >= fault_until {
+				if let Some(Fault::Killed(index)) = fault {
+					eprintln!("Epoch {}: restarting node {}", epoch, index);
+					nodes[index].start();
+				} else {
+					eprintln!("Epoch {}: healing partition", epoch);
+				}
+				fault = None;
+				let finalized = nodes.iter().filter_map(|n| n.finalized_epoch()).min().unwrap_or(0);
+				quiet_since = Some((epoch, finalized));
+			}
+
+			if let Some((since, finalized)) = quiet_since {
+				if epoch >= since + QUIET_EPOCHS {
+					for node in &nodes {
+						let current = node.finalized_epoch().expect("All nodes run when quiet");
+						assert!(
+							current > finalized,
+							"Node {} finality did not resume within {} epochs after epoch {}",
+							node.index, QUIET_EPOCHS, since,
+						);
+					}
+
+					let duration = rng.gen_range(1, MAX_FAULT_EPOCHS + 1);
+					fault_until = epoch + duration;
+					quiet_since = None;
+					fault = Some(match rng.gen_range(0, 3) {
+						0 => {
+							let index = rng.gen_range(0, NODES);
+							eprintln!("Epoch {}: killing node {} for {} epochs", epoch, index, duration);
+							nodes[index].stop();
+							Fault::Killed(index)
+						},
+						1 => {
+							let index = rng.gen_range(0, NODES);
+							eprintln!("Epoch {}: killing and corrupting node {} for {} epochs",
+									  epoch, index, duration);
+							nodes[index].stop();
+							nodes[index].corrupt(&mut rng);
+							Fault::Killed(index)
+						},
+						_ => {
+							let group = (0..NODES).filter(|_| rng.gen::<bool>()).collect::<Vec<_>>();
+							eprintln!("Epoch {}: partitioning {:?} for {} epochs", epoch, group, duration);
+							Fault::Partition(group)
+						},
+					});
+				}
+			}
+		}
+
+		for node in nodes.iter_mut() {
+			if let Err(e) = node.propose(Slot::new(slot)) {
+				panic!("Node {} failed to propose at slot {}: {}", node.index, slot, e);
+			}
+		}
+
+		for a in 0..NODES {
+			for b in 0..NODES {
+				if a != b && reachable(&fault, a, b) {
+					if let Err(e) = nodes[a].sync_from(&nodes[b]) {
+						panic!("Node {} failed to sync from node {}: {}", a, b, e);
+					}
+				}
+			}
+		}
+
+		for a in 0..NODES {
+			let attestations = match nodes[a].attest(Slot::new(slot)) {
+				Ok(attestations) => attestations,
+				Err(e) => panic!("Node {} failed to attest at slot {}: {}", a, slot, e),
+			};
+			for b in 0..NODES {
+				if reachable(&fault, a, b) && nodes[b].running.is_some() {
+					for attestation in &attestations {
+						nodes[b].pool.insert_attestation(attestation.clone());
+					}
+				}
+			}
+		}
+
+		for node in nodes.iter_mut() {
+			let head_state = match node.backend() {
+				Some(backend) => backend.state_at(&backend.head()).ok().map(|s| s.state().clone()),
+				None => None,
+			};
+			if let Some(head_state) = head_state {
+				node.pool.prune(&head_state, Slot::new(slot + 1));
+			}
+		}
+	}
+
+	for node in &nodes {
+		if let Some(finalized) = node.finalized_epoch() {
+			eprintln!("Node {} finalized epoch {}", node.index, finalized);
+		}
+	}
+	let _ = fs::remove_dir_all(&root);
+}
@@ -19,8 +19,11 @@
 #![warn(missing_docs)]
 #![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
+
 #[macro_use]
 mod macros;
+mod uint;
 
 mod validator_id {
 	impl_beacon_fixed_hash!(H384, 48, typenum::U48);
@@ -40,21 +43,30 @@ mod version {
 	pub type Version = H32;
 }
 
+mod execution_address {
+	impl_beacon_fixed_hash!(H160, 20, typenum::U20);
+	/// Address on the execution chain.
+	pub type ExecutionAddress = H160;
+}
+
+mod logs_bloom {
+	impl_beacon_fixed_hash!(H2048, 256, typenum::U256);
+	/// Bloom filter of execution logs.
+	pub type LogsBloom = H2048;
+}
+
 pub use self::validator_id::{ValidatorId, H384};
 pub use self::signature::{Signature, H768};
 pub use self::version::{Version, H32};
+pub use self::execution_address::{ExecutionAddress, H160};
+pub use self::logs_bloom::{LogsBloom, H2048};
+pub use self::uint::{Slot, Epoch, Gwei};
 
 /// Integer type for beacon chain.
 pub type Uint = u64;
 pub use primitive_types::H256;
 
-/// Epoch.
-pub type Epoch = Uint;
-/// Slot.
-pub type Slot = Uint;
 /// Validator index.
 pub type ValidatorIndex = Uint;
 /// Shard.
 pub type Shard = Uint;
-/// Gwei.
-pub type Gwei = Uint;
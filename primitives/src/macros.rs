@@ -24,6 +24,7 @@ macro_rules! impl_beacon_fixed_hash {
 		use bm_le::{ElementalFixedVecRef, ElementalFixedVec,
 					IntoCompactVectorTree, FromCompactVectorTree, Compact, CompactRef};
 		use generic_array::GenericArray;
+		use alloc::vec::Vec;
 
 		const SIZE: usize = $size;
 
@@ -0,0 +1,333 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+
+//! Integer newtypes for slots, epochs and balances. Each is encoded as the
+//! `u64` it wraps, but they are distinct types, so that a slot cannot be
+//! passed where an epoch is expected.
+//!
+//! Arithmetic is defined between values of the same type, and with plain
+//! `u64` counts or factors. Dividing two values of the same type gives their
+//! ratio as `u64`.
+//!
+//! Operators behave as for integers: overflow panics in debug builds, and
+//! division and remainder by zero panic. Where values may come from the
+//! network, use the `checked_*` methods to detect overflow, or the
+//! `saturating_*` methods where saturation is intended.
+
+#[cfg(feature = "serde")]
+use serde::{Serialize, Deserialize};
+use core::fmt;
+use core::iter::Sum;
+use core::ops::{Add, AddAssign, Sub, SubAssign, Mul, MulAssign, Div, DivAssign, Rem, RemAssign};
+use core::str::FromStr;
+
+macro_rules! impl_beacon_uint {
+	( $t:ident ) => {
+		impl $t {
+			/// Create a new value.
+			pub const fn new(value: u64) -> Self {
+				$t(value)
+			}
+
+			/// Get the wrapped integer.
+			pub const fn as_u64(&self) -> u64 {
+				self.0
+			}
+
+			/// Get the wrapped integer as `usize`, for indexing.
+			pub fn as_usize(&self) -> usize {
+				self.0 as usize
+			}
+
+			/// Little-endian bytes of the wrapped integer.
+			pub fn to_le_bytes(&self) -> [u8; 8] {
+				self.0.to_le_bytes()
+			}
+
+			/// Saturating addition.
+			pub fn saturating_add<T: Into<Self>>(self, other: T) -> Self {
+				$t(self.0.saturating_add(other.into().0))
+			}
+
+			/// Saturating subtraction.
+			pub fn saturating_sub<T: Into<Self>>(self, other: T) -> Self {
+				$t(self.0.saturating_sub(other.into().0))
+			}
+
+			/// Checked addition.
+			pub fn checked_add<T: Into<Self>>(self, other: T) -> Option<Self> {
+				self.0.checked_add(other.into().0).map($t)
+			}
+
+			/// Checked subtraction.
+			pub fn checked_sub<T: Into<Self>>(self, other: T) -> Option<Self> {
+				self.0.checked_sub(other.into().0).map($t)
+			}
+
+			/// Checked multiplication by a factor.
+			pub fn checked_mul(self, other: u64) -> Option<Self> {
+				self.0.checked_mul(other).map($t)
+			}
+
+			/// Checked division by a divisor.
+			pub fn checked_div(self, other: u64) -> Option<Self> {
+				self.0.checked_div(other).map($t)
+			}
+		}
+
+		impl From<u64> for $t {
+			fn from(value: u64) -> Self {
+				$t(value)
+			}
+		}
+
+		impl From<$t> for u64 {
+			fn from(value: $t) -> u64 {
+				value.0
+			}
+		}
+
+		impl fmt::Display for $t {
+			fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+				self.0.fmt(f)
+			}
+		}
+
+		impl FromStr for $t {
+			type Err = core::num::ParseIntError;
+
+			fn from_str(s: &str) -> Result<Self, Self::Err> {
+				u64::from_str(s).map($t)
+			}
+		}
+
+		impl PartialEq<u64> for $t {
+			fn eq(&self, other: &u64) -> bool {
+				self.0 == *other
+			}
+		}
+
+		impl PartialOrd<u64> for $t {
+			fn partial_cmp(&self, other: &u64) -> Option<core::cmp::Ordering> {
+				self.0.partial_cmp(other)
+			}
+		}
+
+		impl Add for $t {
+			type Output = Self;
+
+			fn add(self, other: Self) -> Self {
+				$t(self.0 + other.0)
+			}
+		}
+
+		impl Add<u64> for $t {
+			type Output = Self;
+
+			fn add(self, other: u64) -> Self {
+				$t(self.0 + other)
+			}
+		}
+
+		impl AddAssign for $t {
+			fn add_assign(&mut self, other: Self) {
+				self.0 += other.0;
+			}
+		}
+
+		impl AddAssign<u64> for $t {
+			fn add_assign(&mut self, other: u64) {
+				self.0 += other;
+			}
+		}
+
+		impl Sub for $t {
+			type Output = Self;
+
+			fn sub(self, other: Self) -> Self {
+				$t(self.0 - other.0)
+			}
+		}
+
+		impl Sub<u64> for $t {
+			type Output = Self;
+
+			fn sub(self, other: u64) -> Self {
+				$t(self.0 - other)
+			}
+		}
+
+		impl SubAssign for $t {
+			fn sub_assign(&mut self, other: Self) {
+				self.0 -= other.0;
+			}
+		}
+
+		impl SubAssign<u64> for $t {
+			fn sub_assign(&mut self, other: u64) {
+				self.0 -= other;
+			}
+		}
+
+		impl Mul<u64> for $t {
+			type Output = Self;
+
+			fn mul(self, other: u64) -> Self {
+				$t(self.0 * other)
+			}
+		}
+
+		impl Mul<$t> for u64 {
+			type Output = $t;
+
+			fn mul(self, other: $t) -> $t {
+				$t(self * other.0)
+			}
+		}
+
+		impl MulAssign<u64> for $t {
+			fn mul_assign(&mut self, other: u64) {
+				self.0 *= other;
+			}
+		}
+
+		impl Div<u64> for $t {
+			type Output = Self;
+
+			fn div(self, other: u64) -> Self {
+				$t(self.0 / other)
+			}
+		}
+
+		impl Div for $t {
+			type Output = u64;
+
+			fn div(self, other: Self) -> u64 {
+				self.0 / other.0
+			}
+		}
+
+		impl DivAssign<u64> for $t {
+			fn div_assign(&mut self, other: u64) {
+				self.0 /= other;
+			}
+		}
+
+		impl Rem<u64> for $t {
+			type Output = Self;
+
+			fn rem(self, other: u64) -> Self {
+				$t(self.0 % other)
+			}
+		}
+
+		impl Rem for $t {
+			type Output = Self;
+
+			fn rem(self, other: Self) -> Self {
+				$t(self.0 % other.0)
+			}
+		}
+
+		impl RemAssign<u64> for $t {
+			fn rem_assign(&mut self, other: u64) {
+				self.0 %= other;
+			}
+		}
+
+		impl Sum for $t {
+			fn sum<I: Iterator<Item=Self>>(iter: I) -> Self {
+				$t(iter.map(|value| value.0).sum())
+			}
+		}
+
+		impl<'a> Sum<&'a $t> for $t {
+			fn sum<I: Iterator<Item=&'a Self>>(iter: I) -> Self {
+				$t(iter.map(|value| value.0).sum())
+			}
+		}
+
+		impl ssz::Codec for $t {
+			type Size = <u64 as ssz::Codec>::Size;
+		}
+
+		impl ssz::Encode for $t {
+			fn using_encoded<R, F: FnOnce(&[u8]) -> R>(&self, f: F) -> R {
+				ssz::Encode::using_encoded(&self.0, f)
+			}
+		}
+
+		impl ssz::Decode for $t {
+			fn decode(value: &[u8]) -> Result<Self, ssz::Error> {
+				<u64 as ssz::Decode>::decode(value).map($t)
+			}
+		}
+
+		impl bm_le::IntoTree for $t {
+			fn into_tree<DB: bm_le::WriteBackend>(&self, db: &mut DB) -> Result<<DB::Construct as bm_le::Construct>::Value, bm_le::Error<DB::Error>> where
+				DB::Construct: bm_le::CompatibleConstruct
+			{
+				bm_le::IntoTree::into_tree(&self.0, db)
+			}
+		}
+
+		impl bm_le::FromTree for $t {
+			fn from_tree<DB: bm_le::ReadBackend>(root: &<DB::Construct as bm_le::Construct>::Value, db: &mut DB) -> Result<Self, bm_le::Error<DB::Error>> where
+				DB::Construct: bm_le::CompatibleConstruct
+			{
+				<u64 as bm_le::FromTree>::from_tree(root, db).map($t)
+			}
+		}
+	}
+}
+
+/// Slot.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+#[cfg_attr(feature = "parity-codec", derive(parity_codec::Encode, parity_codec::Decode))]
+pub struct Slot(u64);
+
+impl_beacon_uint!(Slot);
+
+impl Slot {
+	/// Epoch of the slot.
+	pub fn epoch(&self, slots_per_epoch: u64) -> Epoch {
+		Epoch(self.0 / slots_per_epoch)
+	}
+}
+
+/// Epoch.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+#[cfg_attr(feature = "parity-codec", derive(parity_codec::Encode, parity_codec::Decode))]
+pub struct Epoch(u64);
+
+impl_beacon_uint!(Epoch);
+
+impl Epoch {
+	/// First slot of the epoch.
+	pub fn start_slot(&self, slots_per_epoch: u64) -> Slot {
+		Slot(self.0 * slots_per_epoch)
+	}
+}
+
+/// Gwei.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize), serde(transparent))]
+#[cfg_attr(feature = "parity-codec", derive(parity_codec::Encode, parity_codec::Decode))]
+pub struct Gwei(u64);
+
+impl_beacon_uint!(Gwei);
@@ -19,3 +19,7 @@ cargo --version
 ./scripts/build.sh
 
 cargo test --all --locked
+
+# The state transition must keep building without std.
+rustup target add wasm32-unknown-unknown
+cargo build --locked --target wasm32-unknown-unknown --no-default-features -p ssz -p beacon
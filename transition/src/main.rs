@@ -0,0 +1,133 @@
+// Copyright 2019 Parity Technologies (UK) Ltd.
+// This file is part of Parity Shasper.
+
+// Parity Shasper is free software: you can redistribute it and/or modify it
+// under the terms of the GNU General Public License as published by the Free
+// Software Foundation, either version 3 of the License, or (at your option) any
+// later version.
+
+// Parity Shasper is distributed in the hope that it will be useful, but WITHOUT
+// ANY WARRANTY; without even the implied warranty of MERCHANTABILITY or FITNESS
+// FOR A PARTICULAR PURPOSE.  See the GNU General Public License for more
+// details.
+
+// You should have received a copy of the GNU General Public License along with
+// Parity Shasper.  If not, see <http://www.gnu.org/licenses/>.
+//! Apply blocks to a pre-state and write the post-state, all SSZ-encoded,
+//! for debugging consensus failures.
+
+use std::fs;
+use std::path::Path;
+use std::process;
+use clap::{App, Arg};
+use ssz::{Encode, Decode};
+use bm_le::tree_root;
+use beacon::{
+	BeaconState, Config, BLSConfig, BLSNoVerification, MinimalConfig, MainnetConfig,
+	SapphireConfig,
+};
+use beacon::types::BeaconBlock;
+use crypto::bls::BLSVerification;
+
+#[derive(Debug)]
+enum Error {
+	Io(String, std::io::Error),
+	Decode(String, ssz::Error),
+	Block(String, beacon::Error),
+}
+
+impl std::fmt::Display for Error {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Error::Io(path, e) => write!(f, "Accessing {} failed: {}", path, e),
+			Error::Decode(path, e) => write!(f, "Decoding {} failed: {:?}", path, e),
+			Error::Block(path, e) => write!(f, "Block {} is invalid: {:?}", path, e),
+		}
+	}
+}
+
+fn read<T: Decode>(path: &str) -> Result<T, Error> {
+	let raw = fs::read(path).map_err(|e| Error::Io(path.to_string(), e))?;
+	T::decode(&raw).map_err(|e| Error::Decode(path.to_string(), e))
+}
+
+fn transition<C: Config, BLS: BLSConfig>(
+	pre: &str,
+	blocks: &[&str],
+	post: Option<&str>,
+) -> Result<(), Error> {
+	let mut state = read::<BeaconState<C>>(pre)?;
+
+	for path in blocks {
+		let block = read::<BeaconBlock<C>>(path)?;
+		beacon::execute_block::<C, BLS>(&block, &mut state)
+			.map_err(|e| Error::Block(path.to_string(), e))?;
+		println!(
+			"Applied block {} at slot {}, state root {:?}",
+			path, block.slot, tree_root::<C::Digest, _>(&state),
+		);
+	}
+
+	if let Some(post) = post {
+		fs::write(Path::new(post), state.encode())
+			.map_err(|e| Error::Io(post.to_string(), e))?;
+		println!("Wrote post-state to {}", post);
+	}
+
+	Ok(())
+}
+
+fn main() {
+	let matches = App::new("shasper-transition")
+		.version("0.1.0")
+		.author("Parity Technologies <admin@parity.io>")
+		.about("Apply SSZ-encoded blocks to a pre-state")
+		.arg(Arg::with_name("pre")
+			 .long("pre")
+			 .value_name("FILE")
+			 .help("Pre-state SSZ file")
+			 .required(true)
+			 .takes_value(true))
+		.arg(Arg::with_name("post")
+			 .long("post")
+			 .value_name("FILE")
+			 .help("Post-state SSZ file to write")
+			 .takes_value(true))
+		.arg(Arg::with_name("BLOCKS")
+			 .help("Block SSZ files, applied in order")
+			 .required(true)
+			 .multiple(true))
+		.arg(Arg::with_name("config")
+			 .long("config")
+			 .value_name("CONFIG")
+			 .help("Configuration, one of minimal, mainnet or sapphire")
+			 .default_value("minimal")
+			 .takes_value(true))
+		.arg(Arg::with_name("no-verify-signatures")
+			 .long("no-verify-signatures")
+			 .help("Skip BLS signature verification"))
+		.get_matches();
+
+	let pre = matches.value_of("pre").expect("Pre-state is required");
+	let post = matches.value_of("post");
+	let blocks = matches.values_of("BLOCKS").expect("Blocks are required").collect::<Vec<_>>();
+	let verify = !matches.is_present("no-verify-signatures");
+
+	let result = match (matches.value_of("config").unwrap_or("minimal"), verify) {
+		("minimal", true) => transition::<MinimalConfig, BLSVerification>(pre, &blocks, post),
+		("minimal", false) => transition::<MinimalConfig, BLSNoVerification>(pre, &blocks, post),
+		("mainnet", true) => transition::<MainnetConfig, BLSVerification>(pre, &blocks, post),
+		("mainnet", false) => transition::<MainnetConfig, BLSNoVerification>(pre, &blocks, post),
+		("sapphire", true) => transition::<SapphireConfig, BLSVerification>(pre, &blocks, post),
+		("sapphire", false) => transition::<SapphireConfig, BLSNoVerification>(pre, &blocks, post),
+		(config, _) => {
+			eprintln!("Unknown config {}", config);
+			process::exit(2);
+		},
+	};
+
+	if let Err(e) = result {
+		eprintln!("{}", e);
+		process::exit(1);
+	}
+}
@@ -1,6 +1,7 @@
 use crate::{Encode, Decode, Error, Codec, VariableSize, MaxVec, Compact, CompactRef};
 use crate::utils::{encode_list, decode_list};
 use typenum::Unsigned;
+use alloc::vec::Vec;
 
 impl<T, ML> Codec for Compact<MaxVec<T, ML>> {
 	type Size = VariableSize;
@@ -0,0 +1,220 @@
+//! Runner for the `ssz_generic` spec test vectors. Valid cases are decoded,
+//! encoded back and their hash tree root compared, and invalid cases must
+//! fail to decode.
+//!
+//! Vectors are read from the directory in `SSZ_GENERIC_TESTS`, defaulting
+//! to `general/phase0/ssz_generic` of the yamltests fixtures. The test is
+//! skipped if the directory does not exist.
+
+use ssz::{Codec, Encode, Decode, Compact, MaxVec};
+use bm_le::{IntoTree, tree_root};
+use generic_array::GenericArray;
+use vecarray::VecArray;
+use primitive_types::H256;
+use sha2::Sha256;
+use core::fmt::Debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+use typenum::*;
+
+#[derive(Codec, Encode, Decode, IntoTree, PartialEq, Debug)]
+struct SingleFieldTestStruct {
+	a: u8,
+}
+
+#[derive(Codec, Encode, Decode, IntoTree, PartialEq, Debug)]
+struct SmallTestStruct {
+	a: u16,
+	b: u16,
+}
+
+#[derive(Codec, Encode, Decode, IntoTree, PartialEq, Debug)]
+struct FixedTestStruct {
+	a: u8,
+	b: u64,
+	c: u32,
+}
+
+#[derive(Codec, Encode, Decode, IntoTree, PartialEq, Debug)]
+struct VarTestStruct {
+	a: u16,
+	#[bm(compact)]
+	b: MaxVec<u16, U1024>,
+	c: u8,
+}
+
+#[derive(Codec, Encode, Decode, IntoTree, PartialEq, Debug)]
+struct ComplexTestStruct {
+	a: u16,
+	#[bm(compact)]
+	b: MaxVec<u16, U128>,
+	c: u8,
+	#[bm(compact)]
+	d: MaxVec<u8, U256>,
+	e: VarTestStruct,
+	f: VecArray<FixedTestStruct, U4>,
+	g: VecArray<VarTestStruct, U2>,
+}
+
+#[derive(Codec, Encode, Decode, IntoTree, PartialEq, Debug)]
+struct BitsStruct {
+	#[bm(compact)]
+	a: MaxVec<bool, U5>,
+	#[bm(compact)]
+	b: GenericArray<bool, U2>,
+	#[bm(compact)]
+	c: GenericArray<bool, U1>,
+	#[bm(compact)]
+	d: MaxVec<bool, U6>,
+	#[bm(compact)]
+	e: GenericArray<bool, U8>,
+}
+
+/// Match a length used by the generators to a type-level number `$n`,
+/// evaluating `$body`, or `false` if the length is not supported.
+macro_rules! with_length {
+	( $len:expr, $n:ident => $body:expr ) => {
+		match $len {
+			1 => { type $n = U1; $body },
+			2 => { type $n = U2; $body },
+			3 => { type $n = U3; $body },
+			4 => { type $n = U4; $body },
+			5 => { type $n = U5; $body },
+			8 => { type $n = U8; $body },
+			16 => { type $n = U16; $body },
+			31 => { type $n = U31; $body },
+			512 => { type $n = U512; $body },
+			513 => { type $n = U513; $body },
+			_ => false,
+		}
+	}
+}
+
+/// Match an element type name to `$t`, evaluating `$body`, or `false` if
+/// the type is not supported.
+macro_rules! with_uint {
+	( $name:expr, $t:ident => $body:expr ) => {
+		match $name {
+			"uint8" => { type $t = u8; $body },
+			"uint16" => { type $t = u16; $body },
+			"uint32" => { type $t = u32; $body },
+			"uint64" => { type $t = u64; $body },
+			"uint128" => { type $t = u128; $body },
+			_ => false,
+		}
+	}
+}
+
+fn read_meta_root(path: &Path) -> H256 {
+	let meta: serde_yaml::Value = serde_yaml::from_reader(
+		fs::File::open(path.join("meta.yaml")).expect("Open meta failed")
+	).expect("Parse meta failed");
+	let root = meta["root"].as_str().expect("Root is missing");
+	H256::from_slice(&hex::decode(root.trim_start_matches("0x")).expect("Root is not hex"))
+}
+
+fn test_case<T>(path: &Path, valid: bool) where
+	T: Encode + Decode + IntoTree + PartialEq + Debug,
+{
+	let serialized = fs::read(path.join("serialized.ssz")).expect("Open serialized failed");
+
+	if valid {
+		let decoded = T::decode(&serialized)
+			.unwrap_or_else(|e| panic!("Decoding {:?} failed: {:?}", path, e));
+		assert_eq!(decoded.encode(), serialized, "Encoding {:?} mismatched", path);
+		assert_eq!(tree_root::<Sha256, _>(&decoded), read_meta_root(path),
+				   "Root of {:?} mismatched", path);
+	} else {
+		assert!(T::decode(&serialized).is_err(), "Decoding {:?} should fail", path);
+	}
+}
+
+/// Run a case, returning whether its type is supported.
+fn run_case(handler: &str, name: &str, path: &Path, valid: bool) -> bool {
+	let parts = name.split('_').collect::<Vec<_>>();
+	let length = parts.get(1).and_then(|s| s.parse::<usize>().ok());
+
+	match handler {
+		"boolean" => { test_case::<bool>(path, valid); true },
+		"uints" => match parts.get(1) {
+			Some(&"8") => { test_case::<u8>(path, valid); true },
+			Some(&"16") => { test_case::<u16>(path, valid); true },
+			Some(&"32") => { test_case::<u32>(path, valid); true },
+			Some(&"64") => { test_case::<u64>(path, valid); true },
+			Some(&"128") => { test_case::<u128>(path, valid); true },
+			_ => false,
+		},
+		"basic_vector" => {
+			let length = parts.get(2).and_then(|s| s.parse::<usize>().ok()).unwrap_or(0);
+			match parts.get(1) {
+				Some(&"bool") => with_length!(length, N => {
+					test_case::<VecArray<bool, N>>(path, valid); true
+				}),
+				Some(elem) => with_uint!(*elem, T => with_length!(length, N => {
+					test_case::<Compact<VecArray<T, N>>>(path, valid); true
+				})),
+				None => false,
+			}
+		},
+		"bitvector" => with_length!(length.unwrap_or(0), N => {
+			test_case::<Compact<GenericArray<bool, N>>>(path, valid); true
+		}),
+		"bitlist" => with_length!(length.unwrap_or(0), N => {
+			test_case::<Compact<MaxVec<bool, N>>>(path, valid); true
+		}),
+		"containers" => match parts[0] {
+			"SingleFieldTestStruct" => { test_case::<SingleFieldTestStruct>(path, valid); true },
+			"SmallTestStruct" => { test_case::<SmallTestStruct>(path, valid); true },
+			"FixedTestStruct" => { test_case::<FixedTestStruct>(path, valid); true },
+			"VarTestStruct" => { test_case::<VarTestStruct>(path, valid); true },
+			"ComplexTestStruct" => { test_case::<ComplexTestStruct>(path, valid); true },
+			"BitsStruct" => { test_case::<BitsStruct>(path, valid); true },
+			_ => false,
+		},
+		_ => false,
+	}
+}
+
+fn subdirectories(path: &Path) -> Vec<PathBuf> {
+	let mut dirs = fs::read_dir(path).expect("Read directory failed")
+		.map(|entry| entry.expect("Read directory entry failed").path())
+		.filter(|path| path.is_dir())
+		.collect::<Vec<_>>();
+	dirs.sort();
+	dirs
+}
+
+fn file_name(path: &Path) -> &str {
+	path.file_name().and_then(|name| name.to_str()).expect("Invalid file name")
+}
+
+#[test]
+fn ssz_generic() {
+	let root = std::env::var("SSZ_GENERIC_TESTS").map(PathBuf::from).unwrap_or_else(|_| {
+		PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+			.join("../../yamltests/res/ethtests/tests/general/phase0/ssz_generic")
+	});
+	if !root.exists() {
+		println!("Skipped ssz_generic, {:?} does not exist", root);
+		return
+	}
+
+	let (mut passed, mut skipped) = (0, 0);
+	for handler in subdirectories(&root) {
+		for suite in subdirectories(&handler) {
+			let valid = match file_name(&suite) {
+				"valid" => true,
+				"invalid" => false,
+				_ => continue,
+			};
+			for case in subdirectories(&suite) {
+				if run_case(file_name(&handler), file_name(&case), &case, valid) {
+					passed += 1;
+				} else {
+					skipped += 1;
+				}
+			}
+		}
+	}
+	println!("ssz_generic: {} passed, {} skipped", passed, skipped);
+}
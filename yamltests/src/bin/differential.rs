@@ -0,0 +1,112 @@
+use std::fs;
+use std::path::PathBuf;
+use clap::{App, Arg};
+use ssz::{Encode, Decode};
+use serde::de::DeserializeOwned;
+use bm_le::tree_root;
+use beacon::{BeaconState, Config, MainnetConfig, MinimalConfig};
+use beacon::types::BeaconBlock;
+use crypto::bls::BLSVerification;
+use yamltests::differential::{Reference, first_divergence};
+
+fn main() {
+	let matches = App::new("differential")
+        .version("0.1.0")
+        .author("Parity Technologies <admin@parity.io>")
+        .about("Compare the state transition against a reference implementation")
+        .arg(Arg::with_name("pre")
+             .long("pre")
+             .value_name("FILE")
+             .help("Pre-state SSZ file")
+             .required(true)
+             .takes_value(true))
+        .arg(Arg::with_name("BLOCKS")
+             .help("Block SSZ files, applied in order")
+             .required(true)
+             .multiple(true))
+        .arg(Arg::with_name("reference")
+             .long("reference")
+             .value_name("COMMAND")
+             .help("Reference command, with {pre}, {post} and {blocks} placeholders")
+             .required(true)
+             .takes_value(true))
+        .arg(Arg::with_name("config")
+             .long("config")
+             .value_name("CONFIG")
+             .help("Configuration, either minimal or mainnet")
+             .default_value("minimal")
+             .takes_value(true))
+        .get_matches();
+
+	let pre = PathBuf::from(matches.value_of("pre").unwrap());
+	let blocks = matches.values_of("BLOCKS").unwrap().map(PathBuf::from).collect::<Vec<_>>();
+	let reference = Reference::new(matches.value_of("reference").unwrap().to_string());
+
+	let agreed = match matches.value_of("config").unwrap() {
+		"minimal" => compare::<MinimalConfig>(&pre, &blocks, &reference),
+		"mainnet" => compare::<MainnetConfig>(&pre, &blocks, &reference),
+		config => panic!("Unknown config {}", config),
+	};
+
+	if !agreed {
+		std::process::exit(1);
+	}
+}
+
+fn compare<C: Config + DeserializeOwned>(
+	pre: &PathBuf,
+	blocks: &[PathBuf],
+	reference: &Reference,
+) -> bool {
+	let mut state = BeaconState::<C>::decode(&fs::read(pre).expect("Open pre-state failed"))
+		.expect("Decode pre-state failed");
+	let mut ours = Ok(());
+	for path in blocks {
+		let block = BeaconBlock::<C>::decode(&fs::read(path).expect("Open block failed"))
+			.expect("Decode block failed");
+		ours = beacon::execute_block::<C, BLSVerification>(&block, &mut state);
+		if let Err(ref e) = ours {
+			println!("Block {} rejected: {:?}", path.display(), e);
+			break
+		}
+	}
+
+	let post = std::env::temp_dir().join(format!("shasper-differential-{}.ssz", std::process::id()));
+	let theirs = reference.transition(pre, blocks, &post).expect("Running reference failed");
+
+	let agreed = match (ours, theirs) {
+		(Err(_), false) => {
+			println!("Both implementations rejected the blocks");
+			true
+		},
+		(Err(_), true) => {
+			println!("Divergence: rejected by shasper, accepted by the reference");
+			false
+		},
+		(Ok(()), false) => {
+			println!("Divergence: accepted by shasper, rejected by the reference");
+			false
+		},
+		(Ok(()), true) => {
+			let theirs = BeaconState::<C>::decode(&fs::read(&post).expect("Open reference post-state failed"))
+				.expect("Decode reference post-state failed");
+			let our_root = tree_root::<C::Digest, _>(&state);
+			let their_root = tree_root::<C::Digest, _>(&theirs);
+
+			if our_root == their_root && state.encode() == theirs.encode() {
+				println!("Post-state roots agree: {:?}", our_root);
+				true
+			} else {
+				println!("Divergence: post-state root {:?} != {:?}", our_root, their_root);
+				match first_divergence(&state, &theirs) {
+					Some(divergence) => println!("First divergent field: {}", divergence),
+					None => println!("No divergent field found"),
+				}
+				false
+			}
+		},
+	};
+
+	let _ = fs::remove_file(&post);
+	agreed
+}
@@ -6,6 +6,14 @@ use crate::{test_name, Error};
 #[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum TestPhase {
 	Phase0,
+	Phase1,
+}
+
+impl TestPhase {
+	/// Whether the beacon executor implements this fork.
+	pub fn is_supported(&self) -> bool {
+		*self == TestPhase::Phase0
+	}
 }
 
 impl FromStr for TestPhase {
@@ -14,6 +22,7 @@ impl FromStr for TestPhase {
 	fn from_str(s: &str) -> Result<Self, Error> {
 		match s {
 			"phase0" => Ok(Self::Phase0),
+			"phase1" => Ok(Self::Phase1),
 			_ => Err(Error::InvalidType),
 		}
 	}
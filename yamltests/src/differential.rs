@@ -0,0 +1,157 @@
+//! Differential testing of the state transition against a reference
+//! implementation run as a subprocess.
+
+use std::fmt::Debug;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use beacon::{BeaconState, Config};
+use beacon::types::{Validator, PendingAttestation};
+
+/// Reference state transition, run as a shell command. In the command,
+/// `{pre}` is replaced by the pre-state SSZ file, `{post}` by the post-state
+/// SSZ file to write, and `{blocks}` by the block SSZ files, separated by
+/// spaces. The command must exit with a non-zero status if a block is
+/// invalid.
+pub struct Reference {
+	command: String,
+}
+
+impl Reference {
+	/// Create a new reference from a command template, such as
+	/// `zcli transition blocks --pre {pre} --post {post} {blocks}`.
+	pub fn new(command: String) -> Self {
+		Self { command }
+	}
+
+	/// Run the reference transition, returning whether the blocks were
+	/// accepted.
+	pub fn transition(&self, pre: &Path, blocks: &[PathBuf], post: &Path) -> io::Result<bool> {
+		let blocks = blocks.iter()
+			.map(|block| block.display().to_string())
+			.collect::<Vec<_>>()
+			.join(" ");
+		let command = self.command
+			.replace("{pre}", &pre.display().to_string())
+			.replace("{post}", &post.display().to_string())
+			.replace("{blocks}", &blocks);
+
+		let status = Command::new("sh").arg("-c").arg(&command).status()?;
+		Ok(status.success())
+	}
+}
+
+fn diff_value<T: PartialEq + Debug>(path: String, ours: &T, theirs: &T) -> Option<String> {
+	if ours != theirs {
+		Some(format!("{}: {:?} != {:?}", path, ours, theirs))
+	} else {
+		None
+	}
+}
+
+fn diff_list<T, F>(path: &str, ours: &[T], theirs: &[T], diff_item: F) -> Option<String> where
+	F: Fn(String, &T, &T) -> Option<String>,
+{
+	for (index, (ours, theirs)) in ours.iter().zip(theirs.iter()).enumerate() {
+		if let Some(divergence) = diff_item(format!("{}[{}]", path, index), ours, theirs) {
+			return Some(divergence)
+		}
+	}
+	if ours.len() != theirs.len() {
+		return Some(format!("{}.len: {} != {}", path, ours.len(), theirs.len()))
+	}
+	None
+}
+
+macro_rules! diff_fields {
+	( $path:expr, $ours:expr, $theirs:expr, [ $( $field:ident ),* ] ) => {
+		$(
+			if let Some(divergence) = diff_value(
+				format!("{}.{}", $path, stringify!($field)), &$ours.$field, &$theirs.$field,
+			) {
+				return Some(divergence)
+			}
+		)*
+	}
+}
+
+fn diff_validator(path: String, ours: &Validator, theirs: &Validator) -> Option<String> {
+	diff_fields!(path, ours, theirs, [
+		pubkey, withdrawal_credentials, effective_balance, slashed,
+		activation_eligibility_epoch, activation_epoch, exit_epoch, withdrawable_epoch
+	]);
+	None
+}
+
+fn diff_pending_attestation<C: Config>(
+	path: String,
+	ours: &PendingAttestation<C>,
+	theirs: &PendingAttestation<C>,
+) -> Option<String> {
+	diff_fields!(path, ours, theirs, [
+		aggregation_bits, data, inclusion_delay, proposer_index
+	]);
+	None
+}
+
+/// Path of the first field at which two states diverge, along with both
+/// values, or `None` if the states are equal.
+pub fn first_divergence<C: Config>(
+	ours: &BeaconState<C>,
+	theirs: &BeaconState<C>,
+) -> Option<String> {
+	diff_fields!("state", ours, theirs, [genesis_time, slot]);
+	diff_fields!("state.fork", ours.fork, theirs.fork, [
+		previous_version, current_version, epoch
+	]);
+	diff_fields!("state.latest_block_header", ours.latest_block_header, theirs.latest_block_header, [
+		slot, parent_root, state_root, body_root, signature
+	]);
+
+	diff_list("state.block_roots", &ours.block_roots[..], &theirs.block_roots[..], diff_value)
+		.or_else(|| diff_list("state.state_roots", &ours.state_roots[..], &theirs.state_roots[..], diff_value))
+		.or_else(|| diff_list(
+			"state.historical_roots", &ours.historical_roots[..], &theirs.historical_roots[..], diff_value,
+		))
+		.or_else(|| diff_value("state.eth1_data".to_string(), &ours.eth1_data, &theirs.eth1_data))
+		.or_else(|| diff_list(
+			"state.eth1_data_votes", &ours.eth1_data_votes[..], &theirs.eth1_data_votes[..], diff_value,
+		))
+		.or_else(|| diff_value(
+			"state.eth1_deposit_index".to_string(), &ours.eth1_deposit_index, &theirs.eth1_deposit_index,
+		))
+		.or_else(|| diff_list(
+			"state.validators", &ours.validators[..], &theirs.validators[..], diff_validator,
+		))
+		.or_else(|| diff_list("state.balances", &ours.balances[..], &theirs.balances[..], diff_value))
+		.or_else(|| diff_list(
+			"state.randao_mixes", &ours.randao_mixes[..], &theirs.randao_mixes[..], diff_value,
+		))
+		.or_else(|| diff_list("state.slashings", &ours.slashings[..], &theirs.slashings[..], diff_value))
+		.or_else(|| diff_list(
+			"state.previous_epoch_attestations",
+			&ours.previous_epoch_attestations[..], &theirs.previous_epoch_attestations[..],
+			diff_pending_attestation,
+		))
+		.or_else(|| diff_list(
+			"state.current_epoch_attestations",
+			&ours.current_epoch_attestations[..], &theirs.current_epoch_attestations[..],
+			diff_pending_attestation,
+		))
+		.or_else(|| diff_list(
+			"state.justification_bits", &ours.justification_bits[..], &theirs.justification_bits[..],
+			diff_value,
+		))
+		.or_else(|| diff_value(
+			"state.previous_justified_checkpoint".to_string(),
+			&ours.previous_justified_checkpoint, &theirs.previous_justified_checkpoint,
+		))
+		.or_else(|| diff_value(
+			"state.current_justified_checkpoint".to_string(),
+			&ours.current_justified_checkpoint, &theirs.current_justified_checkpoint,
+		))
+		.or_else(|| diff_value(
+			"state.finalized_checkpoint".to_string(),
+			&ours.finalized_checkpoint, &theirs.finalized_checkpoint,
+		))
+}
@@ -37,8 +37,7 @@ pub fn test_with_config<C: Config>(typ: EpochProcessingType, desc: TestDescripti
 			}),
 		EpochProcessingType::Slashings =>
 			test_epoch_processing::<C, _>(path, |state| {
-				state.process_slashings();
-				Ok(())
+				state.process_slashings()
 			}),
 	}
 }
@@ -3,9 +3,12 @@ pub mod ssz_static;
 pub mod operations;
 pub mod sanity;
 pub mod epoch_processing;
+pub mod shuffling;
+pub mod differential;
 
 use std::fs::File;
 use std::io::{self, BufReader, Read};
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
 use ssz::Encode;
 use serde::de::DeserializeOwned;
@@ -39,16 +42,64 @@ pub fn test_name<P: AsRef<Path>>(path: P) -> Result<String, Error> {
 	   + s0[4] + "/" + s0[5])
 }
 
+/// Whether a test is run by `test`, rather than skipped.
+pub fn is_supported(desc: &TestDescription) -> bool {
+	desc.phase.is_supported() && match desc.typ {
+		TestType::SszStatic(_) | TestType::Operations(_) | TestType::Sanity(_) |
+		TestType::EpochProcessing(_) | TestType::Shuffling(_) => true,
+		_ => false,
+	}
+}
+
 pub fn test(desc: TestDescription) {
+	if !desc.phase.is_supported() {
+		println!("Skipped {}", test_name(desc.path.unwrap()).unwrap());
+		return
+	}
+
 	match desc.typ {
 		TestType::SszStatic(typ) => ssz_static::test(typ, desc),
 		TestType::Operations(typ) => operations::test(typ, desc),
 		TestType::Sanity(typ) => sanity::test(typ, desc),
 		TestType::EpochProcessing(typ) => epoch_processing::test(typ, desc),
+		TestType::Shuffling(typ) => shuffling::test(typ, desc),
 		_ => println!("Skipped {}", test_name(desc.path.unwrap()).unwrap()),
 	}
 }
 
+/// Outcome of running a set of tests.
+#[derive(Default, Debug)]
+pub struct Summary {
+	pub passed: usize,
+	pub skipped: usize,
+	pub failed: Vec<String>,
+}
+
+/// Run all tests, continuing past failures.
+pub fn run(descs: Vec<TestDescription>) -> Summary {
+	let mut summary = Summary::default();
+
+	for desc in descs {
+		if !is_supported(&desc) {
+			summary.skipped += 1;
+			continue
+		}
+
+		let name = desc.path.as_ref()
+			.and_then(|path| test_name(path).ok())
+			.unwrap_or_else(|| desc.name.clone());
+		match panic::catch_unwind(AssertUnwindSafe(|| test(desc))) {
+			Ok(()) => summary.passed += 1,
+			Err(_) => {
+				println!("");
+				summary.failed.push(name);
+			},
+		}
+	}
+
+	summary
+}
+
 pub fn test_state_with<C: Config, F: FnOnce(&mut BeaconExecutive<C>) -> Result<(), beacon::Error>>(
 	description: &str, pre: &BeaconState<C>, post: Option<&BeaconState<C>>, f: F
 ) {
@@ -1,4 +1,6 @@
+use std::str::FromStr;
 use clap::{App, Arg};
+use yamltests::description::{TestNetwork, TestPhase};
 
 fn main() {
 	let matches = App::new("yamltests")
@@ -8,12 +10,40 @@ fn main() {
         .arg(Arg::with_name("DIR")
              .help("Target yaml files to import")
              .required(true))
+        .arg(Arg::with_name("fork")
+             .long("fork")
+             .value_name("FORK")
+             .help("Only run tests of the given fork directory, such as phase0")
+             .takes_value(true))
+        .arg(Arg::with_name("network")
+             .long("network")
+             .value_name("NETWORK")
+             .help("Only run tests of the given network directory, such as minimal")
+             .takes_value(true))
         .get_matches();
 
 	let dir = matches.value_of("DIR").unwrap();
-	let descs = yamltests::description::read_descriptions(dir).unwrap();
+	let fork = matches.value_of("fork")
+		.map(|fork| TestPhase::from_str(fork).expect("Unknown fork"));
+	let network = matches.value_of("network")
+		.map(|network| TestNetwork::from_str(network).expect("Unknown network"));
 
-	for desc in descs {
-		yamltests::test(desc);
+	let descs = yamltests::description::read_descriptions(dir).unwrap()
+		.into_iter()
+		.filter(|desc| fork.map(|fork| desc.phase == fork).unwrap_or(true))
+		.filter(|desc| network.map(|network| desc.network == network).unwrap_or(true))
+		.collect::<Vec<_>>();
+
+	let summary = yamltests::run(descs);
+	println!(
+		"{} passed, {} failed, {} skipped",
+		summary.passed, summary.failed.len(), summary.skipped,
+	);
+	for name in &summary.failed {
+		println!("Failed: {}", name);
+	}
+
+	if !summary.failed.is_empty() {
+		std::process::exit(1);
 	}
 }
@@ -0,0 +1,56 @@
+use std::path::PathBuf;
+use serde::Deserialize;
+use beacon::{Config, MainnetConfig, MinimalConfig};
+use beacon::primitives::H256;
+use beacon::utils::{shuffled_index, shuffle_list};
+use crate::{test_name, read_value_unwrap};
+use crate::description::{ShufflingType, TestNetwork, TestDescription, TestPhase};
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Mapping {
+	pub seed: H256,
+	pub count: u64,
+	pub mapping: Vec<u64>,
+}
+
+pub fn test(typ: ShufflingType, desc: TestDescription) {
+	match desc.network {
+		TestNetwork::Mainnet => test_with_config::<MainnetConfig>(typ, desc),
+		TestNetwork::Minimal => test_with_config::<MinimalConfig>(typ, desc),
+		TestNetwork::General => unimplemented!("Not supported"),
+	}
+}
+
+pub fn test_with_config<C: Config>(typ: ShufflingType, desc: TestDescription) {
+	assert_eq!(desc.phase, TestPhase::Phase0);
+	let path = desc.path.clone().unwrap();
+
+	match typ {
+		ShufflingType::Core => test_shuffling::<C>(path),
+	}
+}
+
+pub fn test_shuffling<C: Config>(path: PathBuf) {
+	print!("Running test: {} ...", test_name(path.clone()).unwrap());
+
+	let mapping = {
+		let mut path = path.clone();
+		path.push("mapping.yaml");
+
+		read_value_unwrap::<_, Mapping>(path)
+	};
+
+	assert_eq!(mapping.mapping.len() as u64, mapping.count);
+	for (index, expected) in mapping.mapping.iter().enumerate() {
+		let shuffled = shuffled_index::<C>(index as u64, mapping.count, mapping.seed)
+			.expect("Index is within count");
+		assert_eq!(shuffled, *expected);
+	}
+
+	let list = shuffle_list::<C, _>((0..mapping.count).collect(), mapping.seed)
+		.expect("Count is within range");
+	assert_eq!(list, mapping.mapping);
+
+	println!(" passed");
+}
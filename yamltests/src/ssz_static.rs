@@ -5,7 +5,7 @@ use serde::{Serialize, Deserialize, de::DeserializeOwned};
 use beacon::{Config, MinimalConfig, MainnetConfig, BeaconState};
 use beacon::primitives::*;
 use beacon::types::*;
-use bm_le::{FromTree, IntoTree, DigestConstruct, InMemoryBackend};
+use bm_le::{FromTree, IntoTree, DigestConstruct, InMemoryBackend, tree_root};
 use ssz::{Encode, Decode};
 use sha2::Sha256;
 use crate::{test_name, read_raw_unwrap, read_value_unwrap};
@@ -34,30 +34,58 @@ pub fn test_with_config<C: Config>(typ: SszStaticType, desc: TestDescription) wh
 
 	match typ {
 		SszStaticType::AggregateAndProof => println!("Skipped {}", test_name(path).unwrap()),
-		SszStaticType::Attestation => test_ssz::<C, Attestation<C>>(path),
+		SszStaticType::Attestation =>
+			test_ssz_signed::<C, Attestation<C>, _, _>(path, SigningAttestation::from),
 		SszStaticType::AttestationData => test_ssz::<C, AttestationData>(path),
 		SszStaticType::AttestationDataAndCustodyBit => test_ssz::<C, AttestationDataAndCustodyBit>(path),
 		SszStaticType::AttesterSlashing => test_ssz::<C, AttesterSlashing<C>>(path),
-		SszStaticType::BeaconBlock => test_ssz::<C, BeaconBlock<C>>(path),
+		SszStaticType::BeaconBlock =>
+			test_ssz_signed::<C, BeaconBlock<C>, _, _>(path, |v| UnsealedBeaconBlock::from(&v)),
 		SszStaticType::BeaconBlockBody => test_ssz::<C, BeaconBlockBody<C>>(path),
-		SszStaticType::BeaconBlockHeader => test_ssz::<C, BeaconBlockHeader>(path),
+		SszStaticType::BeaconBlockHeader =>
+			test_ssz_signed::<C, BeaconBlockHeader, _, _>(path, SigningBeaconBlockHeader::from),
 		SszStaticType::BeaconState => test_ssz::<C, BeaconState<C>>(path),
 		SszStaticType::Checkpoint => test_ssz::<C, Checkpoint>(path),
 		SszStaticType::Deposit => test_ssz::<C, Deposit>(path),
-		SszStaticType::DepositData => test_ssz::<C, DepositData>(path),
+		SszStaticType::DepositData =>
+			test_ssz_signed::<C, DepositData, _, _>(path, SigningDepositData::from),
 		SszStaticType::Eth1Data => test_ssz::<C, Eth1Data>(path),
 		SszStaticType::Fork => test_ssz::<C, Fork>(path),
 		SszStaticType::HistoricalBatch => test_ssz::<C, HistoricalBatch<C>>(path),
-		SszStaticType::IndexedAttestation => test_ssz::<C, IndexedAttestation<C>>(path),
+		SszStaticType::IndexedAttestation =>
+			test_ssz_signed::<C, IndexedAttestation<C>, _, _>(path, SigningIndexedAttestation::from),
 		SszStaticType::PendingAttestation => test_ssz::<C, PendingAttestation<C>>(path),
 		SszStaticType::ProposerSlashing => test_ssz::<C, ProposerSlashing>(path),
 		SszStaticType::Validator => test_ssz::<C, Validator>(path),
-		SszStaticType::VoluntaryExit => test_ssz::<C, VoluntaryExit>(path),
+		SszStaticType::VoluntaryExit =>
+			test_ssz_signed::<C, VoluntaryExit, _, _>(path, SigningVoluntaryExit::from),
 	}
 }
 
 pub fn test_ssz<C: Config, T>(path: PathBuf) where
 	T: FromTree + IntoTree + Debug + Encode + Decode + Eq + DeserializeOwned,
+{
+	check_ssz::<T>(path);
+
+	println!(" passed");
+}
+
+/// Test a signed container, also comparing the root of its signing form,
+/// with the signature excluded.
+pub fn test_ssz_signed<C: Config, T, S, F>(path: PathBuf, signing: F) where
+	T: FromTree + IntoTree + Debug + Encode + Decode + Eq + DeserializeOwned,
+	S: IntoTree,
+	F: FnOnce(T) -> S,
+{
+	let (value, roots) = check_ssz::<T>(path);
+	let signing_root = tree_root::<Sha256, _>(&signing(value));
+	assert_eq!(Some(signing_root), roots.signing_root);
+
+	println!(" passed");
+}
+
+fn check_ssz<T>(path: PathBuf) -> (T, Roots) where
+	T: FromTree + IntoTree + Debug + Encode + Decode + Eq + DeserializeOwned,
 {
 	print!("Testing {} ...", test_name(&path).unwrap());
 	std::io::stdout().flush().ok().expect("Could not flush stdout");
@@ -95,5 +123,5 @@ pub fn test_ssz<C: Config, T>(path: PathBuf) where
 	let decoded_root = T::from_tree(&encoded_root, &mut db).unwrap();
 	assert_eq!(decoded_root, value);
 
-	println!(" passed");
+	(value, roots)
 }